@@ -0,0 +1,16 @@
+-- migrations/002_deployed_processes.sql
+
+-- User-defined processes deployed via OGC API Processes Part 2
+CREATE TABLE IF NOT EXISTS spatialvault.processes (
+    id TEXT PRIMARY KEY,                   -- process identifier used in /processes/{id}
+    title TEXT NOT NULL,
+    description TEXT,
+    version TEXT NOT NULL DEFAULT '1.0.0',
+    owner TEXT NOT NULL,                   -- admin who deployed the process
+    process_description JSONB NOT NULL,    -- OGC process description (inputs/outputs)
+    execution_unit JSONB NOT NULL,         -- container spec: image, command, env
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+GRANT ALL ON spatialvault.processes TO spatialvault_service;
@@ -0,0 +1,8 @@
+-- migrations/003_job_workflows.sql
+
+-- Workflow jobs: each step is a child job of the workflow's parent job
+ALTER TABLE spatialvault.processes_jobs
+    ADD COLUMN IF NOT EXISTS parent_job_id UUID REFERENCES spatialvault.processes_jobs(id) ON DELETE CASCADE,
+    ADD COLUMN IF NOT EXISTS step_index INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_jobs_parent ON spatialvault.processes_jobs(parent_job_id);
@@ -0,0 +1,7 @@
+-- migrations/004_job_retention.sql
+
+-- Per-job expiry overriding the configured retention policy
+ALTER TABLE spatialvault.processes_jobs
+    ADD COLUMN IF NOT EXISTS expires TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_jobs_finished ON spatialvault.processes_jobs(status, finished);
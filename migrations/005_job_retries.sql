@@ -0,0 +1,9 @@
+-- migrations/005_job_retries.sql
+
+-- Automatic retries: attempt counter, per-job limit, backoff schedule and
+-- the errors of all attempts (oldest first)
+ALTER TABLE spatialvault.processes_jobs
+    ADD COLUMN IF NOT EXISTS attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS max_attempts INTEGER,
+    ADD COLUMN IF NOT EXISTS next_attempt_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS errors JSONB NOT NULL DEFAULT '[]'::jsonb;
@@ -0,0 +1,6 @@
+-- migrations/006_collection_limits.sql
+
+-- Per-collection page size overrides for item listings (NULL = server default)
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS default_limit INTEGER CHECK (default_limit > 0),
+    ADD COLUMN IF NOT EXISTS max_limit INTEGER CHECK (max_limit > 0);
@@ -0,0 +1,7 @@
+-- migrations/007_collection_zoom.sql
+
+-- Per-collection tile zoom range (NULL = full range of the tile matrix set)
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS min_zoom INTEGER CHECK (min_zoom BETWEEN 0 AND 22),
+    ADD COLUMN IF NOT EXISTS max_zoom INTEGER CHECK (max_zoom BETWEEN 0 AND 22),
+    ADD CONSTRAINT collections_zoom_range CHECK (min_zoom <= max_zoom);
@@ -0,0 +1,5 @@
+-- migrations/008_collection_tile_layer.sql
+
+-- Vector tile layer name (NULL = last segment of the canonical name)
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS tile_layer TEXT CHECK (tile_layer <> '');
@@ -0,0 +1,5 @@
+-- migrations/009_tile_properties.sql
+
+-- Properties encoded into vector tiles per zoom range (NULL = all properties)
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS tile_properties JSONB;
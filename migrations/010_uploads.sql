@@ -0,0 +1,29 @@
+-- migrations/010_uploads.sql
+
+-- Chunked, resumable uploads of process inputs, buffered to S3 multipart uploads
+CREATE TABLE IF NOT EXISTS spatialvault.uploads (
+    id UUID PRIMARY KEY,
+    owner TEXT NOT NULL,
+    object_key TEXT NOT NULL,              -- S3 key of the assembled file
+    multipart_id TEXT NOT NULL,            -- S3 multipart upload id
+    media_type TEXT,
+    status TEXT NOT NULL DEFAULT 'open',   -- open, complete
+    size BIGINT,                           -- total size once complete
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_uploads_owner ON spatialvault.uploads(owner);
+CREATE INDEX IF NOT EXISTS idx_uploads_created_at ON spatialvault.uploads(created_at);
+
+-- Parts received so far; a part sent again replaces the earlier one
+CREATE TABLE IF NOT EXISTS spatialvault.upload_parts (
+    upload_id UUID NOT NULL REFERENCES spatialvault.uploads (id) ON DELETE CASCADE,
+    part_number INTEGER NOT NULL,
+    etag TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    PRIMARY KEY (upload_id, part_number)
+);
+
+GRANT ALL ON spatialvault.uploads TO spatialvault_service;
+GRANT ALL ON spatialvault.upload_parts TO spatialvault_service;
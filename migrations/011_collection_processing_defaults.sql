@@ -0,0 +1,5 @@
+-- migrations/011_collection_processing_defaults.sql
+
+-- Import options used when an execute request leaves them out (NULL = none)
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS processing_defaults JSONB;
@@ -0,0 +1,19 @@
+-- migrations/012_collection_assets.sql
+
+-- Assets of a collection itself (STAC collection assets), e.g. exports or documentation
+CREATE TABLE IF NOT EXISTS spatialvault.collection_assets (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    collection_id UUID NOT NULL REFERENCES spatialvault.collections (id) ON DELETE CASCADE,
+    key TEXT NOT NULL,                -- asset key, e.g., "pmtiles", "documentation"
+    href TEXT NOT NULL,               -- S3 URI or URL
+    type TEXT,                        -- media type
+    title TEXT,
+    description TEXT,
+    roles TEXT[],
+    file_size BIGINT,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    UNIQUE (collection_id, key)
+);
+
+GRANT ALL ON spatialvault.collection_assets TO spatialvault_service;
@@ -0,0 +1,16 @@
+-- migrations/013_collection_discovery.sql
+
+-- Keywords/themes used for catalog discovery and facets
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS keywords TEXT[] NOT NULL DEFAULT '{}';
+
+-- Last computed extent, so collections can be searched by bbox and datetime
+-- without computing every collection's extent (NULL extent_updated_at = never computed)
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS extent_bbox geometry(Geometry, 4326),
+    ADD COLUMN IF NOT EXISTS extent_start TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS extent_end TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS extent_updated_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_collections_keywords ON spatialvault.collections USING GIN(keywords);
+CREATE INDEX IF NOT EXISTS idx_collections_extent_bbox ON spatialvault.collections USING GIST(extent_bbox);
@@ -0,0 +1,5 @@
+-- migrations/014_collection_license.sql
+
+-- License of the collection's data: an SPDX identifier, "proprietary" or "various"
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS license TEXT;
@@ -0,0 +1,6 @@
+-- migrations/015_collection_translations.sql
+
+-- Translations of title and description: JSON object of language tag -> text (NULL = none)
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS title_i18n JSONB,
+    ADD COLUMN IF NOT EXISTS description_i18n JSONB;
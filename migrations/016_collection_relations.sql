@@ -0,0 +1,5 @@
+-- migrations/016_collection_relations.sql
+
+-- Relations to other collections that CQL2 filters may follow (NULL = none)
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS relations JSONB;
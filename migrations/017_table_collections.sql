@@ -0,0 +1,8 @@
+-- migrations/017_table_collections.sql
+
+-- Attribute-only collections whose feature table has no geometry column
+ALTER TABLE spatialvault.collections
+    DROP CONSTRAINT IF EXISTS collections_collection_type_check;
+ALTER TABLE spatialvault.collections
+    ADD CONSTRAINT collections_collection_type_check
+    CHECK (collection_type IN ('vector', 'raster', 'pointcloud', 'table'));
@@ -0,0 +1,5 @@
+-- migrations/018_computed_properties.sql
+
+-- Read-only properties computed from expressions over each feature (NULL = none)
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS computed_properties JSONB;
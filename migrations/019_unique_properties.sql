@@ -0,0 +1,6 @@
+-- migrations/019_unique_properties.sql
+
+-- Properties whose values must be unique among a collection's features; each
+-- is enforced by a unique index on the feature table
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS unique_properties TEXT[] NOT NULL DEFAULT '{}';
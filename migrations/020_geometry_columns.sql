@@ -0,0 +1,7 @@
+-- migrations/020_geometry_columns.sql
+
+-- Secondary geometry columns of vector collections, e.g. a label point next
+-- to the footprint polygon. Each is a generated column of the feature table
+-- computed from the GeoJSON geometry in the property of the same name.
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS geometry_columns TEXT[] NOT NULL DEFAULT '{}';
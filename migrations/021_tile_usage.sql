@@ -0,0 +1,11 @@
+-- migrations/021_tile_usage.sql
+
+-- Tile requests and bytes served per collection and hour, aggregated from
+-- the in-memory counters of the API servers
+CREATE TABLE IF NOT EXISTS spatialvault.tile_usage (
+    collection_id UUID NOT NULL REFERENCES spatialvault.collections(id) ON DELETE CASCADE,
+    period_start TIMESTAMPTZ NOT NULL,     -- start of the hour
+    requests BIGINT NOT NULL DEFAULT 0,
+    bytes BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (collection_id, period_start)
+);
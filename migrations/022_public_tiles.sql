@@ -0,0 +1,6 @@
+-- migrations/022_public_tiles.sql
+
+-- Whether the owner may hand out signed tile URLs that serve the collection's
+-- tiles without credentials, e.g. to embed a map in another site
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS public_tiles BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,8 @@
+-- migrations/023_cdn_purge.sql
+
+-- Collection version whose tiles were last purged from the CDN; tiles of
+-- collections with a newer version are purged by the servers' purge loop
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS cdn_purged_version BIGINT NOT NULL DEFAULT 1;
+
+UPDATE spatialvault.collections SET cdn_purged_version = version;
@@ -0,0 +1,13 @@
+-- migrations/024_feature_locks.sql
+
+-- Advisory locks on features and items, so people editing the same feature
+-- get a conflict instead of overwriting each other. A lock only counts until
+-- it expires; expired rows are replaced by the next lock.
+CREATE TABLE IF NOT EXISTS spatialvault.feature_locks (
+    collection_id UUID NOT NULL REFERENCES spatialvault.collections(id) ON DELETE CASCADE,
+    feature_id UUID NOT NULL,
+    locked_by TEXT NOT NULL,
+    locked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (collection_id, feature_id)
+);
@@ -0,0 +1,17 @@
+-- migrations/025_collection_snapshots.sql
+
+-- Copies of feature tables, kept out of the owners' schemas so that they
+-- can't be written to except by restoring
+CREATE SCHEMA IF NOT EXISTS spatialvault_snapshots;
+
+-- Named point-in-time snapshots of a collection's features
+CREATE TABLE IF NOT EXISTS spatialvault.collection_snapshots (
+    collection_id UUID NOT NULL REFERENCES spatialvault.collections(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    table_name TEXT NOT NULL UNIQUE,       -- table in spatialvault_snapshots
+    created_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    collection_version BIGINT NOT NULL,    -- collection version the copy was taken at
+    feature_count BIGINT NOT NULL,
+    PRIMARY KEY (collection_id, name)
+);
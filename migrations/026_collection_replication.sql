@@ -0,0 +1,30 @@
+-- migrations/026_collection_replication.sql
+
+-- Collections published to other SpatialVault instances. Remotes are named
+-- in the configuration, which holds their keys.
+CREATE TABLE IF NOT EXISTS spatialvault.collection_replications (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    collection_id UUID NOT NULL REFERENCES spatialvault.collections(id) ON DELETE CASCADE,
+    remote TEXT NOT NULL,                  -- name of a configured remote instance
+    remote_collection TEXT NOT NULL,       -- collection id on the remote
+    created_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    synced_version BIGINT NOT NULL DEFAULT 0,  -- collection version last pushed
+    synced_until TIMESTAMPTZ,              -- changes up to this time have been pushed
+    synced_at TIMESTAMPTZ,                 -- when the last push finished
+    claimed_until TIMESTAMPTZ,             -- a server is pushing until then
+    last_error TEXT,
+    UNIQUE (collection_id, remote, remote_collection)
+);
+
+-- Features deleted from replicated collections, so that the deletions can
+-- be pushed too. Rows every replication has pushed are pruned.
+CREATE TABLE IF NOT EXISTS spatialvault.feature_deletions (
+    collection_id UUID NOT NULL REFERENCES spatialvault.collections(id) ON DELETE CASCADE,
+    feature_id UUID NOT NULL,
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (collection_id, feature_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_feature_deletions_time
+    ON spatialvault.feature_deletions(collection_id, deleted_at);
@@ -0,0 +1,8 @@
+-- migrations/027_collection_expiry.sql
+
+-- Scratch collections are deleted by the job worker once they expire
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_collections_expires_at
+    ON spatialvault.collections(expires_at) WHERE expires_at IS NOT NULL;
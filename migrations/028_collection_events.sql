@@ -0,0 +1,33 @@
+-- migrations/028_collection_events.sql
+
+-- Changes to the metadata or schema of collections, as opposed to their
+-- features, so that services caching queryables and schemas know when to
+-- refresh them. Old events are pruned.
+CREATE TABLE IF NOT EXISTS spatialvault.collection_events (
+    id BIGSERIAL PRIMARY KEY,
+    collection_id UUID NOT NULL REFERENCES spatialvault.collections(id) ON DELETE CASCADE,
+    event_type TEXT NOT NULL,              -- 'metadata' or 'schema'
+    collection_version BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_collection_events_collection
+    ON spatialvault.collection_events(collection_id, id);
+CREATE INDEX IF NOT EXISTS idx_collection_events_created
+    ON spatialvault.collection_events(created_at);
+
+-- URLs the events of a collection are posted to
+CREATE TABLE IF NOT EXISTS spatialvault.collection_webhooks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    collection_id UUID NOT NULL REFERENCES spatialvault.collections(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    secret TEXT,                           -- key of the HMAC signature header
+    created_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    delivered_until TIMESTAMPTZ NOT NULL DEFAULT NOW(),  -- with delivered_event_id, the
+    delivered_event_id BIGINT NOT NULL DEFAULT 0,        -- last event delivered
+    delivered_at TIMESTAMPTZ,              -- when an event was last delivered
+    claimed_until TIMESTAMPTZ,             -- a server is delivering until then
+    last_error TEXT,
+    UNIQUE (collection_id, url)
+);
@@ -0,0 +1,28 @@
+-- migrations/029_notifications.sql
+
+-- What users want to be notified about; without a row, nothing
+CREATE TABLE IF NOT EXISTS spatialvault.notification_settings (
+    username TEXT PRIMARY KEY,
+    email TEXT,                            -- address passed to the notification service
+    collection_shared BOOLEAN NOT NULL DEFAULT FALSE,
+    job_finished BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Notifications waiting to be sent, and recently sent ones
+CREATE TABLE IF NOT EXISTS spatialvault.notifications (
+    id BIGSERIAL PRIMARY KEY,
+    recipient TEXT NOT NULL,
+    notification_type TEXT NOT NULL,       -- 'collection_shared' or 'job_finished'
+    data JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    claimed_until TIMESTAMPTZ,             -- a server is sending it until then
+    sent_at TIMESTAMPTZ,
+    last_error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_notifications_pending
+    ON spatialvault.notifications(id) WHERE sent_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_notifications_created
+    ON spatialvault.notifications(created_at);
@@ -0,0 +1,76 @@
+-- migrations/030_collection_data_updated_at.sql
+
+-- When the features or items of a collection last changed, as opposed to
+-- updated_at, which tracks its metadata. Kept up to date by statement-level
+-- triggers, so writes that bypass the API (imports, restores) count too.
+ALTER TABLE spatialvault.collections ADD COLUMN IF NOT EXISTS data_updated_at TIMESTAMPTZ;
+
+-- Trigger of a feature table; the collection id is the trigger argument
+CREATE OR REPLACE FUNCTION spatialvault.touch_collection_data()
+RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE spatialvault.collections SET data_updated_at = NOW()
+    WHERE id = TG_ARGV[0]::uuid;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER SET search_path = pg_catalog;
+
+-- Trigger of the items table, touching the collections of the changed rows
+CREATE OR REPLACE FUNCTION spatialvault.touch_item_collections()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        UPDATE spatialvault.collections SET data_updated_at = NOW()
+        WHERE id IN (SELECT collection_id FROM new_rows);
+    END IF;
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        UPDATE spatialvault.collections SET data_updated_at = NOW()
+        WHERE id IN (SELECT collection_id FROM old_rows);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER SET search_path = pg_catalog;
+
+DROP TRIGGER IF EXISTS items_touch_insert ON spatialvault.items;
+CREATE TRIGGER items_touch_insert AFTER INSERT ON spatialvault.items
+    REFERENCING NEW TABLE AS new_rows
+    FOR EACH STATEMENT EXECUTE FUNCTION spatialvault.touch_item_collections();
+DROP TRIGGER IF EXISTS items_touch_update ON spatialvault.items;
+CREATE TRIGGER items_touch_update AFTER UPDATE ON spatialvault.items
+    REFERENCING NEW TABLE AS new_rows OLD TABLE AS old_rows
+    FOR EACH STATEMENT EXECUTE FUNCTION spatialvault.touch_item_collections();
+DROP TRIGGER IF EXISTS items_touch_delete ON spatialvault.items;
+CREATE TRIGGER items_touch_delete AFTER DELETE ON spatialvault.items
+    REFERENCING OLD TABLE AS old_rows
+    FOR EACH STATEMENT EXECUTE FUNCTION spatialvault.touch_item_collections();
+
+-- Backfill from the rows of existing collections and add the triggers of
+-- their feature tables; new ones get theirs when they are created
+DO $$
+DECLARE
+    c RECORD;
+    latest TIMESTAMPTZ;
+BEGIN
+    UPDATE spatialvault.collections c
+    SET data_updated_at = (SELECT MAX(i.updated_at) FROM spatialvault.items i WHERE i.collection_id = c.id)
+    WHERE c.collection_type NOT IN ('vector', 'table');
+
+    FOR c IN
+        SELECT id, schema_name, table_name, updated_at FROM spatialvault.collections
+        WHERE collection_type IN ('vector', 'table')
+          AND to_regclass(format('%I.%I', schema_name, table_name)) IS NOT NULL
+    LOOP
+        EXECUTE format('SELECT MAX(updated_at) FROM %I.%I', c.schema_name, c.table_name)
+            INTO latest;
+        UPDATE spatialvault.collections SET data_updated_at = COALESCE(latest, c.updated_at)
+        WHERE id = c.id;
+
+        EXECUTE format('DROP TRIGGER IF EXISTS touch_collection_data ON %I.%I',
+                       c.schema_name, c.table_name);
+        EXECUTE format(
+            'CREATE TRIGGER touch_collection_data AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE '
+            'ON %I.%I FOR EACH STATEMENT EXECUTE FUNCTION spatialvault.touch_collection_data(%L)',
+            c.schema_name, c.table_name, c.id);
+    END LOOP;
+END;
+$$;
@@ -0,0 +1,11 @@
+-- migrations/031_maintenance.sql
+
+-- Maintenance state shared by all servers; a single row
+CREATE TABLE IF NOT EXISTS spatialvault.maintenance (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    read_only BOOLEAN NOT NULL DEFAULT FALSE, -- writes are rejected with 503
+    reason TEXT,                              -- shown in the rejections
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO spatialvault.maintenance (id) VALUES (TRUE) ON CONFLICT DO NOTHING;
@@ -0,0 +1,27 @@
+-- migrations/032_collection_geometry_type.sql
+
+-- Declared geometry type and coordinate dimension of vector collections.
+-- Writes are checked by the type modifier of the geometry column, e.g.
+-- geometry(PointZ, 4326); these columns record it for the metadata.
+ALTER TABLE spatialvault.collections
+    ADD COLUMN IF NOT EXISTS geometry_type TEXT,
+    ADD COLUMN IF NOT EXISTS geometry_dimension INTEGER;
+
+-- Existing collections have untyped columns, as recorded by PostGIS
+UPDATE spatialvault.collections c
+SET geometry_type = CASE gc.type
+        WHEN 'POINT' THEN 'Point'
+        WHEN 'MULTIPOINT' THEN 'MultiPoint'
+        WHEN 'LINESTRING' THEN 'LineString'
+        WHEN 'MULTILINESTRING' THEN 'MultiLineString'
+        WHEN 'POLYGON' THEN 'Polygon'
+        WHEN 'MULTIPOLYGON' THEN 'MultiPolygon'
+        ELSE 'Geometry'
+    END,
+    geometry_dimension = CASE WHEN gc.coord_dimension = 3 THEN 3 ELSE 2 END
+FROM geometry_columns gc
+WHERE c.collection_type = 'vector'
+  AND c.geometry_type IS NULL
+  AND gc.f_table_schema = c.schema_name
+  AND gc.f_table_name = c.table_name
+  AND gc.f_geometry_column = 'geometry';
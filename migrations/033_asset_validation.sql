@@ -0,0 +1,7 @@
+-- migrations/033_asset_validation.sql
+
+-- Outcome of checking an item asset against the object it references:
+-- the detected media type and size, and mismatches with what the asset
+-- claims (e.g. a COG that is not tiled). NULL when the asset was not checked.
+ALTER TABLE spatialvault.assets
+    ADD COLUMN IF NOT EXISTS validation JSONB;
@@ -0,0 +1,18 @@
+-- migrations/034_asset_link_check.sql
+
+-- Availability of the hrefs of item and collection assets, recorded by the
+-- periodic link check: 'ok' or 'broken'.
+-- NULL until an asset is first checked; assets are checked again once
+-- link_checked_at is old enough.
+ALTER TABLE spatialvault.assets
+    ADD COLUMN IF NOT EXISTS link_status TEXT,
+    ADD COLUMN IF NOT EXISTS link_checked_at TIMESTAMPTZ;
+
+ALTER TABLE spatialvault.collection_assets
+    ADD COLUMN IF NOT EXISTS link_status TEXT,
+    ADD COLUMN IF NOT EXISTS link_checked_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_assets_link_checked_at
+    ON spatialvault.assets (link_checked_at NULLS FIRST);
+CREATE INDEX IF NOT EXISTS idx_collection_assets_link_checked_at
+    ON spatialvault.collection_assets (link_checked_at NULLS FIRST);
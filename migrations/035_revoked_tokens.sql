@@ -0,0 +1,9 @@
+-- migrations/035_revoked_tokens.sql
+
+-- Scoped tokens revoked before they expire, by the token's id; rows are
+-- dropped once the token would have expired anyway
+CREATE TABLE IF NOT EXISTS spatialvault.revoked_tokens (
+    id UUID PRIMARY KEY,
+    expires TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
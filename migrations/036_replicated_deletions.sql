@@ -0,0 +1,47 @@
+-- migrations/036_replicated_deletions.sql
+
+-- Deletions from replicated collections are recorded by a trigger of each
+-- feature table, so deletes that bypass the API and snapshot restores are
+-- pushed too. The collection id is the trigger argument.
+CREATE OR REPLACE FUNCTION spatialvault.record_feature_deletions()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM spatialvault.collection_replications
+        WHERE collection_id = TG_ARGV[0]::uuid
+    ) THEN
+        INSERT INTO spatialvault.feature_deletions (collection_id, feature_id)
+        SELECT TG_ARGV[0]::uuid, id FROM old_rows
+        ON CONFLICT (collection_id, feature_id) DO UPDATE SET deleted_at = NOW();
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER SET search_path = pg_catalog;
+
+DO $$
+DECLARE
+    c RECORD;
+BEGIN
+    FOR c IN
+        SELECT id, schema_name, table_name FROM spatialvault.collections
+        WHERE collection_type IN ('vector', 'table')
+          AND to_regclass(format('%I.%I', schema_name, table_name)) IS NOT NULL
+    LOOP
+        EXECUTE format('DROP TRIGGER IF EXISTS record_feature_deletions ON %I.%I',
+                       c.schema_name, c.table_name);
+        EXECUTE format(
+            'CREATE TRIGGER record_feature_deletions AFTER DELETE ON %I.%I '
+            'REFERENCING OLD TABLE AS old_rows '
+            'FOR EACH STATEMENT EXECUTE FUNCTION spatialvault.record_feature_deletions(%L)',
+            c.schema_name, c.table_name, c.id);
+    END LOOP;
+END;
+$$;
+
+-- Replications of deleted collections are kept until the features pushed
+-- from them are deleted from the remote
+ALTER TABLE spatialvault.collection_replications
+    ALTER COLUMN collection_id DROP NOT NULL,
+    DROP CONSTRAINT IF EXISTS collection_replications_collection_id_fkey,
+    ADD CONSTRAINT collection_replications_collection_id_fkey
+        FOREIGN KEY (collection_id) REFERENCES spatialvault.collections(id) ON DELETE SET NULL;
@@ -0,0 +1,67 @@
+-- migrations/037_deferred_collection_data_touch.sql
+
+-- Updating data_updated_at from the write triggers locked the collection
+-- row until the writing transaction ended, serializing concurrent writers.
+-- The triggers now queue each collection once per transaction, and a
+-- deferred trigger applies the queue while committing, so the row is only
+-- locked for the commit itself. Rows of this table never outlive their
+-- transaction.
+CREATE TABLE IF NOT EXISTS spatialvault.collection_data_touches (
+    collection_id UUID NOT NULL
+);
+
+-- Queue a collection to be touched when the current transaction commits
+CREATE OR REPLACE FUNCTION spatialvault.queue_collection_touch(collection UUID)
+RETURNS VOID AS $$
+DECLARE
+    queued TEXT := 'spatialvault.touch_' || replace(collection::text, '-', '');
+BEGIN
+    IF COALESCE(current_setting(queued, true), '') <> 'on' THEN
+        PERFORM set_config(queued, 'on', true);
+        INSERT INTO spatialvault.collection_data_touches (collection_id) VALUES (collection);
+    END IF;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER SET search_path = pg_catalog;
+
+-- Deferred trigger of the queue; clock_timestamp() rather than NOW() so the
+-- touch is not older than extents computed while the transaction ran
+CREATE OR REPLACE FUNCTION spatialvault.apply_collection_touch()
+RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE spatialvault.collections SET data_updated_at = clock_timestamp()
+    WHERE id = NEW.collection_id;
+    DELETE FROM spatialvault.collection_data_touches WHERE collection_id = NEW.collection_id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER SET search_path = pg_catalog;
+
+DROP TRIGGER IF EXISTS apply_collection_touch ON spatialvault.collection_data_touches;
+CREATE CONSTRAINT TRIGGER apply_collection_touch
+    AFTER INSERT ON spatialvault.collection_data_touches
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE FUNCTION spatialvault.apply_collection_touch();
+
+-- Trigger of a feature table; the collection id is the trigger argument
+CREATE OR REPLACE FUNCTION spatialvault.touch_collection_data()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM spatialvault.queue_collection_touch(TG_ARGV[0]::uuid);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER SET search_path = pg_catalog;
+
+-- Trigger of the items table, touching the collections of the changed rows
+CREATE OR REPLACE FUNCTION spatialvault.touch_item_collections()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        PERFORM spatialvault.queue_collection_touch(changed.collection_id)
+        FROM (SELECT DISTINCT collection_id FROM new_rows) changed;
+    END IF;
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        PERFORM spatialvault.queue_collection_touch(changed.collection_id)
+        FROM (SELECT DISTINCT collection_id FROM old_rows) changed;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER SET search_path = pg_catalog;
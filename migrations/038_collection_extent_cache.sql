@@ -0,0 +1,29 @@
+-- migrations/038_collection_extent_cache.sql
+
+-- Last computed extent, so collections can be searched by bbox and datetime
+-- without computing every collection's extent. Kept apart from the
+-- collections table, which holds no computed metadata.
+-- (NULL computed_at = never computed, or to be computed again)
+CREATE TABLE IF NOT EXISTS spatialvault.collection_extent_cache (
+    collection_id UUID PRIMARY KEY REFERENCES spatialvault.collections(id) ON DELETE CASCADE,
+    bbox geometry(Geometry, 4326),
+    start_time TIMESTAMPTZ,
+    end_time TIMESTAMPTZ,
+    computed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_collection_extent_cache_bbox
+    ON spatialvault.collection_extent_cache USING GIST(bbox);
+
+INSERT INTO spatialvault.collection_extent_cache
+    (collection_id, bbox, start_time, end_time, computed_at)
+SELECT id, extent_bbox, extent_start, extent_end, extent_updated_at
+FROM spatialvault.collections
+ON CONFLICT (collection_id) DO NOTHING;
+
+DROP INDEX IF EXISTS spatialvault.idx_collections_extent_bbox;
+ALTER TABLE spatialvault.collections
+    DROP COLUMN IF EXISTS extent_bbox,
+    DROP COLUMN IF EXISTS extent_start,
+    DROP COLUMN IF EXISTS extent_end,
+    DROP COLUMN IF EXISTS extent_updated_at;
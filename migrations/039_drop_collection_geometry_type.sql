@@ -0,0 +1,8 @@
+-- migrations/039_drop_collection_geometry_type.sql
+
+-- The geometry type and dimension of vector collections are read from
+-- geometry_columns, like the storage CRS, so copies of the type modifier
+-- of the geometry column aren't kept in the collections table.
+ALTER TABLE spatialvault.collections
+    DROP COLUMN IF EXISTS geometry_type,
+    DROP COLUMN IF EXISTS geometry_dimension;
@@ -0,0 +1,16 @@
+-- migrations/040_upload_reserved_bytes.sql
+
+-- Bytes of the parts of an open upload, reserved before a part is stored so
+-- concurrent parts can't together exceed the maximum upload size. A part
+-- being replaced is counted until its replacement is recorded.
+ALTER TABLE spatialvault.uploads
+    ADD COLUMN IF NOT EXISTS reserved_bytes BIGINT NOT NULL DEFAULT 0;
+
+UPDATE spatialvault.uploads u
+SET reserved_bytes = parts.size
+FROM (
+    SELECT upload_id, SUM(size)::BIGINT AS size
+    FROM spatialvault.upload_parts
+    GROUP BY upload_id
+) parts
+WHERE parts.upload_id = u.id;
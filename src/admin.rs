@@ -0,0 +1,526 @@
+//! `spatialvault admin` subcommands
+//!
+//! Provisioning for deployment automation (Terraform, Helm hooks, init
+//! containers) without crafting SQL against the internal schema. Results go
+//! to stdout so they can be captured; progress and errors go to stderr.
+
+use anyhow::{Context, bail};
+use chrono::Utc;
+
+use crate::api::tokens::{ACTIONS, DEFAULT_EXPIRY_SECS};
+use crate::auth::{
+    AccessScope, AuthenticatedUser, OidcValidator, RoleManager, ScopedTokens,
+    policy::PolicyEvaluator,
+};
+use crate::config::Config;
+use crate::db::Database;
+use crate::storage::S3Storage;
+
+/// Object looked up to check the storage credentials; it needn't exist
+const STORAGE_CHECK_KEY: &str = "spatialvault-check";
+
+const USAGE: &str = "\
+Usage: spatialvault admin <command> [options]
+
+Commands:
+  create-namespace <name> [--group] [--member <user>]...
+      Create the database role and schema of a user, or of a group with
+      the given members
+  grant-admin <user>
+      Make a user a member of the process admin group
+  create-api-key <user> [--group <group>]... [--collection <id>]...
+                 [--action <read|write|delete>]... [--process <id>]...
+                 [--expires-in <seconds>]
+      Issue a scoped token acting as the user; prints the token. Tokens
+      expire after an hour unless --expires-in says otherwise
+  revoke-api-key <token>
+      Reject a scoped token from now on, before it expires
+  check-config
+      Check the configuration and that the database, object storage,
+      OIDC provider and authorization policy can be reached, and report
+      the PostGIS version and the tools imports and processes rely on;
+      also run by `spatialvault --check`
+  migrate
+      Apply pending database migrations
+  read-only <on|off|status> [--reason <text>]
+      Reject writes on all servers for a maintenance window and pause jobs,
+      replication, webhooks, link checks and notifications, or resume
+      them; the reason is shown to clients
+";
+
+/// Options of a subcommand: positional arguments, `--name value` options
+/// and `--flag` switches
+#[derive(Debug, Default)]
+pub(crate) struct Options {
+    positional: Vec<String>,
+    values: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+impl Options {
+    /// Parse `args`, where the names in `flags` take no value
+    pub(crate) fn parse(args: &[String], flags: &[&str]) -> anyhow::Result<Self> {
+        let mut options = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                options.positional.push(arg.clone());
+                continue;
+            };
+            if let Some((name, value)) = name.split_once('=') {
+                options.values.push((name.to_string(), value.to_string()));
+            } else if flags.contains(&name) {
+                options.flags.push(name.to_string());
+            } else {
+                let value = args
+                    .next()
+                    .with_context(|| format!("--{} needs a value", name))?;
+                options.values.push((name.to_string(), value.clone()));
+            }
+        }
+        Ok(options)
+    }
+
+    /// The single positional argument, called `what` in errors
+    fn argument(&self, what: &str) -> anyhow::Result<&str> {
+        match self.positional.as_slice() {
+            [argument] => Ok(argument),
+            [] => bail!("Missing {}", what),
+            _ => bail!("Expected a single {}", what),
+        }
+    }
+
+    fn all(&self, name: &str) -> Vec<String> {
+        self.values
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    pub(crate) fn last(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub(crate) fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+
+    /// Fail on options the subcommand doesn't know
+    pub(crate) fn only(&self, names: &[&str]) -> anyhow::Result<()> {
+        let unknown = self
+            .values
+            .iter()
+            .map(|(name, _)| name)
+            .chain(&self.flags)
+            .find(|name| !names.contains(&name.as_str()));
+        match unknown {
+            Some(name) => bail!("Unknown option --{}", name),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Run an admin subcommand; `args` follow `admin` on the command line
+pub async fn run(args: &[String]) -> anyhow::Result<()> {
+    let Some((command, args)) = args.split_first() else {
+        eprint!("{}", USAGE);
+        bail!("Missing admin command");
+    };
+
+    match command.as_str() {
+        "create-namespace" => create_namespace(args).await,
+        "grant-admin" => grant_admin(args).await,
+        "create-api-key" => create_api_key(args),
+        "revoke-api-key" => revoke_api_key(args).await,
+        "check-config" => check_config(args).await,
+        "migrate" => migrate(args).await,
+        "read-only" => read_only(args).await,
+        "help" | "--help" | "-h" => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        other => {
+            eprint!("{}", USAGE);
+            bail!("Unknown admin command: {}", other)
+        }
+    }
+}
+
+pub(crate) async fn connect(config: &Config) -> anyhow::Result<Database> {
+    Database::connect(&config.database)
+        .await
+        .context("Failed to connect to database")
+}
+
+async fn create_namespace(args: &[String]) -> anyhow::Result<()> {
+    let options = Options::parse(args, &["group"])?;
+    options.only(&["group", "member"])?;
+    let name = options.argument("namespace name")?;
+    let members = options.all("member");
+    if !options.flag("group") && !members.is_empty() {
+        bail!("--member requires --group");
+    }
+
+    let config = Config::load()?;
+    let db = connect(&config).await?;
+    let roles = RoleManager::new(db.pool());
+    if options.flag("group") {
+        roles.ensure_group_role(name).await?;
+        for member in &members {
+            roles.ensure_user_role(member).await?;
+            roles.grant_role_to_user(name, member).await?;
+        }
+    } else {
+        roles.ensure_user_role(name).await?;
+    }
+
+    println!("{}", name);
+    Ok(())
+}
+
+async fn grant_admin(args: &[String]) -> anyhow::Result<()> {
+    let options = Options::parse(args, &[])?;
+    options.only(&[])?;
+    let username = options.argument("username")?;
+
+    let config = Config::load()?;
+    let db = connect(&config).await?;
+    let roles = RoleManager::new(db.pool());
+    let admin_group = &config.processing.admin_group;
+    roles.ensure_group_role(admin_group).await?;
+    roles.ensure_user_role(username).await?;
+    roles.grant_role_to_user(admin_group, username).await?;
+
+    eprintln!("Added {} to the {} group", username, admin_group);
+    Ok(())
+}
+
+fn create_api_key(args: &[String]) -> anyhow::Result<()> {
+    let options = Options::parse(args, &[])?;
+    options.only(&["group", "collection", "action", "process", "expires-in"])?;
+    let username = options.argument("username")?;
+
+    let config = Config::load()?;
+    let tokens = ScopedTokens::new(&config.scoped_tokens, &config.base_url)
+        .context("Scoped tokens are not enabled; set scoped_tokens.key")?;
+
+    let actions = options.all("action");
+    if let Some(action) = actions
+        .iter()
+        .find(|action| !ACTIONS.contains(&action.as_str()))
+    {
+        bail!(
+            "Unknown action: {} (supported: {})",
+            action,
+            ACTIONS.join(", ")
+        );
+    }
+    let expires_in = match options.last("expires-in") {
+        Some(secs) => secs
+            .parse::<u64>()
+            .with_context(|| format!("Invalid --expires-in: {}", secs))?,
+        None => DEFAULT_EXPIRY_SECS.min(tokens.max_expiry_secs()),
+    };
+    if expires_in == 0 || expires_in > tokens.max_expiry_secs() {
+        bail!(
+            "--expires-in must be between 1 and {} seconds",
+            tokens.max_expiry_secs()
+        );
+    }
+
+    let user = AuthenticatedUser {
+        username: username.to_string(),
+        subject: username.to_string(),
+        groups: options.all("group"),
+        service_account: None,
+        scope: None,
+    };
+    let scope = AccessScope {
+        collections: options.all("collection"),
+        actions,
+        processes: options.all("process"),
+    };
+    let expires = Utc::now() + chrono::Duration::seconds(expires_in as i64);
+    let token = tokens.issue(&user, &scope, expires)?;
+
+    println!("{}", token);
+    eprintln!("Expires {}", expires.to_rfc3339());
+    Ok(())
+}
+
+async fn revoke_api_key(args: &[String]) -> anyhow::Result<()> {
+    let options = Options::parse(args, &[])?;
+    options.only(&[])?;
+    let token = options.argument("token")?;
+
+    let config = Config::load()?;
+    let tokens = ScopedTokens::new(&config.scoped_tokens, &config.base_url)
+        .context("Scoped tokens are not enabled; set scoped_tokens.key")?;
+    let db = connect(&config).await?;
+    let revoked = tokens.revoke(&db, token).await?;
+
+    eprintln!(
+        "Revoked token {}, which would have expired {}",
+        revoked.id,
+        revoked.expires.to_rfc3339()
+    );
+    Ok(())
+}
+
+/// Print the outcome of a check of an optional tool, whose absence only
+/// degrades some features
+fn report_optional(what: &str, result: Result<String, String>, degraded: &str) {
+    match result {
+        Ok(version) => println!("ok      {}: {}", what, version),
+        Err(e) => println!("MISSING {}: {}; {}", what, e, degraded),
+    }
+}
+
+/// Version reported by a program on the PATH
+async fn tool_version(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| format!("{} is not available: {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!("{} exited with {}", program, output.status));
+    }
+    Ok(version_line(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The line of a version output naming the version, skipping banners
+fn version_line(output: &str) -> String {
+    output
+        .lines()
+        .map(str::trim)
+        .find(|line| line.chars().any(|c| c.is_ascii_digit()))
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(feature = "gdal-support")]
+fn gdal_version() -> Result<String, String> {
+    gdal::DriverManager::get_driver_by_name("COG")
+        .map_err(|e| format!("COG driver not available: {}", e))?;
+    Ok(gdal::version::version_info("RELEASE_NAME"))
+}
+
+#[cfg(not(feature = "gdal-support"))]
+fn gdal_version() -> Result<String, String> {
+    Err("not built with the gdal-support feature".to_string())
+}
+
+/// Version of the PostGIS extension of the database
+async fn postgis_version(db: &Database) -> Result<String, String> {
+    let version: Option<String> =
+        sqlx::query_scalar("SELECT extversion FROM pg_extension WHERE extname = 'postgis'")
+            .fetch_optional(db.pool())
+            .await
+            .map_err(|e| e.to_string())?;
+    version.ok_or_else(|| "extension is not installed".to_string())
+}
+
+/// Print the outcome of a check, returning whether it passed
+fn report<T, E: std::fmt::Display>(what: &str, result: Result<T, E>) -> bool {
+    match result {
+        Ok(_) => {
+            println!("ok      {}", what);
+            true
+        }
+        Err(e) => {
+            println!("FAILED  {}: {}", what, e);
+            false
+        }
+    }
+}
+
+async fn check_config(args: &[String]) -> anyhow::Result<()> {
+    Options::parse(args, &[])?.only(&[])?;
+    check().await
+}
+
+/// Check the configuration, connectivity and capabilities, printing a report
+///
+/// Fails when a required service can't be used; missing optional tools are
+/// reported without failing.
+pub async fn check() -> anyhow::Result<()> {
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            report("configuration", Err::<(), _>(&e));
+            bail!("Configuration is invalid");
+        }
+    };
+    let mut passed = report("configuration", Ok::<_, String>(()));
+
+    match connect(&config).await {
+        Ok(db) => {
+            passed &= report("database connection", Ok::<_, String>(()));
+            passed &= report(
+                "database migrations",
+                db.pending_migrations()
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|pending| match pending {
+                        0 => Ok(()),
+                        n => Err(format!("{} pending, run `spatialvault admin migrate`", n)),
+                    }),
+            );
+            passed &= match postgis_version(&db).await {
+                Ok(version) => report(&format!("PostGIS {}", version), Ok::<_, String>(())),
+                Err(e) => report("PostGIS", Err::<(), _>(e)),
+            };
+        }
+        Err(e) => passed &= report("database connection", Err::<(), _>(format!("{:#}", e))),
+    }
+
+    passed &= match S3Storage::new(&config.s3) {
+        // Any answer but an error shows the bucket and credentials work
+        Ok(storage) => report("object storage", storage.exists(STORAGE_CHECK_KEY).await),
+        Err(e) => report("object storage", Err::<(), _>(e)),
+    };
+    passed &= report(
+        "OIDC provider",
+        OidcValidator::new(config.oidc.clone()).await,
+    );
+    passed &= report("authorization policy", PolicyEvaluator::new(&config.policy));
+
+    report_optional(
+        "GDAL",
+        gdal_version(),
+        "raster imports can't convert to COG or read raster metadata",
+    );
+    report_optional(
+        "PDAL",
+        tool_version("pdal", &["--version"]).await,
+        "point cloud imports fail",
+    );
+    let runtime = &config.processing.container_runtime;
+    report_optional(
+        runtime,
+        tool_version(runtime, &["--version"]).await,
+        "deployed processes can't run",
+    );
+
+    if !passed {
+        bail!("Configuration check failed");
+    }
+    Ok(())
+}
+
+async fn migrate(args: &[String]) -> anyhow::Result<()> {
+    Options::parse(args, &[])?.only(&[])?;
+
+    let config = Config::load()?;
+    let db = connect(&config).await?;
+    let pending = db.pending_migrations().await?;
+    db.run_migrations().await?;
+
+    eprintln!("Applied {} migrations", pending);
+    Ok(())
+}
+
+async fn read_only(args: &[String]) -> anyhow::Result<()> {
+    let options = Options::parse(args, &[])?;
+    options.only(&["reason"])?;
+    let read_only = match options.argument("on, off or status")? {
+        "on" => true,
+        "off" => {
+            if options.last("reason").is_some() {
+                bail!("--reason requires on");
+            }
+            false
+        }
+        "status" => {
+            let config = Config::load()?;
+            let db = connect(&config).await?;
+            let state: (bool, Option<String>, chrono::DateTime<Utc>) = sqlx::query_as(
+                "SELECT read_only, reason, updated_at FROM spatialvault.maintenance",
+            )
+            .fetch_one(db.pool())
+            .await?;
+            match state {
+                (true, reason, since) => println!(
+                    "read-only since {}{}",
+                    since.to_rfc3339(),
+                    reason.map(|r| format!(": {}", r)).unwrap_or_default()
+                ),
+                (false, _, _) => println!("writable"),
+            }
+            return Ok(());
+        }
+        other => bail!("Expected on, off or status, got {}", other),
+    };
+
+    let config = Config::load()?;
+    let db = connect(&config).await?;
+    sqlx::query(
+        "UPDATE spatialvault.maintenance SET read_only = $1, reason = $2, updated_at = NOW()",
+    )
+    .bind(read_only)
+    .bind(options.last("reason"))
+    .execute(db.pool())
+    .await?;
+
+    if read_only {
+        eprintln!(
+            "Writes are rejected and background work is paused until `spatialvault admin read-only off`"
+        );
+    } else {
+        eprintln!("Writes are accepted again");
+    }
+    if config.read_only {
+        eprintln!("This instance is configured read-only regardless");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_version_line() {
+        let pdal = "--------\npdal 2.6.3 (git-version: Release)\n--------\n";
+        assert_eq!(version_line(pdal), "pdal 2.6.3 (git-version: Release)");
+        assert_eq!(
+            version_line("podman version 4.9.3\n"),
+            "podman version 4.9.3"
+        );
+        assert_eq!(version_line(""), "");
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = Options::parse(
+            &args(&[
+                "alice",
+                "--collection",
+                "roads",
+                "--collection=parks",
+                "--group",
+            ]),
+            &["group"],
+        )
+        .unwrap();
+        assert_eq!(options.argument("username").unwrap(), "alice");
+        assert_eq!(options.all("collection"), vec!["roads", "parks"]);
+        assert!(options.flag("group"));
+        assert!(options.only(&["collection", "group"]).is_ok());
+        assert!(options.only(&["collection"]).is_err());
+
+        assert!(Options::parse(&args(&["--expires-in"]), &[]).is_err());
+        let options = Options::parse(&args(&["alice", "bob"]), &[]).unwrap();
+        assert!(options.argument("username").is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use axum::{
+    Router,
+    extract::Request,
+    http::{HeaderValue, Method, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+};
+
+/// Wrap a router so that OPTIONS and 405 responses report the allowed methods
+///
+/// The router sets `Allow` only after its route layers have run, so the
+/// middleware is applied around the whole router rather than via
+/// [`Router::layer`].
+pub fn with_allowed_methods(router: Router) -> Router {
+    Router::new()
+        .fallback_service(router)
+        .layer(middleware::from_fn(allow_middleware))
+}
+
+/// Answer OPTIONS requests and advertise OPTIONS on 405 responses
+///
+/// The router already responds with 405 and an `Allow` header listing the
+/// methods of a route when none match. An OPTIONS request (other than a CORS
+/// preflight, which the CORS layer answers) is routed like any other; its 405
+/// is turned into a 204 that carries the same `Allow` header.
+pub async fn allow_middleware(request: Request, next: Next) -> Response {
+    let is_options = request.method() == Method::OPTIONS;
+    let mut response = next.run(request).await;
+
+    if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+        return response;
+    }
+
+    let allow = with_options(
+        response
+            .headers()
+            .get(header::ALLOW)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default(),
+    );
+    let Ok(allow) = HeaderValue::from_str(&allow) else {
+        return response;
+    };
+
+    if is_options {
+        return (StatusCode::NO_CONTENT, [(header::ALLOW, allow)]).into_response();
+    }
+    response.headers_mut().insert(header::ALLOW, allow);
+    response
+}
+
+/// Add OPTIONS to a comma separated list of methods
+fn with_options(allow: &str) -> String {
+    let mut methods: Vec<&str> = allow
+        .split(',')
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .collect();
+    if !methods.contains(&"OPTIONS") {
+        methods.push("OPTIONS");
+    }
+    methods.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get};
+    use tower::ServiceExt;
+
+    fn router() -> Router {
+        with_allowed_methods(Router::new().route(
+            "/things",
+            get(|| async { "ok" }).post(|| async { "created" }),
+        ))
+    }
+
+    async fn send(method: Method, uri: &str) -> Response {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        router().oneshot(request).await.unwrap()
+    }
+
+    fn allowed(response: &Response) -> Vec<String> {
+        let mut methods: Vec<String> = response.headers()[header::ALLOW]
+            .to_str()
+            .unwrap()
+            .split(", ")
+            .map(str::to_string)
+            .collect();
+        methods.sort();
+        methods
+    }
+
+    #[test]
+    fn test_with_options() {
+        assert_eq!(with_options("GET,HEAD"), "GET, HEAD, OPTIONS");
+        assert_eq!(with_options("GET, OPTIONS"), "GET, OPTIONS");
+        assert_eq!(with_options(""), "OPTIONS");
+    }
+
+    #[tokio::test]
+    async fn test_options_lists_allowed_methods() {
+        let response = send(Method::OPTIONS, "/things").await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(allowed(&response), ["GET", "HEAD", "OPTIONS", "POST"]);
+    }
+
+    #[tokio::test]
+    async fn test_method_not_allowed_lists_allowed_methods() {
+        let response = send(Method::DELETE, "/things").await;
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(allowed(&response), ["GET", "HEAD", "OPTIONS", "POST"]);
+
+        let response = send(Method::OPTIONS, "/missing").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,149 @@
+use aide::OperationInput;
+use aide::openapi::{Operation, ReferenceOr};
+use axum::{
+    Json,
+    extract::{FromRequest, Request},
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+};
+use schemars::JsonSchema;
+use serde::de::{Deserialize, DeserializeOwned, Deserializer};
+
+use super::common::media_type;
+use crate::error::{AppError, AppResult};
+
+/// Media types accepted when creating or replacing resources
+pub const CREATE_MEDIA_TYPES: &[&str] = &[media_type::GEOJSON, media_type::JSON];
+
+/// Media types accepted when partially updating resources
+pub const PATCH_MEDIA_TYPES: &[&str] = &[media_type::MERGE_PATCH];
+
+/// Check that the request's Content-Type is one of `allowed`
+///
+/// Parameters such as `charset` are ignored.
+pub fn require_content_type(headers: &HeaderMap, allowed: &[&str]) -> AppResult<()> {
+    let mime = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_ascii_lowercase());
+
+    match mime {
+        Some(mime) if allowed.contains(&mime.as_str()) => Ok(()),
+        _ => Err(AppError::UnsupportedMediaType(format!(
+            "Expected Content-Type {}",
+            allowed.join(" or ")
+        ))),
+    }
+}
+
+/// Rename the JSON request body media type of an operation to `media_types`
+pub(crate) fn set_request_media_types(operation: &mut Operation, media_types: &[&str]) {
+    let Some(ReferenceOr::Item(body)) = operation.request_body.as_mut() else {
+        return;
+    };
+    let Some(content) = body.content.shift_remove(media_type::JSON) else {
+        return;
+    };
+    for media_type in media_types {
+        body.content.insert(media_type.to_string(), content.clone());
+    }
+}
+
+/// JSON body of a POST or PUT request (`application/geo+json` or `application/json`)
+pub struct JsonBody<T>(pub T);
+
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        require_content_type(req.headers(), CREATE_MEDIA_TYPES).map_err(|e| e.into_response())?;
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|e| e.into_response())?;
+        Ok(JsonBody(value))
+    }
+}
+
+impl<T: JsonSchema> OperationInput for JsonBody<T> {
+    fn operation_input(ctx: &mut aide::generate::GenContext, operation: &mut Operation) {
+        Json::<T>::operation_input(ctx, operation);
+        set_request_media_types(operation, CREATE_MEDIA_TYPES);
+    }
+}
+
+/// Deserialize a merge patch member, telling `null`, which removes the value
+/// (`Some(None)`), apart from a missing member (`None`)
+///
+/// Use with `#[serde(default, deserialize_with = "nullable")]`.
+pub fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// JSON Merge Patch body of a PATCH request (`application/merge-patch+json`)
+pub struct MergePatchBody<T>(pub T);
+
+impl<T, S> FromRequest<S> for MergePatchBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        require_content_type(req.headers(), PATCH_MEDIA_TYPES).map_err(|e| e.into_response())?;
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|e| e.into_response())?;
+        Ok(MergePatchBody(value))
+    }
+}
+
+impl<T: JsonSchema> OperationInput for MergePatchBody<T> {
+    fn operation_input(ctx: &mut aide::generate::GenContext, operation: &mut Operation) {
+        Json::<T>::operation_input(ctx, operation);
+        set_request_media_types(operation, PATCH_MEDIA_TYPES);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(content_type: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_require_content_type() {
+        assert!(require_content_type(&headers("application/geo+json"), CREATE_MEDIA_TYPES).is_ok());
+        assert!(
+            require_content_type(
+                &headers("application/json; charset=utf-8"),
+                CREATE_MEDIA_TYPES
+            )
+            .is_ok()
+        );
+        assert!(
+            require_content_type(&headers("application/merge-patch+json"), PATCH_MEDIA_TYPES)
+                .is_ok()
+        );
+
+        let err = require_content_type(&headers("text/plain"), CREATE_MEDIA_TYPES).unwrap_err();
+        assert!(matches!(err, AppError::UnsupportedMediaType(_)));
+        assert!(err.to_string().contains("application/geo+json"));
+
+        assert!(require_content_type(&headers("application/json"), PATCH_MEDIA_TYPES).is_err());
+        assert!(require_content_type(&HeaderMap::new(), CREATE_MEDIA_TYPES).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! Report of broken asset links of the current user's collections
+//!
+//! The hrefs of item and collection assets are checked periodically when
+//! link checks are enabled; see [`crate::services::LinkCheckService`].
+
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, Query, State},
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::AuthenticatedUser;
+use crate::db::BrokenAsset;
+use crate::error::{AppError, AppResult};
+use crate::services::CollectionService;
+
+/// Broken assets listed when the request doesn't give a limit
+const DEFAULT_LIMIT: i64 = 100;
+
+/// Most broken assets listed at once
+const MAX_LIMIT: i64 = 1000;
+
+/// Query parameters of the broken asset report
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BrokenAssetsParams {
+    /// Only list assets of this collection and its items
+    pub collection: Option<String>,
+    /// Maximum number of assets to return (default 100, at most 1000)
+    pub limit: Option<i64>,
+}
+
+/// An asset whose href did not resolve when it was last checked
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BrokenAssetResponse {
+    pub collection: String,
+    /// Item the asset belongs to; absent for assets of the collection itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_id: Option<Uuid>,
+    pub key: String,
+    pub href: String,
+    /// When the href was last checked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<String>")]
+    pub checked: Option<DateTime<Utc>>,
+}
+
+impl From<BrokenAsset> for BrokenAssetResponse {
+    fn from(asset: BrokenAsset) -> Self {
+        Self {
+            collection: asset.collection,
+            item_id: asset.item_id,
+            key: asset.key,
+            href: asset.href,
+            checked: asset.link_checked_at,
+        }
+    }
+}
+
+/// Broken assets of the current user's collections
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BrokenAssetsResponse {
+    pub assets: Vec<BrokenAssetResponse>,
+    pub number_returned: usize,
+}
+
+/// List broken assets of the collections owned by the current user
+pub async fn list_broken_assets(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    Query(params): Query<BrokenAssetsParams>,
+) -> AppResult<Json<BrokenAssetsResponse>> {
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(AppError::BadRequest(format!(
+            "limit must be between 1 and {}",
+            MAX_LIMIT
+        )));
+    }
+
+    let assets: Vec<BrokenAssetResponse> = service
+        .list_broken_assets(&user.username, params.collection.as_deref(), limit)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(Json(BrokenAssetsResponse {
+        number_returned: assets.len(),
+        assets,
+    }))
+}
+
+fn list_broken_assets_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("List broken assets")
+        .description(
+            "Returns the assets of the current user's collections and their items whose href \
+             did not resolve when it was last checked, with the time of the check. Hrefs are \
+             checked periodically with an S3 head request or an HTTP HEAD request when link \
+             checks are enabled for the deployment; the list is empty otherwise. Hrefs on \
+             private or loopback addresses are never requested and are reported as broken.",
+        )
+        .tag("Collections")
+        .response_with::<200, Json<BrokenAssetsResponse>, _>(|res| res.description("Broken assets"))
+        .response_with::<400, (), _>(|res| res.description("Invalid limit"))
+}
+
+pub fn routes(service: Arc<CollectionService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/broken-assets",
+            get_with(list_broken_assets, list_broken_assets_docs),
+        )
+        .with_state(service)
+}
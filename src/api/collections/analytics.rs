@@ -0,0 +1,200 @@
+//! Tile usage analytics of collections
+//!
+//! Tile requests are counted per collection and hour by the
+//! [`AnalyticsService`], so owners can see how much their maps are used.
+
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, Query, State},
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::resolved::ResolvedCollection;
+use crate::api::edr::query::DateTimeBounds;
+use crate::auth::AuthenticatedUser;
+use crate::error::{AppError, AppResult};
+use crate::services::{AnalyticsService, UsageInterval};
+
+/// Query parameters for collection analytics
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct AnalyticsParams {
+    /// Period to report: an RFC 3339 instant or interval, e.g.
+    /// `2024-05-01T00:00:00Z/..`
+    pub datetime: Option<String>,
+    /// Granularity of the report: `hour` or `day` (default)
+    pub interval: Option<String>,
+}
+
+impl AnalyticsParams {
+    fn usage_interval(&self) -> AppResult<UsageInterval> {
+        match self.interval.as_deref() {
+            None | Some("day") => Ok(UsageInterval::Day),
+            Some("hour") => Ok(UsageInterval::Hour),
+            Some(other) => Err(AppError::BadRequest(format!(
+                "Invalid interval: {} (expected hour or day)",
+                other
+            ))),
+        }
+    }
+
+    fn bounds(&self) -> AppResult<DateTimeBounds> {
+        let parse = |instant: &str| -> AppResult<Option<DateTime<Utc>>> {
+            if instant.is_empty() || instant == ".." {
+                return Ok(None);
+            }
+            DateTime::parse_from_rfc3339(instant)
+                .map(|dt| Some(dt.with_timezone(&Utc)))
+                .map_err(|_| AppError::BadRequest(format!("Invalid datetime: {}", instant)))
+        };
+
+        match self.datetime.as_deref() {
+            None => Ok((None, None)),
+            Some(datetime) => match datetime.split_once('/') {
+                Some((start, end)) => Ok((parse(start)?, parse(end)?)),
+                None => {
+                    let instant = parse(datetime)?;
+                    Ok((instant, instant))
+                }
+            },
+        }
+    }
+}
+
+/// Tile usage in one period
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct UsagePeriod {
+    /// Start of the period
+    #[schemars(with = "String")]
+    pub start: DateTime<Utc>,
+    /// Number of tiles served
+    pub requests: i64,
+    /// Bytes of tile data served
+    pub bytes: i64,
+}
+
+/// Tile usage of a collection over time
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CollectionAnalytics {
+    pub collection: String,
+    /// Granularity of `usage`: `hour` or `day`
+    pub interval: String,
+    /// Total number of tiles served in the reported periods
+    pub requests: i64,
+    /// Total bytes of tile data served in the reported periods
+    pub bytes: i64,
+    /// Periods with any usage, oldest first
+    pub usage: Vec<UsagePeriod>,
+}
+
+/// Path parameters for collection analytics
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/analytics")]
+pub struct CollectionAnalyticsPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+pub async fn get_analytics(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<AnalyticsService>>,
+    _path: CollectionAnalyticsPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    Query(params): Query<AnalyticsParams>,
+) -> AppResult<Json<CollectionAnalytics>> {
+    if collection.owner != user.username {
+        return Err(AppError::Forbidden(
+            "Only owner can view collection analytics".to_string(),
+        ));
+    }
+
+    let interval = params.usage_interval()?;
+    let (start, end) = params.bounds()?;
+
+    // Include this server's most recent requests
+    if let Err(e) = service.flush().await {
+        tracing::warn!("Failed to flush tile usage: {}", e);
+    }
+
+    let usage: Vec<UsagePeriod> = service
+        .get_tile_usage(collection.id, interval, start, end)
+        .await?
+        .into_iter()
+        .map(|period| UsagePeriod {
+            start: period.period_start,
+            requests: period.requests,
+            bytes: period.bytes,
+        })
+        .collect();
+
+    Ok(Json(CollectionAnalytics {
+        collection: collection.canonical_name,
+        interval: interval.as_str().to_string(),
+        requests: usage.iter().map(|period| period.requests).sum(),
+        bytes: usage.iter().map(|period| period.bytes).sum(),
+        usage,
+    }))
+}
+
+fn get_analytics_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get collection analytics")
+        .description(
+            "Returns the number of tiles and bytes of tile data served from a collection per \
+             hour or day (UTC). Counts are buffered by each server and written periodically, so \
+             the latest minutes may be missing. Only the owner may view the analytics.",
+        )
+        .tag("Collections")
+        .response_with::<200, Json<CollectionAnalytics>, _>(|res| {
+            res.description("Tile usage of the collection")
+        })
+        .response_with::<400, (), _>(|res| res.description("Invalid datetime or interval"))
+        .response_with::<403, (), _>(|res| res.description("Only the owner may view the analytics"))
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+pub fn routes(service: Arc<AnalyticsService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/analytics",
+            get_with(get_analytics, get_analytics_docs),
+        )
+        .with_state(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(datetime: Option<&str>, interval: Option<&str>) -> AnalyticsParams {
+        AnalyticsParams {
+            datetime: datetime.map(str::to_string),
+            interval: interval.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_analytics_params() {
+        assert_eq!(
+            params(None, None).usage_interval().unwrap(),
+            UsageInterval::Day
+        );
+        assert_eq!(
+            params(None, Some("hour")).usage_interval().unwrap(),
+            UsageInterval::Hour
+        );
+        assert!(params(None, Some("week")).usage_interval().is_err());
+
+        let (start, end) = params(Some("2024-05-01T00:00:00Z/.."), None)
+            .bounds()
+            .unwrap();
+        assert!(start.is_some());
+        assert!(end.is_none());
+        assert!(params(Some("yesterday"), None).bounds().is_err());
+    }
+}
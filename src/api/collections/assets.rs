@@ -0,0 +1,177 @@
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, State},
+    http::StatusCode,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use super::resolved::ResolvedCollection;
+use super::schemas::{AssetObject, validate_asset_key};
+use crate::api::body::JsonBody;
+use crate::auth::AuthenticatedUser;
+use crate::error::{AppError, AppResult};
+use crate::services::CollectionService;
+
+/// Response listing the assets of a collection
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CollectionAssetsResponse {
+    pub collection_id: String,
+    pub assets: BTreeMap<String, AssetObject>,
+}
+
+/// Path parameters for collection asset endpoints
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/assets")]
+pub struct CollectionAssetsPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+pub async fn list_assets(
+    State(service): State<Arc<CollectionService>>,
+    _path: CollectionAssetsPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> AppResult<Json<CollectionAssetsResponse>> {
+    let assets = service.list_collection_assets(collection.id).await?;
+
+    Ok(Json(CollectionAssetsResponse {
+        collection_id: collection.canonical_name,
+        assets: assets
+            .into_iter()
+            .map(|asset| (asset.key.clone(), asset.into()))
+            .collect(),
+    }))
+}
+
+fn list_assets_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("List collection assets")
+        .description(
+            "Returns the assets attached to the collection itself, such as exports or \
+             documentation. They are also included in the collection response as STAC \
+             collection assets.",
+        )
+        .tag("Collections")
+        .response_with::<200, Json<CollectionAssetsResponse>, _>(|res| {
+            res.description("Assets of the collection")
+        })
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+/// Path parameters for a single collection asset
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/assets/{asset_key}")]
+pub struct CollectionAssetPath {
+    /// The collection identifier
+    pub collection_id: String,
+    /// The asset key
+    pub asset_key: String,
+}
+
+pub async fn get_asset(
+    State(service): State<Arc<CollectionService>>,
+    path: CollectionAssetPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> AppResult<Json<AssetObject>> {
+    service
+        .list_collection_assets(collection.id)
+        .await?
+        .into_iter()
+        .find(|asset| asset.key == path.asset_key)
+        .map(|asset| Json(asset.into()))
+        .ok_or_else(|| AppError::NotFound(format!("Asset not found: {}", path.asset_key)))
+}
+
+fn get_asset_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get collection asset")
+        .description("Returns a single asset of the collection")
+        .tag("Collections")
+        .response_with::<200, Json<AssetObject>, _>(|res| res.description("The asset"))
+        .response_with::<404, (), _>(|res| res.description("Collection or asset not found"))
+}
+
+pub async fn put_asset(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    path: CollectionAssetPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    JsonBody(asset): JsonBody<AssetObject>,
+) -> AppResult<(StatusCode, Json<AssetObject>)> {
+    validate_asset_key(&path.asset_key)?;
+    asset.validate()?;
+
+    let (stored, created) = service
+        .put_collection_asset(
+            &user.username,
+            &collection.canonical_name,
+            &path.asset_key,
+            &asset,
+        )
+        .await?;
+
+    let status = if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, Json(stored.into())))
+}
+
+fn put_asset_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Create or replace collection asset")
+        .description(
+            "Attaches an asset to the collection under the given key, replacing any asset \
+             with the same key. Only the collection owner can change its assets.",
+        )
+        .tag("Collections")
+        .response_with::<201, Json<AssetObject>, _>(|res| res.description("Asset created"))
+        .response_with::<200, Json<AssetObject>, _>(|res| res.description("Asset replaced"))
+        .response_with::<400, (), _>(|res| res.description("Invalid asset key or asset"))
+        .response_with::<403, (), _>(|res| res.description("Permission denied"))
+        .response_with::<507, (), _>(|res| {
+            res.description("The collection's owner is over its storage quota")
+        })
+}
+
+pub async fn delete_asset(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    path: CollectionAssetPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> AppResult<StatusCode> {
+    service
+        .delete_collection_asset(&user.username, &collection.canonical_name, &path.asset_key)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn delete_asset_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Delete collection asset")
+        .description("Removes an asset from the collection. The referenced file is not deleted.")
+        .tag("Collections")
+        .response_with::<204, (), _>(|res| res.description("Asset removed"))
+        .response_with::<403, (), _>(|res| res.description("Permission denied"))
+        .response_with::<404, (), _>(|res| res.description("Collection or asset not found"))
+}
+
+pub fn routes(service: Arc<CollectionService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/assets",
+            get_with(list_assets, list_assets_docs),
+        )
+        .api_route(
+            "/collections/{collection_id}/assets/{asset_key}",
+            get_with(get_asset, get_asset_docs)
+                .put_with(put_asset, put_asset_docs)
+                .delete_with(delete_asset, delete_asset_docs),
+        )
+        .with_state(service)
+}
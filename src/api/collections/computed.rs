@@ -0,0 +1,247 @@
+//! Computed properties of collections
+//!
+//! Owners define read-only properties as CQL2 expressions over each
+//! feature's geometry and properties, e.g. `ST_Area(geometry)` or
+//! `concat(first_name, ' ', last_name)`. They are evaluated when features are
+//! read, can be used in filters and are ignored when features are written.
+
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, State},
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::resolved::ResolvedCollection;
+use crate::api::body::JsonBody;
+use crate::api::common::etag;
+use crate::api::features::query::{ComputedSql, computed_to_sql, expression_uses_geometry};
+use crate::auth::AuthenticatedUser;
+use crate::error::{AppError, AppResult};
+use crate::services::CollectionService;
+
+/// A read-only property computed from an expression
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComputedProperty {
+    /// Name of the property in feature responses
+    pub name: String,
+    /// CQL2 text expression, e.g. `ST_Area(geometry)`
+    pub expression: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// The computed properties of a collection
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComputedProperties {
+    pub computed_properties: Vec<ComputedProperty>,
+}
+
+/// Check that computed properties have unique names and valid expressions
+pub fn validate_computed_properties(properties: &[ComputedProperty]) -> AppResult<()> {
+    for (index, property) in properties.iter().enumerate() {
+        if property.name.trim().is_empty() || property.name.contains('.') {
+            return Err(AppError::BadRequest(format!(
+                "Invalid computed property name: {}",
+                property.name
+            )));
+        }
+        if properties[..index].iter().any(|p| p.name == property.name) {
+            return Err(AppError::BadRequest(format!(
+                "Duplicate computed property name: {}",
+                property.name
+            )));
+        }
+        computed_to_sql(&property.name, &property.expression, "")?;
+    }
+    Ok(())
+}
+
+/// Computed properties stored in a collection's `computed_properties` column
+pub fn parse_computed_properties(value: Option<&serde_json::Value>) -> Vec<ComputedProperty> {
+    value
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// SQL of a collection's computed properties, with columns prefixed by
+/// `prefix`
+pub fn computed_properties_sql(
+    value: Option<&serde_json::Value>,
+    prefix: &str,
+) -> AppResult<Vec<ComputedSql>> {
+    parse_computed_properties(value)
+        .iter()
+        .map(|property| computed_to_sql(&property.name, &property.expression, prefix))
+        .collect()
+}
+
+/// Path parameters for computed property endpoints
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/computed-properties")]
+pub struct ComputedPropertiesPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+pub async fn get_computed_properties(
+    _path: ComputedPropertiesPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> Result<Response, AppError> {
+    let computed_properties = parse_computed_properties(collection.computed_properties.as_ref());
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ETAG, etag::create_etag_header(collection.version)?);
+
+    Ok((
+        headers,
+        Json(ComputedProperties {
+            computed_properties,
+        }),
+    )
+        .into_response())
+}
+
+fn get_computed_properties_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("List computed properties")
+        .description("Returns the read-only properties computed for the features of a collection")
+        .tag("Collections")
+        .response_with::<200, Json<ComputedProperties>, _>(|res| {
+            res.description("Computed properties of the collection")
+        })
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+pub async fn put_computed_properties(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    _path: ComputedPropertiesPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    headers: HeaderMap,
+    JsonBody(request): JsonBody<ComputedProperties>,
+) -> Result<Response, AppError> {
+    // If-Match header is optional - when present, enables optimistic locking
+    let expected_version = etag::extract_expected_version(&headers)?;
+
+    let target = collection.as_collection();
+    if !target.has_feature_table() {
+        return Err(AppError::BadRequest(
+            "Computed properties are only supported for vector and table collections".to_string(),
+        ));
+    }
+    validate_computed_properties(&request.computed_properties)?;
+    if !target.has_geometry()
+        && let Some(property) = request
+            .computed_properties
+            .iter()
+            .find(|p| expression_uses_geometry(&p.expression))
+    {
+        return Err(AppError::BadRequest(format!(
+            "Computed property {} uses the geometry, but the collection has none",
+            property.name
+        )));
+    }
+
+    let collection = service
+        .update_computed_properties(
+            &user.username,
+            &collection.canonical_name,
+            expected_version,
+            &request.computed_properties,
+        )
+        .await?;
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::ETAG, etag::create_etag_header(collection.version)?);
+
+    Ok((
+        response_headers,
+        Json(ComputedProperties {
+            computed_properties: parse_computed_properties(collection.computed_properties.as_ref()),
+        }),
+    )
+        .into_response())
+}
+
+fn put_computed_properties_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Replace computed properties")
+        .description(
+            "Replaces the computed properties of a vector or table collection. Expressions are \
+             CQL2 text over `geometry`, `id` and the feature's properties, using `+`, `-`, `*`, \
+             `/`, `concat`, `upper`, `lower`, `trim`, `length`, `abs`, `round`, `floor`, \
+             `ceil`, `ST_Area`, `ST_Length`, `ST_Perimeter`, `ST_X`, `ST_Y`, `ST_NPoints` and \
+             `ST_GeometryType`. Properties that are not numbers where a number is expected \
+             evaluate to null. If-Match header is optional; when provided, enables optimistic \
+             locking.",
+        )
+        .tag("Collections")
+        .response_with::<200, Json<ComputedProperties>, _>(|res| {
+            res.description("Updated computed properties")
+        })
+        .response_with::<400, (), _>(|res| res.description("Invalid computed properties"))
+        .response_with::<403, (), _>(|res| {
+            res.description("Only the owner may update the computed properties")
+        })
+        .response_with::<412, (), _>(|res| res.description("Precondition failed (ETag mismatch)"))
+}
+
+pub fn routes(service: Arc<CollectionService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/computed-properties",
+            get_with(get_computed_properties, get_computed_properties_docs)
+                .put_with(put_computed_properties, put_computed_properties_docs),
+        )
+        .with_state(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn computed(name: &str, expression: &str) -> ComputedProperty {
+        ComputedProperty {
+            name: name.to_string(),
+            expression: expression.to_string(),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_computed_properties() {
+        assert!(
+            validate_computed_properties(&[
+                computed("area", "ST_Area(geometry)"),
+                computed("label", "concat(name, ' ', kind)"),
+            ])
+            .is_ok()
+        );
+        assert!(
+            validate_computed_properties(&[
+                computed("area", "ST_Area(geometry)"),
+                computed("area", "ST_Length(geometry)"),
+            ])
+            .is_err()
+        );
+        assert!(validate_computed_properties(&[computed("a.b", "1")]).is_err());
+        assert!(validate_computed_properties(&[computed("x", "current_user()")]).is_err());
+    }
+
+    #[test]
+    fn test_computed_properties_sql() {
+        let value = serde_json::json!([{"name": "area", "expression": "ST_Area(geometry)"}]);
+        let sql = computed_properties_sql(Some(&value), "t.").unwrap();
+        assert_eq!(sql.len(), 1);
+        assert_eq!(sql[0].sql, "ST_Area(t.geometry)");
+        assert!(computed_properties_sql(None, "").unwrap().is_empty());
+    }
+}
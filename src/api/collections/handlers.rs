@@ -11,18 +11,26 @@ use axum::{
     http::{HeaderMap, StatusCode, header},
     response::{IntoResponse, Response},
 };
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
+use super::resolved::ResolvedCollection;
 use super::schemas::{
-    CollectionResponse, CollectionSchema, CollectionsResponse, CreateCollectionRequest,
-    ListCollectionsParams, UpdateCollectionRequest,
+    CollectionGeometry, CollectionLimits, CollectionMetadata, CollectionResponse, CollectionSchema,
+    CollectionSchemaParams, CollectionSchemaPatch, CollectionsResponse, CreateCollectionRequest,
+    ListCollectionsParams, ProcessingDefaults, UpdateCollectionRequest, validate_geometry_columns,
+    validate_unique_properties,
 };
+use crate::api::body::{JsonBody, MergePatchBody};
 use crate::api::common::{Extent, Link, crs, etag, media_type, rel};
+use crate::api::language::AcceptLanguage;
+use crate::api::validate_only::ValidateOnly;
 use crate::auth::AuthenticatedUser;
-use crate::config::Config;
+use crate::config::{Config, ModulesConfig};
 use crate::db::Collection;
 use crate::error::{AppError, AppResult};
-use crate::services::CollectionService;
+use crate::openapi;
+use crate::services::{CollectionService, QuotaService};
 
 /// Build the list of CRSes supported for retrieving features from a collection
 /// Always includes WGS84, and adds storage CRS if it's different from WGS84
@@ -45,42 +53,117 @@ fn build_crs_list(storage_crs: Option<i32>) -> Vec<String> {
 ///
 /// The link structure differs between list and detail views:
 /// - List: self, items, tiles/coverage (type-specific)
-/// - Detail: self, items, parent, tiles/coverage, schema
+/// - Detail: self, items, parent, tiles/coverage, schema, ISO 19139 metadata
+///
+/// Title and description are picked from the translations by `Accept-Language`;
+/// the self link then carries the language as `hreflang`.
 fn build_collection_response(
     collection: &Collection,
     base_url: &str,
+    modules: &ModulesConfig,
     extent: Option<Extent>,
     storage_crs: i32,
+    geometry: CollectionGeometry,
     include_extended_links: bool,
+    accept_language: &AcceptLanguage,
 ) -> CollectionResponse {
     let id = &collection.canonical_name;
+    let metadata = CollectionMetadata::from_stored(
+        &collection.keywords,
+        collection.license.as_deref(),
+        collection.title_i18n.as_ref(),
+        collection.description_i18n.as_ref(),
+    );
+    let title = accept_language.localize(
+        &collection.title,
+        metadata.titles.as_ref().unwrap_or(&BTreeMap::new()),
+    );
+    let description = collection.description.as_deref().map(|description| {
+        accept_language.localize(
+            description,
+            metadata.descriptions.as_ref().unwrap_or(&BTreeMap::new()),
+        )
+    });
+
+    let mut self_link = Link::new(format!("{}/collections/{}", base_url, id), rel::SELF)
+        .with_type(media_type::JSON);
+    if let Some(language) = title
+        .language
+        .as_ref()
+        .or_else(|| description.as_ref().and_then(|d| d.language.as_ref()))
+    {
+        self_link = self_link.with_hreflang(language);
+    }
 
     // Base links that always appear
     let mut links = vec![
-        Link::new(format!("{}/collections/{}", base_url, id), rel::SELF)
-            .with_type(media_type::JSON),
+        self_link,
         Link::new(format!("{}/collections/{}/items", base_url, id), rel::ITEMS)
             .with_type(media_type::GEOJSON),
     ];
 
-    // Add type-specific links (always included for both list and detail)
+    // Add type-specific links of the enabled API groups (always included
+    // for both list and detail)
     match collection.collection_type.as_str() {
         "vector" => {
             links.push(
-                Link::new(format!("{}/collections/{}/tiles", base_url, id), "tiles")
-                    .with_type(media_type::JSON),
+                Link::new(
+                    format!("{}/collections/{}/items?f=gml", base_url, id),
+                    rel::ITEMS,
+                )
+                .with_type(media_type::GML_SF0),
+            );
+            links.push(
+                Link::new(
+                    format!("{}/collections/{}/schema.xsd", base_url, id),
+                    "describedby",
+                )
+                .with_type(media_type::XML),
             );
+            if modules.tiles {
+                links.push(
+                    Link::new(format!("{}/collections/{}/tiles", base_url, id), "tiles")
+                        .with_type(media_type::JSON),
+                );
+                links.push(
+                    Link::new(
+                        format!("{}/collections/{}/tiles", base_url, id),
+                        "http://www.opengis.net/def/rel/ogc/1.0/tilesets-vector",
+                    )
+                    .with_type(media_type::JSON),
+                );
+            }
         }
         "raster" => {
             // Raster collections support both tiles and coverage endpoints
-            links.push(
-                Link::new(format!("{}/collections/{}/tiles", base_url, id), "tiles")
+            if modules.tiles {
+                links.push(
+                    Link::new(format!("{}/collections/{}/tiles", base_url, id), "tiles")
+                        .with_type(media_type::JSON),
+                );
+                links.push(
+                    Link::new(
+                        format!("{}/collections/{}/tiles", base_url, id),
+                        "http://www.opengis.net/def/rel/ogc/1.0/tilesets-map",
+                    )
                     .with_type(media_type::JSON),
-            );
+                );
+            }
+            if modules.coverages {
+                links.push(
+                    Link::new(
+                        format!("{}/collections/{}/coverage", base_url, id),
+                        "coverage",
+                    )
+                    .with_type(media_type::JSON),
+                );
+            }
+        }
+        "pointcloud" if modules.pointclouds => {
             links.push(
                 Link::new(
-                    format!("{}/collections/{}/coverage", base_url, id),
-                    "coverage",
+                    format!("{}/collections/{}/3dtiles/tileset.json", base_url, id),
+                    "3dtiles",
                 )
                 .with_type(media_type::JSON),
             );
@@ -101,20 +184,54 @@ fn build_collection_response(
                 format!("{}/collections/{}/schema", base_url, id),
                 "describedby",
             )
-            .with_type(media_type::JSON)
+            .with_type(media_type::SCHEMA_JSON)
             .with_title("Schema for this collection"),
         );
+        links.push(
+            Link::new(
+                format!("{}/collections/{}/schema", base_url, id),
+                "http://www.opengis.net/def/rel/ogc/1.0/schema",
+            )
+            .with_type(media_type::SCHEMA_JSON)
+            .with_title("Schema of the returnables and receivables"),
+        );
+
+        // ISO 19139 metadata document
+        links.push(
+            Link::new(
+                format!("{}/collections/{}/metadata?f=iso19139", base_url, id),
+                "describedby",
+            )
+            .with_type(media_type::XML)
+            .with_title("ISO 19139 metadata"),
+        );
     }
 
     CollectionResponse {
         id: id.clone(),
-        title: collection.title.clone(),
-        description: collection.description.clone(),
+        title: title.text,
+        description: description.map(|description| description.text),
+        metadata,
         links,
         extent,
         item_type: Some("feature".to_string()),
+        geometry,
         crs: Some(build_crs_list(Some(storage_crs))),
         storage_crs: Some(crs::srid_to_uri(storage_crs)),
+        limits: CollectionLimits {
+            default_limit: collection.default_limit.map(|limit| limit as u32),
+            max_limit: collection.max_limit.map(|limit| limit as u32),
+            min_zoom: collection.min_zoom.map(|zoom| zoom as u32),
+            max_zoom: collection.max_zoom.map(|zoom| zoom as u32),
+            tile_layer: collection.tile_layer.clone(),
+        },
+        processing_defaults: ProcessingDefaults::from_stored(
+            collection.processing_defaults.as_ref(),
+        ),
+        assets: None,
+        expires: collection.expires_at,
+        data_updated: collection.data_updated_at,
+        usage: None,
     }
 }
 
@@ -122,11 +239,14 @@ pub async fn list_collections(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
     State(service): State<Arc<CollectionService>>,
+    accept_language: AcceptLanguage,
     Query(params): Query<ListCollectionsParams>,
 ) -> AppResult<Json<CollectionsResponse>> {
-    let collections = service
-        .list_collections(&user.username, params.limit, params.offset)
+    let filter = params.filter()?;
+    let (collections, total) = service
+        .list_collections(&user.username, &filter, params.limit, params.offset)
         .await?;
+    let facets = service.collection_facets(&user.username, &filter).await?;
 
     let base_url = &config.base_url;
 
@@ -137,19 +257,45 @@ pub async fn list_collections(
         collection_responses.push(build_collection_response(
             &c.as_collection(),
             base_url,
+            &config.modules,
             extent,
             c.storage_crs,
+            CollectionGeometry::from_postgis(c.geometry_type.as_deref(), c.geometry_dimension),
             false, // List view: don't include parent and schema links
+            &accept_language,
         ));
     }
 
+    let mut links = vec![
+        Link::new(params.page_href(base_url, params.offset), rel::SELF).with_type(media_type::JSON),
+    ];
+
+    if (params.offset as i64) + (params.limit as i64) < total {
+        links.push(
+            Link::new(
+                params.page_href(base_url, params.offset + params.limit),
+                rel::NEXT,
+            )
+            .with_type(media_type::JSON),
+        );
+    }
+
+    if params.offset > 0 {
+        links.push(
+            Link::new(
+                params.page_href(base_url, params.offset.saturating_sub(params.limit)),
+                rel::PREV,
+            )
+            .with_type(media_type::JSON),
+        );
+    }
+
     let response = CollectionsResponse {
+        number_matched: Some(total as u64),
+        number_returned: Some(collection_responses.len() as u64),
         collections: collection_responses,
-        links: vec![
-            Link::new(format!("{}/collections", base_url), rel::SELF).with_type(media_type::JSON),
-        ],
-        number_matched: None,
-        number_returned: None,
+        links,
+        facets: Some(facets),
     };
 
     Ok(Json(response))
@@ -157,7 +303,14 @@ pub async fn list_collections(
 
 fn list_collections_docs(op: TransformOperation) -> TransformOperation {
     op.summary("List collections")
-        .description("Returns a list of all collections accessible to the authenticated user")
+        .description(
+            "Returns the collections accessible to the authenticated user. `q`, `keyword`, \
+             `type`, `owner`, `bbox` and `datetime` narrow the list for catalog discovery; \
+             `facets` counts the matching collections per keyword and type. `bbox` and \
+             `datetime` match the extent last computed for each collection, which is \
+             recomputed in the background shortly after its features or items change. \
+             Results are paged with limit/offset and next/prev links.",
+        )
         .tag("Collections")
         .response_with::<200, Json<CollectionsResponse>, _>(|res| {
             res.description("List of collections")
@@ -174,52 +327,70 @@ pub struct CollectionPath {
 
 pub async fn get_collection(
     Extension(config): Extension<Arc<Config>>,
-    Extension(user): Extension<AuthenticatedUser>,
+    Extension(quotas): Extension<Arc<QuotaService>>,
     State(service): State<Arc<CollectionService>>,
-    path: CollectionPath,
+    _path: CollectionPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    accept_language: AcceptLanguage,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = service.check_alias_redirect(&collection_id).await? {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::LOCATION,
-            format!("{}/collections/{}", config.base_url, new_name)
-                .parse()
-                .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, headers).into_response());
-    }
-
-    let collection = service
-        .get_collection(&user.username, &collection_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Collection not found: {}", collection_id)))?;
-
     // Get computed extent
     let extent = service.compute_extent(&collection.as_collection()).await?;
 
     let base_url = &config.base_url;
 
     // Build the response using the common helper, with all links included
-    let response = build_collection_response(
+    let mut response = build_collection_response(
         &collection.as_collection(),
         base_url,
+        &config.modules,
         extent,
         collection.storage_crs,
+        CollectionGeometry::from_postgis(
+            collection.geometry_type.as_deref(),
+            collection.geometry_dimension,
+        ),
         true,
+        &accept_language,
     );
+    let assets = service.list_collection_assets(collection.id).await?;
+    if !assets.is_empty() {
+        response.assets = Some(
+            assets
+                .into_iter()
+                .map(|asset| (asset.key.clone(), asset.into()))
+                .collect(),
+        );
+    }
+    response.usage = Some(quotas.collection_usage(collection.id).await?.into());
 
     // Create ETag from version
     let mut headers = HeaderMap::new();
     headers.insert(header::ETAG, etag::create_etag_header(collection.version)?);
+    if let Some(language) = response
+        .links
+        .first()
+        .and_then(|link| link.hreflang.as_ref())
+    {
+        headers.insert(
+            header::CONTENT_LANGUAGE,
+            language
+                .parse()
+                .map_err(|_| AppError::Internal("Invalid language tag".to_string()))?,
+        );
+    }
+    headers.insert(header::VARY, header::ACCEPT_LANGUAGE.into());
 
     Ok((headers, Json(response)).into_response())
 }
 
 fn get_collection_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Get collection")
-        .description("Returns the metadata for a specific collection")
+        .description(
+            "Returns the metadata for a specific collection. Title and description are \
+             given in the language preferred by `Accept-Language` when a translation exists. \
+             `usage` is the storage the collection uses, counted towards the quota of its \
+             owner; see `/usage`.",
+        )
         .tag("Collections")
         .response_with::<200, Json<CollectionResponse>, _>(|res| {
             res.description("Collection metadata")
@@ -231,8 +402,15 @@ pub async fn create_collection(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
     State(service): State<Arc<CollectionService>>,
-    Json(request): Json<CreateCollectionRequest>,
-) -> AppResult<(StatusCode, HeaderMap, Json<CollectionResponse>)> {
+    accept_language: AcceptLanguage,
+    validate_only: ValidateOnly,
+    JsonBody(request): JsonBody<CreateCollectionRequest>,
+) -> AppResult<Response> {
+    let service = if validate_only.0 {
+        Arc::new(service.validate_only())
+    } else {
+        service
+    };
     // Determine canonical name (prepend username if not already prefixed)
     let canonical_name = if request.id.starts_with(&format!("{}:", user.username)) {
         request.id.clone()
@@ -240,6 +418,8 @@ pub async fn create_collection(
         format!("{}:{}", user.username, request.id)
     };
 
+    let expires_at = request.expires_at()?;
+
     // Determine owner (default to current user)
     let owner = request.owner.unwrap_or_else(|| user.username.clone());
 
@@ -251,6 +431,11 @@ pub async fn create_collection(
         )));
     }
 
+    request.limits.validate()?;
+    let metadata = request.metadata.normalized()?;
+    validate_unique_properties(&request.unique_properties)?;
+    validate_geometry_columns(&request.geometry_columns)?;
+
     let collection = service
         .create_collection(
             &user.username,
@@ -260,6 +445,12 @@ pub async fn create_collection(
             request.description.as_deref(),
             &request.collection_type,
             request.crs,
+            &request.limits,
+            &metadata,
+            &request.unique_properties,
+            &request.geometry_columns,
+            &request.geometry,
+            expires_at,
         )
         .await?;
 
@@ -270,9 +461,12 @@ pub async fn create_collection(
     let response = build_collection_response(
         &collection,
         base_url,
+        &config.modules,
         None,        // extent not computed for create response
         request.crs, // storage_crs from request
-        true,        // include all links for consistency
+        request.geometry.as_created(&request.collection_type),
+        true, // include all links for consistency
+        &accept_language,
     );
 
     let mut headers = HeaderMap::new();
@@ -282,13 +476,35 @@ pub async fn create_collection(
     headers.insert(header::LOCATION, location_value);
     headers.insert(header::ETAG, etag::create_etag_header(collection.version)?);
 
-    Ok((StatusCode::CREATED, headers, Json(response)))
+    Ok(validate_only.respond((StatusCode::CREATED, headers, Json(response)).into_response()))
 }
 
 fn create_collection_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Create collection")
-        .description("Creates a new collection owned by the authenticated user")
+        .description(
+            "Creates a new collection owned by the authenticated user. With \
+             `Prefer: handling=validate-only` (or `dryRun=true`) the collection is validated and \
+             rolled back instead of created; the response shows what would have been created, \
+             with status 200. With expiresIn, the collection is a scratch collection that is \
+             deleted automatically once that many seconds have passed. A vector collection can \
+             be declared with a geometryType (e.g. `Polygon`; `Geometry`, the default, allows \
+             any) and a geometryDimension of 3 for geometries with heights; features of another \
+             type or dimension are rejected with 400 Bad Request.",
+        )
         .tag("Collections")
+        .with(|op| {
+            openapi::request_example(
+                op,
+                serde_json::json!({
+                    "id": "buildings",
+                    "title": "Buildings",
+                    "description": "Building footprints",
+                    "collectionType": "vector",
+                    "crs": 3006,
+                    "geometryType": "Polygon"
+                }),
+            )
+        })
         .response_with::<201, Json<CollectionResponse>, _>(|res| {
             res.description("Collection created successfully")
         })
@@ -297,17 +513,30 @@ fn create_collection_docs(op: TransformOperation) -> TransformOperation {
 }
 
 /// PATCH - Partial update using JSON Merge Patch (RFC 7386)
+#[allow(clippy::too_many_arguments)]
 pub async fn patch_collection(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
     State(service): State<Arc<CollectionService>>,
-    path: CollectionPath,
+    _path: CollectionPath,
+    ResolvedCollection(collection): ResolvedCollection,
     headers: HeaderMap,
-    Json(request): Json<UpdateCollectionRequest>,
-) -> AppResult<(HeaderMap, Json<CollectionResponse>)> {
-    let collection_id = path.collection_id;
+    accept_language: AcceptLanguage,
+    validate_only: ValidateOnly,
+    MergePatchBody(request): MergePatchBody<UpdateCollectionRequest>,
+) -> AppResult<Response> {
+    let service = if validate_only.0 {
+        Arc::new(service.validate_only())
+    } else {
+        service
+    };
+    let collection_id = collection.canonical_name.clone();
     // If-Match header is required for PATCH to prevent lost updates
     let expected_version = Some(etag::extract_required_version(&headers)?);
+    if let Some(defaults) = &request.processing_defaults {
+        defaults.validate()?;
+    }
+    let metadata = request.metadata.normalized()?;
 
     let collection = service
         .update_collection(
@@ -317,6 +546,9 @@ pub async fn patch_collection(
             request.title.as_deref(),
             request.description.as_deref(),
             request.id.as_deref(),
+            &request.limits,
+            request.processing_defaults.as_ref(),
+            &metadata,
         )
         .await?;
 
@@ -324,6 +556,7 @@ pub async fn patch_collection(
 
     // Fetch storage_crs from database
     let storage_crs = service.get_storage_crs(&collection).await?.unwrap_or(4326);
+    let geometry = service.get_geometry(&collection).await?;
 
     // Compute extent
     let extent = service.compute_extent(&collection).await?;
@@ -332,21 +565,24 @@ pub async fn patch_collection(
     let response = build_collection_response(
         &collection,
         base_url,
+        &config.modules,
         extent,
         storage_crs,
+        geometry,
         true, // include all links for consistency
+        &accept_language,
     );
 
     let mut response_headers = HeaderMap::new();
     response_headers.insert(header::ETAG, etag::create_etag_header(collection.version)?);
 
-    Ok((response_headers, Json(response)))
+    Ok(validate_only.respond((response_headers, Json(response)).into_response()))
 }
 
 fn patch_collection_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Update collection (partial)")
         .description(
-            "Partially updates a collection using JSON Merge Patch. If-Match header is required to prevent lost updates.",
+            "Partially updates a collection using JSON Merge Patch. If-Match header is required to prevent lost updates. `processingDefaults` replaces the import defaults of the collection as a whole; `{}` removes them. With `Prefer: handling=validate-only` (or `dryRun=true`) the write is validated and rolled back instead of committed.",
         )
         .tag("Collections")
         .response_with::<200, Json<CollectionResponse>, _>(|res| {
@@ -356,15 +592,24 @@ fn patch_collection_docs(op: TransformOperation) -> TransformOperation {
 }
 
 /// PUT - Full replacement of a collection
+#[allow(clippy::too_many_arguments)]
 pub async fn update_collection(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
     State(service): State<Arc<CollectionService>>,
-    path: CollectionPath,
+    _path: CollectionPath,
+    ResolvedCollection(collection): ResolvedCollection,
     headers: HeaderMap,
-    Json(request): Json<CreateCollectionRequest>,
-) -> AppResult<(HeaderMap, Json<CollectionResponse>)> {
-    let collection_id = path.collection_id;
+    accept_language: AcceptLanguage,
+    validate_only: ValidateOnly,
+    JsonBody(request): JsonBody<CreateCollectionRequest>,
+) -> AppResult<Response> {
+    let service = if validate_only.0 {
+        Arc::new(service.validate_only())
+    } else {
+        service
+    };
+    let collection_id = collection.canonical_name.clone();
     // If-Match header is required for PUT to prevent lost updates
     let expected_version = Some(etag::extract_required_version(&headers)?);
 
@@ -381,6 +626,9 @@ pub async fn update_collection(
         ));
     }
 
+    request.limits.validate()?;
+    let metadata = request.metadata.normalized()?;
+
     let collection = service
         .replace_collection(
             &user.username,
@@ -388,6 +636,8 @@ pub async fn update_collection(
             expected_version,
             &request.title,
             request.description.as_deref(),
+            &request.limits,
+            &metadata,
         )
         .await?;
 
@@ -395,6 +645,7 @@ pub async fn update_collection(
 
     // Fetch storage_crs from database
     let storage_crs = service.get_storage_crs(&collection).await?.unwrap_or(4326);
+    let geometry = service.get_geometry(&collection).await?;
 
     // Compute extent
     let extent = service.compute_extent(&collection).await?;
@@ -403,21 +654,24 @@ pub async fn update_collection(
     let response = build_collection_response(
         &collection,
         base_url,
+        &config.modules,
         extent,
         storage_crs,
+        geometry,
         true, // include all links for consistency
+        &accept_language,
     );
 
     let mut response_headers = HeaderMap::new();
     response_headers.insert(header::ETAG, etag::create_etag_header(collection.version)?);
 
-    Ok((response_headers, Json(response)))
+    Ok(validate_only.respond((response_headers, Json(response)).into_response()))
 }
 
 fn update_collection_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Replace collection")
         .description(
-            "Fully replaces a collection. If-Match header is required to prevent lost updates.",
+            "Fully replaces a collection. If-Match header is required to prevent lost updates. With `Prefer: handling=validate-only` (or `dryRun=true`) the write is validated and rolled back instead of committed.",
         )
         .tag("Collections")
         .response_with::<200, Json<CollectionResponse>, _>(|res| {
@@ -431,10 +685,11 @@ fn update_collection_docs(op: TransformOperation) -> TransformOperation {
 pub async fn delete_collection(
     Extension(user): Extension<AuthenticatedUser>,
     State(service): State<Arc<CollectionService>>,
-    path: CollectionPath,
+    _path: CollectionPath,
+    ResolvedCollection(collection): ResolvedCollection,
     headers: HeaderMap,
 ) -> AppResult<StatusCode> {
-    let collection_id = path.collection_id;
+    let collection_id = collection.canonical_name.clone();
     // If-Match header is optional - when present, enables optimistic locking
     let expected_version = etag::extract_expected_version(&headers)?;
 
@@ -465,35 +720,115 @@ pub async fn get_collection_schema(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
     State(service): State<Arc<CollectionService>>,
-    path: CollectionSchemaPath,
+    _path: CollectionSchemaPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    Query(params): Query<CollectionSchemaParams>,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = service.check_alias_redirect(&collection_id).await? {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::LOCATION,
-            format!("{}/collections/{}/schema", config.base_url, new_name)
-                .parse()
-                .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, headers).into_response());
-    }
-
+    let collection_id = collection.canonical_name.clone();
     let schema = service
-        .get_collection_schema(&user.username, &collection_id)
-        .await?;
+        .get_collection_schema(&user.username, &collection_id, &config.base_url)
+        .await?
+        .with_profile(params.profile);
 
-    Ok(Json(schema).into_response())
+    Ok((
+        [(header::CONTENT_TYPE, media_type::SCHEMA_JSON)],
+        Json(schema),
+    )
+        .into_response())
 }
 
 fn get_collection_schema_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Get collection schema")
-        .description("Returns the JSON Schema describing features in this collection")
+        .description(
+            "Returns the JSON Schema describing features in this collection (OGC API Features \
+             Part 5): the id, the geometry and the properties found in the stored features. \
+             Properties have their roles in `x-ogc-role` (`id`, `primary-geometry`, \
+             `primary-instant`) and those maintained by the server are `readOnly`. `profile=receivables` leaves \
+             out the read-only properties, e.g. to build an edit form, and \
+             `profile=returnables` lists the properties returned in features.",
+        )
         .tag("Collections")
         .response_with::<200, Json<CollectionSchema>, _>(|res| res.description("Collection schema"))
 }
 
+pub async fn patch_collection_schema(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    _path: CollectionSchemaPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    headers: HeaderMap,
+    MergePatchBody(request): MergePatchBody<CollectionSchemaPatch>,
+) -> Result<Response, AppError> {
+    // If-Match header is optional - when present, enables optimistic locking
+    let expected_version = etag::extract_expected_version(&headers)?;
+
+    // Each update bumps the version, so a second one expects the first's
+    let mut version = collection.version;
+    let mut expected_version = expected_version;
+    if let Some(unique_properties) = &request.unique_properties {
+        validate_unique_properties(unique_properties)?;
+        version = service
+            .update_unique_properties(
+                &user.username,
+                &collection.canonical_name,
+                expected_version,
+                unique_properties,
+            )
+            .await?
+            .version;
+        expected_version = Some(version);
+    }
+    if let Some(geometry_columns) = &request.geometry_columns {
+        validate_geometry_columns(geometry_columns)?;
+        version = service
+            .update_geometry_columns(
+                &user.username,
+                &collection.canonical_name,
+                expected_version,
+                geometry_columns,
+            )
+            .await?
+            .version;
+    }
+
+    let schema = service
+        .get_collection_schema(&user.username, &collection.canonical_name, &config.base_url)
+        .await?;
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(
+        header::CONTENT_TYPE,
+        media_type::SCHEMA_JSON.parse().unwrap(),
+    );
+    response_headers.insert(header::ETAG, etag::create_etag_header(version)?);
+
+    Ok((response_headers, Json(schema)).into_response())
+}
+
+fn patch_collection_schema_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Update collection schema")
+        .description(
+            "Updates the constraints of a vector or table collection using JSON Merge Patch. \
+             `x-uniqueProperties` lists the properties whose values must be unique among the \
+             features; the database enforces this with a unique index, and writes that would \
+             duplicate a value fail with 409 Conflict. `x-geometryColumns` lists secondary \
+             geometry columns of a vector collection, each generated from the GeoJSON geometry \
+             in the property of the same name and selectable with `geom` on items and tiles. \
+             If-Match header is optional; when provided, enables optimistic locking.",
+        )
+        .tag("Collections")
+        .response_with::<200, Json<CollectionSchema>, _>(|res| {
+            res.description("Updated collection schema")
+        })
+        .response_with::<400, (), _>(|res| res.description("Invalid schema update"))
+        .response_with::<403, (), _>(|res| res.description("Only the owner may update the schema"))
+        .response_with::<409, (), _>(|res| {
+            res.description("Existing features have duplicate values")
+        })
+        .response_with::<412, (), _>(|res| res.description("Precondition failed (ETag mismatch)"))
+}
+
 pub fn routes(service: Arc<CollectionService>) -> ApiRouter {
     ApiRouter::new()
         .api_route(
@@ -510,7 +845,8 @@ pub fn routes(service: Arc<CollectionService>) -> ApiRouter {
         )
         .api_route(
             "/collections/{collection_id}/schema",
-            get_with(get_collection_schema, get_collection_schema_docs),
+            get_with(get_collection_schema, get_collection_schema_docs)
+                .patch_with(patch_collection_schema, patch_collection_schema_docs),
         )
         .with_state(service)
 }
@@ -0,0 +1,472 @@
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    extract::{Extension, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use super::resolved::ResolvedCollection;
+use crate::api::common::{Bbox, Extent, media_type};
+use crate::config::{Config, MetadataConfig};
+use crate::db::CollectionWithCrs;
+use crate::error::{AppError, AppResult};
+use crate::services::CollectionService;
+
+/// Query parameters for the metadata document
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MetadataParams {
+    /// Metadata format; only `iso19139` is supported
+    #[serde(default = "default_format")]
+    pub f: String,
+}
+
+fn default_format() -> String {
+    "iso19139".to_string()
+}
+
+/// Escape text for use in XML content and attribute values
+pub(crate) fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A `gco:CharacterString` element wrapped in `tag`
+fn character_string(tag: &str, text: &str) -> String {
+    format!(
+        "<{tag}><gco:CharacterString>{}</gco:CharacterString></{tag}>",
+        xml_escape(text)
+    )
+}
+
+/// A code list value, e.g. a role or a date type
+fn code_list(tag: &str, list: &str, value: &str) -> String {
+    format!(
+        "<{tag}><gmd:{list} codeList=\"http://standards.iso.org/iso/19139/resources/gmxCodelists.xml#{list}\" codeListValue=\"{value}\">{value}</gmd:{list}></{tag}>"
+    )
+}
+
+/// Values of the ISO 19115 `MD_TopicCategoryCode` list
+const TOPIC_CATEGORIES: &[&str] = &[
+    "farming",
+    "biota",
+    "boundaries",
+    "climatologyMeteorologyAtmosphere",
+    "economy",
+    "elevation",
+    "environment",
+    "geoscientificInformation",
+    "health",
+    "imageryBaseMapsEarthCover",
+    "intelligenceMilitary",
+    "inlandWaters",
+    "location",
+    "oceans",
+    "planningCadastre",
+    "society",
+    "structure",
+    "transportation",
+    "utilitiesCommunication",
+];
+
+/// Topic categories named by a collection's keywords, or else the
+/// configured default
+fn topic_categories<'a>(keywords: &[String], default: Option<&'a str>) -> Vec<&'a str> {
+    let categories: Vec<&str> = TOPIC_CATEGORIES
+        .iter()
+        .copied()
+        .filter(|category| {
+            keywords
+                .iter()
+                .any(|keyword| keyword.eq_ignore_ascii_case(category))
+        })
+        .collect();
+    if categories.is_empty() {
+        default.into_iter().collect()
+    } else {
+        categories
+    }
+}
+
+fn responsible_party(tag: &str, organisation: &str, email: Option<&str>) -> String {
+    let contact = email.map_or_else(String::new, |email| {
+        format!(
+            "<gmd:contactInfo><gmd:CI_Contact><gmd:address><gmd:CI_Address>{}</gmd:CI_Address></gmd:address></gmd:CI_Contact></gmd:contactInfo>",
+            character_string("gmd:electronicMailAddress", email)
+        )
+    });
+    format!(
+        "<{tag}><gmd:CI_ResponsibleParty>{}{}{}</gmd:CI_ResponsibleParty></{tag}>",
+        character_string("gmd:organisationName", organisation),
+        contact,
+        code_list("gmd:role", "CI_RoleCode", "pointOfContact"),
+    )
+}
+
+/// Lineage statement of a collection's data
+fn lineage(collection: &CollectionWithCrs) -> String {
+    let source = match collection.collection_type.as_str() {
+        "raster" => "Raster items",
+        "pointcloud" => "Point cloud items",
+        "table" => "Attribute records",
+        _ => "Features",
+    };
+    let mut statement = format!(
+        "{} maintained in the collection {}.",
+        source, collection.canonical_name
+    );
+    if let Some(created) = collection.created_at {
+        let _ = write!(
+            statement,
+            " Created {}.",
+            created.format("%Y-%m-%dT%H:%M:%SZ")
+        );
+    }
+    if let Some(updated) = collection.data_updated_at {
+        let _ = write!(
+            statement,
+            " Data last changed {}.",
+            updated.format("%Y-%m-%dT%H:%M:%SZ")
+        );
+    }
+    statement
+}
+
+/// Generate an ISO 19115 metadata document in the ISO 19139 XML encoding
+///
+/// Extents are given in WGS84, as computed for the collection response.
+/// Keywords that are ISO topic categories are listed as such.
+pub fn iso19139_document(
+    collection: &CollectionWithCrs,
+    extent: Option<&Extent>,
+    base_url: &str,
+    config: &MetadataConfig,
+) -> String {
+    let id = &collection.canonical_name;
+    let organisation = config.organisation.as_deref().unwrap_or(&collection.owner);
+    let email = config.contact_email.as_deref();
+    let language = code_list("gmd:language", "LanguageCode", &config.language);
+    let mut xml = String::new();
+    xml.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push_str(
+        r#"<gmd:MD_Metadata xmlns:gmd="http://www.isotc211.org/2005/gmd" xmlns:gco="http://www.isotc211.org/2005/gco" xmlns:gml="http://www.opengis.net/gml/3.2" xmlns:xlink="http://www.w3.org/1999/xlink">"#,
+    );
+    xml.push_str(&character_string(
+        "gmd:fileIdentifier",
+        &collection.id.to_string(),
+    ));
+    xml.push_str(&language);
+    xml.push_str(&code_list(
+        "gmd:characterSet",
+        "MD_CharacterSetCode",
+        "utf8",
+    ));
+    xml.push_str(&code_list("gmd:hierarchyLevel", "MD_ScopeCode", "dataset"));
+    xml.push_str(&responsible_party("gmd:contact", organisation, email));
+    if let Some(updated) = collection.updated_at.or(collection.created_at) {
+        let _ = write!(
+            xml,
+            "<gmd:dateStamp><gco:DateTime>{}</gco:DateTime></gmd:dateStamp>",
+            updated.format("%Y-%m-%dT%H:%M:%SZ")
+        );
+    }
+    xml.push_str(&character_string(
+        "gmd:metadataStandardName",
+        "ISO 19115:2003/19139",
+    ));
+    xml.push_str(&character_string("gmd:metadataStandardVersion", "1.0"));
+    let _ = write!(
+        xml,
+        "<gmd:referenceSystemInfo><gmd:MD_ReferenceSystem><gmd:referenceSystemIdentifier><gmd:RS_Identifier>{}</gmd:RS_Identifier></gmd:referenceSystemIdentifier></gmd:MD_ReferenceSystem></gmd:referenceSystemInfo>",
+        character_string(
+            "gmd:code",
+            &format!(
+                "http://www.opengis.net/def/crs/EPSG/0/{}",
+                collection.storage_crs
+            )
+        )
+    );
+
+    // Identification
+    xml.push_str("<gmd:identificationInfo><gmd:MD_DataIdentification>");
+    xml.push_str("<gmd:citation><gmd:CI_Citation>");
+    xml.push_str(&character_string("gmd:title", &collection.title));
+    if let Some(created) = collection.created_at {
+        let _ = write!(
+            xml,
+            "<gmd:date><gmd:CI_Date><gmd:date><gco:DateTime>{}</gco:DateTime></gmd:date>{}</gmd:CI_Date></gmd:date>",
+            created.format("%Y-%m-%dT%H:%M:%SZ"),
+            code_list("gmd:dateType", "CI_DateTypeCode", "creation")
+        );
+    }
+    let _ = write!(
+        xml,
+        "<gmd:identifier><gmd:MD_Identifier>{}</gmd:MD_Identifier></gmd:identifier>",
+        character_string("gmd:code", id)
+    );
+    xml.push_str("</gmd:CI_Citation></gmd:citation>");
+    xml.push_str(&character_string(
+        "gmd:abstract",
+        collection
+            .description
+            .as_deref()
+            .unwrap_or(&collection.title),
+    ));
+    xml.push_str(&responsible_party(
+        "gmd:pointOfContact",
+        organisation,
+        email,
+    ));
+    if !collection.keywords.is_empty() {
+        xml.push_str("<gmd:descriptiveKeywords><gmd:MD_Keywords>");
+        for keyword in &collection.keywords {
+            xml.push_str(&character_string("gmd:keyword", keyword));
+        }
+        xml.push_str("</gmd:MD_Keywords></gmd:descriptiveKeywords>");
+    }
+    if let Some(license) = &collection.license {
+        let _ = write!(
+            xml,
+            "<gmd:resourceConstraints><gmd:MD_LegalConstraints>{}{}</gmd:MD_LegalConstraints></gmd:resourceConstraints>",
+            code_list("gmd:useConstraints", "MD_RestrictionCode", "license"),
+            character_string("gmd:otherConstraints", license)
+        );
+    }
+    xml.push_str(&language);
+    for category in topic_categories(&collection.keywords, config.topic_category.as_deref()) {
+        let _ = write!(
+            xml,
+            "<gmd:topicCategory><gmd:MD_TopicCategoryCode>{}</gmd:MD_TopicCategoryCode></gmd:topicCategory>",
+            xml_escape(category)
+        );
+    }
+
+    let bbox = extent
+        .and_then(|extent| extent.spatial.as_ref())
+        .and_then(|spatial| spatial.bbox.first());
+    let interval = extent
+        .and_then(|extent| extent.temporal.as_ref())
+        .and_then(|temporal| temporal.interval.first());
+    if bbox.is_some() || interval.is_some() {
+        xml.push_str("<gmd:extent><gmd:EX_Extent>");
+        if let Some(bbox) = bbox {
+            let [west, south, east, north] = match bbox {
+                Bbox::TwoD(coords) => *coords,
+                Bbox::ThreeD([minx, miny, _, maxx, maxy, _]) => [*minx, *miny, *maxx, *maxy],
+            };
+            let _ = write!(
+                xml,
+                "<gmd:geographicElement><gmd:EX_GeographicBoundingBox>\
+                 <gmd:westBoundLongitude><gco:Decimal>{west}</gco:Decimal></gmd:westBoundLongitude>\
+                 <gmd:eastBoundLongitude><gco:Decimal>{east}</gco:Decimal></gmd:eastBoundLongitude>\
+                 <gmd:southBoundLatitude><gco:Decimal>{south}</gco:Decimal></gmd:southBoundLatitude>\
+                 <gmd:northBoundLatitude><gco:Decimal>{north}</gco:Decimal></gmd:northBoundLatitude>\
+                 </gmd:EX_GeographicBoundingBox></gmd:geographicElement>"
+            );
+        }
+        if let Some([begin, end]) = interval {
+            let position = |tag: &str, value: &Option<String>| match value {
+                Some(value) => format!("<gml:{tag}>{}</gml:{tag}>", xml_escape(value)),
+                None => format!("<gml:{tag} indeterminatePosition=\"unknown\"/>"),
+            };
+            let _ = write!(
+                xml,
+                "<gmd:temporalElement><gmd:EX_TemporalExtent><gmd:extent><gml:TimePeriod gml:id=\"extent\">{}{}</gml:TimePeriod></gmd:extent></gmd:EX_TemporalExtent></gmd:temporalElement>",
+                position("beginPosition", begin),
+                position("endPosition", end)
+            );
+        }
+        xml.push_str("</gmd:EX_Extent></gmd:extent>");
+    }
+    xml.push_str("</gmd:MD_DataIdentification></gmd:identificationInfo>");
+
+    // Distribution through the collection's API endpoint
+    let _ = write!(
+        xml,
+        "<gmd:distributionInfo><gmd:MD_Distribution><gmd:transferOptions><gmd:MD_DigitalTransferOptions><gmd:onLine><gmd:CI_OnlineResource><gmd:linkage><gmd:URL>{}</gmd:URL></gmd:linkage>{}</gmd:CI_OnlineResource></gmd:onLine></gmd:MD_DigitalTransferOptions></gmd:transferOptions></gmd:MD_Distribution></gmd:distributionInfo>",
+        xml_escape(&format!("{}/collections/{}", base_url, id)),
+        character_string("gmd:protocol", "OGC API")
+    );
+
+    let _ = write!(
+        xml,
+        "<gmd:dataQualityInfo><gmd:DQ_DataQuality><gmd:scope><gmd:DQ_Scope>{}</gmd:DQ_Scope></gmd:scope><gmd:lineage><gmd:LI_Lineage>{}</gmd:LI_Lineage></gmd:lineage></gmd:DQ_DataQuality></gmd:dataQualityInfo>",
+        code_list("gmd:level", "MD_ScopeCode", "dataset"),
+        character_string("gmd:statement", &lineage(collection))
+    );
+
+    xml.push_str("</gmd:MD_Metadata>");
+    xml
+}
+
+/// Path parameters for the metadata document
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/metadata")]
+pub struct CollectionMetadataPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+pub async fn get_metadata(
+    Extension(config): Extension<Arc<Config>>,
+    State(service): State<Arc<CollectionService>>,
+    _path: CollectionMetadataPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    Query(params): Query<MetadataParams>,
+) -> AppResult<Response> {
+    if params.f != "iso19139" {
+        return Err(AppError::BadRequest(format!(
+            "Unsupported metadata format: {} (supported: iso19139)",
+            params.f
+        )));
+    }
+
+    let extent = service.compute_extent(&collection.as_collection()).await?;
+    let document = iso19139_document(
+        &collection,
+        extent.as_ref(),
+        &config.base_url,
+        &config.metadata,
+    );
+
+    Ok(([(header::CONTENT_TYPE, media_type::XML)], document).into_response())
+}
+
+fn get_metadata_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get collection metadata document")
+        .description(
+            "Returns an ISO 19115 metadata document (ISO 19139 XML encoding) generated from \
+             the collection's title, description, keywords, license and computed extents. \
+             Keywords naming ISO topic categories, e.g. `transportation`, become topic \
+             categories; the contact and language are configured per deployment.",
+        )
+        .tag("Collections")
+        .response_with::<200, String, _>(|res| res.description("ISO 19139 metadata document"))
+        .response_with::<400, (), _>(|res| res.description("Unsupported format"))
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+pub fn routes(service: Arc<CollectionService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/metadata",
+            get_with(get_metadata, get_metadata_docs),
+        )
+        .with_state(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::common::{SpatialExtent, TemporalExtent};
+
+    #[test]
+    fn test_iso19139_document() {
+        let collection = CollectionWithCrs {
+            id: uuid::Uuid::nil(),
+            canonical_name: "alice:roads".to_string(),
+            owner: "alice".to_string(),
+            schema_name: "alice".to_string(),
+            table_name: "roads".to_string(),
+            collection_type: "vector".to_string(),
+            title: "Roads & <paths>".to_string(),
+            description: None,
+            version: 1,
+            created_at: None,
+            updated_at: None,
+            data_updated_at: None,
+            default_limit: None,
+            max_limit: None,
+            min_zoom: None,
+            max_zoom: None,
+            tile_layer: None,
+            tile_properties: None,
+            processing_defaults: None,
+            keywords: vec!["transport".to_string(), "Transportation".to_string()],
+            license: Some("CC-BY-4.0".to_string()),
+            title_i18n: None,
+            description_i18n: None,
+            relations: None,
+            computed_properties: None,
+            unique_properties: Vec::new(),
+            geometry_columns: Vec::new(),
+            public_tiles: false,
+            expires_at: None,
+            geometry_type: Some("GEOMETRY".to_string()),
+            geometry_dimension: Some(2),
+            storage_crs: 3006,
+        };
+        let extent = Extent {
+            spatial: Some(SpatialExtent {
+                bbox: vec![Bbox::two_d(10.0, 50.0, 20.0, 60.0)],
+                crs: None,
+            }),
+            temporal: Some(TemporalExtent {
+                interval: vec![[Some("2024-01-01T00:00:00+00:00".to_string()), None]],
+            }),
+        };
+
+        let config = MetadataConfig {
+            contact_email: Some("gis@example.com".to_string()),
+            ..Default::default()
+        };
+        let xml = iso19139_document(&collection, Some(&extent), "http://localhost", &config);
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains("<gmd:title><gco:CharacterString>Roads &amp; &lt;paths&gt;"));
+        assert!(xml.contains("<gmd:westBoundLongitude><gco:Decimal>10</gco:Decimal>"));
+        assert!(xml.contains("<gmd:northBoundLatitude><gco:Decimal>60</gco:Decimal>"));
+        assert!(xml.contains("<gml:beginPosition>2024-01-01T00:00:00+00:00</gml:beginPosition>"));
+        assert!(xml.contains("<gml:endPosition indeterminatePosition=\"unknown\"/>"));
+        assert!(xml.contains("<gmd:keyword><gco:CharacterString>transport"));
+        assert!(xml.contains("<gmd:otherConstraints><gco:CharacterString>CC-BY-4.0"));
+        assert!(xml.contains("http://www.opengis.net/def/crs/EPSG/0/3006"));
+        assert!(xml.contains("<gmd:organisationName><gco:CharacterString>alice"));
+        assert!(xml.contains("<gmd:electronicMailAddress><gco:CharacterString>gis@example.com"));
+        assert!(xml.contains("codeListValue=\"eng\""));
+        assert!(
+            xml.contains("<gmd:MD_TopicCategoryCode>transportation</gmd:MD_TopicCategoryCode>")
+        );
+        assert!(xml.contains(
+            "<gmd:statement><gco:CharacterString>Features maintained in the collection alice:roads."
+        ));
+        assert!(xml.ends_with("</gmd:MD_Metadata>"));
+
+        let config = MetadataConfig {
+            organisation: Some("Lantmäteriet".to_string()),
+            topic_category: Some("planningCadastre".to_string()),
+            ..Default::default()
+        };
+        let collection = CollectionWithCrs {
+            keywords: Vec::new(),
+            ..collection
+        };
+        let xml = iso19139_document(&collection, None, "http://localhost", &config);
+        assert!(!xml.contains("<gmd:extent>"));
+        assert!(!xml.contains("electronicMailAddress"));
+        assert!(xml.contains("<gmd:organisationName><gco:CharacterString>Lantmäteriet"));
+        assert!(
+            xml.contains("<gmd:MD_TopicCategoryCode>planningCadastre</gmd:MD_TopicCategoryCode>")
+        );
+    }
+
+    #[test]
+    fn test_xml_escape() {
+        assert_eq!(xml_escape("a<b>&\"c'"), "a&lt;b&gt;&amp;&quot;c&apos;");
+    }
+}
@@ -1,6 +1,16 @@
+pub mod analytics;
+pub mod assets;
+pub mod computed;
+pub mod events;
 pub mod handlers;
+pub mod metadata;
+pub mod relations;
+pub mod replications;
+pub mod resolved;
 pub mod schemas;
 pub mod sharing;
+pub mod snapshots;
 
 pub use handlers::*;
+pub use resolved::ResolvedCollection;
 pub use schemas::*;
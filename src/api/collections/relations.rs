@@ -0,0 +1,235 @@
+//! Relations between collections
+//!
+//! A relation declares that a property of a collection's features holds the
+//! key of a feature in another collection, e.g. the `owner_id` of a parcel
+//! referring to a row of an `owners` collection. CQL2 filters can then refer
+//! to attributes of the related feature as `owner.name`.
+
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, State},
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::resolved::ResolvedCollection;
+use crate::api::body::JsonBody;
+use crate::api::common::etag;
+use crate::auth::AuthenticatedUser;
+use crate::error::{AppError, AppResult};
+use crate::services::CollectionService;
+
+/// A foreign-key relation to another collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionRelation {
+    /// Name used in filters, e.g. `owner` for `owner.name = 'Alice'`
+    pub name: String,
+    /// The related collection
+    pub collection: String,
+    /// Property of this collection holding the key
+    pub property: String,
+    /// Property of the related collection the key refers to; `id` for the
+    /// feature id
+    #[serde(default = "default_references")]
+    pub references: String,
+}
+
+fn default_references() -> String {
+    "id".to_string()
+}
+
+/// The relations of a collection
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CollectionRelations {
+    pub relations: Vec<CollectionRelation>,
+}
+
+/// Names that already have a meaning in filters
+const RESERVED_NAMES: &[&str] = &["properties", "geometry", "id"];
+
+/// Check that relation names are unique identifiers and keys are named
+pub fn validate_relations(relations: &[CollectionRelation]) -> AppResult<()> {
+    for (index, relation) in relations.iter().enumerate() {
+        let valid_name = relation
+            .name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && relation
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name || RESERVED_NAMES.contains(&relation.name.as_str()) {
+            return Err(AppError::BadRequest(format!(
+                "Invalid relation name: {}",
+                relation.name
+            )));
+        }
+        if relations[..index].iter().any(|r| r.name == relation.name) {
+            return Err(AppError::BadRequest(format!(
+                "Duplicate relation name: {}",
+                relation.name
+            )));
+        }
+        if relation.property.trim().is_empty() || relation.references.trim().is_empty() {
+            return Err(AppError::BadRequest(format!(
+                "Relation {} must name the key properties",
+                relation.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Relations stored in a collection's `relations` column
+pub fn parse_relations(value: Option<&serde_json::Value>) -> Vec<CollectionRelation> {
+    value
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Path parameters for collection relation endpoints
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/relations")]
+pub struct CollectionRelationsPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+pub async fn get_relations(
+    _path: CollectionRelationsPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> Result<Response, AppError> {
+    let relations = parse_relations(collection.relations.as_ref());
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ETAG, etag::create_etag_header(collection.version)?);
+
+    Ok((headers, Json(CollectionRelations { relations })).into_response())
+}
+
+fn get_relations_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("List relations")
+        .description(
+            "Returns the relations of a collection to other collections. A relation named \
+             `owner` lets CQL2 filters refer to attributes of the related feature, e.g. \
+             `owner.name = 'Alice'`.",
+        )
+        .tag("Collections")
+        .response_with::<200, Json<CollectionRelations>, _>(|res| {
+            res.description("Relations of the collection")
+        })
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+pub async fn put_relations(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    _path: CollectionRelationsPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    headers: HeaderMap,
+    JsonBody(request): JsonBody<CollectionRelations>,
+) -> Result<Response, AppError> {
+    // If-Match header is optional - when present, enables optimistic locking
+    let expected_version = etag::extract_expected_version(&headers)?;
+
+    if !collection.as_collection().has_feature_table() {
+        return Err(AppError::BadRequest(
+            "Relations are only supported for vector and table collections".to_string(),
+        ));
+    }
+    validate_relations(&request.relations)?;
+
+    let collection = service
+        .update_relations(
+            &user.username,
+            &collection.canonical_name,
+            expected_version,
+            &request.relations,
+        )
+        .await?;
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::ETAG, etag::create_etag_header(collection.version)?);
+
+    Ok((
+        response_headers,
+        Json(CollectionRelations {
+            relations: parse_relations(collection.relations.as_ref()),
+        }),
+    )
+        .into_response())
+}
+
+fn put_relations_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Replace relations")
+        .description(
+            "Replaces the relations of a vector or table collection. Each relation names a \
+             property of this collection holding the key of a feature in another vector or \
+             table collection of the same owner, and the property of that collection it \
+             refers to (`id` for the feature id). If-Match header is optional; when provided, \
+             enables optimistic locking.",
+        )
+        .tag("Collections")
+        .response_with::<200, Json<CollectionRelations>, _>(|res| {
+            res.description("Updated relations")
+        })
+        .response_with::<400, (), _>(|res| res.description("Invalid relations"))
+        .response_with::<403, (), _>(|res| {
+            res.description("Only the owner may update the relations")
+        })
+        .response_with::<412, (), _>(|res| res.description("Precondition failed (ETag mismatch)"))
+}
+
+pub fn routes(service: Arc<CollectionService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/relations",
+            get_with(get_relations, get_relations_docs).put_with(put_relations, put_relations_docs),
+        )
+        .with_state(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relation(name: &str) -> CollectionRelation {
+        CollectionRelation {
+            name: name.to_string(),
+            collection: "alice:owners".to_string(),
+            property: "owner_id".to_string(),
+            references: default_references(),
+        }
+    }
+
+    #[test]
+    fn test_validate_relations() {
+        assert!(validate_relations(&[relation("owner"), relation("_previous2")]).is_ok());
+        assert!(validate_relations(&[relation("owner"), relation("owner")]).is_err());
+        assert!(validate_relations(&[relation("properties")]).is_err());
+        assert!(validate_relations(&[relation("2nd")]).is_err());
+        assert!(validate_relations(&[relation("owner.name")]).is_err());
+
+        let mut missing_key = relation("owner");
+        missing_key.property = " ".to_string();
+        assert!(validate_relations(&[missing_key]).is_err());
+    }
+
+    #[test]
+    fn test_parse_relations() {
+        let value = serde_json::json!([
+            {"name": "owner", "collection": "alice:owners", "property": "owner_id"}
+        ]);
+        assert_eq!(parse_relations(Some(&value)), vec![relation("owner")]);
+        assert!(parse_relations(None).is_empty());
+    }
+}
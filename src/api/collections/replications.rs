@@ -0,0 +1,272 @@
+//! Publication of collections to other SpatialVault instances
+//!
+//! An owner can publish a collection to a collection on a remote instance,
+//! e.g. to keep a public read-only instance in sync with an internal editing
+//! instance. Changed and deleted features are pushed to the remote's API in
+//! the background; see [`crate::services::ReplicationService`].
+
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::resolved::ResolvedCollection;
+use crate::api::body::JsonBody;
+use crate::auth::AuthenticatedUser;
+use crate::config::Config;
+use crate::db::CollectionReplication;
+use crate::error::{AppError, AppResult};
+use crate::services::CollectionService;
+
+/// Request to publish a collection to a remote instance
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateReplicationRequest {
+    /// Name of a remote instance configured on this server
+    pub remote: String,
+    /// Id of the collection on the remote to push the features to
+    pub remote_collection: String,
+}
+
+/// A publication of a collection to a remote instance
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicationResponse {
+    pub id: Uuid,
+    pub remote: String,
+    pub remote_collection: String,
+    #[schemars(with = "String")]
+    pub created: DateTime<Utc>,
+    pub created_by: String,
+    /// Changes up to this time have been pushed
+    #[schemars(with = "Option<String>")]
+    pub synced_until: Option<DateTime<Utc>>,
+    /// When the last push finished
+    #[schemars(with = "Option<String>")]
+    pub synced_at: Option<DateTime<Utc>>,
+    /// Why the last push failed, if it did
+    pub last_error: Option<String>,
+}
+
+impl From<CollectionReplication> for ReplicationResponse {
+    fn from(replication: CollectionReplication) -> Self {
+        Self {
+            id: replication.id,
+            remote: replication.remote,
+            remote_collection: replication.remote_collection,
+            created: replication.created_at,
+            created_by: replication.created_by,
+            synced_until: replication.synced_until,
+            synced_at: replication.synced_at,
+            last_error: replication.last_error,
+        }
+    }
+}
+
+/// The publications of a collection
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ReplicationsResponse {
+    pub replications: Vec<ReplicationResponse>,
+}
+
+/// Path parameters for collection replication endpoints
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/replications")]
+pub struct ReplicationsPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+/// Path parameters for a single replication
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/replications/{replication_id}")]
+pub struct ReplicationPath {
+    /// The collection identifier
+    pub collection_id: String,
+    /// The replication id
+    pub replication_id: Uuid,
+}
+
+/// Replications name remotes and their errors, so only the owner sees them
+fn require_owner(owner: &str, user: &AuthenticatedUser) -> AppResult<()> {
+    if owner != user.username {
+        return Err(AppError::Forbidden(
+            "Only the owner may manage replications".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn list_replications(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    _path: ReplicationsPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> AppResult<Json<ReplicationsResponse>> {
+    require_owner(&collection.owner, &user)?;
+    let replications = service.list_replications(collection.id).await?;
+
+    Ok(Json(ReplicationsResponse {
+        replications: replications.into_iter().map(Into::into).collect(),
+    }))
+}
+
+fn list_replications_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("List replications")
+        .description("Returns the remote instances a collection is published to and how far their pushes got")
+        .tag("Collections")
+        .response_with::<200, Json<ReplicationsResponse>, _>(|res| {
+            res.description("Replications of the collection")
+        })
+        .response_with::<403, (), _>(|res| res.description("Only the owner may list replications"))
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+pub async fn create_replication(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    _path: ReplicationsPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    JsonBody(request): JsonBody<CreateReplicationRequest>,
+) -> AppResult<Response> {
+    if !config.replication.remotes.contains_key(&request.remote) {
+        return Err(AppError::BadRequest(format!(
+            "Unknown remote: {}",
+            request.remote
+        )));
+    }
+    if request.remote_collection.trim().is_empty() {
+        return Err(AppError::BadRequest(
+            "remoteCollection must not be empty".to_string(),
+        ));
+    }
+
+    let replication = service
+        .create_replication(
+            &user.username,
+            &collection.canonical_name,
+            &request.remote,
+            &request.remote_collection,
+        )
+        .await?;
+
+    let location = format!(
+        "{}/collections/{}/replications/{}",
+        config.base_url, collection.canonical_name, replication.id
+    );
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::LOCATION,
+        HeaderValue::from_str(&location)
+            .map_err(|e| AppError::Internal(format!("Invalid Location header: {}", e)))?,
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        headers,
+        Json(ReplicationResponse::from(replication)),
+    )
+        .into_response())
+}
+
+fn create_replication_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Publish collection")
+        .description(
+            "Publishes a vector or table collection to an existing collection on a remote \
+             SpatialVault instance configured on this server. All features are pushed, then \
+             changed and deleted features are pushed periodically. Remote features carry the \
+             id of their source feature in the `source_id` property and geometries are pushed \
+             as stored, so the remote collection should use the same CRS.",
+        )
+        .tag("Collections")
+        .response_with::<201, Json<ReplicationResponse>, _>(|res| {
+            res.description("Collection published")
+        })
+        .response_with::<400, (), _>(|res| {
+            res.description("Unknown remote, or the collection has no features to publish")
+        })
+        .response_with::<403, (), _>(|res| res.description("Only the owner may publish"))
+        .response_with::<409, (), _>(|res| {
+            res.description("The collection is already published there")
+        })
+}
+
+pub async fn get_replication(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    path: ReplicationPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> AppResult<Json<ReplicationResponse>> {
+    require_owner(&collection.owner, &user)?;
+    let replication = service
+        .get_replication(collection.id, path.replication_id)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound(format!("Replication not found: {}", path.replication_id))
+        })?;
+
+    Ok(Json(replication.into()))
+}
+
+fn get_replication_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get replication")
+        .tag("Collections")
+        .response_with::<200, Json<ReplicationResponse>, _>(|res| {
+            res.description("The replication")
+        })
+        .response_with::<403, (), _>(|res| res.description("Only the owner may see replications"))
+        .response_with::<404, (), _>(|res| res.description("Replication not found"))
+}
+
+pub async fn delete_replication(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    path: ReplicationPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> AppResult<StatusCode> {
+    service
+        .delete_replication(
+            &user.username,
+            &collection.canonical_name,
+            path.replication_id,
+        )
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn delete_replication_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Unpublish collection")
+        .description("Stops pushing changes to the remote; features already pushed stay there")
+        .tag("Collections")
+        .response_with::<204, (), _>(|res| res.description("Replication deleted"))
+        .response_with::<403, (), _>(|res| res.description("Only the owner may unpublish"))
+        .response_with::<404, (), _>(|res| res.description("Replication not found"))
+}
+
+pub fn routes(service: Arc<CollectionService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/replications",
+            get_with(list_replications, list_replications_docs)
+                .post_with(create_replication, create_replication_docs),
+        )
+        .api_route(
+            "/collections/{collection_id}/replications/{replication_id}",
+            get_with(get_replication, get_replication_docs)
+                .delete_with(delete_replication, delete_replication_docs),
+        )
+        .with_state(service)
+}
@@ -0,0 +1,149 @@
+use aide::OperationInput;
+use axum::{
+    extract::{Extension, FromRequestParts, RawPathParams},
+    http::{StatusCode, Uri, header, request::Parts},
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::auth::AuthenticatedUser;
+use crate::config::Config;
+use crate::db::CollectionWithCrs;
+use crate::error::AppError;
+use crate::services::CollectionService;
+
+/// The collection named by the `{collection_id}` path parameter
+///
+/// Extracting it answers requests for a renamed collection with a 307
+/// redirect to its new name and requests for an unknown collection, or one
+/// the user neither owns nor may read, with 404, so handlers don't have to.
+/// The collection is resolved once per request and kept in the request
+/// extensions.
+///
+/// Requires `Extension<Arc<CollectionService>>` and `Extension<Arc<Config>>`,
+/// and the `AuthenticatedUser` of the auth middleware.
+#[derive(Debug, Clone)]
+pub struct ResolvedCollection(pub CollectionWithCrs);
+
+impl<S: Send + Sync> FromRequestParts<S> for ResolvedCollection {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(resolved) = parts.extensions.get::<ResolvedCollection>() {
+            return Ok(resolved.clone());
+        }
+
+        let params = RawPathParams::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let collection_id = params
+            .iter()
+            .find(|(key, _)| *key == "collection_id")
+            .map(|(_, value)| value.to_string())
+            .ok_or_else(|| {
+                AppError::Internal("Route has no collection_id parameter".to_string())
+                    .into_response()
+            })?;
+
+        let Extension(service) =
+            Extension::<Arc<CollectionService>>::from_request_parts(parts, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+        let Extension(config) = Extension::<Arc<Config>>::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        // Collections the user can't read are reported as not found, so
+        // their existence doesn't leak
+        let username = parts
+            .extensions
+            .get::<AuthenticatedUser>()
+            .map(|user| user.username.clone())
+            .unwrap_or_default();
+        let not_found = || {
+            AppError::NotFound(format!("Collection not found: {}", collection_id)).into_response()
+        };
+
+        // An active collection takes precedence over an alias with the same name
+        if let Some(collection) = service
+            .get_collection(&username, &collection_id)
+            .await
+            .map_err(IntoResponse::into_response)?
+        {
+            if !service
+                .can_read(&username, &collection.as_collection())
+                .await
+                .map_err(IntoResponse::into_response)?
+            {
+                return Err(not_found());
+            }
+            let resolved = ResolvedCollection(collection);
+            parts.extensions.insert(resolved.clone());
+            return Ok(resolved);
+        }
+
+        let new_name = service
+            .get_alias(&collection_id)
+            .await
+            .map_err(IntoResponse::into_response)?
+            .ok_or_else(not_found)?;
+        let renamed = service
+            .get_collection(&username, &new_name)
+            .await
+            .map_err(IntoResponse::into_response)?
+            .ok_or_else(not_found)?;
+        if !service
+            .can_read(&username, &renamed.as_collection())
+            .await
+            .map_err(IntoResponse::into_response)?
+        {
+            return Err(not_found());
+        }
+
+        let location = redirect_location(&config.base_url, &parts.uri, &new_name);
+        Err((
+            StatusCode::TEMPORARY_REDIRECT,
+            [(header::LOCATION, location)],
+        )
+            .into_response())
+    }
+}
+
+impl OperationInput for ResolvedCollection {}
+
+/// The request URL with the collection segment replaced by `new_name`
+fn redirect_location(base_url: &str, uri: &Uri, new_name: &str) -> String {
+    let mut segments: Vec<&str> = uri.path().split('/').collect();
+    if let Some(index) = segments.iter().position(|s| *s == "collections")
+        && index + 1 < segments.len()
+    {
+        segments[index + 1] = new_name;
+    }
+
+    let mut location = format!("{}{}", base_url, segments.join("/"));
+    if let Some(query) = uri.query() {
+        location.push('?');
+        location.push_str(query);
+    }
+    location
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect_location() {
+        let uri: Uri = "/collections/old/items/1?limit=5".parse().unwrap();
+        assert_eq!(
+            redirect_location("https://example.com/api", &uri, "new"),
+            "https://example.com/api/collections/new/items/1?limit=5"
+        );
+
+        let uri: Uri = "/collections/old".parse().unwrap();
+        assert_eq!(
+            redirect_location("http://localhost", &uri, "new"),
+            "http://localhost/collections/new"
+        );
+    }
+}
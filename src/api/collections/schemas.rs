@@ -1,7 +1,23 @@
+use chrono::{DateTime, Utc};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
+use crate::api::body::nullable;
 use crate::api::common::{Extent, Link};
+use crate::api::features::query::FeatureQueryParams;
+use crate::api::language::stored_translations;
+use crate::api::processes::import_pointcloud::PipelineOptions;
+use crate::api::tiles::vector::MAX_ZOOM;
+use crate::api::usage::StorageUsageResponse;
+use crate::db::CollectionAsset;
+use crate::error::{AppError, AppResult};
+
+/// Largest page size a collection may allow for its items
+pub const MAX_COLLECTION_LIMIT: u32 = 100_000;
+
+/// Longest time to live of a scratch collection (one year)
+pub const MAX_COLLECTION_TTL_SECS: u64 = 365 * 24 * 60 * 60;
 
 /// OGC API Collection response
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -11,19 +27,618 @@ pub struct CollectionResponse {
     pub title: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(flatten)]
+    pub metadata: CollectionMetadata,
     pub links: Vec<Link>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub extent: Option<Extent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub item_type: Option<String>,
+    /// Declared geometry of the features of a vector collection
+    #[serde(flatten)]
+    pub geometry: CollectionGeometry,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub crs: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub storage_crs: Option<String>,
+    #[serde(flatten)]
+    pub limits: CollectionLimits,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub processing_defaults: Option<ProcessingDefaults>,
+    /// Assets of the collection itself (STAC collection assets)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assets: Option<BTreeMap<String, AssetObject>>,
+    /// When a scratch collection will be deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<String>")]
+    pub expires: Option<DateTime<Utc>>,
+    /// When the features or items of the collection last changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<String>")]
+    pub data_updated: Option<DateTime<Utc>>,
+    /// Storage used by the collection, counted towards its owner's quota
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<StorageUsageResponse>,
+}
+
+/// STAC asset object
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AssetObject {
+    /// Location of the asset (S3 URI or HTTP(S) URL)
+    pub href: String,
+    /// Media type of the asset
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Semantic roles, e.g. `data`, `metadata` or `overview`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roles: Option<Vec<String>>,
+    /// Size of the file in bytes
+    #[serde(rename = "file:size", default, skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<i64>,
+}
+
+impl AssetObject {
+    pub fn validate(&self) -> AppResult<()> {
+        if !["s3://", "http://", "https://"]
+            .iter()
+            .any(|scheme| self.href.starts_with(scheme))
+        {
+            return Err(AppError::BadRequest(
+                "Asset href must be an S3 URI or HTTP(S) URL".to_string(),
+            ));
+        }
+        if self.file_size.is_some_and(|size| size < 0) {
+            return Err(AppError::BadRequest(
+                "file:size cannot be negative".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl From<CollectionAsset> for AssetObject {
+    fn from(asset: CollectionAsset) -> Self {
+        Self {
+            href: asset.href,
+            media_type: asset.media_type,
+            title: asset.title,
+            description: asset.description,
+            roles: asset.roles,
+            file_size: asset.file_size,
+        }
+    }
+}
+
+/// Asset of an item registered for a file already in object storage
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RegisteredAsset {
+    #[serde(flatten)]
+    pub asset: AssetObject,
+    /// Further STAC asset fields, e.g. `proj:epsg` or `raster:bands`
+    #[serde(flatten)]
+    pub extra_fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl RegisteredAsset {
+    pub fn validate(&self) -> AppResult<()> {
+        if !self.asset.href.starts_with("s3://") {
+            return Err(AppError::BadRequest(
+                "Registered assets must reference an S3 URI".to_string(),
+            ));
+        }
+        self.asset.validate()
+    }
+
+    /// Check that the asset references an object of the deployment's bucket
+    /// in one of `namespaces`; see [`validate_asset_location`]
+    pub fn validate_location(&self, bucket_uri: &str, namespaces: &[&str]) -> AppResult<()> {
+        validate_asset_location(&self.asset.href, bucket_uri, namespaces)
+    }
+}
+
+/// Check that `href` references an object of the deployment's bucket in one
+/// of `namespaces`, e.g. `s3://bucket/alice/scene/B04.tif`
+///
+/// `bucket_uri` is the URI of the bucket root, `s3://bucket/`. Objects of
+/// other buckets or namespaces would be served with the server's own
+/// credentials.
+pub fn validate_asset_location(href: &str, bucket_uri: &str, namespaces: &[&str]) -> AppResult<()> {
+    let key = href.strip_prefix(bucket_uri).ok_or_else(|| {
+        AppError::BadRequest(format!(
+            "Registered assets must be in {}",
+            bucket_uri.trim_end_matches('/')
+        ))
+    })?;
+    if key
+        .split('/')
+        .any(|segment| matches!(segment, "" | "." | ".."))
+    {
+        return Err(AppError::BadRequest(format!("Invalid object key: {}", key)));
+    }
+    let in_namespace = namespaces.iter().any(|namespace| {
+        key.strip_prefix(namespace)
+            .is_some_and(|rest| rest.starts_with('/'))
+    });
+    if !in_namespace {
+        return Err(AppError::Forbidden(format!(
+            "Registered assets must be under {}",
+            namespaces
+                .iter()
+                .map(|namespace| format!("{}{}/", bucket_uri, namespace))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        )));
+    }
+    Ok(())
+}
+
+/// Check that an asset key can be used as a path segment
+pub fn validate_asset_key(key: &str) -> AppResult<()> {
+    if key.is_empty()
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(AppError::BadRequest(format!(
+            "Invalid asset key: {} (use letters, digits, '-', '_' and '.')",
+            key
+        )));
+    }
+    Ok(())
+}
+
+/// Descriptive metadata of a collection used for catalog discovery
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionMetadata {
+    /// Keywords/themes, matched by the `keyword` filter of the collection listing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<Vec<String>>,
+    /// License of the data: an SPDX identifier, `proprietary` or `various`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// Translations of the title by language tag, picked by `Accept-Language`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub titles: Option<BTreeMap<String, String>>,
+    /// Translations of the description by language tag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub descriptions: Option<BTreeMap<String, String>>,
+}
+
+impl CollectionMetadata {
+    /// Metadata as stored on a collection
+    pub fn from_stored(
+        keywords: &[String],
+        license: Option<&str>,
+        titles: Option<&serde_json::Value>,
+        descriptions: Option<&serde_json::Value>,
+    ) -> Self {
+        let translations = |value| Some(stored_translations(value)).filter(|map| !map.is_empty());
+        Self {
+            keywords: (!keywords.is_empty()).then(|| keywords.to_vec()),
+            license: license.map(str::to_string),
+            titles: translations(titles),
+            descriptions: translations(descriptions),
+        }
+    }
+
+    /// Validate the metadata, trimming keywords and dropping duplicates
+    pub fn normalized(&self) -> AppResult<Self> {
+        let license = match self.license.as_deref().map(str::trim) {
+            Some("") => {
+                return Err(AppError::BadRequest("license cannot be empty".to_string()));
+            }
+            license => license.map(str::to_string),
+        };
+        Ok(Self {
+            keywords: self
+                .keywords
+                .as_deref()
+                .map(normalize_keywords)
+                .transpose()?,
+            license,
+            titles: self
+                .titles
+                .as_ref()
+                .map(normalize_translations)
+                .transpose()?,
+            descriptions: self
+                .descriptions
+                .as_ref()
+                .map(normalize_translations)
+                .transpose()?,
+        })
+    }
+}
+
+/// Check that a string looks like a BCP 47 language tag, e.g. `sv` or `en-GB`
+fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    (2..=8).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+/// Validate translations, lowercasing the language tags and trimming the texts
+pub fn normalize_translations(
+    translations: &BTreeMap<String, String>,
+) -> AppResult<BTreeMap<String, String>> {
+    translations
+        .iter()
+        .map(|(language, text)| {
+            if !is_language_tag(language) {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid language tag: {}",
+                    language
+                )));
+            }
+            let text = text.trim();
+            if text.is_empty() {
+                return Err(AppError::BadRequest(format!(
+                    "Translation for {} cannot be empty",
+                    language
+                )));
+            }
+            Ok((language.to_ascii_lowercase(), text.to_string()))
+        })
+        .collect()
+}
+
+/// Longest accepted keyword
+pub const MAX_KEYWORD_LENGTH: usize = 100;
+
+/// Most keywords a collection may have
+pub const MAX_KEYWORDS: usize = 50;
+
+/// Trim keywords and drop duplicates, keeping their order
+pub fn normalize_keywords(keywords: &[String]) -> AppResult<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::with_capacity(keywords.len());
+    for keyword in keywords {
+        let keyword = keyword.trim();
+        if keyword.is_empty() || keyword.chars().count() > MAX_KEYWORD_LENGTH {
+            return Err(AppError::BadRequest(format!(
+                "Keywords must be between 1 and {} characters",
+                MAX_KEYWORD_LENGTH
+            )));
+        }
+        if !normalized.iter().any(|existing| existing == keyword) {
+            normalized.push(keyword.to_string());
+        }
+    }
+    if normalized.len() > MAX_KEYWORDS {
+        return Err(AppError::BadRequest(format!(
+            "A collection can have at most {} keywords",
+            MAX_KEYWORDS
+        )));
+    }
+    Ok(normalized)
+}
+
+/// Page size and tile overrides for a collection
+///
+/// Unset values fall back to the server defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionLimits {
+    /// Page size when a request has no `limit`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_limit: Option<u32>,
+    /// Largest accepted `limit`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_limit: Option<u32>,
+    /// Lowest zoom level tiles are served at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_zoom: Option<u32>,
+    /// Highest zoom level tiles are served at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_zoom: Option<u32>,
+    /// Layer name in vector tiles (defaults to the last segment of the id)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tile_layer: Option<String>,
+}
+
+impl CollectionLimits {
+    pub fn validate(&self) -> AppResult<()> {
+        for limit in [self.default_limit, self.max_limit].into_iter().flatten() {
+            if limit == 0 || limit > MAX_COLLECTION_LIMIT {
+                return Err(AppError::BadRequest(format!(
+                    "Collection limits must be between 1 and {}",
+                    MAX_COLLECTION_LIMIT
+                )));
+            }
+        }
+
+        if let (Some(default), Some(max)) = (self.default_limit, self.max_limit) {
+            if default > max {
+                return Err(AppError::BadRequest(
+                    "defaultLimit cannot exceed maxLimit".to_string(),
+                ));
+            }
+        }
+
+        if let Some(zoom) = [self.min_zoom, self.max_zoom]
+            .into_iter()
+            .flatten()
+            .find(|zoom| *zoom > MAX_ZOOM)
+        {
+            return Err(AppError::BadRequest(format!(
+                "Zoom level {} is outside 0..={}",
+                zoom, MAX_ZOOM
+            )));
+        }
+
+        if let (Some(min), Some(max)) = (self.min_zoom, self.max_zoom)
+            && min > max
+        {
+            return Err(AppError::BadRequest(
+                "minZoom cannot exceed maxZoom".to_string(),
+            ));
+        }
+
+        if self
+            .tile_layer
+            .as_deref()
+            .is_some_and(|layer| layer.trim().is_empty())
+        {
+            return Err(AppError::BadRequest(
+                "tileLayer cannot be empty".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Changes to a collection's limits in a merge patch
+///
+/// A `null` page size or zoom level reverts to the server default.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionLimitsPatch {
+    /// Page size when a request has no `limit`
+    #[serde(default, deserialize_with = "nullable")]
+    pub default_limit: Option<Option<u32>>,
+    /// Largest accepted `limit`
+    #[serde(default, deserialize_with = "nullable")]
+    pub max_limit: Option<Option<u32>>,
+    /// Lowest zoom level tiles are served at
+    #[serde(default, deserialize_with = "nullable")]
+    pub min_zoom: Option<Option<u32>>,
+    /// Highest zoom level tiles are served at
+    #[serde(default, deserialize_with = "nullable")]
+    pub max_zoom: Option<Option<u32>>,
+    /// Layer name in vector tiles (defaults to the last segment of the id)
+    #[serde(default)]
+    pub tile_layer: Option<String>,
+}
+
+impl CollectionLimitsPatch {
+    /// The limits after applying the patch to `current`
+    pub fn apply(&self, current: &CollectionLimits) -> CollectionLimits {
+        CollectionLimits {
+            default_limit: self.default_limit.unwrap_or(current.default_limit),
+            max_limit: self.max_limit.unwrap_or(current.max_limit),
+            min_zoom: self.min_zoom.unwrap_or(current.min_zoom),
+            max_zoom: self.max_zoom.unwrap_or(current.max_zoom),
+            tile_layer: self
+                .tile_layer
+                .clone()
+                .or_else(|| current.tile_layer.clone()),
+        }
+    }
+}
+
+/// Geometry types a vector collection can be declared with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum GeometryType {
+    Point,
+    MultiPoint,
+    LineString,
+    MultiLineString,
+    Polygon,
+    MultiPolygon,
+    /// Any geometry type
+    #[default]
+    Geometry,
+}
+
+impl GeometryType {
+    const ALL: [Self; 7] = [
+        Self::Point,
+        Self::MultiPoint,
+        Self::LineString,
+        Self::MultiLineString,
+        Self::Polygon,
+        Self::MultiPolygon,
+        Self::Geometry,
+    ];
+
+    /// GeoJSON name of the type, also its name in PostGIS type modifiers
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Point => "Point",
+            Self::MultiPoint => "MultiPoint",
+            Self::LineString => "LineString",
+            Self::MultiLineString => "MultiLineString",
+            Self::Polygon => "Polygon",
+            Self::MultiPolygon => "MultiPolygon",
+            Self::Geometry => "Geometry",
+        }
+    }
+
+    /// Type of a PostGIS type name, e.g. `MULTIPOLYGON`
+    pub fn from_postgis(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|t| t.as_str().eq_ignore_ascii_case(name))
+    }
+
+    /// `format` of the geometry in a JSON Schema (OGC API Features Part 5)
+    pub fn schema_format(self) -> String {
+        match self {
+            Self::Geometry => "geometry-any".to_string(),
+            other => format!("geometry-{}", other.as_str().to_lowercase()),
+        }
+    }
+}
+
+/// Declared geometry of a vector collection, enforced on writes
+///
+/// Both are fixed at creation; the default allows any 2D geometry.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionGeometry {
+    /// Geometry type of the features; `Geometry` allows any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geometry_type: Option<GeometryType>,
+    /// Coordinate dimension: 2, or 3 for geometries with heights
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geometry_dimension: Option<u8>,
+}
+
+impl CollectionGeometry {
+    pub fn validate(&self, collection_type: &str) -> AppResult<()> {
+        if collection_type != "vector" && *self != Self::default() {
+            return Err(AppError::BadRequest(
+                "geometryType and geometryDimension are only supported for vector collections"
+                    .to_string(),
+            ));
+        }
+        if self
+            .geometry_dimension
+            .is_some_and(|dimension| !matches!(dimension, 2 | 3))
+        {
+            return Err(AppError::BadRequest(
+                "geometryDimension must be 2 or 3".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The declared geometry of a stored collection, from the `type` and
+    /// `coord_dimension` of its geometry column in `geometry_columns`
+    pub fn from_postgis(geometry_type: Option<&str>, coord_dimension: Option<i32>) -> Self {
+        let Some(geometry_type) = geometry_type else {
+            return Self::default();
+        };
+        Self {
+            geometry_type: Some(GeometryType::from_postgis(geometry_type).unwrap_or_default()),
+            geometry_dimension: Some(if coord_dimension == Some(3) { 3 } else { 2 }),
+        }
+    }
+
+    /// The geometry a collection of `collection_type` is created with
+    pub fn as_created(&self, collection_type: &str) -> Self {
+        if collection_type != "vector" {
+            return Self::default();
+        }
+        let (geometry_type, dimension) = self.resolved();
+        Self {
+            geometry_type: Some(geometry_type),
+            geometry_dimension: Some(dimension),
+        }
+    }
+
+    /// The declared type with the defaults applied
+    pub fn resolved(&self) -> (GeometryType, u8) {
+        (
+            self.geometry_type.unwrap_or_default(),
+            self.geometry_dimension.unwrap_or(2),
+        )
+    }
+
+    /// PostGIS type modifier of the geometry column, e.g. `PointZ`
+    pub fn type_modifier(&self) -> String {
+        match self.resolved() {
+            (geometry_type, 3) => format!("{}Z", geometry_type.as_str()),
+            (geometry_type, _) => geometry_type.as_str().to_string(),
+        }
+    }
+}
+
+/// Import options used when an execute request leaves them out
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessingDefaults {
+    /// Keep the original file of converted imports as a `source` asset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_source: Option<bool>,
+    /// Item property holding the item datetime, used when no datetime is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datetime_property: Option<String>,
+    /// Properties of every imported item; the request's properties take precedence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub properties: Option<serde_json::Map<String, serde_json::Value>>,
+    /// PDAL pipeline options for point cloud imports, e.g. a target CRS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pipeline: Option<PipelineOptions>,
+}
+
+impl ProcessingDefaults {
+    /// Defaults stored on a collection, if any
+    pub fn from_stored(value: Option<&serde_json::Value>) -> Option<Self> {
+        value.and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn validate(&self) -> AppResult<()> {
+        if self
+            .datetime_property
+            .as_deref()
+            .is_some_and(|property| property.trim().is_empty())
+        {
+            return Err(AppError::BadRequest(
+                "processingDefaults.datetimeProperty cannot be empty".to_string(),
+            ));
+        }
+        if let Some(pipeline) = &self.pipeline {
+            pipeline.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Item properties with the default properties filled in
+    pub fn merge_properties(
+        &self,
+        properties: Option<serde_json::Value>,
+    ) -> Option<serde_json::Value> {
+        let Some(defaults) = &self.properties else {
+            return properties;
+        };
+        match properties {
+            Some(serde_json::Value::Object(mut properties)) => {
+                for (key, value) in defaults {
+                    properties
+                        .entry(key.clone())
+                        .or_insert_with(|| value.clone());
+                }
+                Some(serde_json::Value::Object(properties))
+            }
+            None => Some(serde_json::Value::Object(defaults.clone())),
+            other => other,
+        }
+    }
+
+    /// Item datetime read from the datetime property, if set
+    pub fn datetime_from(&self, properties: Option<&serde_json::Value>) -> Option<String> {
+        let property = self.datetime_property.as_deref()?;
+        properties?.get(property)?.as_str().map(str::to_string)
+    }
 }
 
 /// List of collections
 #[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct CollectionsResponse {
     pub collections: Vec<CollectionResponse>,
     pub links: Vec<Link>,
@@ -31,6 +646,17 @@ pub struct CollectionsResponse {
     pub number_matched: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub number_returned: Option<u64>,
+    /// Counts per keyword and type over all matching collections
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facets: Option<CollectionFacets>,
+}
+
+/// Facet counts of a collection search
+#[derive(Debug, Default, Serialize, JsonSchema)]
+pub struct CollectionFacets {
+    pub keywords: BTreeMap<String, u64>,
+    #[serde(rename = "type")]
+    pub collection_type: BTreeMap<String, u64>,
 }
 
 /// Request to create a new collection
@@ -43,7 +669,8 @@ pub struct CreateCollectionRequest {
     pub title: String,
     #[serde(default)]
     pub description: Option<String>,
-    /// Collection type: "vector", "raster", or "pointcloud"
+    /// Collection type: "vector", "raster", "pointcloud", or "table" for
+    /// attribute-only features without geometry
     pub collection_type: String,
     /// Optional owner override (for group ownership)
     #[serde(default)]
@@ -51,12 +678,51 @@ pub struct CreateCollectionRequest {
     /// CRS for the collection (EPSG code). Default: 4326
     #[serde(default = "default_crs")]
     pub crs: i32,
+    /// Keywords and license for catalog discovery
+    #[serde(flatten)]
+    pub metadata: CollectionMetadata,
+    /// Page size overrides for the collection's items
+    #[serde(flatten)]
+    pub limits: CollectionLimits,
+    /// Properties whose values must be unique among the features, enforced
+    /// by the database
+    #[serde(default)]
+    pub unique_properties: Vec<String>,
+    /// Secondary geometry columns of a vector collection, each generated
+    /// from the GeoJSON geometry in the property of the same name
+    #[serde(default)]
+    pub geometry_columns: Vec<String>,
+    /// Geometry type and coordinate dimension of a vector collection
+    #[serde(flatten)]
+    pub geometry: CollectionGeometry,
+    /// Seconds until the collection is deleted automatically, for scratch
+    /// collections such as analysis intermediates
+    #[serde(default)]
+    pub expires_in: Option<u64>,
 }
 
 fn default_crs() -> i32 {
     4326
 }
 
+impl CreateCollectionRequest {
+    /// When the collection expires, if it is a scratch collection
+    pub fn expires_at(&self) -> AppResult<Option<DateTime<Utc>>> {
+        let Some(expires_in) = self.expires_in else {
+            return Ok(None);
+        };
+        if expires_in == 0 || expires_in > MAX_COLLECTION_TTL_SECS {
+            return Err(AppError::BadRequest(format!(
+                "expiresIn must be between 1 and {} seconds",
+                MAX_COLLECTION_TTL_SECS
+            )));
+        }
+        Ok(Some(
+            Utc::now() + chrono::Duration::seconds(expires_in as i64),
+        ))
+    }
+}
+
 /// Request to update a collection
 #[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -68,6 +734,15 @@ pub struct UpdateCollectionRequest {
     /// New canonical name for rename/move (creates alias from old name)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
+    /// Keywords and license; given keywords replace the current ones
+    #[serde(flatten)]
+    pub metadata: CollectionMetadata,
+    /// Page size and tile overrides
+    #[serde(flatten)]
+    pub limits: CollectionLimitsPatch,
+    /// Defaults for imports into the collection; `{}` removes them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub processing_defaults: Option<ProcessingDefaults>,
 }
 
 /// Collection schema (OGC API Schemas)
@@ -83,6 +758,135 @@ pub struct CollectionSchema {
     pub properties: serde_json::Value,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub required: Option<Vec<String>>,
+    /// Feature properties whose values must be unique
+    #[serde(rename = "x-uniqueProperties", skip_serializing_if = "Vec::is_empty")]
+    pub unique_properties: Vec<String>,
+    /// Secondary geometry columns, selectable with `geom`
+    #[serde(rename = "x-geometryColumns", skip_serializing_if = "Vec::is_empty")]
+    pub geometry_columns: Vec<String>,
+}
+
+impl CollectionSchema {
+    /// Limit the schema to the properties of a profile
+    ///
+    /// Receivables leave out the read-only properties, which the server
+    /// maintains, so a client can build an edit form from them; returnables
+    /// would leave out write-only properties, of which there are none.
+    pub fn with_profile(mut self, profile: SchemaProfile) -> Self {
+        if profile != SchemaProfile::Receivables {
+            return self;
+        }
+        let Some(properties) = self.properties.as_object_mut() else {
+            return self;
+        };
+        let read_only: Vec<String> = properties
+            .iter()
+            .filter(|(_, schema)| schema["readOnly"] == true)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in &read_only {
+            properties.remove(name);
+        }
+        if let Some(required) = &mut self.required {
+            required.retain(|name| !read_only.contains(name));
+        }
+        if self.required.as_ref().is_some_and(Vec::is_empty) {
+            self.required = None;
+        }
+        self
+    }
+}
+
+/// Profiles of a collection schema (OGC API Features Part 5)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum SchemaProfile {
+    /// All properties, with read-only ones marked `readOnly`
+    #[default]
+    ReturnablesAndReceivables,
+    /// The properties returned in features
+    Returnables,
+    /// The properties accepted when creating or updating features
+    Receivables,
+}
+
+/// Query parameters of the collection schema
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct CollectionSchemaParams {
+    /// `returnables-and-receivables` (the default), `returnables` or `receivables`
+    #[serde(default)]
+    pub profile: SchemaProfile,
+}
+
+/// Partial update of a collection schema (JSON Merge Patch)
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CollectionSchemaPatch {
+    /// Properties whose values must be unique; replaces the current list
+    #[serde(default, rename = "x-uniqueProperties")]
+    pub unique_properties: Option<Vec<String>>,
+    /// Secondary geometry columns; replaces the current list
+    #[serde(default, rename = "x-geometryColumns")]
+    pub geometry_columns: Option<Vec<String>>,
+}
+
+/// Whether a name can be used as a column or in an index definition
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.len() <= 63
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Check that unique properties are distinct identifiers
+///
+/// The names are used in the definitions of the unique indexes.
+pub fn validate_unique_properties(properties: &[String]) -> AppResult<()> {
+    for (index, property) in properties.iter().enumerate() {
+        if !is_identifier(property) {
+            return Err(AppError::BadRequest(format!(
+                "Invalid unique property name: {}",
+                property
+            )));
+        }
+        if properties[..index].contains(property) {
+            return Err(AppError::BadRequest(format!(
+                "Duplicate unique property: {}",
+                property
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Columns of every feature table
+const FEATURE_TABLE_COLUMNS: &[&str] = &[
+    "id",
+    "geometry",
+    "properties",
+    "version",
+    "created_at",
+    "updated_at",
+];
+
+/// Check that secondary geometry columns are distinct identifiers that do
+/// not clash with the columns of the feature table
+pub fn validate_geometry_columns(columns: &[String]) -> AppResult<()> {
+    for (index, column) in columns.iter().enumerate() {
+        if !is_identifier(column) || FEATURE_TABLE_COLUMNS.contains(&column.as_str()) {
+            return Err(AppError::BadRequest(format!(
+                "Invalid geometry column name: {}",
+                column
+            )));
+        }
+        if columns[..index].contains(column) {
+            return Err(AppError::BadRequest(format!(
+                "Duplicate geometry column: {}",
+                column
+            )));
+        }
+    }
+    Ok(())
 }
 
 /// Query parameters for listing collections
@@ -93,6 +897,14 @@ pub struct ListCollectionsParams {
     pub collection_type: Option<String>,
     /// Filter by owner
     pub owner: Option<String>,
+    /// Free text matched against title, description and keywords
+    pub q: Option<String>,
+    /// Comma-separated keywords the collection must all have
+    pub keyword: Option<String>,
+    /// Bounding box the collection extent must intersect: minx,miny,maxx,maxy
+    pub bbox: Option<String>,
+    /// Instant or interval the collection's temporal extent must overlap
+    pub datetime: Option<String>,
     /// Limit results
     #[serde(default = "default_limit")]
     pub limit: u32,
@@ -104,3 +916,533 @@ pub struct ListCollectionsParams {
 fn default_limit() -> u32 {
     100
 }
+
+/// Largest page of collections
+pub const MAX_COLLECTIONS_PAGE: u32 = 10_000;
+
+/// Collection search criteria, ready to be bound to a query
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CollectionFilter {
+    /// ILIKE pattern for the free text search
+    pub pattern: Option<String>,
+    pub keywords: Option<Vec<String>>,
+    pub collection_type: Option<String>,
+    pub owner: Option<String>,
+    /// minx, miny, maxx, maxy in WGS84
+    pub bbox: Option<Vec<f64>>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+/// Escape LIKE wildcards so text is matched literally
+fn like_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+impl ListCollectionsParams {
+    pub fn filter(&self) -> AppResult<CollectionFilter> {
+        if self.limit == 0 || self.limit > MAX_COLLECTIONS_PAGE {
+            return Err(AppError::BadRequest(format!(
+                "limit must be between 1 and {}",
+                MAX_COLLECTIONS_PAGE
+            )));
+        }
+
+        let extent_params = FeatureQueryParams {
+            bbox: self.bbox.clone(),
+            datetime: self.datetime.clone(),
+            ..Default::default()
+        };
+        extent_params.validate()?;
+
+        let bbox = match &self.bbox {
+            Some(bbox) => Some(extent_params.parse_bbox(bbox)?.to_vec()),
+            None => None,
+        };
+
+        let (start, end) = match self.datetime.as_deref() {
+            Some(datetime) => match datetime.split_once('/') {
+                Some((start, end)) => (parse_instant(start), parse_instant(end)),
+                None => (parse_instant(datetime), parse_instant(datetime)),
+            },
+            None => (None, None),
+        };
+
+        let keywords: Vec<String> = self
+            .keyword
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|keyword| !keyword.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Ok(CollectionFilter {
+            pattern: self
+                .q
+                .as_deref()
+                .map(str::trim)
+                .filter(|q| !q.is_empty())
+                .map(like_pattern),
+            keywords: (!keywords.is_empty()).then_some(keywords),
+            collection_type: self.collection_type.clone(),
+            owner: self.owner.clone(),
+            bbox,
+            start,
+            end,
+        })
+    }
+}
+
+impl ListCollectionsParams {
+    /// Collection list URL for the given offset, preserving the filters
+    pub fn page_href(&self, base_url: &str, offset: u32) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        for (key, value) in [
+            ("type", &self.collection_type),
+            ("owner", &self.owner),
+            ("q", &self.q),
+            ("keyword", &self.keyword),
+            ("bbox", &self.bbox),
+            ("datetime", &self.datetime),
+        ] {
+            if let Some(value) = value {
+                query.append_pair(key, value);
+            }
+        }
+        query.append_pair("limit", &self.limit.to_string());
+        query.append_pair("offset", &offset.to_string());
+        format!("{}/collections?{}", base_url, query.finish())
+    }
+}
+
+/// Parse a validated datetime bound; `..` and empty bounds are open
+fn parse_instant(instant: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(instant)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_collection_expires_at() {
+        let request = |expires_in: Option<u64>| CreateCollectionRequest {
+            expires_in,
+            ..serde_json::from_value(serde_json::json!({
+                "id": "scratch",
+                "title": "Scratch",
+                "collectionType": "vector"
+            }))
+            .unwrap()
+        };
+        assert!(request(None).expires_at().unwrap().is_none());
+        let expires = request(Some(60)).expires_at().unwrap().unwrap();
+        assert!(expires > Utc::now());
+        assert!(request(Some(0)).expires_at().is_err());
+        assert!(
+            request(Some(MAX_COLLECTION_TTL_SECS + 1))
+                .expires_at()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_schema_receivables() {
+        let schema = CollectionSchema {
+            schema: "https://json-schema.org/draft/2020-12/schema".to_string(),
+            id: "http://localhost/collections/alice:roads/schema".to_string(),
+            schema_type: "object".to_string(),
+            title: "Roads".to_string(),
+            properties: serde_json::json!({
+                "id": { "type": "string", "x-ogc-role": "id", "readOnly": true },
+                "geometry": { "format": "geometry-any", "x-ogc-role": "primary-geometry" },
+                "name": { "type": "string" },
+                "length": { "type": "number", "readOnly": true },
+            }),
+            required: Some(vec!["id".to_string(), "geometry".to_string()]),
+            unique_properties: Vec::new(),
+            geometry_columns: Vec::new(),
+        };
+        let receivables = schema.with_profile(SchemaProfile::Receivables);
+        let properties = receivables.properties.as_object().unwrap();
+        let mut names: Vec<_> = properties.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["geometry", "name"]);
+        assert_eq!(receivables.required, Some(vec!["geometry".to_string()]));
+    }
+
+    #[test]
+    fn test_collection_geometry() {
+        let geometry = CollectionGeometry::default();
+        assert!(geometry.validate("vector").is_ok());
+        assert!(geometry.validate("raster").is_ok());
+        assert_eq!(geometry.type_modifier(), "Geometry");
+
+        let geometry: CollectionGeometry = serde_json::from_value(serde_json::json!({
+            "geometryType": "Point",
+            "geometryDimension": 3
+        }))
+        .unwrap();
+        assert!(geometry.validate("vector").is_ok());
+        assert!(geometry.validate("table").is_err());
+        assert_eq!(geometry.type_modifier(), "PointZ");
+        assert_eq!(
+            CollectionGeometry::from_postgis(Some("POINT"), Some(3)),
+            geometry
+        );
+        assert_eq!(
+            CollectionGeometry::from_postgis(Some("GEOMETRY"), Some(2)).resolved(),
+            (GeometryType::Geometry, 2)
+        );
+        assert_eq!(
+            CollectionGeometry::from_postgis(None, None),
+            CollectionGeometry::default()
+        );
+
+        let geometry = CollectionGeometry {
+            geometry_dimension: Some(4),
+            ..Default::default()
+        };
+        assert!(geometry.validate("vector").is_err());
+        assert!(
+            serde_json::from_value::<CollectionGeometry>(
+                serde_json::json!({ "geometryType": "Circle" })
+            )
+            .is_err()
+        );
+
+        assert_eq!(GeometryType::Geometry.schema_format(), "geometry-any");
+        assert_eq!(
+            GeometryType::MultiPolygon.schema_format(),
+            "geometry-multipolygon"
+        );
+    }
+
+    #[test]
+    fn test_validate_collection_limits() {
+        let limits = |default_limit, max_limit| CollectionLimits {
+            default_limit,
+            max_limit,
+            ..Default::default()
+        };
+        assert!(limits(None, None).validate().is_ok());
+        assert!(limits(Some(1000), Some(50000)).validate().is_ok());
+        assert!(limits(Some(0), None).validate().is_err());
+        assert!(
+            limits(None, Some(MAX_COLLECTION_LIMIT + 1))
+                .validate()
+                .is_err()
+        );
+        assert!(limits(Some(100), Some(50)).validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_zoom_limits() {
+        let zooms = |min_zoom, max_zoom| CollectionLimits {
+            min_zoom,
+            max_zoom,
+            ..Default::default()
+        };
+        assert!(zooms(Some(0), Some(MAX_ZOOM)).validate().is_ok());
+        assert!(zooms(Some(4), None).validate().is_ok());
+        assert!(zooms(None, Some(MAX_ZOOM + 1)).validate().is_err());
+        assert!(zooms(Some(10), Some(5)).validate().is_err());
+    }
+
+    #[test]
+    fn test_apply_limits_patch() {
+        let current = CollectionLimits {
+            min_zoom: Some(2),
+            max_zoom: Some(5),
+            ..Default::default()
+        };
+        let patch: CollectionLimitsPatch =
+            serde_json::from_value(serde_json::json!({ "maxZoom": null, "minZoom": 6 })).unwrap();
+        let patched = patch.apply(&current);
+        assert_eq!((patched.min_zoom, patched.max_zoom), (Some(6), None));
+
+        let current = CollectionLimits {
+            default_limit: Some(50),
+            max_limit: Some(500),
+            ..current
+        };
+        let patch: CollectionLimitsPatch =
+            serde_json::from_value(serde_json::json!({ "defaultLimit": null })).unwrap();
+        let patched = patch.apply(&current);
+        assert_eq!(
+            (patched.default_limit, patched.max_limit),
+            (None, Some(500))
+        );
+
+        let patch: CollectionLimitsPatch = serde_json::from_value(serde_json::json!({})).unwrap();
+        let patched = patch.apply(&current);
+        assert_eq!((patched.min_zoom, patched.max_zoom), (Some(2), Some(5)));
+    }
+
+    #[test]
+    fn test_validate_tile_layer() {
+        let layer = |tile_layer: &str| CollectionLimits {
+            tile_layer: Some(tile_layer.to_string()),
+            ..Default::default()
+        };
+        assert!(layer("roads").validate().is_ok());
+        assert!(layer(" ").validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_asset() {
+        let asset = |href: &str| AssetObject {
+            href: href.to_string(),
+            media_type: None,
+            title: None,
+            description: None,
+            roles: None,
+            file_size: None,
+        };
+        assert!(asset("s3://bucket/export.pmtiles").validate().is_ok());
+        assert!(asset("https://example.com/spec.pdf").validate().is_ok());
+        assert!(asset("ftp://example.com/spec.pdf").validate().is_err());
+
+        assert!(validate_asset_key("pmtiles").is_ok());
+        assert!(validate_asset_key("mosaic.json").is_ok());
+        assert!(validate_asset_key("").is_err());
+        assert!(validate_asset_key("a/b").is_err());
+    }
+
+    #[test]
+    fn test_registered_asset() {
+        let registered: RegisteredAsset = serde_json::from_value(serde_json::json!({
+            "href": "s3://bucket/scene/B04.tif",
+            "type": "image/tiff; application=geotiff",
+            "roles": ["data"],
+            "proj:epsg": 32633,
+            "eo:bands": [{ "name": "B04" }]
+        }))
+        .unwrap();
+        assert!(registered.validate().is_ok());
+        assert_eq!(registered.asset.roles, Some(vec!["data".to_string()]));
+        assert_eq!(registered.extra_fields.len(), 2);
+        assert_eq!(registered.extra_fields["proj:epsg"], 32633);
+        assert!(!registered.extra_fields.contains_key("href"));
+
+        let registered: RegisteredAsset = serde_json::from_value(serde_json::json!({
+            "href": "https://example.com/scene/B04.tif"
+        }))
+        .unwrap();
+        assert!(registered.validate().is_err());
+    }
+
+    #[test]
+    fn test_registered_asset_location() {
+        let registered = |href: &str| -> RegisteredAsset {
+            serde_json::from_value(serde_json::json!({ "href": href })).unwrap()
+        };
+        let namespaces = ["alice", "surveys"];
+
+        assert!(
+            registered("s3://bucket/alice/scene/B04.tif")
+                .validate_location("s3://bucket/", &namespaces)
+                .is_ok()
+        );
+        assert!(
+            registered("s3://bucket/surveys/scene/B04.tif")
+                .validate_location("s3://bucket/", &namespaces)
+                .is_ok()
+        );
+        // Other owners, buckets and escapes from the namespace
+        for href in [
+            "s3://bucket/bob/scene/B04.tif",
+            "s3://bucket/alicebob/scene/B04.tif",
+            "s3://other/alice/scene/B04.tif",
+            "s3://bucket/alice/../bob/B04.tif",
+            "s3://bucket/alice//B04.tif",
+            "s3://bucket/alice",
+        ] {
+            assert!(
+                registered(href)
+                    .validate_location("s3://bucket/", &namespaces)
+                    .is_err(),
+                "{} should be rejected",
+                href
+            );
+        }
+    }
+
+    #[test]
+    fn test_processing_defaults() {
+        let defaults: ProcessingDefaults = serde_json::from_value(serde_json::json!({
+            "datetimeProperty": "acquired",
+            "properties": { "platform": "drone", "acquired": "2024-01-01T00:00:00Z" }
+        }))
+        .unwrap();
+        assert!(defaults.validate().is_ok());
+
+        let properties = defaults
+            .merge_properties(Some(
+                serde_json::json!({ "acquired": "2024-06-01T00:00:00Z" }),
+            ))
+            .unwrap();
+        assert_eq!(properties["platform"], "drone");
+        assert_eq!(properties["acquired"], "2024-06-01T00:00:00Z");
+        assert_eq!(
+            defaults.datetime_from(Some(&properties)).as_deref(),
+            Some("2024-06-01T00:00:00Z")
+        );
+        assert!(defaults.merge_properties(None).is_some());
+        assert!(
+            ProcessingDefaults::default()
+                .merge_properties(None)
+                .is_none()
+        );
+
+        let invalid = ProcessingDefaults {
+            pipeline: Some(PipelineOptions {
+                decimation_step: Some(0),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_normalize_metadata() {
+        let metadata = CollectionMetadata {
+            keywords: Some(vec![" roads ".to_string()]),
+            license: Some(" CC-BY-4.0 ".to_string()),
+            titles: Some(BTreeMap::from([(
+                "sv-SE".to_string(),
+                " Vägar ".to_string(),
+            )])),
+            descriptions: None,
+        };
+        assert_eq!(
+            metadata.normalized().unwrap(),
+            CollectionMetadata {
+                keywords: Some(vec!["roads".to_string()]),
+                license: Some("CC-BY-4.0".to_string()),
+                titles: Some(BTreeMap::from([("sv-se".to_string(), "Vägar".to_string())])),
+                descriptions: None,
+            }
+        );
+        for (language, text) in [("sv", " "), ("s", "Vägar"), ("sv_SE", "Vägar")] {
+            let invalid = CollectionMetadata {
+                descriptions: Some(BTreeMap::from([(language.to_string(), text.to_string())])),
+                ..Default::default()
+            };
+            assert!(invalid.normalized().is_err());
+        }
+        let empty_license = CollectionMetadata {
+            license: Some(" ".to_string()),
+            ..Default::default()
+        };
+        assert!(empty_license.normalized().is_err());
+        assert_eq!(
+            CollectionMetadata::from_stored(&[], None, None, None),
+            CollectionMetadata::default()
+        );
+    }
+
+    #[test]
+    fn test_normalize_keywords() {
+        let keywords = |values: &[&str]| -> Vec<String> {
+            values.iter().map(|value| value.to_string()).collect()
+        };
+        assert_eq!(
+            normalize_keywords(&keywords(&[" roads ", "transport", "roads"])).unwrap(),
+            keywords(&["roads", "transport"])
+        );
+        assert!(normalize_keywords(&keywords(&["  "])).is_err());
+        assert!(normalize_keywords(&keywords(&[&"x".repeat(MAX_KEYWORD_LENGTH + 1)])).is_err());
+    }
+
+    #[test]
+    fn test_collection_filter() {
+        let params = ListCollectionsParams {
+            q: Some(" 50%_off ".to_string()),
+            keyword: Some("roads, ,transport".to_string()),
+            bbox: Some("10,50,20,60".to_string()),
+            datetime: Some("../2024-01-01T00:00:00Z".to_string()),
+            limit: 10,
+            ..Default::default()
+        };
+        let filter = params.filter().unwrap();
+        assert_eq!(filter.pattern.as_deref(), Some("%50\\%\\_off%"));
+        assert_eq!(
+            filter.keywords,
+            Some(vec!["roads".to_string(), "transport".to_string()])
+        );
+        assert_eq!(filter.bbox, Some(vec![10.0, 50.0, 20.0, 60.0]));
+        assert_eq!(filter.start, None);
+        assert_eq!(
+            filter.end.map(|end| end.to_rfc3339()),
+            Some("2024-01-01T00:00:00+00:00".to_string())
+        );
+
+        let instant = ListCollectionsParams {
+            datetime: Some("2024-01-01T00:00:00Z".to_string()),
+            limit: 10,
+            ..Default::default()
+        }
+        .filter()
+        .unwrap();
+        assert_eq!(instant.start, instant.end);
+        assert!(instant.start.is_some());
+
+        let unfiltered = ListCollectionsParams {
+            limit: 10,
+            ..Default::default()
+        };
+        let unfiltered = unfiltered.filter().unwrap();
+        assert!(unfiltered.bbox.is_none() && unfiltered.start.is_none());
+        let invalid = ListCollectionsParams {
+            bbox: Some("20,50,10,60".to_string()),
+            limit: 10,
+            ..Default::default()
+        };
+        assert!(invalid.filter().is_err());
+        assert!(ListCollectionsParams::default().filter().is_err());
+    }
+
+    #[test]
+    fn test_collections_page_href() {
+        let params = ListCollectionsParams {
+            keyword: Some("roads,rail".to_string()),
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(
+            params.page_href("http://localhost", 20),
+            "http://localhost/collections?keyword=roads%2Crail&limit=10&offset=20"
+        );
+    }
+
+    #[test]
+    fn test_validate_unique_properties() {
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert!(validate_unique_properties(&names(&["parcel_id", "_code2"])).is_ok());
+        assert!(validate_unique_properties(&[]).is_ok());
+        assert!(validate_unique_properties(&names(&["code", "code"])).is_err());
+        assert!(validate_unique_properties(&names(&["2nd"])).is_err());
+        assert!(validate_unique_properties(&names(&["name'); DROP"])).is_err());
+        assert!(validate_unique_properties(&["x".repeat(64)]).is_err());
+    }
+
+    #[test]
+    fn test_validate_geometry_columns() {
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert!(validate_geometry_columns(&names(&["label", "entrance"])).is_ok());
+        assert!(validate_geometry_columns(&names(&["label", "label"])).is_err());
+        assert!(validate_geometry_columns(&names(&["geometry"])).is_err());
+        assert!(validate_geometry_columns(&names(&["updated_at"])).is_err());
+        assert!(validate_geometry_columns(&names(&["label point"])).is_err());
+    }
+}
@@ -8,15 +8,15 @@ use aide::{
 use axum::{
     Json,
     extract::{Extension, State},
-    http::{HeaderMap, StatusCode, header},
+    http::StatusCode,
     response::{IntoResponse, Response},
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+use super::resolved::ResolvedCollection;
 use crate::auth::AuthenticatedUser;
-use crate::config::Config;
 use crate::error::{AppError, AppResult};
 use crate::services::CollectionService;
 
@@ -80,24 +80,12 @@ pub struct CollectionSharingPath {
 }
 
 pub async fn list_shares(
-    Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
     State(service): State<Arc<CollectionService>>,
-    path: CollectionSharingPath,
+    _path: CollectionSharingPath,
+    ResolvedCollection(collection): ResolvedCollection,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = service.check_alias_redirect(&collection_id).await? {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::LOCATION,
-            format!("{}/collections/{}/sharing", config.base_url, new_name)
-                .parse()
-                .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, headers).into_response());
-    }
-
+    let collection_id = collection.canonical_name.clone();
     let shares = service.list_shares(&user.username, &collection_id).await?;
 
     Ok(Json(SharesResponse {
@@ -117,25 +105,13 @@ fn list_shares_docs(op: TransformOperation) -> TransformOperation {
 }
 
 pub async fn add_share(
-    Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
     State(service): State<Arc<CollectionService>>,
-    path: CollectionSharingPath,
+    _path: CollectionSharingPath,
+    ResolvedCollection(collection): ResolvedCollection,
     Json(request): Json<AddShareRequest>,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = service.check_alias_redirect(&collection_id).await? {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::LOCATION,
-            format!("{}/collections/{}/sharing", config.base_url, new_name)
-                .parse()
-                .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, headers).into_response());
-    }
-
+    let collection_id = collection.canonical_name.clone();
     service
         .add_share(
             &user.username,
@@ -169,27 +145,12 @@ pub struct SharePrincipalPath {
 }
 
 pub async fn remove_share(
-    Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
     State(service): State<Arc<CollectionService>>,
     path: SharePrincipalPath,
+    ResolvedCollection(collection): ResolvedCollection,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = service.check_alias_redirect(&collection_id).await? {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::LOCATION,
-            format!(
-                "{}/collections/{}/sharing/{}",
-                config.base_url, new_name, path.principal
-            )
-            .parse()
-            .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, headers).into_response());
-    }
-
+    let collection_id = collection.canonical_name.clone();
     let principal = path.principal;
     service
         .remove_share(&user.username, &collection_id, &principal)
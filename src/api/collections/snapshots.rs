@@ -0,0 +1,317 @@
+//! Named snapshots of a collection's features
+//!
+//! A snapshot copies the feature table of a vector or table collection, so
+//! that its owner can roll back a bulk edit that went wrong by restoring it.
+
+use aide::{
+    axum::{
+        ApiRouter,
+        routing::{get_with, post_with},
+    },
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::resolved::ResolvedCollection;
+use crate::api::body::JsonBody;
+use crate::api::common::etag;
+use crate::auth::AuthenticatedUser;
+use crate::config::Config;
+use crate::db::CollectionSnapshot;
+use crate::error::{AppError, AppResult};
+use crate::services::CollectionService;
+
+/// Longest snapshot name
+const MAX_NAME_LEN: usize = 64;
+
+/// Request to snapshot a collection
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CreateSnapshotRequest {
+    /// Name of the snapshot, e.g. `before-import`; letters, digits, `-`, `_`
+    /// and `.`
+    pub name: String,
+}
+
+/// A snapshot of a collection
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotResponse {
+    pub name: String,
+    /// When the snapshot was taken
+    #[schemars(with = "String")]
+    pub created: DateTime<Utc>,
+    /// User who took the snapshot
+    pub created_by: String,
+    /// Collection version the snapshot was taken at
+    pub collection_version: i64,
+    /// Number of features in the snapshot
+    pub number_of_features: i64,
+}
+
+impl From<CollectionSnapshot> for SnapshotResponse {
+    fn from(snapshot: CollectionSnapshot) -> Self {
+        Self {
+            name: snapshot.name,
+            created: snapshot.created_at,
+            created_by: snapshot.created_by,
+            collection_version: snapshot.collection_version,
+            number_of_features: snapshot.feature_count,
+        }
+    }
+}
+
+/// The snapshots of a collection
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SnapshotsResponse {
+    pub snapshots: Vec<SnapshotResponse>,
+}
+
+/// Check that a snapshot name is usable in a URL path segment
+pub fn validate_snapshot_name(name: &str) -> AppResult<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && !name.starts_with('.');
+    if !valid {
+        return Err(AppError::BadRequest(format!(
+            "Invalid snapshot name: {}",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Path parameters for collection snapshot endpoints
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/snapshots")]
+pub struct SnapshotsPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+/// Path parameters for a single snapshot
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/snapshots/{name}")]
+pub struct SnapshotPath {
+    /// The collection identifier
+    pub collection_id: String,
+    /// The snapshot name
+    pub name: String,
+}
+
+/// Path parameters for restoring a snapshot
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/snapshots/{name}/restore")]
+pub struct RestoreSnapshotPath {
+    /// The collection identifier
+    pub collection_id: String,
+    /// The snapshot name
+    pub name: String,
+}
+
+pub async fn list_snapshots(
+    State(service): State<Arc<CollectionService>>,
+    _path: SnapshotsPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> AppResult<Json<SnapshotsResponse>> {
+    let snapshots = service.list_snapshots(collection.id).await?;
+
+    Ok(Json(SnapshotsResponse {
+        snapshots: snapshots.into_iter().map(Into::into).collect(),
+    }))
+}
+
+fn list_snapshots_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("List snapshots")
+        .description("Returns the snapshots of a collection, oldest first")
+        .tag("Collections")
+        .response_with::<200, Json<SnapshotsResponse>, _>(|res| {
+            res.description("Snapshots of the collection")
+        })
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+pub async fn create_snapshot(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    _path: SnapshotsPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    JsonBody(request): JsonBody<CreateSnapshotRequest>,
+) -> AppResult<Response> {
+    validate_snapshot_name(&request.name)?;
+
+    let snapshot = service
+        .create_snapshot(&user.username, &collection.canonical_name, &request.name)
+        .await?;
+
+    let location = format!(
+        "{}/collections/{}/snapshots/{}",
+        config.base_url, collection.canonical_name, snapshot.name
+    );
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::LOCATION,
+        HeaderValue::from_str(&location)
+            .map_err(|e| AppError::Internal(format!("Invalid Location header: {}", e)))?,
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        headers,
+        Json(SnapshotResponse::from(snapshot)),
+    )
+        .into_response())
+}
+
+fn create_snapshot_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Create snapshot")
+        .description(
+            "Copies the features of a vector or table collection into a named snapshot, which \
+             the owner can restore later, e.g. to undo a bulk edit. Only the owner may take \
+             snapshots.",
+        )
+        .tag("Collections")
+        .response_with::<201, Json<SnapshotResponse>, _>(|res| res.description("Snapshot taken"))
+        .response_with::<400, (), _>(|res| {
+            res.description("Invalid name, or the collection has no features to snapshot")
+        })
+        .response_with::<403, (), _>(|res| res.description("Only the owner may take snapshots"))
+        .response_with::<409, (), _>(|res| res.description("Snapshot already exists"))
+        .response_with::<507, (), _>(|res| {
+            res.description("The namespace of the collection is over its storage quota")
+        })
+}
+
+pub async fn get_snapshot(
+    State(service): State<Arc<CollectionService>>,
+    path: SnapshotPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> AppResult<Json<SnapshotResponse>> {
+    let snapshot = service
+        .get_snapshot(collection.id, &path.name)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Snapshot not found: {}", path.name)))?;
+
+    Ok(Json(snapshot.into()))
+}
+
+fn get_snapshot_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get snapshot")
+        .tag("Collections")
+        .response_with::<200, Json<SnapshotResponse>, _>(|res| res.description("The snapshot"))
+        .response_with::<404, (), _>(|res| res.description("Snapshot not found"))
+}
+
+pub async fn delete_snapshot(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    path: SnapshotPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> AppResult<StatusCode> {
+    service
+        .delete_snapshot(&user.username, &collection.canonical_name, &path.name)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn delete_snapshot_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Delete snapshot")
+        .tag("Collections")
+        .response_with::<204, (), _>(|res| res.description("Snapshot deleted"))
+        .response_with::<403, (), _>(|res| res.description("Only the owner may delete snapshots"))
+        .response_with::<404, (), _>(|res| res.description("Snapshot not found"))
+}
+
+pub async fn restore_snapshot(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    path: RestoreSnapshotPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    // If-Match header is optional - when present, enables optimistic locking
+    let expected_version = etag::extract_expected_version(&headers)?;
+
+    let (snapshot, collection) = service
+        .restore_snapshot(
+            &user.username,
+            &collection.canonical_name,
+            &path.name,
+            expected_version,
+        )
+        .await?;
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::ETAG, etag::create_etag_header(collection.version)?);
+
+    Ok((response_headers, Json(SnapshotResponse::from(snapshot))).into_response())
+}
+
+fn restore_snapshot_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Restore snapshot")
+        .description(
+            "Replaces all features of the collection with those in the snapshot. Features \
+             created since are removed and restored features get new versions, so ETags taken \
+             before the restore no longer match. The snapshot is kept. Only the owner may \
+             restore snapshots. If-Match header is optional and refers to the collection; when \
+             provided, enables optimistic locking.",
+        )
+        .tag("Collections")
+        .response_with::<200, Json<SnapshotResponse>, _>(|res| {
+            res.description("Snapshot restored; the ETag is the new collection version")
+        })
+        .response_with::<403, (), _>(|res| res.description("Only the owner may restore snapshots"))
+        .response_with::<404, (), _>(|res| res.description("Snapshot not found"))
+        .response_with::<412, (), _>(|res| res.description("Precondition failed (ETag mismatch)"))
+        .response_with::<507, (), _>(|res| {
+            res.description("The namespace of the collection is over its storage quota")
+        })
+}
+
+pub fn routes(service: Arc<CollectionService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/snapshots",
+            get_with(list_snapshots, list_snapshots_docs)
+                .post_with(create_snapshot, create_snapshot_docs),
+        )
+        .api_route(
+            "/collections/{collection_id}/snapshots/{name}",
+            get_with(get_snapshot, get_snapshot_docs)
+                .delete_with(delete_snapshot, delete_snapshot_docs),
+        )
+        .api_route(
+            "/collections/{collection_id}/snapshots/{name}/restore",
+            post_with(restore_snapshot, restore_snapshot_docs),
+        )
+        .with_state(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_snapshot_name() {
+        assert!(validate_snapshot_name("before-import").is_ok());
+        assert!(validate_snapshot_name("2024_06_01.v2").is_ok());
+        assert!(validate_snapshot_name("").is_err());
+        assert!(validate_snapshot_name("..").is_err());
+        assert!(validate_snapshot_name("a/b").is_err());
+        assert!(validate_snapshot_name("with space").is_err());
+        assert!(validate_snapshot_name(&"a".repeat(MAX_NAME_LEN + 1)).is_err());
+    }
+}
@@ -1,4 +1,8 @@
-use axum::http::{HeaderMap, HeaderValue, header};
+use axum::{
+    body::{Body, Bytes},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -37,6 +41,11 @@ impl Link {
         self.title = Some(title.into());
         self
     }
+
+    pub fn with_hreflang(mut self, hreflang: impl Into<String>) -> Self {
+        self.hreflang = Some(hreflang.into());
+        self
+    }
 }
 
 /// Standard link relations
@@ -61,15 +70,32 @@ pub mod rel {
 /// Standard media types
 pub mod media_type {
     pub const JSON: &str = "application/json";
+    pub const SCHEMA_JSON: &str = "application/schema+json";
     pub const GEOJSON: &str = "application/geo+json";
+    pub const MERGE_PATCH: &str = "application/merge-patch+json";
     pub const OPENAPI_JSON: &str = "application/vnd.oai.openapi+json;version=3.0";
+    pub const OPENAPI_YAML: &str = "application/vnd.oai.openapi;version=3.0";
+    pub const YAML: &str = "application/yaml";
     pub const HTML: &str = "text/html";
+    pub const XML: &str = "application/xml";
+    pub const CSV: &str = "text/csv; charset=utf-8";
+    pub const NDJSON: &str = "application/x-ndjson";
+    pub const GML_SF0: &str = "application/gml+xml; version=3.2; profile=http://www.opengis.net/def/profile/ogc/2.0/gml-sf0";
     pub const MVT: &str = "application/vnd.mapbox-vector-tile";
     pub const PNG: &str = "image/png";
     pub const WEBP: &str = "image/webp";
     pub const TIFF: &str = "image/tiff";
     pub const COG: &str = "image/tiff; application=geotiff; profile=cloud-optimized";
     pub const COPC: &str = "application/vnd.laszip+copc";
+    pub const COVERAGE_JSON: &str = "application/prs.coverage+json";
+}
+
+/// Response to a HEAD request whose body was not produced
+///
+/// The body has no known length, so no `Content-Length: 0` is derived from it.
+pub fn head_response(headers: HeaderMap) -> Response {
+    let body = Body::from_stream(futures::stream::empty::<Result<Bytes, std::io::Error>>());
+    (StatusCode::OK, headers, body).into_response()
 }
 
 /// Bounding box [minx, miny, maxx, maxy] or [minx, miny, minz, maxx, maxy, maxz]
@@ -208,11 +234,169 @@ pub mod etag {
     }
 }
 
+/// HTTP caching of data rendered from a collection (tiles, coverages)
+///
+/// Rendered data only changes when the collection's version does, so the
+/// version is its validator, and URLs naming the current version (`v`) can
+/// be cached forever.
+pub mod cache {
+    use super::*;
+    use crate::config::CacheConfig;
+    use chrono::{DateTime, Utc};
+
+    /// Query parameter naming the collection version a URL is for
+    #[derive(Debug, Default, Deserialize, JsonSchema)]
+    pub struct VersionParams {
+        /// Collection version the URL is for; responses for the current
+        /// version are cached as immutable
+        pub v: Option<i64>,
+    }
+
+    /// Caching headers of data rendered from a collection at `version`, or
+    /// a 304 response when the client's copy is still current
+    pub fn validate(
+        config: &CacheConfig,
+        version: i64,
+        variant: &str,
+        requested_version: Option<i64>,
+        request_headers: &HeaderMap,
+    ) -> Result<HeaderMap, Box<Response>> {
+        let etag = data_etag(version, variant);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CACHE_CONTROL,
+            cache_control(config, requested_version == Some(version)),
+        );
+        headers.insert(header::ETAG, etag.clone());
+        if is_not_modified(request_headers, &etag) {
+            return Err(Box::new(not_modified_response(headers)));
+        }
+        Ok(headers)
+    }
+
+    /// ETag of data rendered from a collection at `version`; `variant`
+    /// distinguishes representations negotiated for the same URL
+    pub fn data_etag(version: i64, variant: &str) -> HeaderValue {
+        HeaderValue::from_str(&format!("\"{}-{}\"", version, variant))
+            .unwrap_or_else(|_| HeaderValue::from_static("\"0\""))
+    }
+
+    /// Cache-Control of rendered data; `versioned` when the URL names the
+    /// current collection version
+    pub fn cache_control(config: &CacheConfig, versioned: bool) -> HeaderValue {
+        let value = if versioned {
+            format!(
+                "public, max-age={}, immutable",
+                config.immutable_max_age_secs
+            )
+        } else {
+            format!(
+                "public, max-age={}, stale-while-revalidate={}",
+                config.max_age_secs, config.stale_while_revalidate_secs
+            )
+        };
+        HeaderValue::from_str(&value).unwrap_or_else(|_| HeaderValue::from_static("no-cache"))
+    }
+
+    /// Whether the request's If-None-Match header matches `etag`
+    pub fn is_not_modified(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+        let Ok(etag) = etag.to_str() else {
+            return false;
+        };
+        headers
+            .get_all(header::IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|candidate| candidate.trim())
+            .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+    }
+
+    /// Last-Modified value of a time, as an HTTP date
+    pub fn last_modified(time: DateTime<Utc>) -> HeaderValue {
+        HeaderValue::from_str(&time.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+            .unwrap_or_else(|_| HeaderValue::from_static("Thu, 01 Jan 1970 00:00:00 GMT"))
+    }
+
+    /// Whether the request's If-Modified-Since header is no earlier than
+    /// `time`; ignored when If-None-Match is present, which takes precedence
+    pub fn is_unmodified_since(headers: &HeaderMap, time: DateTime<Utc>) -> bool {
+        if headers.contains_key(header::IF_NONE_MATCH) {
+            return false;
+        }
+        headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+            // HTTP dates have whole seconds
+            .is_some_and(|since| time.timestamp() <= since.timestamp())
+    }
+
+    /// 304 Not Modified with the caching headers of the full response
+    pub fn not_modified_response(mut headers: HeaderMap) -> Response {
+        headers.remove(header::CONTENT_TYPE);
+        (StatusCode::NOT_MODIFIED, headers).into_response()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use axum::http::{HeaderMap, HeaderValue, header};
 
+    #[test]
+    fn test_cache_validation() {
+        let etag = cache::data_etag(7, "png");
+        assert_eq!(etag.to_str().unwrap(), "\"7-png\"");
+
+        let mut headers = HeaderMap::new();
+        assert!(!cache::is_not_modified(&headers, &etag));
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_static("\"6-png\", W/\"7-png\""),
+        );
+        assert!(cache::is_not_modified(&headers, &etag));
+        assert!(!cache::is_not_modified(
+            &headers,
+            &cache::data_etag(8, "png")
+        ));
+    }
+
+    #[test]
+    fn test_last_modified() {
+        let time = chrono::DateTime::parse_from_rfc3339("2024-03-05T08:09:10.5Z")
+            .unwrap()
+            .to_utc();
+        let value = cache::last_modified(time);
+        assert_eq!(value.to_str().unwrap(), "Tue, 05 Mar 2024 08:09:10 GMT");
+
+        let mut headers = HeaderMap::new();
+        assert!(!cache::is_unmodified_since(&headers, time));
+        headers.insert(header::IF_MODIFIED_SINCE, value);
+        assert!(cache::is_unmodified_since(&headers, time));
+        assert!(!cache::is_unmodified_since(
+            &headers,
+            time + chrono::Duration::seconds(1)
+        ));
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"1\""));
+        assert!(!cache::is_unmodified_since(&headers, time));
+    }
+
+    #[test]
+    fn test_cache_control() {
+        let config = crate::config::CacheConfig::default();
+        assert_eq!(
+            cache::cache_control(&config, false).to_str().unwrap(),
+            "public, max-age=3600, stale-while-revalidate=86400"
+        );
+        assert!(
+            cache::cache_control(&config, true)
+                .to_str()
+                .unwrap()
+                .ends_with("immutable")
+        );
+    }
+
     #[test]
     fn test_extract_expected_version_none() {
         let headers = HeaderMap::new();
@@ -231,7 +415,10 @@ mod tests {
     #[test]
     fn test_extract_expected_version_invalid_format() {
         let mut headers = HeaderMap::new();
-        headers.insert(header::IF_MATCH, HeaderValue::from_static("\"not-a-number\""));
+        headers.insert(
+            header::IF_MATCH,
+            HeaderValue::from_static("\"not-a-number\""),
+        );
         let result = etag::extract_expected_version(&headers);
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), AppError::BadRequest(_)));
@@ -250,7 +437,10 @@ mod tests {
         let headers = HeaderMap::new();
         let result = etag::extract_required_version(&headers);
         assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), AppError::PreconditionFailed(_)));
+        assert!(matches!(
+            result.unwrap_err(),
+            AppError::PreconditionFailed(_)
+        ));
     }
 
     #[test]
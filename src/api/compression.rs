@@ -0,0 +1,75 @@
+use axum::http::{Extensions, HeaderMap, StatusCode, Version, header};
+use tower_http::compression::{
+    CompressionLayer, CompressionLevel, Predicate,
+    predicate::{And, SizeAbove},
+};
+
+use crate::config::CompressionConfig;
+
+/// Content types that are already compressed, or must reach the client
+/// unbuffered, by prefix
+///
+/// Vector tiles, GeoJSON and other text formats are not listed; they shrink
+/// a lot, especially large FeatureCollections.
+const UNCOMPRESSED_CONTENT_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/webp",
+    "image/tiff",
+    "application/vnd.laszip",
+    "application/vnd.google-earth.kmz",
+    "application/zip",
+    "application/gzip",
+    "text/event-stream",
+    "application/grpc",
+];
+
+/// Whether a response's content type is worth compressing
+pub fn is_compressible(content_type: &str) -> bool {
+    !UNCOMPRESSED_CONTENT_TYPES
+        .iter()
+        .any(|prefix| content_type.starts_with(prefix))
+}
+
+type ContentTypePredicate = fn(StatusCode, Version, &HeaderMap, &Extensions) -> bool;
+
+fn compressible_response(
+    _status: StatusCode,
+    _version: Version,
+    headers: &HeaderMap,
+    _extensions: &Extensions,
+) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_none_or(is_compressible)
+}
+
+/// Layer compressing responses with zstd, brotli or gzip, as the client
+/// accepts
+pub fn layer(config: &CompressionConfig) -> CompressionLayer<And<SizeAbove, ContentTypePredicate>> {
+    let quality = config
+        .level
+        .map_or(CompressionLevel::Default, CompressionLevel::Precise);
+
+    CompressionLayer::new().quality(quality).compress_when(
+        SizeAbove::new(config.min_size_bytes).and(compressible_response as ContentTypePredicate),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::common::media_type;
+
+    #[test]
+    fn test_is_compressible() {
+        assert!(is_compressible(media_type::MVT));
+        assert!(is_compressible(media_type::GEOJSON));
+        assert!(is_compressible("image/svg+xml"));
+        assert!(!is_compressible(media_type::PNG));
+        assert!(!is_compressible("image/jpeg"));
+        assert!(!is_compressible(media_type::COPC));
+        assert!(!is_compressible(media_type::COG));
+    }
+}
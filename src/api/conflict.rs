@@ -0,0 +1,154 @@
+use axum::{
+    Json,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::api::common::etag;
+use crate::error::{AppError, AppResult};
+use crate::services::FeatureService;
+
+/// Body of a 412 response to a write based on a stale version
+///
+/// Carries the current server version, so a client can merge its edit
+/// without fetching the resource again.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictResponse {
+    pub code: String,
+    pub description: String,
+    /// The current version on the server, as sent in the `ETag` header
+    pub server_version: i64,
+    /// The current resource on the server
+    pub server: serde_json::Value,
+    /// The fields the client sent that differ from the server's
+    pub conflicts: Vec<FieldConflict>,
+}
+
+/// A field whose value on the server differs from the client's
+#[derive(Debug, PartialEq, Serialize, JsonSchema)]
+pub struct FieldConflict {
+    /// JSON Pointer to the field
+    pub path: String,
+    /// The value on the server, null when the server doesn't have the field
+    pub server: serde_json::Value,
+    /// The value the client sent
+    pub client: serde_json::Value,
+}
+
+/// The fields in `client` whose values differ from those in `server`
+///
+/// Objects are compared field by field, so that only the properties a client
+/// changed are reported; any other value is compared as a whole. Fields the
+/// client didn't send are not conflicts.
+pub fn conflicting_fields(
+    server: &serde_json::Value,
+    client: &serde_json::Value,
+) -> Vec<FieldConflict> {
+    let mut conflicts = Vec::new();
+    collect_conflicts(String::new(), server, client, &mut conflicts);
+    conflicts
+}
+
+fn collect_conflicts(
+    path: String,
+    server: &serde_json::Value,
+    client: &serde_json::Value,
+    conflicts: &mut Vec<FieldConflict>,
+) {
+    match (server, client) {
+        (serde_json::Value::Object(server), serde_json::Value::Object(client)) => {
+            for (key, client_value) in client {
+                let field_path = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                let server_value = server.get(key).unwrap_or(&serde_json::Value::Null);
+                collect_conflicts(field_path, server_value, client_value, conflicts);
+            }
+        }
+        (server, client) if server != client => conflicts.push(FieldConflict {
+            path,
+            server: server.clone(),
+            client: client.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// The 412 response to a feature write based on a stale version
+///
+/// `client` holds the fields the client sent, in the feature's shape.
+pub async fn feature_conflict(
+    service: &FeatureService,
+    username: &str,
+    collection_id: &str,
+    feature_id: Uuid,
+    description: String,
+    client: &serde_json::Value,
+) -> AppResult<Response> {
+    let (feature, version, _) = service
+        .get_feature(username, collection_id, feature_id, None, None)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Feature not found: {}", feature_id)))?;
+    let server = serde_json::to_value(feature)?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ETAG, etag::create_etag_header(version)?);
+
+    let body = ConflictResponse {
+        code: "PreconditionFailed".to_string(),
+        description,
+        server_version: version,
+        conflicts: conflicting_fields(&server, client),
+        server,
+    };
+
+    Ok((StatusCode::PRECONDITION_FAILED, headers, Json(body)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_conflicting_fields() {
+        let server = json!({
+            "type": "Feature",
+            "geometry": {"type": "Point", "coordinates": [1.0, 2.0]},
+            "properties": {"name": "Server", "height": 10, "a/b": 1}
+        });
+        let client = json!({
+            "properties": {"name": "Client", "height": 10, "colour": "red", "a/b": 2}
+        });
+
+        assert_eq!(
+            conflicting_fields(&server, &client),
+            vec![
+                FieldConflict {
+                    path: "/properties/name".to_string(),
+                    server: json!("Server"),
+                    client: json!("Client"),
+                },
+                FieldConflict {
+                    path: "/properties/colour".to_string(),
+                    server: serde_json::Value::Null,
+                    client: json!("red"),
+                },
+                FieldConflict {
+                    path: "/properties/a~1b".to_string(),
+                    server: json!(1),
+                    client: json!(2),
+                },
+            ]
+        );
+
+        let client = json!({"geometry": {"type": "Point", "coordinates": [3.0, 2.0]}});
+        let conflicts = conflicting_fields(&server, &client);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].path, "/geometry/coordinates");
+
+        assert!(conflicting_fields(&server, &json!({})).is_empty());
+    }
+}
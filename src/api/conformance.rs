@@ -3,9 +3,12 @@ use aide::{
     axum::{ApiRouter, routing::get_with},
     transform::TransformOperation,
 };
-use axum::Json;
+use axum::{Extension, Json};
 use schemars::JsonSchema;
 use serde::Serialize;
+use std::sync::Arc;
+
+use crate::config::{Config, ModulesConfig};
 
 /// OGC API Conformance declaration
 #[derive(Debug, Serialize, JsonSchema, OperationIo)]
@@ -28,9 +31,19 @@ pub mod classes {
     pub const FEATURES_CORE: &str = "http://www.opengis.net/spec/ogcapi-features-1/1.0/conf/core";
     pub const FEATURES_GEOJSON: &str =
         "http://www.opengis.net/spec/ogcapi-features-1/1.0/conf/geojson";
+    pub const FEATURES_GMLSF0: &str =
+        "http://www.opengis.net/spec/ogcapi-features-1/1.0/conf/gmlsf0";
     pub const FEATURES_OAS30: &str = "http://www.opengis.net/spec/ogcapi-features-1/1.0/conf/oas30";
     pub const FEATURES_CRS: &str = "http://www.opengis.net/spec/ogcapi-features-2/1.0/conf/crs";
 
+    // OGC API Features Part 5 - Schemas
+    pub const FEATURES_SCHEMAS: &str =
+        "http://www.opengis.net/spec/ogcapi-features-5/1.0/conf/schemas";
+    pub const FEATURES_CORE_ROLES: &str =
+        "http://www.opengis.net/spec/ogcapi-features-5/1.0/conf/core-roles-features";
+    pub const FEATURES_RETURNABLES_RECEIVABLES: &str =
+        "http://www.opengis.net/spec/ogcapi-features-5/1.0/conf/returnables-and-receivables";
+
     // OGC API Features Part 4 - CRUD
     pub const FEATURES_CREATE_REPLACE_DELETE: &str =
         "http://www.opengis.net/spec/ogcapi-features-4/1.0/conf/create-replace-delete";
@@ -42,6 +55,12 @@ pub mod classes {
     // OGC API Tiles
     pub const TILES_CORE: &str = "http://www.opengis.net/spec/ogcapi-tiles-1/1.0/conf/core";
     pub const TILES_TILESET: &str = "http://www.opengis.net/spec/ogcapi-tiles-1/1.0/conf/tileset";
+    pub const TILES_TILESETS_LIST: &str =
+        "http://www.opengis.net/spec/ogcapi-tiles-1/1.0/conf/tilesets-list";
+    pub const TILES_DATASET_TILESETS: &str =
+        "http://www.opengis.net/spec/ogcapi-tiles-1/1.0/conf/dataset-tilesets";
+    pub const TILES_GEODATA_TILESETS: &str =
+        "http://www.opengis.net/spec/ogcapi-tiles-1/1.0/conf/geodata-tilesets";
 
     // OGC API Coverages
     pub const COVERAGES_CORE: &str = "http://www.opengis.net/spec/ogcapi-coverages-1/0.0/conf/core";
@@ -58,6 +77,18 @@ pub mod classes {
     pub const PROCESSES_DISMISS: &str =
         "http://www.opengis.net/spec/ogcapi-processes-1/1.0/conf/dismiss";
 
+    // OGC API Processes Part 2 - Deploy, Replace, Undeploy
+    pub const PROCESSES_DEPLOY_REPLACE_UNDEPLOY: &str =
+        "http://www.opengis.net/spec/ogcapi-processes-2/1.0/conf/deploy-replace-undeploy";
+    pub const PROCESSES_OGC_APPPKG: &str =
+        "http://www.opengis.net/spec/ogcapi-processes-2/1.0/conf/ogcapppkg";
+
+    // OGC API Records
+    pub const RECORDS_CORE: &str =
+        "http://www.opengis.net/spec/ogcapi-records-1/1.0/conf/record-core";
+    pub const RECORDS_API: &str =
+        "http://www.opengis.net/spec/ogcapi-records-1/1.0/conf/record-api";
+
     // STAC Core
     pub const STAC_CORE: &str = "https://api.stacspec.org/v1.0.0/core";
     pub const STAC_ITEM_SEARCH: &str = "https://api.stacspec.org/v1.0.0/item-search";
@@ -75,47 +106,100 @@ pub mod classes {
     pub const CQL2_JSON: &str = "http://www.opengis.net/spec/cql2/1.0/conf/cql2-json";
 }
 
-async fn get_conformance() -> Json<Conformance> {
-    let conformance = Conformance {
-        conforms_to: vec![
-            // OGC API Common
-            classes::COMMON_CORE.to_string(),
-            classes::COMMON_LANDING.to_string(),
-            classes::COMMON_JSON.to_string(),
-            classes::COMMON_OAS30.to_string(),
-            // OGC API Features
-            classes::FEATURES_CORE.to_string(),
-            classes::FEATURES_GEOJSON.to_string(),
-            classes::FEATURES_OAS30.to_string(),
-            classes::FEATURES_CRS.to_string(),
+/// Conformance classes of the API, leaving out those of disabled groups
+pub fn conformance_classes(modules: &ModulesConfig) -> Vec<&'static str> {
+    let mut conforms_to = vec![
+        // OGC API Common
+        classes::COMMON_CORE,
+        classes::COMMON_LANDING,
+        classes::COMMON_JSON,
+        classes::COMMON_OAS30,
+        // OGC API Features
+        classes::FEATURES_CORE,
+        classes::FEATURES_GEOJSON,
+        classes::FEATURES_GMLSF0,
+        classes::FEATURES_OAS30,
+        classes::FEATURES_CRS,
+        // OGC API Features Part 5
+        classes::FEATURES_SCHEMAS,
+        classes::FEATURES_CORE_ROLES,
+        classes::FEATURES_RETURNABLES_RECEIVABLES,
+    ];
+    if modules.transactions {
+        conforms_to.extend([
             // OGC API Features Part 4 - CRUD
-            classes::FEATURES_CREATE_REPLACE_DELETE.to_string(),
-            classes::FEATURES_UPDATE.to_string(),
-            classes::FEATURES_OPTIMISTIC_LOCKING_ETAGS.to_string(),
+            classes::FEATURES_CREATE_REPLACE_DELETE,
+            classes::FEATURES_UPDATE,
+            classes::FEATURES_OPTIMISTIC_LOCKING_ETAGS,
+        ]);
+    }
+    if modules.tiles {
+        conforms_to.extend([
             // OGC API Tiles
-            classes::TILES_CORE.to_string(),
-            classes::TILES_TILESET.to_string(),
+            classes::TILES_CORE,
+            classes::TILES_TILESET,
+            classes::TILES_TILESETS_LIST,
+            classes::TILES_DATASET_TILESETS,
+            classes::TILES_GEODATA_TILESETS,
+        ]);
+    }
+    if modules.coverages {
+        conforms_to.extend([
             // OGC API Coverages
-            classes::COVERAGES_CORE.to_string(),
-            classes::COVERAGES_GEOTIFF.to_string(),
+            classes::COVERAGES_CORE,
+            classes::COVERAGES_GEOTIFF,
+        ]);
+    }
+    if modules.processes {
+        conforms_to.extend([
             // OGC API Processes
-            classes::PROCESSES_CORE.to_string(),
-            classes::PROCESSES_JSON.to_string(),
-            classes::PROCESSES_OGC_PROCESS.to_string(),
-            classes::PROCESSES_JOB_LIST.to_string(),
-            classes::PROCESSES_DISMISS.to_string(),
+            classes::PROCESSES_CORE,
+            classes::PROCESSES_JSON,
+            classes::PROCESSES_OGC_PROCESS,
+            classes::PROCESSES_JOB_LIST,
+            classes::PROCESSES_DISMISS,
+            // OGC API Processes Part 2
+            classes::PROCESSES_DEPLOY_REPLACE_UNDEPLOY,
+            classes::PROCESSES_OGC_APPPKG,
+        ]);
+    }
+    if modules.records {
+        conforms_to.extend([
+            // OGC API Records
+            classes::RECORDS_CORE,
+            classes::RECORDS_API,
+        ]);
+    }
+    if modules.stac {
+        conforms_to.extend([
             // STAC Core
-            classes::STAC_CORE.to_string(),
-            classes::STAC_COLLECTIONS.to_string(),
-            classes::STAC_FEATURES.to_string(),
-            classes::STAC_ITEM_SEARCH.to_string(),
+            classes::STAC_CORE,
+            classes::STAC_COLLECTIONS,
+            classes::STAC_FEATURES,
+            classes::STAC_ITEM_SEARCH,
+        ]);
+    }
+    if modules.stac && modules.transactions {
+        conforms_to.extend([
             // STAC Transaction Extensions
-            classes::STAC_COLLECTION_TRANSACTION.to_string(),
-            classes::STAC_ITEM_TRANSACTION.to_string(),
-            // CQL2
-            classes::CQL2_TEXT.to_string(),
-            classes::CQL2_JSON.to_string(),
-        ],
+            classes::STAC_COLLECTION_TRANSACTION,
+            classes::STAC_ITEM_TRANSACTION,
+        ]);
+    }
+    conforms_to.extend([
+        // CQL2
+        classes::CQL2_TEXT,
+        classes::CQL2_JSON,
+    ]);
+    conforms_to
+}
+
+async fn get_conformance(Extension(config): Extension<Arc<Config>>) -> Json<Conformance> {
+    let conformance = Conformance {
+        conforms_to: conformance_classes(&config.modules)
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
     };
 
     Json(conformance)
@@ -123,7 +207,10 @@ async fn get_conformance() -> Json<Conformance> {
 
 fn get_conformance_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Conformance declaration")
-        .description("Returns the list of conformance classes that this API implements")
+        .description(
+            "Returns the list of conformance classes that this API implements; the classes of \
+             API groups disabled on this instance are left out",
+        )
         .tag("Core")
         .response_with::<200, Json<Conformance>, _>(|res| {
             res.description("Conformance declaration response")
@@ -136,3 +223,38 @@ pub fn routes() -> ApiRouter {
         get_with(get_conformance, get_conformance_docs),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conformance_classes() {
+        let all = conformance_classes(&ModulesConfig::default());
+        assert!(all.contains(&classes::TILES_CORE));
+        assert!(all.contains(&classes::FEATURES_CREATE_REPLACE_DELETE));
+
+        let features_only = conformance_classes(&ModulesConfig {
+            tiles: false,
+            coverages: false,
+            processes: false,
+            pointclouds: false,
+            records: false,
+            stac: true,
+            transactions: false,
+        });
+        assert!(features_only.contains(&classes::FEATURES_CORE));
+        assert!(features_only.contains(&classes::FEATURES_SCHEMAS));
+        assert!(features_only.contains(&classes::STAC_ITEM_SEARCH));
+        for class in [
+            classes::RECORDS_CORE,
+            classes::TILES_CORE,
+            classes::COVERAGES_CORE,
+            classes::PROCESSES_CORE,
+            classes::FEATURES_UPDATE,
+            classes::STAC_ITEM_TRANSACTION,
+        ] {
+            assert!(!features_only.contains(&class), "{}", class);
+        }
+    }
+}
@@ -6,7 +6,7 @@ use axum::{
     Json,
     body::Body,
     extract::{Extension, Query, State},
-    http::{HeaderMap, StatusCode, header},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
 use schemars::JsonSchema;
@@ -14,11 +14,13 @@ use serde::Serialize;
 use std::sync::Arc;
 
 use super::range_subset::CoverageSubsetParams;
+use crate::api::collections::ResolvedCollection;
+use crate::api::common::cache::{self, VersionParams};
 use crate::api::common::{Link, SpatialExtent, media_type, rel};
 use crate::auth::AuthenticatedUser;
 use crate::config::Config;
-use crate::error::{AppError, AppResult};
-use crate::services::{CollectionService, CoverageService};
+use crate::error::AppError;
+use crate::services::CoverageService;
 
 /// Coverage description (OGC API Coverages)
 #[derive(Debug, Serialize, JsonSchema)]
@@ -51,7 +53,26 @@ pub struct GeneralGrid {
     pub grid_type: String,
     pub srs_name: String,
     pub axis_labels: Vec<String>,
-    pub axis: Vec<GridAxis>,
+    pub axis: Vec<DomainAxis>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grid_limits: Option<GridLimits>,
+}
+
+/// Axis of a grid: regularly spaced, or with listed coordinates
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(untagged)]
+pub enum DomainAxis {
+    Regular(GridAxis),
+    Irregular(IrregularAxis),
+}
+
+impl DomainAxis {
+    pub fn label(&self) -> &str {
+        match self {
+            Self::Regular(axis) => &axis.axis_label,
+            Self::Irregular(axis) => &axis.axis_label,
+        }
+    }
 }
 
 /// Grid axis description
@@ -67,6 +88,40 @@ pub struct GridAxis {
     pub uom_label: String,
 }
 
+/// Grid axis with irregularly spaced coordinates, such as the dates of the
+/// items of a time series
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IrregularAxis {
+    #[serde(rename = "type")]
+    pub axis_type: String,
+    pub axis_label: String,
+    pub uom_label: String,
+    pub coordinate: Vec<String>,
+}
+
+/// Pixel index limits of a grid
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GridLimits {
+    #[serde(rename = "type")]
+    pub limits_type: String,
+    pub srs_name: String,
+    pub axis_labels: Vec<String>,
+    pub axis: Vec<IndexAxis>,
+}
+
+/// Index range of one grid axis
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexAxis {
+    #[serde(rename = "type")]
+    pub axis_type: String,
+    pub axis_label: String,
+    pub lower_bound: i64,
+    pub upper_bound: i64,
+}
+
 /// Range type (band/channel descriptions)
 #[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -88,6 +143,19 @@ pub struct RangeField {
     pub description: Option<String>,
     pub definition: String,
     pub uom: UnitOfMeasure,
+    /// Data type of the values, e.g. `UInt16` or `Float32`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<String>,
+    /// Values that mark missing data
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub nil_values: Vec<NilValue>,
+}
+
+/// A value reserved for missing data
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct NilValue {
+    pub reason: String,
+    pub value: f64,
 }
 
 /// Unit of measure
@@ -106,34 +174,18 @@ pub struct CoveragePath {
     pub collection_id: String,
 }
 
-/// Get coverage description
+/// Get coverage description, or its data when a data format or range
+/// subset is requested
 pub async fn get_coverage(
     Extension(config): Extension<Arc<Config>>,
-    Extension(user): Extension<AuthenticatedUser>,
-    State((service, collection_service)): State<(Arc<CoverageService>, Arc<CollectionService>)>,
-    path: CoveragePath,
+    State(service): State<Arc<CoverageService>>,
+    _path: CoveragePath,
+    ResolvedCollection(collection): ResolvedCollection,
+    Query(version): Query<VersionParams>,
+    Query(params): Query<CoverageSubsetParams>,
+    headers: HeaderMap,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = collection_service
-        .check_alias_redirect(&collection_id)
-        .await?
-    {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::LOCATION,
-            format!("{}/collections/{}/coverage", config.base_url, new_name)
-                .parse()
-                .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, headers).into_response());
-    }
-
-    let collection = service
-        .get_collection(&user.username, &collection_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Collection not found: {}", collection_id)))?;
-
+    let collection_id = collection.canonical_name.clone();
     // Verify this is a raster collection
     if collection.collection_type != "raster" {
         return Err(AppError::BadRequest(
@@ -141,9 +193,48 @@ pub async fn get_coverage(
         ));
     }
 
+    if params.requests_data() {
+        let variant = format!(
+            "tiff-{}-{}-{}-{:?}-{}-{}",
+            params.properties.as_deref().unwrap_or_default(),
+            params.datetime.as_deref().unwrap_or_default(),
+            params.subset.as_deref().unwrap_or_default(),
+            params.scale_factor,
+            params.scale_axes.as_deref().unwrap_or_default(),
+            params.scale_size.as_deref().unwrap_or_default()
+        );
+        let mut response_headers = match cache::validate(
+            &config.cache,
+            collection.version,
+            &variant,
+            version.v,
+            &headers,
+        ) {
+            Ok(response_headers) => response_headers,
+            Err(not_modified) => return Ok(*not_modified),
+        };
+        let data = service.get_coverage_data(collection.id, &params).await?;
+        response_headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(media_type::COG),
+        );
+        return Ok((StatusCode::OK, response_headers, Body::from(data)).into_response());
+    }
+
+    let response_headers = match cache::validate(
+        &config.cache,
+        collection.version,
+        "json",
+        version.v,
+        &headers,
+    ) {
+        Ok(response_headers) => response_headers,
+        Err(not_modified) => return Ok(*not_modified),
+    };
+
     let base_url = &config.base_url;
 
-    let coverage = CoverageDescription {
+    let mut coverage = CoverageDescription {
         id: collection.canonical_name.clone(),
         title: collection.title.clone(),
         description: collection.description.clone(),
@@ -174,6 +265,12 @@ pub async fn get_coverage(
                 "rangetype",
             )
             .with_type(media_type::JSON),
+            Link::new(
+                format!("{}/collections/{}/mosaic.json", base_url, collection_id),
+                rel::ALTERNATE,
+            )
+            .with_type(media_type::JSON)
+            .with_title("MosaicJSON"),
             Link::new(
                 format!("{}/collections/{}", base_url, collection_id),
                 rel::COLLECTION,
@@ -181,18 +278,57 @@ pub async fn get_coverage(
             .with_type(media_type::JSON),
         ],
     };
+    // Previews are rendered as tiles
+    if config.modules.tiles {
+        coverage.links.push(
+            Link::new(
+                format!(
+                    "{}/collections/{}/coverage/preview",
+                    base_url, collection_id
+                ),
+                "preview",
+            )
+            .with_type(media_type::PNG),
+        );
+    }
 
-    Ok(Json(coverage).into_response())
+    Ok((response_headers, Json(coverage)).into_response())
 }
 
 fn get_coverage_docs(op: TransformOperation) -> TransformOperation {
-    op.summary("Get coverage description")
-        .description("Returns the coverage description for a raster collection")
+    op.summary("Get coverage description or data")
+        .description(
+            "Returns the coverage description for a raster collection. With f=tiff or a \
+             properties (rangeSubset) list of band ids, names or indexes, returns the data \
+             of the newest item as a Cloud Optimized GeoTIFF with only the selected bands, \
+             in the requested order. Dated items form the time axis of the coverage: \
+             datetime selects the newest item at an instant or within an interval. subset \
+             selects a window in the native CRS (e.g. E(500000:510000) or Lat(40:50)) and \
+             scale-factor, scale-axes or scale-size scale it down; data larger than 256 MiB \
+             of samples is refused with 400, so large rasters need a subset or scaling. \
+             Coverage responses carry an ETag of the collection version for revalidation; \
+             with v set to the current collection version they are cached as immutable.",
+        )
         .tag("Coverages")
         .response_with::<200, Json<CoverageDescription>, _>(|res| {
             res.description("Coverage description")
         })
-        .response_with::<400, (), _>(|res| res.description("Not a raster collection"))
+        .response_with::<304, (), _>(|res| res.description("Coverage description not modified"))
+        .response_with::<400, (), _>(|res| {
+            res.description(
+                "Not a raster collection, an unknown range field or axis, or too much data",
+            )
+        })
+        .response_with::<404, (), _>(|res| {
+            res.description("Collection not found, or no data at the datetime")
+        })
+}
+
+fn head_coverage_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Check coverage description")
+        .description("Returns the headers of the coverage description without the body")
+        .tag("Coverages")
+        .response_with::<200, (), _>(|res| res.description("Coverage description headers"))
         .response_with::<404, (), _>(|res| res.description("Collection not found"))
 }
 
@@ -208,38 +344,36 @@ pub struct CoverageDomainsetPath {
 pub async fn get_domainset(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
-    State((service, collection_service)): State<(Arc<CoverageService>, Arc<CollectionService>)>,
-    path: CoverageDomainsetPath,
+    State(service): State<Arc<CoverageService>>,
+    _path: CoverageDomainsetPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    Query(version): Query<VersionParams>,
+    headers: HeaderMap,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = collection_service
-        .check_alias_redirect(&collection_id)
-        .await?
-    {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::LOCATION,
-            format!(
-                "{}/collections/{}/coverage/domainset",
-                config.base_url, new_name
-            )
-            .parse()
-            .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, headers).into_response());
-    }
-
+    let collection_id = collection.canonical_name.clone();
+    let response_headers = match cache::validate(
+        &config.cache,
+        collection.version,
+        "json",
+        version.v,
+        &headers,
+    ) {
+        Ok(response_headers) => response_headers,
+        Err(not_modified) => return Ok(*not_modified),
+    };
     let domain = service
         .get_domainset(&user.username, &collection_id)
         .await?;
 
-    Ok(Json(domain).into_response())
+    Ok((response_headers, Json(domain)).into_response())
 }
 
 fn get_domainset_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Get domain set")
-        .description("Returns the spatial/temporal extent and resolution of a coverage")
+        .description(
+            "Returns the spatial/temporal extent and resolution of a coverage. Collections \
+             with dated items get a time axis listing the dates of their items.",
+        )
         .tag("Coverages")
         .response_with::<200, Json<DomainSet>, _>(|res| res.description("Domain set description"))
 }
@@ -256,33 +390,28 @@ pub struct CoverageRangetypePath {
 pub async fn get_rangetype(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
-    State((service, collection_service)): State<(Arc<CoverageService>, Arc<CollectionService>)>,
-    path: CoverageRangetypePath,
+    State(service): State<Arc<CoverageService>>,
+    _path: CoverageRangetypePath,
+    ResolvedCollection(collection): ResolvedCollection,
+    Query(version): Query<VersionParams>,
+    headers: HeaderMap,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = collection_service
-        .check_alias_redirect(&collection_id)
-        .await?
-    {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::LOCATION,
-            format!(
-                "{}/collections/{}/coverage/rangetype",
-                config.base_url, new_name
-            )
-            .parse()
-            .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, headers).into_response());
-    }
-
+    let collection_id = collection.canonical_name.clone();
+    let response_headers = match cache::validate(
+        &config.cache,
+        collection.version,
+        "json",
+        version.v,
+        &headers,
+    ) {
+        Ok(response_headers) => response_headers,
+        Err(not_modified) => return Ok(*not_modified),
+    };
     let rangetype = service
         .get_rangetype(&user.username, &collection_id)
         .await?;
 
-    Ok(Json(rangetype).into_response())
+    Ok((response_headers, Json(rangetype)).into_response())
 }
 
 fn get_rangetype_docs(op: TransformOperation) -> TransformOperation {
@@ -292,47 +421,11 @@ fn get_rangetype_docs(op: TransformOperation) -> TransformOperation {
         .response_with::<200, Json<RangeType>, _>(|res| res.description("Range type description"))
 }
 
-/// Get coverage data with optional subsetting
-pub async fn get_coverage_data(
-    Extension(user): Extension<AuthenticatedUser>,
-    State(service): State<Arc<CoverageService>>,
-    path: CoveragePath,
-    Query(params): Query<CoverageSubsetParams>,
-) -> AppResult<Response> {
-    let collection_id = path.collection_id;
-    let data = service
-        .get_coverage_data(&user.username, &collection_id, &params)
-        .await?;
-
-    let content_type = match params.output_format() {
-        "image/tiff" | "image/geotiff" => "image/tiff",
-        "image/png" => "image/png",
-        _ => "image/tiff",
-    };
-
-    let mut headers = HeaderMap::new();
-    headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
-
-    Ok((StatusCode::OK, headers, Body::from(data)).into_response())
-}
-
-fn get_coverage_data_docs(op: TransformOperation) -> TransformOperation {
-    op.summary("Get coverage data")
-        .description("Returns the actual coverage data with optional spatial subsetting")
-        .tag("Coverages")
-        .response_with::<200, (), _>(|res| {
-            res.description("Coverage data (image/tiff or image/png)")
-        })
-}
-
-pub fn routes(
-    service: Arc<CoverageService>,
-    collection_service: Arc<CollectionService>,
-) -> ApiRouter {
+pub fn routes(service: Arc<CoverageService>) -> ApiRouter {
     ApiRouter::new()
         .api_route(
             "/collections/{collection_id}/coverage",
-            get_with(get_coverage, get_coverage_docs),
+            get_with(get_coverage, get_coverage_docs).head_with(get_coverage, head_coverage_docs),
         )
         .api_route(
             "/collections/{collection_id}/coverage/domainset",
@@ -342,5 +435,5 @@ pub fn routes(
             "/collections/{collection_id}/coverage/rangetype",
             get_with(get_rangetype, get_rangetype_docs),
         )
-        .with_state((service, collection_service))
+        .with_state(service)
 }
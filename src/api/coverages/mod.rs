@@ -1,4 +1,6 @@
 pub mod handlers;
+pub mod mosaic;
+pub mod preview;
 pub mod range_subset;
 
 pub use handlers::*;
@@ -0,0 +1,291 @@
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::api::collections::ResolvedCollection;
+use crate::api::common::cache::{self, VersionParams};
+use crate::config::Config;
+use crate::error::{AppError, AppResult};
+use crate::services::CoverageService;
+use crate::services::coverage_service::MosaicItem;
+
+/// Version of the MosaicJSON specification documents follow
+const MOSAICJSON_VERSION: &str = "0.0.3";
+
+/// Highest zoom level of a mosaic
+const MAX_ZOOM: u8 = 24;
+
+/// Most quadkeys in one mosaic, bounding the size of the document
+const MAX_QUADKEYS: usize = 100_000;
+
+/// Latitude limit of the Web Mercator projection
+const MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+/// MosaicJSON document of a raster collection
+///
+/// See <https://github.com/developmentseed/mosaicjson-spec>.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct MosaicJson {
+    pub mosaicjson: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub version: String,
+    pub minzoom: u8,
+    pub maxzoom: u8,
+    pub quadkey_zoom: u8,
+    /// West, south, east and north bounds in WGS 84
+    pub bounds: [f64; 4],
+    /// Longitude, latitude and zoom to center a map on
+    pub center: [f64; 3],
+    /// Asset hrefs of each quadkey, newest item first
+    pub tiles: BTreeMap<String, Vec<String>>,
+}
+
+/// Path parameters for the MosaicJSON endpoint
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/mosaic.json")]
+pub struct MosaicPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+/// Query parameters for the MosaicJSON endpoint
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MosaicParams {
+    /// Lowest zoom level tilers serve (default: 0)
+    pub minzoom: Option<u8>,
+    /// Highest zoom level tilers serve (default: 24)
+    pub maxzoom: Option<u8>,
+    /// Zoom level of the quadkeys (default: minzoom)
+    pub quadkey_zoom: Option<u8>,
+    /// Key of the item asset to mosaic (default: data)
+    pub asset: Option<String>,
+}
+
+impl MosaicParams {
+    /// Minimum, maximum and quadkey zoom levels
+    fn zooms(&self) -> AppResult<(u8, u8, u8)> {
+        let minzoom = self.minzoom.unwrap_or(0);
+        let maxzoom = self.maxzoom.unwrap_or(MAX_ZOOM);
+        let quadkey_zoom = self.quadkey_zoom.unwrap_or(minzoom);
+        if maxzoom > MAX_ZOOM || minzoom > maxzoom {
+            return Err(AppError::BadRequest(format!(
+                "minzoom and maxzoom must satisfy 0 <= minzoom <= maxzoom <= {}",
+                MAX_ZOOM
+            )));
+        }
+        if quadkey_zoom < minzoom || quadkey_zoom > maxzoom {
+            return Err(AppError::BadRequest(
+                "quadkey_zoom must be between minzoom and maxzoom".to_string(),
+            ));
+        }
+        Ok((minzoom, maxzoom, quadkey_zoom))
+    }
+}
+
+/// Get a MosaicJSON document of a raster collection
+pub async fn get_mosaic(
+    Extension(config): Extension<Arc<Config>>,
+    State(service): State<Arc<CoverageService>>,
+    _path: MosaicPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    Query(params): Query<MosaicParams>,
+    Query(version): Query<VersionParams>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    if collection.collection_type != "raster" {
+        return Err(AppError::BadRequest(
+            "MosaicJSON is only available for raster collections".to_string(),
+        ));
+    }
+    let (minzoom, maxzoom, quadkey_zoom) = params.zooms()?;
+    let asset = params.asset.as_deref().unwrap_or("data");
+
+    let variant = format!("mosaic-{}-{}-{}-{}", minzoom, maxzoom, quadkey_zoom, asset);
+    let response_headers = match cache::validate(
+        &config.cache,
+        collection.version,
+        &variant,
+        version.v,
+        &headers,
+    ) {
+        Ok(response_headers) => response_headers,
+        Err(not_modified) => return Ok(*not_modified),
+    };
+
+    let items = service.get_mosaic_items(collection.id, asset).await?;
+    let bounds = mosaic_bounds(&items);
+    let [west, south, east, north] = bounds;
+    let mosaic = MosaicJson {
+        mosaicjson: MOSAICJSON_VERSION.to_string(),
+        name: collection.canonical_name.clone(),
+        description: collection.description.clone(),
+        version: "1.0.0".to_string(),
+        minzoom,
+        maxzoom,
+        quadkey_zoom,
+        bounds,
+        center: [(west + east) / 2.0, (south + north) / 2.0, minzoom as f64],
+        tiles: mosaic_tiles(&items, quadkey_zoom)?,
+    };
+
+    Ok((response_headers, Json(mosaic)).into_response())
+}
+
+fn get_mosaic_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get MosaicJSON")
+        .description(
+            "Returns a MosaicJSON document of a raster collection, listing the hrefs of an \
+             asset of its items (by default `data`) for each quadkey their footprints cover, \
+             newest item first. Dynamic tilers such as TiTiler can serve the collection from \
+             it directly. Responses carry an ETag of the collection version.",
+        )
+        .tag("Coverages")
+        .response_with::<200, Json<MosaicJson>, _>(|res| res.description("MosaicJSON document"))
+        .response_with::<304, (), _>(|res| res.description("MosaicJSON not modified"))
+        .response_with::<400, (), _>(|res| {
+            res.description("Not a raster collection, invalid zoom levels or too many quadkeys")
+        })
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+/// Bounds covering every item, or the whole world without items
+fn mosaic_bounds(items: &[MosaicItem]) -> [f64; 4] {
+    if items.is_empty() {
+        return [-180.0, -MAX_LATITUDE, 180.0, MAX_LATITUDE];
+    }
+    items.iter().fold(
+        [f64::MAX, f64::MAX, f64::MIN, f64::MIN],
+        |[west, south, east, north], item| {
+            [
+                west.min(item.minx),
+                south.min(item.miny),
+                east.max(item.maxx),
+                north.max(item.maxy),
+            ]
+        },
+    )
+}
+
+/// Asset hrefs by the quadkeys at `zoom` each item's bounds cover
+fn mosaic_tiles(items: &[MosaicItem], zoom: u8) -> AppResult<BTreeMap<String, Vec<String>>> {
+    let mut tiles: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for item in items {
+        let (min_x, min_y) = tile_index(item.minx, item.maxy, zoom);
+        let (max_x, max_y) = tile_index(item.maxx, item.miny, zoom);
+        let count = (max_x - min_x + 1) as usize * (max_y - min_y + 1) as usize;
+        if count > MAX_QUADKEYS {
+            return Err(too_many_quadkeys());
+        }
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                tiles
+                    .entry(quadkey(zoom, x, y))
+                    .or_default()
+                    .push(item.href.clone());
+            }
+        }
+        if tiles.len() > MAX_QUADKEYS {
+            return Err(too_many_quadkeys());
+        }
+    }
+    Ok(tiles)
+}
+
+fn too_many_quadkeys() -> AppError {
+    AppError::BadRequest(format!(
+        "The mosaic would have more than {} quadkeys; lower quadkey_zoom",
+        MAX_QUADKEYS
+    ))
+}
+
+/// Web Mercator tile column and row containing a WGS 84 position
+fn tile_index(lon: f64, lat: f64, zoom: u8) -> (u32, u32) {
+    let n = 2f64.powi(zoom as i32);
+    let lat = lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    let x = (lon.clamp(-180.0, 180.0) + 180.0) / 360.0 * n;
+    let y = (1.0 - lat.tan().asinh() / PI) / 2.0 * n;
+    let last = n as u32 - 1;
+    ((x.floor() as u32).min(last), (y.floor() as u32).min(last))
+}
+
+/// Quadkey of a Web Mercator tile
+fn quadkey(zoom: u8, x: u32, y: u32) -> String {
+    (1..=zoom)
+        .rev()
+        .map(|level| {
+            let mask = 1 << (level - 1);
+            let digit = u8::from(x & mask != 0) + 2 * u8::from(y & mask != 0);
+            char::from(b'0' + digit)
+        })
+        .collect()
+}
+
+pub fn routes(service: Arc<CoverageService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/mosaic.json",
+            get_with(get_mosaic, get_mosaic_docs),
+        )
+        .with_state(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(href: &str, bounds: [f64; 4]) -> MosaicItem {
+        MosaicItem {
+            href: href.to_string(),
+            minx: bounds[0],
+            miny: bounds[1],
+            maxx: bounds[2],
+            maxy: bounds[3],
+        }
+    }
+
+    #[test]
+    fn test_quadkey() {
+        assert_eq!(quadkey(0, 0, 0), "");
+        assert_eq!(quadkey(3, 3, 5), "213");
+        assert_eq!(tile_index(-180.0, 90.0, 1), (0, 0));
+        assert_eq!(tile_index(180.0, -90.0, 1), (1, 1));
+        assert_eq!(tile_index(10.0, 50.0, 2), (2, 1));
+    }
+
+    #[test]
+    fn test_mosaic_tiles() {
+        let items = [
+            item("s3://bucket/new.tif", [10.0, 50.0, 11.0, 51.0]),
+            item("s3://bucket/old.tif", [-10.0, -10.0, 10.5, 50.5]),
+        ];
+        let tiles = mosaic_tiles(&items, 1).unwrap();
+        assert_eq!(tiles.len(), 4);
+        assert_eq!(tiles["1"], ["s3://bucket/new.tif", "s3://bucket/old.tif"]);
+        assert_eq!(tiles["2"], ["s3://bucket/old.tif"]);
+        assert_eq!(mosaic_bounds(&items), [-10.0, -10.0, 11.0, 51.0]);
+
+        assert!(mosaic_tiles(&items, 24).is_err());
+
+        let params = MosaicParams {
+            minzoom: Some(4),
+            maxzoom: Some(12),
+            quadkey_zoom: Some(2),
+            asset: None,
+        };
+        assert!(params.zooms().is_err());
+    }
+}
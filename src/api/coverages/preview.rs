@@ -0,0 +1,115 @@
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    extract::{Extension, Query, State},
+    http::{HeaderMap, Method},
+    response::Response,
+};
+use std::sync::Arc;
+
+use crate::api::collections::ResolvedCollection;
+use crate::api::tiles::vector::{preview_tile, tile_matrix_sets, zoom_range};
+use crate::api::tiles::{TileQueryParams, TileRequest, tile_response};
+use crate::auth::AuthenticatedUser;
+use crate::config::Config;
+use crate::error::AppError;
+use crate::services::{AnalyticsService, TileService};
+
+/// Path parameters for the coverage preview endpoint
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/coverage/preview")]
+pub struct CoveragePreviewPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+/// Render a preview image of a coverage
+///
+/// The preview is the smallest tile within the collection's zoom levels that
+/// covers its data, so it is styled, cached and validated like that tile.
+#[allow(clippy::too_many_arguments)]
+pub async fn get_coverage_preview(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Extension(analytics): Extension<Arc<AnalyticsService>>,
+    State(service): State<Arc<TileService>>,
+    _path: CoveragePreviewPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    Query(params): Query<TileQueryParams>,
+    method: Method,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    if collection.collection_type != "raster" {
+        return Err(AppError::BadRequest(
+            "Coverage previews only available for raster collections".to_string(),
+        ));
+    }
+
+    let bbox = service.data_bbox(&collection.as_collection()).await?;
+    let (z, x, y) = preview_tile(bbox, &zoom_range(collection.min_zoom, collection.max_zoom));
+    let request = TileRequest {
+        tile_matrix_set_id: tile_matrix_sets::WEB_MERCATOR_QUAD,
+        z,
+        y,
+        x,
+        params: &params,
+        method: &method,
+        headers: &headers,
+    };
+    tile_response(
+        &service,
+        &analytics,
+        &config.cache,
+        &user.username,
+        &collection,
+        request,
+    )
+    .await
+}
+
+fn get_coverage_preview_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get coverage preview")
+        .description(
+            "Returns a PNG, JPEG or WebP image of a raster coverage: the smallest Web \
+             Mercator tile within the collection's zoom levels covering its data. Accepts \
+             the styling parameters of raster tiles (bidx, expression, rescale, \
+             colormap_name and algorithm). Served previews are counted as tiles in the \
+             collection's analytics.",
+        )
+        .tag("Coverages")
+        .response_with::<200, (), _>(|res| res.description("Preview image (image/*)"))
+        .response_with::<304, (), _>(|res| res.description("Preview not modified"))
+        .response_with::<400, (), _>(|res| {
+            res.description("Not a raster collection or invalid styling parameters")
+        })
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+fn head_coverage_preview_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Check coverage preview")
+        .description(
+            "Returns the headers of a coverage preview without rendering it. \
+             Content-Length is included once the preview has been served.",
+        )
+        .tag("Coverages")
+        .response_with::<200, (), _>(|res| res.description("Preview headers"))
+        .response_with::<304, (), _>(|res| res.description("Preview not modified"))
+        .response_with::<400, (), _>(|res| {
+            res.description("Not a raster collection or invalid styling parameters")
+        })
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+/// Routes of coverage previews, rendered by the tile service
+pub fn routes(service: Arc<TileService>, analytics: Arc<AnalyticsService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/coverage/preview",
+            get_with(get_coverage_preview, get_coverage_preview_docs)
+                .head_with(get_coverage_preview, head_coverage_preview_docs),
+        )
+        .with_state(service)
+        .layer(Extension(analytics))
+}
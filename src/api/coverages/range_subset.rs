@@ -1,30 +1,64 @@
+use crate::api::edr::query::{DateTimeBounds, parse_datetime_bounds};
 use crate::error::{AppError, AppResult};
+use schemars::JsonSchema;
 use serde::Deserialize;
 
+/// Largest coverage returned at once, in bytes of uncompressed samples
+pub const MAX_COVERAGE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Labels of the horizontal and vertical axes of coverages, as in the domain
+/// set and accepted in subsets and scaling
+const X_AXES: [&str; 4] = ["Long", "Lon", "E", "x"];
+const Y_AXES: [&str; 3] = ["Lat", "N", "y"];
+
+/// Part of a raster to read and the size to return it at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoverageWindow {
+    /// Bounds of the window in the raster's CRS, snapped to its pixels:
+    /// minx, miny, maxx, maxy
+    pub bounds: [f64; 4],
+    /// Width and height of the returned coverage in pixels
+    pub size: [u32; 2],
+}
+
 /// Coverage subset parameters
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Default, Deserialize, JsonSchema)]
 pub struct CoverageSubsetParams {
     /// Subset by axis (e.g., "Lat(40:50),Long(-10:10)")
     pub subset: Option<String>,
 
-    /// Scale factor
+    /// Factor to scale the coverage down by along both axes (2 halves its
+    /// width and height)
+    #[serde(rename = "scale-factor", alias = "scale_factor")]
     pub scale_factor: Option<f64>,
 
-    /// Scale axes (e.g., "Lat(0.5),Long(0.5)")
+    /// Factors to scale down by per axis (e.g., "Lat(2),Long(2)")
+    #[serde(rename = "scale-axes", alias = "scale_axes")]
     pub scale_axes: Option<String>,
 
-    /// Scale size (e.g., "Lat(256),Long(256)")
+    /// Size in pixels per axis (e.g., "Lat(256),Long(256)")
+    #[serde(rename = "scale-size", alias = "scale_size")]
     pub scale_size: Option<String>,
 
     /// Output CRS
     pub crs: Option<String>,
 
     /// Subset CRS
+    #[serde(rename = "subset-crs", alias = "subset_crs")]
     pub subset_crs: Option<String>,
 
     /// Output format
     #[serde(rename = "f")]
     pub format: Option<String>,
+
+    /// Bands to return, in order, by field id (`band4`), name or 1-based
+    /// index (e.g. "band4,band3"); also accepted as `rangeSubset`
+    #[serde(alias = "rangeSubset")]
+    pub properties: Option<String>,
+
+    /// Time slice to return: the newest item at this instant or within this
+    /// interval (RFC 3339, `..` for open ends); the newest item when unset
+    pub datetime: Option<String>,
 }
 
 /// Parsed axis subset
@@ -92,10 +126,175 @@ impl CoverageSubsetParams {
         Ok(result)
     }
 
+    /// Window of a raster with the given bounds and pixel size selected by
+    /// `subset`, and the size `scale-factor`, `scale-axes` or `scale-size`
+    /// scales it to
+    pub fn window(&self, bounds: [f64; 4], resolution: [f64; 2]) -> AppResult<CoverageWindow> {
+        let mut window = bounds;
+        for subset in self.parse_subset()? {
+            let axis = spatial_axis(&subset.axis)?;
+            let (min, max) = (bounds[axis], bounds[axis + 2]);
+            let low = subset.low.unwrap_or(min);
+            let high = subset.high.unwrap_or(max);
+            if low > high {
+                return Err(AppError::BadRequest(format!(
+                    "Subset of {} has its low above its high",
+                    subset.axis
+                )));
+            }
+            if high < min || low > max {
+                return Err(AppError::BadRequest(format!(
+                    "Subset of {} is outside the coverage",
+                    subset.axis
+                )));
+            }
+            // Whole pixels covering the subset; a slice is one pixel
+            let resolution = resolution[axis];
+            let first = ((low.max(min) - min) / resolution).floor();
+            let last = ((high.min(max) - min) / resolution).ceil().max(first + 1.0);
+            window[axis] = min + first * resolution;
+            window[axis + 2] = (min + last * resolution).min(max);
+        }
+
+        let native = [0, 1].map(|axis| {
+            ((window[axis + 2] - window[axis]) / resolution[axis])
+                .round()
+                .max(1.0)
+        });
+        let mut size = native;
+        let scalings = [
+            self.scale_factor.is_some(),
+            self.scale_axes.is_some(),
+            self.scale_size.is_some(),
+        ];
+        if scalings.into_iter().filter(|given| *given).count() > 1 {
+            return Err(AppError::BadRequest(
+                "Only one of scale-factor, scale-axes and scale-size can be given".to_string(),
+            ));
+        }
+        if let Some(factor) = self.scale_factor {
+            size = native.map(|pixels| scaled(pixels, factor));
+        }
+        for (axis, factor) in parse_axis_values(self.scale_axes.as_deref())? {
+            size[axis] = scaled(native[axis], factor);
+        }
+        for (axis, pixels) in parse_axis_values(self.scale_size.as_deref())? {
+            size[axis] = pixels.round();
+        }
+        if size
+            .iter()
+            .any(|pixels| !pixels.is_finite() || *pixels < 1.0)
+        {
+            return Err(AppError::BadRequest(
+                "Scaling must leave at least one pixel per axis".to_string(),
+            ));
+        }
+        if size.iter().any(|pixels| *pixels > u32::MAX as f64) {
+            return Err(AppError::BadRequest(
+                "Scaling gives too many pixels".to_string(),
+            ));
+        }
+
+        Ok(CoverageWindow {
+            bounds: window,
+            size: size.map(|pixels| pixels as u32),
+        })
+    }
+
     /// Get output format with default
     pub fn output_format(&self) -> &str {
         self.format.as_deref().unwrap_or("image/tiff")
     }
+
+    /// Whether the request is for coverage data rather than its description
+    pub fn requests_data(&self) -> bool {
+        self.properties.is_some()
+            || self.datetime.is_some()
+            || self.subset.is_some()
+            || self.scale_factor.is_some()
+            || self.scale_axes.is_some()
+            || self.scale_size.is_some()
+            || self
+                .format
+                .as_deref()
+                .is_some_and(|f| f != "json" && f != "application/json")
+    }
+
+    /// Inclusive bounds of the requested time slice
+    pub fn datetime_bounds(&self) -> AppResult<DateTimeBounds> {
+        parse_datetime_bounds(self.datetime.as_deref())
+    }
+
+    /// 1-based indexes of the bands selected by `properties`, in the
+    /// requested order, out of the bands with the given names; every band
+    /// when no selection is given
+    pub fn selected_bands(&self, band_names: &[String]) -> AppResult<Vec<usize>> {
+        let Some(properties) = &self.properties else {
+            return Ok((1..=band_names.len()).collect());
+        };
+
+        properties
+            .split(',')
+            .map(str::trim)
+            .map(|field| {
+                let index = field
+                    .strip_prefix("band")
+                    .unwrap_or(field)
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|index| (1..=band_names.len()).contains(index))
+                    .or_else(|| {
+                        band_names
+                            .iter()
+                            .position(|name| name.eq_ignore_ascii_case(field))
+                            .map(|position| position + 1)
+                    });
+                index.ok_or_else(|| AppError::BadRequest(format!("Unknown range field: {}", field)))
+            })
+            .collect()
+    }
+}
+
+/// Index of a spatial axis in bounds, 0 for x and 1 for y
+fn spatial_axis(label: &str) -> AppResult<usize> {
+    let is = |labels: &[&str]| labels.iter().any(|l| l.eq_ignore_ascii_case(label));
+    if is(&X_AXES) {
+        Ok(0)
+    } else if is(&Y_AXES) {
+        Ok(1)
+    } else {
+        Err(AppError::BadRequest(format!("Unknown axis: {}", label)))
+    }
+}
+
+/// Pixels along an axis scaled down by a factor
+fn scaled(pixels: f64, factor: f64) -> f64 {
+    if factor > 0.0 {
+        (pixels / factor).round()
+    } else {
+        f64::NAN
+    }
+}
+
+/// Values per spatial axis of a list like "Lat(256),Long(256)"
+fn parse_axis_values(list: Option<&str>) -> AppResult<Vec<(usize, f64)>> {
+    let Some(list) = list else {
+        return Ok(Vec::new());
+    };
+    list.split(',')
+        .map(|part| {
+            let part = part.trim();
+            let (axis, value) = part
+                .strip_suffix(')')
+                .and_then(|part| part.split_once('('))
+                .ok_or_else(|| AppError::BadRequest(format!("Invalid axis value: {}", part)))?;
+            let value: f64 = value
+                .trim()
+                .parse()
+                .map_err(|_| AppError::BadRequest(format!("Invalid number: {}", value)))?;
+            Ok((spatial_axis(axis.trim())?, value))
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -128,4 +327,59 @@ mod tests {
         assert_eq!(subsets[1].low, Some(-10.0));
         assert_eq!(subsets[1].high, None);
     }
+
+    #[test]
+    fn test_selected_bands() {
+        let names = ["Blue", "Green", "Red", "NIR"].map(String::from);
+        let mut params = CoverageSubsetParams::default();
+        assert_eq!(params.selected_bands(&names).unwrap(), [1, 2, 3, 4]);
+        assert!(!params.requests_data());
+
+        params.properties = Some("band4, red,2".to_string());
+        assert_eq!(params.selected_bands(&names).unwrap(), [4, 3, 2]);
+        assert!(params.requests_data());
+
+        params.properties = Some("band5".to_string());
+        assert!(params.selected_bands(&names).is_err());
+        params.properties = Some("SWIR".to_string());
+        assert!(params.selected_bands(&names).is_err());
+    }
+
+    #[test]
+    fn test_window() {
+        // 1000 x 500 pixels of 10 m
+        let bounds = [0.0, 0.0, 10_000.0, 5_000.0];
+        let resolution = [10.0, 10.0];
+        let mut params = CoverageSubsetParams::default();
+        let window = params.window(bounds, resolution).unwrap();
+        assert_eq!(window.bounds, bounds);
+        assert_eq!(window.size, [1000, 500]);
+
+        // Subsets snap outwards to whole pixels
+        params.subset = Some("E(1005:2000),N(:95)".to_string());
+        let window = params.window(bounds, resolution).unwrap();
+        assert_eq!(window.bounds, [1000.0, 0.0, 2000.0, 100.0]);
+        assert_eq!(window.size, [100, 10]);
+
+        params.scale_factor = Some(2.0);
+        assert_eq!(params.window(bounds, resolution).unwrap().size, [50, 5]);
+        params.scale_factor = None;
+        params.scale_size = Some("E(20)".to_string());
+        assert_eq!(params.window(bounds, resolution).unwrap().size, [20, 10]);
+        params.scale_axes = Some("N(5)".to_string());
+        assert!(params.window(bounds, resolution).is_err());
+        params.scale_size = None;
+        assert_eq!(params.window(bounds, resolution).unwrap().size, [100, 2]);
+        params.scale_axes = None;
+
+        params.subset = Some("E(20000:30000)".to_string());
+        assert!(params.window(bounds, resolution).is_err());
+        params.subset = Some("Lat(10:20)".to_string());
+        assert!(params.window(bounds, resolution).is_ok());
+        params.subset = Some("time(10:20)".to_string());
+        assert!(params.window(bounds, resolution).is_err());
+        params.subset = None;
+        params.scale_factor = Some(0.0);
+        assert!(params.window(bounds, resolution).is_err());
+    }
 }
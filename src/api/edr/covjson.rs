@@ -0,0 +1,382 @@
+/// CoverageJSON responses for EDR queries
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::query::AreaGrid;
+use super::sample::SampledItem;
+use crate::api::common::crs;
+use crate::error::{AppError, AppResult};
+
+/// CoverageJSON coverage
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Coverage {
+    #[serde(rename = "type")]
+    pub coverage_type: String,
+    pub domain: Domain,
+    pub parameters: BTreeMap<String, Parameter>,
+    pub ranges: BTreeMap<String, NdArray>,
+}
+
+/// Coverage domain
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Domain {
+    #[serde(rename = "type")]
+    pub object_type: String,
+    /// `Point`, `PointSeries` or `Grid`
+    pub domain_type: String,
+    pub axes: Axes,
+    pub referencing: Vec<Referencing>,
+}
+
+/// Domain axes
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Axes {
+    pub x: Axis<f64>,
+    pub y: Axis<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub t: Option<Axis<String>>,
+}
+
+/// Axis coordinate values
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Axis<T> {
+    pub values: Vec<T>,
+}
+
+/// Reference system of some of the domain axes
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Referencing {
+    pub coordinates: Vec<String>,
+    pub system: ReferenceSystem,
+}
+
+/// Spatial or temporal reference system
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ReferenceSystem {
+    #[serde(rename = "type")]
+    pub system_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calendar: Option<String>,
+}
+
+/// Description of a range parameter (band)
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameter {
+    #[serde(rename = "type")]
+    pub parameter_type: String,
+    pub observed_property: ObservedProperty,
+}
+
+/// Observed property of a parameter
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ObservedProperty {
+    pub label: BTreeMap<String, String>,
+}
+
+/// Range values of one parameter
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NdArray {
+    #[serde(rename = "type")]
+    pub array_type: String,
+    pub data_type: String,
+    pub axis_names: Vec<String>,
+    pub shape: Vec<usize>,
+    pub values: Vec<Option<f64>>,
+}
+
+/// Sampled values merged into time steps
+#[derive(Debug, PartialEq)]
+pub struct Layers {
+    /// Time of each step, or `None` for a single undated step
+    pub times: Option<Vec<DateTime<Utc>>>,
+    /// Band number (1-based) and its values, in `[step][point]` order
+    pub bands: Vec<(usize, Vec<Option<f64>>)>,
+}
+
+/// Merge sampled items into time steps
+///
+/// Dated items form a time series with one step per distinct datetime;
+/// undated items are then ignored. Without dated items, all items form a
+/// single step. Within a step, the first item with data at a point wins.
+pub fn merge_samples(
+    samples: &[SampledItem],
+    point_count: usize,
+    bands: Option<&[usize]>,
+) -> AppResult<Layers> {
+    let band_count = samples.iter().map(|s| s.values.len()).max().unwrap_or(0);
+    let bands: Vec<usize> = match bands {
+        Some(bands) => {
+            if let Some(band) = bands.iter().find(|&&b| b > band_count) {
+                return Err(AppError::BadRequest(format!(
+                    "Unknown parameter: band{}",
+                    band
+                )));
+            }
+            bands.to_vec()
+        }
+        None => (1..=band_count).collect(),
+    };
+
+    let mut dated: Vec<&SampledItem> = samples.iter().filter(|s| s.datetime.is_some()).collect();
+    dated.sort_by_key(|s| s.datetime);
+    let (times, steps): (Option<Vec<DateTime<Utc>>>, Vec<Vec<&SampledItem>>) = if dated.is_empty() {
+        (None, vec![samples.iter().collect()])
+    } else {
+        let mut times: Vec<DateTime<Utc>> = Vec::new();
+        let mut steps: Vec<Vec<&SampledItem>> = Vec::new();
+        for sample in dated {
+            let datetime = sample.datetime.expect("dated items have a datetime");
+            if times.last() != Some(&datetime) {
+                times.push(datetime);
+                steps.push(Vec::new());
+            }
+            steps.last_mut().expect("a step was pushed").push(sample);
+        }
+        (Some(times), steps)
+    };
+
+    let bands = bands
+        .into_iter()
+        .map(|band| {
+            let values = steps
+                .iter()
+                .flat_map(|step| {
+                    (0..point_count).map(move |point| {
+                        step.iter().find_map(|sample| {
+                            sample
+                                .values
+                                .get(band - 1)
+                                .and_then(|values| values.get(point).copied().flatten())
+                        })
+                    })
+                })
+                .collect();
+            (band, values)
+        })
+        .collect();
+
+    Ok(Layers { times, bands })
+}
+
+fn referencing(with_time: bool) -> Vec<Referencing> {
+    let mut referencing = vec![Referencing {
+        coordinates: vec!["x".to_string(), "y".to_string()],
+        system: ReferenceSystem {
+            system_type: "GeographicCRS".to_string(),
+            id: Some(crs::WGS84.to_string()),
+            calendar: None,
+        },
+    }];
+    if with_time {
+        referencing.push(Referencing {
+            coordinates: vec!["t".to_string()],
+            system: ReferenceSystem {
+                system_type: "TemporalRS".to_string(),
+                id: None,
+                calendar: Some("Gregorian".to_string()),
+            },
+        });
+    }
+    referencing
+}
+
+fn coverage(
+    domain_type: &str,
+    axes: Axes,
+    layers: Layers,
+    axis_names: &[&str],
+    shape: Vec<usize>,
+) -> Coverage {
+    let mut parameters = BTreeMap::new();
+    let mut ranges = BTreeMap::new();
+    for (band, values) in layers.bands {
+        let name = format!("band{}", band);
+        parameters.insert(
+            name.clone(),
+            Parameter {
+                parameter_type: "Parameter".to_string(),
+                observed_property: ObservedProperty {
+                    label: BTreeMap::from([("en".to_string(), format!("Band {}", band))]),
+                },
+            },
+        );
+        ranges.insert(
+            name,
+            NdArray {
+                array_type: "NdArray".to_string(),
+                data_type: "float".to_string(),
+                axis_names: axis_names.iter().map(|n| n.to_string()).collect(),
+                shape: shape.clone(),
+                values,
+            },
+        );
+    }
+
+    Coverage {
+        coverage_type: "Coverage".to_string(),
+        domain: Domain {
+            object_type: "Domain".to_string(),
+            domain_type: domain_type.to_string(),
+            referencing: referencing(axes.t.is_some()),
+            axes,
+        },
+        parameters,
+        ranges,
+    }
+}
+
+fn time_axis(times: &Option<Vec<DateTime<Utc>>>) -> Option<Axis<String>> {
+    times.as_ref().map(|times| Axis {
+        values: times.iter().map(|t| t.to_rfc3339()).collect(),
+    })
+}
+
+/// Coverage of a position query: a `Point`, or a `PointSeries` over time
+pub fn position_coverage([x, y]: [f64; 2], layers: Layers) -> Coverage {
+    let axes = Axes {
+        x: Axis { values: vec![x] },
+        y: Axis { values: vec![y] },
+        t: time_axis(&layers.times),
+    };
+    match layers.times.as_ref().map(Vec::len) {
+        Some(steps) => coverage("PointSeries", axes, layers, &["t"], vec![steps]),
+        None => coverage("Point", axes, layers, &[], Vec::new()),
+    }
+}
+
+/// Coverage of an area query: a `Grid`, with a time axis when items are dated
+pub fn area_coverage(grid: &AreaGrid, layers: Layers) -> Coverage {
+    let axes = Axes {
+        x: Axis {
+            values: grid.x_values(),
+        },
+        y: Axis {
+            values: grid.y_values(),
+        },
+        t: time_axis(&layers.times),
+    };
+    match layers.times.as_ref().map(Vec::len) {
+        Some(steps) => coverage(
+            "Grid",
+            axes,
+            layers,
+            &["t", "y", "x"],
+            vec![steps, grid.height, grid.width],
+        ),
+        None => coverage(
+            "Grid",
+            axes,
+            layers,
+            &["y", "x"],
+            vec![grid.height, grid.width],
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(datetime: Option<&str>, values: Vec<Vec<Option<f64>>>) -> SampledItem {
+        SampledItem {
+            datetime: datetime.map(|dt| dt.parse().unwrap()),
+            values,
+        }
+    }
+
+    #[test]
+    fn test_merge_undated_samples() {
+        let samples = [
+            sample(None, vec![vec![None, Some(2.0)]]),
+            sample(None, vec![vec![Some(3.0), Some(4.0)]]),
+        ];
+        let layers = merge_samples(&samples, 2, None).unwrap();
+        assert_eq!(layers.times, None);
+        assert_eq!(layers.bands, vec![(1, vec![Some(3.0), Some(2.0)])]);
+    }
+
+    #[test]
+    fn test_merge_time_series() {
+        let samples = [
+            sample(
+                Some("2024-02-01T00:00:00Z"),
+                vec![vec![Some(2.0)], vec![Some(20.0)]],
+            ),
+            sample(None, vec![vec![Some(9.0)]]),
+            sample(
+                Some("2024-01-01T00:00:00Z"),
+                vec![vec![Some(1.0)], vec![None]],
+            ),
+        ];
+        let layers = merge_samples(&samples, 1, Some(&[2])).unwrap();
+        assert_eq!(layers.times.as_ref().unwrap().len(), 2);
+        assert_eq!(layers.bands, vec![(2, vec![None, Some(20.0)])]);
+
+        assert!(merge_samples(&samples, 1, Some(&[3])).is_err());
+    }
+
+    #[test]
+    fn test_position_coverage() {
+        let samples = [
+            sample(Some("2024-01-01T00:00:00Z"), vec![vec![Some(1.0)]]),
+            sample(Some("2024-02-01T00:00:00Z"), vec![vec![Some(2.0)]]),
+        ];
+        let layers = merge_samples(&samples, 1, None).unwrap();
+        let json = serde_json::to_value(position_coverage([18.0, 59.0], layers)).unwrap();
+
+        assert_eq!(json["type"], "Coverage");
+        assert_eq!(json["domain"]["type"], "Domain");
+        assert_eq!(json["domain"]["domainType"], "PointSeries");
+        assert_eq!(
+            json["domain"]["axes"]["x"]["values"],
+            serde_json::json!([18.0])
+        );
+        assert_eq!(
+            json["domain"]["axes"]["t"]["values"][0],
+            "2024-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            json["ranges"]["band1"]["axisNames"],
+            serde_json::json!(["t"])
+        );
+        assert_eq!(json["ranges"]["band1"]["shape"], serde_json::json!([2]));
+        assert_eq!(
+            json["ranges"]["band1"]["values"],
+            serde_json::json!([1.0, 2.0])
+        );
+        assert_eq!(
+            json["parameters"]["band1"]["observedProperty"]["label"]["en"],
+            "Band 1"
+        );
+    }
+
+    #[test]
+    fn test_area_coverage() {
+        let grid = AreaGrid {
+            bbox: [0.0, 0.0, 2.0, 1.0],
+            width: 2,
+            height: 1,
+        };
+        let layers = merge_samples(&[sample(None, vec![vec![Some(1.0), None]])], 2, None).unwrap();
+        let json = serde_json::to_value(area_coverage(&grid, layers)).unwrap();
+
+        assert_eq!(json["domain"]["domainType"], "Grid");
+        assert!(json["domain"]["axes"].get("t").is_none());
+        assert_eq!(
+            json["domain"]["axes"]["x"]["values"],
+            serde_json::json!([0.5, 1.5])
+        );
+        assert_eq!(json["ranges"]["band1"]["shape"], serde_json::json!([1, 2]));
+        assert_eq!(
+            json["ranges"]["band1"]["values"],
+            serde_json::json!([1.0, null])
+        );
+    }
+}
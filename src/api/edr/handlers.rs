@@ -0,0 +1,177 @@
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use super::covjson::{Coverage, area_coverage, merge_samples, position_coverage};
+use super::query::{AreaGrid, Coords, EdrQueryParams, parse_coords};
+use crate::api::collections::ResolvedCollection;
+use crate::api::common::media_type;
+use crate::error::{AppError, AppResult};
+use crate::services::CoverageService;
+
+/// Parse the required `coords` parameter of a raster collection query
+fn query_coords(collection_type: &str, params: &EdrQueryParams) -> AppResult<Coords> {
+    if collection_type != "raster" {
+        return Err(AppError::BadRequest(
+            "EDR queries are only available for raster collections".to_string(),
+        ));
+    }
+    let coords = params
+        .coords
+        .as_deref()
+        .ok_or_else(|| AppError::BadRequest("coords is required".to_string()))?;
+    parse_coords(coords)
+}
+
+fn coverage_response(coverage: Coverage) -> Response {
+    (
+        [(header::CONTENT_TYPE, media_type::COVERAGE_JSON)],
+        Json(coverage),
+    )
+        .into_response()
+}
+
+/// Path parameters for the position query
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/position")]
+pub struct PositionPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+/// Sample raster values at a point
+pub async fn get_position(
+    State(service): State<Arc<CoverageService>>,
+    _path: PositionPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    Query(params): Query<EdrQueryParams>,
+) -> AppResult<Response> {
+    let coords = query_coords(&collection.collection_type, &params)?;
+    let Coords::Point(point) = coords else {
+        return Err(AppError::BadRequest(
+            "Position queries require a POINT".to_string(),
+        ));
+    };
+    let bands = params.bands()?;
+
+    let samples = service
+        .sample_items(
+            collection.id,
+            &coords,
+            params.datetime_bounds()?,
+            vec![point],
+        )
+        .await?;
+    let layers = merge_samples(&samples, 1, bands.as_deref())?;
+
+    Ok(coverage_response(position_coverage(point, layers)))
+}
+
+fn get_position_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Query values at a position")
+        .description(
+            "Returns the band values of the raster items at a point as CoverageJSON (OGC API - \
+             EDR position query). Dated items form a time series; otherwise the newest item \
+             with data at the point is used.",
+        )
+        .tag("Coverages")
+        .response_with::<200, Json<Coverage>, _>(|res| {
+            res.description("Sampled values (application/prs.coverage+json)")
+        })
+        .response_with::<400, (), _>(|res| {
+            res.description(
+                "Invalid coords, datetime or parameter-name, or not a raster collection",
+            )
+        })
+        .response_with::<404, (), _>(|res| {
+            res.description("Collection not found or no data at the position")
+        })
+}
+
+/// Path parameters for the area query
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/area")]
+pub struct AreaPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+/// Sample raster values within a polygon
+pub async fn get_area(
+    State(service): State<Arc<CoverageService>>,
+    _path: AreaPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    Query(params): Query<EdrQueryParams>,
+) -> AppResult<Response> {
+    let coords = query_coords(&collection.collection_type, &params)?;
+    if !matches!(coords, Coords::Polygon(_)) {
+        return Err(AppError::BadRequest(
+            "Area queries require a POLYGON".to_string(),
+        ));
+    }
+    let bands = params.bands()?;
+
+    let grid = AreaGrid::for_bbox(coords.bbox());
+    let points = grid.points();
+    let inside: Vec<bool> = points.iter().map(|&p| coords.contains(p)).collect();
+
+    let samples = service
+        .sample_items(collection.id, &coords, params.datetime_bounds()?, points)
+        .await?;
+    let mut layers = merge_samples(&samples, inside.len(), bands.as_deref())?;
+
+    // Cells outside the polygon have no value
+    for (_, values) in &mut layers.bands {
+        for step in values.chunks_mut(inside.len()) {
+            for (value, inside) in step.iter_mut().zip(&inside) {
+                if !inside {
+                    *value = None;
+                }
+            }
+        }
+    }
+
+    Ok(coverage_response(area_coverage(&grid, layers)))
+}
+
+fn get_area_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Query values within an area")
+        .description(
+            "Returns the band values of the raster items within a polygon as a CoverageJSON grid \
+             (OGC API - EDR area query), sampled on a regular CRS84 grid of at most 256 cells \
+             per side. Dated items form a time series.",
+        )
+        .tag("Coverages")
+        .response_with::<200, Json<Coverage>, _>(|res| {
+            res.description("Sampled values (application/prs.coverage+json)")
+        })
+        .response_with::<400, (), _>(|res| {
+            res.description(
+                "Invalid coords, datetime or parameter-name, or not a raster collection",
+            )
+        })
+        .response_with::<404, (), _>(|res| {
+            res.description("Collection not found or no data in the area")
+        })
+}
+
+pub fn routes(service: Arc<CoverageService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/position",
+            get_with(get_position, get_position_docs),
+        )
+        .api_route(
+            "/collections/{collection_id}/area",
+            get_with(get_area, get_area_docs),
+        )
+        .with_state(service)
+}
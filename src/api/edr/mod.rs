@@ -0,0 +1,6 @@
+pub mod covjson;
+pub mod handlers;
+pub mod query;
+pub mod sample;
+
+pub use handlers::*;
@@ -0,0 +1,326 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::error::{AppError, AppResult};
+
+/// Cells along the longer side of an area query grid
+pub const AREA_GRID_SIZE: usize = 256;
+
+/// Inclusive datetime bounds, `None` for an open end
+pub type DateTimeBounds = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+/// Query parameters shared by EDR position and area queries
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct EdrQueryParams {
+    /// Location to sample as WKT in CRS84: `POINT(lon lat)` for position
+    /// queries, `POLYGON((lon lat, ...))` for area queries
+    pub coords: Option<String>,
+    /// Only sample items at this instant or within this interval (RFC 3339, `..` for open ends)
+    pub datetime: Option<String>,
+    /// Comma separated bands to return, e.g. `band1,band3` (default: all)
+    #[serde(rename = "parameter-name")]
+    pub parameter_name: Option<String>,
+}
+
+impl EdrQueryParams {
+    /// The requested band numbers (1-based), or `None` for all bands
+    pub fn bands(&self) -> AppResult<Option<Vec<usize>>> {
+        let Some(names) = &self.parameter_name else {
+            return Ok(None);
+        };
+        names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                name.strip_prefix("band")
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| AppError::BadRequest(format!("Unknown parameter: {}", name)))
+            })
+            .collect::<AppResult<Vec<_>>>()
+            .map(Some)
+    }
+
+    /// Inclusive datetime bounds
+    pub fn datetime_bounds(&self) -> AppResult<DateTimeBounds> {
+        parse_datetime_bounds(self.datetime.as_deref())
+    }
+}
+
+/// Inclusive bounds of an RFC 3339 instant or `start/end` interval, with
+/// `..` or an empty string for an open end
+pub fn parse_datetime_bounds(datetime: Option<&str>) -> AppResult<DateTimeBounds> {
+    let Some(datetime) = datetime else {
+        return Ok((None, None));
+    };
+    let parse = |instant: &str| -> AppResult<Option<DateTime<Utc>>> {
+        if instant.is_empty() || instant == ".." {
+            return Ok(None);
+        }
+        DateTime::parse_from_rfc3339(instant)
+            .map(|dt| Some(dt.with_timezone(&Utc)))
+            .map_err(|_| AppError::BadRequest(format!("Invalid datetime: {}", instant)))
+    };
+
+    match datetime.split_once('/') {
+        Some((start, end)) => Ok((parse(start)?, parse(end)?)),
+        None => {
+            let instant = parse(datetime)?;
+            Ok((instant, instant))
+        }
+    }
+}
+
+/// A query location in CRS84
+#[derive(Debug, Clone, PartialEq)]
+pub enum Coords {
+    Point([f64; 2]),
+    /// Exterior ring followed by any holes, each closed
+    Polygon(Vec<Vec<[f64; 2]>>),
+}
+
+impl Coords {
+    /// WKT of the location, for spatial filtering in the database
+    pub fn to_wkt(&self) -> String {
+        let ring = |ring: &[[f64; 2]]| {
+            let points: Vec<String> = ring.iter().map(|[x, y]| format!("{} {}", x, y)).collect();
+            format!("({})", points.join(", "))
+        };
+        match self {
+            Coords::Point([x, y]) => format!("POINT({} {})", x, y),
+            Coords::Polygon(rings) => {
+                let rings: Vec<String> = rings.iter().map(|r| ring(r)).collect();
+                format!("POLYGON({})", rings.join(", "))
+            }
+        }
+    }
+
+    /// Bounding box as [minx, miny, maxx, maxy]
+    pub fn bbox(&self) -> [f64; 4] {
+        let points: &[[f64; 2]] = match self {
+            Coords::Point(point) => std::slice::from_ref(point),
+            Coords::Polygon(rings) => &rings[0],
+        };
+        points.iter().fold(
+            [f64::MAX, f64::MAX, f64::MIN, f64::MIN],
+            |[minx, miny, maxx, maxy], [x, y]| {
+                [minx.min(*x), miny.min(*y), maxx.max(*x), maxy.max(*y)]
+            },
+        )
+    }
+
+    /// Whether a point lies inside the polygon (even-odd rule, so holes are excluded)
+    pub fn contains(&self, [px, py]: [f64; 2]) -> bool {
+        let Coords::Polygon(rings) = self else {
+            return false;
+        };
+        let mut inside = false;
+        for ring in rings {
+            for edge in ring.windows(2) {
+                let ([x1, y1], [x2, y2]) = (edge[0], edge[1]);
+                if (y1 > py) != (y2 > py) && px < x1 + (py - y1) / (y2 - y1) * (x2 - x1) {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+}
+
+/// Parse a WKT `POINT` or `POLYGON` in CRS84
+pub fn parse_coords(wkt: &str) -> AppResult<Coords> {
+    let invalid = || AppError::BadRequest(format!("Invalid coords: {}", wkt));
+
+    let (keyword, body) = wkt.trim().split_once('(').ok_or_else(invalid)?;
+    let body = body.trim_end().strip_suffix(')').ok_or_else(invalid)?;
+
+    let parse_point = |text: &str| -> AppResult<[f64; 2]> {
+        let values: Vec<f64> = text
+            .split_whitespace()
+            .map(|v| v.parse::<f64>().map_err(|_| invalid()))
+            .collect::<AppResult<_>>()?;
+        match values[..] {
+            [x, y] | [x, y, _] if (-180.0..=180.0).contains(&x) && (-90.0..=90.0).contains(&y) => {
+                Ok([x, y])
+            }
+            _ => Err(invalid()),
+        }
+    };
+
+    match keyword.trim().to_ascii_uppercase().as_str() {
+        "POINT" => Ok(Coords::Point(parse_point(body)?)),
+        "POLYGON" => {
+            let rings = body
+                .split(')')
+                .map(|ring| ring.trim().trim_start_matches(',').trim())
+                .filter(|ring| !ring.is_empty())
+                .map(|ring| {
+                    let ring = ring.strip_prefix('(').ok_or_else(invalid)?;
+                    let points: Vec<[f64; 2]> =
+                        ring.split(',').map(parse_point).collect::<AppResult<_>>()?;
+                    if points.len() < 4 || points.first() != points.last() {
+                        return Err(AppError::BadRequest(
+                            "Polygon rings must be closed and have at least 4 points".to_string(),
+                        ));
+                    }
+                    Ok(points)
+                })
+                .collect::<AppResult<Vec<_>>>()?;
+            if rings.is_empty() {
+                return Err(invalid());
+            }
+            Ok(Coords::Polygon(rings))
+        }
+        _ => Err(AppError::BadRequest(format!(
+            "Unsupported coords geometry: {}",
+            keyword.trim()
+        ))),
+    }
+}
+
+/// Regular CRS84 grid over the bounding box of an area query
+#[derive(Debug, Clone, PartialEq)]
+pub struct AreaGrid {
+    pub bbox: [f64; 4],
+    pub width: usize,
+    pub height: usize,
+}
+
+impl AreaGrid {
+    /// Grid with at most `AREA_GRID_SIZE` cells along each side and
+    /// roughly square cells on the ground
+    pub fn for_bbox(bbox: [f64; 4]) -> Self {
+        let [minx, miny, maxx, maxy] = bbox;
+        let width = (maxx - minx) * ((miny + maxy) / 2.0).to_radians().cos();
+        let height = maxy - miny;
+        let cells = |side: f64, other: f64| {
+            if side >= other {
+                AREA_GRID_SIZE
+            } else {
+                ((AREA_GRID_SIZE as f64 * side / other).round() as usize).max(1)
+            }
+        };
+        Self {
+            bbox,
+            width: cells(width, height),
+            height: cells(height, width),
+        }
+    }
+
+    /// Longitudes of the cell centers, west to east
+    pub fn x_values(&self) -> Vec<f64> {
+        let [minx, _, maxx, _] = self.bbox;
+        let step = (maxx - minx) / self.width as f64;
+        (0..self.width)
+            .map(|i| minx + (i as f64 + 0.5) * step)
+            .collect()
+    }
+
+    /// Latitudes of the cell centers, north to south
+    pub fn y_values(&self) -> Vec<f64> {
+        let [_, miny, _, maxy] = self.bbox;
+        let step = (maxy - miny) / self.height as f64;
+        (0..self.height)
+            .map(|j| maxy - (j as f64 + 0.5) * step)
+            .collect()
+    }
+
+    /// Cell centers in row-major order
+    pub fn points(&self) -> Vec<[f64; 2]> {
+        let xs = self.x_values();
+        self.y_values()
+            .into_iter()
+            .flat_map(|y| xs.iter().map(move |&x| [x, y]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_point() {
+        assert_eq!(
+            parse_coords("POINT(18.07 59.33)").unwrap(),
+            Coords::Point([18.07, 59.33])
+        );
+        assert_eq!(
+            parse_coords(" point ( -1 2 10 ) ").unwrap(),
+            Coords::Point([-1.0, 2.0])
+        );
+
+        assert!(parse_coords("POINT(200 0)").is_err());
+        assert!(parse_coords("POINT(1)").is_err());
+        assert!(parse_coords("POINT 1 2").is_err());
+        assert!(parse_coords("LINESTRING(0 0, 1 1)").is_err());
+    }
+
+    #[test]
+    fn test_parse_polygon() {
+        let coords =
+            parse_coords("POLYGON((0 0, 10 0, 10 10, 0 10, 0 0), (4 4, 6 4, 6 6, 4 6, 4 4))")
+                .unwrap();
+        let Coords::Polygon(rings) = &coords else {
+            panic!("expected a polygon");
+        };
+        assert_eq!(rings.len(), 2);
+        assert_eq!(coords.bbox(), [0.0, 0.0, 10.0, 10.0]);
+
+        assert!(coords.contains([2.0, 2.0]));
+        assert!(!coords.contains([5.0, 5.0]));
+        assert!(!coords.contains([11.0, 5.0]));
+
+        assert!(parse_coords("POLYGON((0 0, 1 0, 1 1))").is_err());
+        assert!(parse_coords("POLYGON((0 0, 1 0, 1 1, 0 1))").is_err());
+        assert!(parse_coords("POLYGON(())").is_err());
+    }
+
+    #[test]
+    fn test_coords_to_wkt() {
+        assert_eq!(Coords::Point([1.5, 2.0]).to_wkt(), "POINT(1.5 2)");
+        let polygon = parse_coords("POLYGON((0 0,1 0,1 1,0 0))").unwrap();
+        assert_eq!(polygon.to_wkt(), "POLYGON((0 0, 1 0, 1 1, 0 0))");
+    }
+
+    #[test]
+    fn test_area_grid() {
+        let grid = AreaGrid::for_bbox([0.0, 0.0, 2.0, 1.0]);
+        assert_eq!(
+            (grid.width, grid.height),
+            (AREA_GRID_SIZE, AREA_GRID_SIZE / 2)
+        );
+
+        let points = grid.points();
+        assert_eq!(points.len(), grid.width * grid.height);
+        let half_cell = 1.0 / AREA_GRID_SIZE as f64;
+        assert_eq!(points[0], [half_cell, 1.0 - half_cell]);
+
+        // Longitude degrees shrink towards the poles
+        let grid = AreaGrid::for_bbox([0.0, 59.0, 2.0, 61.0]);
+        assert_eq!(grid.height, AREA_GRID_SIZE);
+        assert_eq!(grid.width, 128);
+    }
+
+    #[test]
+    fn test_query_params() {
+        let params = EdrQueryParams {
+            parameter_name: Some("band1, band3".to_string()),
+            datetime: Some("2024-01-01T00:00:00Z/..".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(params.bands().unwrap(), Some(vec![1, 3]));
+        let (start, end) = params.datetime_bounds().unwrap();
+        assert!(start.is_some() && end.is_none());
+
+        let params = EdrQueryParams {
+            parameter_name: Some("temperature".to_string()),
+            datetime: Some("yesterday".to_string()),
+            ..Default::default()
+        };
+        assert!(params.bands().is_err());
+        assert!(params.datetime_bounds().is_err());
+    }
+}
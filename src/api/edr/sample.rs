@@ -0,0 +1,122 @@
+use chrono::{DateTime, Utc};
+
+use crate::error::{AppError, AppResult};
+
+/// Largest raster window read in one request, in pixels per side
+///
+/// Windows covering more of the raster are read from a coarser overview.
+#[cfg(feature = "gdal-support")]
+const MAX_WINDOW_SIZE: usize = 1024;
+
+/// Values sampled from one item
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampledItem {
+    pub datetime: Option<DateTime<Utc>>,
+    /// Values per band and point; `None` for no data or outside the raster
+    pub values: Vec<Vec<Option<f64>>>,
+}
+
+/// Sample every band of a raster at CRS84 points
+pub fn sample_raster(href: &str, points: &[[f64; 2]]) -> AppResult<Vec<Vec<Option<f64>>>> {
+    #[cfg(feature = "gdal-support")]
+    {
+        sample_raster_gdal(href, points)
+    }
+    #[cfg(not(feature = "gdal-support"))]
+    {
+        let _ = (href, points);
+        Err(AppError::Processing(
+            "Sampling raster values requires the 'gdal-support' feature. \
+            Build with: cargo build --features gdal-support"
+                .to_string(),
+        ))
+    }
+}
+
+#[cfg(feature = "gdal-support")]
+fn sample_raster_gdal(href: &str, points: &[[f64; 2]]) -> AppResult<Vec<Vec<Option<f64>>>> {
+    use gdal::Dataset;
+    use gdal::raster::ResampleAlg;
+    use gdal::spatial_ref::{AxisMappingStrategy, CoordTransform, SpatialRef};
+
+    let dataset = Dataset::open(crate::api::tiles::raster::href_to_vsi_path(href))
+        .map_err(|e| AppError::Processing(format!("Failed to open raster: {}", e)))?;
+    let (raster_width, raster_height) = dataset.raster_size();
+    let gt = dataset
+        .geo_transform()
+        .map_err(|e| AppError::Processing(format!("Failed to get geotransform: {}", e)))?;
+
+    // Points in the raster's CRS, in x/y order
+    let mut xs: Vec<f64> = points.iter().map(|p| p[0]).collect();
+    let mut ys: Vec<f64> = points.iter().map(|p| p[1]).collect();
+    let mut source = SpatialRef::from_epsg(4326)
+        .map_err(|e| AppError::Processing(format!("Failed to create CRS: {}", e)))?;
+    source.set_axis_mapping_strategy(AxisMappingStrategy::TraditionalGisOrder);
+    let mut target = dataset
+        .spatial_ref()
+        .map_err(|e| AppError::Processing(format!("Failed to determine raster CRS: {}", e)))?;
+    target.set_axis_mapping_strategy(AxisMappingStrategy::TraditionalGisOrder);
+    CoordTransform::new(&source, &target)
+        .and_then(|transform| transform.transform_coords(&mut xs, &mut ys, &mut []))
+        .map_err(|e| AppError::Processing(format!("Failed to transform coordinates: {}", e)))?;
+
+    // Pixel of each point, if it falls on the raster
+    let pixels: Vec<Option<(usize, usize)>> = xs
+        .iter()
+        .zip(&ys)
+        .map(|(x, y)| {
+            let col = ((x - gt[0]) / gt[1]).floor();
+            let row = ((y - gt[3]) / gt[5]).floor();
+            (col >= 0.0 && row >= 0.0 && col < raster_width as f64 && row < raster_height as f64)
+                .then_some((col as usize, row as usize))
+        })
+        .collect();
+
+    let band_count = dataset.raster_count();
+    let Some((min_col, min_row, max_col, max_row)) = pixels.iter().flatten().fold(
+        None,
+        |window: Option<(usize, usize, usize, usize)>, &(col, row)| {
+            Some(match window {
+                None => (col, row, col, row),
+                Some((c0, r0, c1, r1)) => (c0.min(col), r0.min(row), c1.max(col), r1.max(row)),
+            })
+        },
+    ) else {
+        return Ok(vec![vec![None; points.len()]; band_count]);
+    };
+
+    // Read the window covering all points, downsampled if it is large
+    let window = (max_col - min_col + 1, max_row - min_row + 1);
+    let shape = (window.0.min(MAX_WINDOW_SIZE), window.1.min(MAX_WINDOW_SIZE));
+    let mut values = Vec::with_capacity(band_count);
+    for band_index in 1..=band_count {
+        let band = dataset
+            .rasterband(band_index)
+            .map_err(|e| AppError::Processing(format!("Failed to get band: {}", e)))?;
+        let nodata = band.no_data_value();
+        let buffer = band
+            .read_as::<f64>(
+                (min_col as isize, min_row as isize),
+                window,
+                shape,
+                Some(ResampleAlg::NearestNeighbour),
+            )
+            .map_err(|e| AppError::Processing(format!("Failed to read band: {}", e)))?;
+        let data = buffer.data();
+
+        values.push(
+            pixels
+                .iter()
+                .map(|pixel| {
+                    let (col, row) = (*pixel)?;
+                    let x = (col - min_col) * shape.0 / window.0;
+                    let y = (row - min_row) * shape.1 / window.1;
+                    let value = data[y * shape.0 + x];
+                    (value.is_finite() && Some(value) != nodata).then_some(value)
+                })
+                .collect(),
+        );
+    }
+
+    Ok(values)
+}
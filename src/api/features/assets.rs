@@ -0,0 +1,256 @@
+//! Registration of item assets uploaded directly to object storage
+//!
+//! Pipelines that write their files straight to the bucket only need the
+//! catalog entries; they register all assets of an item in one request,
+//! optionally checking each asset against the object it references.
+
+use aide::{
+    axum::{ApiRouter, routing::post_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, Query, State},
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::body::JsonBody;
+use crate::api::collections::ResolvedCollection;
+use crate::api::collections::schemas::{RegisteredAsset, validate_asset_key};
+use crate::api::common::etag;
+use crate::auth::AuthenticatedUser;
+use crate::error::{AppError, AppResult};
+use crate::processing::asset_check::{self, AssetValidation};
+use crate::services::FeatureService;
+use crate::storage::S3Storage;
+
+/// Most assets registered in one request
+const MAX_BATCH_ASSETS: usize = 1000;
+
+/// Objects read at the same time when checking assets
+const CHECK_CONCURRENCY: usize = 8;
+
+/// Query parameters for batch asset registration
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct RegisterAssetsParams {
+    /// Read the start of each object to check its media type, size and
+    /// COG/COPC layout, recording the outcome in the asset's `validation`
+    #[serde(default)]
+    pub check: bool,
+}
+
+/// Request registering assets of an item
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RegisterAssetsRequest {
+    /// Assets by key; assets with existing keys are replaced
+    pub assets: BTreeMap<String, RegisteredAsset>,
+}
+
+impl RegisterAssetsRequest {
+    pub fn validate(&self) -> AppResult<()> {
+        if self.assets.is_empty() {
+            return Err(AppError::BadRequest("assets cannot be empty".to_string()));
+        }
+        if self.assets.len() > MAX_BATCH_ASSETS {
+            return Err(AppError::BadRequest(format!(
+                "At most {} assets can be registered at once",
+                MAX_BATCH_ASSETS
+            )));
+        }
+        for (key, asset) in &self.assets {
+            validate_asset_key(key)?;
+            if let Err(AppError::BadRequest(message)) = asset.validate() {
+                return Err(AppError::BadRequest(format!("Asset {}: {}", key, message)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that every asset references an object in one of `namespaces`
+    /// of the bucket at `bucket_uri`
+    pub fn validate_locations(&self, bucket_uri: &str, namespaces: &[&str]) -> AppResult<()> {
+        for (key, asset) in &self.assets {
+            match asset.validate_location(bucket_uri, namespaces) {
+                Err(AppError::BadRequest(message)) => {
+                    return Err(AppError::BadRequest(format!("Asset {}: {}", key, message)));
+                }
+                Err(AppError::Forbidden(message)) => {
+                    return Err(AppError::Forbidden(format!("Asset {}: {}", key, message)));
+                }
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Assets of an item after registration
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterAssetsResponse {
+    pub item_id: Uuid,
+    /// Number of assets registered by the request
+    pub registered: usize,
+    /// All assets of the item
+    pub assets: serde_json::Value,
+}
+
+/// Path parameters for batch asset registration
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/items/{feature_id}/assets:batch")]
+pub struct ItemAssetsBatchPath {
+    /// The collection identifier
+    pub collection_id: String,
+    /// The item UUID
+    pub feature_id: Uuid,
+}
+
+/// Register assets of an item in one transaction
+pub async fn register_assets(
+    Extension(user): Extension<AuthenticatedUser>,
+    Extension(storage): Extension<Arc<S3Storage>>,
+    State(service): State<Arc<FeatureService>>,
+    path: ItemAssetsBatchPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    Query(params): Query<RegisterAssetsParams>,
+    headers: HeaderMap,
+    JsonBody(request): JsonBody<RegisterAssetsRequest>,
+) -> AppResult<Response> {
+    request.validate()?;
+    // Only objects of the user's or the collection owner's namespace
+    request.validate_locations(
+        &storage.s3_uri(""),
+        &[user.username.as_str(), collection.owner.as_str()],
+    )?;
+    let expected_version = etag::extract_expected_version(&headers)?;
+
+    // Locations are checked above, so only the allowed namespaces are read
+    let validations: BTreeMap<String, AssetValidation> = if params.check {
+        futures::stream::iter(request.assets.clone())
+            .map(|(key, registered)| {
+                let storage = storage.clone();
+                async move {
+                    let asset = registered.asset;
+                    let validation = asset_check::validate_asset(
+                        &storage,
+                        &asset.href,
+                        asset.media_type.as_deref(),
+                        asset.file_size,
+                    )
+                    .await;
+                    (key, validation)
+                }
+            })
+            .buffer_unordered(CHECK_CONCURRENCY)
+            .collect()
+            .await
+    } else {
+        BTreeMap::new()
+    };
+
+    let (assets, version) = service
+        .register_item_assets(
+            &user.username,
+            &collection.canonical_name,
+            path.feature_id,
+            expected_version,
+            &request.assets,
+            &validations,
+        )
+        .await?;
+
+    let response = RegisterAssetsResponse {
+        item_id: path.feature_id,
+        registered: request.assets.len(),
+        assets,
+    };
+    Ok((
+        [(header::ETAG, etag::create_etag_header(version)?)],
+        Json(response),
+    )
+        .into_response())
+}
+
+fn register_assets_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Register item assets")
+        .description(
+            "Registers assets of a raster or point cloud item whose files are already in object \
+             storage, e.g. written to the bucket by an external pipeline. All assets are stored \
+             in one transaction: either all are registered or none. Assets with existing keys \
+             are replaced. Hrefs must be S3 URIs of the deployment's bucket under the \
+             namespace of the user or of the collection's owner, e.g. \
+             `s3://bucket/alice/scene/B04.tif`; the objects themselves are not read. Fields \
+             beyond the core STAC asset fields, such as `proj:epsg`, are kept and returned with \
+             the item. With `check=true`, the start of each object is read to detect its media \
+             type and size and, for assets claiming to be a COG or COPC, its layout; the outcome \
+             is recorded in the asset's `validation` field, with status `invalid` for mismatches \
+             (e.g. a COG that is not tiled) and `unreachable` for objects that cannot be read. \
+             Failed checks are reported, not rejected. If-Match header is optional; when \
+             provided, the item must be unchanged.",
+        )
+        .tag("Features")
+        .response_with::<200, Json<RegisterAssetsResponse>, _>(|res| {
+            res.description("Assets registered")
+        })
+        .response_with::<400, (), _>(|res| {
+            res.description("Invalid asset key or asset, or not a raster/pointcloud collection")
+        })
+        .response_with::<403, (), _>(|res| {
+            res.description("An asset references an object outside the allowed namespaces")
+        })
+        .response_with::<404, (), _>(|res| res.description("Collection or item not found"))
+        .response_with::<409, (), _>(|res| res.description("Item is locked by another user"))
+        .response_with::<412, (), _>(|res| res.description("Precondition failed (ETag mismatch)"))
+        .response_with::<507, (), _>(|res| {
+            res.description("The collection's owner is over its storage quota")
+        })
+}
+
+pub fn routes(service: Arc<FeatureService>, storage: Arc<S3Storage>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/items/{feature_id}/assets:batch",
+            post_with(register_assets, register_assets_docs),
+        )
+        .layer(Extension(storage))
+        .with_state(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_register_assets() {
+        let request = |assets: serde_json::Value| -> RegisterAssetsRequest {
+            serde_json::from_value(serde_json::json!({ "assets": assets })).unwrap()
+        };
+
+        assert!(
+            request(serde_json::json!({
+                "B04": { "href": "s3://bucket/scene/B04.tif", "roles": ["data"] },
+                "thumbnail": { "href": "s3://bucket/scene/thumb.png", "roles": ["thumbnail"] }
+            }))
+            .validate()
+            .is_ok()
+        );
+        assert!(request(serde_json::json!({})).validate().is_err());
+        assert!(
+            request(serde_json::json!({ "a/b": { "href": "s3://bucket/x.tif" } }))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            request(serde_json::json!({ "B04": { "href": "s3://bucket/x.tif", "file:size": -1 } }))
+                .validate()
+                .is_err()
+        );
+    }
+}
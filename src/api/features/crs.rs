@@ -1,3 +1,5 @@
+use axum::http::HeaderMap;
+
 use crate::api::common::crs::{srid_to_uri, uri_to_srid};
 use crate::error::{AppError, AppResult};
 
@@ -28,6 +30,37 @@ pub fn transform_geometry_sql(column: &str, source_srid: i32, target_srid: Optio
     }
 }
 
+/// Parse the `Content-Crs` header of a write, e.g. `<http://www.opengis.net/def/crs/EPSG/0/3006>`
+pub fn parse_content_crs_header(headers: &HeaderMap) -> AppResult<Option<i32>> {
+    let Some(value) = headers.get("Content-Crs") else {
+        return Ok(None);
+    };
+    let value = value
+        .to_str()
+        .map_err(|_| AppError::BadRequest("Invalid Content-Crs header".to_string()))?
+        .trim();
+    let uri = value
+        .strip_prefix('<')
+        .and_then(|value| value.strip_suffix('>'))
+        .unwrap_or(value);
+    parse_crs_param(Some(uri))
+}
+
+/// Build SQL reading a GeoJSON geometry into the storage CRS, transforming
+/// it when it is given in another CRS
+pub fn input_geometry_sql(geojson: &str, input_srid: Option<i32>, storage_srid: i32) -> String {
+    match input_srid {
+        Some(input) if input != storage_srid => format!(
+            "ST_Transform(ST_SetSRID(ST_GeomFromGeoJSON({}), {}), {})",
+            geojson, input, storage_srid
+        ),
+        _ => format!(
+            "ST_SetSRID(ST_GeomFromGeoJSON({}), {})",
+            geojson, storage_srid
+        ),
+    }
+}
+
 /// Build bbox filter SQL with optional CRS transformation
 pub fn bbox_filter_sql(
     geometry_column: &str,
@@ -90,6 +123,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_content_crs_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_content_crs_header(&headers).unwrap(), None);
+        headers.insert(
+            "Content-Crs",
+            "<http://www.opengis.net/def/crs/EPSG/0/3006>"
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(parse_content_crs_header(&headers).unwrap(), Some(3006));
+        headers.insert("Content-Crs", "<urn:unknown>".parse().unwrap());
+        assert!(parse_content_crs_header(&headers).is_err());
+    }
+
+    #[test]
+    fn test_input_geometry_sql() {
+        assert_eq!(
+            input_geometry_sql("$1", None, 3006),
+            "ST_SetSRID(ST_GeomFromGeoJSON($1), 3006)"
+        );
+        assert_eq!(
+            input_geometry_sql("$1", Some(3006), 3006),
+            "ST_SetSRID(ST_GeomFromGeoJSON($1), 3006)"
+        );
+        assert_eq!(
+            input_geometry_sql("g", Some(4326), 3006),
+            "ST_Transform(ST_SetSRID(ST_GeomFromGeoJSON(g), 4326), 3006)"
+        );
+    }
+
     #[test]
     fn test_bbox_filter_sql() {
         let bbox = [-180.0, -90.0, 180.0, 90.0];
@@ -0,0 +1,222 @@
+//! CSV encoding of feature listings
+//!
+//! Each feature becomes a row with its id, its geometry as WKT or as
+//! longitude/latitude columns, and its properties. Nested objects are
+//! flattened into dotted column names and arrays are written as JSON.
+
+use geo::Centroid;
+use geozero::{ToGeo, ToWkt, geojson::GeoJson};
+use serde_json::{Map, Value};
+
+use super::handlers::Feature;
+use crate::error::{AppError, AppResult};
+
+/// How geometries are written to CSV
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CsvGeometry {
+    /// A single `wkt` column
+    #[default]
+    Wkt,
+    /// `lon` and `lat` columns with the point, or the centroid of other
+    /// geometry types
+    LonLat,
+}
+
+impl CsvGeometry {
+    /// Parse the `csv-geometry` query parameter
+    pub fn from_param(value: &str) -> AppResult<Self> {
+        match value {
+            "wkt" => Ok(Self::Wkt),
+            "lonlat" => Ok(Self::LonLat),
+            other => Err(AppError::BadRequest(format!(
+                "Invalid csv-geometry: {} (supported: wkt, lonlat)",
+                other
+            ))),
+        }
+    }
+
+    fn columns(self) -> &'static [&'static str] {
+        match self {
+            Self::Wkt => &["wkt"],
+            Self::LonLat => &["lon", "lat"],
+        }
+    }
+
+    fn values(self, geometry: &Value) -> AppResult<Vec<String>> {
+        if geometry.is_null() {
+            return Ok(vec![String::new(); self.columns().len()]);
+        }
+        let geojson = geometry.to_string();
+        let invalid = |e: geozero::error::GeozeroError| {
+            AppError::Internal(format!("Failed to convert geometry: {}", e))
+        };
+        match self {
+            Self::Wkt => Ok(vec![escape(
+                &GeoJson(&geojson).to_wkt().map_err(invalid)?,
+                false,
+            )]),
+            Self::LonLat => {
+                let centroid = GeoJson(&geojson).to_geo().map_err(invalid)?.centroid();
+                Ok(match centroid {
+                    Some(point) => vec![point.x().to_string(), point.y().to_string()],
+                    None => vec![String::new(), String::new()],
+                })
+            }
+        }
+    }
+}
+
+/// Flatten nested objects into dotted keys
+fn flatten_into(prefix: &str, object: &Map<String, Value>, row: &mut Vec<(String, Value)>) {
+    for (key, value) in object {
+        let key = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            Value::Object(nested) => flatten_into(&key, nested, row),
+            other => row.push((key, other.clone())),
+        }
+    }
+}
+
+/// Quote a field as needed by RFC 4180
+///
+/// Text starting with a formula character is prefixed with `'` so that
+/// spreadsheets do not evaluate it.
+fn escape(field: &str, is_text: bool) -> String {
+    let field = if is_text && field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", field)
+    } else {
+        field.to_string()
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => escape(text, true),
+        other => escape(&other.to_string(), false),
+    }
+}
+
+/// Encode features as CSV, with a header row
+///
+/// The property columns are the union of the properties of all features, in
+/// order of first appearance.
+pub fn features_to_csv(features: &[Feature], geometry: CsvGeometry) -> AppResult<String> {
+    let rows: Vec<Vec<(String, Value)>> = features
+        .iter()
+        .map(|feature| {
+            let mut row = Vec::new();
+            if let Some(properties) = feature.properties.as_object() {
+                flatten_into("", properties, &mut row);
+            }
+            row
+        })
+        .collect();
+    let mut columns: Vec<&str> = Vec::new();
+    for (key, _) in rows.iter().flatten() {
+        if !columns.contains(&key.as_str()) {
+            columns.push(key);
+        }
+    }
+
+    let mut csv = String::new();
+    let header: Vec<String> = ["id"]
+        .iter()
+        .chain(geometry.columns())
+        .chain(columns.iter())
+        .map(|column| escape(column, true))
+        .collect();
+    csv.push_str(&header.join(","));
+    csv.push_str("\r\n");
+
+    for (feature, row) in features.iter().zip(&rows) {
+        let mut cells = vec![escape(&feature.id, true)];
+        cells.extend(geometry.values(&feature.geometry)?);
+        cells.extend(columns.iter().map(|column| {
+            row.iter()
+                .find(|(key, _)| key == column)
+                .map(|(_, value)| cell(value))
+                .unwrap_or_default()
+        }));
+        csv.push_str(&cells.join(","));
+        csv.push_str("\r\n");
+    }
+    Ok(csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn feature(id: &str, geometry: Value, properties: Value) -> Feature {
+        Feature {
+            feature_type: "Feature".to_string(),
+            id: id.to_string(),
+            geometry,
+            properties,
+            links: None,
+            bbox: None,
+            assets: None,
+            collection: None,
+            stac_version: None,
+            stac_extensions: None,
+        }
+    }
+
+    #[test]
+    fn test_features_to_csv() {
+        let features = vec![
+            feature(
+                "a",
+                json!({"type": "Point", "coordinates": [11.5, 57.7]}),
+                json!({"name": "Göteborg, Sweden", "address": {"zip": "411 01"}, "tags": ["port"]}),
+            ),
+            feature(
+                "b",
+                json!({"type": "Polygon", "coordinates": [[[0, 0], [2, 0], [2, 2], [0, 2], [0, 0]]]}),
+                json!({"name": "=SUM(A1)", "population": 12, "note": "say \"hi\""}),
+            ),
+            feature("c", Value::Null, json!({})),
+        ];
+
+        let csv = features_to_csv(&features, CsvGeometry::Wkt).unwrap();
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(lines[0], "id,wkt,name,address.zip,tags,population,note");
+        assert_eq!(
+            lines[1],
+            "a,POINT(11.5 57.7),\"Göteborg, Sweden\",411 01,\"[\"\"port\"\"]\",,"
+        );
+        assert_eq!(
+            lines[2],
+            "b,\"POLYGON((0 0,2 0,2 2,0 2,0 0))\",'=SUM(A1),,,12,\"say \"\"hi\"\"\""
+        );
+        assert_eq!(lines[3], "c,,,,,,");
+        assert_eq!(lines[4], "");
+
+        let csv = features_to_csv(&features, CsvGeometry::LonLat).unwrap();
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+        assert!(lines[0].starts_with("id,lon,lat,name"));
+        assert!(lines[1].starts_with("a,11.5,57.7,"));
+        assert!(lines[2].starts_with("b,1,1,"));
+        assert!(lines[3].starts_with("c,,,"));
+    }
+
+    #[test]
+    fn test_csv_geometry_param() {
+        assert_eq!(
+            CsvGeometry::from_param("lonlat").unwrap(),
+            CsvGeometry::LonLat
+        );
+        assert!(CsvGeometry::from_param("geojson").is_err());
+    }
+}
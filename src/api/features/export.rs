@@ -0,0 +1,180 @@
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio_util::io::ReaderStream;
+
+use super::query::FeatureQueryParams;
+use crate::api::collections::ResolvedCollection;
+use crate::error::{AppError, AppResult};
+use crate::processing::export::{ExportFormat, TextEncoding, file_stem, write_export};
+use crate::services::FeatureService;
+
+/// Query parameters for collection exports
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExportParams {
+    /// Export format: `shapefile` (zipped), `kml`, `kmz` or `ndjson`
+    pub f: String,
+    /// Character encoding of Shapefile attributes: `utf-8` (default) or
+    /// `iso-8859-1`. KML is always UTF-8.
+    pub encoding: Option<String>,
+    /// Only export the features intersecting this WGS 84 bounding box:
+    /// minx,miny,maxx,maxy
+    pub bbox: Option<String>,
+    /// Clip exported geometries to `bbox`
+    #[serde(default)]
+    pub clip: bool,
+}
+
+/// Path parameters for collection exports
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/export")]
+pub struct CollectionExportPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+pub async fn export_collection(
+    State(service): State<Arc<FeatureService>>,
+    _path: CollectionExportPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    Query(params): Query<ExportParams>,
+) -> AppResult<Response> {
+    let format = ExportFormat::from_param(&params.f)?;
+    if format == ExportFormat::GeoPackage {
+        return Err(AppError::BadRequest(
+            "GeoPackage exports run as the export-collection process".to_string(),
+        ));
+    }
+    let encoding = params
+        .encoding
+        .as_deref()
+        .map(TextEncoding::from_param)
+        .transpose()?
+        .unwrap_or_default();
+
+    let bbox = match &params.bbox {
+        Some(bbox) => {
+            let query = FeatureQueryParams {
+                bbox: Some(bbox.clone()),
+                ..Default::default()
+            };
+            query.validate()?;
+            Some(query.parse_bbox(bbox)?)
+        }
+        None if params.clip => {
+            return Err(AppError::BadRequest("clip requires a bbox".to_string()));
+        }
+        None => None,
+    };
+
+    // Newline-delimited GeoJSON is streamed, so it has no size limit
+    if format == ExportFormat::NdJson {
+        let lines = service
+            .stream_export_features(&collection.canonical_name, bbox, params.clip)
+            .await?;
+        return Ok((
+            [
+                (header::CONTENT_TYPE, format.media_type().to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!(
+                        "attachment; filename=\"{}.{}\"",
+                        collection.table_name,
+                        format.extension()
+                    ),
+                ),
+            ],
+            Body::from_stream(lines),
+        )
+            .into_response());
+    }
+
+    // Other formats are written to a temporary file, then streamed from it
+    let dir = std::env::temp_dir().join("spatialvault");
+    tokio::fs::create_dir_all(&dir).await?;
+    let path = dir.join(format!(
+        "export-{}.{}",
+        uuid::Uuid::new_v4(),
+        format.extension()
+    ));
+    let features = service
+        .export_feature_stream(&collection.canonical_name, bbox, params.clip)
+        .await?;
+    let written = write_export(
+        format,
+        &collection.table_name,
+        &collection.title,
+        features,
+        encoding,
+        &path,
+    )
+    .await;
+    let file = match written {
+        Ok(_) => tokio::fs::File::open(&path).await.map_err(AppError::from),
+        Err(e) => Err(e),
+    };
+    // An open file stays readable once it is unlinked
+    tokio::fs::remove_file(&path).await.ok();
+    let file = file?;
+    let length = file.metadata().await?.len();
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.media_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}.{}\"",
+                    file_stem(&collection.table_name),
+                    format.extension()
+                ),
+            ),
+            (header::CONTENT_LENGTH, length.to_string()),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
+
+fn export_collection_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Export collection")
+        .description(
+            "Downloads all features of a vector collection in WGS 84 as a zipped ESRI \
+             Shapefile (`f=shapefile`), KML (`f=kml`), KMZ (`f=kmz`) or newline-delimited \
+             GeoJSON (`f=ndjson`). Newline-delimited GeoJSON is streamed one feature per \
+             line; the other formats are written to a temporary file, which is streamed once \
+             complete. No format limits the number of features. Shapefile attribute \
+             names are truncated to 10 characters and made unique; features with different \
+             geometry types are written to separate layers in the archive, and geometries \
+             with heights as PointZ, PolyLineZ, PolygonZ or MultiPointZ shapes. Attribute \
+             text is UTF-8 unless `encoding=iso-8859-1` is given, and the encoding is \
+             declared in a `.cpg` file. `bbox` limits the export to the features \
+             intersecting a WGS 84 bounding box, and `clip=true` cuts their geometries to \
+             it. For large collections, and for GeoPackages, run the `export-collection` process instead.",
+        )
+        .tag("Features")
+        .response_with::<200, Vec<u8>, _>(|res| res.description("Exported collection"))
+        .response_with::<400, (), _>(|res| {
+            res.description("Unsupported format, encoding or bbox, or not a vector collection")
+        })
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+pub fn routes(service: Arc<FeatureService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/export",
+            get_with(export_collection, export_collection_docs),
+        )
+        .with_state(service)
+}
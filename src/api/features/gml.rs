@@ -0,0 +1,559 @@
+//! GML 3.2 encoding of features, Simple Features profile level 0 (SF-0)
+//!
+//! Feature collections use the `sf:FeatureCollection` element of OGC API
+//! Features. Each collection has its own application schema, generated from
+//! the property types found in its data and served at
+//! `/collections/{collection_id}/schema.xsd`. Properties are written in
+//! name order, matching the sequence of the schema.
+
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    extract::{Extension, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use super::handlers::Feature;
+use crate::api::collections::ResolvedCollection;
+use crate::api::collections::metadata::xml_escape;
+use crate::api::common::{Link, crs::srid_to_uri, media_type};
+use crate::config::Config;
+use crate::error::AppResult;
+use crate::processing::export::{Geometry, Position};
+use crate::services::{FeatureService, PropertyType};
+
+const GML_NAMESPACE: &str = "http://www.opengis.net/gml/3.2";
+const GML_SCHEMA: &str = "http://schemas.opengis.net/gml/3.2.1/gml.xsd";
+const SF_NAMESPACE: &str = "http://www.opengis.net/ogcapi-features-1/1.0/sf";
+const SF_SCHEMA: &str = "http://schemas.opengis.net/ogcapi/features/part1/1.0/xml/core-sf.xsd";
+const GMLSF_NAMESPACE: &str = "http://www.opengis.net/gmlsf/2.0";
+const GMLSF_SCHEMA: &str = "http://schemas.opengis.net/gmlsfProfile/2.0/gmlsfLevels.xsd";
+const ATOM_NAMESPACE: &str = "http://www.w3.org/2005/Atom";
+const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
+
+/// Element name of the geometry property
+const GEOMETRY_ELEMENT: &str = "geometry";
+
+/// Turn a name into an XML NCName, replacing characters that are not allowed
+pub fn ncname(name: &str) -> String {
+    let mut result: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !result
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+    {
+        result.insert(0, '_');
+    }
+    result
+}
+
+/// Element name of a property; never clashes with the geometry
+fn property_element(key: &str) -> String {
+    let element = ncname(key);
+    if element == GEOMETRY_ELEMENT {
+        format!("{}_", element)
+    } else {
+        element
+    }
+}
+
+/// Names and namespaces of a collection's GML encoding
+#[derive(Debug, Clone)]
+pub struct GmlEncoding {
+    /// Feature type element name, without prefix
+    pub type_name: String,
+    /// Target namespace of the application schema
+    pub namespace: String,
+    /// URL of the application schema
+    pub schema_url: String,
+    /// `srsName` of geometries
+    pub srs_name: String,
+}
+
+impl GmlEncoding {
+    pub fn new(base_url: &str, collection_id: &str, table_name: &str, srid: i32) -> Self {
+        let collection_url = format!("{}/collections/{}", base_url, collection_id);
+        Self {
+            type_name: ncname(table_name),
+            schema_url: format!("{}/schema.xsd", collection_url),
+            namespace: collection_url,
+            srs_name: srid_to_uri(srid),
+        }
+    }
+
+    fn namespace_declarations(&self) -> String {
+        format!(
+            "xmlns:app=\"{}\" xmlns:gml=\"{}\" xmlns:xsi=\"{}\"",
+            xml_escape(&self.namespace),
+            GML_NAMESPACE,
+            XSI_NAMESPACE
+        )
+    }
+
+    /// Encode a page of features as an `sf:FeatureCollection`
+    pub fn feature_collection(
+        &self,
+        features: &[Feature],
+        number_matched: Option<usize>,
+        links: &[Link],
+        timestamp: &str,
+    ) -> AppResult<String> {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+        let _ = write!(
+            xml,
+            "<sf:FeatureCollection xmlns:sf=\"{}\" xmlns:atom=\"{}\" {} \
+             xsi:schemaLocation=\"{} {} {} {}\"",
+            SF_NAMESPACE,
+            ATOM_NAMESPACE,
+            self.namespace_declarations(),
+            SF_NAMESPACE,
+            SF_SCHEMA,
+            xml_escape(&self.namespace),
+            xml_escape(&self.schema_url)
+        );
+        if let Some(total) = number_matched {
+            let _ = write!(xml, " numberMatched=\"{}\"", total);
+        }
+        let _ = write!(
+            xml,
+            " numberReturned=\"{}\" timeStamp=\"{}\">",
+            features.len(),
+            xml_escape(timestamp)
+        );
+        for link in links {
+            let _ = write!(
+                xml,
+                "<atom:link href=\"{}\" rel=\"{}\"",
+                xml_escape(&link.href),
+                xml_escape(&link.rel)
+            );
+            if let Some(media_type) = &link.media_type {
+                let _ = write!(xml, " type=\"{}\"", xml_escape(media_type));
+            }
+            xml.push_str("/>");
+        }
+        for feature in features {
+            xml.push_str("<sf:featureMember>");
+            self.write_feature(&mut xml, feature, "")?;
+            xml.push_str("</sf:featureMember>");
+        }
+        xml.push_str("</sf:FeatureCollection>");
+        Ok(xml)
+    }
+
+    /// Encode a single feature as the root element of a document
+    pub fn feature(&self, feature: &Feature) -> AppResult<String> {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+        let attributes = format!(
+            " {} xsi:schemaLocation=\"{} {}\"",
+            self.namespace_declarations(),
+            xml_escape(&self.namespace),
+            xml_escape(&self.schema_url)
+        );
+        self.write_feature(&mut xml, feature, &attributes)?;
+        Ok(xml)
+    }
+
+    fn write_feature(
+        &self,
+        xml: &mut String,
+        feature: &Feature,
+        attributes: &str,
+    ) -> AppResult<()> {
+        let id = ncname(&format!("{}.{}", self.type_name, feature.id));
+        let _ = write!(
+            xml,
+            "<app:{}{} gml:id=\"{}\">",
+            self.type_name,
+            attributes,
+            xml_escape(&id)
+        );
+
+        if let Some(geometry) = Geometry::from_geojson(&feature.geometry)? {
+            let _ = write!(xml, "<app:{}>", GEOMETRY_ELEMENT);
+            let mut ids = 0;
+            write_geometry(xml, &geometry, &id, &mut ids, Some(&self.srs_name));
+            let _ = write!(xml, "</app:{}>", GEOMETRY_ELEMENT);
+        }
+
+        let mut properties: Vec<(String, &Value)> = feature
+            .properties
+            .as_object()
+            .map(|object| {
+                object
+                    .iter()
+                    .filter(|(_, value)| !value.is_null())
+                    .map(|(key, value)| (property_element(key), value))
+                    .collect()
+            })
+            .unwrap_or_default();
+        properties.sort_by(|a, b| a.0.cmp(&b.0));
+        for (element, value) in properties {
+            let text = match value {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            let _ = write!(
+                xml,
+                "<app:{}>{}</app:{}>",
+                element,
+                xml_escape(&text),
+                element
+            );
+        }
+
+        let _ = write!(xml, "</app:{}>", self.type_name);
+        Ok(())
+    }
+
+    /// Application schema declaring the feature type of the collection
+    pub fn application_schema(&self, properties: &[(String, PropertyType)]) -> String {
+        let mut elements: Vec<(String, &'static str)> = Vec::new();
+        for (key, property_type) in properties {
+            let element = property_element(key);
+            let xsd_type = match property_type {
+                PropertyType::String => "xs:string",
+                PropertyType::Integer => "xs:integer",
+                PropertyType::Number => "xs:double",
+                PropertyType::Boolean => "xs:boolean",
+            };
+            match elements.iter_mut().find(|(name, _)| *name == element) {
+                // Keys that map to the same element may hold anything
+                Some(existing) if existing.1 != xsd_type => existing.1 = "xs:string",
+                Some(_) => {}
+                None => elements.push((element, xsd_type)),
+            }
+        }
+        elements.sort();
+
+        let mut xsd = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+        let _ = write!(
+            xsd,
+            "<xs:schema xmlns:xs=\"http://www.w3.org/2001/XMLSchema\" xmlns:gml=\"{}\" \
+             xmlns:gmlsf=\"{}\" xmlns:app=\"{ns}\" targetNamespace=\"{ns}\" \
+             elementFormDefault=\"qualified\" version=\"1.0\">",
+            GML_NAMESPACE,
+            GMLSF_NAMESPACE,
+            ns = xml_escape(&self.namespace)
+        );
+        let _ = write!(
+            xsd,
+            "<xs:annotation><xs:appinfo source=\"{}\"><gmlsf:ComplianceLevel>0</gmlsf:ComplianceLevel></xs:appinfo></xs:annotation>\
+             <xs:import namespace=\"{}\" schemaLocation=\"{}\"/>\
+             <xs:import namespace=\"{}\" schemaLocation=\"{}\"/>",
+            GMLSF_SCHEMA, GML_NAMESPACE, GML_SCHEMA, GMLSF_NAMESPACE, GMLSF_SCHEMA
+        );
+        let _ = write!(
+            xsd,
+            "<xs:element name=\"{name}\" type=\"app:{name}Type\" substitutionGroup=\"gml:AbstractFeature\"/>\
+             <xs:complexType name=\"{name}Type\"><xs:complexContent>\
+             <xs:extension base=\"gml:AbstractFeatureType\"><xs:sequence>\
+             <xs:element name=\"{}\" type=\"gml:GeometryPropertyType\" minOccurs=\"0\"/>",
+            GEOMETRY_ELEMENT,
+            name = self.type_name
+        );
+        for (element, xsd_type) in elements {
+            let _ = write!(
+                xsd,
+                "<xs:element name=\"{}\" type=\"{}\" minOccurs=\"0\"/>",
+                element, xsd_type
+            );
+        }
+        xsd.push_str(
+            "</xs:sequence></xs:extension></xs:complexContent></xs:complexType></xs:schema>",
+        );
+        xsd
+    }
+}
+
+fn write_positions(xml: &mut String, element: &str, positions: &[Position]) {
+    let dimension = positions.iter().map(Vec::len).max().unwrap_or(2).min(3);
+    if dimension == 3 {
+        let _ = write!(xml, "<gml:{} srsDimension=\"3\">", element);
+    } else {
+        let _ = write!(xml, "<gml:{}>", element);
+    }
+    let values: Vec<String> = positions
+        .iter()
+        .flat_map(|position| {
+            (0..dimension).map(|i| position.get(i).copied().unwrap_or_default().to_string())
+        })
+        .collect();
+    xml.push_str(&values.join(" "));
+    let _ = write!(xml, "</gml:{}>", element);
+}
+
+/// Write a GML geometry; every geometry gets a `gml:id` derived from the feature's
+fn write_geometry(
+    xml: &mut String,
+    geometry: &Geometry,
+    feature_id: &str,
+    ids: &mut usize,
+    srs_name: Option<&str>,
+) {
+    *ids += 1;
+    let open = |xml: &mut String, element: &str| {
+        let _ = write!(
+            xml,
+            "<gml:{} gml:id=\"{}.g{}\"",
+            element,
+            xml_escape(feature_id),
+            ids
+        );
+        if let Some(srs_name) = srs_name {
+            let _ = write!(xml, " srsName=\"{}\"", xml_escape(srs_name));
+        }
+        xml.push('>');
+    };
+    let polygon_body = |xml: &mut String, rings: &[Vec<Position>]| {
+        for (index, ring) in rings.iter().enumerate() {
+            let boundary = if index == 0 { "exterior" } else { "interior" };
+            let _ = write!(xml, "<gml:{}><gml:LinearRing>", boundary);
+            write_positions(xml, "posList", ring);
+            let _ = write!(xml, "</gml:LinearRing></gml:{}>", boundary);
+        }
+    };
+
+    match geometry {
+        Geometry::Point(position) => {
+            open(xml, "Point");
+            write_positions(xml, "pos", std::slice::from_ref(position));
+            xml.push_str("</gml:Point>");
+        }
+        Geometry::LineString(line) => {
+            open(xml, "LineString");
+            write_positions(xml, "posList", line);
+            xml.push_str("</gml:LineString>");
+        }
+        Geometry::Polygon(rings) => {
+            open(xml, "Polygon");
+            polygon_body(xml, rings);
+            xml.push_str("</gml:Polygon>");
+        }
+        Geometry::MultiPoint(positions) => {
+            open(xml, "MultiPoint");
+            for position in positions {
+                xml.push_str("<gml:pointMember>");
+                write_geometry(
+                    xml,
+                    &Geometry::Point(position.clone()),
+                    feature_id,
+                    ids,
+                    None,
+                );
+                xml.push_str("</gml:pointMember>");
+            }
+            xml.push_str("</gml:MultiPoint>");
+        }
+        Geometry::MultiLineString(lines) => {
+            open(xml, "MultiCurve");
+            for line in lines {
+                xml.push_str("<gml:curveMember>");
+                write_geometry(
+                    xml,
+                    &Geometry::LineString(line.clone()),
+                    feature_id,
+                    ids,
+                    None,
+                );
+                xml.push_str("</gml:curveMember>");
+            }
+            xml.push_str("</gml:MultiCurve>");
+        }
+        Geometry::MultiPolygon(polygons) => {
+            open(xml, "MultiSurface");
+            for rings in polygons {
+                xml.push_str("<gml:surfaceMember>");
+                write_geometry(
+                    xml,
+                    &Geometry::Polygon(rings.clone()),
+                    feature_id,
+                    ids,
+                    None,
+                );
+                xml.push_str("</gml:surfaceMember>");
+            }
+            xml.push_str("</gml:MultiSurface>");
+        }
+        Geometry::GeometryCollection(geometries) => {
+            open(xml, "MultiGeometry");
+            for member in geometries {
+                xml.push_str("<gml:geometryMember>");
+                write_geometry(xml, member, feature_id, ids, None);
+                xml.push_str("</gml:geometryMember>");
+            }
+            xml.push_str("</gml:MultiGeometry>");
+        }
+    }
+}
+
+/// Path parameters for the GML application schema
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/schema.xsd")]
+pub struct ApplicationSchemaPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+pub async fn get_application_schema(
+    Extension(config): Extension<Arc<Config>>,
+    State(service): State<Arc<FeatureService>>,
+    _path: ApplicationSchemaPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> AppResult<Response> {
+    let properties = service.property_types(&collection.canonical_name).await?;
+    let encoding = GmlEncoding::new(
+        &config.base_url,
+        &collection.canonical_name,
+        &collection.table_name,
+        collection.storage_crs,
+    );
+    Ok((
+        [(header::CONTENT_TYPE, media_type::XML)],
+        encoding.application_schema(&properties),
+    )
+        .into_response())
+}
+
+fn get_application_schema_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get GML application schema")
+        .description(
+            "Returns the XML Schema of the collection's features in GML 3.2 (Simple Features \
+             level 0). Property types are derived from the stored features.",
+        )
+        .tag("Features")
+        .response_with::<200, String, _>(|res| res.description("GML application schema"))
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+pub fn routes(service: Arc<FeatureService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/schema.xsd",
+            get_with(get_application_schema, get_application_schema_docs),
+        )
+        .with_state(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn feature(id: &str, geometry: Value, properties: Value) -> Feature {
+        Feature {
+            feature_type: "Feature".to_string(),
+            id: id.to_string(),
+            geometry,
+            properties,
+            links: None,
+            bbox: None,
+            assets: None,
+            collection: None,
+            stac_version: None,
+            stac_extensions: None,
+        }
+    }
+
+    fn encoding() -> GmlEncoding {
+        GmlEncoding::new("http://localhost", "alice:roads", "roads", 4326)
+    }
+
+    #[test]
+    fn test_ncname() {
+        assert_eq!(ncname("roads"), "roads");
+        assert_eq!(ncname("2024 count"), "_2024_count");
+        assert_eq!(ncname("höjd"), "höjd");
+        assert_eq!(property_element("geometry"), "geometry_");
+    }
+
+    #[test]
+    fn test_feature_collection() {
+        let features = vec![
+            feature(
+                "8f0c",
+                json!({"type": "Point", "coordinates": [11.5, 57.7]}),
+                json!({"name": "A & B", "lanes": 2, "note": null}),
+            ),
+            feature(
+                "9a1d",
+                json!({"type": "MultiPolygon", "coordinates": [[[[0, 0, 1], [1, 0, 1], [1, 1, 1], [0, 0, 1]]]]}),
+                json!({}),
+            ),
+        ];
+        let links = vec![
+            Link::new(
+                "http://localhost/collections/alice:roads/items?f=gml",
+                "self",
+            )
+            .with_type(media_type::GML_SF0),
+        ];
+        let xml = encoding()
+            .feature_collection(&features, Some(10), &links, "2024-01-01T00:00:00Z")
+            .unwrap();
+
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?><sf:FeatureCollection"));
+        assert!(xml.contains(
+            "xsi:schemaLocation=\"http://www.opengis.net/ogcapi-features-1/1.0/sf http://schemas.opengis.net/ogcapi/features/part1/1.0/xml/core-sf.xsd http://localhost/collections/alice:roads http://localhost/collections/alice:roads/schema.xsd\""
+        ));
+        assert!(xml.contains(
+            " numberMatched=\"10\" numberReturned=\"2\" timeStamp=\"2024-01-01T00:00:00Z\">"
+        ));
+        assert!(xml.contains(
+            "<atom:link href=\"http://localhost/collections/alice:roads/items?f=gml\" rel=\"self\""
+        ));
+        assert!(xml.contains(
+            "<sf:featureMember><app:roads gml:id=\"roads.8f0c\"><app:geometry><gml:Point gml:id=\"roads.8f0c.g1\" srsName=\"http://www.opengis.net/def/crs/OGC/1.3/CRS84\"><gml:pos>11.5 57.7</gml:pos></gml:Point></app:geometry><app:lanes>2</app:lanes><app:name>A &amp; B</app:name></app:roads></sf:featureMember>"
+        ));
+        assert!(!xml.contains("app:note"));
+        assert!(xml.contains(
+            "<gml:MultiSurface gml:id=\"roads.9a1d.g1\" srsName=\"http://www.opengis.net/def/crs/OGC/1.3/CRS84\"><gml:surfaceMember><gml:Polygon gml:id=\"roads.9a1d.g2\"><gml:exterior><gml:LinearRing><gml:posList srsDimension=\"3\">0 0 1 1 0 1 1 1 1 0 0 1</gml:posList>"
+        ));
+        assert!(xml.ends_with("</sf:FeatureCollection>"));
+    }
+
+    #[test]
+    fn test_single_feature() {
+        let xml = encoding()
+            .feature(&feature("a", Value::Null, json!({"geometry": "x"})))
+            .unwrap();
+        assert!(xml.contains("<app:roads xmlns:app=\"http://localhost/collections/alice:roads\""));
+        assert!(xml.contains("gml:id=\"roads.a\"><app:geometry_>x</app:geometry_></app:roads>"));
+    }
+
+    #[test]
+    fn test_application_schema() {
+        let xsd = encoding().application_schema(&[
+            ("name".to_string(), PropertyType::String),
+            ("lanes".to_string(), PropertyType::Integer),
+            ("speed limit".to_string(), PropertyType::Number),
+            ("speed_limit".to_string(), PropertyType::Integer),
+        ]);
+        assert!(xsd.contains("targetNamespace=\"http://localhost/collections/alice:roads\""));
+        assert!(xsd.contains("<gmlsf:ComplianceLevel>0</gmlsf:ComplianceLevel>"));
+        assert!(xsd.contains(
+            "<xs:element name=\"roads\" type=\"app:roadsType\" substitutionGroup=\"gml:AbstractFeature\"/>"
+        ));
+        assert!(xsd.contains(
+            "<xs:element name=\"geometry\" type=\"gml:GeometryPropertyType\" minOccurs=\"0\"/>\
+             <xs:element name=\"lanes\" type=\"xs:integer\" minOccurs=\"0\"/>\
+             <xs:element name=\"name\" type=\"xs:string\" minOccurs=\"0\"/>\
+             <xs:element name=\"speed_limit\" type=\"xs:string\" minOccurs=\"0\"/></xs:sequence>"
+        ));
+    }
+}
@@ -7,7 +7,7 @@ use aide::{
 };
 use axum::{
     Json,
-    extract::{Extension, Query, State},
+    extract::{DefaultBodyLimit, Extension, Query, State},
     http::{HeaderMap, StatusCode, header},
     response::{IntoResponse, Response},
 };
@@ -16,13 +16,20 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
-use super::crs::{content_crs_header, parse_crs_param};
-use super::query::FeatureQueryParams;
-use crate::api::common::{Link, etag, media_type, rel};
+use super::crs::{content_crs_header, parse_content_crs_header, parse_crs_param};
+use super::csv::{CsvGeometry, features_to_csv};
+use super::gml::GmlEncoding;
+use super::ingest::{self, IngestBody, IngestParams, ParsedBody};
+use super::query::{FeatureQueryParams, PageLimits, parse_ids, parse_within_distance};
+use crate::api::body::{JsonBody, MergePatchBody};
+use crate::api::collections::ResolvedCollection;
+use crate::api::common::{Link, cache, etag, media_type, rel};
+use crate::api::conflict::{self, ConflictResponse};
+use crate::api::validate_only::ValidateOnly;
 use crate::auth::AuthenticatedUser;
 use crate::config::Config;
 use crate::error::{AppError, AppResult};
-use crate::services::{CollectionService, FeatureService};
+use crate::services::{CountMode, FeatureService, ResultType};
 
 /// GeoJSON Feature (also serves as STAC Item for raster/pointcloud collections)
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -47,6 +54,24 @@ pub struct Feature {
     pub stac_extensions: Option<Vec<String>>,
 }
 
+impl Feature {
+    /// A feature carrying only its id, as listed by `resultType=ids`
+    pub fn id_only(id: String) -> Self {
+        Self {
+            feature_type: "Feature".to_string(),
+            id,
+            geometry: serde_json::Value::Null,
+            properties: serde_json::json!({}),
+            links: None,
+            bbox: None,
+            assets: None,
+            collection: None,
+            stac_version: None,
+            stac_extensions: None,
+        }
+    }
+}
+
 /// GeoJSON FeatureCollection
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct FeatureCollection {
@@ -63,6 +88,19 @@ pub struct FeatureCollection {
     pub timestamp: Option<String>,
 }
 
+/// The ids of matching features, returned for `resultType=ids`
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct FeatureIds {
+    pub ids: Vec<String>,
+    pub links: Vec<Link>,
+    #[serde(rename = "numberMatched", skip_serializing_if = "Option::is_none")]
+    pub number_matched: Option<u64>,
+    #[serde(rename = "numberReturned")]
+    pub number_returned: u64,
+    #[serde(rename = "timeStamp")]
+    pub timestamp: String,
+}
+
 /// Request to create a feature or STAC item
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct CreateFeatureRequest {
@@ -75,6 +113,19 @@ pub struct CreateFeatureRequest {
     pub assets: Option<serde_json::Value>,
 }
 
+/// Result of ingesting a FeatureCollection
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkIngestResponse {
+    pub number_inserted: u64,
+    /// Existing features updated by `on-conflict=update`
+    pub number_updated: u64,
+    /// Features matching an existing feature that was left unchanged, or
+    /// superseded by a later feature with the same key
+    pub number_skipped: u64,
+    pub links: Vec<Link>,
+}
+
 /// Request to update a feature or STAC item (PATCH - JSON Merge Patch)
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct UpdateFeatureRequest {
@@ -96,119 +147,321 @@ pub struct CollectionItemsPath {
 pub async fn list_features(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
-    State((service, collection_service)): State<(Arc<FeatureService>, Arc<CollectionService>)>,
-    path: CollectionItemsPath,
+    State(service): State<Arc<FeatureService>>,
+    _path: CollectionItemsPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    request_headers: HeaderMap,
     Query(params): Query<FeatureQueryParams>,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = collection_service
-        .check_alias_redirect(&collection_id)
-        .await?
-    {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::LOCATION,
-            format!("{}/collections/{}/items", config.base_url, new_name)
-                .parse()
-                .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, headers).into_response());
-    }
-
+    let collection_id = collection.canonical_name.clone();
     params.validate()?;
 
+    let limits = PageLimits {
+        default: config.features.default_limit,
+        max: config.features.max_limit,
+    }
+    .with_overrides(collection.default_limit, collection.max_limit);
+    let limit = params.page_limit(&limits)?;
+    let ids = params.ids.as_deref().map(parse_ids).transpose()?;
+    let within_distance = params
+        .within_distance
+        .as_deref()
+        .map(parse_within_distance)
+        .transpose()?;
+
     let target_crs = parse_crs_param(params.crs.as_deref())?;
     let bbox_crs = parse_crs_param(params.bbox_crs.as_deref())?;
+    let format = ItemsFormat::from_params(&params)?;
+    let result_type = params.parse_result_type()?;
+    if result_type != ResultType::Results && format != ItemsFormat::GeoJson {
+        return Err(AppError::BadRequest(
+            "resultType=ids and resultType=hits are only available as JSON".to_string(),
+        ));
+    }
+    if format == ItemsFormat::Gml && collection.collection_type != "vector" {
+        return Err(AppError::BadRequest(
+            "GML is only available for vector collections".to_string(),
+        ));
+    }
+
+    // Computed properties and other metadata shape the features as well
+    let last_modified = collection.data_updated_at.max(collection.updated_at);
+    if let Some(time) = last_modified
+        && cache::is_unmodified_since(&request_headers, time)
+    {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::LAST_MODIFIED, cache::last_modified(time));
+        return Ok(cache::not_modified_response(headers));
+    }
 
-    let (features, total_count, storage_srid) = service
+    let return_minimal = prefers_minimal(&request_headers);
+    let count_mode = match params.count {
+        // The count is all a request for hits returns
+        _ if result_type == ResultType::Hits => CountMode::Exact,
+        Some(false) => CountMode::Skip,
+        None if return_minimal => CountMode::Skip,
+        Some(true) => CountMode::Exact,
+        None => match config.features.count_estimate_threshold {
+            0 => CountMode::Exact,
+            threshold => CountMode::EstimateAbove(threshold),
+        },
+    };
+
+    let (features, number_matched, storage_srid) = service
         .list_features(
             &user.username,
             &collection_id,
-            params.limit,
+            limit,
             params.offset,
             params.bbox.as_deref(),
             bbox_crs,
+            params.intersects.as_deref(),
+            within_distance,
+            params.clip.unwrap_or(false),
             target_crs,
             params.datetime.as_deref(),
             params.filter.as_deref(),
+            ids.as_deref(),
+            count_mode,
+            params.geom.as_deref(),
+            result_type,
         )
         .await?;
 
     let base_url = &config.base_url;
     let response_crs = target_crs.unwrap_or(storage_srid);
 
-    // Build pagination links
+    // Build pagination links, keeping the requested format and result type
+    let items_url = format!("{}/collections/{}/items", base_url, collection_id);
+    let format_query = match result_type {
+        ResultType::Results => format.query(&params),
+        ResultType::Ids => "resultType=ids".to_string(),
+        ResultType::Hits => "resultType=hits".to_string(),
+    };
+    let response_type = match result_type {
+        ResultType::Ids => media_type::JSON,
+        _ => format.media_type(),
+    };
     let mut links = vec![
         Link::new(
-            format!("{}/collections/{}/items", base_url, collection_id),
+            match format_query.as_str() {
+                "" => items_url.clone(),
+                query => format!("{}?{}", items_url, query),
+            },
             rel::SELF,
         )
-        .with_type(media_type::GEOJSON),
+        .with_type(response_type),
         Link::new(
             format!("{}/collections/{}", base_url, collection_id),
             rel::COLLECTION,
         )
         .with_type(media_type::JSON),
     ];
+    match format {
+        ItemsFormat::GeoJson if result_type != ResultType::Results => {
+            links.push(Link::new(items_url.clone(), rel::ALTERNATE).with_type(media_type::GEOJSON))
+        }
+        ItemsFormat::GeoJson if collection.collection_type == "vector" => links.push(
+            Link::new(format!("{}?f=gml", items_url), rel::ALTERNATE)
+                .with_type(media_type::GML_SF0),
+        ),
+        ItemsFormat::Gml => {
+            links.push(Link::new(items_url.clone(), rel::ALTERNATE).with_type(media_type::GEOJSON))
+        }
+        _ => {}
+    }
+    let page_url = |offset: u32| {
+        let mut url = format!("{}?offset={}&limit={}", items_url, offset, limit);
+        if !format_query.is_empty() {
+            url.push('&');
+            url.push_str(&format_query);
+        }
+        url
+    };
 
-    // Add next/prev links if needed
-    if params.offset + params.limit < total_count as u32 {
-        links.push(
-            Link::new(
-                format!(
-                    "{}/collections/{}/items?offset={}&limit={}",
-                    base_url,
-                    collection_id,
-                    params.offset + params.limit,
-                    params.limit
-                ),
-                rel::NEXT,
-            )
-            .with_type(media_type::GEOJSON),
-        );
+    // Add next/prev links if needed; without an exact count a full page implies more
+    let has_next = features.len() as u32 == limit
+        && number_matched.is_none_or(|total| ((params.offset + limit) as usize) < total);
+    if has_next {
+        links.push(Link::new(page_url(params.offset + limit), rel::NEXT).with_type(response_type));
     }
 
-    if params.offset > 0 {
-        let prev_offset = params.offset.saturating_sub(params.limit);
-        links.push(
-            Link::new(
-                format!(
-                    "{}/collections/{}/items?offset={}&limit={}",
-                    base_url, collection_id, prev_offset, params.limit
-                ),
-                rel::PREV,
-            )
-            .with_type(media_type::GEOJSON),
-        );
+    // Hits have no pages
+    if params.offset > 0 && result_type != ResultType::Hits {
+        let prev_offset = params.offset.saturating_sub(limit);
+        links.push(Link::new(page_url(prev_offset), rel::PREV).with_type(response_type));
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, response_type.parse().unwrap());
+    headers.insert(
+        "Content-Crs",
+        content_crs_header(response_crs).parse().unwrap(),
+    );
+    if return_minimal && params.count.is_none() {
+        headers.insert("Preference-Applied", "return=minimal".parse().unwrap());
+    }
+    if let Some(time) = last_modified {
+        headers.insert(header::LAST_MODIFIED, cache::last_modified(time));
+    }
+
+    if result_type == ResultType::Ids {
+        let ids = FeatureIds {
+            number_matched: number_matched.map(|total| total as u64),
+            number_returned: features.len() as u64,
+            ids: features.into_iter().map(|feature| feature.id).collect(),
+            links,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        return Ok((headers, Json(ids)).into_response());
+    }
+
+    match format {
+        ItemsFormat::GeoJson => {}
+        // CSV has no room for links, so paging links go in a Link header
+        ItemsFormat::Csv(csv_geometry) => {
+            let body = features_to_csv(&features, csv_geometry)?;
+            insert_paging_link_header(&mut headers, &links);
+            return Ok((headers, body).into_response());
+        }
+        // One feature per line, so the page can be processed line by line
+        ItemsFormat::NdJson => {
+            let mut body = Vec::new();
+            for feature in &features {
+                serde_json::to_writer(&mut body, feature)?;
+                body.push(b'\n');
+            }
+            insert_paging_link_header(&mut headers, &links);
+            return Ok((headers, body).into_response());
+        }
+        ItemsFormat::Gml => {
+            let encoding = GmlEncoding::new(
+                base_url,
+                &collection_id,
+                &collection.table_name,
+                response_crs,
+            );
+            let body = encoding.feature_collection(
+                &features,
+                number_matched,
+                &links,
+                &chrono::Utc::now().to_rfc3339(),
+            )?;
+            return Ok((headers, body).into_response());
+        }
     }
 
     let collection = FeatureCollection {
         feature_type: "FeatureCollection".to_string(),
-        number_matched: Some(total_count as u64),
+        number_matched: number_matched.map(|total| total as u64),
         number_returned: Some(features.len() as u64),
         features,
         links: Some(links),
         timestamp: Some(chrono::Utc::now().to_rfc3339()),
     };
 
-    let mut headers = HeaderMap::new();
-    headers.insert(header::CONTENT_TYPE, media_type::GEOJSON.parse().unwrap());
-    headers.insert(
-        "Content-Crs",
-        content_crs_header(response_crs).parse().unwrap(),
-    );
-
     Ok((headers, Json(collection)).into_response())
 }
 
+/// Put the next and previous page links of a listing in a `Link` header
+fn insert_paging_link_header(headers: &mut HeaderMap, links: &[Link]) {
+    let link_header = links
+        .iter()
+        .filter(|link| link.rel == rel::NEXT || link.rel == rel::PREV)
+        .map(|link| format!("<{}>; rel=\"{}\"", link.href, link.rel))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if !link_header.is_empty()
+        && let Ok(value) = link_header.parse()
+    {
+        headers.insert(header::LINK, value);
+    }
+}
+
+/// Encodings of item listings, selected with `f`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ItemsFormat {
+    GeoJson,
+    Csv(CsvGeometry),
+    Gml,
+    NdJson,
+}
+
+impl ItemsFormat {
+    fn from_params(params: &FeatureQueryParams) -> AppResult<Self> {
+        match params.f.as_deref() {
+            None | Some("json") | Some("geojson") => Ok(Self::GeoJson),
+            Some("csv") => Ok(Self::Csv(
+                params
+                    .csv_geometry
+                    .as_deref()
+                    .map(CsvGeometry::from_param)
+                    .transpose()?
+                    .unwrap_or_default(),
+            )),
+            Some("gml") => Ok(Self::Gml),
+            Some("ndjson") => Ok(Self::NdJson),
+            Some(other) => Err(AppError::BadRequest(format!(
+                "Unsupported format: {} (supported: json, csv, gml, ndjson)",
+                other
+            ))),
+        }
+    }
+
+    fn media_type(self) -> &'static str {
+        match self {
+            Self::GeoJson => media_type::GEOJSON,
+            Self::Csv(_) => media_type::CSV,
+            Self::Gml => media_type::GML_SF0,
+            Self::NdJson => media_type::NDJSON,
+        }
+    }
+
+    /// Query string selecting this format in links
+    fn query(self, params: &FeatureQueryParams) -> String {
+        match (self, &params.csv_geometry) {
+            (Self::GeoJson, _) => String::new(),
+            (Self::Csv(_), Some(geometry)) => format!("f=csv&csv-geometry={}", geometry),
+            (Self::Csv(_), None) => "f=csv".to_string(),
+            (Self::Gml, _) => "f=gml".to_string(),
+            (Self::NdJson, _) => "f=ndjson".to_string(),
+        }
+    }
+}
+
+/// Whether the `Prefer` header asks for `return=minimal` (RFC 7240)
+fn prefers_minimal(headers: &HeaderMap) -> bool {
+    headers
+        .get_all("Prefer")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|preference| {
+            preference
+                .split(';')
+                .next()
+                .is_some_and(|p| p.trim().eq_ignore_ascii_case("return=minimal"))
+        })
+}
+
 fn list_features_docs(op: TransformOperation) -> TransformOperation {
     op.summary("List features")
-        .description("Returns a paginated list of features in a collection, with optional spatial, temporal, and CQL filtering")
+        .description("Returns a paginated list of features in a collection, with optional spatial, temporal, and CQL filtering. `numberMatched` is estimated for large results; use `count=true` for an exact count, or `count=false` (or `Prefer: return=minimal`) to omit it. With `f=csv` the features are returned as CSV with the geometry as WKT (or `csv-geometry=lonlat` for longitude/latitude columns) and flattened properties, and paging links in the `Link` header. With `f=gml` vector features are returned as a GML 3.2 (Simple Features level 0) feature collection whose application schema is at `/collections/{collectionId}/schema.xsd`. With `f=ndjson` the features are returned as newline-delimited GeoJSON, one feature per line, with paging links in the `Link` header. With `geom`, a secondary geometry column listed in the collection schema's `x-geometryColumns` is returned and filtered by `bbox` or `intersects` instead of the primary geometry. `intersects` takes a GeoJSON geometry in WGS 84 (as in STAC search), e.g. a drawn area of interest, and can't be combined with `bbox`. `within-distance=lon,lat,meters` returns the features within a distance of a WGS 84 point, measured in meters on the spheroid whatever the storage CRS. With `clip=true` vector geometries are cut to the `bbox` or `intersects` area, so a small viewport doesn't receive whole country-sized polygons. `Last-Modified` is when the features or metadata of the collection last changed, and `If-Modified-Since` is answered with 304 Not Modified when they haven't since. `resultType=ids` returns only the ids of the matching features, as `{ids, numberMatched, numberReturned, links}`, and `resultType=hits` only the exact `numberMatched` with no features, e.g. for sync and QA tools")
         .tag("Features")
         .response_with::<200, Json<FeatureCollection>, _>(|res| {
             res.description("List of features")
         })
+        .response_with::<304, (), _>(|res| {
+            res.description("The collection hasn't changed since If-Modified-Since")
+        })
+}
+
+fn head_features_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Check features")
+        .description("Returns the headers of a page of features without the body")
+        .tag("Features")
+        .response_with::<200, (), _>(|res| res.description("Feature collection headers"))
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
 }
 
 /// Path parameters for single feature endpoints
@@ -224,34 +477,33 @@ pub struct FeaturePath {
 pub async fn get_feature(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
-    State((service, collection_service)): State<(Arc<FeatureService>, Arc<CollectionService>)>,
+    State(service): State<Arc<FeatureService>>,
     path: FeaturePath,
+    ResolvedCollection(collection): ResolvedCollection,
     Query(params): Query<FeatureQueryParams>,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = collection_service
-        .check_alias_redirect(&collection_id)
-        .await?
-    {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::LOCATION,
-            format!(
-                "{}/collections/{}/items/{}",
-                config.base_url, new_name, path.feature_id
-            )
-            .parse()
-            .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, headers).into_response());
-    }
-
+    let collection_id = collection.canonical_name.clone();
     let feature_id = path.feature_id;
     let target_crs = parse_crs_param(params.crs.as_deref())?;
+    let gml = match params.f.as_deref() {
+        None | Some("json") | Some("geojson") => false,
+        Some("gml") if collection.collection_type == "vector" => true,
+        Some(other) => {
+            return Err(AppError::BadRequest(format!(
+                "Unsupported format for this feature: {}",
+                other
+            )));
+        }
+    };
 
     let (feature, version, storage_srid) = service
-        .get_feature(&user.username, &collection_id, feature_id, target_crs)
+        .get_feature(
+            &user.username,
+            &collection_id,
+            feature_id,
+            target_crs,
+            params.geom.as_deref(),
+        )
         .await?
         .ok_or_else(|| {
             AppError::NotFound(format!(
@@ -288,40 +540,129 @@ pub async fn get_feature(
     );
     headers.insert(header::ETAG, format!("\"{}\"", version).parse().unwrap());
 
+    if gml {
+        let encoding = GmlEncoding::new(
+            base_url,
+            &collection_id,
+            &collection.table_name,
+            response_crs,
+        );
+        headers.insert(header::CONTENT_TYPE, media_type::GML_SF0.parse().unwrap());
+        return Ok((headers, encoding.feature(&feature)?).into_response());
+    }
+
     Ok((headers, Json(feature)).into_response())
 }
 
 fn get_feature_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Get feature")
-        .description("Returns a single feature by ID from a collection")
+        .description("Returns a single feature by ID from a collection, as GeoJSON or, with `f=gml`, as GML 3.2 (Simple Features level 0)")
         .tag("Features")
         .response_with::<200, Json<Feature>, _>(|res| res.description("Feature details"))
         .response_with::<404, (), _>(|res| res.description("Feature not found"))
 }
 
+fn head_feature_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Check feature")
+        .description(
+            "Returns the headers of a feature (ETag, Content-Length, Content-Crs) without the body",
+        )
+        .tag("Features")
+        .response_with::<200, (), _>(|res| res.description("Feature headers"))
+        .response_with::<404, (), _>(|res| res.description("Feature not found"))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn create_feature(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
-    State((service, collection_service)): State<(Arc<FeatureService>, Arc<CollectionService>)>,
-    path: CollectionItemsPath,
-    Json(request): Json<CreateFeatureRequest>,
+    State(service): State<Arc<FeatureService>>,
+    _path: CollectionItemsPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    Query(params): Query<IngestParams>,
+    headers: HeaderMap,
+    validate_only: ValidateOnly,
+    body: IngestBody,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = collection_service
-        .check_alias_redirect(&collection_id)
-        .await?
-    {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::LOCATION,
-            format!("{}/collections/{}/items", config.base_url, new_name)
-                .parse()
-                .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, headers).into_response());
+    let service = if validate_only.0 {
+        Arc::new(service.validate_only())
+    } else {
+        service
+    };
+    // Geometries in another CRS than the storage CRS are transformed into it
+    let service = match parse_content_crs_header(&headers)? {
+        Some(srid) => Arc::new(service.with_input_crs(srid)),
+        None => service,
+    };
+    let collection_id = collection.canonical_name.clone();
+    let on_conflict = params.on_conflict()?;
+    // If-Match names the collection version; the write fails if the
+    // collection changes before it is committed, e.g. by a schema change
+    let expected_version = etag::extract_expected_version(&headers)?;
+    // The body is received before any transaction starts, so a slow upload
+    // doesn't hold a database connection; features of a FeatureCollection are
+    // then inserted in batches while the rest of it is still being parsed
+    let spooled = ingest::spool(body.0, config.limits.max_ingest_bytes).await?;
+    let (mut features, parser) =
+        ingest::parse(spooled.body().await?, config.limits.max_ingest_bytes);
+    let mut bulk = None;
+    let mut batch = Vec::with_capacity(ingest::INGEST_BATCH_SIZE);
+    while let Some(feature) = features.recv().await {
+        if bulk.is_none() {
+            bulk = Some(
+                service
+                    .begin_bulk_insert(&collection_id, expected_version, on_conflict.clone())
+                    .await?,
+            );
+        }
+        batch.push((feature.geometry, feature.properties));
+        if batch.len() == ingest::INGEST_BATCH_SIZE
+            && let Some(bulk) = bulk.as_mut()
+        {
+            bulk.insert(std::mem::take(&mut batch)).await?;
+        }
     }
 
+    let parsed = parser
+        .await
+        .map_err(|e| AppError::Internal(format!("Ingest task failed: {}", e)))??;
+    let request = match parsed {
+        ParsedBody::Feature(_) if on_conflict.is_some() => {
+            return Err(AppError::BadRequest(
+                "on-conflict is only supported when ingesting a FeatureCollection".to_string(),
+            ));
+        }
+        ParsedBody::Feature(request) => *request,
+        ParsedBody::Collection(_) => {
+            let mut bulk = match bulk {
+                Some(bulk) => bulk,
+                None => {
+                    service
+                        .begin_bulk_insert(&collection_id, expected_version, on_conflict)
+                        .await?
+                }
+            };
+            if !batch.is_empty() {
+                bulk.insert(batch).await?;
+            }
+            let counts = bulk.commit().await?;
+
+            let response = BulkIngestResponse {
+                number_inserted: counts.inserted,
+                number_updated: counts.updated,
+                number_skipped: counts.skipped,
+                links: vec![
+                    Link::new(
+                        format!("{}/collections/{}/items", config.base_url, collection_id),
+                        rel::ITEMS,
+                    )
+                    .with_type(media_type::GEOJSON),
+                ],
+            };
+            return Ok(validate_only.respond((StatusCode::CREATED, Json(response)).into_response()));
+        }
+    };
+
     // Extract datetime from properties if present
     let datetime = request
         .properties
@@ -341,6 +682,7 @@ pub async fn create_feature(
                 &request.properties,
                 datetime,
                 request.assets.as_ref(),
+                expected_version,
             )
             .await?
     } else {
@@ -349,6 +691,7 @@ pub async fn create_feature(
             .create_feature(
                 &user.username,
                 &collection_id,
+                expected_version,
                 &request.geometry,
                 &request.properties,
             )
@@ -365,6 +708,7 @@ pub async fn create_feature(
                         &request.properties,
                         datetime,
                         None,
+                        expected_version,
                     )
                     .await?
             }
@@ -387,48 +731,60 @@ pub async fn create_feature(
     );
     headers.insert(header::ETAG, format!("\"{}\"", version).parse().unwrap());
 
-    Ok((StatusCode::CREATED, headers, Json(feature)).into_response())
+    Ok(validate_only.respond((StatusCode::CREATED, headers, Json(feature)).into_response()))
 }
 
 fn create_feature_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Create feature")
         .description(
-            "Creates a new feature in a collection. Supports both vector features and STAC items.",
+            "Creates a new feature in a collection. Supports both vector features and STAC items. \
+             A FeatureCollection body is ingested into a vector collection in a single transaction; \
+             the body is received in full, large ones on disk, before it is ingested. With \
+             `on-conflict-property`, features whose value of that property matches an existing \
+             feature are not inserted again: `on-conflict=update` replaces the existing feature \
+             when it differs, `on-conflict=skip` keeps it. Declaring the property unique in the \
+             collection schema makes the lookup fast. If-Match is optional; with the \
+             collection's ETag, the write fails with 412 if the collection changes before it is \
+             committed, so ingestion pipelines can detect concurrent schema changes or renames \
+             and abort; a failed FeatureCollection ingest inserts nothing. With \
+             `Prefer: handling=validate-only` (or `dryRun=true`) the features are validated, \
+             including geometry and database constraints, and rolled back instead of committed; \
+             the response tells what would have been created, with status 200. A `Content-Crs` \
+             header (e.g. `<http://www.opengis.net/def/crs/EPSG/0/3006>`) gives the CRS of the \
+             geometries, which are then transformed into the storage CRS; without it they are \
+             taken to be in the storage CRS.",
         )
         .tag("Features")
         .response_with::<201, Json<Feature>, _>(|res| {
             res.description("Feature created successfully")
         })
         .response_with::<400, (), _>(|res| res.description("Invalid request"))
+        .response_with::<412, (), _>(|res| res.description("Collection has been modified"))
+        .response_with::<507, (), _>(|res| {
+            res.description("The collection's owner is over its storage quota")
+        })
 }
 
 pub async fn update_feature(
-    Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
-    State((service, collection_service)): State<(Arc<FeatureService>, Arc<CollectionService>)>,
+    State(service): State<Arc<FeatureService>>,
     path: FeaturePath,
+    ResolvedCollection(collection): ResolvedCollection,
     headers: HeaderMap,
-    Json(request): Json<UpdateFeatureRequest>,
+    validate_only: ValidateOnly,
+    MergePatchBody(request): MergePatchBody<UpdateFeatureRequest>,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = collection_service
-        .check_alias_redirect(&collection_id)
-        .await?
-    {
-        let mut redirect_headers = HeaderMap::new();
-        redirect_headers.insert(
-            header::LOCATION,
-            format!(
-                "{}/collections/{}/items/{}",
-                config.base_url, new_name, path.feature_id
-            )
-            .parse()
-            .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, redirect_headers).into_response());
-    }
-
+    let service = if validate_only.0 {
+        Arc::new(service.validate_only())
+    } else {
+        service
+    };
+    // Geometries in another CRS than the storage CRS are transformed into it
+    let service = match parse_content_crs_header(&headers)? {
+        Some(srid) => Arc::new(service.with_input_crs(srid)),
+        None => service,
+    };
+    let collection_id = collection.canonical_name.clone();
     let feature_id = path.feature_id;
     // If-Match header is optional - when present, enables optimistic locking
     let expected_version = etag::extract_expected_version(&headers)?;
@@ -443,7 +799,7 @@ pub async fn update_feature(
         .map(|dt| dt.with_timezone(&chrono::Utc));
 
     // Try vector update first, fall back to item update
-    let (feature, new_version) = match service
+    let written = match service
         .update_feature(
             &user.username,
             &collection_id,
@@ -454,7 +810,7 @@ pub async fn update_feature(
         )
         .await
     {
-        Ok(result) => result,
+        Ok(result) => Ok(result),
         Err(AppError::NotFound(_)) if request.assets.is_some() || datetime.is_some() => {
             // May be a STAC item, try that
             service
@@ -468,7 +824,7 @@ pub async fn update_feature(
                     datetime,
                     request.assets.as_ref(),
                 )
-                .await?
+                .await
         }
         Err(AppError::BadRequest(msg)) if msg.contains("vector") => {
             // Collection is not vector, try as STAC item
@@ -483,7 +839,29 @@ pub async fn update_feature(
                     datetime,
                     request.assets.as_ref(),
                 )
-                .await?
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    let (feature, new_version) = match written {
+        Ok(result) => result,
+        Err(AppError::PreconditionFailed(description)) => {
+            let mut client = serde_json::Map::new();
+            if let Some(geometry) = &request.geometry {
+                client.insert("geometry".to_string(), geometry.clone());
+            }
+            if let Some(properties) = &request.properties {
+                client.insert("properties".to_string(), properties.clone());
+            }
+            return conflict::feature_conflict(
+                &service,
+                &user.username,
+                &collection_id,
+                feature_id,
+                description,
+                &client.into(),
+            )
+            .await;
         }
         Err(e) => return Err(e),
     };
@@ -491,47 +869,46 @@ pub async fn update_feature(
     let mut response_headers = HeaderMap::new();
     response_headers.insert(header::ETAG, etag::create_etag_header(new_version)?);
 
-    Ok((response_headers, Json(feature)).into_response())
+    Ok(validate_only.respond((response_headers, Json(feature)).into_response()))
 }
 
 fn update_feature_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Update feature (partial)")
-        .description("Partially updates a feature using JSON Merge Patch. If-Match header is optional; when provided, enables optimistic locking.")
+        .description("Partially updates a feature using JSON Merge Patch. If-Match header is optional; when provided, enables optimistic locking. With `Prefer: handling=validate-only` (or `dryRun=true`) the write is validated and rolled back instead of committed. A `Content-Crs` header gives the CRS of the geometry, which is then transformed into the storage CRS.")
         .tag("Features")
         .response_with::<200, Json<Feature>, _>(|res| {
             res.description("Feature updated successfully")
         })
         .response_with::<404, (), _>(|res| res.description("Feature not found"))
-        .response_with::<412, (), _>(|res| res.description("Precondition failed (ETag mismatch)"))
+        .response_with::<412, Json<ConflictResponse>, _>(|res| {
+            res.description("Precondition failed (ETag mismatch); the body holds the current feature and the fields that conflict")
+        })
+        .response_with::<409, (), _>(|res| res.description("Feature is locked by another user"))
+        .response_with::<507, (), _>(|res| {
+            res.description("The collection's owner is over its storage quota")
+        })
 }
 
 pub async fn replace_feature(
-    Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
-    State((service, collection_service)): State<(Arc<FeatureService>, Arc<CollectionService>)>,
+    State(service): State<Arc<FeatureService>>,
     path: FeaturePath,
+    ResolvedCollection(collection): ResolvedCollection,
     headers: HeaderMap,
-    Json(request): Json<CreateFeatureRequest>,
+    validate_only: ValidateOnly,
+    JsonBody(request): JsonBody<CreateFeatureRequest>,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = collection_service
-        .check_alias_redirect(&collection_id)
-        .await?
-    {
-        let mut redirect_headers = HeaderMap::new();
-        redirect_headers.insert(
-            header::LOCATION,
-            format!(
-                "{}/collections/{}/items/{}",
-                config.base_url, new_name, path.feature_id
-            )
-            .parse()
-            .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, redirect_headers).into_response());
-    }
-
+    let service = if validate_only.0 {
+        Arc::new(service.validate_only())
+    } else {
+        service
+    };
+    // Geometries in another CRS than the storage CRS are transformed into it
+    let service = match parse_content_crs_header(&headers)? {
+        Some(srid) => Arc::new(service.with_input_crs(srid)),
+        None => service,
+    };
+    let collection_id = collection.canonical_name.clone();
     let feature_id = path.feature_id;
     // If-Match header is optional - when present, enables optimistic locking
     let expected_version = etag::extract_expected_version(&headers)?;
@@ -545,7 +922,7 @@ pub async fn replace_feature(
         .map(|dt| dt.with_timezone(&chrono::Utc));
 
     // Try vector replace first, fall back to item replace
-    let (feature, new_version) = if request.assets.is_some() {
+    let written = if request.assets.is_some() {
         // Has assets, must be a STAC item
         service
             .replace_item(
@@ -558,7 +935,7 @@ pub async fn replace_feature(
                 datetime,
                 request.assets.as_ref(),
             )
-            .await?
+            .await
     } else {
         match service
             .replace_feature(
@@ -571,7 +948,7 @@ pub async fn replace_feature(
             )
             .await
         {
-            Ok(result) => result,
+            Ok(result) => Ok(result),
             Err(AppError::NotFound(_)) => {
                 // May be a STAC item
                 service
@@ -585,73 +962,91 @@ pub async fn replace_feature(
                         datetime,
                         None,
                     )
-                    .await?
+                    .await
             }
-            Err(e) => return Err(e),
+            Err(e) => Err(e),
+        }
+    };
+    let (feature, new_version) = match written {
+        Ok(result) => result,
+        Err(AppError::PreconditionFailed(description)) => {
+            let client = serde_json::json!({
+                "geometry": request.geometry,
+                "properties": request.properties,
+            });
+            return conflict::feature_conflict(
+                &service,
+                &user.username,
+                &collection_id,
+                feature_id,
+                description,
+                &client,
+            )
+            .await;
         }
+        Err(e) => return Err(e),
     };
 
     let mut response_headers = HeaderMap::new();
     response_headers.insert(header::ETAG, etag::create_etag_header(new_version)?);
 
-    Ok((response_headers, Json(feature)).into_response())
+    Ok(validate_only.respond((response_headers, Json(feature)).into_response()))
 }
 
 fn replace_feature_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Replace feature")
-        .description("Fully replaces a feature in a collection. If-Match header is optional; when provided, enables optimistic locking.")
+        .description("Fully replaces a feature in a collection. If-Match header is optional; when provided, enables optimistic locking. With `Prefer: handling=validate-only` (or `dryRun=true`) the write is validated and rolled back instead of committed. A `Content-Crs` header gives the CRS of the geometry, which is then transformed into the storage CRS.")
         .tag("Features")
         .response_with::<200, Json<Feature>, _>(|res| {
             res.description("Feature replaced successfully")
         })
         .response_with::<404, (), _>(|res| res.description("Feature not found"))
-        .response_with::<412, (), _>(|res| res.description("Precondition failed (ETag mismatch)"))
+        .response_with::<412, Json<ConflictResponse>, _>(|res| {
+            res.description("Precondition failed (ETag mismatch); the body holds the current feature and the fields that conflict")
+        })
+        .response_with::<409, (), _>(|res| res.description("Feature is locked by another user"))
+        .response_with::<507, (), _>(|res| {
+            res.description("The collection's owner is over its storage quota")
+        })
 }
 
 pub async fn delete_feature(
-    Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
-    State((service, collection_service)): State<(Arc<FeatureService>, Arc<CollectionService>)>,
+    State(service): State<Arc<FeatureService>>,
     path: FeaturePath,
+    ResolvedCollection(collection): ResolvedCollection,
     headers: HeaderMap,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = collection_service
-        .check_alias_redirect(&collection_id)
-        .await?
-    {
-        let mut redirect_headers = HeaderMap::new();
-        redirect_headers.insert(
-            header::LOCATION,
-            format!(
-                "{}/collections/{}/items/{}",
-                config.base_url, new_name, path.feature_id
-            )
-            .parse()
-            .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, redirect_headers).into_response());
-    }
-
+    let collection_id = collection.canonical_name.clone();
     let feature_id = path.feature_id;
     // If-Match header is optional - when present, enables optimistic locking
     let expected_version = etag::extract_expected_version(&headers)?;
 
     // Try vector delete first, fall back to item delete
-    match service
+    let deleted = match service
         .delete_feature(&user.username, &collection_id, feature_id, expected_version)
         .await
     {
-        Ok(()) => {}
         Err(AppError::NotFound(_)) => {
             // May be a STAC item
             service
                 .delete_item(&user.username, &collection_id, feature_id, expected_version)
-                .await?;
+                .await
         }
-        Err(e) => return Err(e),
+        result => result,
+    };
+    if let Err(AppError::PreconditionFailed(description)) = deleted {
+        return conflict::feature_conflict(
+            &service,
+            &user.username,
+            &collection_id,
+            feature_id,
+            description,
+            &serde_json::json!({}),
+        )
+        .await;
     }
+    deleted?;
 
     Ok(StatusCode::NO_CONTENT.into_response())
 }
@@ -662,25 +1057,33 @@ fn delete_feature_docs(op: TransformOperation) -> TransformOperation {
         .tag("Features")
         .response_with::<204, (), _>(|res| res.description("Feature deleted"))
         .response_with::<404, (), _>(|res| res.description("Feature not found"))
-        .response_with::<412, (), _>(|res| res.description("Precondition failed (ETag mismatch)"))
+        .response_with::<412, Json<ConflictResponse>, _>(|res| {
+            res.description("Precondition failed (ETag mismatch); the body holds the current feature and the fields that conflict")
+        })
+        .response_with::<409, (), _>(|res| res.description("Feature is locked by another user"))
 }
 
-pub fn routes(
-    service: Arc<FeatureService>,
-    collection_service: Arc<CollectionService>,
-) -> ApiRouter {
-    ApiRouter::new()
+pub fn routes(service: Arc<FeatureService>) -> ApiRouter {
+    // Feature ingest accepts FeatureCollections far larger than the default
+    // body limit; the ingest parser enforces its own limit while streaming
+    let ingest_routes = ApiRouter::new()
         .api_route(
             "/collections/{collection_id}/items",
             get_with(list_features, list_features_docs)
+                .head_with(list_features, head_features_docs)
                 .post_with(create_feature, create_feature_docs),
         )
+        .layer(DefaultBodyLimit::disable());
+
+    ApiRouter::new()
+        .merge(ingest_routes)
         .api_route(
             "/collections/{collection_id}/items/{feature_id}",
             get_with(get_feature, get_feature_docs)
+                .head_with(get_feature, head_feature_docs)
                 .put_with(replace_feature, replace_feature_docs)
                 .patch_with(update_feature, update_feature_docs)
                 .delete_with(delete_feature, delete_feature_docs),
         )
-        .with_state((service, collection_service))
+        .with_state(service)
 }
@@ -0,0 +1,401 @@
+use aide::OperationInput;
+use axum::{
+    Json,
+    body::Body,
+    extract::{FromRequest, Request},
+    response::{IntoResponse, Response},
+};
+use futures::{StreamExt, TryStreamExt};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_util::io::{ReaderStream, StreamReader, SyncIoBridge};
+use uuid::Uuid;
+
+use super::handlers::CreateFeatureRequest;
+use crate::api::body;
+use crate::error::{AppError, AppResult};
+use crate::services::{ConflictAction, OnConflict};
+
+/// Number of features inserted per batch during bulk ingest
+pub const INGEST_BATCH_SIZE: usize = 1000;
+
+/// Request bodies up to this size are spooled in memory, larger ones on disk
+const SPOOL_MEMORY_BYTES: usize = 8 * 1024 * 1024;
+
+/// Query parameters for creating features
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct IngestParams {
+    /// What to do with features of a FeatureCollection whose
+    /// `on-conflict-property` matches an existing feature: `update` or `skip`
+    pub on_conflict: Option<String>,
+
+    /// Property identifying features across ingests, e.g. `external_id`
+    pub on_conflict_property: Option<String>,
+}
+
+impl IngestParams {
+    /// How features matching existing ones are handled, if at all
+    pub fn on_conflict(&self) -> AppResult<Option<OnConflict>> {
+        let action = match self.on_conflict.as_deref() {
+            None => None,
+            Some("update") => Some(ConflictAction::Update),
+            Some("skip") => Some(ConflictAction::Skip),
+            Some(other) => {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid on-conflict: {} (supported: update, skip)",
+                    other
+                )));
+            }
+        };
+        match (action, &self.on_conflict_property) {
+            (None, None) => Ok(None),
+            (Some(action), Some(property)) if !property.trim().is_empty() => Ok(Some(OnConflict {
+                property: property.clone(),
+                action,
+            })),
+            _ => Err(AppError::BadRequest(
+                "on-conflict and on-conflict-property must be given together".to_string(),
+            )),
+        }
+    }
+}
+
+/// Raw body of a feature creation request (a Feature or a FeatureCollection)
+///
+/// The body is not buffered in memory; [`spool`] receives it and [`parse`]
+/// streams it so that arbitrarily large FeatureCollections can be ingested
+/// with bounded memory.
+pub struct IngestBody(pub Body);
+
+impl<S: Send + Sync> FromRequest<S> for IngestBody {
+    type Rejection = Response;
+
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        body::require_content_type(req.headers(), body::CREATE_MEDIA_TYPES)
+            .map_err(|e| e.into_response())?;
+        Ok(IngestBody(req.into_body()))
+    }
+}
+
+impl OperationInput for IngestBody {
+    fn operation_input(
+        ctx: &mut aide::generate::GenContext,
+        operation: &mut aide::openapi::Operation,
+    ) {
+        Json::<CreateFeatureRequest>::operation_input(ctx, operation);
+        body::set_request_media_types(operation, body::CREATE_MEDIA_TYPES);
+    }
+}
+
+/// Request body received in full, so that ingest does not wait on the client
+pub enum SpooledBody {
+    Memory(bytes::Bytes),
+    File(SpoolFile),
+}
+
+/// Spool file of a request body, removed when dropped
+pub struct SpoolFile(PathBuf);
+
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.0).ok();
+    }
+}
+
+impl SpooledBody {
+    /// Stream the spooled body
+    pub async fn body(&self) -> AppResult<Body> {
+        match self {
+            SpooledBody::Memory(bytes) => Ok(Body::from(bytes.clone())),
+            SpooledBody::File(file) => {
+                let file = tokio::fs::File::open(&file.0).await?;
+                Ok(Body::from_stream(ReaderStream::new(file)))
+            }
+        }
+    }
+}
+
+/// Receive a request body of at most `max_bytes`, on disk when it is large
+///
+/// Bulk ingest runs in one transaction; receiving the body first keeps a slow
+/// upload from holding a database connection.
+pub async fn spool(body: Body, max_bytes: u64) -> AppResult<SpooledBody> {
+    spool_with_limit(body, max_bytes, SPOOL_MEMORY_BYTES).await
+}
+
+async fn spool_with_limit(
+    body: Body,
+    max_bytes: u64,
+    memory_bytes: usize,
+) -> AppResult<SpooledBody> {
+    let mut stream = body.into_data_stream();
+    let mut buffer = Vec::new();
+    let mut spool_file: Option<(SpoolFile, tokio::fs::File)> = None;
+    let mut seen = 0u64;
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk
+            .map_err(|e| AppError::BadRequest(format!("Failed to read request body: {}", e)))?;
+        seen += chunk.len() as u64;
+        if seen > max_bytes {
+            return Err(AppError::BadRequest(format!(
+                "Request body exceeds the maximum size of {} bytes",
+                max_bytes
+            )));
+        }
+
+        match spool_file.as_mut() {
+            Some((_, file)) => file.write_all(&chunk).await?,
+            None if buffer.len() + chunk.len() > memory_bytes => {
+                let dir = std::env::temp_dir().join("spatialvault");
+                tokio::fs::create_dir_all(&dir).await?;
+                let path = dir.join(format!("ingest-{}.json", Uuid::new_v4()));
+                let mut file = tokio::fs::File::create(&path).await?;
+                let guard = SpoolFile(path);
+                file.write_all(&std::mem::take(&mut buffer)).await?;
+                file.write_all(&chunk).await?;
+                spool_file = Some((guard, file));
+            }
+            None => buffer.extend_from_slice(&chunk),
+        }
+    }
+
+    match spool_file {
+        Some((guard, mut file)) => {
+            file.flush().await?;
+            Ok(SpooledBody::File(guard))
+        }
+        None => Ok(SpooledBody::Memory(buffer.into())),
+    }
+}
+
+/// Result of parsing a feature creation body
+pub enum ParsedBody {
+    /// A single Feature
+    Feature(Box<CreateFeatureRequest>),
+    /// A FeatureCollection whose features were streamed to the channel
+    Collection(u64),
+}
+
+/// Start parsing a request body in the background
+///
+/// Features of a FeatureCollection are sent to the returned channel as they
+/// are parsed; the join handle yields the outcome once the body is consumed.
+/// Dropping the receiver aborts parsing.
+pub fn parse(
+    body: Body,
+    max_bytes: u64,
+) -> (
+    mpsc::Receiver<CreateFeatureRequest>,
+    JoinHandle<AppResult<ParsedBody>>,
+) {
+    let (tx, rx) = mpsc::channel(INGEST_BATCH_SIZE);
+    let too_large = Arc::new(AtomicBool::new(false));
+
+    let mut seen = 0u64;
+    let exceeded = too_large.clone();
+    let stream = body
+        .into_data_stream()
+        .map_err(std::io::Error::other)
+        .map(move |chunk| {
+            let chunk = chunk?;
+            seen += chunk.len() as u64;
+            if seen > max_bytes {
+                exceeded.store(true, Ordering::Relaxed);
+                return Err(std::io::Error::other("request body too large"));
+            }
+            Ok(chunk)
+        });
+
+    let handle = tokio::task::spawn_blocking(move || {
+        let reader = std::io::BufReader::new(SyncIoBridge::new(StreamReader::new(stream)));
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+
+        let parsed = de::Deserializer::deserialize_map(&mut deserializer, BodyVisitor { tx: &tx })
+            .and_then(|parsed| deserializer.end().map(|_| parsed));
+
+        parsed.map_err(|e| {
+            if too_large.load(Ordering::Relaxed) {
+                AppError::BadRequest(format!(
+                    "Request body exceeds the maximum size of {} bytes",
+                    max_bytes
+                ))
+            } else {
+                AppError::BadRequest(format!("Invalid GeoJSON: {}", e))
+            }
+        })
+    });
+
+    (rx, handle)
+}
+
+/// Top-level visitor: streams `features` and collects all other members
+struct BodyVisitor<'a> {
+    tx: &'a mpsc::Sender<CreateFeatureRequest>,
+}
+
+impl<'de> Visitor<'de> for BodyVisitor<'_> {
+    type Value = ParsedBody;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a GeoJSON Feature or FeatureCollection")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<ParsedBody, A::Error> {
+        let mut members = serde_json::Map::new();
+        let mut streamed = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "features" {
+                streamed = Some(map.next_value_seed(FeaturesSeed { tx: self.tx })?);
+            } else {
+                members.insert(key, map.next_value()?);
+            }
+        }
+
+        match streamed {
+            Some(count) => Ok(ParsedBody::Collection(count)),
+            None => serde_json::from_value(serde_json::Value::Object(members))
+                .map(|feature| ParsedBody::Feature(Box::new(feature)))
+                .map_err(de::Error::custom),
+        }
+    }
+}
+
+/// Streams the elements of the `features` array into the channel
+struct FeaturesSeed<'a> {
+    tx: &'a mpsc::Sender<CreateFeatureRequest>,
+}
+
+impl<'de> DeserializeSeed<'de> for FeaturesSeed<'_> {
+    type Value = u64;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<u64, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FeaturesSeed<'_> {
+    type Value = u64;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of GeoJSON Features")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<u64, A::Error> {
+        let mut count = 0;
+        while let Some(feature) = seq.next_element::<CreateFeatureRequest>()? {
+            self.tx
+                .blocking_send(feature)
+                .map_err(|_| de::Error::custom("ingest aborted"))?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn parse_all(body: &'static str, max_bytes: u64) -> (AppResult<ParsedBody>, usize) {
+        let (mut rx, handle) = parse(Body::from(body), max_bytes);
+        let mut received = 0;
+        while rx.recv().await.is_some() {
+            received += 1;
+        }
+        (handle.await.unwrap(), received)
+    }
+
+    #[tokio::test]
+    async fn test_parse_feature() {
+        let body = r#"{"type":"Feature","geometry":{"type":"Point","coordinates":[1,2]},"properties":{"a":1}}"#;
+        let (parsed, received) = parse_all(body, 1024).await;
+        assert!(matches!(parsed, Ok(ParsedBody::Feature(_))));
+        assert_eq!(received, 0);
+    }
+
+    #[tokio::test]
+    async fn test_parse_feature_collection() {
+        let body = r#"{"features":[
+            {"type":"Feature","geometry":{"type":"Point","coordinates":[1,2]},"properties":{}},
+            {"type":"Feature","geometry":{"type":"Point","coordinates":[3,4]},"properties":{}}
+        ],"type":"FeatureCollection"}"#;
+        let (parsed, received) = parse_all(body, 1024).await;
+        assert!(matches!(parsed, Ok(ParsedBody::Collection(2))));
+        assert_eq!(received, 2);
+    }
+
+    #[test]
+    fn test_ingest_params_on_conflict() {
+        let params = |action: Option<&str>, property: Option<&str>| IngestParams {
+            on_conflict: action.map(str::to_string),
+            on_conflict_property: property.map(str::to_string),
+        };
+        assert_eq!(params(None, None).on_conflict().unwrap(), None);
+        assert_eq!(
+            params(Some("skip"), Some("external_id"))
+                .on_conflict()
+                .unwrap(),
+            Some(OnConflict {
+                property: "external_id".to_string(),
+                action: ConflictAction::Skip,
+            })
+        );
+        assert!(
+            params(Some("merge"), Some("external_id"))
+                .on_conflict()
+                .is_err()
+        );
+        assert!(params(Some("update"), None).on_conflict().is_err());
+        assert!(params(None, Some("external_id")).on_conflict().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_spool() {
+        let body = r#"{"type":"FeatureCollection","features":[]}"#;
+        let spooled = spool_with_limit(Body::from(body), 1024, 1024)
+            .await
+            .unwrap();
+        assert!(matches!(spooled, SpooledBody::Memory(_)));
+
+        // Larger bodies go to disk, which is cleaned up with the spool
+        let spooled = spool_with_limit(Body::from(body), 1024, 8).await.unwrap();
+        let SpooledBody::File(file) = &spooled else {
+            panic!("expected a spool file");
+        };
+        let path = file.0.clone();
+        let (mut rx, handle) = parse(spooled.body().await.unwrap(), 1024);
+        assert!(rx.recv().await.is_none());
+        assert!(matches!(
+            handle.await.unwrap(),
+            Ok(ParsedBody::Collection(0))
+        ));
+        drop(spooled);
+        assert!(!path.exists());
+
+        match spool_with_limit(Body::from(body), 10, 8).await {
+            Err(AppError::BadRequest(msg)) => assert!(msg.contains("maximum size")),
+            _ => panic!("expected size error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parse_too_large() {
+        let body = r#"{"type":"FeatureCollection","features":[]}"#;
+        let (parsed, _) = parse_all(body, 10).await;
+        match parsed {
+            Err(AppError::BadRequest(msg)) => assert!(msg.contains("maximum size")),
+            _ => panic!("expected size error"),
+        }
+    }
+}
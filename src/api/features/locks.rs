@@ -0,0 +1,187 @@
+//! Advisory feature locks for collaborative editing
+//!
+//! A user editing a feature can lock it, so others get a conflict when they
+//! write to it instead of silently overwriting the edit. Locks expire, so a
+//! forgotten lock doesn't block a feature for good.
+
+use aide::{
+    axum::{ApiRouter, routing::post_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::body::JsonBody;
+use crate::api::collections::ResolvedCollection;
+use crate::auth::AuthenticatedUser;
+use crate::error::{AppError, AppResult};
+use crate::services::{FeatureLock, FeatureService};
+
+/// Lifetime of a lock when the request doesn't give one
+const DEFAULT_LOCK_SECS: u64 = 15 * 60;
+
+/// Longest lifetime of a lock; longer edits renew their lock
+const MAX_LOCK_SECS: u64 = 24 * 60 * 60;
+
+/// Request to lock a feature
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LockRequest {
+    /// Seconds until the lock expires (default 900, at most 86400)
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+}
+
+/// A lock on a feature
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureLockResponse {
+    pub feature_id: Uuid,
+    /// User holding the lock
+    pub locked_by: String,
+    /// When the lock was taken
+    #[schemars(with = "String")]
+    pub locked_at: DateTime<Utc>,
+    /// When the lock expires unless it is renewed
+    #[schemars(with = "String")]
+    pub expires: DateTime<Utc>,
+}
+
+impl From<FeatureLock> for FeatureLockResponse {
+    fn from(lock: FeatureLock) -> Self {
+        Self {
+            feature_id: lock.feature_id,
+            locked_by: lock.locked_by,
+            locked_at: lock.locked_at,
+            expires: lock.expires_at,
+        }
+    }
+}
+
+/// Path parameters for feature locks
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/items/{feature_id}/lock")]
+pub struct FeatureLockPath {
+    /// The collection identifier
+    pub collection_id: String,
+    /// The feature UUID
+    pub feature_id: Uuid,
+}
+
+/// Lock a feature, or renew the caller's lock
+pub async fn lock_feature(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<FeatureService>>,
+    path: FeatureLockPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    JsonBody(request): JsonBody<LockRequest>,
+) -> AppResult<Json<FeatureLockResponse>> {
+    let expires_in = request.expires_in.unwrap_or(DEFAULT_LOCK_SECS);
+    if expires_in == 0 || expires_in > MAX_LOCK_SECS {
+        return Err(AppError::BadRequest(format!(
+            "expiresIn must be between 1 and {} seconds",
+            MAX_LOCK_SECS
+        )));
+    }
+
+    let lock = service
+        .lock_feature(
+            &user.username,
+            &collection.canonical_name,
+            path.feature_id,
+            chrono::Duration::seconds(expires_in as i64),
+        )
+        .await?;
+
+    Ok(Json(lock.into()))
+}
+
+fn lock_feature_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Lock feature")
+        .description(
+            "Locks a feature or item for editing until the lock expires or is released. While \
+             it is locked, updates and deletes by other users fail with 409. Locking a feature \
+             again renews the caller's lock. Locks are advisory: they don't stop bulk ingest. \
+             Only users who may write the collection can lock its features.",
+        )
+        .tag("Features")
+        .response_with::<200, Json<FeatureLockResponse>, _>(|res| res.description("The lock"))
+        .response_with::<400, (), _>(|res| res.description("Invalid expiry"))
+        .response_with::<403, (), _>(|res| res.description("No write access to the collection"))
+        .response_with::<404, (), _>(|res| res.description("Feature not found"))
+        .response_with::<409, (), _>(|res| res.description("Feature is locked by another user"))
+}
+
+/// Get the lock on a feature
+pub async fn get_feature_lock(
+    State(service): State<Arc<FeatureService>>,
+    path: FeatureLockPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> AppResult<Json<FeatureLockResponse>> {
+    let lock = service
+        .get_feature_lock(&collection.canonical_name, path.feature_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Feature is not locked: {}", path.feature_id)))?;
+
+    Ok(Json(lock.into()))
+}
+
+fn get_feature_lock_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get feature lock")
+        .description("Returns who holds the lock on a feature and when it expires")
+        .tag("Features")
+        .response_with::<200, Json<FeatureLockResponse>, _>(|res| res.description("The lock"))
+        .response_with::<404, (), _>(|res| res.description("Feature is not locked"))
+}
+
+/// Release a feature lock
+pub async fn unlock_feature(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<FeatureService>>,
+    path: FeatureLockPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> AppResult<StatusCode> {
+    // The collection owner may break locks left behind by others
+    let break_lock = collection.owner == user.username;
+    service
+        .unlock_feature(
+            &user.username,
+            &collection.canonical_name,
+            path.feature_id,
+            break_lock,
+        )
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn unlock_feature_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Unlock feature")
+        .description(
+            "Releases the lock on a feature. Only the user holding the lock, or the owner of \
+             the collection, may release it.",
+        )
+        .tag("Features")
+        .response_with::<204, (), _>(|res| res.description("Lock released"))
+        .response_with::<403, (), _>(|res| res.description("Feature is locked by another user"))
+        .response_with::<404, (), _>(|res| res.description("Feature is not locked"))
+}
+
+pub fn routes(service: Arc<FeatureService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/items/{feature_id}/lock",
+            post_with(lock_feature, lock_feature_docs)
+                .get_with(get_feature_lock, get_feature_lock_docs)
+                .delete_with(unlock_feature, unlock_feature_docs),
+        )
+        .with_state(service)
+}
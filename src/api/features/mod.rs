@@ -1,5 +1,11 @@
+pub mod assets;
 pub mod crs;
+pub mod csv;
+pub mod export;
+pub mod gml;
 pub mod handlers;
+pub mod ingest;
+pub mod locks;
 pub mod query;
 
 pub use handlers::*;
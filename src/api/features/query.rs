@@ -1,7 +1,10 @@
 use schemars::JsonSchema;
 use serde::Deserialize;
 
+use uuid::Uuid;
+
 use crate::error::{AppError, AppResult};
+use crate::services::ResultType;
 
 // Re-export cql2 crate for parsing
 pub use cql2;
@@ -10,9 +13,8 @@ pub use cql2;
 #[derive(Debug, Default, Deserialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub struct FeatureQueryParams {
-    /// Maximum number of features to return
-    #[serde(default = "default_limit")]
-    pub limit: u32,
+    /// Maximum number of features to return (defaults to the collection's page size)
+    pub limit: Option<u32>,
 
     /// Offset for pagination
     #[serde(default)]
@@ -24,6 +26,15 @@ pub struct FeatureQueryParams {
     /// CRS for bbox coordinates
     pub bbox_crs: Option<String>,
 
+    /// Spatial filter: a GeoJSON geometry in WGS 84, as in STAC search
+    pub intersects: Option<String>,
+
+    /// Distance filter: lon,lat,meters in WGS 84
+    pub within_distance: Option<String>,
+
+    /// Clip returned geometries to the `bbox` or `intersects` area
+    pub clip: Option<bool>,
+
     /// CRS for response geometry
     pub crs: Option<String>,
 
@@ -44,24 +55,176 @@ pub struct FeatureQueryParams {
 
     /// Sort by property (prefix with - for descending)
     pub sortby: Option<String>,
+
+    /// Only return the features with these ids (comma-separated UUIDs)
+    pub ids: Option<String>,
+
+    /// Whether to compute `numberMatched`: `false` omits it, `true` forces an
+    /// exact count; by default large results get an estimate
+    pub count: Option<bool>,
+
+    /// What to return of the matching features: `results` (the default),
+    /// `ids` for only their ids, or `hits` for only `numberMatched`
+    #[serde(rename = "resultType")]
+    pub result_type: Option<String>,
+
+    /// Response format: `json` (GeoJSON, the default), `csv`, `gml` or `ndjson`
+    pub f: Option<String>,
+
+    /// Geometry columns in CSV output: `wkt` (default) or `lonlat`
+    pub csv_geometry: Option<String>,
+
+    /// Geometry column returned and filtered by `bbox`: `geometry` (default)
+    /// or a secondary geometry column of the collection
+    pub geom: Option<String>,
+}
+
+/// Default and maximum page size for item listings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageLimits {
+    pub default: u32,
+    pub max: u32,
+}
+
+impl Default for PageLimits {
+    fn default() -> Self {
+        Self {
+            default: 10,
+            max: 10000,
+        }
+    }
+}
+
+impl PageLimits {
+    /// Apply a collection's overrides (stored as nullable integers)
+    pub fn with_overrides(self, default: Option<i32>, max: Option<i32>) -> Self {
+        let max = max.map_or(self.max, |max| max.max(1) as u32);
+        let default = default.map_or(self.default, |default| default.max(1) as u32);
+        Self {
+            default: default.min(max),
+            max,
+        }
+    }
+}
+
+/// GeoJSON geometry types accepted by `intersects`
+const GEOMETRY_TYPES: &[&str] = &[
+    "Point",
+    "MultiPoint",
+    "LineString",
+    "MultiLineString",
+    "Polygon",
+    "MultiPolygon",
+    "GeometryCollection",
+];
+
+/// Parse the GeoJSON geometry of an `intersects` parameter
+///
+/// Only checks the shape of the object; PostGIS reads the coordinates.
+pub fn parse_intersects(intersects: &str) -> AppResult<serde_json::Value> {
+    let geometry: serde_json::Value = serde_json::from_str(intersects)
+        .map_err(|e| AppError::BadRequest(format!("intersects is not valid JSON: {}", e)))?;
+
+    let geometry_type = geometry.get("type").and_then(|t| t.as_str());
+    if !geometry_type.is_some_and(|t| GEOMETRY_TYPES.contains(&t)) {
+        return Err(AppError::BadRequest(
+            "intersects must be a GeoJSON geometry".to_string(),
+        ));
+    }
+    let members = if geometry_type == Some("GeometryCollection") {
+        "geometries"
+    } else {
+        "coordinates"
+    };
+    if !geometry.get(members).is_some_and(|m| m.is_array()) {
+        return Err(AppError::BadRequest(format!(
+            "intersects geometry must have {}",
+            members
+        )));
+    }
+
+    Ok(geometry)
 }
 
-fn default_limit() -> u32 {
-    10
+/// Parse a `within-distance` parameter into longitude, latitude and meters
+pub fn parse_within_distance(within_distance: &str) -> AppResult<[f64; 3]> {
+    let values: Vec<f64> = within_distance
+        .split(',')
+        .map(|part| part.trim().parse::<f64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| {
+            AppError::BadRequest(format!(
+                "Invalid within-distance '{}': must be lon,lat,meters",
+                within_distance
+            ))
+        })?;
+    let [lon, lat, meters] = values[..] else {
+        return Err(AppError::BadRequest(
+            "within-distance must have 3 values: lon,lat,meters".to_string(),
+        ));
+    };
+
+    if !(-180.0..=180.0).contains(&lon) || !(-90.0..=90.0).contains(&lat) {
+        return Err(AppError::BadRequest(
+            "within-distance coordinates must be a WGS 84 longitude and latitude".to_string(),
+        ));
+    }
+    if !(meters.is_finite() && meters > 0.0) {
+        return Err(AppError::BadRequest(
+            "within-distance must be a positive number of meters".to_string(),
+        ));
+    }
+
+    Ok([lon, lat, meters])
+}
+
+/// Parse a comma-separated list of feature ids
+pub fn parse_ids(ids: &str) -> AppResult<Vec<Uuid>> {
+    ids.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            Uuid::parse_str(id)
+                .map_err(|_| AppError::BadRequest(format!("Invalid feature id: {}", id)))
+        })
+        .collect()
 }
 
 impl FeatureQueryParams {
-    pub fn validate(&self) -> AppResult<()> {
-        if self.limit == 0 {
+    /// Resolve the requested page size against the limits
+    ///
+    /// With `ids` and no explicit `limit`, the page holds all requested ids.
+    pub fn page_limit(&self, limits: &PageLimits) -> AppResult<u32> {
+        let requested_ids = match &self.ids {
+            Some(ids) => Some(parse_ids(ids)?.len() as u32),
+            None => None,
+        };
+        if requested_ids.is_some_and(|count| count > limits.max) {
+            return Err(AppError::BadRequest(format!(
+                "At most {} ids can be requested at once",
+                limits.max
+            )));
+        }
+
+        let limit = self
+            .limit
+            .or(requested_ids.filter(|count| *count > 0))
+            .unwrap_or(limits.default);
+        if limit == 0 {
             return Err(AppError::BadRequest("Limit must be at least 1".to_string()));
         }
 
-        if self.limit > 10000 {
-            return Err(AppError::BadRequest(
-                "Limit cannot exceed 10000".to_string(),
-            ));
+        if limit > limits.max {
+            return Err(AppError::BadRequest(format!(
+                "Limit cannot exceed {}",
+                limits.max
+            )));
         }
 
+        Ok(limit)
+    }
+
+    pub fn validate(&self) -> AppResult<()> {
         if let Some(ref bbox) = self.bbox {
             let coords = self.parse_bbox(bbox)?;
             // Validate bbox coordinates are sensible
@@ -77,6 +240,15 @@ impl FeatureQueryParams {
             }
         }
 
+        if let Some(ref intersects) = self.intersects {
+            if self.bbox.is_some() {
+                return Err(AppError::BadRequest(
+                    "Only one of bbox and intersects may be given".to_string(),
+                ));
+            }
+            parse_intersects(intersects)?;
+        }
+
         if let Some(ref dt) = self.datetime {
             self.validate_datetime(dt)?;
         }
@@ -148,6 +320,28 @@ impl FeatureQueryParams {
         Ok(())
     }
 
+    /// Parse the `resultType` parameter
+    pub fn parse_result_type(&self) -> AppResult<ResultType> {
+        let result_type = match self.result_type.as_deref() {
+            None | Some("results") => ResultType::Results,
+            Some("ids") => ResultType::Ids,
+            Some("hits") => ResultType::Hits,
+            Some(other) => {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid resultType: {} (supported: results, ids, hits)",
+                    other
+                )));
+            }
+        };
+        if result_type == ResultType::Hits && self.count == Some(false) {
+            return Err(AppError::BadRequest(
+                "resultType=hits returns only the count and can't be combined with count=false"
+                    .to_string(),
+            ));
+        }
+        Ok(result_type)
+    }
+
     /// Parse properties parameter into list of property names
     pub fn parse_properties(&self) -> Option<Vec<String>> {
         self.properties.as_ref().map(|p| {
@@ -177,6 +371,390 @@ impl FeatureQueryParams {
     }
 }
 
+/// A declared relation from the filtered collection to another collection
+#[derive(Debug, Clone)]
+pub struct JoinRelation {
+    /// Name used in filters, as in `owner.name`
+    pub name: String,
+    /// Quoted `schema.table` of the related collection
+    pub table: String,
+    /// Property of the filtered collection holding the key
+    pub property: String,
+    /// Property of the related collection the key refers to; `id` for feature ids
+    pub references: String,
+}
+
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+impl JoinRelation {
+    /// Text of a property, or the feature id, of a table's features
+    fn key_sql(table: &str, property: &str) -> String {
+        match property {
+            "id" => format!("{}id::text", table),
+            property => format!("{}properties->>{}", table, quote_literal(property)),
+        }
+    }
+
+    /// Subquery selecting an attribute of the related feature
+    fn attribute_sql(&self, attribute: &str, prefix: &str) -> String {
+        let value = match attribute {
+            "geometry" => "related.geometry".to_string(),
+            attribute => Self::key_sql("related.", attribute),
+        };
+        format!(
+            "(SELECT {} FROM {} related WHERE {} = {} LIMIT 1)",
+            value,
+            self.table,
+            Self::key_sql("related.", &self.references),
+            Self::key_sql(prefix, &self.property)
+        )
+    }
+}
+
+/// Type of a computed property's value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    Number,
+    Text,
+    Boolean,
+    Geometry,
+}
+
+impl ValueType {
+    /// JSON Schema type of values of this type
+    pub fn json_type(self) -> &'static str {
+        match self {
+            Self::Number => "number",
+            Self::Text => "string",
+            Self::Boolean => "boolean",
+            Self::Geometry => "object",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Number => "a number",
+            Self::Text => "text",
+            Self::Boolean => "a boolean",
+            Self::Geometry => "a geometry",
+        }
+    }
+}
+
+/// A function computed properties may call
+struct ComputedFunction {
+    /// Lowercase name in expressions
+    name: &'static str,
+    /// SQL function
+    sql: &'static str,
+    args: &'static [ValueType],
+    result: ValueType,
+}
+
+const COMPUTED_FUNCTIONS: &[ComputedFunction] = &[
+    ComputedFunction {
+        name: "st_area",
+        sql: "ST_Area",
+        args: &[ValueType::Geometry],
+        result: ValueType::Number,
+    },
+    ComputedFunction {
+        name: "st_length",
+        sql: "ST_Length",
+        args: &[ValueType::Geometry],
+        result: ValueType::Number,
+    },
+    ComputedFunction {
+        name: "st_perimeter",
+        sql: "ST_Perimeter",
+        args: &[ValueType::Geometry],
+        result: ValueType::Number,
+    },
+    ComputedFunction {
+        name: "st_x",
+        sql: "ST_X",
+        args: &[ValueType::Geometry],
+        result: ValueType::Number,
+    },
+    ComputedFunction {
+        name: "st_y",
+        sql: "ST_Y",
+        args: &[ValueType::Geometry],
+        result: ValueType::Number,
+    },
+    ComputedFunction {
+        name: "st_npoints",
+        sql: "ST_NPoints",
+        args: &[ValueType::Geometry],
+        result: ValueType::Number,
+    },
+    ComputedFunction {
+        name: "st_geometrytype",
+        sql: "ST_GeometryType",
+        args: &[ValueType::Geometry],
+        result: ValueType::Text,
+    },
+    ComputedFunction {
+        name: "upper",
+        sql: "UPPER",
+        args: &[ValueType::Text],
+        result: ValueType::Text,
+    },
+    ComputedFunction {
+        name: "lower",
+        sql: "LOWER",
+        args: &[ValueType::Text],
+        result: ValueType::Text,
+    },
+    ComputedFunction {
+        name: "trim",
+        sql: "TRIM",
+        args: &[ValueType::Text],
+        result: ValueType::Text,
+    },
+    ComputedFunction {
+        name: "length",
+        sql: "CHAR_LENGTH",
+        args: &[ValueType::Text],
+        result: ValueType::Number,
+    },
+    ComputedFunction {
+        name: "abs",
+        sql: "ABS",
+        args: &[ValueType::Number],
+        result: ValueType::Number,
+    },
+    ComputedFunction {
+        name: "round",
+        sql: "ROUND",
+        args: &[ValueType::Number],
+        result: ValueType::Number,
+    },
+    ComputedFunction {
+        name: "floor",
+        sql: "FLOOR",
+        args: &[ValueType::Number],
+        result: ValueType::Number,
+    },
+    ComputedFunction {
+        name: "ceil",
+        sql: "CEIL",
+        args: &[ValueType::Number],
+        result: ValueType::Number,
+    },
+];
+
+/// A computed property translated to SQL
+#[derive(Debug, Clone)]
+pub struct ComputedSql {
+    pub name: String,
+    pub sql: String,
+    pub value_type: ValueType,
+}
+
+/// Translation of computed property expressions
+///
+/// Expressions are CQL2 text over the feature's geometry, id and properties
+/// (`name` and `properties.name` are the same), using arithmetic,
+/// `concat(...)` and the functions in [`COMPUTED_FUNCTIONS`].
+struct ComputedExpr<'a> {
+    prefix: &'a str,
+}
+
+impl ComputedExpr<'_> {
+    fn unsupported(what: &str) -> AppError {
+        AppError::BadRequest(format!("Unsupported in computed properties: {}", what))
+    }
+
+    fn value_type(expr: &cql2::Expr) -> AppResult<ValueType> {
+        match expr {
+            cql2::Expr::Property { property } if property == "geometry" => Ok(ValueType::Geometry),
+            cql2::Expr::Property { .. } | cql2::Expr::Literal(_) => Ok(ValueType::Text),
+            cql2::Expr::Float(_) => Ok(ValueType::Number),
+            cql2::Expr::Bool(_) => Ok(ValueType::Boolean),
+            cql2::Expr::Operation { op, .. } => match op.to_lowercase().as_str() {
+                "+" | "-" | "*" | "/" => Ok(ValueType::Number),
+                "concat" => Ok(ValueType::Text),
+                name => COMPUTED_FUNCTIONS
+                    .iter()
+                    .find(|f| f.name == name)
+                    .map(|f| f.result)
+                    .ok_or_else(|| Self::unsupported(&format!("function {}", op))),
+            },
+            other => Err(Self::unsupported(&format!("{:?}", other))),
+        }
+    }
+
+    /// SQL of a property, read as the given type
+    ///
+    /// JSON values of another type read as NULL rather than failing the query.
+    fn property_sql(&self, property: &str, value_type: ValueType) -> AppResult<String> {
+        let prefix = self.prefix;
+        let name = property.strip_prefix("properties.").unwrap_or(property);
+        match (name, value_type) {
+            ("geometry", ValueType::Geometry) => Ok(format!("{}geometry", prefix)),
+            ("id", ValueType::Text) => Ok(format!("{}id::text", prefix)),
+            (_, ValueType::Text) => Ok(format!("{}properties->>{}", prefix, quote_literal(name))),
+            (_, ValueType::Number | ValueType::Boolean) => {
+                let (json_type, sql_type) = if value_type == ValueType::Number {
+                    ("number", "double precision")
+                } else {
+                    ("boolean", "boolean")
+                };
+                Ok(format!(
+                    "CASE WHEN jsonb_typeof({p}properties->{n}) = '{}' \
+                     THEN ({p}properties->>{n})::{} END",
+                    json_type,
+                    sql_type,
+                    p = prefix,
+                    n = quote_literal(name)
+                ))
+            }
+            (_, ValueType::Geometry) => Err(AppError::BadRequest(format!(
+                "Property {} is not a geometry",
+                property
+            ))),
+        }
+    }
+
+    /// SQL of an expression whose value has the given type
+    fn to_sql(&self, expr: &cql2::Expr, value_type: ValueType) -> AppResult<String> {
+        if let cql2::Expr::Property { property } = expr
+            && property != "geometry"
+        {
+            return self.property_sql(property, value_type);
+        }
+
+        let actual = Self::value_type(expr)?;
+        let sql = match expr {
+            cql2::Expr::Property { property } => self.property_sql(property, actual)?,
+            cql2::Expr::Float(value) => value.to_string(),
+            cql2::Expr::Literal(value) => quote_literal(value),
+            cql2::Expr::Bool(value) => value.to_string().to_uppercase(),
+            cql2::Expr::Operation { op, args } => self.operation_sql(op, args)?,
+            other => return Err(Self::unsupported(&format!("{:?}", other))),
+        };
+
+        match (actual, value_type) {
+            (actual, wanted) if actual == wanted => Ok(sql),
+            (ValueType::Number | ValueType::Boolean, ValueType::Text) => {
+                Ok(format!("({})::text", sql))
+            }
+            (actual, wanted) => Err(AppError::BadRequest(format!(
+                "Expected {} but got {} in computed property",
+                wanted.name(),
+                actual.name()
+            ))),
+        }
+    }
+
+    fn operation_sql(&self, op: &str, args: &[Box<cql2::Expr>]) -> AppResult<String> {
+        let op_lower = op.to_lowercase();
+        match op_lower.as_str() {
+            "+" | "-" | "*" | "/" => {
+                let [left, right] = args else {
+                    return Err(AppError::BadRequest(format!("{} requires 2 arguments", op)));
+                };
+                let left = self.to_sql(left, ValueType::Number)?;
+                let right = self.to_sql(right, ValueType::Number)?;
+                // Division by zero gives NULL rather than failing the query
+                if op_lower == "/" {
+                    Ok(format!("({} / NULLIF({}, 0))", left, right))
+                } else {
+                    Ok(format!("({} {} {})", left, op_lower, right))
+                }
+            }
+            "concat" => {
+                let parts = args
+                    .iter()
+                    .map(|arg| self.to_sql(arg, ValueType::Text))
+                    .collect::<AppResult<Vec<_>>>()?;
+                Ok(format!("CONCAT({})", parts.join(", ")))
+            }
+            name => {
+                let function = COMPUTED_FUNCTIONS
+                    .iter()
+                    .find(|f| f.name == name)
+                    .ok_or_else(|| Self::unsupported(&format!("function {}", op)))?;
+                if args.len() != function.args.len() {
+                    return Err(AppError::BadRequest(format!(
+                        "{} requires {} argument(s)",
+                        op,
+                        function.args.len()
+                    )));
+                }
+                let args = args
+                    .iter()
+                    .zip(function.args)
+                    .map(|(arg, value_type)| self.to_sql(arg, *value_type))
+                    .collect::<AppResult<Vec<_>>>()?;
+                Ok(format!("{}({})", function.sql, args.join(", ")))
+            }
+        }
+    }
+}
+
+/// Translate a computed property expression to SQL
+///
+/// Column references are prefixed with `prefix`. Expressions evaluating to
+/// a geometry are rejected.
+pub fn computed_to_sql(name: &str, expression: &str, prefix: &str) -> AppResult<ComputedSql> {
+    let expr = cql2::parse_text(expression.trim())
+        .map_err(|e| AppError::BadRequest(format!("Invalid expression for {}: {}", name, e)))?;
+    let value_type = ComputedExpr::value_type(&expr)?;
+    if value_type == ValueType::Geometry {
+        return Err(AppError::BadRequest(format!(
+            "Computed property {} cannot be a geometry",
+            name
+        )));
+    }
+    Ok(ComputedSql {
+        name: name.to_string(),
+        sql: ComputedExpr { prefix }.to_sql(&expr, value_type)?,
+        value_type,
+    })
+}
+
+/// Whether an expression refers to the feature's geometry
+pub fn expression_uses_geometry(expression: &str) -> bool {
+    fn uses_geometry(expr: &cql2::Expr) -> bool {
+        match expr {
+            cql2::Expr::Property { property } => property == "geometry",
+            cql2::Expr::Operation { args, .. } => args.iter().any(|arg| uses_geometry(arg)),
+            _ => false,
+        }
+    }
+    cql2::parse_text(expression.trim()).is_ok_and(|expr| uses_geometry(&expr))
+}
+
+/// Select expression of a feature's properties including computed ones
+pub fn properties_with_computed_sql(prefix: &str, computed: &[ComputedSql]) -> String {
+    if computed.is_empty() {
+        return format!("{}properties", prefix);
+    }
+    let pairs: Vec<String> = computed
+        .iter()
+        .map(|c| format!("{}, {}", quote_literal(&c.name), c.sql))
+        .collect();
+    format!(
+        "(COALESCE({}properties, '{{}}'::jsonb) || jsonb_build_object({}))",
+        prefix,
+        pairs.join(", ")
+    )
+}
+
+/// Context for translating a filter into SQL
+struct SqlContext<'a> {
+    /// Prefix of column references
+    prefix: &'a str,
+    /// Relations properties may refer to
+    relations: &'a [JoinRelation],
+    /// Computed properties, by name
+    computed: &'a [ComputedSql],
+}
+
 /// CQL2 parser using the cql2 crate with PostGIS-compatible SQL output
 pub struct Cql2Parser;
 
@@ -190,7 +768,39 @@ impl Cql2Parser {
             .map_err(|e| AppError::BadRequest(format!("CQL2 parse error: {}", e)))?;
 
         // Convert to PostGIS-compatible SQL
-        Self::expr_to_postgis_sql(&expr, property_prefix)
+        Self::expr_to_postgis_sql(
+            &expr,
+            &SqlContext {
+                prefix: property_prefix,
+                relations: &[],
+                computed: &[],
+            },
+        )
+    }
+
+    /// Parse a CQL2-text filter on a collection's feature table
+    ///
+    /// `relation.attribute` is looked up in the related collection, and
+    /// computed properties are evaluated. Columns of the filtered table are
+    /// qualified with `table`, since the lookups are subqueries with columns
+    /// of their own; computed properties must use the same qualification.
+    pub fn parse_to_sql_for_table(
+        filter: &str,
+        table: &str,
+        relations: &[JoinRelation],
+        computed: &[ComputedSql],
+    ) -> AppResult<String> {
+        let expr = cql2::parse_text(filter.trim())
+            .map_err(|e| AppError::BadRequest(format!("CQL2 parse error: {}", e)))?;
+
+        Self::expr_to_postgis_sql(
+            &expr,
+            &SqlContext {
+                prefix: &format!("{}.", table),
+                relations,
+                computed,
+            },
+        )
     }
 
     /// Parse a CQL2-json filter into SQL WHERE clause
@@ -198,11 +808,18 @@ impl Cql2Parser {
         let expr = cql2::parse_json(filter)
             .map_err(|e| AppError::BadRequest(format!("CQL2 JSON parse error: {}", e)))?;
 
-        Self::expr_to_postgis_sql(&expr, property_prefix)
+        Self::expr_to_postgis_sql(
+            &expr,
+            &SqlContext {
+                prefix: property_prefix,
+                relations: &[],
+                computed: &[],
+            },
+        )
     }
 
     /// Convert a CQL2 expression to PostGIS-compatible SQL
-    fn expr_to_postgis_sql(expr: &cql2::Expr, prefix: &str) -> AppResult<String> {
+    fn expr_to_postgis_sql(expr: &cql2::Expr, ctx: &SqlContext) -> AppResult<String> {
         match expr {
             // Boolean literals
             cql2::Expr::Bool(b) => Ok(if *b { "TRUE" } else { "FALSE" }.to_string()),
@@ -214,20 +831,20 @@ impl Cql2Parser {
             cql2::Expr::Literal(s) => Ok(format!("'{}'", s.replace('\'', "''"))),
 
             // Property reference
-            cql2::Expr::Property { property } => Ok(Self::property_to_sql(property, prefix)),
+            cql2::Expr::Property { property } => Ok(Self::property_to_sql(property, ctx)),
 
             // Null
             cql2::Expr::Null => Ok("NULL".to_string()),
 
             // Date (contains a boxed Expr that should be a Literal)
             cql2::Expr::Date { date } => {
-                let date_str = Self::expr_to_postgis_sql(date, prefix)?;
+                let date_str = Self::expr_to_postgis_sql(date, ctx)?;
                 Ok(format!("DATE {}", date_str))
             }
 
             // Timestamp (contains a boxed Expr that should be a Literal)
             cql2::Expr::Timestamp { timestamp } => {
-                let ts_str = Self::expr_to_postgis_sql(timestamp, prefix)?;
+                let ts_str = Self::expr_to_postgis_sql(timestamp, ctx)?;
                 Ok(format!("TIMESTAMP {}", ts_str))
             }
 
@@ -238,8 +855,8 @@ impl Cql2Parser {
                         "Interval must have 2 elements".to_string(),
                     ));
                 }
-                let start_sql = Self::expr_to_postgis_sql(&interval[0], prefix)?;
-                let end_sql = Self::expr_to_postgis_sql(&interval[1], prefix)?;
+                let start_sql = Self::expr_to_postgis_sql(&interval[0], ctx)?;
+                let end_sql = Self::expr_to_postgis_sql(&interval[1], ctx)?;
                 Ok(format!("TSTZRANGE({}, {})", start_sql, end_sql))
             }
 
@@ -252,7 +869,7 @@ impl Cql2Parser {
                 }
                 let coords: Vec<String> = bbox
                     .iter()
-                    .map(|e| Self::expr_to_postgis_sql(e, prefix))
+                    .map(|e| Self::expr_to_postgis_sql(e, ctx))
                     .collect::<AppResult<Vec<_>>>()?;
                 Ok(format!(
                     "ST_MakeEnvelope({}, {}, {}, {}, 4326)",
@@ -267,18 +884,18 @@ impl Cql2Parser {
             cql2::Expr::Array(items) => {
                 let items_sql: Vec<String> = items
                     .iter()
-                    .map(|e| Self::expr_to_postgis_sql(e, prefix))
+                    .map(|e| Self::expr_to_postgis_sql(e, ctx))
                     .collect::<AppResult<Vec<_>>>()?;
                 Ok(format!("ARRAY[{}]", items_sql.join(", ")))
             }
 
             // All operations (AND, OR, =, >, spatial functions, etc.)
-            cql2::Expr::Operation { op, args } => Self::operation_to_sql(op, args, prefix),
+            cql2::Expr::Operation { op, args } => Self::operation_to_sql(op, args, ctx),
         }
     }
 
     /// Convert CQL2 operations to PostGIS SQL
-    fn operation_to_sql(op: &str, args: &[Box<cql2::Expr>], prefix: &str) -> AppResult<String> {
+    fn operation_to_sql(op: &str, args: &[Box<cql2::Expr>], ctx: &SqlContext) -> AppResult<String> {
         let op_lower = op.to_lowercase();
 
         // Binary comparison/logical operators
@@ -286,14 +903,14 @@ impl Cql2Parser {
             "and" => {
                 let parts: Vec<String> = args
                     .iter()
-                    .map(|a| Self::expr_to_postgis_sql(a, prefix))
+                    .map(|a| Self::expr_to_postgis_sql(a, ctx))
                     .collect::<AppResult<Vec<_>>>()?;
                 return Ok(format!("({})", parts.join(" AND ")));
             }
             "or" => {
                 let parts: Vec<String> = args
                     .iter()
-                    .map(|a| Self::expr_to_postgis_sql(a, prefix))
+                    .map(|a| Self::expr_to_postgis_sql(a, ctx))
                     .collect::<AppResult<Vec<_>>>()?;
                 return Ok(format!("({})", parts.join(" OR ")));
             }
@@ -301,31 +918,31 @@ impl Cql2Parser {
                 if args.len() != 1 {
                     return Err(AppError::BadRequest("NOT requires 1 argument".to_string()));
                 }
-                let inner = Self::expr_to_postgis_sql(&args[0], prefix)?;
+                let inner = Self::expr_to_postgis_sql(&args[0], ctx)?;
                 return Ok(format!("NOT ({})", inner));
             }
-            "=" | "eq" => return Self::binary_op(args, "=", prefix),
-            "<>" | "!=" | "neq" => return Self::binary_op(args, "<>", prefix),
-            "<" | "lt" => return Self::binary_op(args, "<", prefix),
-            ">" | "gt" => return Self::binary_op(args, ">", prefix),
-            "<=" | "lte" => return Self::binary_op(args, "<=", prefix),
-            ">=" | "gte" => return Self::binary_op(args, ">=", prefix),
-            "+" => return Self::binary_op(args, "+", prefix),
-            "-" => return Self::binary_op(args, "-", prefix),
-            "*" => return Self::binary_op(args, "*", prefix),
-            "/" => return Self::binary_op(args, "/", prefix),
-            "%" => return Self::binary_op(args, "%", prefix),
-            "like" => return Self::binary_op(args, "LIKE", prefix),
-            "ilike" => return Self::binary_op(args, "ILIKE", prefix),
+            "=" | "eq" => return Self::binary_op(args, "=", ctx),
+            "<>" | "!=" | "neq" => return Self::binary_op(args, "<>", ctx),
+            "<" | "lt" => return Self::binary_op(args, "<", ctx),
+            ">" | "gt" => return Self::binary_op(args, ">", ctx),
+            "<=" | "lte" => return Self::binary_op(args, "<=", ctx),
+            ">=" | "gte" => return Self::binary_op(args, ">=", ctx),
+            "+" => return Self::binary_op(args, "+", ctx),
+            "-" => return Self::binary_op(args, "-", ctx),
+            "*" => return Self::binary_op(args, "*", ctx),
+            "/" => return Self::binary_op(args, "/", ctx),
+            "%" => return Self::binary_op(args, "%", ctx),
+            "like" => return Self::binary_op(args, "LIKE", ctx),
+            "ilike" => return Self::binary_op(args, "ILIKE", ctx),
             "between" => {
                 if args.len() != 3 {
                     return Err(AppError::BadRequest(
                         "BETWEEN requires 3 arguments".to_string(),
                     ));
                 }
-                let val = Self::expr_to_postgis_sql(&args[0], prefix)?;
-                let lower = Self::expr_to_postgis_sql(&args[1], prefix)?;
-                let upper = Self::expr_to_postgis_sql(&args[2], prefix)?;
+                let val = Self::expr_to_postgis_sql(&args[0], ctx)?;
+                let lower = Self::expr_to_postgis_sql(&args[1], ctx)?;
+                let upper = Self::expr_to_postgis_sql(&args[2], ctx)?;
                 return Ok(format!("{} BETWEEN {} AND {}", val, lower, upper));
             }
             "in" => {
@@ -334,10 +951,10 @@ impl Cql2Parser {
                         "IN requires at least 2 arguments".to_string(),
                     ));
                 }
-                let val = Self::expr_to_postgis_sql(&args[0], prefix)?;
+                let val = Self::expr_to_postgis_sql(&args[0], ctx)?;
                 let list: Vec<String> = args[1..]
                     .iter()
-                    .map(|a| Self::expr_to_postgis_sql(a, prefix))
+                    .map(|a| Self::expr_to_postgis_sql(a, ctx))
                     .collect::<AppResult<Vec<_>>>()?;
                 return Ok(format!("{} IN ({})", val, list.join(", ")));
             }
@@ -347,7 +964,7 @@ impl Cql2Parser {
                         "IS NULL requires 1 argument".to_string(),
                     ));
                 }
-                let inner = Self::expr_to_postgis_sql(&args[0], prefix)?;
+                let inner = Self::expr_to_postgis_sql(&args[0], ctx)?;
                 return Ok(format!("{} IS NULL", inner));
             }
             _ => {}
@@ -370,8 +987,8 @@ impl Cql2Parser {
                 if args.len() != 2 {
                     return Err(AppError::BadRequest(format!("{} requires 2 arguments", op)));
                 }
-                let arg1 = Self::expr_to_postgis_sql(&args[0], prefix)?;
-                let arg2 = Self::expr_to_postgis_sql(&args[1], prefix)?;
+                let arg1 = Self::expr_to_postgis_sql(&args[0], ctx)?;
+                let arg2 = Self::expr_to_postgis_sql(&args[1], ctx)?;
                 return Ok(format!("{}({}, {})", pg_name, arg1, arg2));
             }
         }
@@ -383,9 +1000,9 @@ impl Cql2Parser {
                     "S_DWITHIN requires 3 arguments".to_string(),
                 ));
             }
-            let geom1 = Self::expr_to_postgis_sql(&args[0], prefix)?;
-            let geom2 = Self::expr_to_postgis_sql(&args[1], prefix)?;
-            let distance = Self::expr_to_postgis_sql(&args[2], prefix)?;
+            let geom1 = Self::expr_to_postgis_sql(&args[0], ctx)?;
+            let geom2 = Self::expr_to_postgis_sql(&args[1], ctx)?;
+            let distance = Self::expr_to_postgis_sql(&args[2], ctx)?;
             return Ok(format!("ST_DWithin({}, {}, {})", geom1, geom2, distance));
         }
 
@@ -396,8 +1013,8 @@ impl Cql2Parser {
                     "T_INTERSECTS requires 2 arguments".to_string(),
                 ));
             }
-            let time1 = Self::expr_to_postgis_sql(&args[0], prefix)?;
-            let time2 = Self::expr_to_postgis_sql(&args[1], prefix)?;
+            let time1 = Self::expr_to_postgis_sql(&args[0], ctx)?;
+            let time2 = Self::expr_to_postgis_sql(&args[1], ctx)?;
             return Ok(format!("({} && {})", time1, time2));
         }
 
@@ -408,30 +1025,30 @@ impl Cql2Parser {
                     "A_CONTAINS requires 2 arguments".to_string(),
                 ));
             }
-            let arr = Self::expr_to_postgis_sql(&args[0], prefix)?;
-            let val = Self::expr_to_postgis_sql(&args[1], prefix)?;
+            let arr = Self::expr_to_postgis_sql(&args[0], ctx)?;
+            let val = Self::expr_to_postgis_sql(&args[1], ctx)?;
             return Ok(format!("({} @> {})", arr, val));
         }
 
         // Generic function call
         let args_sql: Vec<String> = args
             .iter()
-            .map(|a| Self::expr_to_postgis_sql(a, prefix))
+            .map(|a| Self::expr_to_postgis_sql(a, ctx))
             .collect::<AppResult<Vec<_>>>()?;
 
         Ok(format!("{}({})", op.to_uppercase(), args_sql.join(", ")))
     }
 
     /// Helper for binary operators
-    fn binary_op(args: &[Box<cql2::Expr>], sql_op: &str, prefix: &str) -> AppResult<String> {
+    fn binary_op(args: &[Box<cql2::Expr>], sql_op: &str, ctx: &SqlContext) -> AppResult<String> {
         if args.len() != 2 {
             return Err(AppError::BadRequest(format!(
                 "{} requires 2 arguments",
                 sql_op
             )));
         }
-        let left = Self::expr_to_postgis_sql(&args[0], prefix)?;
-        let right = Self::expr_to_postgis_sql(&args[1], prefix)?;
+        let left = Self::expr_to_postgis_sql(&args[0], ctx)?;
+        let right = Self::expr_to_postgis_sql(&args[1], ctx)?;
         Ok(format!("({} {} {})", left, sql_op, right))
     }
 
@@ -455,13 +1072,19 @@ impl Cql2Parser {
     }
 
     /// Convert property name to SQL
-    fn property_to_sql(property: &str, prefix: &str) -> String {
-        if property.contains('.') {
+    fn property_to_sql(property: &str, ctx: &SqlContext) -> String {
+        let prefix = ctx.prefix;
+        let name = property.strip_prefix("properties.").unwrap_or(property);
+        if let Some(computed) = ctx.computed.iter().find(|c| c.name == name) {
+            format!("({})", computed.sql)
+        } else if property.contains('.') {
             // Nested property access via JSONB
             let parts: Vec<&str> = property.split('.').collect();
             if parts[0] == "properties" {
                 // Access into properties JSONB column
                 format!("{}properties->>'{}'", prefix, parts[1..].join("'->>"))
+            } else if let Some(relation) = ctx.relations.iter().find(|r| r.name == parts[0]) {
+                relation.attribute_sql(&parts[1..].join("."), prefix)
             } else {
                 format!("{}\"{}\"", prefix, parts[0])
             }
@@ -490,29 +1113,112 @@ mod tests {
         assert!(params.parse_bbox("inf,0,1,1").is_err());
     }
 
+    #[test]
+    fn test_parse_intersects() {
+        let polygon = r#"{"type":"Polygon","coordinates":[[[0,0],[1,0],[1,1],[0,0]]]}"#;
+        assert_eq!(parse_intersects(polygon).unwrap()["type"], "Polygon");
+        let collection = r#"{"type":"GeometryCollection","geometries":[]}"#;
+        assert!(parse_intersects(collection).is_ok());
+
+        assert!(parse_intersects("POINT(0 0)").is_err());
+        assert!(parse_intersects(r#"{"type":"Feature","geometry":null}"#).is_err());
+        assert!(parse_intersects(r#"{"type":"Point"}"#).is_err());
+
+        let params = FeatureQueryParams {
+            bbox: Some("0,0,1,1".to_string()),
+            intersects: Some(polygon.to_string()),
+            ..Default::default()
+        };
+        assert!(params.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_within_distance() {
+        assert_eq!(
+            parse_within_distance("18.07, 59.33,500").unwrap(),
+            [18.07, 59.33, 500.0]
+        );
+
+        assert!(parse_within_distance("18.07,59.33").is_err());
+        assert!(parse_within_distance("18.07,59.33,500,1").is_err());
+        assert!(parse_within_distance("a,59.33,500").is_err());
+        assert!(parse_within_distance("200,59.33,500").is_err());
+        assert!(parse_within_distance("18.07,59.33,0").is_err());
+        assert!(parse_within_distance("18.07,59.33,inf").is_err());
+    }
+
     #[test]
     fn test_validate_limit() {
         let mut params = FeatureQueryParams::default();
+        let limits = PageLimits::default();
+
+        // Default page size applies when no limit is given
+        assert_eq!(params.page_limit(&limits).unwrap(), 10);
 
         // Zero limit should fail
-        params.limit = 0;
-        assert!(params.validate().is_err());
+        params.limit = Some(0);
+        assert!(params.page_limit(&limits).is_err());
 
         // Valid limit
-        params.limit = 10;
-        params.bbox = None;
-        params.datetime = None;
-        assert!(params.validate().is_ok());
+        params.limit = Some(10);
+        assert!(params.page_limit(&limits).is_ok());
 
         // Excessive limit should fail
-        params.limit = 10001;
-        assert!(params.validate().is_err());
+        params.limit = Some(10001);
+        assert!(params.page_limit(&limits).is_err());
+
+        // ...unless the collection allows larger pages
+        let limits = limits.with_overrides(None, Some(50000));
+        assert_eq!(params.page_limit(&limits).unwrap(), 10001);
+    }
+
+    #[test]
+    fn test_parse_ids() {
+        let id = "6f1c2b9e-3a44-4f4a-9a57-0b8f4c6d2e11";
+        assert_eq!(parse_ids(&format!("{}, {}", id, id)).unwrap().len(), 2);
+        assert!(parse_ids("").unwrap().is_empty());
+        assert!(parse_ids("not-a-uuid").is_err());
+
+        let mut params = FeatureQueryParams::default();
+        params.ids = Some(format!("{},{},{}", id, id, id));
+        assert_eq!(params.page_limit(&PageLimits::default()).unwrap(), 3);
+
+        let limits = PageLimits { default: 1, max: 2 };
+        assert!(params.page_limit(&limits).is_err());
+    }
+
+    #[test]
+    fn test_parse_result_type() {
+        let mut params = FeatureQueryParams::default();
+        assert_eq!(params.parse_result_type().unwrap(), ResultType::Results);
+        params.result_type = Some("ids".to_string());
+        assert_eq!(params.parse_result_type().unwrap(), ResultType::Ids);
+        params.result_type = Some("hits".to_string());
+        assert_eq!(params.parse_result_type().unwrap(), ResultType::Hits);
+        params.count = Some(false);
+        assert!(params.parse_result_type().is_err());
+        params.result_type = Some("features".to_string());
+        assert!(params.parse_result_type().is_err());
+    }
+
+    #[test]
+    fn test_page_limit_overrides() {
+        let limits = PageLimits::default();
+        assert_eq!(limits.with_overrides(None, None), limits);
+        assert_eq!(
+            limits.with_overrides(Some(1000), Some(50000)),
+            PageLimits {
+                default: 1000,
+                max: 50000
+            }
+        );
+        // The default never exceeds the maximum
+        assert_eq!(limits.with_overrides(None, Some(5)).default, 5);
     }
 
     #[test]
     fn test_validate_bbox_bounds() {
         let mut params = FeatureQueryParams::default();
-        params.limit = 10;
         params.datetime = None;
 
         // Valid bbox
@@ -587,4 +1293,77 @@ mod tests {
         let sql = Cql2Parser::parse_to_sql("name = 'test'", "t.").unwrap();
         assert!(sql.contains("t."));
     }
+
+    #[test]
+    fn test_cql2_related_property() {
+        let relations = [JoinRelation {
+            name: "owner".to_string(),
+            table: "\"alice\".\"owners\"".to_string(),
+            property: "owner_id".to_string(),
+            references: "id".to_string(),
+        }];
+        let sql = Cql2Parser::parse_to_sql_for_table(
+            "owner.name = 'Alice' AND properties.area > 100",
+            "\"alice\".\"parcels\"",
+            &relations,
+            &[],
+        )
+        .unwrap();
+        assert!(sql.contains(
+            "(SELECT related.properties->>'name' FROM \"alice\".\"owners\" related \
+             WHERE related.id::text = \"alice\".\"parcels\".properties->>'owner_id' LIMIT 1)"
+        ));
+        assert!(sql.contains("\"alice\".\"parcels\".properties->>'area'"));
+
+        // Names that are not relations keep their meaning
+        let sql =
+            Cql2Parser::parse_to_sql_for_table("other.name = 'x'", "t", &relations, &[]).unwrap();
+        assert!(!sql.contains("SELECT"));
+    }
+
+    #[test]
+    fn test_computed_to_sql() {
+        let area = computed_to_sql("area", "ST_Area(geometry)", "").unwrap();
+        assert_eq!(area.sql, "ST_Area(geometry)");
+        assert_eq!(area.value_type, ValueType::Number);
+
+        let density =
+            computed_to_sql("density", "properties.population / ST_Area(geometry)", "t.").unwrap();
+        assert_eq!(
+            density.sql,
+            "(CASE WHEN jsonb_typeof(t.properties->'population') = 'number' \
+             THEN (t.properties->>'population')::double precision END / NULLIF(ST_Area(t.geometry), 0))"
+        );
+
+        let label = computed_to_sql("label", "concat(upper(name), ' (', id, ')')", "").unwrap();
+        assert_eq!(
+            label.sql,
+            "CONCAT(UPPER(properties->>'name'), ' (', id::text, ')')"
+        );
+        assert_eq!(label.value_type, ValueType::Text);
+
+        assert!(computed_to_sql("x", "pg_sleep(10)", "").is_err());
+        assert!(computed_to_sql("x", "geometry", "").is_err());
+        assert!(computed_to_sql("x", "ST_Area(name)", "").is_err());
+        assert!(computed_to_sql("x", "upper(ST_Area(geometry))", "").is_ok());
+
+        assert!(expression_uses_geometry(
+            "round(ST_Length(geometry) / 1000)"
+        ));
+        assert!(!expression_uses_geometry(
+            "concat(name, ' ', geometry_type)"
+        ));
+    }
+
+    #[test]
+    fn test_filter_on_computed_property() {
+        let computed = [computed_to_sql("area", "ST_Area(geometry)", "t.").unwrap()];
+        let sql = Cql2Parser::parse_to_sql_for_table("area > 100", "t", &[], &computed).unwrap();
+        assert_eq!(sql, "((ST_Area(t.geometry)) > 100)");
+
+        assert_eq!(
+            properties_with_computed_sql("", &computed),
+            "(COALESCE(properties, '{}'::jsonb) || jsonb_build_object('area', ST_Area(t.geometry)))"
+        );
+    }
 }
@@ -0,0 +1,384 @@
+//! Forwarded headers of trusted reverse proxies
+//!
+//! Behind a proxy the peer address is the proxy's, and the request may have
+//! reached it under another scheme and host than `base_url`. On connections
+//! from trusted proxies the middleware takes the client address from
+//! `X-Forwarded-For` and, when enabled, derives the base URL of generated
+//! links from `X-Forwarded-Proto` and `X-Forwarded-Host`.
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, header},
+    middleware::Next,
+    response::Response,
+};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use tracing::Span;
+
+use crate::config::Config;
+use crate::error::{AppError, AppResult};
+use crate::server::Peer;
+
+/// Most derived configurations kept; hosts beyond get a fresh one per request
+const MAX_DERIVED_CONFIGS: usize = 64;
+
+/// Address of the client, behind any trusted proxies
+///
+/// Missing for requests over a Unix socket that carry no `X-Forwarded-For`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// An address or network, e.g. `10.0.0.0/8`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IpNetwork {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    fn parse(network: &str) -> Option<Self> {
+        let (addr, prefix) = match network.split_once('/') {
+            Some((addr, prefix)) => (addr.parse().ok()?, Some(prefix.parse().ok()?)),
+            None => (network.parse().ok()?, None),
+        };
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Self { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        // IPv4 peers of dual-stack sockets show up as mapped IPv6 addresses
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            IpAddr::V4(_) => ip,
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_matches(u32::from(net).into(), u32::from(ip).into(), 32, self.prefix)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_matches(u128::from(net), u128::from(ip), 128, self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(net: u128, ip: u128, bits: u8, prefix: u8) -> bool {
+    if prefix == 0 {
+        return true;
+    }
+    let shift = bits - prefix;
+    net >> shift == ip >> shift
+}
+
+/// Trusted proxies and the configurations of derived base URLs
+pub struct Forwarding {
+    trusted: Vec<IpNetwork>,
+    config: Arc<Config>,
+    derived: RwLock<HashMap<String, Arc<Config>>>,
+}
+
+impl Forwarding {
+    pub fn new(config: Arc<Config>) -> AppResult<Self> {
+        let trusted = config
+            .proxy
+            .trusted_proxies
+            .iter()
+            .map(|proxy| {
+                IpNetwork::parse(proxy).ok_or_else(|| {
+                    AppError::Config(format!("Invalid trusted proxy address: {}", proxy))
+                })
+            })
+            .collect::<AppResult<_>>()?;
+        Ok(Self {
+            trusted,
+            config,
+            derived: RwLock::new(HashMap::new()),
+        })
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.trusted.iter().any(|network| network.contains(ip))
+    }
+
+    /// Address of the peer and whether its forwarded headers are honored
+    ///
+    /// Only local proxies can reach a Unix socket. Without the peer of the
+    /// connection, e.g. when the router is served without connect info,
+    /// forwarded headers are never trusted.
+    fn peer_trust(&self, peer: Option<&Peer>) -> (Option<IpAddr>, bool) {
+        match peer {
+            Some(Peer::Tcp(addr)) => (Some(addr.ip()), self.is_trusted(addr.ip())),
+            Some(Peer::Unix) => (None, true),
+            None => (None, false),
+        }
+    }
+
+    /// Client address, walking `X-Forwarded-For` back from the proxy
+    /// nearest to us until the first address that isn't a trusted proxy
+    ///
+    /// `peer` is `None` on Unix sockets, which only local proxies can reach.
+    fn client_ip(&self, peer: Option<IpAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+        if let Some(peer) = peer
+            && !self.is_trusted(peer)
+        {
+            return Some(peer);
+        }
+        let forwarded: Vec<IpAddr> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|addr| addr.trim().parse().ok())
+            .collect();
+        forwarded
+            .iter()
+            .rev()
+            .find(|ip| !self.is_trusted(**ip))
+            .or(forwarded.first())
+            .copied()
+            .or(peer)
+    }
+
+    /// Base URL from the forwarded protocol and host, keeping the path of
+    /// the configured one
+    fn derived_base_url(&self, headers: &HeaderMap) -> Option<String> {
+        let configured = url::Url::parse(&self.config.base_url).ok()?;
+        let host = first_value(headers, "x-forwarded-host")
+            .or_else(|| first_value(headers, header::HOST.as_str()))?;
+        if !is_valid_host(host) || !self.is_allowed_host(host) {
+            return None;
+        }
+        let scheme = match first_value(headers, "x-forwarded-proto") {
+            Some(proto) if proto.eq_ignore_ascii_case("https") => "https",
+            Some(proto) if proto.eq_ignore_ascii_case("http") => "http",
+            Some(_) => return None,
+            None => configured.scheme(),
+        };
+        Some(format!(
+            "{}://{}{}",
+            scheme,
+            host.to_ascii_lowercase(),
+            configured.path().trim_end_matches('/')
+        ))
+    }
+
+    fn is_allowed_host(&self, host: &str) -> bool {
+        let allowed = &self.config.proxy.allowed_hosts;
+        allowed.is_empty()
+            || allowed
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(host_name(host)))
+    }
+
+    /// Configuration whose `base_url` is `base_url`
+    fn config_for(&self, base_url: String) -> Arc<Config> {
+        if let Some(config) = self
+            .derived
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&base_url)
+        {
+            return config.clone();
+        }
+        let config = Arc::new(Config {
+            base_url: base_url.clone(),
+            ..(*self.config).clone()
+        });
+        let mut derived = self.derived.write().unwrap_or_else(|e| e.into_inner());
+        if derived.len() < MAX_DERIVED_CONFIGS {
+            derived.insert(base_url, config.clone());
+        }
+        config
+    }
+}
+
+fn first_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)?
+        .to_str()
+        .ok()?
+        .split(',')
+        .next()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// Whether a host header value is a plain `host[:port]`, so it can't smuggle
+/// a path or credentials into links
+fn is_valid_host(host: &str) -> bool {
+    host.len() <= 255
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '[' | ']'))
+}
+
+/// Host without the port
+fn host_name(host: &str) -> &str {
+    if host.starts_with('[') {
+        return host.split_once(']').map_or(host, |(name, _)| &name[1..]);
+    }
+    match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    }
+}
+
+/// Middleware recording the client address and, for trusted proxies with
+/// `derive_base_url`, replacing the configuration of the request with one
+/// of the derived base URL
+///
+/// Must run inside the layer providing the configuration extension, so the
+/// replacement isn't overwritten.
+pub async fn middleware(
+    State(forwarding): State<Arc<Forwarding>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let connect_info = request.extensions().get::<ConnectInfo<Peer>>();
+    let (peer, trusted) = forwarding.peer_trust(connect_info.map(|ConnectInfo(peer)| peer));
+
+    let client_ip = if trusted {
+        forwarding.client_ip(peer, request.headers())
+    } else {
+        peer
+    };
+    if let Some(ip) = client_ip {
+        Span::current().record("client_ip", ip.to_string());
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+
+    if trusted
+        && forwarding.config.proxy.derive_base_url
+        && let Some(base_url) = forwarding.derived_base_url(request.headers())
+        && base_url != forwarding.config.base_url
+    {
+        let config = forwarding.config_for(base_url);
+        request.extensions_mut().insert(config);
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn forwarding(trusted: &[&str], allowed_hosts: &[&str]) -> Forwarding {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "database": { "url": "postgres://localhost/test" },
+            "oidc": { "issuer_url": "http://localhost", "audience": "test" },
+            "base_url": "http://localhost:8080/geo",
+            "proxy": {
+                "trusted_proxies": trusted,
+                "derive_base_url": true,
+                "allowed_hosts": allowed_hosts
+            }
+        }))
+        .unwrap();
+        Forwarding::new(Arc::new(config)).unwrap()
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_ip_network() {
+        let network = IpNetwork::parse("10.0.0.0/8").unwrap();
+        assert!(network.contains("10.1.2.3".parse().unwrap()));
+        assert!(network.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!network.contains("11.0.0.1".parse().unwrap()));
+        assert!(
+            IpNetwork::parse("fd00::/8")
+                .unwrap()
+                .contains("fd12::1".parse().unwrap())
+        );
+        assert!(
+            IpNetwork::parse("0.0.0.0/0")
+                .unwrap()
+                .contains("1.2.3.4".parse().unwrap())
+        );
+        assert!(IpNetwork::parse("10.0.0.0/33").is_none());
+        assert!(IpNetwork::parse("proxy").is_none());
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let forwarding = forwarding(&["10.0.0.0/8"], &[]);
+        let proxy = Some("10.0.0.5".parse().unwrap());
+        let forwarded = headers(&[("x-forwarded-for", "203.0.113.7, 10.0.0.9")]);
+
+        assert_eq!(
+            forwarding.client_ip(proxy, &forwarded),
+            Some("203.0.113.7".parse().unwrap())
+        );
+        // Untrusted peers can't claim another address
+        assert_eq!(
+            forwarding.client_ip(Some("198.51.100.1".parse().unwrap()), &forwarded),
+            Some("198.51.100.1".parse().unwrap())
+        );
+        assert_eq!(forwarding.client_ip(proxy, &HeaderMap::new()), proxy);
+        assert_eq!(forwarding.client_ip(None, &HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_peer_trust() {
+        let forwarding = forwarding(&["10.0.0.0/8"], &[]);
+        let proxy: IpAddr = "10.0.0.5".parse().unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+
+        assert_eq!(
+            forwarding.peer_trust(Some(&Peer::Tcp((proxy, 443).into()))),
+            (Some(proxy), true)
+        );
+        assert_eq!(
+            forwarding.peer_trust(Some(&Peer::Tcp((client, 443).into()))),
+            (Some(client), false)
+        );
+        assert_eq!(forwarding.peer_trust(Some(&Peer::Unix)), (None, true));
+        // A missing peer is never trusted
+        assert_eq!(forwarding.peer_trust(None), (None, false));
+    }
+
+    #[test]
+    fn test_derived_base_url() {
+        let forwarding = forwarding(&[], &["maps.example.com"]);
+        assert_eq!(
+            forwarding
+                .derived_base_url(&headers(&[
+                    ("x-forwarded-proto", "https"),
+                    ("x-forwarded-host", "maps.example.com"),
+                ]))
+                .as_deref(),
+            Some("https://maps.example.com/geo")
+        );
+        assert_eq!(
+            forwarding
+                .derived_base_url(&headers(&[("host", "maps.example.com:8443")]))
+                .as_deref(),
+            Some("http://maps.example.com:8443/geo")
+        );
+        assert!(
+            forwarding
+                .derived_base_url(&headers(&[("x-forwarded-host", "evil.example.com")]))
+                .is_none()
+        );
+        assert!(
+            forwarding
+                .derived_base_url(&headers(&[("x-forwarded-host", "maps.example.com/x?")]))
+                .is_none()
+        );
+    }
+}
@@ -9,6 +9,7 @@ use serde::Serialize;
 use std::sync::Arc;
 
 use super::common::{Link, media_type, rel};
+use super::language::AcceptLanguage;
 use crate::config::Config;
 
 /// OGC API Landing Page response
@@ -20,28 +21,74 @@ pub struct LandingPage {
     pub links: Vec<Link>,
 }
 
-async fn get_landing_page(Extension(config): Extension<Arc<Config>>) -> Json<LandingPage> {
+async fn get_landing_page(
+    Extension(config): Extension<Arc<Config>>,
+    accept_language: AcceptLanguage,
+) -> Json<LandingPage> {
     let base_url = &config.base_url;
+    let title = accept_language.localize("SpatialVault", &config.localization.title);
+    let description = accept_language.localize(
+        "OGC API compliant geospatial data service with STAC integration",
+        &config.localization.description,
+    );
+    let mut self_link = Link::new(base_url, rel::SELF);
+    if let Some(language) = title.language.or(description.language) {
+        self_link = self_link.with_hreflang(language);
+    }
 
-    let landing = LandingPage {
-        title: "SpatialVault".to_string(),
-        description: "OGC API compliant geospatial data service with STAC integration".to_string(),
-        links: vec![
-            Link::new(base_url, rel::SELF)
-                .with_type(media_type::JSON)
-                .with_title("This document"),
-            Link::new(format!("{}/api", base_url), rel::SERVICE_DESC)
-                .with_type(media_type::OPENAPI_JSON)
-                .with_title("OpenAPI definition"),
-            Link::new(format!("{}/conformance", base_url), rel::CONFORMANCE)
-                .with_type(media_type::JSON)
-                .with_title("Conformance declaration"),
-            Link::new(format!("{}/collections", base_url), rel::DATA)
+    let modules = &config.modules;
+    let mut links = vec![
+        self_link
+            .with_type(media_type::JSON)
+            .with_title("This document"),
+        Link::new(format!("{}/api", base_url), rel::SERVICE_DESC)
+            .with_type(media_type::OPENAPI_JSON)
+            .with_title("OpenAPI definition"),
+        Link::new(format!("{}/api?f=yaml", base_url), rel::SERVICE_DESC)
+            .with_type(media_type::OPENAPI_YAML)
+            .with_title("OpenAPI definition (YAML)"),
+        Link::new(format!("{}/docs", base_url), rel::SERVICE_DOC)
+            .with_type(media_type::HTML)
+            .with_title("API console"),
+        Link::new(format!("{}/conformance", base_url), rel::CONFORMANCE)
+            .with_type(media_type::JSON)
+            .with_title("Conformance declaration"),
+        Link::new(format!("{}/collections", base_url), rel::DATA)
+            .with_type(media_type::JSON)
+            .with_title("Collections"),
+    ];
+    if modules.records {
+        links.push(
+            Link::new(format!("{}/catalogues", base_url), rel::DATA)
                 .with_type(media_type::JSON)
-                .with_title("Collections"),
+                .with_title("Record catalogues"),
+        );
+    }
+    if modules.stac {
+        links.push(
             Link::new(format!("{}/stac", base_url), rel::ROOT)
                 .with_type(media_type::JSON)
                 .with_title("STAC Catalog"),
+        );
+    }
+    if modules.tiles {
+        links.extend([
+            Link::new(
+                format!("{}/tiles", base_url),
+                "http://www.opengis.net/def/rel/ogc/1.0/tilesets-vector",
+            )
+            .with_type(media_type::JSON)
+            .with_title("Vector tilesets"),
+            Link::new(
+                format!("{}/tileMatrixSets", base_url),
+                "http://www.opengis.net/def/rel/ogc/1.0/tiling-schemes",
+            )
+            .with_type(media_type::JSON)
+            .with_title("Tile matrix sets"),
+        ]);
+    }
+    if modules.processes {
+        links.extend([
             Link::new(
                 format!("{}/processes", base_url),
                 "http://www.opengis.net/def/rel/ogc/1.0/processes",
@@ -54,7 +101,13 @@ async fn get_landing_page(Extension(config): Extension<Arc<Config>>) -> Json<Lan
             )
             .with_type(media_type::JSON)
             .with_title("Jobs"),
-        ],
+        ]);
+    }
+
+    let landing = LandingPage {
+        title: title.text,
+        description: description.text,
+        links,
     };
 
     Json(landing)
@@ -0,0 +1,146 @@
+use aide::OperationInput;
+use axum::{
+    extract::FromRequestParts,
+    http::{HeaderMap, header, request::Parts},
+};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+
+/// Languages the client accepts, most preferred first, from `Accept-Language`
+///
+/// Tags are lowercased; `*` and languages with `q=0` are left out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AcceptLanguage(pub Vec<String>);
+
+/// Text in the language picked for a response
+#[derive(Debug, Clone, PartialEq)]
+pub struct Localized {
+    pub text: String,
+    /// Language of the text, when a translation was picked
+    pub language: Option<String>,
+}
+
+impl AcceptLanguage {
+    pub fn parse(value: &str) -> Self {
+        let mut ranges: Vec<(String, f32)> = value
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim().to_ascii_lowercase();
+                let quality = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        // Stable, so equally preferred languages keep their order
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Self(ranges.into_iter().map(|(tag, _)| tag).collect())
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(Self::parse)
+            .unwrap_or_default()
+    }
+
+    /// The best translation for the client
+    ///
+    /// For each accepted language in order, an exact match is preferred,
+    /// then a translation with the same primary language (`en` for `en-gb`
+    /// and the other way round).
+    pub fn pick<'a>(
+        &self,
+        translations: &'a BTreeMap<String, String>,
+    ) -> Option<(&'a str, &'a str)> {
+        let primary = |tag: &str| tag.split('-').next().unwrap_or_default().to_string();
+        self.0.iter().find_map(|accepted| {
+            translations
+                .iter()
+                .find(|(language, _)| language.eq_ignore_ascii_case(accepted))
+                .or_else(|| {
+                    translations.iter().find(|(language, _)| {
+                        primary(&language.to_ascii_lowercase()) == primary(accepted)
+                    })
+                })
+                .map(|(language, text)| (language.as_str(), text.as_str()))
+        })
+    }
+
+    /// Text in the best language, falling back to `default`
+    pub fn localize(&self, default: &str, translations: &BTreeMap<String, String>) -> Localized {
+        match self.pick(translations) {
+            Some((language, text)) => Localized {
+                text: text.to_string(),
+                language: Some(language.to_string()),
+            },
+            None => Localized {
+                text: default.to_string(),
+                language: None,
+            },
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AcceptLanguage {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
+
+impl OperationInput for AcceptLanguage {}
+
+/// Translations stored as a JSON object of language tag to text
+pub fn stored_translations(value: Option<&serde_json::Value>) -> BTreeMap<String, String> {
+    value
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translations(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(language, text)| (language.to_string(), text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_accept_language() {
+        assert_eq!(
+            AcceptLanguage::parse("sv-SE, en;q=0.8, de;q=0.9, *;q=0.1, fr;q=0"),
+            AcceptLanguage(vec![
+                "sv-se".to_string(),
+                "de".to_string(),
+                "en".to_string()
+            ])
+        );
+        assert_eq!(AcceptLanguage::parse(""), AcceptLanguage::default());
+    }
+
+    #[test]
+    fn test_pick_translation() {
+        let titles = translations(&[("en", "Roads"), ("sv", "Vägar"), ("pt-BR", "Estradas")]);
+
+        let accept = AcceptLanguage::parse("sv-SE,en;q=0.5");
+        assert_eq!(accept.pick(&titles), Some(("sv", "Vägar")));
+        let accept = AcceptLanguage::parse("pt-br");
+        assert_eq!(accept.pick(&titles), Some(("pt-BR", "Estradas")));
+        let accept = AcceptLanguage::parse("pt");
+        assert_eq!(accept.pick(&titles), Some(("pt-BR", "Estradas")));
+        let accept = AcceptLanguage::parse("fi");
+        assert_eq!(accept.pick(&titles), None);
+
+        let localized = AcceptLanguage::parse("fi").localize("Default", &titles);
+        assert_eq!(localized.text, "Default");
+        assert_eq!(localized.language, None);
+    }
+}
@@ -1,11 +1,29 @@
+pub mod allow;
+pub mod body;
+pub mod broken_assets;
 pub mod collections;
 pub mod common;
+pub mod compression;
+pub mod conflict;
 pub mod conformance;
 pub mod coverages;
+pub mod edr;
 pub mod features;
+pub mod forwarded;
 pub mod landing;
+pub mod language;
+pub mod modules;
+pub mod notifications;
+pub mod pointclouds;
 pub mod processes;
+pub mod quota;
+pub mod read_only;
+pub mod records;
 pub mod stac;
 pub mod tiles;
+pub mod tokens;
+pub mod uploads;
+pub mod usage;
+pub mod validate_only;
 
 pub use common::*;
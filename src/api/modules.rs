@@ -0,0 +1,30 @@
+//! Optional API groups
+//!
+//! Deployments switch groups off in [`crate::config::ModulesConfig`], e.g.
+//! for a features-only instance. Disabled groups are not routed, and the
+//! conformance declaration, OpenAPI definition and links leave them out.
+
+use axum::{
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Reject requests creating, changing or deleting collections and features
+/// on instances without transactions
+///
+/// Answers as for a route without the method, allowing only reads.
+pub async fn reject_transactions(request: Request, next: Next) -> Response {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        return next.run(request).await;
+    }
+    (
+        StatusCode::METHOD_NOT_ALLOWED,
+        [(header::ALLOW, "GET,HEAD")],
+    )
+        .into_response()
+}
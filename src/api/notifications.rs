@@ -0,0 +1,124 @@
+//! Notification preferences of the current user
+//!
+//! Users opt in to being notified when a collection is shared with them or
+//! when one of their jobs finishes; see
+//! [`crate::services::NotificationService`].
+
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, State},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::api::body::JsonBody;
+use crate::auth::AuthenticatedUser;
+use crate::db::NotificationSettings;
+use crate::error::{AppError, AppResult};
+use crate::services::NotificationService;
+
+/// Longest accepted email address
+const MAX_EMAIL_LEN: usize = 254;
+
+/// What the user is notified about
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationSettingsBody {
+    /// Address the notification service sends to; the service may also
+    /// look up users by name
+    #[serde(default)]
+    pub email: Option<String>,
+    /// Notify when a collection is shared with the user
+    #[serde(default)]
+    pub collection_shared: bool,
+    /// Notify when a job of the user succeeds or fails
+    #[serde(default)]
+    pub job_finished: bool,
+}
+
+impl From<NotificationSettings> for NotificationSettingsBody {
+    fn from(settings: NotificationSettings) -> Self {
+        Self {
+            email: settings.email,
+            collection_shared: settings.collection_shared,
+            job_finished: settings.job_finished,
+        }
+    }
+}
+
+/// Get the notification preferences of the current user
+pub async fn get_settings(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<NotificationService>>,
+) -> AppResult<Json<NotificationSettingsBody>> {
+    let settings = service.get_settings(&user.username).await?;
+    Ok(Json(settings.into()))
+}
+
+fn get_settings_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get notification settings")
+        .description("Returns what the current user is notified about. Nothing without settings.")
+        .tag("Notifications")
+        .response_with::<200, Json<NotificationSettingsBody>, _>(|res| {
+            res.description("Notification settings")
+        })
+}
+
+/// Replace the notification preferences of the current user
+pub async fn put_settings(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<NotificationService>>,
+    JsonBody(request): JsonBody<NotificationSettingsBody>,
+) -> AppResult<Json<NotificationSettingsBody>> {
+    if !service.is_enabled() {
+        return Err(AppError::BadRequest(
+            "Notifications are not enabled".to_string(),
+        ));
+    }
+    if let Some(email) = &request.email
+        && (email.len() > MAX_EMAIL_LEN || !email.contains('@'))
+    {
+        return Err(AppError::BadRequest(format!(
+            "Invalid email address: {}",
+            email
+        )));
+    }
+
+    let settings = NotificationSettings {
+        email: request.email,
+        collection_shared: request.collection_shared,
+        job_finished: request.job_finished,
+    };
+    let settings = service.update_settings(&user.username, &settings).await?;
+    Ok(Json(settings.into()))
+}
+
+fn put_settings_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Update notification settings")
+        .description(
+            "Replaces what the current user is notified about: collections shared with \
+             them and their finished jobs. Notifications are sent through the notification \
+             service of the deployment, e.g. as email.",
+        )
+        .tag("Notifications")
+        .response_with::<200, Json<NotificationSettingsBody>, _>(|res| {
+            res.description("Updated notification settings")
+        })
+        .response_with::<400, (), _>(|res| {
+            res.description("Invalid email address, or notifications are not enabled")
+        })
+}
+
+pub fn routes(service: Arc<NotificationService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/notifications/settings",
+            get_with(get_settings, get_settings_docs).put_with(put_settings, put_settings_docs),
+        )
+        .with_state(service)
+}
@@ -0,0 +1,292 @@
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::collections::ResolvedCollection;
+use crate::error::{AppError, AppResult};
+use crate::services::PointCloudService;
+use crate::services::pointcloud_service::PointCloudItem;
+
+/// Height range assumed for item bounding regions, in meters
+///
+/// Item footprints are stored in 2D, so the region covers any plausible
+/// terrain height rather than the exact point heights.
+const REGION_HEIGHTS: (f64, f64) = (-500.0, 9000.0);
+
+/// Meters per degree of latitude, used to size geometric errors
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// Media type of 3D Tiles point cloud content
+const PNTS_MEDIA_TYPE: &str = "application/octet-stream";
+
+/// 3D Tiles tileset (3D Tiles 1.1)
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Tileset {
+    pub asset: TilesetAsset,
+    pub geometric_error: f64,
+    pub root: Tile,
+}
+
+/// Tileset asset metadata
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TilesetAsset {
+    pub version: String,
+}
+
+/// A tile in the tileset hierarchy
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Tile {
+    pub bounding_volume: BoundingVolume,
+    pub geometric_error: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refine: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<TileContent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Tile>,
+}
+
+/// Bounding region: west, south, east, north (radians), min and max height (meters)
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BoundingVolume {
+    pub region: [f64; 6],
+}
+
+/// Tile content reference, relative to the tileset
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TileContent {
+    pub uri: String,
+}
+
+/// Bounding region of a WGS84 bounding box
+fn region(minx: f64, miny: f64, maxx: f64, maxy: f64) -> BoundingVolume {
+    BoundingVolume {
+        region: [
+            minx.to_radians(),
+            miny.to_radians(),
+            maxx.to_radians(),
+            maxy.to_radians(),
+            REGION_HEIGHTS.0,
+            REGION_HEIGHTS.1,
+        ],
+    }
+}
+
+/// Approximate diagonal of a WGS84 bounding box in meters
+fn diagonal_meters(minx: f64, miny: f64, maxx: f64, maxy: f64) -> f64 {
+    let mid_lat = ((miny + maxy) / 2.0).to_radians();
+    let width = (maxx - minx) * METERS_PER_DEGREE * mid_lat.cos();
+    let height = (maxy - miny) * METERS_PER_DEGREE;
+    width.hypot(height)
+}
+
+/// Build a tileset with one point cloud tile per item
+///
+/// The root has no content of its own and is refined into the item tiles as
+/// soon as the viewer is close enough to need them.
+pub fn build_tileset(items: &[PointCloudItem]) -> Tileset {
+    let children: Vec<Tile> = items
+        .iter()
+        .map(|item| Tile {
+            bounding_volume: region(item.minx, item.miny, item.maxx, item.maxy),
+            geometric_error: 0.0,
+            refine: None,
+            content: Some(TileContent {
+                uri: format!("items/{}", item.id),
+            }),
+            children: Vec::new(),
+        })
+        .collect();
+
+    let (minx, miny, maxx, maxy) = if items.is_empty() {
+        (-180.0, -90.0, 180.0, 90.0)
+    } else {
+        items.iter().fold(
+            (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |(minx, miny, maxx, maxy), item| {
+                (
+                    minx.min(item.minx),
+                    miny.min(item.miny),
+                    maxx.max(item.maxx),
+                    maxy.max(item.maxy),
+                )
+            },
+        )
+    };
+    let geometric_error = diagonal_meters(minx, miny, maxx, maxy);
+
+    Tileset {
+        asset: TilesetAsset {
+            version: "1.1".to_string(),
+        },
+        geometric_error,
+        root: Tile {
+            bounding_volume: region(minx, miny, maxx, maxy),
+            geometric_error,
+            refine: Some("ADD".to_string()),
+            content: None,
+            children,
+        },
+    }
+}
+
+fn require_pointcloud(collection_type: &str) -> AppResult<()> {
+    if collection_type != "pointcloud" {
+        return Err(AppError::BadRequest(
+            "3D Tiles are only available for point cloud collections".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Path parameters for the tileset endpoint
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/3dtiles/tileset.json")]
+pub struct TilesetPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+/// Get the 3D Tiles tileset of a point cloud collection
+pub async fn get_tileset(
+    State(service): State<Arc<PointCloudService>>,
+    _path: TilesetPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> AppResult<Response> {
+    require_pointcloud(&collection.collection_type)?;
+    let items = service.list_items(collection.id).await?;
+    Ok(Json(build_tileset(&items)).into_response())
+}
+
+fn get_tileset_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get 3D Tiles tileset")
+        .description(
+            "Returns a 3D Tiles tileset for a point cloud collection, with one point cloud tile \
+             per item, for streaming into viewers such as Cesium",
+        )
+        .tag("3D Tiles")
+        .response_with::<200, Json<Tileset>, _>(|res| res.description("3D Tiles tileset"))
+        .response_with::<400, (), _>(|res| res.description("Not a point cloud collection"))
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+/// Path parameters for point cloud tile content
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/3dtiles/items/{item_id}")]
+pub struct TileContentPath {
+    /// The collection identifier
+    pub collection_id: String,
+    /// The item identifier
+    pub item_id: String,
+}
+
+/// Get the point cloud tile of an item
+pub async fn get_tile_content(
+    State(service): State<Arc<PointCloudService>>,
+    path: TileContentPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> AppResult<Response> {
+    require_pointcloud(&collection.collection_type)?;
+    let item_id = Uuid::parse_str(&path.item_id)
+        .map_err(|_| AppError::NotFound(format!("Item not found: {}", path.item_id)))?;
+
+    let tile = service
+        .get_item_tile(&collection.owner, collection.id, item_id)
+        .await?;
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, PNTS_MEDIA_TYPE)],
+        Body::from(tile),
+    )
+        .into_response())
+}
+
+fn get_tile_content_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get point cloud tile")
+        .description(
+            "Returns the points of an item as a 3D Tiles point cloud (pnts) tile. Large files \
+             are thinned. Only uncompressed LAS data can be served; LAZ and COPC assets are \
+             answered with 409. Points are reprojected from the `proj:epsg` of the data asset \
+             or item; without one, the data must be longitudes and latitudes in WGS84.",
+        )
+        .tag("3D Tiles")
+        .response_with::<200, (), _>(|res| res.description("Point cloud tile (pnts)"))
+        .response_with::<400, (), _>(|res| res.description("Not a point cloud collection"))
+        .response_with::<404, (), _>(|res| res.description("Collection or item not found"))
+        .response_with::<409, (), _>(|res| {
+            res.description("Item data cannot be served as a point cloud tile")
+        })
+}
+
+pub fn routes(service: Arc<PointCloudService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/collections/{collection_id}/3dtiles/tileset.json",
+            get_with(get_tileset, get_tileset_docs),
+        )
+        .api_route(
+            "/collections/{collection_id}/3dtiles/items/{item_id}",
+            get_with(get_tile_content, get_tile_content_docs),
+        )
+        .with_state(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(minx: f64, miny: f64, maxx: f64, maxy: f64) -> PointCloudItem {
+        PointCloudItem {
+            id: Uuid::nil(),
+            minx,
+            miny,
+            maxx,
+            maxy,
+        }
+    }
+
+    #[test]
+    fn test_build_tileset() {
+        let tileset = build_tileset(&[item(18.0, 59.0, 18.1, 59.1), item(18.1, 59.0, 18.2, 59.2)]);
+        assert_eq!(tileset.asset.version, "1.1");
+
+        let root = &tileset.root;
+        assert_eq!(root.children.len(), 2);
+        assert!(root.content.is_none());
+        let [west, south, east, north, ..] = root.bounding_volume.region;
+        assert_eq!(
+            [west, south, east, north],
+            [18.0f64, 59.0, 18.2, 59.2].map(f64::to_radians)
+        );
+        // About 11 km wide and 22 km tall at this latitude
+        assert!((tileset.geometric_error - 25_000.0).abs() < 100.0);
+
+        let child = &root.children[0];
+        assert_eq!(child.geometric_error, 0.0);
+        assert_eq!(
+            child.content.as_ref().unwrap().uri,
+            format!("items/{}", Uuid::nil())
+        );
+    }
+
+    #[test]
+    fn test_build_empty_tileset() {
+        let tileset = build_tileset(&[]);
+        assert!(tileset.root.children.is_empty());
+        assert_eq!(tileset.root.bounding_volume.region[2], std::f64::consts::PI);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod handlers;
+pub mod pnts;
+
+pub use handlers::*;
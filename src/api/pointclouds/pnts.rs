@@ -0,0 +1,116 @@
+/// 3D Tiles Point Cloud (`.pnts`) encoding
+use serde_json::json;
+
+/// WGS84 semi-major axis in meters
+const WGS84_A: f64 = 6_378_137.0;
+/// WGS84 flattening
+const WGS84_F: f64 = 1.0 / 298.257_223_563;
+
+/// Earth-centered, earth-fixed coordinates of a WGS84 position
+///
+/// Longitude and latitude are in degrees, height in meters above the
+/// ellipsoid.
+pub fn geodetic_to_ecef(lon: f64, lat: f64, height: f64) -> [f64; 3] {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let (lon, lat) = (lon.to_radians(), lat.to_radians());
+    let n = WGS84_A / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+    [
+        (n + height) * lat.cos() * lon.cos(),
+        (n + height) * lat.cos() * lon.sin(),
+        (n * (1.0 - e2) + height) * lat.sin(),
+    ]
+}
+
+/// Encode a point cloud tile
+///
+/// Positions are ECEF offsets from `rtc_center`, which keeps them precise as
+/// 32-bit floats. Colors, when given, must have one entry per position.
+pub fn encode_pnts(
+    rtc_center: [f64; 3],
+    positions: &[[f32; 3]],
+    colors: Option<&[[u8; 3]]>,
+) -> Vec<u8> {
+    const HEADER_LENGTH: usize = 28;
+
+    let mut binary: Vec<u8> = positions
+        .iter()
+        .flatten()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let mut feature_table = json!({
+        "POINTS_LENGTH": positions.len(),
+        "RTC_CENTER": rtc_center,
+        "POSITION": { "byteOffset": 0 },
+    });
+    if let Some(colors) = colors {
+        feature_table["RGB"] = json!({ "byteOffset": binary.len() });
+        binary.extend(colors.iter().flatten());
+    }
+
+    // The binary body must start and end on 8-byte boundaries
+    let mut feature_json = feature_table.to_string().into_bytes();
+    while !(HEADER_LENGTH + feature_json.len()).is_multiple_of(8) {
+        feature_json.push(b' ');
+    }
+    while !binary.len().is_multiple_of(8) {
+        binary.push(0);
+    }
+
+    let byte_length = HEADER_LENGTH + feature_json.len() + binary.len();
+    let mut tile = Vec::with_capacity(byte_length);
+    tile.extend_from_slice(b"pnts");
+    tile.extend_from_slice(&1u32.to_le_bytes());
+    for value in [byte_length, feature_json.len(), binary.len(), 0, 0] {
+        tile.extend_from_slice(&(value as u32).to_le_bytes());
+    }
+    tile.extend(feature_json);
+    tile.extend(binary);
+    tile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_at(data: &[u8], offset: usize) -> usize {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize
+    }
+
+    #[test]
+    fn test_geodetic_to_ecef() {
+        let [x, y, z] = geodetic_to_ecef(0.0, 0.0, 0.0);
+        assert!((x - WGS84_A).abs() < 1e-6 && y.abs() < 1e-6 && z.abs() < 1e-6);
+
+        // The pole lies on the semi-minor axis
+        let [x, _, z] = geodetic_to_ecef(0.0, 90.0, 100.0);
+        assert!(x.abs() < 1e-6);
+        assert!((z - 6_356_852.314).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_encode_pnts() {
+        let positions = [[1.0, 2.0, 3.0], [-1.0, 0.5, 0.0]];
+        let colors = [[255, 0, 0], [0, 255, 0]];
+        let tile = encode_pnts([10.0, 20.0, 30.0], &positions, Some(&colors));
+
+        assert_eq!(&tile[0..4], b"pnts");
+        assert_eq!(u32_at(&tile, 4), 1);
+        assert_eq!(u32_at(&tile, 8), tile.len());
+
+        let json_length = u32_at(&tile, 12);
+        let binary_length = u32_at(&tile, 16);
+        assert_eq!((28 + json_length) % 8, 0);
+        assert_eq!(binary_length, 32);
+        assert_eq!(28 + json_length + binary_length, tile.len());
+
+        let feature_table: serde_json::Value =
+            serde_json::from_slice(&tile[28..28 + json_length]).unwrap();
+        assert_eq!(feature_table["POINTS_LENGTH"], 2);
+        assert_eq!(feature_table["RTC_CENTER"], json!([10.0, 20.0, 30.0]));
+        assert_eq!(feature_table["RGB"]["byteOffset"], 24);
+
+        let binary = &tile[28 + json_length..];
+        assert_eq!(&binary[0..4], &1.0f32.to_le_bytes());
+        assert_eq!(&binary[24..30], &[255, 0, 0, 0, 255, 0]);
+    }
+}
@@ -0,0 +1,108 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+/// Process definition for collection backup
+pub const PROCESS_ID: &str = "backup-collection";
+
+/// Input schema for collection backup
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct BackupCollectionInputs {
+    /// Collection to back up
+    pub collection: String,
+}
+
+impl BackupCollectionInputs {
+    /// Validate the inputs
+    pub fn validate(&self) -> AppResult<()> {
+        if self.collection.is_empty() {
+            return Err(AppError::BadRequest("collection is required".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Object storage prefix of a user's backups
+///
+/// Backups are kept outside the job prefix, so job retention doesn't remove
+/// them.
+pub fn backup_prefix(owner: &str) -> String {
+    format!("{}/backups/", owner)
+}
+
+/// Reference to the backup file
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BackupReference {
+    /// Managed S3 URI of the file, passed to `restore-collection`
+    pub href: String,
+    /// Media type of the file
+    #[serde(rename = "type")]
+    pub media_type: String,
+}
+
+/// Output schema for collection backup
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupCollectionOutputs {
+    /// The backup, downloadable from `/jobs/{jobId}/results/backup`
+    pub backup: BackupReference,
+
+    /// Collection that was backed up
+    pub collection: String,
+
+    /// Collection version the backup was taken at
+    pub collection_version: i64,
+
+    /// Number of features backed up
+    pub number_of_features: usize,
+
+    /// Number of items backed up
+    pub number_of_items: usize,
+}
+
+/// Process description for OpenAPI
+pub fn process_description() -> serde_json::Value {
+    serde_json::json!({
+        "id": PROCESS_ID,
+        "title": "Back Up Collection",
+        "description": "Serialize a collection you own into a versioned, gzipped JSON lines archive: its definition and metadata, its features or items, and the manifest of its assets. Asset files are referenced, not copied. The backup is kept under your backups prefix when the job is purged, and restored with `restore-collection`.",
+        "version": "1.0.0",
+        "jobControlOptions": ["async-execute"],
+        "outputTransmission": ["reference"],
+        "inputs": {
+            "collection": {
+                "title": "Collection ID",
+                "description": "Collection to back up",
+                "schema": { "type": "string", "minLength": 1 }
+            }
+        },
+        "outputs": {
+            "backup": {
+                "title": "Backup",
+                "description": "Reference to the backup file",
+                "schema": { "type": "string", "contentMediaType": "application/gzip" }
+            },
+            "collection": {
+                "title": "Collection",
+                "description": "Collection that was backed up",
+                "schema": { "type": "string" }
+            },
+            "collectionVersion": {
+                "title": "Collection Version",
+                "description": "Collection version the backup was taken at",
+                "schema": { "type": "integer" }
+            },
+            "numberOfFeatures": {
+                "title": "Number of Features",
+                "description": "Number of features backed up",
+                "schema": { "type": "integer" }
+            },
+            "numberOfItems": {
+                "title": "Number of Items",
+                "description": "Number of items backed up",
+                "schema": { "type": "integer" }
+            }
+        }
+    })
+}
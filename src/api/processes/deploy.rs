@@ -0,0 +1,248 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{export_collection, import_pointcloud, import_raster, temporal_composite, workflow};
+use crate::error::{AppError, AppResult};
+
+/// OGC Application Package (OGC API Processes Part 2)
+///
+/// Only the container-based execution unit is supported; CWL packages are
+/// rejected during validation.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplicationPackage {
+    /// Process description (id, title, version, inputs, outputs)
+    pub process_description: DeployedProcessDescription,
+
+    /// How the process is executed
+    pub execution_unit: ExecutionUnit,
+}
+
+/// Process description embedded in an application package
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeployedProcessDescription {
+    pub id: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default = "default_version")]
+    pub version: String,
+    /// Input definitions keyed by input id
+    #[serde(default)]
+    pub inputs: serde_json::Map<String, serde_json::Value>,
+    /// Output definitions keyed by output id
+    #[serde(default)]
+    pub outputs: serde_json::Map<String, serde_json::Value>,
+}
+
+fn default_version() -> String {
+    "1.0.0".to_string()
+}
+
+/// Container execution unit
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ExecutionUnit {
+    /// Execution unit type; only "docker" (OCI image) is supported
+    #[serde(rename = "type")]
+    pub unit_type: String,
+
+    /// Container image reference (e.g., "ghcr.io/org/ndvi:1.2")
+    pub image: String,
+
+    /// Command to run inside the container (defaults to the image entrypoint)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+
+    /// Additional environment variables for the container
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+}
+
+impl ExecutionUnit {
+    /// Check that environment variable names are plain identifiers, so they
+    /// can't inject options into the container run, and don't override the
+    /// variables set by the worker
+    pub fn validate_env(&self) -> Result<(), String> {
+        for key in self.env.keys() {
+            let valid = key
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(format!(
+                    "executionUnit.env name '{}' must match [A-Za-z_][A-Za-z0-9_]*",
+                    key
+                ));
+            }
+            if key.starts_with("SPATIALVAULT_") {
+                return Err(format!(
+                    "executionUnit.env name '{}' uses the reserved SPATIALVAULT_ prefix",
+                    key
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ApplicationPackage {
+    /// Validate the package before it is stored
+    pub fn validate(&self) -> AppResult<()> {
+        let id = &self.process_description.id;
+        let valid_id = !id.is_empty()
+            && id.len() <= 64
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            && id.chars().next().is_some_and(|c| c.is_ascii_alphanumeric());
+        if !valid_id {
+            return Err(AppError::BadRequest(
+                "processDescription.id must be 1-64 characters of [A-Za-z0-9_-] starting with a letter or digit".to_string(),
+            ));
+        }
+
+        if is_builtin_process(id) {
+            return Err(AppError::Conflict(format!(
+                "Process '{}' is built in and cannot be redeployed",
+                id
+            )));
+        }
+
+        if self.process_description.title.trim().is_empty() {
+            return Err(AppError::BadRequest(
+                "processDescription.title is required".to_string(),
+            ));
+        }
+
+        match self.execution_unit.unit_type.as_str() {
+            "docker" | "oci" => {}
+            "cwl" => {
+                return Err(AppError::BadRequest(
+                    "CWL execution units are not supported; use a container (docker) execution unit".to_string(),
+                ));
+            }
+            other => {
+                return Err(AppError::BadRequest(format!(
+                    "Unsupported executionUnit.type: {}",
+                    other
+                )));
+            }
+        }
+
+        let image = &self.execution_unit.image;
+        if image.is_empty() || image.starts_with('-') || image.chars().any(char::is_whitespace) {
+            return Err(AppError::BadRequest(
+                "executionUnit.image must be a valid container image reference".to_string(),
+            ));
+        }
+
+        self.execution_unit
+            .validate_env()
+            .map_err(AppError::BadRequest)?;
+
+        Ok(())
+    }
+}
+
+/// Whether a process id refers to one of the processes built into the worker
+pub fn is_builtin_process(process_id: &str) -> bool {
+    process_id == import_raster::PROCESS_ID
+        || process_id == import_pointcloud::PROCESS_ID
+        || process_id == export_collection::PROCESS_ID
+        || process_id == temporal_composite::PROCESS_ID
+        || process_id == workflow::PROCESS_ID
+}
+
+/// Check that all required inputs of a deployed process are present
+///
+/// Inputs are required unless their definition has `minOccurs: 0`.
+pub fn validate_execute_inputs(
+    process_description: &serde_json::Value,
+    inputs: &serde_json::Map<String, serde_json::Value>,
+) -> AppResult<()> {
+    if let Some(definitions) = process_description["inputs"].as_object() {
+        for (name, definition) in definitions {
+            let min_occurs = definition["minOccurs"].as_u64().unwrap_or(1);
+            if min_occurs > 0 && !inputs.contains_key(name) {
+                return Err(AppError::BadRequest(format!(
+                    "Missing required input: {}",
+                    name
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(id: &str, unit_type: &str, image: &str) -> ApplicationPackage {
+        serde_json::from_value(serde_json::json!({
+            "processDescription": { "id": id, "title": "NDVI" },
+            "executionUnit": { "type": unit_type, "image": image }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate_package() {
+        assert!(
+            package("ndvi", "docker", "ghcr.io/org/ndvi:1.0")
+                .validate()
+                .is_ok()
+        );
+        assert!(package("", "docker", "ndvi").validate().is_err());
+        assert!(package("../etc", "docker", "ndvi").validate().is_err());
+        assert!(
+            package("import-raster", "docker", "ndvi")
+                .validate()
+                .is_err()
+        );
+        assert!(package("ndvi", "cwl", "ndvi").validate().is_err());
+        assert!(
+            package("ndvi", "docker", "--privileged")
+                .validate()
+                .is_err()
+        );
+
+        let mut with_env = package("ndvi", "docker", "ndvi");
+        with_env
+            .execution_unit
+            .env
+            .insert("GDAL_CACHEMAX".to_string(), "512".to_string());
+        assert!(with_env.validate().is_ok());
+        for key in ["", "1ST", "A=B", "X --privileged", "SPATIALVAULT_INPUTS"] {
+            let mut with_env = package("ndvi", "docker", "ndvi");
+            with_env
+                .execution_unit
+                .env
+                .insert(key.to_string(), "1".to_string());
+            assert!(with_env.validate().is_err(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_validate_execute_inputs() {
+        let description = serde_json::json!({
+            "inputs": {
+                "red": { "schema": { "type": "string" } },
+                "scale": { "schema": { "type": "number" }, "minOccurs": 0 }
+            }
+        });
+
+        let mut inputs = serde_json::Map::new();
+        assert!(validate_execute_inputs(&description, &inputs).is_err());
+
+        inputs.insert(
+            "red".to_string(),
+            serde_json::json!({ "href": "s3://b/red.tif" }),
+        );
+        assert!(validate_execute_inputs(&description, &inputs).is_ok());
+    }
+}
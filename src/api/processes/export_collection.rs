@@ -0,0 +1,152 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::processing::export::{ExportFormat, TextEncoding};
+
+/// Process definition for collection export
+pub const PROCESS_ID: &str = "export-collection";
+
+/// Input schema for collection export
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportCollectionInputs {
+    /// Vector collection to export
+    pub collection: String,
+
+    /// Export format: `gpkg`, `shapefile` (zipped), `kml`, `kmz` or `ndjson`
+    pub format: String,
+
+    /// Character encoding of Shapefile attributes: `utf-8` (default) or
+    /// `iso-8859-1`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+impl ExportCollectionInputs {
+    /// Validate the inputs
+    pub fn validate(&self) -> AppResult<()> {
+        if self.collection.is_empty() {
+            return Err(AppError::BadRequest("collection is required".to_string()));
+        }
+        self.export_format().map(|_| ())
+    }
+
+    /// The requested format and attribute encoding
+    pub fn export_format(&self) -> AppResult<(ExportFormat, TextEncoding)> {
+        let format = ExportFormat::from_param(&self.format)?;
+        let encoding = self
+            .encoding
+            .as_deref()
+            .map(TextEncoding::from_param)
+            .transpose()?
+            .unwrap_or_default();
+        Ok((format, encoding))
+    }
+}
+
+/// Reference to the exported file
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ExportReference {
+    /// Managed S3 URI of the file
+    pub href: String,
+    /// Media type of the file
+    #[serde(rename = "type")]
+    pub media_type: String,
+}
+
+/// Output schema for collection export
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportCollectionOutputs {
+    /// The exported file, downloadable from `/jobs/{jobId}/results/export`
+    pub export: ExportReference,
+
+    /// Collection that was exported
+    pub collection: String,
+
+    /// File name of the export
+    pub file_name: String,
+
+    /// Number of features exported
+    pub number_of_features: usize,
+}
+
+/// Process description for OpenAPI
+pub fn process_description() -> serde_json::Value {
+    serde_json::json!({
+        "id": PROCESS_ID,
+        "title": "Export Collection",
+        "description": "Export all features of a vector collection in WGS 84 as a GeoPackage, zipped ESRI Shapefile, KML, KMZ or newline-delimited GeoJSON, like `GET /collections/{collectionId}/export` but as a job. Features are written to disk as they are read, so exports have no size limit. GeoParquet and PMTiles are not offered. The file is kept with the job and downloaded from `/jobs/{jobId}/results/export`.",
+        "version": "1.0.0",
+        "jobControlOptions": ["async-execute"],
+        "outputTransmission": ["reference"],
+        "inputs": {
+            "collection": {
+                "title": "Collection ID",
+                "description": "Vector collection to export",
+                "schema": { "type": "string", "minLength": 1 }
+            },
+            "format": {
+                "title": "Format",
+                "description": "Export format",
+                "schema": { "type": "string", "enum": ["gpkg", "shapefile", "kml", "kmz", "ndjson"] }
+            },
+            "encoding": {
+                "title": "Encoding",
+                "description": "Character encoding of Shapefile attributes. KML is always UTF-8.",
+                "schema": { "type": "string", "enum": ["utf-8", "iso-8859-1"], "default": "utf-8" },
+                "minOccurs": 0
+            }
+        },
+        "outputs": {
+            "export": {
+                "title": "Export",
+                "description": "Reference to the exported file",
+                "schema": { "type": "string", "contentMediaType": "application/octet-stream" }
+            },
+            "collection": {
+                "title": "Collection",
+                "description": "Collection that was exported",
+                "schema": { "type": "string" }
+            },
+            "fileName": {
+                "title": "File Name",
+                "description": "File name of the export",
+                "schema": { "type": "string" }
+            },
+            "numberOfFeatures": {
+                "title": "Number of Features",
+                "description": "Number of features exported",
+                "schema": { "type": "integer" }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(format: &str, encoding: Option<&str>) -> ExportCollectionInputs {
+        ExportCollectionInputs {
+            collection: "alice:roads".to_string(),
+            format: format.to_string(),
+            encoding: encoding.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_validate_export_inputs() {
+        assert!(inputs("shapefile", Some("iso-8859-1")).validate().is_ok());
+        assert!(inputs("kmz", None).validate().is_ok());
+        assert!(inputs("gpkg", None).validate().is_ok());
+        assert!(inputs("pmtiles", None).validate().is_err());
+        assert!(inputs("geojson", None).validate().is_err());
+        assert!(inputs("shapefile", Some("utf-16")).validate().is_err());
+
+        let mut missing = inputs("kml", None);
+        missing.collection = String::new();
+        assert!(missing.validate().is_err());
+    }
+}
@@ -7,20 +7,34 @@ use aide::{
 };
 use axum::{
     Json,
-    extract::{Extension, State},
+    extract::{DefaultBodyLimit, Extension, Query, State},
     http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use uuid::Uuid;
 
-use super::{import_pointcloud, import_raster};
+use super::deploy::{self, ApplicationPackage};
+use super::upload::{self, ExecuteBody};
+use super::workflow::{self, WorkflowRequest};
+use super::{
+    InputValue, backup_collection, export_collection, import_pointcloud, import_raster,
+    restore_collection, temporal_composite,
+};
 use crate::api::common::{Link, media_type, rel};
 use crate::auth::AuthenticatedUser;
 use crate::config::Config;
+use crate::db::{DeployedProcess, ProcessJob};
 use crate::error::{AppError, AppResult};
-use crate::services::ProcessService;
+use crate::openapi;
+use crate::services::upload_service::UPLOAD_SCHEME;
+use crate::services::{
+    CollectionService, JobListFilter, ProcessService, QuotaService, UploadService,
+};
+use crate::storage::S3Storage;
 
 /// Process summary
 #[derive(Debug, Serialize, JsonSchema)]
@@ -62,33 +76,210 @@ pub struct JobStatusResponse {
     pub finished: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub updated: Option<String>,
+    /// Explicit expiry overriding the retention policy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    /// Number of execution attempts so far
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<i32>,
+    /// Errors of failed attempts, oldest first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<serde_json::Value>,
+    /// Per-step status for workflow jobs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steps: Option<Vec<WorkflowStepStatus>>,
     pub links: Vec<Link>,
 }
 
+/// Status of a single workflow step
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowStepStatus {
+    pub step: i32,
+    pub job_id: String,
+    pub process_id: String,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<i32>,
+}
+
 /// Job list response
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct JobList {
     pub jobs: Vec<JobStatusResponse>,
     pub links: Vec<Link>,
+    /// Counts per type over all matching jobs
+    pub facets: JobFacets,
+}
+
+/// Facet counts of a job list
+#[derive(Debug, Default, Serialize, JsonSchema)]
+pub struct JobFacets {
+    #[serde(rename = "type")]
+    pub job_type: BTreeMap<String, u64>,
 }
 
 /// Execute request for import-raster process
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct ExecuteImportRaster {
     pub inputs: import_raster::ImportRasterInputs,
+
+    /// Optional expiry overriding the job retention policy
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    pub expires: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 /// Execute request for import-pointcloud process
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct ExecuteImportPointCloud {
     pub inputs: import_pointcloud::ImportPointCloudInputs,
+
+    /// Optional expiry overriding the job retention policy
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    pub expires: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Execute request for export-collection process
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExecuteExportCollection {
+    pub inputs: export_collection::ExportCollectionInputs,
+
+    /// Optional expiry overriding the job retention policy
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    pub expires: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Execute request for temporal-composite process
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExecuteTemporalComposite {
+    pub inputs: temporal_composite::TemporalCompositeInputs,
+
+    /// Optional expiry overriding the job retention policy
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    pub expires: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Execute request for backup-collection process
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExecuteBackupCollection {
+    pub inputs: backup_collection::BackupCollectionInputs,
+
+    /// Optional expiry overriding the job retention policy
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    pub expires: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Execute request for restore-collection process
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExecuteRestoreCollection {
+    pub inputs: restore_collection::RestoreCollectionInputs,
+
+    /// Optional expiry overriding the job retention policy
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    pub expires: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Execute request for a deployed (user-defined) process
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExecuteDeployedProcess {
+    #[serde(default)]
+    pub inputs: serde_json::Map<String, serde_json::Value>,
+
+    /// Optional expiry overriding the job retention policy
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    pub expires: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Object storage prefix for files uploaded with a job's execute request
+///
+/// Keeping uploads under the job prefix lets job retention remove them.
+fn upload_prefix(username: &str, job_id: Uuid) -> String {
+    format!("{}/jobs/{}/uploads", username, job_id)
+}
+
+/// Pass a validation result through, removing the request's uploads on error
+async fn reject_uploads_on_error(
+    storage: &S3Storage,
+    upload_prefix: &str,
+    uploaded: &[String],
+    result: AppResult<()>,
+) -> AppResult<()> {
+    if result.is_err() && !uploaded.is_empty() {
+        upload::discard_uploads(storage, upload_prefix).await;
+    }
+    result
+}
+
+/// Fail when the namespace an import writes to is over its storage quota
+///
+/// The collection is created in the user's namespace unless it exists.
+async fn check_import_quota(
+    collections: &CollectionService,
+    quotas: &QuotaService,
+    username: &str,
+    collection: &str,
+) -> AppResult<()> {
+    if !quotas.is_enabled() {
+        return Ok(());
+    }
+    let namespace = match collections.get_collection(username, collection).await? {
+        Some(existing) => existing.owner,
+        None => username.to_string(),
+    };
+    quotas.check(&namespace).await
+}
+
+/// Point an `upload://` data reference at the completed upload
+///
+/// The upload is removed once it expires, so it must be copied on import.
+async fn resolve_upload_input(
+    uploads: &UploadService,
+    owner: &str,
+    data: &mut InputValue,
+    copy: bool,
+) -> AppResult<()> {
+    let InputValue::Reference(reference) = data else {
+        return Ok(());
+    };
+    if !reference.href.starts_with(UPLOAD_SCHEME) {
+        return Ok(());
+    }
+    if !copy {
+        return Err(AppError::BadRequest(
+            "Uploads must be imported with copy enabled".to_string(),
+        ));
+    }
+    reference.href = uploads.resolve_href(owner, &reference.href).await?;
+    Ok(())
+}
+
+/// Reject job expiry overrides that are already in the past
+fn validate_expires(expires: Option<chrono::DateTime<chrono::Utc>>) -> AppResult<()> {
+    if expires.is_some_and(|expires| expires <= chrono::Utc::now()) {
+        return Err(AppError::BadRequest(
+            "expires must be in the future".to_string(),
+        ));
+    }
+    Ok(())
 }
 
 /// List available processes
-pub async fn list_processes(Extension(config): Extension<Arc<Config>>) -> Json<ProcessList> {
+pub async fn list_processes(
+    Extension(config): Extension<Arc<Config>>,
+    State(service): State<Arc<ProcessService>>,
+) -> AppResult<Json<ProcessList>> {
     let base_url = &config.base_url;
 
-    let processes = vec![
+    let mut processes = vec![
         ProcessSummary {
             id: import_raster::PROCESS_ID.to_string(),
             title: "Import Raster".to_string(),
@@ -117,19 +308,98 @@ pub async fn list_processes(Extension(config): Extension<Arc<Config>>) -> Json<P
                 .with_type(media_type::JSON),
             ],
         },
+        ProcessSummary {
+            id: export_collection::PROCESS_ID.to_string(),
+            title: "Export Collection".to_string(),
+            description: Some("Export a vector collection as Shapefile, KML or KMZ".to_string()),
+            version: "1.0.0".to_string(),
+            job_control_options: vec!["async-execute".to_string()],
+            links: vec![
+                Link::new(
+                    format!("{}/processes/{}", base_url, export_collection::PROCESS_ID),
+                    rel::SELF,
+                )
+                .with_type(media_type::JSON),
+            ],
+        },
+        ProcessSummary {
+            id: temporal_composite::PROCESS_ID.to_string(),
+            title: "Temporal Composite".to_string(),
+            description: Some(
+                "Combine the items of a raster collection over a time interval into one item"
+                    .to_string(),
+            ),
+            version: "1.0.0".to_string(),
+            job_control_options: vec!["async-execute".to_string()],
+            links: vec![
+                Link::new(
+                    format!("{}/processes/{}", base_url, temporal_composite::PROCESS_ID),
+                    rel::SELF,
+                )
+                .with_type(media_type::JSON),
+            ],
+        },
+        ProcessSummary {
+            id: backup_collection::PROCESS_ID.to_string(),
+            title: "Back Up Collection".to_string(),
+            description: Some(
+                "Back up a collection with its features or items into an archive".to_string(),
+            ),
+            version: "1.0.0".to_string(),
+            job_control_options: vec!["async-execute".to_string()],
+            links: vec![
+                Link::new(
+                    format!("{}/processes/{}", base_url, backup_collection::PROCESS_ID),
+                    rel::SELF,
+                )
+                .with_type(media_type::JSON),
+            ],
+        },
+        ProcessSummary {
+            id: restore_collection::PROCESS_ID.to_string(),
+            title: "Restore Collection".to_string(),
+            description: Some("Create a collection from a backup".to_string()),
+            version: "1.0.0".to_string(),
+            job_control_options: vec!["async-execute".to_string()],
+            links: vec![
+                Link::new(
+                    format!("{}/processes/{}", base_url, restore_collection::PROCESS_ID),
+                    rel::SELF,
+                )
+                .with_type(media_type::JSON),
+            ],
+        },
     ];
 
-    Json(ProcessList {
+    processes.extend(
+        service
+            .list_deployed_processes()
+            .await?
+            .into_iter()
+            .map(|process| ProcessSummary {
+                links: vec![
+                    Link::new(format!("{}/processes/{}", base_url, process.id), rel::SELF)
+                        .with_type(media_type::JSON),
+                ],
+                id: process.id,
+                title: process.title,
+                description: process.description,
+                version: process.version,
+                job_control_options: vec!["async-execute".to_string()],
+            }),
+    );
+
+    Ok(Json(ProcessList {
         processes,
         links: vec![
             Link::new(format!("{}/processes", base_url), rel::SELF).with_type(media_type::JSON),
         ],
-    })
+    }))
 }
 
 fn list_processes_docs(op: TransformOperation) -> TransformOperation {
     op.summary("List processes")
-        .description("Returns the list of available processing operations")
+        .description("Returns the list of available processing operations, including deployed user-defined processes")
         .tag("Processes")
         .response_with::<200, Json<ProcessList>, _>(|res| {
             res.description("List of available processes")
@@ -146,24 +416,176 @@ pub struct ProcessPath {
 
 /// Get process description
 pub async fn get_process(
-    Extension(_config): Extension<Arc<Config>>,
+    Extension(config): Extension<Arc<Config>>,
+    State(service): State<Arc<ProcessService>>,
     path: ProcessPath,
 ) -> AppResult<Json<serde_json::Value>> {
     let process_id = path.process_id;
     let description = match process_id.as_str() {
         "import-raster" => import_raster::process_description(),
         "import-pointcloud" => import_pointcloud::process_description(),
+        "export-collection" => export_collection::process_description(),
+        "temporal-composite" => temporal_composite::process_description(),
+        "backup-collection" => backup_collection::process_description(),
+        "restore-collection" => restore_collection::process_description(),
         _ => {
-            return Err(AppError::NotFound(format!(
-                "Process not found: {}",
-                process_id
-            )));
+            let process = service
+                .get_deployed_process(&process_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("Process not found: {}", process_id)))?;
+            deployed_process_description(&process, &config.base_url)
         }
     };
 
     Ok(Json(description))
 }
 
+/// Build the OGC process description for a deployed process
+fn deployed_process_description(process: &DeployedProcess, base_url: &str) -> serde_json::Value {
+    let mut description = process.process_description.clone();
+    description["jobControlOptions"] = serde_json::json!(["async-execute"]);
+    description["outputTransmission"] = serde_json::json!(["value", "reference"]);
+    description["links"] = serde_json::json!([
+        Link::new(format!("{}/processes/{}", base_url, process.id), rel::SELF)
+            .with_type(media_type::JSON),
+        Link::new(
+            format!("{}/processes/{}/execution", base_url, process.id),
+            "http://www.opengis.net/def/rel/ogc/1.0/execute",
+        )
+        .with_type(media_type::JSON),
+    ]);
+    description
+}
+
+/// Ensure the user may manage deployed processes, being in the admin group
+/// either by token or by database role
+async fn require_process_admin(
+    config: &Config,
+    user: &AuthenticatedUser,
+    service: &ProcessService,
+) -> AppResult<()> {
+    let admin_group = &config.processing.admin_group;
+    if user.groups.iter().any(|group| group == admin_group)
+        || service.is_admin_member(&user.username, admin_group).await?
+    {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden(format!(
+            "Managing processes requires membership in the '{}' group",
+            config.processing.admin_group
+        )))
+    }
+}
+
+/// Deploy a new process (OGC API Processes Part 2)
+pub async fn deploy_process(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<ProcessService>>,
+    Json(package): Json<ApplicationPackage>,
+) -> AppResult<(StatusCode, HeaderMap, Json<ProcessSummary>)> {
+    require_process_admin(&config, &user, &service).await?;
+    package.validate()?;
+
+    let process = service.deploy_process(&user.username, &package).await?;
+
+    let location = format!("{}/processes/{}", config.base_url, process.id);
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::LOCATION,
+        location
+            .parse()
+            .map_err(|_| AppError::Internal("Invalid location URL".to_string()))?,
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        headers,
+        Json(ProcessSummary {
+            links: vec![Link::new(location, rel::SELF).with_type(media_type::JSON)],
+            id: process.id,
+            title: process.title,
+            description: process.description,
+            version: process.version,
+            job_control_options: vec!["async-execute".to_string()],
+        }),
+    ))
+}
+
+fn deploy_process_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Deploy process")
+        .description("Deploys a user-defined process from an OGC Application Package with a container execution unit. Requires process admin privileges.")
+        .tag("Processes")
+        .response_with::<201, Json<ProcessSummary>, _>(|res| {
+            res.description("Process deployed")
+        })
+        .response_with::<400, (), _>(|res| res.description("Invalid application package"))
+        .response_with::<403, (), _>(|res| res.description("Not a process admin"))
+        .response_with::<409, (), _>(|res| res.description("Process already exists"))
+}
+
+/// Replace a deployed process (OGC API Processes Part 2)
+pub async fn replace_process(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<ProcessService>>,
+    path: ProcessPath,
+    Json(package): Json<ApplicationPackage>,
+) -> AppResult<StatusCode> {
+    require_process_admin(&config, &user, &service).await?;
+
+    if package.process_description.id != path.process_id {
+        return Err(AppError::BadRequest(
+            "processDescription.id must match the process id in the path".to_string(),
+        ));
+    }
+    package.validate()?;
+
+    service.replace_process(&path.process_id, &package).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn replace_process_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Replace process")
+        .description(
+            "Replaces the definition of a deployed process. Requires process admin privileges.",
+        )
+        .tag("Processes")
+        .response_with::<204, (), _>(|res| res.description("Process replaced"))
+        .response_with::<400, (), _>(|res| res.description("Invalid application package"))
+        .response_with::<403, (), _>(|res| res.description("Not a process admin"))
+        .response_with::<404, (), _>(|res| res.description("Process not found"))
+}
+
+/// Undeploy a process (OGC API Processes Part 2)
+pub async fn undeploy_process(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<ProcessService>>,
+    path: ProcessPath,
+) -> AppResult<StatusCode> {
+    require_process_admin(&config, &user, &service).await?;
+
+    if deploy::is_builtin_process(&path.process_id) {
+        return Err(AppError::Forbidden(format!(
+            "Process '{}' is built in and cannot be undeployed",
+            path.process_id
+        )));
+    }
+
+    service.undeploy_process(&path.process_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn undeploy_process_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Undeploy process")
+        .description("Removes a deployed process. Built-in processes cannot be undeployed. Requires process admin privileges.")
+        .tag("Processes")
+        .response_with::<204, (), _>(|res| res.description("Process undeployed"))
+        .response_with::<403, (), _>(|res| res.description("Not a process admin"))
+        .response_with::<404, (), _>(|res| res.description("Process not found"))
+}
+
 fn get_process_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Get process description")
         .description("Returns the detailed description of a processing operation, including inputs and outputs")
@@ -178,6 +600,7 @@ fn get_process_docs(op: TransformOperation) -> TransformOperation {
 fn create_job_response(
     job_id: Uuid,
     process_id: &str,
+    expires: Option<chrono::DateTime<chrono::Utc>>,
     base_url: &str,
 ) -> (StatusCode, HeaderMap, Json<JobStatusResponse>) {
     let response = JobStatusResponse {
@@ -191,6 +614,10 @@ fn create_job_response(
         started: None,
         finished: None,
         updated: Some(chrono::Utc::now().to_rfc3339()),
+        expires: expires.map(|dt| dt.to_rfc3339()),
+        attempts: None,
+        errors: None,
+        steps: None,
         links: vec![
             Link::new(format!("{}/jobs/{}", base_url, job_id), rel::SELF)
                 .with_type(media_type::JSON),
@@ -215,66 +642,754 @@ fn create_job_response(
 pub async fn execute_import_raster(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
+    Extension(collections): Extension<Arc<CollectionService>>,
+    Extension(quotas): Extension<Arc<QuotaService>>,
     State(service): State<Arc<ProcessService>>,
-    Json(request): Json<ExecuteImportRaster>,
+    Extension(storage): Extension<Arc<S3Storage>>,
+    Extension(uploads): Extension<Arc<UploadService>>,
+    body: ExecuteBody<ExecuteImportRaster>,
 ) -> AppResult<(StatusCode, HeaderMap, Json<JobStatusResponse>)> {
-    // Validate inputs
-    request.inputs.validate()?;
+    let job_id = Uuid::new_v4();
+    let upload_prefix = upload_prefix(&user.username, job_id);
+    let (mut request, uploaded) = body
+        .into_request(&storage, &upload_prefix, &config.processing)
+        .await?;
+    let resolved = resolve_upload_input(
+        &uploads,
+        &user.username,
+        &mut request.inputs.data,
+        request.inputs.copy,
+    )
+    .await;
+    reject_uploads_on_error(&storage, &upload_prefix, &uploaded, resolved).await?;
+
+    // Validate inputs; uploads live with the job, so they must be copied
+    let validation = request.inputs.validate().and_then(|_| {
+        user.check_collection(&request.inputs.collection)?;
+        validate_expires(request.expires)?;
+        if !uploaded.is_empty() && !request.inputs.copy {
+            return Err(AppError::BadRequest(
+                "Uploaded files must be imported with copy enabled".to_string(),
+            ));
+        }
+        Ok(())
+    });
+    reject_uploads_on_error(&storage, &upload_prefix, &uploaded, validation).await?;
+    let quota = check_import_quota(
+        &collections,
+        &quotas,
+        &user.username,
+        &request.inputs.collection,
+    )
+    .await;
+    reject_uploads_on_error(&storage, &upload_prefix, &uploaded, quota).await?;
 
     // Create job with inputs serialized to JSON
     let inputs_json = serde_json::to_value(&request.inputs)?;
-    let job_id = service
-        .create_job(&user.username, import_raster::PROCESS_ID, &inputs_json)
+    service
+        .create_job(
+            job_id,
+            &user.username,
+            import_raster::PROCESS_ID,
+            &inputs_json,
+            request.expires,
+        )
         .await?;
 
     Ok(create_job_response(
         job_id,
         import_raster::PROCESS_ID,
+        request.expires,
         &config.base_url,
     ))
 }
 
 fn execute_import_raster_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Execute import-raster")
-        .description("Imports a raster file into a collection. Accepts COG (pass-through) or other formats (converts to COG via GDAL).")
+        .description("Imports a raster file into a collection. Accepts COG (pass-through) or other formats (converts to COG via GDAL). Files can be uploaded with a multipart/form-data request instead of base64 inline values, or referenced as `upload://{id}` after a chunked upload.")
         .tag("Processes")
+        .with(|op| {
+            openapi::request_example(
+                op,
+                serde_json::json!({
+                    "inputs": {
+                        "collection": "elevation",
+                        "data": { "href": "https://example.com/dem.tif", "type": "image/tiff" },
+                        "title": "DEM 2024",
+                        "datetime": "2024-06-01T00:00:00Z"
+                    }
+                }),
+            )
+        })
         .response_with::<201, Json<JobStatusResponse>, _>(|res| {
             res.description("Job created successfully")
         })
         .response_with::<400, (), _>(|res| res.description("Invalid inputs"))
+        .response_with::<507, (), _>(|res| {
+            res.description("The namespace of the collection is over its storage quota")
+        })
 }
 
 /// Execute import-pointcloud process
 pub async fn execute_import_pointcloud(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
+    Extension(collections): Extension<Arc<CollectionService>>,
+    Extension(quotas): Extension<Arc<QuotaService>>,
     State(service): State<Arc<ProcessService>>,
-    Json(request): Json<ExecuteImportPointCloud>,
+    Extension(storage): Extension<Arc<S3Storage>>,
+    Extension(uploads): Extension<Arc<UploadService>>,
+    body: ExecuteBody<ExecuteImportPointCloud>,
 ) -> AppResult<(StatusCode, HeaderMap, Json<JobStatusResponse>)> {
-    // Validate inputs
-    request.inputs.validate()?;
+    let job_id = Uuid::new_v4();
+    let upload_prefix = upload_prefix(&user.username, job_id);
+    let (mut request, uploaded) = body
+        .into_request(&storage, &upload_prefix, &config.processing)
+        .await?;
+    let resolved = resolve_upload_input(
+        &uploads,
+        &user.username,
+        &mut request.inputs.data,
+        request.inputs.copy,
+    )
+    .await;
+    reject_uploads_on_error(&storage, &upload_prefix, &uploaded, resolved).await?;
+
+    // Validate inputs; uploads live with the job, so they must be copied
+    let validation = request.inputs.validate().and_then(|_| {
+        user.check_collection(&request.inputs.collection)?;
+        validate_expires(request.expires)?;
+        if !uploaded.is_empty() && !request.inputs.copy {
+            return Err(AppError::BadRequest(
+                "Uploaded files must be imported with copy enabled".to_string(),
+            ));
+        }
+        Ok(())
+    });
+    reject_uploads_on_error(&storage, &upload_prefix, &uploaded, validation).await?;
+    let quota = check_import_quota(
+        &collections,
+        &quotas,
+        &user.username,
+        &request.inputs.collection,
+    )
+    .await;
+    reject_uploads_on_error(&storage, &upload_prefix, &uploaded, quota).await?;
 
     // Create job with inputs serialized to JSON
     let inputs_json = serde_json::to_value(&request.inputs)?;
-    let job_id = service
-        .create_job(&user.username, import_pointcloud::PROCESS_ID, &inputs_json)
+    service
+        .create_job(
+            job_id,
+            &user.username,
+            import_pointcloud::PROCESS_ID,
+            &inputs_json,
+            request.expires,
+        )
         .await?;
 
     Ok(create_job_response(
         job_id,
         import_pointcloud::PROCESS_ID,
+        request.expires,
         &config.base_url,
     ))
 }
 
 fn execute_import_pointcloud_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Execute import-pointcloud")
-        .description("Imports a point cloud file into a collection. Accepts COPC (pass-through) or other formats (converts to COPC).")
+        .description("Imports a point cloud file into a collection. Accepts COPC (pass-through) or other formats (converts to COPC). Files can be uploaded with a multipart/form-data request instead of base64 inline values, or referenced as `upload://{id}` after a chunked upload.")
+        .tag("Processes")
+        .with(|op| {
+            openapi::request_example(
+                op,
+                serde_json::json!({
+                    "inputs": {
+                        "collection": "lidar",
+                        "data": { "href": "https://example.com/survey.laz" },
+                        "title": "Survey 2024"
+                    }
+                }),
+            )
+        })
+        .response_with::<201, Json<JobStatusResponse>, _>(|res| {
+            res.description("Job created successfully")
+        })
+        .response_with::<400, (), _>(|res| res.description("Invalid inputs"))
+        .response_with::<507, (), _>(|res| {
+            res.description("The namespace of the collection is over its storage quota")
+        })
+}
+
+/// Execute export-collection process
+pub async fn execute_export_collection(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Extension(collections): Extension<Arc<CollectionService>>,
+    State(service): State<Arc<ProcessService>>,
+    Json(request): Json<ExecuteExportCollection>,
+) -> AppResult<(StatusCode, HeaderMap, Json<JobStatusResponse>)> {
+    request.inputs.validate()?;
+    validate_expires(request.expires)?;
+    user.check_collection(&request.inputs.collection)?;
+
+    // Fail early rather than in the worker
+    let collection = collections
+        .get_readable_collection(&user.username, &request.inputs.collection)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "Collection not found: {}",
+                request.inputs.collection
+            ))
+        })?;
+    if !collection.as_collection().has_geometry() {
+        return Err(AppError::BadRequest(format!(
+            "Only vector collections can be exported, {} is a {} collection",
+            collection.canonical_name, collection.collection_type
+        )));
+    }
+
+    let job_id = Uuid::new_v4();
+    let inputs_json = serde_json::to_value(&request.inputs)?;
+    service
+        .create_job(
+            job_id,
+            &user.username,
+            export_collection::PROCESS_ID,
+            &inputs_json,
+            request.expires,
+        )
+        .await?;
+
+    Ok(create_job_response(
+        job_id,
+        export_collection::PROCESS_ID,
+        request.expires,
+        &config.base_url,
+    ))
+}
+
+fn execute_export_collection_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Execute export-collection")
+        .description("Exports a vector collection as a zipped ESRI Shapefile, KML or KMZ in a background job, so large exports don't tie up a request. The file is downloaded from `/jobs/{jobId}/results/export` once the job has succeeded.")
+        .tag("Processes")
+        .with(|op| {
+            openapi::request_example(
+                op,
+                serde_json::json!({
+                    "inputs": {
+                        "collection": "roads",
+                        "format": "shapefile",
+                        "encoding": "iso-8859-1"
+                    }
+                }),
+            )
+        })
+        .response_with::<201, Json<JobStatusResponse>, _>(|res| {
+            res.description("Job created successfully")
+        })
+        .response_with::<400, (), _>(|res| res.description("Invalid inputs"))
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+/// Execute the temporal-composite process
+pub async fn execute_temporal_composite(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Extension(collections): Extension<Arc<CollectionService>>,
+    Extension(quotas): Extension<Arc<QuotaService>>,
+    State(service): State<Arc<ProcessService>>,
+    Json(request): Json<ExecuteTemporalComposite>,
+) -> AppResult<(StatusCode, HeaderMap, Json<JobStatusResponse>)> {
+    request.inputs.validate()?;
+    validate_expires(request.expires)?;
+    user.check_collection(&request.inputs.collection)?;
+    user.check_collection(&request.inputs.target)?;
+
+    // Fail early rather than in the worker
+    let collection = collections
+        .get_readable_collection(&user.username, &request.inputs.collection)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "Collection not found: {}",
+                request.inputs.collection
+            ))
+        })?;
+    if collection.collection_type != "raster" {
+        return Err(AppError::BadRequest(format!(
+            "Only raster collections can be composited, {} is a {} collection",
+            collection.canonical_name, collection.collection_type
+        )));
+    }
+    check_import_quota(
+        &collections,
+        &quotas,
+        &user.username,
+        &request.inputs.target,
+    )
+    .await?;
+
+    let job_id = Uuid::new_v4();
+    let inputs_json = serde_json::to_value(&request.inputs)?;
+    service
+        .create_job(
+            job_id,
+            &user.username,
+            temporal_composite::PROCESS_ID,
+            &inputs_json,
+            request.expires,
+        )
+        .await?;
+
+    Ok(create_job_response(
+        job_id,
+        temporal_composite::PROCESS_ID,
+        request.expires,
+        &config.base_url,
+    ))
+}
+
+fn execute_temporal_composite_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Execute temporal-composite")
+        .description("Computes a median, mean, minimum or maximum composite of the items of a raster collection over a time interval and area of interest in a background job, of all bands or of a normalized difference such as NDVI, adding it to the target collection as a new Cloud Optimized GeoTIFF item.")
+        .tag("Processes")
+        .with(|op| {
+            openapi::request_example(
+                op,
+                serde_json::json!({
+                    "inputs": {
+                        "collection": "sentinel2-ndvi",
+                        "datetime": "2024-06-01T00:00:00Z/2024-08-31T23:59:59Z",
+                        "bbox": [11.0, 57.0, 12.5, 58.0],
+                        "method": "median",
+                        "target": "ndvi-summer-composites"
+                    }
+                }),
+            )
+        })
+        .response_with::<201, Json<JobStatusResponse>, _>(|res| {
+            res.description("Job created successfully")
+        })
+        .response_with::<400, (), _>(|res| res.description("Invalid inputs"))
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+        .response_with::<507, (), _>(|res| {
+            res.description("The namespace of the target collection is over its storage quota")
+        })
+}
+
+/// Execute the backup-collection process
+pub async fn execute_backup_collection(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Extension(collections): Extension<Arc<CollectionService>>,
+    State(service): State<Arc<ProcessService>>,
+    Json(request): Json<ExecuteBackupCollection>,
+) -> AppResult<(StatusCode, HeaderMap, Json<JobStatusResponse>)> {
+    request.inputs.validate()?;
+    validate_expires(request.expires)?;
+    user.check_collection(&request.inputs.collection)?;
+
+    // Fail early rather than in the worker
+    let collection = collections
+        .get_collection(&user.username, &request.inputs.collection)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "Collection not found: {}",
+                request.inputs.collection
+            ))
+        })?;
+    if collection.owner != user.username {
+        return Err(AppError::Forbidden(
+            "Only the owner may back up a collection".to_string(),
+        ));
+    }
+
+    let job_id = Uuid::new_v4();
+    let inputs_json = serde_json::to_value(&request.inputs)?;
+    service
+        .create_job(
+            job_id,
+            &user.username,
+            backup_collection::PROCESS_ID,
+            &inputs_json,
+            request.expires,
+        )
+        .await?;
+
+    Ok(create_job_response(
+        job_id,
+        backup_collection::PROCESS_ID,
+        request.expires,
+        &config.base_url,
+    ))
+}
+
+fn execute_backup_collection_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Execute backup-collection")
+        .description("Backs up a collection you own in a background job: its definition and metadata, its features or items and the manifest of its assets are streamed to a versioned, gzipped JSON lines archive, one line per feature or item. The backup outlives the job and is downloaded from `/jobs/{jobId}/results/backup` once the job has succeeded.")
+        .tag("Processes")
+        .with(|op| {
+            openapi::request_example(
+                op,
+                serde_json::json!({
+                    "inputs": {
+                        "collection": "roads"
+                    }
+                }),
+            )
+        })
+        .response_with::<201, Json<JobStatusResponse>, _>(|res| {
+            res.description("Job created successfully")
+        })
+        .response_with::<400, (), _>(|res| res.description("Invalid inputs"))
+        .response_with::<403, (), _>(|res| res.description("Only the owner may back up a collection"))
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+/// Execute the restore-collection process
+pub async fn execute_restore_collection(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Extension(collections): Extension<Arc<CollectionService>>,
+    Extension(quotas): Extension<Arc<QuotaService>>,
+    Extension(storage): Extension<Arc<S3Storage>>,
+    State(service): State<Arc<ProcessService>>,
+    Json(request): Json<ExecuteRestoreCollection>,
+) -> AppResult<(StatusCode, HeaderMap, Json<JobStatusResponse>)> {
+    request.inputs.validate()?;
+    validate_expires(request.expires)?;
+
+    // Only the user's own backups can be restored
+    let key = request
+        .inputs
+        .backup
+        .strip_prefix(&storage.s3_uri(""))
+        .filter(|key| key.starts_with(&backup_collection::backup_prefix(&user.username)))
+        .ok_or_else(|| {
+            AppError::BadRequest(format!(
+                "Not one of your backups: {}",
+                request.inputs.backup
+            ))
+        })?;
+    if !storage.exists(key).await? {
+        return Err(AppError::NotFound(format!(
+            "Backup not found: {}",
+            request.inputs.backup
+        )));
+    }
+
+    // The target must be free; without an id it is only known in the worker
+    if let Some(collection) = &request.inputs.collection {
+        user.check_collection(collection)?;
+        let canonical_name = restore_collection::target_collection(&user.username, collection)?;
+        if collections
+            .get_collection(&user.username, &canonical_name)
+            .await?
+            .is_some()
+        {
+            return Err(AppError::Conflict(format!(
+                "Collection already exists: {}",
+                canonical_name
+            )));
+        }
+    }
+
+    // The restored collection is created in the user's namespace
+    quotas.check(&user.username).await?;
+
+    let job_id = Uuid::new_v4();
+    let inputs_json = serde_json::to_value(&request.inputs)?;
+    service
+        .create_job(
+            job_id,
+            &user.username,
+            restore_collection::PROCESS_ID,
+            &inputs_json,
+            request.expires,
+        )
+        .await?;
+
+    Ok(create_job_response(
+        job_id,
+        restore_collection::PROCESS_ID,
+        request.expires,
+        &config.base_url,
+    ))
+}
+
+fn execute_restore_collection_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Execute restore-collection")
+        .description("Creates a collection from one of your backups in a background job, under the id of the backed up collection or the given one. The collection must not exist yet.")
+        .tag("Processes")
+        .with(|op| {
+            openapi::request_example(
+                op,
+                serde_json::json!({
+                    "inputs": {
+                        "backup": "s3://spatialvault/alice/backups/0b7e2f3a-5c4d-4e6f-8a9b-1c2d3e4f5a6b/alice_roads-20240601T120000Z.backup.json.gz",
+                        "collection": "roads-restored"
+                    }
+                }),
+            )
+        })
+        .response_with::<201, Json<JobStatusResponse>, _>(|res| {
+            res.description("Job created successfully")
+        })
+        .response_with::<400, (), _>(|res| res.description("Invalid inputs, or not one of your backups"))
+        .response_with::<403, (), _>(|res| res.description("The collection id is in another user's schema"))
+        .response_with::<404, (), _>(|res| res.description("Backup not found"))
+        .response_with::<409, (), _>(|res| res.description("The collection already exists"))
+        .response_with::<507, (), _>(|res| {
+            res.description("Your namespace is over its storage quota")
+        })
+}
+
+/// Path parameters for process execution endpoint
+#[aide::axum::typed_path]
+#[typed_path("/processes/{process_id}/execution")]
+pub struct ProcessExecutionPath {
+    /// The process identifier
+    pub process_id: String,
+}
+
+/// Execute a deployed process
+pub async fn execute_deployed_process(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<ProcessService>>,
+    Extension(storage): Extension<Arc<S3Storage>>,
+    Extension(uploads): Extension<Arc<UploadService>>,
+    path: ProcessExecutionPath,
+    body: ExecuteBody<ExecuteDeployedProcess>,
+) -> AppResult<(StatusCode, HeaderMap, Json<JobStatusResponse>)> {
+    let process = service
+        .get_deployed_process(&path.process_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Process not found: {}", path.process_id)))?;
+
+    let job_id = Uuid::new_v4();
+    let upload_prefix = upload_prefix(&user.username, job_id);
+    let (request, uploaded) = body
+        .into_request(&storage, &upload_prefix, &config.processing)
+        .await?;
+
+    let validation = deploy::validate_execute_inputs(&process.process_description, &request.inputs)
+        .and_then(|_| validate_expires(request.expires))
+        .and_then(|_| {
+            user.check_inputs(
+                &serde_json::Value::Object(request.inputs.clone()),
+                &config.base_url,
+            )
+        });
+    reject_uploads_on_error(&storage, &upload_prefix, &uploaded, validation).await?;
+
+    let mut inputs_json = serde_json::Value::Object(request.inputs);
+    let resolved = uploads
+        .resolve_hrefs(&user.username, &mut inputs_json)
+        .await;
+    reject_uploads_on_error(&storage, &upload_prefix, &uploaded, resolved).await?;
+    service
+        .create_job(
+            job_id,
+            &user.username,
+            &process.id,
+            &inputs_json,
+            request.expires,
+        )
+        .await?;
+
+    Ok(create_job_response(
+        job_id,
+        &process.id,
+        request.expires,
+        &config.base_url,
+    ))
+}
+
+fn execute_deployed_process_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Execute deployed process")
+        .description("Executes a deployed user-defined process asynchronously. Reference inputs (href), including `upload://{id}` chunked uploads, are staged into the container; output files are uploaded to storage.")
         .tag("Processes")
         .response_with::<201, Json<JobStatusResponse>, _>(|res| {
             res.description("Job created successfully")
         })
         .response_with::<400, (), _>(|res| res.description("Invalid inputs"))
+        .response_with::<404, (), _>(|res| res.description("Process not found"))
+}
+
+/// Execute a workflow (chain of processes)
+pub async fn execute_workflow(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<ProcessService>>,
+    Json(request): Json<WorkflowRequest>,
+) -> AppResult<(StatusCode, HeaderMap, Json<JobStatusResponse>)> {
+    // Steps are executed like direct requests, so the token's scope applies to each
+    for step in &request.steps {
+        user.check_process(&step.process)?;
+        user.check_inputs(
+            &serde_json::Value::Object(step.inputs.clone()),
+            &config.base_url,
+        )?;
+    }
+
+    request.validate()?;
+    validate_expires(request.expires)?;
+
+    for process_id in request.deployed_processes() {
+        if service.get_deployed_process(process_id).await?.is_none() {
+            return Err(AppError::BadRequest(format!(
+                "Unknown process in workflow: {}",
+                process_id
+            )));
+        }
+    }
+
+    let job_id = service
+        .create_workflow_job(&user.username, &request)
+        .await?;
+
+    Ok(create_job_response(
+        job_id,
+        workflow::PROCESS_ID,
+        request.expires,
+        &config.base_url,
+    ))
+}
+
+fn execute_workflow_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Execute workflow")
+        .description("Runs a chain of processes as one job. Each step runs after the previous one succeeds; a step input of the form {\"$output\": \"name\"} receives the named output of the previous step. Per-step status is reported in the job's `steps`. A scoped token must allow the process and collections of every step.")
+        .tag("Processes")
+        .response_with::<201, Json<JobStatusResponse>, _>(|res| {
+            res.description("Workflow job created successfully")
+        })
+        .response_with::<400, (), _>(|res| res.description("Invalid workflow"))
+        .response_with::<403, (), _>(|res| {
+            res.description("The token's scope doesn't allow a step")
+        })
+}
+
+/// Query parameters for the job list
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct ListJobsParams {
+    /// Comma-separated job statuses (accepted, running, successful, failed, dismissed)
+    pub status: Option<String>,
+
+    /// Comma-separated process identifiers
+    #[serde(rename = "processID")]
+    pub process_id: Option<String>,
+
+    /// Comma-separated job types (e.g., "process")
+    #[serde(rename = "type")]
+    pub job_type: Option<String>,
+
+    /// Creation time instant or interval (RFC 3339, open ends as "..")
+    pub datetime: Option<String>,
+
+    /// Jobs per page (1-10000, default 100)
+    #[serde(default = "default_job_limit")]
+    pub limit: u32,
+
+    #[serde(default)]
+    pub offset: u32,
+}
+
+fn default_job_limit() -> u32 {
+    100
+}
+
+const JOB_STATUSES: &[&str] = &["accepted", "running", "successful", "failed", "dismissed"];
+
+impl ListJobsParams {
+    /// Validate the parameters and convert them into a service filter
+    pub fn to_filter(&self) -> AppResult<JobListFilter> {
+        if self.limit == 0 || self.limit > 10000 {
+            return Err(AppError::BadRequest(
+                "limit must be between 1 and 10000".to_string(),
+            ));
+        }
+
+        let statuses = split_list(self.status.as_deref());
+        if let Some(invalid) = statuses
+            .iter()
+            .flatten()
+            .find(|status| !JOB_STATUSES.contains(&status.as_str()))
+        {
+            return Err(AppError::BadRequest(format!(
+                "Invalid job status: {}",
+                invalid
+            )));
+        }
+
+        let (created_after, created_before) = match self.datetime.as_deref() {
+            Some(datetime) => parse_job_datetime(datetime)?,
+            None => (None, None),
+        };
+
+        Ok(JobListFilter {
+            statuses,
+            process_ids: split_list(self.process_id.as_deref()),
+            types: split_list(self.job_type.as_deref()),
+            created_after,
+            created_before,
+        })
+    }
+
+    /// Job list URL for the given offset, preserving the filters
+    fn page_href(&self, base_url: &str, offset: u32) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        for (key, value) in [
+            ("status", &self.status),
+            ("processID", &self.process_id),
+            ("type", &self.job_type),
+            ("datetime", &self.datetime),
+        ] {
+            if let Some(value) = value {
+                query.append_pair(key, value);
+            }
+        }
+        query.append_pair("limit", &self.limit.to_string());
+        query.append_pair("offset", &offset.to_string());
+        format!("{}/jobs?{}", base_url, query.finish())
+    }
+}
+
+fn split_list(value: Option<&str>) -> Option<Vec<String>> {
+    value.map(|v| {
+        v.split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect()
+    })
+}
+
+type DateTimeBounds = (
+    Option<chrono::DateTime<chrono::Utc>>,
+    Option<chrono::DateTime<chrono::Utc>>,
+);
+
+/// Parse a datetime instant or interval into inclusive bounds
+fn parse_job_datetime(datetime: &str) -> AppResult<DateTimeBounds> {
+    let parse = |instant: &str| -> AppResult<Option<chrono::DateTime<chrono::Utc>>> {
+        if instant.is_empty() || instant == ".." {
+            return Ok(None);
+        }
+        chrono::DateTime::parse_from_rfc3339(instant)
+            .map(|dt| Some(dt.with_timezone(&chrono::Utc)))
+            .map_err(|_| AppError::BadRequest(format!("Invalid datetime: {}", instant)))
+    };
+
+    match datetime.split_once('/') {
+        Some((start, end)) => Ok((parse(start)?, parse(end)?)),
+        None => {
+            let instant = parse(datetime)?;
+            Ok((instant, instant))
+        }
+    }
 }
 
 /// List jobs
@@ -282,8 +1397,13 @@ pub async fn list_jobs(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
     State(service): State<Arc<ProcessService>>,
+    Query(params): Query<ListJobsParams>,
 ) -> AppResult<Json<JobList>> {
-    let jobs = service.list_jobs(&user.username).await?;
+    let filter = params.to_filter()?;
+    let (jobs, type_counts) = service
+        .list_jobs(&user.username, &filter, params.limit, params.offset)
+        .await?;
+    let total: u64 = type_counts.values().sum();
 
     let base_url = &config.base_url;
 
@@ -300,6 +1420,10 @@ pub async fn list_jobs(
             started: job.started.map(|dt| dt.to_rfc3339()),
             finished: job.finished.map(|dt| dt.to_rfc3339()),
             updated: job.updated.map(|dt| dt.to_rfc3339()),
+            expires: job.expires.map(|dt| dt.to_rfc3339()),
+            attempts: None,
+            errors: None,
+            steps: None,
             links: vec![
                 Link::new(format!("{}/jobs/{}", base_url, job.id), rel::SELF)
                     .with_type(media_type::JSON),
@@ -307,17 +1431,50 @@ pub async fn list_jobs(
         })
         .collect();
 
+    let mut links = vec![
+        Link::new(params.page_href(base_url, params.offset), rel::SELF).with_type(media_type::JSON),
+    ];
+
+    if (params.offset as u64) + (params.limit as u64) < total {
+        links.push(
+            Link::new(
+                params.page_href(base_url, params.offset + params.limit),
+                rel::NEXT,
+            )
+            .with_type(media_type::JSON),
+        );
+    }
+
+    if params.offset > 0 {
+        links.push(
+            Link::new(
+                params.page_href(base_url, params.offset.saturating_sub(params.limit)),
+                rel::PREV,
+            )
+            .with_type(media_type::JSON),
+        );
+    }
+
     Ok(Json(JobList {
         jobs: job_responses,
-        links: vec![Link::new(format!("{}/jobs", base_url), rel::SELF).with_type(media_type::JSON)],
+        links,
+        facets: JobFacets {
+            job_type: type_counts,
+        },
     }))
 }
 
 fn list_jobs_docs(op: TransformOperation) -> TransformOperation {
     op.summary("List jobs")
-        .description("Returns a list of all jobs owned by the authenticated user")
+        .description(
+            "Returns the jobs owned by the authenticated user, newest first. \
+             Jobs can be filtered by status, processID, type and creation datetime; \
+             results are paged with limit (default 100) and offset and next/prev links. \
+             `facets` counts the matching jobs per type.",
+        )
         .tag("Processes")
         .response_with::<200, Json<JobList>, _>(|res| res.description("List of jobs"))
+        .response_with::<400, (), _>(|res| res.description("Invalid query parameters"))
 }
 
 /// Path parameters for single job endpoint
@@ -336,12 +1493,53 @@ pub async fn get_job(
     path: JobPath,
 ) -> AppResult<Json<JobStatusResponse>> {
     let job_id = path.job_id;
+    let job = find_job(&service, &user, job_id).await?;
+
+    Ok(Json(
+        job_status_response(&service, job, &config.base_url).await?,
+    ))
+}
+
+/// Job of the user, if the user's scope allows accessing it
+async fn find_job(
+    service: &ProcessService,
+    user: &AuthenticatedUser,
+    job_id: Uuid,
+) -> AppResult<ProcessJob> {
     let job = service
         .get_job(&user.username, job_id)
         .await?
         .ok_or_else(|| AppError::NotFound(format!("Job not found: {}", job_id)))?;
+    user.check_job(&job)?;
+    Ok(job)
+}
 
-    let base_url = &config.base_url;
+/// Build the detailed status of a job, including workflow steps and errors
+async fn job_status_response(
+    service: &ProcessService,
+    job: ProcessJob,
+    base_url: &str,
+) -> AppResult<JobStatusResponse> {
+    let job_id = job.id;
+
+    let steps = if job.process_id == workflow::PROCESS_ID {
+        let steps = service.list_workflow_steps(job.id).await?;
+        Some(
+            steps
+                .into_iter()
+                .map(|step| WorkflowStepStatus {
+                    step: step.step_index.unwrap_or_default(),
+                    job_id: step.id.to_string(),
+                    process_id: step.process_id,
+                    status: step.status,
+                    message: step.message,
+                    progress: step.progress,
+                })
+                .collect(),
+        )
+    } else {
+        None
+    };
 
     let response = JobStatusResponse {
         job_id: job.id.to_string(),
@@ -354,6 +1552,14 @@ pub async fn get_job(
         started: job.started.map(|dt| dt.to_rfc3339()),
         finished: job.finished.map(|dt| dt.to_rfc3339()),
         updated: job.updated.map(|dt| dt.to_rfc3339()),
+        expires: job.expires.map(|dt| dt.to_rfc3339()),
+        attempts: Some(job.attempts),
+        errors: job
+            .errors
+            .as_array()
+            .is_some_and(|errors| !errors.is_empty())
+            .then_some(job.errors),
+        steps,
         links: vec![
             Link::new(format!("{}/jobs/{}", base_url, job_id), rel::SELF)
                 .with_type(media_type::JSON),
@@ -365,7 +1571,7 @@ pub async fn get_job(
         ],
     };
 
-    Ok(Json(response))
+    Ok(response)
 }
 
 fn get_job_docs(op: TransformOperation) -> TransformOperation {
@@ -391,10 +1597,7 @@ pub async fn get_job_results(
     path: JobResultsPath,
 ) -> AppResult<Json<serde_json::Value>> {
     let job_id = path.job_id;
-    let job = service
-        .get_job(&user.username, job_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Job not found: {}", job_id)))?;
+    let job = find_job(&service, &user, job_id).await?;
 
     // Check if job is complete
     if job.status != "successful" {
@@ -420,6 +1623,85 @@ fn get_job_results_docs(op: TransformOperation) -> TransformOperation {
         .response_with::<404, (), _>(|res| res.description("Job not found"))
 }
 
+/// Path parameters for a single job result
+#[aide::axum::typed_path]
+#[typed_path("/jobs/{job_id}/results/{output_id}")]
+pub struct JobResultPath {
+    /// The job UUID
+    pub job_id: Uuid,
+    /// The output identifier
+    pub output_id: String,
+}
+
+/// Download the file a job output refers to
+pub async fn get_job_result(
+    Extension(user): Extension<AuthenticatedUser>,
+    Extension(storage): Extension<Arc<S3Storage>>,
+    State(service): State<Arc<ProcessService>>,
+    path: JobResultPath,
+) -> AppResult<Response> {
+    let job_id = path.job_id;
+    let job = find_job(&service, &user, job_id).await?;
+
+    if job.status != "successful" {
+        return Err(AppError::BadRequest(format!(
+            "Job is not complete. Status: {}",
+            job.status
+        )));
+    }
+
+    let output = job
+        .outputs
+        .as_ref()
+        .and_then(|outputs| outputs.get(&path.output_id))
+        .ok_or_else(|| AppError::NotFound(format!("Output not found: {}", path.output_id)))?;
+
+    // Only files the job stored itself can be downloaded
+    let job_prefix = storage.s3_uri(&format!("{}/jobs/{}/", job.owner, job_id));
+    let backup_prefix = storage.s3_uri(&format!(
+        "{}{}/",
+        backup_collection::backup_prefix(&job.owner),
+        job_id
+    ));
+    let key = output
+        .get("href")
+        .and_then(|href| href.as_str())
+        .filter(|href| href.starts_with(&job_prefix) || href.starts_with(&backup_prefix))
+        .and_then(|href| href.strip_prefix(&storage.s3_uri("")))
+        .ok_or_else(|| AppError::BadRequest(format!("Output {} is not a file", path.output_id)))?;
+    let media_type = output
+        .get("type")
+        .and_then(|media_type| media_type.as_str())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    let file_name = key.rsplit('/').next().unwrap_or(key).to_string();
+
+    let content = storage.get(key).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, media_type),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", file_name),
+            ),
+        ],
+        content,
+    )
+        .into_response())
+}
+
+fn get_job_result_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Download job result")
+        .description("Downloads the file a job output refers to, e.g. the `export` output of an export-collection job")
+        .tag("Processes")
+        .response_with::<200, Vec<u8>, _>(|res| res.description("Output file"))
+        .response_with::<400, (), _>(|res| {
+            res.description("Job not yet complete, or the output is not a file")
+        })
+        .response_with::<404, (), _>(|res| res.description("Job or output not found"))
+}
+
 /// Dismiss (cancel) a job
 pub async fn dismiss_job(
     Extension(user): Extension<AuthenticatedUser>,
@@ -427,6 +1709,7 @@ pub async fn dismiss_job(
     path: JobPath,
 ) -> AppResult<StatusCode> {
     let job_id = path.job_id;
+    find_job(&service, &user, job_id).await?;
     service.dismiss_job(&user.username, job_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -439,12 +1722,53 @@ fn dismiss_job_docs(op: TransformOperation) -> TransformOperation {
         .response_with::<404, (), _>(|res| res.description("Job not found"))
 }
 
-pub fn routes(service: Arc<ProcessService>) -> ApiRouter {
-    ApiRouter::new()
-        .api_route("/processes", get_with(list_processes, list_processes_docs))
+/// Path parameters for job retry endpoint
+#[aide::axum::typed_path]
+#[typed_path("/jobs/{job_id}/retry")]
+pub struct JobRetryPath {
+    /// The job UUID
+    pub job_id: Uuid,
+}
+
+/// Requeue a failed job
+pub async fn retry_job(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<ProcessService>>,
+    path: JobRetryPath,
+) -> AppResult<(StatusCode, Json<JobStatusResponse>)> {
+    let job_id = path.job_id;
+    find_job(&service, &user, job_id).await?;
+    service.requeue_job(&user.username, job_id).await?;
+
+    let job = find_job(&service, &user, job_id).await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(job_status_response(&service, job, &config.base_url).await?),
+    ))
+}
+
+fn retry_job_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Retry job")
+        .description("Requeues a failed job with a fresh attempt budget. Errors of earlier attempts are kept. Workflows resume at the step that failed; steps that succeeded aren't run again.")
+        .tag("Processes")
+        .response_with::<202, Json<JobStatusResponse>, _>(|res| res.description("Job requeued"))
+        .response_with::<404, (), _>(|res| res.description("Job not found"))
+        .response_with::<409, (), _>(|res| res.description("Job has not failed"))
+}
+
+pub fn routes(
+    service: Arc<ProcessService>,
+    storage: Arc<S3Storage>,
+    uploads: Arc<UploadService>,
+) -> ApiRouter {
+    // Execute requests may carry large uploads; ExecuteBody enforces the
+    // configured limits instead of the default body limit
+    let execute_routes = ApiRouter::new()
         .api_route(
-            "/processes/{process_id}",
-            get_with(get_process, get_process_docs),
+            "/processes/{process_id}/execution",
+            post_with(execute_deployed_process, execute_deployed_process_docs),
         )
         .api_route(
             "/processes/import-raster/execution",
@@ -454,6 +1778,52 @@ pub fn routes(service: Arc<ProcessService>) -> ApiRouter {
             "/processes/import-pointcloud/execution",
             post_with(execute_import_pointcloud, execute_import_pointcloud_docs),
         )
+        .layer(DefaultBodyLimit::disable())
+        .layer(Extension(storage.clone()))
+        .layer(Extension(uploads));
+
+    // Routes reading job results and backups from object storage
+    let storage_routes = ApiRouter::new()
+        .api_route(
+            "/jobs/{job_id}/results/{output_id}",
+            get_with(get_job_result, get_job_result_docs),
+        )
+        .api_route(
+            "/processes/restore-collection/execution",
+            post_with(execute_restore_collection, execute_restore_collection_docs),
+        )
+        .layer(Extension(storage));
+
+    ApiRouter::new()
+        .merge(execute_routes)
+        .merge(storage_routes)
+        .api_route(
+            "/processes",
+            get_with(list_processes, list_processes_docs)
+                .post_with(deploy_process, deploy_process_docs),
+        )
+        .api_route(
+            "/processes/{process_id}",
+            get_with(get_process, get_process_docs)
+                .put_with(replace_process, replace_process_docs)
+                .delete_with(undeploy_process, undeploy_process_docs),
+        )
+        .api_route(
+            "/processes/export-collection/execution",
+            post_with(execute_export_collection, execute_export_collection_docs),
+        )
+        .api_route(
+            "/processes/temporal-composite/execution",
+            post_with(execute_temporal_composite, execute_temporal_composite_docs),
+        )
+        .api_route(
+            "/processes/backup-collection/execution",
+            post_with(execute_backup_collection, execute_backup_collection_docs),
+        )
+        .api_route(
+            "/workflows",
+            post_with(execute_workflow, execute_workflow_docs),
+        )
         .api_route("/jobs", get_with(list_jobs, list_jobs_docs))
         .api_route(
             "/jobs/{job_id}",
@@ -463,5 +1833,6 @@ pub fn routes(service: Arc<ProcessService>) -> ApiRouter {
             "/jobs/{job_id}/results",
             get_with(get_job_results, get_job_results_docs),
         )
+        .api_route("/jobs/{job_id}/retry", post_with(retry_job, retry_job_docs))
         .with_state(service)
 }
@@ -1,6 +1,8 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::api::collections::schemas::ProcessingDefaults;
+use crate::api::features::crs::parse_crs_param;
 use crate::error::{AppError, AppResult};
 
 // Re-export common types for convenience
@@ -34,13 +36,94 @@ pub struct ImportPointCloudInputs {
     /// Whether to skip conversion if already COPC
     #[serde(default = "default_skip_if_copc")]
     pub skip_if_copc: bool,
+
+    /// Whether to copy the data into managed storage. When false, the item
+    /// references the source href in place (reference inputs only)
+    #[serde(default = "default_copy")]
+    pub copy: bool,
+
+    /// Keep the original file as a `source` asset when it is converted
+    /// (default: the server's `processing.keep_source_files`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_source: Option<bool>,
+
+    /// Extra processing applied while converting to COPC
+    #[serde(default)]
+    pub pipeline: PipelineOptions,
+}
+
+/// Optional PDAL stages applied while converting to COPC
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineOptions {
+    /// Reproject the points to this CRS (OGC CRS URI)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_crs: Option<String>,
+
+    /// Keep only every n-th point
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decimation_step: Option<u32>,
+
+    /// Remove statistical outliers (noise)
+    #[serde(default)]
+    pub remove_outliers: bool,
+
+    /// Classify ground points (class 2) with SMRF
+    #[serde(default)]
+    pub classify_ground: bool,
+}
+
+impl PipelineOptions {
+    /// Whether no extra processing is requested
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// SRID of the reprojection target, if any
+    pub fn target_srid(&self) -> AppResult<Option<i32>> {
+        parse_crs_param(self.target_crs.as_deref())
+    }
+
+    /// Validate the options
+    pub fn validate(&self) -> AppResult<()> {
+        self.target_srid()?;
+        if self.decimation_step == Some(0) {
+            return Err(AppError::BadRequest(
+                "pipeline.decimationStep must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
 }
 
 fn default_skip_if_copc() -> bool {
     true
 }
 
+fn default_copy() -> bool {
+    true
+}
+
 impl ImportPointCloudInputs {
+    /// Fill in options the request left out from the collection's defaults
+    ///
+    /// Options that need a copy are only applied to copied imports.
+    pub fn apply_defaults(&mut self, defaults: &ProcessingDefaults) {
+        if self.copy {
+            self.keep_source = self.keep_source.or(defaults.keep_source);
+        }
+        if self.copy
+            && self.pipeline.is_empty()
+            && let Some(pipeline) = &defaults.pipeline
+        {
+            self.pipeline = pipeline.clone();
+        }
+        self.properties = defaults.merge_properties(self.properties.take());
+        if self.datetime.is_none() {
+            self.datetime = defaults.datetime_from(self.properties.as_ref());
+        }
+    }
+
     /// Validate the inputs
     pub fn validate(&self) -> AppResult<()> {
         // Validate collection name
@@ -51,6 +134,11 @@ impl ImportPointCloudInputs {
         // Validate data input
         match &self.data {
             InputValue::Inline(inline) => {
+                if !self.copy {
+                    return Err(AppError::BadRequest(
+                        "copy=false requires a reference (href) input".to_string(),
+                    ));
+                }
                 if inline.value.is_empty() {
                     return Err(AppError::BadRequest(
                         "data.value cannot be empty".to_string(),
@@ -83,6 +171,21 @@ impl ImportPointCloudInputs {
             }
         }
 
+        if !self.pipeline.is_empty() {
+            if !self.copy {
+                return Err(AppError::BadRequest(
+                    "pipeline options require copy=true".to_string(),
+                ));
+            }
+            self.pipeline.validate()?;
+        }
+
+        if self.keep_source == Some(true) && !self.copy {
+            return Err(AppError::BadRequest(
+                "keepSource requires copy=true".to_string(),
+            ));
+        }
+
         // Validate datetime if provided
         if let Some(ref dt) = self.datetime {
             if chrono::DateTime::parse_from_rfc3339(dt).is_err() {
@@ -106,11 +209,14 @@ pub struct ImportPointCloudOutputs {
     /// Collection the item was added to
     pub collection: String,
 
-    /// Asset href (S3 URI)
+    /// Asset href (managed S3 URI, or the original location when not copied)
     pub asset_href: String,
 
     /// Whether conversion was performed
     pub converted: bool,
+
+    /// Whether the data was copied into managed storage
+    pub copied: bool,
 }
 
 /// Process description for OpenAPI
@@ -118,7 +224,7 @@ pub fn process_description() -> serde_json::Value {
     serde_json::json!({
         "id": PROCESS_ID,
         "title": "Import Point Cloud",
-        "description": "Import a point cloud file into a collection. Accepts COPC (pass-through) or LAS/LAZ (converted to COPC via PDAL). Data can be provided inline (base64-encoded) or as a reference URL.",
+        "description": "Import a point cloud file into a collection. Accepts COPC (pass-through) or LAS/LAZ (converted to COPC via PDAL). Data can be provided inline (base64-encoded) or as a reference URL. A ZIP archive is imported as one item per .las/.laz file it contains; the outputs then list the result of each file.",
         "version": "1.0.0",
         "jobControlOptions": ["async-execute"],
         "outputTransmission": ["value"],
@@ -191,6 +297,48 @@ pub fn process_description() -> serde_json::Value {
                 "description": "Skip conversion if source is already a valid COPC",
                 "schema": { "type": "boolean", "default": true },
                 "minOccurs": 0
+            },
+            "copy": {
+                "title": "Copy to Storage",
+                "description": "Copy the data into managed storage. When false, the item references the source href in place and metadata is read with range requests; no conversion to COPC is performed. Requires a reference input; S3 URIs must be in the namespace of the user or the collection owner. The import fails if the bounds cannot be read from the LAS header or the CRS from its WKT or GeoTIFF VLR.",
+                "schema": { "type": "boolean", "default": true },
+                "minOccurs": 0
+            },
+            "pipeline": {
+                "title": "Pipeline Options",
+                "description": "Extra PDAL stages applied while converting to COPC. The conversion always runs when any option is set, and the job fails if it cannot.",
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "targetCrs": {
+                            "type": "string",
+                            "format": "uri",
+                            "description": "Reproject the points to this CRS, e.g. http://www.opengis.net/def/crs/EPSG/0/3006"
+                        },
+                        "decimationStep": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "description": "Keep only every n-th point"
+                        },
+                        "removeOutliers": {
+                            "type": "boolean",
+                            "default": false,
+                            "description": "Remove statistical outliers (noise)"
+                        },
+                        "classifyGround": {
+                            "type": "boolean",
+                            "default": false,
+                            "description": "Classify ground points (class 2) with SMRF"
+                        }
+                    }
+                },
+                "minOccurs": 0
+            },
+            "keepSource": {
+                "title": "Keep Source",
+                "description": "Keep the original file as a `source` asset of the item when it is converted to COPC. Defaults to the server configuration. Requires copy.",
+                "schema": { "type": "boolean" },
+                "minOccurs": 0
             }
         },
         "outputs": {
@@ -206,13 +354,38 @@ pub fn process_description() -> serde_json::Value {
             },
             "asset_href": {
                 "title": "Asset Href",
-                "description": "S3 URI of the stored asset",
+                "description": "URI of the asset (managed S3 URI, or the original href when not copied)",
                 "schema": { "type": "string", "format": "uri" }
             },
             "converted": {
                 "title": "Converted",
                 "description": "Whether the file was converted to COPC",
                 "schema": { "type": "boolean" }
+            },
+            "source_href": {
+                "title": "Source Href",
+                "description": "Managed S3 URI of the original file, when it was kept",
+                "schema": { "type": "string", "format": "uri" }
+            },
+            "copied": {
+                "title": "Copied",
+                "description": "Whether the data was copied into managed storage",
+                "schema": { "type": "boolean" }
+            },
+            "files": {
+                "title": "Files",
+                "description": "Archive imports only: per-file results with the file name and either the item_id, asset_href and converted outputs or an error",
+                "schema": { "type": "array", "items": { "type": "object" } }
+            },
+            "imported": {
+                "title": "Imported",
+                "description": "Archive imports only: number of files imported",
+                "schema": { "type": "integer" }
+            },
+            "failed": {
+                "title": "Failed",
+                "description": "Archive imports only: number of files that could not be imported",
+                "schema": { "type": "integer" }
             }
         }
     })
@@ -237,6 +410,9 @@ mod tests {
             datetime: None,
             properties: None,
             skip_if_copc: true,
+            copy: true,
+            keep_source: None,
+            pipeline: PipelineOptions::default(),
         };
 
         assert!(inputs.validate().is_ok());
@@ -254,8 +430,85 @@ mod tests {
             datetime: None,
             properties: None,
             skip_if_copc: true,
+            copy: true,
+            keep_source: None,
+            pipeline: PipelineOptions::default(),
         };
 
         assert!(inputs.validate().is_ok());
     }
+
+    #[test]
+    fn test_validate_pipeline_options() {
+        let mut inputs = ImportPointCloudInputs {
+            collection: "test:collection".to_string(),
+            data: InputValue::Reference(ReferenceValue {
+                href: "https://example.com/file.laz".to_string(),
+                media_type: None,
+            }),
+            title: None,
+            datetime: None,
+            properties: None,
+            skip_if_copc: true,
+            copy: true,
+            keep_source: None,
+            pipeline: PipelineOptions {
+                target_crs: Some("http://www.opengis.net/def/crs/EPSG/0/3006".to_string()),
+                decimation_step: Some(2),
+                ..Default::default()
+            },
+        };
+        assert!(inputs.validate().is_ok());
+        assert_eq!(inputs.pipeline.target_srid().unwrap(), Some(3006));
+
+        inputs.pipeline.target_crs = Some("EPSG:3006".to_string());
+        assert!(inputs.validate().is_err());
+
+        inputs.pipeline.target_crs = None;
+        inputs.pipeline.decimation_step = Some(0);
+        assert!(inputs.validate().is_err());
+
+        inputs.pipeline.decimation_step = None;
+        inputs.pipeline.remove_outliers = true;
+        inputs.copy = false;
+        assert!(inputs.validate().is_err());
+    }
+
+    #[test]
+    fn test_apply_defaults() {
+        let mut inputs = ImportPointCloudInputs {
+            collection: "test:collection".to_string(),
+            data: InputValue::Reference(ReferenceValue {
+                href: "https://example.com/file.laz".to_string(),
+                media_type: None,
+            }),
+            title: None,
+            datetime: None,
+            properties: Some(serde_json::json!({ "surveyed": "2024-05-01T00:00:00Z" })),
+            skip_if_copc: true,
+            copy: true,
+            keep_source: Some(false),
+            pipeline: PipelineOptions::default(),
+        };
+        let defaults = ProcessingDefaults {
+            keep_source: Some(true),
+            datetime_property: Some("surveyed".to_string()),
+            pipeline: Some(PipelineOptions {
+                target_crs: Some("http://www.opengis.net/def/crs/EPSG/0/3006".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        inputs.apply_defaults(&defaults);
+        assert_eq!(inputs.keep_source, Some(false));
+        assert_eq!(inputs.pipeline, defaults.pipeline.clone().unwrap());
+        assert_eq!(inputs.datetime.as_deref(), Some("2024-05-01T00:00:00Z"));
+
+        // Pipeline defaults need a copy
+        inputs.copy = false;
+        inputs.pipeline = PipelineOptions::default();
+        inputs.apply_defaults(&defaults);
+        assert!(inputs.pipeline.is_empty());
+    }
 }
@@ -1,6 +1,7 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::api::collections::schemas::ProcessingDefaults;
 use crate::error::{AppError, AppResult};
 
 // Re-export common types for convenience
@@ -34,13 +35,80 @@ pub struct ImportRasterInputs {
     /// Whether to skip conversion if already COG
     #[serde(default = "default_skip_if_cog")]
     pub skip_if_cog: bool,
+
+    /// Whether to copy the data into managed storage. When false, the item
+    /// references the source href in place (reference inputs only)
+    #[serde(default = "default_copy")]
+    pub copy: bool,
+
+    /// Keep the original file as a `source` asset when it is converted
+    /// (default: the server's `processing.keep_source_files`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_source: Option<bool>,
+
+    /// Further files of the item, such as masks or metadata sidecars, each
+    /// registered as a separate asset next to `data`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assets: Vec<AdditionalAsset>,
+}
+
+/// A further file imported as an asset of the same item
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct AdditionalAsset {
+    /// Asset key, unique within the item
+    pub key: String,
+
+    /// File data - either inline (base64) or reference (href)
+    pub data: InputValue,
+
+    /// Asset roles, e.g. `metadata` or `overview`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+
+    /// Media type of the asset (default: the media type of the data)
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+
+    /// Optional asset title
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl AdditionalAsset {
+    /// Media type of the asset, from its own or its data's type
+    pub fn media_type(&self) -> Option<&str> {
+        self.media_type.as_deref().or(match &self.data {
+            InputValue::Inline(inline) => inline.media_type.as_deref(),
+            InputValue::Reference(reference) => reference.media_type.as_deref(),
+        })
+    }
 }
 
+/// Asset keys the import itself registers
+const RESERVED_ASSET_KEYS: &[&str] = &["data", "source"];
+
 fn default_skip_if_cog() -> bool {
     true
 }
 
+fn default_copy() -> bool {
+    true
+}
+
 impl ImportRasterInputs {
+    /// Fill in options the request left out from the collection's defaults
+    ///
+    /// Options that need a copy are only applied to copied imports.
+    pub fn apply_defaults(&mut self, defaults: &ProcessingDefaults) {
+        if self.copy {
+            self.keep_source = self.keep_source.or(defaults.keep_source);
+        }
+        self.properties = defaults.merge_properties(self.properties.take());
+        if self.datetime.is_none() {
+            self.datetime = defaults.datetime_from(self.properties.as_ref());
+        }
+    }
+
     /// Validate the inputs
     pub fn validate(&self) -> AppResult<()> {
         // Validate collection name
@@ -48,39 +116,12 @@ impl ImportRasterInputs {
             return Err(AppError::BadRequest("collection is required".to_string()));
         }
 
-        // Validate data input
-        match &self.data {
-            InputValue::Inline(inline) => {
-                if inline.value.is_empty() {
-                    return Err(AppError::BadRequest(
-                        "data.value cannot be empty".to_string(),
-                    ));
-                }
-                // Validate base64
-                if base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &inline.value)
-                    .is_err()
-                {
-                    return Err(AppError::BadRequest(
-                        "data.value must be valid base64".to_string(),
-                    ));
-                }
-            }
-            InputValue::Reference(reference) => {
-                if reference.href.is_empty() {
-                    return Err(AppError::BadRequest(
-                        "data.href cannot be empty".to_string(),
-                    ));
-                }
-                // Validate URL scheme
-                if !reference.href.starts_with("s3://")
-                    && !reference.href.starts_with("http://")
-                    && !reference.href.starts_with("https://")
-                {
-                    return Err(AppError::BadRequest(
-                        "data.href must be an S3 URI or HTTP(S) URL".to_string(),
-                    ));
-                }
-            }
+        validate_input_value(&self.data, "data", self.copy)?;
+
+        if self.keep_source == Some(true) && !self.copy {
+            return Err(AppError::BadRequest(
+                "keepSource requires copy=true".to_string(),
+            ));
         }
 
         // Validate datetime if provided
@@ -92,10 +133,90 @@ impl ImportRasterInputs {
             }
         }
 
+        let mut keys = std::collections::HashSet::new();
+        for asset in &self.assets {
+            // Keys end up in storage keys, so keep them to safe characters
+            if asset.key.is_empty()
+                || !asset
+                    .key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+            {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid asset key '{}': use letters, digits, '_', '-' and '.'",
+                    asset.key
+                )));
+            }
+            if RESERVED_ASSET_KEYS.contains(&asset.key.as_str()) {
+                return Err(AppError::BadRequest(format!(
+                    "Asset key '{}' is reserved for the raster",
+                    asset.key
+                )));
+            }
+            if !keys.insert(asset.key.as_str()) {
+                return Err(AppError::BadRequest(format!(
+                    "Duplicate asset key '{}'",
+                    asset.key
+                )));
+            }
+            validate_input_value(
+                &asset.data,
+                &format!("assets.{}.data", asset.key),
+                self.copy,
+            )?;
+        }
+
         Ok(())
     }
 }
 
+/// Validate a file input; inputs that are not copied must be references
+fn validate_input_value(value: &InputValue, name: &str, copy: bool) -> AppResult<()> {
+    match value {
+        InputValue::Inline(inline) => {
+            if !copy {
+                return Err(AppError::BadRequest(
+                    "copy=false requires a reference (href) input".to_string(),
+                ));
+            }
+            if inline.value.is_empty() {
+                return Err(AppError::BadRequest(format!(
+                    "{}.value cannot be empty",
+                    name
+                )));
+            }
+            // Validate base64
+            if base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &inline.value)
+                .is_err()
+            {
+                return Err(AppError::BadRequest(format!(
+                    "{}.value must be valid base64",
+                    name
+                )));
+            }
+        }
+        InputValue::Reference(reference) => {
+            if reference.href.is_empty() {
+                return Err(AppError::BadRequest(format!(
+                    "{}.href cannot be empty",
+                    name
+                )));
+            }
+            // Validate URL scheme
+            if !reference.href.starts_with("s3://")
+                && !reference.href.starts_with("http://")
+                && !reference.href.starts_with("https://")
+            {
+                return Err(AppError::BadRequest(format!(
+                    "{}.href must be an S3 URI or HTTP(S) URL",
+                    name
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Output schema for raster import
 #[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -106,11 +227,14 @@ pub struct ImportRasterOutputs {
     /// Collection the item was added to
     pub collection: String,
 
-    /// Asset href (S3 URI)
+    /// Asset href (managed S3 URI, or the original location when not copied)
     pub asset_href: String,
 
     /// Whether conversion was performed
     pub converted: bool,
+
+    /// Whether the data was copied into managed storage
+    pub copied: bool,
 }
 
 /// Process description for OpenAPI
@@ -118,7 +242,7 @@ pub fn process_description() -> serde_json::Value {
     serde_json::json!({
         "id": PROCESS_ID,
         "title": "Import Raster",
-        "description": "Import a raster file into a collection. Accepts COG (pass-through) or other formats (converted to COG via GDAL). Data can be provided inline (base64-encoded) or as a reference URL.",
+        "description": "Import a raster file into a collection. Accepts COG (pass-through) or other formats (converted to COG via GDAL). Data can be provided inline (base64-encoded) or as a reference URL. A ZIP archive is imported as one item per .tif/.tiff file it contains; the outputs then list the result of each file. Further files of the item, such as masks or metadata sidecars, can be imported as additional assets with their own keys and roles.",
         "version": "1.0.0",
         "jobControlOptions": ["async-execute"],
         "outputTransmission": ["value"],
@@ -191,6 +315,44 @@ pub fn process_description() -> serde_json::Value {
                 "description": "Skip conversion if source is already a valid COG",
                 "schema": { "type": "boolean", "default": true },
                 "minOccurs": 0
+            },
+            "copy": {
+                "title": "Copy to Storage",
+                "description": "Copy the data into managed storage. When false, the item references the source href in place and metadata is read with range requests; no conversion to COG is performed. Requires a reference input; S3 URIs must be in the namespace of the user or the collection owner. The import fails if the metadata cannot be read, which needs the gdal-support feature.",
+                "schema": { "type": "boolean", "default": true },
+                "minOccurs": 0
+            },
+            "keepSource": {
+                "title": "Keep Source",
+                "description": "Keep the original file as a `source` asset of the item when it is converted to COG. Defaults to the server configuration. Requires copy.",
+                "schema": { "type": "boolean" },
+                "minOccurs": 0
+            },
+            "assets": {
+                "title": "Additional Assets",
+                "description": "Further files of the item, such as masks or metadata sidecars, each registered as a separate asset with its key and roles. Files are copied along with the raster, or referenced in place when copy is false. Not supported for ZIP archives.",
+                "schema": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["key", "data"],
+                        "properties": {
+                            "key": {
+                                "type": "string",
+                                "pattern": "^[A-Za-z0-9_.-]+$",
+                                "description": "Asset key, other than data and source"
+                            },
+                            "data": {
+                                "type": "object",
+                                "description": "Inline base64-encoded value or reference URL, as for the raster data"
+                            },
+                            "roles": { "type": "array", "items": { "type": "string" } },
+                            "type": { "type": "string", "description": "Media type of the asset" },
+                            "title": { "type": "string" }
+                        }
+                    }
+                },
+                "minOccurs": 0
             }
         },
         "outputs": {
@@ -206,13 +368,43 @@ pub fn process_description() -> serde_json::Value {
             },
             "asset_href": {
                 "title": "Asset Href",
-                "description": "S3 URI of the stored asset",
+                "description": "URI of the asset (managed S3 URI, or the original href when not copied)",
                 "schema": { "type": "string", "format": "uri" }
             },
             "converted": {
                 "title": "Converted",
                 "description": "Whether the file was converted to COG",
                 "schema": { "type": "boolean" }
+            },
+            "source_href": {
+                "title": "Source Href",
+                "description": "Managed S3 URI of the original file, when it was kept",
+                "schema": { "type": "string", "format": "uri" }
+            },
+            "copied": {
+                "title": "Copied",
+                "description": "Whether the data was copied into managed storage",
+                "schema": { "type": "boolean" }
+            },
+            "assets": {
+                "title": "Assets",
+                "description": "Hrefs of the additional assets by key",
+                "schema": { "type": "object", "additionalProperties": { "type": "string", "format": "uri" } }
+            },
+            "files": {
+                "title": "Files",
+                "description": "Archive imports only: per-file results with the file name and either the item_id, asset_href and converted outputs or an error",
+                "schema": { "type": "array", "items": { "type": "object" } }
+            },
+            "imported": {
+                "title": "Imported",
+                "description": "Archive imports only: number of files imported",
+                "schema": { "type": "integer" }
+            },
+            "failed": {
+                "title": "Failed",
+                "description": "Archive imports only: number of files that could not be imported",
+                "schema": { "type": "integer" }
             }
         }
     })
@@ -237,6 +429,9 @@ mod tests {
             datetime: None,
             properties: None,
             skip_if_cog: true,
+            copy: true,
+            keep_source: None,
+            assets: Vec::new(),
         };
 
         assert!(inputs.validate().is_ok());
@@ -254,6 +449,9 @@ mod tests {
             datetime: None,
             properties: None,
             skip_if_cog: true,
+            copy: true,
+            keep_source: None,
+            assets: Vec::new(),
         };
 
         assert!(inputs.validate().is_ok());
@@ -271,6 +469,9 @@ mod tests {
             datetime: None,
             properties: None,
             skip_if_cog: true,
+            copy: true,
+            keep_source: None,
+            assets: Vec::new(),
         };
 
         assert!(inputs.validate().is_err());
@@ -288,6 +489,9 @@ mod tests {
             datetime: None,
             properties: None,
             skip_if_cog: true,
+            copy: true,
+            keep_source: None,
+            assets: Vec::new(),
         };
 
         assert!(inputs.validate().is_err());
@@ -305,8 +509,88 @@ mod tests {
             datetime: Some("not-a-date".to_string()),
             properties: None,
             skip_if_cog: true,
+            copy: true,
+            keep_source: None,
+            assets: Vec::new(),
+        };
+
+        assert!(inputs.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_no_copy_requires_reference() {
+        let mut inputs = ImportRasterInputs {
+            collection: "test:collection".to_string(),
+            data: InputValue::Inline(InlineValue {
+                value: base64::Engine::encode(
+                    &base64::engine::general_purpose::STANDARD,
+                    b"test data",
+                ),
+                media_type: None,
+            }),
+            title: None,
+            datetime: None,
+            properties: None,
+            skip_if_cog: true,
+            copy: false,
+            keep_source: None,
+            assets: Vec::new(),
         };
 
         assert!(inputs.validate().is_err());
+
+        inputs.data = InputValue::Reference(ReferenceValue {
+            href: "https://example.com/archive/scene.tif".to_string(),
+            media_type: None,
+        });
+        assert!(inputs.validate().is_ok());
+
+        // The source is only kept for copied imports
+        inputs.keep_source = Some(true);
+        assert!(inputs.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_additional_assets() {
+        let asset = |key: &str, href: &str| AdditionalAsset {
+            key: key.to_string(),
+            data: InputValue::Reference(ReferenceValue {
+                href: href.to_string(),
+                media_type: None,
+            }),
+            roles: vec!["metadata".to_string()],
+            media_type: Some("application/xml".to_string()),
+            title: None,
+        };
+        let mut inputs = ImportRasterInputs {
+            collection: "test:collection".to_string(),
+            data: InputValue::Reference(ReferenceValue {
+                href: "s3://bucket/scene.tif".to_string(),
+                media_type: None,
+            }),
+            title: None,
+            datetime: None,
+            properties: None,
+            skip_if_cog: true,
+            copy: true,
+            keep_source: None,
+            assets: vec![
+                asset("mask", "s3://bucket/scene.msk.tif"),
+                asset("metadata", "s3://bucket/scene.xml"),
+            ],
+        };
+        assert!(inputs.validate().is_ok());
+        assert_eq!(inputs.assets[1].media_type(), Some("application/xml"));
+
+        // Keys are unique, not reserved and safe in storage keys
+        inputs.assets[1].key = "mask".to_string();
+        assert!(inputs.validate().is_err());
+        inputs.assets[1].key = "data".to_string();
+        assert!(inputs.validate().is_err());
+        inputs.assets[1].key = "../metadata".to_string();
+        assert!(inputs.validate().is_err());
+
+        inputs.assets[1] = asset("metadata", "ftp://bucket/scene.xml");
+        assert!(inputs.validate().is_err());
     }
 }
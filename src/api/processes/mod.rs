@@ -1,6 +1,13 @@
+pub mod backup_collection;
+pub mod deploy;
+pub mod export_collection;
 pub mod handlers;
 pub mod import_pointcloud;
 pub mod import_raster;
+pub mod restore_collection;
+pub mod temporal_composite;
+pub mod upload;
+pub mod workflow;
 
 pub use handlers::*;
 
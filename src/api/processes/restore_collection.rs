@@ -0,0 +1,131 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+/// Process definition for collection restore
+pub const PROCESS_ID: &str = "restore-collection";
+
+/// Input schema for collection restore
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct RestoreCollectionInputs {
+    /// S3 URI of a backup, as returned by `backup-collection`
+    pub backup: String,
+
+    /// Id of the collection to create; defaults to the id of the backed up
+    /// collection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+}
+
+impl RestoreCollectionInputs {
+    /// Validate the inputs
+    pub fn validate(&self) -> AppResult<()> {
+        if self.backup.is_empty() {
+            return Err(AppError::BadRequest("backup is required".to_string()));
+        }
+        if self.collection.as_deref() == Some("") {
+            return Err(AppError::BadRequest(
+                "collection must not be empty".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Canonical id of the collection to restore into, in the owner's schema
+pub fn target_collection(owner: &str, collection: &str) -> AppResult<String> {
+    let canonical_name = match collection.split_once(':') {
+        Some((schema, _)) if schema != owner => {
+            return Err(AppError::Forbidden(format!(
+                "Backups can only be restored into your own collections, not {}",
+                collection
+            )));
+        }
+        Some(_) => collection.to_string(),
+        None => format!("{}:{}", owner, collection),
+    };
+    Ok(canonical_name)
+}
+
+/// Output schema for collection restore
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreCollectionOutputs {
+    /// Collection that was created
+    pub collection: String,
+
+    /// Collection the backup was taken of
+    pub source_collection: String,
+
+    /// Number of features restored
+    pub number_of_features: usize,
+
+    /// Number of items restored
+    pub number_of_items: usize,
+}
+
+/// Process description for OpenAPI
+pub fn process_description() -> serde_json::Value {
+    serde_json::json!({
+        "id": PROCESS_ID,
+        "title": "Restore Collection",
+        "description": "Create a collection from a backup taken by `backup-collection`, under its original or a new id in your schema. The collection must not exist yet. Features keep their ids; items get new ids and reference the same asset files as before.",
+        "version": "1.0.0",
+        "jobControlOptions": ["async-execute"],
+        "outputTransmission": ["value"],
+        "inputs": {
+            "backup": {
+                "title": "Backup",
+                "description": "S3 URI of one of your backups, as returned in the `backup` output of `backup-collection`",
+                "schema": { "type": "string", "minLength": 1 }
+            },
+            "collection": {
+                "title": "Collection ID",
+                "description": "Id of the collection to create. Defaults to the id of the backed up collection.",
+                "schema": { "type": "string", "minLength": 1 },
+                "minOccurs": 0
+            }
+        },
+        "outputs": {
+            "collection": {
+                "title": "Collection",
+                "description": "Collection that was created",
+                "schema": { "type": "string" }
+            },
+            "sourceCollection": {
+                "title": "Source Collection",
+                "description": "Collection the backup was taken of",
+                "schema": { "type": "string" }
+            },
+            "numberOfFeatures": {
+                "title": "Number of Features",
+                "description": "Number of features restored",
+                "schema": { "type": "integer" }
+            },
+            "numberOfItems": {
+                "title": "Number of Items",
+                "description": "Number of items restored",
+                "schema": { "type": "integer" }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_collection() {
+        assert_eq!(target_collection("alice", "roads").unwrap(), "alice:roads");
+        assert_eq!(
+            target_collection("alice", "alice:roads:2024").unwrap(),
+            "alice:roads:2024"
+        );
+        assert!(matches!(
+            target_collection("alice", "bob:roads"),
+            Err(AppError::Forbidden(_))
+        ));
+    }
+}
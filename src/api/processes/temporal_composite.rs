@@ -0,0 +1,260 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::processing::composite::CompositeMethod;
+
+/// Process definition for temporal composites
+pub const PROCESS_ID: &str = "temporal-composite";
+
+/// Most items combined into one composite
+pub const MAX_COMPOSITE_ITEMS: i64 = 100;
+
+/// Input schema for temporal composites
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TemporalCompositeInputs {
+    /// Raster collection to composite
+    pub collection: String,
+
+    /// Closed interval of the items to combine, `start/end` in RFC 3339
+    pub datetime: String,
+
+    /// Area of interest as `[minx, miny, maxx, maxy]` in CRS84 (default: all
+    /// items in the interval)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<[f64; 4]>,
+
+    /// Statistic of each pixel: `median` (default), `mean`, `min` or `max`
+    #[serde(default = "default_method")]
+    pub method: String,
+
+    /// Bands `[a, b]` (1-based) to composite `(a - b) / (a + b)` of instead
+    /// of all bands, e.g. `[8, 4]` for the NDVI of Sentinel-2
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalized_difference: Option<[usize; 2]>,
+
+    /// Raster collection the composite is added to as a new item (created if
+    /// it doesn't exist)
+    pub target: String,
+
+    /// Optional item title
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+fn default_method() -> String {
+    "median".to_string()
+}
+
+impl TemporalCompositeInputs {
+    /// Validate the inputs
+    pub fn validate(&self) -> AppResult<()> {
+        if self.collection.is_empty() {
+            return Err(AppError::BadRequest("collection is required".to_string()));
+        }
+        if self.target.is_empty() {
+            return Err(AppError::BadRequest("target is required".to_string()));
+        }
+        self.interval()?;
+        self.composite_method()?;
+
+        if let Some([minx, miny, maxx, maxy]) = self.bbox
+            && !(minx < maxx
+                && miny < maxy
+                && (-180.0..=180.0).contains(&minx)
+                && (-180.0..=180.0).contains(&maxx)
+                && (-90.0..=90.0).contains(&miny)
+                && (-90.0..=90.0).contains(&maxy))
+        {
+            return Err(AppError::BadRequest(
+                "bbox must be [minx, miny, maxx, maxy] in CRS84".to_string(),
+            ));
+        }
+        if let Some([a, b]) = self.normalized_difference
+            && (a == 0 || b == 0 || a == b)
+        {
+            return Err(AppError::BadRequest(
+                "normalizedDifference must be two different 1-based band numbers".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Start and end of the interval
+    pub fn interval(&self) -> AppResult<(DateTime<Utc>, DateTime<Utc>)> {
+        let invalid = || {
+            AppError::BadRequest(
+                "datetime must be a closed interval start/end of RFC 3339 timestamps".to_string(),
+            )
+        };
+        let (start, end) = self.datetime.split_once('/').ok_or_else(invalid)?;
+        let parse = |instant: &str| {
+            DateTime::parse_from_rfc3339(instant)
+                .map(|dt| dt.with_timezone(&Utc))
+                .map_err(|_| invalid())
+        };
+        let (start, end) = (parse(start)?, parse(end)?);
+        if start > end {
+            return Err(AppError::BadRequest(
+                "datetime must start before it ends".to_string(),
+            ));
+        }
+        Ok((start, end))
+    }
+
+    pub fn composite_method(&self) -> AppResult<CompositeMethod> {
+        CompositeMethod::from_param(&self.method)
+    }
+}
+
+/// Output schema for temporal composites
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TemporalCompositeOutputs {
+    /// Created item ID
+    pub item_id: String,
+
+    /// Collection the item was added to
+    pub collection: String,
+
+    /// Managed S3 URI of the composite
+    pub asset_href: String,
+
+    /// Number of items combined
+    pub number_of_items: usize,
+}
+
+/// Process description for OpenAPI
+pub fn process_description() -> serde_json::Value {
+    serde_json::json!({
+        "id": PROCESS_ID,
+        "title": "Temporal Composite",
+        "description": format!("Combine the items of a raster collection within a time interval and area of interest into a single Cloud Optimized GeoTIFF, taking the median, mean, minimum or maximum of the valid values of each pixel and band. With normalizedDifference the composite is of the single band (a - b) / (a + b) of each item instead, such as NDVI. The items must share CRS and bands; the composite has the resolution of the newest item. It is added as a new item to the target collection, with start_datetime and end_datetime properties of the interval. At most {} items are combined.", MAX_COMPOSITE_ITEMS),
+        "version": "1.0.0",
+        "jobControlOptions": ["async-execute"],
+        "outputTransmission": ["value"],
+        "inputs": {
+            "collection": {
+                "title": "Collection ID",
+                "description": "Raster collection to composite",
+                "schema": { "type": "string", "minLength": 1 }
+            },
+            "datetime": {
+                "title": "Interval",
+                "description": "Closed interval of the items to combine, start/end in RFC 3339",
+                "schema": { "type": "string" }
+            },
+            "bbox": {
+                "title": "Area of Interest",
+                "description": "[minx, miny, maxx, maxy] in CRS84; the composite covers all items in the interval when omitted",
+                "schema": { "type": "array", "items": { "type": "number" }, "minItems": 4, "maxItems": 4 },
+                "minOccurs": 0
+            },
+            "method": {
+                "title": "Method",
+                "description": "Statistic of each pixel",
+                "schema": { "type": "string", "enum": ["median", "mean", "min", "max"], "default": "median" },
+                "minOccurs": 0
+            },
+            "normalizedDifference": {
+                "title": "Normalized Difference",
+                "description": "Bands [a, b] (1-based) to composite (a - b) / (a + b) of instead of all bands, e.g. [8, 4] for the NDVI of Sentinel-2",
+                "schema": { "type": "array", "items": { "type": "integer", "minimum": 1 }, "minItems": 2, "maxItems": 2 },
+                "minOccurs": 0
+            },
+            "target": {
+                "title": "Target Collection",
+                "description": "Raster collection the composite is added to. Collection will be created if it doesn't exist.",
+                "schema": { "type": "string", "minLength": 1 }
+            },
+            "title": {
+                "title": "Item Title",
+                "description": "Optional title for the composite",
+                "schema": { "type": "string" },
+                "minOccurs": 0
+            }
+        },
+        "outputs": {
+            "itemId": {
+                "title": "Item ID",
+                "description": "ID of the created item",
+                "schema": { "type": "string", "format": "uuid" }
+            },
+            "collection": {
+                "title": "Collection",
+                "description": "Collection the item was added to",
+                "schema": { "type": "string" }
+            },
+            "assetHref": {
+                "title": "Asset Href",
+                "description": "Managed S3 URI of the composite",
+                "schema": { "type": "string", "format": "uri" }
+            },
+            "numberOfItems": {
+                "title": "Number of Items",
+                "description": "Number of items combined",
+                "schema": { "type": "integer" }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(datetime: &str, bbox: Option<[f64; 4]>, method: &str) -> TemporalCompositeInputs {
+        TemporalCompositeInputs {
+            collection: "alice:sentinel2-ndvi".to_string(),
+            datetime: datetime.to_string(),
+            bbox,
+            method: method.to_string(),
+            normalized_difference: None,
+            target: "ndvi-composites".to_string(),
+            title: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_composite_inputs() {
+        let interval = "2024-06-01T00:00:00Z/2024-08-31T23:59:59Z";
+        assert!(inputs(interval, None, "median").validate().is_ok());
+        assert!(
+            inputs(interval, Some([11.0, 57.0, 12.5, 58.0]), "max")
+                .validate()
+                .is_ok()
+        );
+
+        assert!(inputs(interval, None, "mode").validate().is_err());
+        assert!(
+            inputs(interval, Some([12.5, 57.0, 11.0, 58.0]), "mean")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            inputs("2024-06-01T00:00:00Z", None, "median")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            inputs("2024-06-01T00:00:00Z/..", None, "median")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            inputs("2024-08-31T00:00:00Z/2024-06-01T00:00:00Z", None, "median")
+                .validate()
+                .is_err()
+        );
+
+        let mut ndvi = inputs(interval, None, "median");
+        ndvi.normalized_difference = Some([8, 4]);
+        assert!(ndvi.validate().is_ok());
+        ndvi.normalized_difference = Some([0, 4]);
+        assert!(ndvi.validate().is_err());
+        ndvi.normalized_difference = Some([4, 4]);
+        assert!(ndvi.validate().is_err());
+    }
+}
@@ -0,0 +1,214 @@
+use aide::OperationInput;
+use aide::openapi::{MediaType, Operation, ReferenceOr, SchemaObject};
+use axum::{
+    Json,
+    extract::{FromRequest, Multipart, Request},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+use crate::config::{Config, ProcessingConfig};
+use crate::error::{AppError, AppResult};
+use crate::processing::worker::{safe_extension, sanitize_file_name};
+use crate::storage::S3Storage;
+
+/// Body of an execute request: JSON or `multipart/form-data`
+///
+/// A multipart request carries the remaining inputs as JSON in a part named
+/// `inputs` (and optionally an `expires` part). Every file part becomes a
+/// reference input named after the part, and its content is streamed
+/// straight to object storage instead of being base64-encoded in the JSON.
+pub enum ExecuteBody<T> {
+    Json(T),
+    Multipart(Multipart),
+}
+
+impl<T, S> FromRequest<S> for ExecuteBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_multipart = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("multipart/form-data"));
+
+        if is_multipart {
+            return Multipart::from_request(req, state)
+                .await
+                .map(ExecuteBody::Multipart)
+                .map_err(IntoResponse::into_response);
+        }
+
+        // Execute routes disable the default body limit, so bound JSON here
+        let limit = req
+            .extensions()
+            .get::<Arc<Config>>()
+            .map(|config| config.processing.max_execute_json_bytes)
+            .unwrap_or_else(|| ProcessingConfig::default().max_execute_json_bytes);
+        let (parts, body) = req.into_parts();
+        let bytes = axum::body::to_bytes(body, limit).await.map_err(|_| {
+            AppError::BadRequest(format!(
+                "Execute request exceeds the maximum size of {} bytes; use multipart/form-data for large files",
+                limit
+            ))
+            .into_response()
+        })?;
+
+        Json::<T>::from_request(Request::from_parts(parts, bytes.into()), state)
+            .await
+            .map(|Json(request)| ExecuteBody::Json(request))
+            .map_err(IntoResponse::into_response)
+    }
+}
+
+impl<T: schemars::JsonSchema> OperationInput for ExecuteBody<T> {
+    fn operation_input(ctx: &mut aide::generate::GenContext, operation: &mut Operation) {
+        Json::<T>::operation_input(ctx, operation);
+
+        if let Some(ReferenceOr::Item(body)) = &mut operation.request_body {
+            body.content.insert(
+                "multipart/form-data".to_string(),
+                MediaType {
+                    schema: Some(SchemaObject {
+                        json_schema: schemars::json_schema!({
+                            "type": "object",
+                            "properties": {
+                                "inputs": {
+                                    "type": "string",
+                                    "description": "JSON object with the non-file inputs"
+                                },
+                                "expires": { "type": "string", "format": "date-time" }
+                            },
+                            "additionalProperties": { "type": "string", "format": "binary" }
+                        }),
+                        example: None,
+                        external_docs: None,
+                    }),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+}
+
+impl<T: DeserializeOwned> ExecuteBody<T> {
+    /// Resolve the body into an execute request
+    ///
+    /// Files are uploaded below `upload_prefix`. Returns the request together
+    /// with the names of the inputs that were uploaded.
+    pub async fn into_request(
+        self,
+        storage: &S3Storage,
+        upload_prefix: &str,
+        processing: &ProcessingConfig,
+    ) -> AppResult<(T, Vec<String>)> {
+        let multipart = match self {
+            ExecuteBody::Json(request) => return Ok((request, Vec::new())),
+            ExecuteBody::Multipart(multipart) => multipart,
+        };
+
+        let result = read_multipart(multipart, storage, upload_prefix, processing).await;
+        if result.is_err() {
+            discard_uploads(storage, upload_prefix).await;
+        }
+        result
+    }
+}
+
+/// Remove files uploaded for a request that was rejected
+pub async fn discard_uploads(storage: &S3Storage, upload_prefix: &str) {
+    if let Err(e) = storage.delete_prefix(upload_prefix).await {
+        tracing::warn!("Failed to remove uploads under {}: {}", upload_prefix, e);
+    }
+}
+
+async fn read_multipart<T: DeserializeOwned>(
+    mut multipart: Multipart,
+    storage: &S3Storage,
+    upload_prefix: &str,
+    processing: &ProcessingConfig,
+) -> AppResult<(T, Vec<String>)> {
+    let mut inputs = serde_json::Map::new();
+    let mut expires = None;
+    let mut uploaded = Vec::new();
+    let mut remaining = processing.max_upload_bytes;
+
+    while let Some(mut field) = multipart.next_field().await.map_err(invalid_multipart)? {
+        let name = field
+            .name()
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| AppError::BadRequest("Multipart part without a name".to_string()))?
+            .to_string();
+
+        if let Some(file_name) = field.file_name() {
+            let key = format!(
+                "{}/{}.{}",
+                upload_prefix,
+                sanitize_file_name(&name),
+                safe_extension(file_name)
+            );
+            let media_type = field.content_type().map(str::to_string);
+
+            let size = storage.put_stream(&key, &mut field, remaining).await?;
+            remaining -= size;
+
+            inputs.insert(
+                name.clone(),
+                serde_json::json!({ "href": storage.s3_uri(&key), "type": media_type }),
+            );
+            uploaded.push(name);
+            continue;
+        }
+
+        let mut text = Vec::new();
+        while let Some(chunk) = field.chunk().await.map_err(invalid_multipart)? {
+            text.extend_from_slice(&chunk);
+            if text.len() > processing.max_execute_json_bytes {
+                return Err(AppError::BadRequest(format!(
+                    "Multipart part '{}' is too large",
+                    name
+                )));
+            }
+        }
+
+        match name.as_str() {
+            "inputs" => {
+                let values: serde_json::Map<String, serde_json::Value> =
+                    serde_json::from_slice(&text)
+                        .map_err(|e| AppError::BadRequest(format!("Invalid inputs part: {}", e)))?;
+                for (key, value) in values {
+                    inputs.entry(key).or_insert(value);
+                }
+            }
+            "expires" => {
+                expires = Some(String::from_utf8_lossy(&text).into_owned());
+            }
+            other => {
+                return Err(AppError::BadRequest(format!(
+                    "Unexpected multipart part '{}'; non-file inputs go in the 'inputs' part",
+                    other
+                )));
+            }
+        }
+    }
+
+    let mut request = serde_json::json!({ "inputs": inputs });
+    if let Some(expires) = expires {
+        request["expires"] = serde_json::Value::String(expires);
+    }
+
+    let request = serde_json::from_value(request)
+        .map_err(|e| AppError::BadRequest(format!("Invalid execute request: {}", e)))?;
+    Ok((request, uploaded))
+}
+
+fn invalid_multipart(error: axum::extract::multipart::MultipartError) -> AppError {
+    AppError::BadRequest(format!("Invalid multipart request: {}", error.body_text()))
+}
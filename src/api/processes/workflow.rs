@@ -0,0 +1,210 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{deploy, export_collection, import_pointcloud, import_raster};
+use crate::error::{AppError, AppResult};
+
+/// Process id recorded on the parent job of a workflow
+pub const PROCESS_ID: &str = "workflow";
+
+/// Maximum number of steps in a single workflow
+pub const MAX_STEPS: usize = 20;
+
+/// Workflow execute request: a chain of processes run in order
+///
+/// A step input of the form `{"$output": "<name>"}` is replaced by the
+/// output `<name>` of the previous step when the step starts.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct WorkflowRequest {
+    pub steps: Vec<WorkflowStep>,
+
+    /// Optional expiry overriding the job retention policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<String>")]
+    pub expires: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A single workflow step
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct WorkflowStep {
+    /// Process to execute (built-in or deployed)
+    pub process: String,
+
+    /// Inputs for the process, possibly referencing previous outputs
+    #[serde(default)]
+    pub inputs: serde_json::Map<String, serde_json::Value>,
+}
+
+impl WorkflowRequest {
+    /// Validate the workflow structure
+    ///
+    /// Built-in steps whose inputs are fully known up front are validated
+    /// like a direct execute request; steps that reference previous outputs
+    /// are validated by the worker once the references are resolved.
+    pub fn validate(&self) -> AppResult<()> {
+        if self.steps.is_empty() {
+            return Err(AppError::BadRequest(
+                "A workflow needs at least one step".to_string(),
+            ));
+        }
+        if self.steps.len() > MAX_STEPS {
+            return Err(AppError::BadRequest(format!(
+                "A workflow can have at most {} steps",
+                MAX_STEPS
+            )));
+        }
+
+        for (index, step) in self.steps.iter().enumerate() {
+            if step.process == PROCESS_ID {
+                return Err(AppError::BadRequest(
+                    "Workflows cannot be nested".to_string(),
+                ));
+            }
+
+            let inputs = serde_json::Value::Object(step.inputs.clone());
+            if has_output_reference(&inputs) {
+                if index == 0 {
+                    return Err(AppError::BadRequest(
+                        "The first workflow step cannot reference previous outputs".to_string(),
+                    ));
+                }
+                continue;
+            }
+
+            match step.process.as_str() {
+                import_raster::PROCESS_ID => {
+                    serde_json::from_value::<import_raster::ImportRasterInputs>(inputs)
+                        .map_err(|e| invalid_step(index, e))?
+                        .validate()?;
+                }
+                import_pointcloud::PROCESS_ID => {
+                    serde_json::from_value::<import_pointcloud::ImportPointCloudInputs>(inputs)
+                        .map_err(|e| invalid_step(index, e))?
+                        .validate()?;
+                }
+                export_collection::PROCESS_ID => {
+                    serde_json::from_value::<export_collection::ExportCollectionInputs>(inputs)
+                        .map_err(|e| invalid_step(index, e))?
+                        .validate()?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Process ids of steps that are not built in (must be deployed)
+    pub fn deployed_processes(&self) -> impl Iterator<Item = &str> {
+        self.steps
+            .iter()
+            .map(|step| step.process.as_str())
+            .filter(|process| !deploy::is_builtin_process(process))
+    }
+}
+
+fn invalid_step(index: usize, error: serde_json::Error) -> AppError {
+    AppError::BadRequest(format!("Invalid inputs for step {}: {}", index + 1, error))
+}
+
+/// Whether a value contains an `{"$output": ...}` reference
+fn has_output_reference(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.contains_key("$output") || map.values().any(has_output_reference)
+        }
+        serde_json::Value::Array(values) => values.iter().any(has_output_reference),
+        _ => false,
+    }
+}
+
+/// Replace `{"$output": "<name>"}` references with the previous step's outputs
+pub fn resolve_step_inputs(
+    inputs: &serde_json::Value,
+    previous_outputs: &serde_json::Value,
+) -> AppResult<serde_json::Value> {
+    match inputs {
+        serde_json::Value::Object(map) => {
+            if let Some(name) = map.get("$output") {
+                let name = name.as_str().ok_or_else(|| {
+                    AppError::BadRequest("$output must be an output name".to_string())
+                })?;
+                return previous_outputs.get(name).cloned().ok_or_else(|| {
+                    AppError::Processing(format!("Previous step has no output '{}'", name))
+                });
+            }
+
+            let resolved = map
+                .iter()
+                .map(|(key, value)| {
+                    Ok((key.clone(), resolve_step_inputs(value, previous_outputs)?))
+                })
+                .collect::<AppResult<serde_json::Map<_, _>>>()?;
+            Ok(serde_json::Value::Object(resolved))
+        }
+        serde_json::Value::Array(values) => values
+            .iter()
+            .map(|value| resolve_step_inputs(value, previous_outputs))
+            .collect::<AppResult<Vec<_>>>()
+            .map(serde_json::Value::Array),
+        other => Ok(other.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_step_inputs() {
+        let inputs = serde_json::json!({
+            "collection": { "$output": "collection" },
+            "items": [{ "$output": "item_id" }],
+            "scale": 2
+        });
+        let previous = serde_json::json!({ "collection": "jan:dem", "item_id": "abc" });
+
+        let resolved = resolve_step_inputs(&inputs, &previous).unwrap();
+        assert_eq!(
+            resolved,
+            serde_json::json!({ "collection": "jan:dem", "items": ["abc"], "scale": 2 })
+        );
+
+        let missing = serde_json::json!({ "x": { "$output": "nope" } });
+        assert!(resolve_step_inputs(&missing, &previous).is_err());
+    }
+
+    #[test]
+    fn test_validate_workflow() {
+        let workflow: WorkflowRequest = serde_json::from_value(serde_json::json!({
+            "steps": [
+                {
+                    "process": "import-raster",
+                    "inputs": { "collection": "dem", "data": { "href": "s3://b/dem.tif" } }
+                },
+                {
+                    "process": "generate-overview",
+                    "inputs": { "collection": { "$output": "collection" } }
+                }
+            ]
+        }))
+        .unwrap();
+        assert!(workflow.validate().is_ok());
+        assert_eq!(
+            workflow.deployed_processes().collect::<Vec<_>>(),
+            vec!["generate-overview"]
+        );
+
+        let first_references: WorkflowRequest = serde_json::from_value(serde_json::json!({
+            "steps": [{ "process": "x", "inputs": { "a": { "$output": "b" } } }]
+        }))
+        .unwrap();
+        assert!(first_references.validate().is_err());
+
+        let empty = WorkflowRequest {
+            steps: vec![],
+            expires: None,
+        };
+        assert!(empty.validate().is_err());
+    }
+}
@@ -0,0 +1,114 @@
+//! Storage quotas
+//!
+//! Writes adding features or assets to a collection are rejected with 507
+//! while the namespace owning the collection is at or over its quota, see
+//! [`crate::config::QuotaConfig`]. Deletes keep working, so owners can make
+//! room. Imports and collection restores check the quota of their target
+//! when the job is created.
+//!
+//! The middleware runs after authentication, so only authenticated requests
+//! compute usage.
+
+use axum::{
+    extract::{FromRequestParts, MatchedPath, Request, State},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::api::collections::ResolvedCollection;
+use crate::config::Config;
+use crate::services::QuotaService;
+
+/// Routes whose writes add features, assets or snapshots to the collection
+const QUOTA_ROUTES: &[&str] = &[
+    "/collections/{collection_id}/items",
+    "/collections/{collection_id}/items/{feature_id}",
+    "/collections/{collection_id}/items/{feature_id}/assets:batch",
+    "/collections/{collection_id}/assets/{asset_key}",
+    "/collections/{collection_id}/snapshots",
+    "/collections/{collection_id}/snapshots/{name}/restore",
+];
+
+/// Whether a request may add to a collection's storage
+fn adds_data(method: &Method, route: Option<&str>) -> bool {
+    matches!(*method, Method::POST | Method::PUT | Method::PATCH)
+        && route.is_some_and(|route| QUOTA_ROUTES.contains(&route))
+}
+
+/// Reject writes to collections whose namespace is over its quota
+pub async fn middleware(
+    State(quotas): State<Arc<QuotaService>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !quotas.is_enabled() {
+        return next.run(request).await;
+    }
+
+    // Matched routes include the path prefix the API is mounted under
+    let path_prefix = request
+        .extensions()
+        .get::<Arc<Config>>()
+        .map(|config| config.path_prefix.clone())
+        .unwrap_or_default();
+    let route = request.extensions().get::<MatchedPath>().map(|route| {
+        route
+            .as_str()
+            .strip_prefix(path_prefix.as_str())
+            .unwrap_or(route.as_str())
+    });
+    if !adds_data(request.method(), route) {
+        return next.run(request).await;
+    }
+
+    // The resolved collection stays in the extensions for the handler
+    let (mut parts, body) = request.into_parts();
+    let collection = match ResolvedCollection::from_request_parts(&mut parts, &()).await {
+        Ok(ResolvedCollection(collection)) => collection,
+        Err(response) => return response,
+    };
+    if let Err(e) = quotas.check(&collection.owner).await {
+        return e.into_response();
+    }
+    next.run(Request::from_parts(parts, body)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adds_data() {
+        assert!(adds_data(
+            &Method::POST,
+            Some("/collections/{collection_id}/items")
+        ));
+        assert!(adds_data(
+            &Method::PUT,
+            Some("/collections/{collection_id}/items/{feature_id}")
+        ));
+        assert!(adds_data(
+            &Method::PUT,
+            Some("/collections/{collection_id}/assets/{asset_key}")
+        ));
+        assert!(!adds_data(
+            &Method::DELETE,
+            Some("/collections/{collection_id}/items/{feature_id}")
+        ));
+        assert!(!adds_data(
+            &Method::GET,
+            Some("/collections/{collection_id}/items")
+        ));
+        assert!(!adds_data(
+            &Method::POST,
+            Some("/collections/{collection_id}/items/{feature_id}/lock")
+        ));
+        assert!(adds_data(
+            &Method::POST,
+            Some("/collections/{collection_id}/snapshots/{name}/restore")
+        ));
+        assert!(!adds_data(&Method::POST, None));
+    }
+}
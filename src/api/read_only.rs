@@ -0,0 +1,148 @@
+//! Read-only mode
+//!
+//! Replicas fed by replication are read-only through `read_only` in the
+//! configuration, and operators switch an instance to read-only for a
+//! maintenance window with `spatialvault admin read-only on`. Writes are
+//! then rejected, 403 on read-only instances and 503 during maintenance,
+//! while reads keep working. Background writers (the job worker,
+//! replication, webhooks, link checks and notifications) pause during
+//! maintenance too, and pick up where they left off once it ends.
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+
+/// Routes taking a POST that only read, e.g. a search with its parameters
+/// in the body
+const READ_ROUTES: &[&str] = &[
+    "/stac/search",
+    "/collections/{collection_id}/tiles/sign",
+    "/tokens",
+];
+
+/// Whether the instance is read-only, by configuration or for maintenance
+pub struct ReadOnly {
+    db: Arc<Database>,
+    configured: bool,
+    path_prefix: String,
+}
+
+impl ReadOnly {
+    pub fn new(db: Arc<Database>, config: &Config) -> Self {
+        Self {
+            db,
+            configured: config.read_only,
+            path_prefix: config.path_prefix.clone(),
+        }
+    }
+
+    /// The reason of the maintenance window, when one is ongoing
+    ///
+    /// Read for each write, so switching applies to all servers at once.
+    pub async fn maintenance(&self) -> AppResult<Option<String>> {
+        maintenance(&self.db).await
+    }
+
+    /// The error for a write, when writes are rejected
+    async fn rejection(&self) -> AppResult<Option<AppError>> {
+        if self.configured {
+            return Ok(Some(AppError::Forbidden(
+                "This instance is read-only".to_string(),
+            )));
+        }
+        Ok(self.maintenance().await?.map(|reason| {
+            AppError::Unavailable(if reason.is_empty() {
+                "The service is read-only for maintenance".to_string()
+            } else {
+                format!("The service is read-only for maintenance: {}", reason)
+            })
+        }))
+    }
+}
+
+/// The reason of the maintenance window, when one is ongoing
+pub async fn maintenance(db: &Database) -> AppResult<Option<String>> {
+    let state: Option<(bool, Option<String>)> =
+        sqlx::query_as("SELECT read_only, reason FROM spatialvault.maintenance")
+            .fetch_optional(db.pool())
+            .await?;
+    Ok(match state {
+        Some((true, reason)) => Some(reason.unwrap_or_default()),
+        _ => None,
+    })
+}
+
+/// Whether a background writer should skip its round for maintenance
+///
+/// When the state can't be read the round goes ahead, and fails on its own
+/// if the database is down.
+pub async fn paused(db: &Database) -> bool {
+    match maintenance(db).await {
+        Ok(reason) => reason.is_some(),
+        Err(e) => {
+            tracing::warn!("Failed to read the maintenance state: {}", e);
+            false
+        }
+    }
+}
+
+/// Whether a request changes data
+fn is_write(method: &Method, route: Option<&str>) -> bool {
+    match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS => false,
+        Method::POST => !route.is_some_and(|route| READ_ROUTES.contains(&route)),
+        _ => true,
+    }
+}
+
+/// Reject writes while the instance is read-only
+pub async fn middleware(
+    State(read_only): State<Arc<ReadOnly>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Matched routes include the path prefix the API is mounted under
+    let route = request.extensions().get::<MatchedPath>().map(|route| {
+        route
+            .as_str()
+            .strip_prefix(read_only.path_prefix.as_str())
+            .unwrap_or(route.as_str())
+    });
+    if !is_write(request.method(), route) {
+        return next.run(request).await;
+    }
+    match read_only.rejection().await {
+        Ok(None) => next.run(request).await,
+        Ok(Some(e)) | Err(e) => e.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_write() {
+        assert!(!is_write(&Method::GET, Some("/collections")));
+        assert!(!is_write(&Method::HEAD, None));
+        assert!(!is_write(&Method::POST, Some("/stac/search")));
+        assert!(!is_write(
+            &Method::POST,
+            Some("/collections/{collection_id}/tiles/sign")
+        ));
+        assert!(is_write(&Method::POST, Some("/collections")));
+        assert!(is_write(&Method::POST, None));
+        assert!(is_write(
+            &Method::DELETE,
+            Some("/collections/{collection_id}")
+        ));
+    }
+}
@@ -0,0 +1,514 @@
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::api::collections::schemas::ListCollectionsParams;
+use crate::api::common::{Bbox, Extent, Link, media_type, rel};
+use crate::auth::AuthenticatedUser;
+use crate::config::Config;
+use crate::db::CollectionWithCrs;
+use crate::error::{AppError, AppResult};
+use crate::services::CollectionService;
+
+/// Identifier of the catalogue holding a record per collection
+pub const CATALOGUE_ID: &str = "main";
+
+/// Conformance class of the records returned
+const RECORD_CORE: &str = "http://www.opengis.net/spec/ogcapi-records-1/1.0/req/record-core";
+
+/// Catalogue description (OGC API Records)
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Catalogue {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub item_type: String,
+    pub links: Vec<Link>,
+}
+
+/// List of catalogues
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Catalogues {
+    pub catalogues: Vec<Catalogue>,
+    pub links: Vec<Link>,
+}
+
+/// Temporal extent of a record; open ends are `..`
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RecordTime {
+    pub interval: [String; 2],
+}
+
+/// Record properties; the names map to Dublin Core terms (`title` is
+/// `dct:title`, `description` is `dct:description`, `license` is
+/// `dct:license`, `created`/`updated` are `dct:created`/`dct:modified`)
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RecordProperties {
+    /// Resource type, always `dataset`
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+}
+
+/// Metadata record describing a collection
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Record {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub feature_type: String,
+    pub conforms_to: Vec<String>,
+    /// Footprint of the collection, `null` when it has no spatial extent
+    pub geometry: Option<serde_json::Value>,
+    /// Temporal extent, `null` when it has none
+    pub time: Option<RecordTime>,
+    pub properties: RecordProperties,
+    pub links: Vec<Link>,
+}
+
+/// Page of records
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordCollection {
+    #[serde(rename = "type")]
+    pub feature_type: String,
+    pub features: Vec<Record>,
+    pub number_matched: u64,
+    pub number_returned: u64,
+    pub links: Vec<Link>,
+}
+
+/// Query parameters for searching records
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RecordQueryParams {
+    /// Free text matched against title, description and keywords
+    pub q: Option<String>,
+    /// Comma-separated keywords the record must all have
+    pub keyword: Option<String>,
+    /// Bounding box the record's extent must intersect: minx,miny,maxx,maxy
+    pub bbox: Option<String>,
+    /// Instant or interval the record's temporal extent must overlap
+    pub datetime: Option<String>,
+    /// Limit results
+    #[serde(default = "default_record_limit")]
+    pub limit: u32,
+    /// Offset for pagination
+    #[serde(default)]
+    pub offset: u32,
+}
+
+fn default_record_limit() -> u32 {
+    10
+}
+
+impl RecordQueryParams {
+    /// The equivalent collection listing parameters
+    fn as_collection_params(&self) -> ListCollectionsParams {
+        ListCollectionsParams {
+            q: self.q.clone(),
+            keyword: self.keyword.clone(),
+            bbox: self.bbox.clone(),
+            datetime: self.datetime.clone(),
+            limit: self.limit,
+            offset: self.offset,
+            ..Default::default()
+        }
+    }
+
+    /// Record search URL for the given offset, preserving the filters
+    fn page_href(&self, base_url: &str, offset: u32) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        for (key, value) in [
+            ("q", &self.q),
+            ("keyword", &self.keyword),
+            ("bbox", &self.bbox),
+            ("datetime", &self.datetime),
+        ] {
+            if let Some(value) = value {
+                query.append_pair(key, value);
+            }
+        }
+        query.append_pair("limit", &self.limit.to_string());
+        query.append_pair("offset", &offset.to_string());
+        format!(
+            "{}/catalogues/{}/items?{}",
+            base_url,
+            CATALOGUE_ID,
+            query.finish()
+        )
+    }
+}
+
+fn catalogue(base_url: &str) -> Catalogue {
+    let href = format!("{}/catalogues/{}", base_url, CATALOGUE_ID);
+    Catalogue {
+        id: CATALOGUE_ID.to_string(),
+        title: "SpatialVault collections".to_string(),
+        description: "Metadata records of the collections accessible to the user".to_string(),
+        item_type: "record".to_string(),
+        links: vec![
+            Link::new(&href, rel::SELF).with_type(media_type::JSON),
+            Link::new(format!("{}/items", href), rel::ITEMS).with_type(media_type::GEOJSON),
+        ],
+    }
+}
+
+/// Polygon covering a bounding box, as GeoJSON
+fn bbox_polygon(bbox: &Bbox) -> serde_json::Value {
+    let [minx, miny, maxx, maxy] = match bbox {
+        Bbox::TwoD(coords) => *coords,
+        Bbox::ThreeD([minx, miny, _, maxx, maxy, _]) => [*minx, *miny, *maxx, *maxy],
+    };
+    serde_json::json!({
+        "type": "Polygon",
+        "coordinates": [[
+            [minx, miny],
+            [maxx, miny],
+            [maxx, maxy],
+            [minx, maxy],
+            [minx, miny]
+        ]]
+    })
+}
+
+/// Map a collection and its extent to a record
+pub fn collection_record(
+    collection: &CollectionWithCrs,
+    extent: Option<Extent>,
+    base_url: &str,
+) -> Record {
+    let id = &collection.canonical_name;
+    let geometry = extent
+        .as_ref()
+        .and_then(|extent| extent.spatial.as_ref())
+        .and_then(|spatial| spatial.bbox.first())
+        .map(bbox_polygon);
+    let time = extent
+        .and_then(|extent| extent.temporal)
+        .and_then(|temporal| temporal.interval.into_iter().next())
+        .map(|[start, end]| RecordTime {
+            interval: [
+                start.unwrap_or_else(|| "..".to_string()),
+                end.unwrap_or_else(|| "..".to_string()),
+            ],
+        });
+
+    Record {
+        id: id.clone(),
+        feature_type: "Feature".to_string(),
+        conforms_to: vec![RECORD_CORE.to_string()],
+        geometry,
+        time,
+        properties: RecordProperties {
+            record_type: "dataset".to_string(),
+            title: collection.title.clone(),
+            description: collection.description.clone(),
+            keywords: collection.keywords.clone(),
+            license: collection.license.clone(),
+            created: collection.created_at.map(|dt| dt.to_rfc3339()),
+            updated: collection.updated_at.map(|dt| dt.to_rfc3339()),
+        },
+        links: vec![
+            Link::new(
+                format!("{}/catalogues/{}/items/{}", base_url, CATALOGUE_ID, id),
+                rel::SELF,
+            )
+            .with_type(media_type::GEOJSON),
+            Link::new(
+                format!("{}/catalogues/{}", base_url, CATALOGUE_ID),
+                rel::COLLECTION,
+            )
+            .with_type(media_type::JSON),
+            Link::new(format!("{}/collections/{}", base_url, id), "describes")
+                .with_type(media_type::JSON)
+                .with_title("The described collection"),
+        ],
+    }
+}
+
+fn geojson(body: impl Serialize) -> Response {
+    ([(header::CONTENT_TYPE, media_type::GEOJSON)], Json(body)).into_response()
+}
+
+pub async fn list_catalogues(Extension(config): Extension<Arc<Config>>) -> Json<Catalogues> {
+    Json(Catalogues {
+        catalogues: vec![catalogue(&config.base_url)],
+        links: vec![
+            Link::new(format!("{}/catalogues", config.base_url), rel::SELF)
+                .with_type(media_type::JSON),
+        ],
+    })
+}
+
+fn list_catalogues_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("List catalogues")
+        .description("Returns the record catalogues; `main` holds a record per collection")
+        .tag("Records")
+        .response_with::<200, Json<Catalogues>, _>(|res| res.description("List of catalogues"))
+}
+
+/// Path parameters for a catalogue
+#[aide::axum::typed_path]
+#[typed_path("/catalogues/{catalogue_id}")]
+pub struct CataloguePath {
+    /// The catalogue identifier (`main`)
+    pub catalogue_id: String,
+}
+
+fn check_catalogue(catalogue_id: &str) -> AppResult<()> {
+    if catalogue_id != CATALOGUE_ID {
+        return Err(AppError::NotFound(format!(
+            "Catalogue not found: {}",
+            catalogue_id
+        )));
+    }
+    Ok(())
+}
+
+pub async fn get_catalogue(
+    Extension(config): Extension<Arc<Config>>,
+    path: CataloguePath,
+) -> AppResult<Json<Catalogue>> {
+    check_catalogue(&path.catalogue_id)?;
+    Ok(Json(catalogue(&config.base_url)))
+}
+
+fn get_catalogue_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get catalogue")
+        .description("Returns the description of a record catalogue")
+        .tag("Records")
+        .response_with::<200, Json<Catalogue>, _>(|res| res.description("Catalogue description"))
+        .response_with::<404, (), _>(|res| res.description("Catalogue not found"))
+}
+
+/// Path parameters for the records of a catalogue
+#[aide::axum::typed_path]
+#[typed_path("/catalogues/{catalogue_id}/items")]
+pub struct RecordsPath {
+    /// The catalogue identifier (`main`)
+    pub catalogue_id: String,
+}
+
+pub async fn list_records(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    path: RecordsPath,
+    Query(params): Query<RecordQueryParams>,
+) -> AppResult<Response> {
+    check_catalogue(&path.catalogue_id)?;
+    let filter = params.as_collection_params().filter()?;
+    let (collections, total) = service
+        .list_collections(&user.username, &filter, params.limit, params.offset)
+        .await?;
+
+    let base_url = &config.base_url;
+    let mut features = Vec::with_capacity(collections.len());
+    for collection in &collections {
+        let extent = service.compute_extent(&collection.as_collection()).await?;
+        features.push(collection_record(collection, extent, base_url));
+    }
+
+    let mut links = vec![
+        Link::new(params.page_href(base_url, params.offset), rel::SELF)
+            .with_type(media_type::GEOJSON),
+        Link::new(
+            format!("{}/catalogues/{}", base_url, CATALOGUE_ID),
+            rel::COLLECTION,
+        )
+        .with_type(media_type::JSON),
+    ];
+
+    if (params.offset as i64) + (params.limit as i64) < total {
+        links.push(
+            Link::new(
+                params.page_href(base_url, params.offset + params.limit),
+                rel::NEXT,
+            )
+            .with_type(media_type::GEOJSON),
+        );
+    }
+
+    if params.offset > 0 {
+        links.push(
+            Link::new(
+                params.page_href(base_url, params.offset.saturating_sub(params.limit)),
+                rel::PREV,
+            )
+            .with_type(media_type::GEOJSON),
+        );
+    }
+
+    Ok(geojson(RecordCollection {
+        feature_type: "FeatureCollection".to_string(),
+        number_matched: total as u64,
+        number_returned: features.len() as u64,
+        features,
+        links,
+    }))
+}
+
+fn list_records_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Search records")
+        .description(
+            "Returns metadata records of the collections accessible to the user, for \
+             harvesting into geoportals. `q`, `keyword`, `bbox` and `datetime` filter the \
+             records like the collection listing; results are paged with limit/offset.",
+        )
+        .tag("Records")
+        .response_with::<200, Json<RecordCollection>, _>(|res| res.description("Records"))
+        .response_with::<400, (), _>(|res| res.description("Invalid query parameters"))
+        .response_with::<404, (), _>(|res| res.description("Catalogue not found"))
+}
+
+/// Path parameters for a single record
+#[aide::axum::typed_path]
+#[typed_path("/catalogues/{catalogue_id}/items/{record_id}")]
+pub struct RecordPath {
+    /// The catalogue identifier (`main`)
+    pub catalogue_id: String,
+    /// The record identifier (the collection id)
+    pub record_id: String,
+}
+
+pub async fn get_record(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<CollectionService>>,
+    path: RecordPath,
+) -> AppResult<Response> {
+    check_catalogue(&path.catalogue_id)?;
+    let collection = service
+        .get_collection(&user.username, &path.record_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Record not found: {}", path.record_id)))?;
+    let extent = service.compute_extent(&collection.as_collection()).await?;
+
+    Ok(geojson(collection_record(
+        &collection,
+        extent,
+        &config.base_url,
+    )))
+}
+
+fn get_record_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get record")
+        .description("Returns the metadata record of a collection")
+        .tag("Records")
+        .response_with::<200, Json<Record>, _>(|res| res.description("The record"))
+        .response_with::<404, (), _>(|res| res.description("Catalogue or record not found"))
+}
+
+pub fn routes(service: Arc<CollectionService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/catalogues",
+            get_with(list_catalogues, list_catalogues_docs),
+        )
+        .api_route(
+            "/catalogues/{catalogue_id}",
+            get_with(get_catalogue, get_catalogue_docs),
+        )
+        .api_route(
+            "/catalogues/{catalogue_id}/items",
+            get_with(list_records, list_records_docs),
+        )
+        .api_route(
+            "/catalogues/{catalogue_id}/items/{record_id}",
+            get_with(get_record, get_record_docs),
+        )
+        .with_state(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::common::{SpatialExtent, TemporalExtent};
+
+    #[test]
+    fn test_collection_record() {
+        let collection = CollectionWithCrs {
+            id: uuid::Uuid::nil(),
+            canonical_name: "alice:roads".to_string(),
+            owner: "alice".to_string(),
+            schema_name: "alice".to_string(),
+            table_name: "roads".to_string(),
+            collection_type: "vector".to_string(),
+            title: "Roads".to_string(),
+            description: None,
+            version: 1,
+            created_at: None,
+            updated_at: None,
+            data_updated_at: None,
+            default_limit: None,
+            max_limit: None,
+            min_zoom: None,
+            max_zoom: None,
+            tile_layer: None,
+            tile_properties: None,
+            processing_defaults: None,
+            keywords: vec!["transport".to_string()],
+            license: Some("CC-BY-4.0".to_string()),
+            title_i18n: None,
+            description_i18n: None,
+            relations: None,
+            computed_properties: None,
+            unique_properties: Vec::new(),
+            geometry_columns: Vec::new(),
+            public_tiles: false,
+            expires_at: None,
+            geometry_type: Some("GEOMETRY".to_string()),
+            geometry_dimension: Some(2),
+            storage_crs: 4326,
+        };
+        let extent = Extent {
+            spatial: Some(SpatialExtent {
+                bbox: vec![Bbox::two_d(10.0, 50.0, 20.0, 60.0)],
+                crs: None,
+            }),
+            temporal: Some(TemporalExtent {
+                interval: vec![[Some("2024-01-01T00:00:00+00:00".to_string()), None]],
+            }),
+        };
+
+        let record = collection_record(&collection, Some(extent), "http://localhost");
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(value["id"], "alice:roads");
+        assert_eq!(value["properties"]["title"], "Roads");
+        assert_eq!(value["properties"]["license"], "CC-BY-4.0");
+        assert_eq!(
+            value["geometry"]["coordinates"][0][2],
+            serde_json::json!([20.0, 60.0])
+        );
+        assert_eq!(
+            value["time"]["interval"],
+            serde_json::json!(["2024-01-01T00:00:00+00:00", ".."])
+        );
+
+        let record = collection_record(&collection, None, "http://localhost");
+        let value = serde_json::to_value(&record).unwrap();
+        assert!(value["geometry"].is_null());
+        assert!(value["time"].is_null());
+    }
+}
@@ -8,6 +8,7 @@ use serde::Serialize;
 use std::sync::Arc;
 
 use crate::api::common::{Link, media_type, rel};
+use crate::api::language::AcceptLanguage;
 use crate::config::Config;
 
 /// STAC Catalog root
@@ -26,18 +27,31 @@ pub struct StacCatalog {
 }
 
 /// Get STAC catalog root
-pub async fn get_catalog(Extension(config): Extension<Arc<Config>>) -> Json<StacCatalog> {
+pub async fn get_catalog(
+    Extension(config): Extension<Arc<Config>>,
+    accept_language: AcceptLanguage,
+) -> Json<StacCatalog> {
     let base_url = &config.base_url;
+    let title = accept_language.localize("SpatialVault STAC Catalog", &config.localization.title);
+    let description = accept_language.localize(
+        "STAC catalog for SpatialVault geospatial data",
+        &config.localization.description,
+    );
+    let mut self_link =
+        Link::new(format!("{}/stac", base_url), rel::SELF).with_type(media_type::JSON);
+    if let Some(language) = title.language.or(description.language) {
+        self_link = self_link.with_hreflang(language);
+    }
 
     let catalog = StacCatalog {
         catalog_type: "Catalog".to_string(),
         stac_version: "1.0.0".to_string(),
         stac_extensions: vec![],
         id: "spatialvault".to_string(),
-        title: "SpatialVault STAC Catalog".to_string(),
-        description: "STAC catalog for SpatialVault geospatial data".to_string(),
+        title: title.text,
+        description: description.text,
         links: vec![
-            Link::new(format!("{}/stac", base_url), rel::SELF).with_type(media_type::JSON),
+            self_link,
             Link::new(format!("{}/stac", base_url), rel::ROOT).with_type(media_type::JSON),
             Link::new(base_url, "parent")
                 .with_type(media_type::JSON)
@@ -48,6 +62,12 @@ pub async fn get_catalog(Extension(config): Extension<Arc<Config>>) -> Json<Stac
             Link::new(format!("{}/stac/search", base_url), "search")
                 .with_type(media_type::GEOJSON)
                 .with_title("STAC Search"),
+            Link::new(
+                format!("{}/queryables", base_url),
+                "http://www.opengis.net/def/rel/ogc/1.0/queryables",
+            )
+            .with_type(media_type::SCHEMA_JSON)
+            .with_title("Queryables of the STAC search"),
             Link::new(format!("{}/api", base_url), rel::SERVICE_DESC)
                 .with_type(media_type::OPENAPI_JSON)
                 .with_title("OpenAPI definition"),
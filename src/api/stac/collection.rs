@@ -2,6 +2,7 @@ use schemars::JsonSchema;
 use serde::Serialize;
 
 use crate::api::common::{Extent, Link};
+use crate::api::language::{AcceptLanguage, stored_translations};
 
 /// STAC Collection
 #[derive(Debug, Serialize, JsonSchema)]
@@ -29,23 +30,45 @@ impl StacCollection {
         collection: &crate::db::Collection,
         extent: Option<Extent>,
         base_url: &str,
+        accept_language: &AcceptLanguage,
     ) -> Self {
         use crate::api::common::{media_type, rel};
 
         let id = &collection.canonical_name;
+        let title = accept_language.localize(
+            &collection.title,
+            &stored_translations(collection.title_i18n.as_ref()),
+        );
+        let description = collection.description.as_deref().map(|description| {
+            accept_language.localize(
+                description,
+                &stored_translations(collection.description_i18n.as_ref()),
+            )
+        });
+        let mut self_link = Link::new(format!("{}/collections/{}", base_url, id), rel::SELF)
+            .with_type(media_type::JSON);
+        if let Some(language) = title
+            .language
+            .as_ref()
+            .or_else(|| description.as_ref().and_then(|d| d.language.as_ref()))
+        {
+            self_link = self_link.with_hreflang(language);
+        }
 
         Self {
             collection_type: "Collection".to_string(),
             stac_version: "1.0.0".to_string(),
             stac_extensions: vec![],
             id: id.clone(),
-            title: collection.title.clone(),
-            description: collection.description.clone(),
-            license: "proprietary".to_string(),
+            title: title.text,
+            description: description.map(|description| description.text),
+            license: collection
+                .license
+                .clone()
+                .unwrap_or_else(|| "proprietary".to_string()),
             extent,
             links: vec![
-                Link::new(format!("{}/collections/{}", base_url, id), rel::SELF)
-                    .with_type(media_type::JSON),
+                self_link,
                 Link::new(format!("{}/stac", base_url), rel::ROOT).with_type(media_type::JSON),
                 Link::new(format!("{}/stac", base_url), rel::PARENT).with_type(media_type::JSON),
                 Link::new(format!("{}/collections/{}/items", base_url, id), rel::ITEMS)
@@ -80,6 +80,8 @@ pub struct StacSearchBody {
     pub params: StacSearchParams,
     /// Intersects geometry as GeoJSON object
     pub intersects: Option<serde_json::Value>,
+    /// Item IDs as an array
+    pub ids: Option<Vec<String>>,
 }
 
 /// STAC ItemCollection (search result)
@@ -109,7 +111,9 @@ pub async fn search_get(
     State(service): State<Arc<StacService>>,
     Query(params): Query<StacSearchParams>,
 ) -> AppResult<Json<StacItemCollection>> {
-    let results = service.search(&user.username, &params).await?;
+    let results = service
+        .search(&user.username, &params, &config.base_url)
+        .await?;
 
     let base_url = &config.base_url;
 
@@ -145,9 +149,15 @@ pub async fn search_post(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
     State(service): State<Arc<StacService>>,
-    Json(body): Json<StacSearchBody>,
+    Json(mut body): Json<StacSearchBody>,
 ) -> AppResult<Json<StacItemCollection>> {
-    let results = service.search(&user.username, &body.params).await?;
+    if let Some(ids) = body.ids.take() {
+        body.params.ids = Some(ids.join(","));
+    }
+
+    let results = service
+        .search(&user.username, &body.params, &config.base_url)
+        .await?;
 
     let base_url = &config.base_url;
 
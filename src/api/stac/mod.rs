@@ -1,5 +1,6 @@
 pub mod catalog;
 pub mod collection;
 pub mod item;
+pub mod queryables;
 
 pub use catalog::*;
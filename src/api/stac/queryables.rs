@@ -0,0 +1,176 @@
+//! Queryables of the STAC item search
+//!
+//! Lists the properties items can be filtered by across all collections the
+//! user can read: the item geometry and datetime, the standard STAC fields,
+//! and the custom properties present in a good share of the items.
+
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::api::common::media_type;
+use crate::auth::AuthenticatedUser;
+use crate::config::Config;
+use crate::error::AppResult;
+use crate::services::{PropertyType, StacService};
+
+/// Queryables as a JSON Schema (OGC API Features Part 3)
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Queryables {
+    #[serde(rename = "$schema")]
+    pub schema: String,
+    #[serde(rename = "$id")]
+    pub id: String,
+    #[serde(rename = "type")]
+    pub schema_type: String,
+    pub title: String,
+    pub properties: serde_json::Map<String, serde_json::Value>,
+    /// Items may have properties besides the listed ones
+    #[serde(rename = "additionalProperties")]
+    pub additional_properties: bool,
+}
+
+/// Fields of the STAC item specification and common extensions
+fn standard_queryables() -> Vec<(&'static str, serde_json::Value)> {
+    let date_time =
+        |title: &str| json!({ "title": title, "type": "string", "format": "date-time" });
+    vec![
+        ("id", json!({ "title": "Item ID", "type": "string" })),
+        (
+            "collection",
+            json!({ "title": "Collection ID", "type": "string" }),
+        ),
+        (
+            "geometry",
+            json!({ "title": "Footprint", "$ref": "https://geojson.org/schema/Geometry.json" }),
+        ),
+        ("datetime", date_time("Acquisition time")),
+        ("start_datetime", date_time("Start of the acquisition")),
+        ("end_datetime", date_time("End of the acquisition")),
+        ("created", date_time("Creation time of the metadata")),
+        ("updated", date_time("Update time of the metadata")),
+        ("title", json!({ "title": "Title", "type": "string" })),
+        ("platform", json!({ "title": "Platform", "type": "string" })),
+        (
+            "instruments",
+            json!({ "title": "Instruments", "type": "array", "items": { "type": "string" } }),
+        ),
+        (
+            "constellation",
+            json!({ "title": "Constellation", "type": "string" }),
+        ),
+        ("mission", json!({ "title": "Mission", "type": "string" })),
+        (
+            "gsd",
+            json!({ "title": "Ground sample distance", "type": "number", "exclusiveMinimum": 0 }),
+        ),
+        (
+            "eo:cloud_cover",
+            json!({ "title": "Cloud cover", "type": "number", "minimum": 0, "maximum": 100 }),
+        ),
+        (
+            "proj:epsg",
+            json!({ "title": "EPSG code", "type": "integer" }),
+        ),
+    ]
+}
+
+fn property_schema(property_type: PropertyType) -> serde_json::Value {
+    match property_type {
+        PropertyType::String => json!({ "type": "string" }),
+        PropertyType::Integer => json!({ "type": "integer" }),
+        PropertyType::Number => json!({ "type": "number" }),
+        PropertyType::Boolean => json!({ "type": "boolean" }),
+    }
+}
+
+/// Queryables of the standard fields and the common custom properties; the
+/// standard definitions win over ones inferred from the values
+fn build_queryables(base_url: &str, common: Vec<(String, PropertyType)>) -> Queryables {
+    let mut properties: serde_json::Map<String, serde_json::Value> = standard_queryables()
+        .into_iter()
+        .map(|(name, schema)| (name.to_string(), schema))
+        .collect();
+    for (name, property_type) in common {
+        properties
+            .entry(name)
+            .or_insert_with(|| property_schema(property_type));
+    }
+
+    Queryables {
+        schema: "https://json-schema.org/draft/2020-12/schema".to_string(),
+        id: format!("{}/queryables", base_url),
+        schema_type: "object".to_string(),
+        title: "Queryables of the STAC item search".to_string(),
+        properties,
+        additional_properties: true,
+    }
+}
+
+/// Get the queryables of the STAC item search
+pub async fn get_queryables(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<StacService>>,
+) -> AppResult<Response> {
+    let common = service.common_properties(&user.username).await?;
+    let queryables = build_queryables(&config.base_url, common);
+
+    Ok((
+        [(header::CONTENT_TYPE, media_type::SCHEMA_JSON)],
+        Json(queryables),
+    )
+        .into_response())
+}
+
+fn get_queryables_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get search queryables")
+        .description(
+            "Returns a JSON Schema of the properties STAC items can be searched by across \
+             all collections the user can read: geometry, datetime, the standard STAC fields, \
+             and custom properties present in at least a tenth of a sample of the items.",
+        )
+        .tag("STAC")
+        .response_with::<200, Json<Queryables>, _>(|res| res.description("Queryables"))
+}
+
+pub fn routes(service: Arc<StacService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route("/queryables", get_with(get_queryables, get_queryables_docs))
+        .with_state(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_queryables() {
+        let queryables = build_queryables(
+            "http://localhost:8080",
+            vec![
+                ("gsd".to_string(), PropertyType::Integer),
+                ("sensor".to_string(), PropertyType::String),
+                ("cloudy".to_string(), PropertyType::Boolean),
+            ],
+        );
+        assert_eq!(queryables.id, "http://localhost:8080/queryables");
+        assert_eq!(queryables.properties["datetime"]["format"], "date-time");
+        assert!(queryables.properties["geometry"]["$ref"].is_string());
+        // Standard fields keep their definition
+        assert_eq!(queryables.properties["gsd"]["type"], "number");
+        assert_eq!(queryables.properties["sensor"]["type"], "string");
+        assert_eq!(queryables.properties["cloudy"]["type"], "boolean");
+    }
+}
@@ -6,20 +6,33 @@ use axum::{
     Json,
     body::Body,
     extract::{Extension, Query, State},
-    http::{HeaderMap, StatusCode, header},
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
     response::{IntoResponse, Response},
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::ops::RangeInclusive;
 use std::sync::Arc;
 
-use super::raster::RasterFormat;
-use super::vector::{tile_matrix_sets, validate_tile_coords};
-use crate::api::common::{Link, media_type, rel};
+use super::properties::{TilePropertyRule, parse_property_rules, validate_property_rules};
+use super::raster::{RasterFormat, RasterRendering};
+use super::style::RasterStyleParams;
+use super::terrain::{TerrainMode, TerrainParams};
+use super::vector::{
+    tile_matrix_sets, tile_range_web_mercator, validate_tile_coords, validate_tile_in_bbox,
+    zoom_range,
+};
+use crate::api::body::MergePatchBody;
+use crate::api::collections::ResolvedCollection;
+use crate::api::collections::schemas::CollectionFilter;
+use crate::api::common::{Link, cache, etag, head_response, media_type, rel};
 use crate::auth::AuthenticatedUser;
-use crate::config::Config;
+use crate::config::{CacheConfig, Config};
+use crate::db::{Collection, CollectionWithCrs};
 use crate::error::{AppError, AppResult};
-use crate::services::{CollectionService, TileService};
+use crate::services::cdn_service;
+use crate::services::{AnalyticsService, CollectionService, TileService};
 
 /// Query parameters for tile requests
 #[derive(Debug, Default, Deserialize, JsonSchema)]
@@ -27,6 +40,42 @@ pub struct TileQueryParams {
     /// Output format: png, jpeg, webp
     #[serde(rename = "f")]
     pub format: Option<String>,
+    /// Styling of raster tiles
+    #[serde(flatten)]
+    pub style: RasterStyleParams,
+    /// Terrain rendering of elevation raster tiles
+    #[serde(flatten)]
+    pub terrain: TerrainParams,
+    /// Geometry column of vector tiles: `geometry` (default) or a secondary
+    /// geometry column of the collection
+    pub geom: Option<String>,
+    /// Collection version the URL is for; tiles of the current version are
+    /// cached as immutable
+    pub v: Option<i64>,
+}
+
+impl TileQueryParams {
+    /// How a raster tile in `format` is rendered
+    fn raster_rendering(&self, format: RasterFormat) -> AppResult<RasterRendering> {
+        match (self.style.parse()?, self.terrain.parse()?) {
+            (Some(_), Some(_)) => Err(AppError::BadRequest(
+                "algorithm cannot be combined with other styling parameters".to_string(),
+            )),
+            (Some(style), None) => Ok(RasterRendering::Style(style)),
+            (None, Some(TerrainMode::TerrainRgb)) if format == RasterFormat::Jpeg => Err(
+                AppError::BadRequest("terrain-rgb requires a lossless format".to_string()),
+            ),
+            (None, Some(mode)) => Ok(RasterRendering::Terrain(mode)),
+            (None, None) => Ok(RasterRendering::Default),
+        }
+    }
+}
+
+/// Query parameters for multi-collection tile requests
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LayeredTileQueryParams {
+    /// Comma separated vector collections, one layer each
+    pub collections: String,
 }
 
 /// Negotiate raster tile format from Accept header and query parameter
@@ -59,6 +108,33 @@ fn negotiate_raster_format(headers: &HeaderMap, query_format: Option<&str>) -> R
     RasterFormat::Png
 }
 
+/// WebMercatorQuad limits of the tiles covering `bbox` at each zoom level
+fn tile_matrix_set_limits(zooms: RangeInclusive<u32>, bbox: [f64; 4]) -> Vec<TileMatrixSetLimit> {
+    zooms
+        .map(|z| {
+            let (min_tile_col, min_tile_row, max_tile_col, max_tile_row) =
+                tile_range_web_mercator(z, bbox);
+            TileMatrixSetLimit {
+                tile_matrix: z.to_string(),
+                min_tile_row,
+                max_tile_row,
+                min_tile_col,
+                max_tile_col,
+            }
+        })
+        .collect()
+}
+
+/// Definition of the WebMercatorQuad tile matrix set in the OGC registry
+const WEB_MERCATOR_QUAD_URI: &str =
+    "http://www.opengis.net/def/tilematrixset/OGC/1.0/WebMercatorQuad";
+
+/// Link relation of a tileset to its tile matrix set definition
+const TILING_SCHEME_REL: &str = "http://www.opengis.net/def/rel/ogc/1.0/tiling-scheme";
+
+/// Most collections offered as layers of the dataset tileset
+const MAX_DATASET_LAYERS: u32 = 100;
+
 /// TileMatrixSet reference
 #[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -75,6 +151,38 @@ pub struct TileMatrixSetListResponse {
     pub tile_matrix_sets: Vec<TileMatrixSetRef>,
 }
 
+/// Entry of a tileset list
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TilesetSummary {
+    pub title: String,
+    pub data_type: String,
+    pub crs: String,
+    #[serde(rename = "tileMatrixSetURI")]
+    pub tile_matrix_set_uri: String,
+    /// Links to the tileset description and its tile matrix set
+    pub links: Vec<Link>,
+}
+
+/// Tilesets of a collection or of the whole dataset
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TilesetListResponse {
+    pub tilesets: Vec<TilesetSummary>,
+    pub links: Vec<Link>,
+}
+
+/// A collection contributing a layer to the dataset's vector tiles
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TilesetLayer {
+    /// Layer name in the tiles
+    pub id: String,
+    pub title: String,
+    pub data_type: String,
+    /// Links to the collection
+    pub links: Vec<Link>,
+}
+
 /// Tileset metadata
 #[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -85,9 +193,34 @@ pub struct TilesetMetadata {
     pub data_type: String,
     pub crs: String,
     pub tile_matrix_set_id: String,
+    #[serde(rename = "tileMatrixSetURI")]
+    pub tile_matrix_set_uri: String,
     pub links: Vec<Link>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tile_matrix_set_limits: Option<Vec<TileMatrixSetLimit>>,
+    /// Collections contributing a layer to the tiles of the dataset tileset
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<TilesetLayer>,
+    /// Properties encoded into vector tiles per zoom range; the first rule
+    /// covering a zoom level applies, and levels without a rule encode all
+    /// properties
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub property_rules: Vec<TilePropertyRule>,
+    /// Whether the owner may hand out signed tile URLs that serve the tiles
+    /// without credentials
+    pub public_tiles: bool,
+}
+
+/// Partial update of a tileset's configuration (JSON Merge Patch)
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TilesetPatch {
+    /// Replaces the property rules; an empty list removes them
+    #[serde(default)]
+    pub property_rules: Option<Vec<TilePropertyRule>>,
+    /// Allows or disallows signed tile URLs
+    #[serde(default)]
+    pub public_tiles: Option<bool>,
 }
 
 /// Tile matrix set limit
@@ -107,7 +240,7 @@ pub async fn list_tile_matrix_sets() -> Json<TileMatrixSetListResponse> {
         TileMatrixSetRef {
             id: tile_matrix_sets::WEB_MERCATOR_QUAD.to_string(),
             title: Some("Google Maps Compatible for the World".to_string()),
-            uri: "http://www.opengis.net/def/tilematrixset/OGC/1.0/WebMercatorQuad".to_string(),
+            uri: WEB_MERCATOR_QUAD_URI.to_string(),
         },
         TileMatrixSetRef {
             id: tile_matrix_sets::WORLD_CRS84_QUAD.to_string(),
@@ -136,35 +269,128 @@ pub struct CollectionTilesPath {
     pub collection_id: String,
 }
 
+/// Path parameters for a collection's tileset
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/tiles/{tile_matrix_set_id}")]
+pub struct CollectionTilesetPath {
+    /// The collection identifier
+    pub collection_id: String,
+    /// The tile matrix set identifier (e.g., WebMercatorQuad)
+    pub tile_matrix_set_id: String,
+}
+
+/// Fail for tile matrix sets tilesets aren't offered in
+fn check_tileset_tile_matrix_set(tile_matrix_set_id: &str) -> AppResult<()> {
+    if tile_matrix_set_id != tile_matrix_sets::WEB_MERCATOR_QUAD {
+        return Err(AppError::NotFound(format!(
+            "TileMatrixSet not supported: {}",
+            tile_matrix_set_id
+        )));
+    }
+    Ok(())
+}
+
+/// Tileset list entry for a tileset described at `href`
+fn tileset_summary(title: &str, data_type: &str, href: String) -> TilesetSummary {
+    TilesetSummary {
+        title: title.to_string(),
+        data_type: data_type.to_string(),
+        crs: "http://www.opengis.net/def/crs/EPSG/0/3857".to_string(),
+        tile_matrix_set_uri: WEB_MERCATOR_QUAD_URI.to_string(),
+        links: vec![
+            Link::new(href, rel::SELF)
+                .with_type(media_type::JSON)
+                .with_title("Tileset description"),
+            Link::new(WEB_MERCATOR_QUAD_URI, TILING_SCHEME_REL)
+                .with_type(media_type::JSON)
+                .with_title("WebMercatorQuad definition"),
+        ],
+    }
+}
+
+/// Tile data type of a collection's tileset, if it has one
+fn collection_data_type(collection: &Collection) -> Option<&'static str> {
+    match collection.collection_type.as_str() {
+        "vector" => Some("vector"),
+        "raster" => Some("map"),
+        _ => None,
+    }
+}
+
+/// List the tilesets of a collection
+pub async fn list_tilesets(
+    Extension(config): Extension<Arc<Config>>,
+    _path: CollectionTilesPath,
+    ResolvedCollection(collection): ResolvedCollection,
+) -> Json<TilesetListResponse> {
+    let base_url = &config.base_url;
+    let collection_id = &collection.canonical_name;
+    let tilesets = collection_data_type(&collection.as_collection())
+        .map(|data_type| {
+            tileset_summary(
+                &collection.title,
+                data_type,
+                format!(
+                    "{}/collections/{}/tiles/{}",
+                    base_url,
+                    collection_id,
+                    tile_matrix_sets::WEB_MERCATOR_QUAD
+                ),
+            )
+        })
+        .into_iter()
+        .collect();
+
+    Json(TilesetListResponse {
+        tilesets,
+        links: vec![
+            Link::new(
+                format!("{}/collections/{}/tiles", base_url, collection_id),
+                rel::SELF,
+            )
+            .with_type(media_type::JSON),
+            Link::new(
+                format!("{}/collections/{}", base_url, collection_id),
+                rel::COLLECTION,
+            )
+            .with_type(media_type::JSON),
+        ],
+    })
+}
+
+fn list_tilesets_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("List collection tilesets")
+        .description(
+            "Returns the tilesets of a collection: vector tiles of vector collections and map \
+             tiles of raster collections, in WebMercatorQuad",
+        )
+        .tag("Tiles")
+        .response_with::<200, Json<TilesetListResponse>, _>(|res| {
+            res.description("Tilesets of the collection")
+        })
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
 /// Get tileset metadata for a collection
 pub async fn get_tileset(
     Extension(config): Extension<Arc<Config>>,
-    Extension(user): Extension<AuthenticatedUser>,
-    State((service, collection_service)): State<(Arc<TileService>, Arc<CollectionService>)>,
-    path: CollectionTilesPath,
+    State(service): State<Arc<TileService>>,
+    path: CollectionTilesetPath,
+    ResolvedCollection(collection): ResolvedCollection,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = collection_service
-        .check_alias_redirect(&collection_id)
-        .await?
-    {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::LOCATION,
-            format!("{}/collections/{}/tiles", config.base_url, new_name)
-                .parse()
-                .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, headers).into_response());
-    }
+    check_tileset_tile_matrix_set(&path.tile_matrix_set_id)?;
+    let tileset = build_tileset(&config.base_url, &service, &collection.as_collection()).await?;
 
-    let collection = service
-        .get_collection(&user.username, &collection_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Collection not found: {}", collection_id)))?;
+    Ok(Json(tileset).into_response())
+}
 
-    let base_url = &config.base_url;
+/// Tileset metadata of a collection
+async fn build_tileset(
+    base_url: &str,
+    service: &TileService,
+    collection: &Collection,
+) -> AppResult<TilesetMetadata> {
+    let collection_id = collection.canonical_name.clone();
 
     // Determine tile type and content type based on collection type
     let data_type = match collection.collection_type.as_str() {
@@ -175,7 +401,12 @@ pub async fn get_tileset(
 
     let mut links = vec![
         Link::new(
-            format!("{}/collections/{}/tiles", base_url, collection_id),
+            format!(
+                "{}/collections/{}/tiles/{}",
+                base_url,
+                collection_id,
+                tile_matrix_sets::WEB_MERCATOR_QUAD
+            ),
             rel::SELF,
         )
         .with_type(media_type::JSON),
@@ -184,6 +415,9 @@ pub async fn get_tileset(
             rel::COLLECTION,
         )
         .with_type(media_type::JSON),
+        Link::new(WEB_MERCATOR_QUAD_URI, TILING_SCHEME_REL)
+            .with_type(media_type::JSON)
+            .with_title("WebMercatorQuad definition"),
     ];
 
     // Add tile URL templates based on collection type
@@ -191,8 +425,8 @@ pub async fn get_tileset(
         links.push(
             Link::new(
                 format!(
-                    "{}/collections/{}/tiles/WebMercatorQuad/{{tileMatrix}}/{{tileRow}}/{{tileCol}}",
-                    base_url, collection_id
+                    "{}/collections/{}/tiles/WebMercatorQuad/{{tileMatrix}}/{{tileRow}}/{{tileCol}}?v={}",
+                    base_url, collection_id, collection.version
                 ),
                 "item",
             )
@@ -204,8 +438,8 @@ pub async fn get_tileset(
         links.push(
             Link::new(
                 format!(
-                    "{}/collections/{}/tiles/WebMercatorQuad/{{tileMatrix}}/{{tileRow}}/{{tileCol}}?f=png",
-                    base_url, collection_id
+                    "{}/collections/{}/tiles/WebMercatorQuad/{{tileMatrix}}/{{tileRow}}/{{tileCol}}?f=png&v={}",
+                    base_url, collection_id, collection.version
                 ),
                 "item",
             )
@@ -215,8 +449,8 @@ pub async fn get_tileset(
         links.push(
             Link::new(
                 format!(
-                    "{}/collections/{}/tiles/WebMercatorQuad/{{tileMatrix}}/{{tileRow}}/{{tileCol}}?f=jpeg",
-                    base_url, collection_id
+                    "{}/collections/{}/tiles/WebMercatorQuad/{{tileMatrix}}/{{tileRow}}/{{tileCol}}?f=jpeg&v={}",
+                    base_url, collection_id, collection.version
                 ),
                 "item",
             )
@@ -238,17 +472,25 @@ pub async fn get_tileset(
         }
     }
 
+    // Limit the tile matrices to the collection's zoom range and data extent
+    let bbox = service.data_bbox(collection).await?;
+    let limits = tile_matrix_set_limits(zoom_range(collection.min_zoom, collection.max_zoom), bbox);
+
     let tileset = TilesetMetadata {
         title: collection.title.clone(),
         description: collection.description.clone(),
         data_type: data_type.to_string(),
         crs: "http://www.opengis.net/def/crs/EPSG/0/3857".to_string(),
         tile_matrix_set_id: tile_matrix_sets::WEB_MERCATOR_QUAD.to_string(),
+        tile_matrix_set_uri: WEB_MERCATOR_QUAD_URI.to_string(),
         links,
-        tile_matrix_set_limits: None,
+        tile_matrix_set_limits: Some(limits),
+        layers: Vec::new(),
+        property_rules: parse_property_rules(collection.tile_properties.as_ref())?,
+        public_tiles: collection.public_tiles,
     };
 
-    Ok(Json(tileset).into_response())
+    Ok(tileset)
 }
 
 fn get_tileset_docs(op: TransformOperation) -> TransformOperation {
@@ -256,7 +498,78 @@ fn get_tileset_docs(op: TransformOperation) -> TransformOperation {
         .description("Returns tileset metadata for a collection, including tile URL templates")
         .tag("Tiles")
         .response_with::<200, Json<TilesetMetadata>, _>(|res| res.description("Tileset metadata"))
-        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+        .response_with::<404, (), _>(|res| {
+            res.description("Collection or tile matrix set not found")
+        })
+}
+
+/// Update the tileset configuration of a collection
+#[allow(clippy::too_many_arguments)]
+pub async fn patch_tileset(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(collection_service): Extension<Arc<CollectionService>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<TileService>>,
+    path: CollectionTilesetPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    headers: HeaderMap,
+    MergePatchBody(patch): MergePatchBody<TilesetPatch>,
+) -> Result<Response, AppError> {
+    check_tileset_tile_matrix_set(&path.tile_matrix_set_id)?;
+
+    // If-Match header is optional - when present, enables optimistic locking
+    let mut expected_version = etag::extract_expected_version(&headers)?;
+
+    // Each update bumps the version, so a second one expects the first's
+    let mut collection = collection.as_collection();
+    if let Some(rules) = patch.property_rules {
+        validate_property_rules(&rules)?;
+        collection = collection_service
+            .update_tile_properties(
+                &user.username,
+                &collection.canonical_name,
+                expected_version,
+                &rules,
+            )
+            .await?;
+        expected_version = Some(collection.version);
+    }
+    if let Some(public_tiles) = patch.public_tiles {
+        collection = collection_service
+            .update_public_tiles(
+                &user.username,
+                &collection.canonical_name,
+                expected_version,
+                public_tiles,
+            )
+            .await?;
+    }
+
+    let tileset = build_tileset(&config.base_url, &service, &collection).await?;
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::ETAG, etag::create_etag_header(collection.version)?);
+
+    Ok((response_headers, Json(tileset)).into_response())
+}
+
+fn patch_tileset_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Update tileset configuration")
+        .description(
+            "Partially updates a collection's tileset configuration using JSON Merge Patch, \
+             e.g. which properties are encoded into vector tiles at which zoom levels, or \
+             whether signed tile URLs may be handed out (publicTiles). If-Match header is optional; when provided, enables optimistic locking.",
+        )
+        .tag("Tiles")
+        .response_with::<200, Json<TilesetMetadata>, _>(|res| {
+            res.description("Updated tileset metadata")
+        })
+        .response_with::<400, (), _>(|res| res.description("Invalid property rules"))
+        .response_with::<403, (), _>(|res| res.description("Only the owner may update the tileset"))
+        .response_with::<404, (), _>(|res| {
+            res.description("Collection or tile matrix set not found")
+        })
+        .response_with::<412, (), _>(|res| res.description("Precondition failed (ETag mismatch)"))
 }
 
 /// Path parameters for single tile endpoint
@@ -276,37 +589,68 @@ pub struct TilePath {
 }
 
 /// Get a single tile
+#[allow(clippy::too_many_arguments)]
 pub async fn get_tile(
     Extension(config): Extension<Arc<Config>>,
     Extension(user): Extension<AuthenticatedUser>,
-    State((service, collection_service)): State<(Arc<TileService>, Arc<CollectionService>)>,
+    Extension(analytics): Extension<Arc<AnalyticsService>>,
+    State(service): State<Arc<TileService>>,
     path: TilePath,
+    ResolvedCollection(collection): ResolvedCollection,
     Query(params): Query<TileQueryParams>,
+    method: Method,
     headers: HeaderMap,
 ) -> Result<Response, AppError> {
-    let collection_id = path.collection_id;
-    // Check for alias redirect (only if no active collection with this exact name exists)
-    if let Some(new_name) = collection_service
-        .check_alias_redirect(&collection_id)
-        .await?
-    {
-        let mut redirect_headers = HeaderMap::new();
-        redirect_headers.insert(
-            header::LOCATION,
-            format!(
-                "{}/collections/{}/tiles/{}/{}/{}/{}",
-                config.base_url, new_name, path.tile_matrix_set_id, path.z, path.y, path.x
-            )
-            .parse()
-            .map_err(|_| AppError::Internal("Invalid redirect URL".to_string()))?,
-        );
-        return Ok((StatusCode::TEMPORARY_REDIRECT, redirect_headers).into_response());
-    }
+    let request = TileRequest {
+        tile_matrix_set_id: &path.tile_matrix_set_id,
+        z: path.z,
+        y: path.y,
+        x: path.x,
+        params: &params,
+        method: &method,
+        headers: &headers,
+    };
+    tile_response(
+        &service,
+        &analytics,
+        &config.cache,
+        &user.username,
+        &collection,
+        request,
+    )
+    .await
+}
+
+/// A tile requested from a collection
+pub(crate) struct TileRequest<'a> {
+    pub tile_matrix_set_id: &'a str,
+    pub z: u32,
+    pub y: u32,
+    pub x: u32,
+    pub params: &'a TileQueryParams,
+    pub method: &'a Method,
+    pub headers: &'a HeaderMap,
+}
 
-    let tile_matrix_set_id = path.tile_matrix_set_id;
-    let z = path.z;
-    let y = path.y;
-    let x = path.x;
+/// Render a tile of a collection, counting it in the collection's analytics
+pub(crate) async fn tile_response(
+    service: &TileService,
+    analytics: &AnalyticsService,
+    cache_config: &CacheConfig,
+    username: &str,
+    collection: &CollectionWithCrs,
+    request: TileRequest<'_>,
+) -> Result<Response, AppError> {
+    let TileRequest {
+        tile_matrix_set_id,
+        z,
+        y,
+        x,
+        params,
+        method,
+        headers,
+    } = request;
+    let collection_id = collection.canonical_name.clone();
     // Validate tile matrix set
     if tile_matrix_set_id != tile_matrix_sets::WEB_MERCATOR_QUAD {
         return Err(AppError::NotFound(format!(
@@ -316,76 +660,399 @@ pub async fn get_tile(
     }
 
     // Validate coordinates
-    validate_tile_coords(z, x, y, 22)?;
+    validate_tile_coords(
+        z,
+        x,
+        y,
+        &zoom_range(collection.min_zoom, collection.max_zoom),
+    )?;
+    let bbox = service.data_bbox(&collection.as_collection()).await?;
+    validate_tile_in_bbox(z, x, y, bbox)?;
 
-    // Get collection to determine type
-    let collection = service
-        .get_collection(&user.username, &collection_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Collection not found: {}", collection_id)))?;
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(
+        header::CACHE_CONTROL,
+        cache::cache_control(cache_config, params.v == Some(collection.version)),
+    );
+    // Lets a CDN purge all tiles of the collection when it changes
+    if let Ok(key) = HeaderValue::from_str(&cdn_service::surrogate_key(collection.id)) {
+        response_headers.insert("surrogate-key", key);
+    }
 
-    match collection.collection_type.as_str() {
+    let format = match collection.collection_type.as_str() {
         "vector" => {
-            // Get MVT tile data
-            let tile_data = service
-                .get_vector_tile(&user.username, &collection_id, z, x, y)
-                .await?;
-
-            let mut response_headers = HeaderMap::new();
             response_headers.insert(header::CONTENT_TYPE, media_type::MVT.parse().unwrap());
-            response_headers.insert(
-                header::CACHE_CONTROL,
-                "public, max-age=3600".parse().unwrap(),
-            );
-
-            Ok((StatusCode::OK, response_headers, Body::from(tile_data)).into_response())
+            None
         }
         "raster" => {
             // Negotiate format from Accept header and query parameter
-            let format = negotiate_raster_format(&headers, params.format.as_deref());
-
-            // Get raster tile in requested format
-            let tile_data = service
-                .get_raster_tile(&user.username, &collection_id, z, x, y, format)
-                .await?;
-
-            let mut response_headers = HeaderMap::new();
-            response_headers.insert(
-                header::CONTENT_TYPE,
-                format.content_type().parse().unwrap(),
-            );
-            response_headers.insert(
-                header::CACHE_CONTROL,
-                "public, max-age=3600".parse().unwrap(),
-            );
+            let format = negotiate_raster_format(headers, params.format.as_deref());
+            response_headers.insert(header::CONTENT_TYPE, format.content_type().parse().unwrap());
             // Add Vary header for proper caching with content negotiation
             response_headers.insert(header::VARY, "Accept".parse().unwrap());
-
-            Ok((StatusCode::OK, response_headers, Body::from(tile_data)).into_response())
+            Some((format, params.raster_rendering(format)?))
         }
         "pointcloud" => {
-            Err(AppError::BadRequest(
-                "Tiles not available for point cloud collections. Use STAC items endpoint to access COPC files.".to_string(),
-            ))
+            return Err(AppError::BadRequest(
+                "Tiles not available for point cloud collections. Use the 3D Tiles tileset or the STAC items endpoint.".to_string(),
+            ));
+        }
+        _ => {
+            return Err(AppError::BadRequest(format!(
+                "Unknown collection type: {}",
+                collection.collection_type
+            )));
         }
-        _ => Err(AppError::BadRequest(format!(
-            "Unknown collection type: {}",
-            collection.collection_type
-        ))),
+    };
+
+    // Tiles only change with the collection, so its version validates them
+    let etag = cache::data_etag(
+        collection.version,
+        format
+            .as_ref()
+            .map_or("mvt", |(format, _)| format.extension()),
+    );
+    response_headers.insert(header::ETAG, etag.clone());
+    if cache::is_not_modified(headers, &etag) {
+        return Ok(cache::not_modified_response(response_headers));
     }
+
+    // The tile is only rendered when its content is requested; its length is
+    // known once it has been served
+    let size_key = format!(
+        "{}/{}/{}/{} {:?} {:?} {:?}",
+        collection.id,
+        z,
+        y,
+        x,
+        etag,
+        params.geom,
+        format.as_ref().map(|(_, rendering)| rendering)
+    );
+    if method == Method::HEAD {
+        if let Some(size) = service.tile_size(&size_key) {
+            response_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(size));
+        }
+        return Ok(head_response(response_headers));
+    }
+
+    let tile_data = match format {
+        // Get MVT tile data
+        None => {
+            service
+                .get_vector_tile(username, &collection_id, z, x, y, params.geom.as_deref())
+                .await?
+        }
+        // Get raster tile in requested format
+        Some((format, rendering)) => {
+            service
+                .get_raster_tile(username, &collection_id, z, x, y, format, rendering)
+                .await?
+        }
+    };
+    analytics.record_tile(collection.id, tile_data.len());
+    service.record_tile_size(size_key, tile_data.len());
+
+    Ok((StatusCode::OK, response_headers, Body::from(tile_data)).into_response())
 }
 
 fn get_tile_docs(op: TransformOperation) -> TransformOperation {
     op.summary("Get tile")
-        .description("Returns a single tile as MVT (vector) or PNG/JPEG/WebP (raster)")
+        .description(
+            "Returns a single tile as MVT (vector) or PNG/JPEG/WebP (raster). \
+             Raster tiles can be styled with bidx or a band math expression, \
+             rescale and colormap_name, or rendered from elevation with \
+             algorithm=terrain-rgb or algorithm=hillshade (azimuth, altitude). \
+             Vector tiles of a secondary geometry column are selected with geom. \
+             Served tiles are counted in the collection's analytics. Tiles carry an ETag \
+             of the collection version for revalidation, and URLs with v set to the \
+             current collection version (as in the tileset's URL templates) are cached \
+             as immutable.",
+        )
         .tag("Tiles")
         .response_with::<200, (), _>(|res| {
             res.description("Tile data (application/vnd.mapbox-vector-tile or image/*)")
         })
+        .response_with::<304, (), _>(|res| res.description("Tile not modified"))
+        .response_with::<404, (), _>(|res| {
+            res.description(
+                "Collection not found or tile outside the tileset's tile matrix set limits",
+            )
+        })
+}
+
+fn head_tile_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Check tile")
+        .description(
+            "Returns the headers of a tile without rendering it. Content-Length is \
+             included once the tile has been served.",
+        )
+        .tag("Tiles")
+        .response_with::<200, (), _>(|res| res.description("Tile headers"))
+        .response_with::<304, (), _>(|res| res.description("Tile not modified"))
+        .response_with::<404, (), _>(|res| res.description("Collection or tile not found"))
+}
+
+/// Path parameters for multi-collection tile endpoint
+#[aide::axum::typed_path]
+#[typed_path("/tiles/{tile_matrix_set_id}/{z}/{y}/{x}")]
+pub struct LayeredTilePath {
+    /// The tile matrix set identifier (e.g., WebMercatorQuad)
+    pub tile_matrix_set_id: String,
+    /// Zoom level
+    pub z: u32,
+    /// Row (y) coordinate
+    pub y: u32,
+    /// Column (x) coordinate
+    pub x: u32,
+}
+
+/// Path parameters for the dataset tileset
+#[aide::axum::typed_path]
+#[typed_path("/tiles/{tile_matrix_set_id}")]
+pub struct DatasetTilesetPath {
+    /// The tile matrix set identifier (e.g., WebMercatorQuad)
+    pub tile_matrix_set_id: String,
+}
+
+/// List the tilesets of the whole dataset
+pub async fn list_dataset_tilesets(
+    Extension(config): Extension<Arc<Config>>,
+) -> Json<TilesetListResponse> {
+    let base_url = &config.base_url;
+
+    Json(TilesetListResponse {
+        tilesets: vec![tileset_summary(
+            "SpatialVault",
+            "vector",
+            format!("{}/tiles/{}", base_url, tile_matrix_sets::WEB_MERCATOR_QUAD),
+        )],
+        links: vec![
+            Link::new(format!("{}/tiles", base_url), rel::SELF).with_type(media_type::JSON),
+        ],
+    })
+}
+
+fn list_dataset_tilesets_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("List dataset tilesets")
+        .description("Returns the tilesets combining several collections into one tile")
+        .tag("Tiles")
+        .response_with::<200, Json<TilesetListResponse>, _>(|res| {
+            res.description("Tilesets of the dataset")
+        })
+}
+
+/// Get the vector tileset with a layer per accessible collection
+pub async fn get_dataset_tileset(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Extension(collection_service): Extension<Arc<CollectionService>>,
+    path: DatasetTilesetPath,
+) -> AppResult<Json<TilesetMetadata>> {
+    check_tileset_tile_matrix_set(&path.tile_matrix_set_id)?;
+    let base_url = &config.base_url;
+
+    let filter = CollectionFilter {
+        collection_type: Some("vector".to_string()),
+        ..Default::default()
+    };
+    let (collections, _) = collection_service
+        .list_collections(&user.username, &filter, MAX_DATASET_LAYERS, 0)
+        .await?;
+
+    // A tile can't hold two layers of the same name, so the first one wins
+    let mut layer_names = std::collections::HashSet::new();
+    let collections: Vec<Collection> = collections
+        .iter()
+        .map(CollectionWithCrs::as_collection)
+        .filter(|collection| layer_names.insert(collection.tile_layer_name().to_string()))
+        .collect();
+
+    let mut links = vec![
+        Link::new(
+            format!("{}/tiles/{}", base_url, tile_matrix_sets::WEB_MERCATOR_QUAD),
+            rel::SELF,
+        )
+        .with_type(media_type::JSON),
+        Link::new(WEB_MERCATOR_QUAD_URI, TILING_SCHEME_REL)
+            .with_type(media_type::JSON)
+            .with_title("WebMercatorQuad definition"),
+    ];
+    if !collections.is_empty() {
+        let ids: Vec<&str> = collections
+            .iter()
+            .map(|collection| collection.canonical_name.as_str())
+            .collect();
+        links.push(
+            Link::new(
+                format!(
+                    "{}/tiles/{}/{{tileMatrix}}/{{tileRow}}/{{tileCol}}?collections={}",
+                    base_url,
+                    tile_matrix_sets::WEB_MERCATOR_QUAD,
+                    ids.join(",")
+                ),
+                "item",
+            )
+            .with_type(media_type::MVT)
+            .with_title("Vector tile (MVT)"),
+        );
+    }
+
+    let layers = collections
+        .iter()
+        .map(|collection| TilesetLayer {
+            id: collection.tile_layer_name().to_string(),
+            title: collection.title.clone(),
+            data_type: "vector".to_string(),
+            links: vec![
+                Link::new(
+                    format!("{}/collections/{}", base_url, collection.canonical_name),
+                    rel::COLLECTION,
+                )
+                .with_type(media_type::JSON),
+            ],
+        })
+        .collect();
+
+    Ok(Json(TilesetMetadata {
+        title: "SpatialVault".to_string(),
+        description: Some("Vector tiles with a layer per collection".to_string()),
+        data_type: "vector".to_string(),
+        crs: "http://www.opengis.net/def/crs/EPSG/0/3857".to_string(),
+        tile_matrix_set_id: tile_matrix_sets::WEB_MERCATOR_QUAD.to_string(),
+        tile_matrix_set_uri: WEB_MERCATOR_QUAD_URI.to_string(),
+        links,
+        tile_matrix_set_limits: None,
+        layers,
+        property_rules: Vec::new(),
+        public_tiles: false,
+    }))
+}
+
+fn get_dataset_tileset_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get dataset tileset")
+        .description(
+            "Returns the vector tileset combining the vector collections you can access, one \
+             layer each, with a tile URL template selecting them. Up to 100 collections are \
+             listed; collections sharing a layer name with an earlier one are left out.",
+        )
+        .tag("Tiles")
+        .response_with::<200, Json<TilesetMetadata>, _>(|res| res.description("Tileset metadata"))
+        .response_with::<404, (), _>(|res| res.description("Tile matrix set not found"))
+}
+
+/// Get a vector tile with a layer per collection
+pub async fn get_layered_tile(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<TileService>>,
+    path: LayeredTilePath,
+    Query(params): Query<LayeredTileQueryParams>,
+    method: Method,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    if path.tile_matrix_set_id != tile_matrix_sets::WEB_MERCATOR_QUAD {
+        return Err(AppError::NotFound(format!(
+            "TileMatrixSet not supported: {}",
+            path.tile_matrix_set_id
+        )));
+    }
+
+    let collection_ids: Vec<String> = params
+        .collections
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect();
+    if collection_ids.is_empty() {
+        return Err(AppError::BadRequest(
+            "At least one collection is required".to_string(),
+        ));
+    }
+
+    validate_tile_coords(path.z, path.x, path.y, &zoom_range(None, None))?;
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(
+        header::CACHE_CONTROL,
+        cache::cache_control(&config.cache, false),
+    );
+    response_headers.insert(header::CONTENT_TYPE, media_type::MVT.parse().unwrap());
+
+    let collections = service
+        .resolve_tile_layers(&user.username, &collection_ids)
+        .await?;
+    let etag = layered_tile_etag(&collections);
+    response_headers.insert(header::ETAG, etag.clone());
+    if cache::is_not_modified(&headers, &etag) {
+        return Ok(cache::not_modified_response(response_headers));
+    }
+
+    let size_key = format!("{}/{}/{} {:?}", path.z, path.y, path.x, etag);
+    if method == Method::HEAD {
+        if let Some(size) = service.tile_size(&size_key) {
+            response_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(size));
+        }
+        return Ok(head_response(response_headers));
+    }
+
+    let tile_data = service
+        .get_vector_tile_layers(&collections, path.z, path.x, path.y)
+        .await?;
+    service.record_tile_size(size_key, tile_data.len());
+
+    Ok((StatusCode::OK, response_headers, Body::from(tile_data)).into_response())
+}
+
+/// ETag of a multi-collection tile, changing with the version of any layer
+fn layered_tile_etag(collections: &[Collection]) -> HeaderValue {
+    let mut hasher = Sha256::new();
+    for collection in collections {
+        hasher.update(format!("{}:{};", collection.id, collection.version));
+    }
+    let digest: String = hasher.finalize()[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    HeaderValue::from_str(&format!("\"{}-mvt\"", digest))
+        .unwrap_or_else(|_| HeaderValue::from_static("\"0\""))
+}
+
+fn get_layered_tile_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get multi-collection tile")
+        .description(
+            "Returns a vector tile (MVT) with one layer per requested collection. \
+             Collections whose zoom range excludes the tile contribute no layer. \
+             Tiles carry an ETag of the versions of their collections for revalidation.",
+        )
+        .tag("Tiles")
+        .response_with::<200, (), _>(|res| {
+            res.description("Tile data (application/vnd.mapbox-vector-tile)")
+        })
+        .response_with::<304, (), _>(|res| res.description("Tile not modified"))
+        .response_with::<400, (), _>(|res| {
+            res.description("No collections, a non-vector collection or duplicate layer names")
+        })
+        .response_with::<404, (), _>(|res| res.description("Collection or tile not found"))
+}
+
+fn head_layered_tile_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Check multi-collection tile")
+        .description(
+            "Returns the headers of a multi-collection vector tile without rendering it. \
+             Content-Length is included once the tile has been served.",
+        )
+        .tag("Tiles")
+        .response_with::<200, (), _>(|res| res.description("Tile headers"))
+        .response_with::<304, (), _>(|res| res.description("Tile not modified"))
+        .response_with::<400, (), _>(|res| {
+            res.description("No collections, a non-vector collection or duplicate layer names")
+        })
         .response_with::<404, (), _>(|res| res.description("Collection or tile not found"))
 }
 
-pub fn routes(service: Arc<TileService>, collection_service: Arc<CollectionService>) -> ApiRouter {
+pub fn routes(service: Arc<TileService>, analytics: Arc<AnalyticsService>) -> ApiRouter {
     ApiRouter::new()
         .api_route(
             "/tileMatrixSets",
@@ -393,11 +1060,29 @@ pub fn routes(service: Arc<TileService>, collection_service: Arc<CollectionServi
         )
         .api_route(
             "/collections/{collection_id}/tiles",
-            get_with(get_tileset, get_tileset_docs),
+            get_with(list_tilesets, list_tilesets_docs),
+        )
+        .api_route(
+            "/collections/{collection_id}/tiles/{tile_matrix_set_id}",
+            get_with(get_tileset, get_tileset_docs).patch_with(patch_tileset, patch_tileset_docs),
         )
         .api_route(
             "/collections/{collection_id}/tiles/{tile_matrix_set_id}/{z}/{y}/{x}",
-            get_with(get_tile, get_tile_docs),
+            get_with(get_tile, get_tile_docs).head_with(get_tile, head_tile_docs),
+        )
+        .api_route(
+            "/tiles",
+            get_with(list_dataset_tilesets, list_dataset_tilesets_docs),
+        )
+        .api_route(
+            "/tiles/{tile_matrix_set_id}",
+            get_with(get_dataset_tileset, get_dataset_tileset_docs),
+        )
+        .api_route(
+            "/tiles/{tile_matrix_set_id}/{z}/{y}/{x}",
+            get_with(get_layered_tile, get_layered_tile_docs)
+                .head_with(get_layered_tile, head_layered_tile_docs),
         )
-        .with_state((service, collection_service))
+        .with_state(service)
+        .layer(Extension(analytics))
 }
@@ -1,5 +1,9 @@
 pub mod handlers;
+pub mod properties;
 pub mod raster;
+pub mod signed;
+pub mod style;
+pub mod terrain;
 pub mod vector;
 
 pub use handlers::*;
@@ -0,0 +1,114 @@
+/// Zoom-dependent selection of the properties encoded into vector tiles
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::vector::MAX_ZOOM;
+use crate::error::{AppError, AppResult};
+
+/// Properties encoded into vector tiles over a range of zoom levels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TilePropertyRule {
+    /// Lowest zoom level the rule applies to
+    #[serde(default)]
+    pub min_zoom: u32,
+    /// Highest zoom level the rule applies to
+    #[serde(default = "default_max_zoom")]
+    pub max_zoom: u32,
+    /// Properties to encode; an empty list encodes none
+    pub properties: Vec<String>,
+}
+
+fn default_max_zoom() -> u32 {
+    MAX_ZOOM
+}
+
+/// Check that every rule covers a valid zoom range and names its properties
+pub fn validate_property_rules(rules: &[TilePropertyRule]) -> AppResult<()> {
+    for rule in rules {
+        if rule.min_zoom > rule.max_zoom || rule.max_zoom > MAX_ZOOM {
+            return Err(AppError::BadRequest(format!(
+                "Invalid zoom range {}..={} for tile properties",
+                rule.min_zoom, rule.max_zoom
+            )));
+        }
+        if rule.properties.iter().any(|p| p.trim().is_empty()) {
+            return Err(AppError::BadRequest(
+                "Tile property names cannot be empty".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Rules stored in a collection's `tile_properties` column
+pub fn parse_property_rules(value: Option<&serde_json::Value>) -> AppResult<Vec<TilePropertyRule>> {
+    match value {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| AppError::BadRequest(format!("Invalid tile property rules: {}", e))),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Properties to encode at zoom `z`, or `None` for all of them
+///
+/// The first rule covering `z` applies.
+pub fn properties_at_zoom(rules: &[TilePropertyRule], z: u32) -> Option<Vec<String>> {
+    rules
+        .iter()
+        .find(|rule| (rule.min_zoom..=rule.max_zoom).contains(&z))
+        .map(|rule| rule.properties.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(min_zoom: u32, max_zoom: u32, properties: &[&str]) -> TilePropertyRule {
+        TilePropertyRule {
+            min_zoom,
+            max_zoom,
+            properties: properties.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_properties_at_zoom() {
+        let rules = [rule(0, 11, &[]), rule(12, 14, &["name"])];
+        assert_eq!(properties_at_zoom(&rules, 5), Some(vec![]));
+        assert_eq!(
+            properties_at_zoom(&rules, 12),
+            Some(vec!["name".to_string()])
+        );
+        assert_eq!(properties_at_zoom(&rules, 15), None);
+        assert_eq!(properties_at_zoom(&[], 15), None);
+
+        // Overlapping rules: the first one wins
+        let rules = [rule(10, 12, &["a"]), rule(0, 22, &["b"])];
+        assert_eq!(properties_at_zoom(&rules, 11), Some(vec!["a".to_string()]));
+    }
+
+    #[test]
+    fn test_validate_property_rules() {
+        assert!(validate_property_rules(&[rule(0, 11, &[]), rule(12, 22, &["name"])]).is_ok());
+        assert!(validate_property_rules(&[rule(5, 4, &[])]).is_err());
+        assert!(validate_property_rules(&[rule(0, MAX_ZOOM + 1, &[])]).is_err());
+        assert!(validate_property_rules(&[rule(0, 4, &[""])]).is_err());
+    }
+
+    #[test]
+    fn test_parse_property_rules() {
+        let value = serde_json::json!([{ "minZoom": 12, "properties": ["name"] }]);
+        assert_eq!(
+            parse_property_rules(Some(&value)).unwrap(),
+            vec![rule(12, MAX_ZOOM, &["name"])]
+        );
+        assert!(parse_property_rules(None).unwrap().is_empty());
+
+        let value = serde_json::json!([{ "minZoom": "twelve", "properties": ["name"] }]);
+        assert!(matches!(
+            parse_property_rules(Some(&value)),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+}
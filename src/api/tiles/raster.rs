@@ -1,4 +1,6 @@
 /// Raster tile rendering utilities
+use super::style::RasterStyle;
+use super::terrain::TerrainMode;
 use crate::error::{AppError, AppResult};
 
 /// Supported raster tile formats
@@ -36,6 +38,18 @@ impl RasterFormat {
     }
 }
 
+/// How raster values are turned into colors
+#[derive(Debug, Clone, Default)]
+pub enum RasterRendering {
+    /// RGB(A) for rasters with three or more bands, grayscale otherwise
+    #[default]
+    Default,
+    /// Band selection or band math with rescaling and colormaps
+    Style(RasterStyle),
+    /// The first band rendered as elevation
+    Terrain(TerrainMode),
+}
+
 /// Parameters for raster tile rendering
 #[derive(Debug, Clone)]
 pub struct RasterTileParams {
@@ -44,6 +58,7 @@ pub struct RasterTileParams {
     pub y: u32,
     pub format: RasterFormat,
     pub tile_size: u32,
+    pub rendering: RasterRendering,
 }
 
 impl Default for RasterTileParams {
@@ -54,6 +69,7 @@ impl Default for RasterTileParams {
             y: 0,
             format: RasterFormat::Png,
             tile_size: 256,
+            rendering: RasterRendering::Default,
         }
     }
 }
@@ -170,7 +186,92 @@ pub fn render_raster_tile_gdal(cog_href: &str, params: &RasterTileParams) -> App
         rgba_buffer[i * 4 + 3] = 0; // Alpha = 0
     }
 
-    if band_count >= 3 {
+    if let RasterRendering::Terrain(mode) = params.rendering {
+        use super::terrain::render_terrain;
+        use super::vector::tile_bounds_wgs84;
+
+        let band = dataset
+            .rasterband(1)
+            .map_err(|e| AppError::Processing(format!("Failed to get band: {}", e)))?;
+        let nodata = band.no_data_value();
+
+        let data: Vec<f64> = band
+            .read_as::<f64>(
+                (src_minx, src_miny),
+                (src_width, src_height),
+                (dst_width, dst_height),
+                Some(ResampleAlg::Bilinear),
+            )
+            .map_err(|e| AppError::Processing(format!("Failed to read band: {}", e)))?
+            .data()
+            .iter()
+            .map(|&v| if Some(v) == nodata { f64::NAN } else { v })
+            .collect();
+
+        // Elevations over the whole tile, NaN outside the raster
+        let mut grid = vec![f64::NAN; tile_size * tile_size];
+        for row in 0..dst_height {
+            for col in 0..dst_width {
+                let dst_idx = (row + dst_offset_y) * tile_size + col + dst_offset_x;
+                if let (Some(&value), Some(cell)) =
+                    (data.get(row * dst_width + col), grid.get_mut(dst_idx))
+                {
+                    *cell = value;
+                }
+            }
+        }
+
+        // Web Mercator stretches ground distances by 1 / cos(latitude)
+        let (_, lat_min, _, lat_max) = tile_bounds_wgs84(params.z, params.x, params.y);
+        let cell_size = (tile_maxx - tile_minx) / tile_size as f64
+            * ((lat_min + lat_max) / 2.0).to_radians().cos();
+
+        let rgba = render_terrain(mode, &grid, tile_size, cell_size);
+        return encode_image(&rgba, tile_size, tile_size, params.format);
+    }
+
+    if let RasterRendering::Style(style) = &params.rendering {
+        let style = style.clone().for_band_count(band_count as usize)?;
+
+        // Band values as f64, with nodata as NaN
+        let mut bands = Vec::with_capacity(style.source_band_count());
+        for band_idx in 1..=style.source_band_count() {
+            let band = dataset
+                .rasterband(band_idx as _)
+                .map_err(|e| AppError::Processing(format!("Failed to get band: {}", e)))?;
+            let nodata = band.no_data_value();
+
+            let data: Vec<f64> = band
+                .read_as::<f64>(
+                    (src_minx, src_miny),
+                    (src_width, src_height),
+                    (dst_width, dst_height),
+                    Some(ResampleAlg::Bilinear),
+                )
+                .map_err(|e| AppError::Processing(format!("Failed to read band: {}", e)))?
+                .data()
+                .iter()
+                .map(|&v| if Some(v) == nodata { f64::NAN } else { v })
+                .collect();
+            bands.push(data);
+        }
+
+        for row in 0..dst_height {
+            for col in 0..dst_width {
+                let src_idx = row * dst_width + col;
+                let values: Vec<f64> = bands
+                    .iter()
+                    .map(|band| band.get(src_idx).copied().unwrap_or(f64::NAN))
+                    .collect();
+                let dst_idx = ((row + dst_offset_y) * tile_size + col + dst_offset_x) * 4;
+                if let Some(rgba) = style.style_pixel(&values)
+                    && dst_idx + 3 < rgba_buffer.len()
+                {
+                    rgba_buffer[dst_idx..dst_idx + 4].copy_from_slice(&rgba);
+                }
+            }
+        }
+    } else if band_count >= 3 {
         // RGB or RGBA raster
         for (band_idx, rgba_idx) in [(1, 0), (2, 1), (3, 2)] {
             let band = dataset
@@ -273,7 +374,7 @@ pub fn render_raster_tile_gdal(cog_href: &str, params: &RasterTileParams) -> App
 
 /// Convert an S3 or HTTP URL to a GDAL VSI path
 #[cfg(feature = "gdal-support")]
-fn href_to_vsi_path(href: &str) -> String {
+pub(crate) fn href_to_vsi_path(href: &str) -> String {
     if href.starts_with("s3://") {
         // Convert s3://bucket/key to /vsis3/bucket/key
         format!("/vsis3/{}", &href[5..])
@@ -0,0 +1,336 @@
+//! Signed tile URLs
+//!
+//! The owner of a collection with public tiles can create a URL template that
+//! serves its tiles without credentials until it expires, e.g. to embed a map
+//! in another site. The signature is an HMAC of the template's path and
+//! expiry, so it covers every tile of the collection and nothing else.
+
+use aide::{
+    axum::{
+        ApiRouter,
+        routing::{get_with, post_with},
+    },
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, Query, State},
+    http::{HeaderMap, HeaderValue, Method, header},
+    response::Response,
+};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Arc;
+
+use super::handlers::{TileQueryParams, TileRequest, tile_response};
+use super::vector::tile_matrix_sets;
+use crate::api::body::JsonBody;
+use crate::api::collections::ResolvedCollection;
+use crate::auth::AuthenticatedUser;
+use crate::config::{Config, TileSigningConfig};
+use crate::error::{AppError, AppResult};
+use crate::services::{AnalyticsService, CollectionService, TileService};
+
+/// Lifetime of a signed tile URL when the request doesn't give one
+const DEFAULT_EXPIRY_SECS: u64 = 60 * 60;
+
+/// Longest time a signed tile may be cached
+const MAX_TILE_AGE_SECS: i64 = 60 * 60;
+
+/// Path of the signed tile URLs of a collection, without the tile indices
+fn signed_tiles_path(collection_id: &str, tile_matrix_set_id: &str) -> String {
+    format!(
+        "/signed/collections/{}/tiles/{}",
+        collection_id, tile_matrix_set_id
+    )
+}
+
+fn tile_mac(key: &str, path: &str, expires: i64) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(path.as_bytes());
+    mac.update(b"\n");
+    mac.update(expires.to_string().as_bytes());
+    mac
+}
+
+/// Signature of the tiles under `path` until `expires` (Unix time)
+pub fn sign_tiles(key: &str, path: &str, expires: i64) -> String {
+    URL_SAFE_NO_PAD.encode(tile_mac(key, path, expires).finalize().into_bytes())
+}
+
+/// Check a signature made by [`sign_tiles`]
+pub fn verify_tiles(key: &str, path: &str, expires: i64, signature: &str) -> bool {
+    URL_SAFE_NO_PAD.decode(signature).is_ok_and(|signature| {
+        tile_mac(key, path, expires)
+            .verify_slice(&signature)
+            .is_ok()
+    })
+}
+
+fn signing_key(config: &TileSigningConfig) -> AppResult<&str> {
+    config
+        .key
+        .as_deref()
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| AppError::BadRequest("Signed tile URLs are not enabled".to_string()))
+}
+
+/// Request for a signed tile URL template
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignTilesRequest {
+    /// Seconds until the URLs expire (default 3600)
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+}
+
+/// A signed tile URL template
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedTiles {
+    /// Tile URL template with `{tileMatrix}`, `{tileRow}` and `{tileCol}`
+    /// placeholders; tile query parameters such as `f` may be appended
+    pub tiles: String,
+    /// When the URLs stop working
+    #[schemars(with = "String")]
+    pub expires: DateTime<Utc>,
+}
+
+/// Path parameters for signing tile URLs
+#[aide::axum::typed_path]
+#[typed_path("/collections/{collection_id}/tiles/sign")]
+pub struct SignTilesPath {
+    /// The collection identifier
+    pub collection_id: String,
+}
+
+/// Create a signed tile URL template
+pub async fn sign_tile_urls(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    _path: SignTilesPath,
+    ResolvedCollection(collection): ResolvedCollection,
+    JsonBody(request): JsonBody<SignTilesRequest>,
+) -> AppResult<Json<SignedTiles>> {
+    let key = signing_key(&config.tile_signing)?;
+
+    if collection.owner != user.username {
+        return Err(AppError::Forbidden(
+            "Only owner can sign tile URLs".to_string(),
+        ));
+    }
+    if !collection.public_tiles {
+        return Err(AppError::BadRequest(
+            "Collection tiles are not public; set publicTiles on the tileset first".to_string(),
+        ));
+    }
+    if !matches!(collection.collection_type.as_str(), "vector" | "raster") {
+        return Err(AppError::BadRequest(format!(
+            "Tiles not available for {} collections",
+            collection.collection_type
+        )));
+    }
+
+    let expires_in = request.expires_in.unwrap_or(DEFAULT_EXPIRY_SECS);
+    let max_expiry_secs = config.tile_signing.max_expiry_secs;
+    if expires_in == 0 || expires_in > max_expiry_secs {
+        return Err(AppError::BadRequest(format!(
+            "expiresIn must be between 1 and {} seconds",
+            max_expiry_secs
+        )));
+    }
+    let expires = Utc::now() + chrono::Duration::seconds(expires_in as i64);
+
+    let path = signed_tiles_path(
+        &collection.canonical_name,
+        tile_matrix_sets::WEB_MERCATOR_QUAD,
+    );
+    let signature = sign_tiles(key, &path, expires.timestamp());
+
+    Ok(Json(SignedTiles {
+        tiles: format!(
+            "{}{}/{{tileMatrix}}/{{tileRow}}/{{tileCol}}?expires={}&signature={}",
+            config.base_url,
+            path,
+            expires.timestamp(),
+            signature
+        ),
+        expires,
+    }))
+}
+
+fn sign_tile_urls_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Sign tile URLs")
+        .description(
+            "Creates a tile URL template that serves the collection's tiles without \
+             credentials until it expires, e.g. to embed a map in another site. The \
+             collection's tileset must have publicTiles set; unsetting it revokes all signed \
+             URLs of the collection. Only the owner may sign tile URLs.",
+        )
+        .tag("Tiles")
+        .response_with::<200, Json<SignedTiles>, _>(|res| {
+            res.description("Signed tile URL template")
+        })
+        .response_with::<400, (), _>(|res| {
+            res.description("Tiles are not public, invalid expiry or signing is not enabled")
+        })
+        .response_with::<403, (), _>(|res| res.description("Only the owner may sign tile URLs"))
+        .response_with::<404, (), _>(|res| res.description("Collection not found"))
+}
+
+/// Signature query parameters of a signed tile URL
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SignatureParams {
+    /// Unix time the URL expires at
+    pub expires: i64,
+    /// Signature of the URL
+    pub signature: String,
+}
+
+/// Path parameters for signed tiles
+#[aide::axum::typed_path]
+#[typed_path("/signed/collections/{collection_id}/tiles/{tile_matrix_set_id}/{z}/{y}/{x}")]
+pub struct SignedTilePath {
+    /// The collection identifier
+    pub collection_id: String,
+    /// The tile matrix set identifier (e.g., WebMercatorQuad)
+    pub tile_matrix_set_id: String,
+    /// Zoom level
+    pub z: u32,
+    /// Row (y) coordinate
+    pub y: u32,
+    /// Column (x) coordinate
+    pub x: u32,
+}
+
+/// Get a tile through a signed URL
+#[allow(clippy::too_many_arguments)]
+pub async fn get_signed_tile(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(collection_service): Extension<Arc<CollectionService>>,
+    Extension(analytics): Extension<Arc<AnalyticsService>>,
+    State(service): State<Arc<TileService>>,
+    path: SignedTilePath,
+    Query(signature): Query<SignatureParams>,
+    Query(params): Query<TileQueryParams>,
+    method: Method,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let key = signing_key(&config.tile_signing)?;
+
+    // The signature is checked before anything about the collection is revealed
+    let signed_path = signed_tiles_path(&path.collection_id, &path.tile_matrix_set_id);
+    if !verify_tiles(key, &signed_path, signature.expires, &signature.signature) {
+        return Err(AppError::Forbidden("Invalid tile signature".to_string()));
+    }
+    let remaining_secs = signature.expires - Utc::now().timestamp();
+    if remaining_secs <= 0 {
+        return Err(AppError::Forbidden(
+            "Signed tile URL has expired".to_string(),
+        ));
+    }
+
+    let collection = collection_service
+        .get_collection("", &path.collection_id)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound(format!("Collection not found: {}", path.collection_id))
+        })?;
+    if !collection.public_tiles {
+        return Err(AppError::Forbidden(
+            "Collection tiles are no longer public".to_string(),
+        ));
+    }
+
+    // Tiles are rendered with the owner's access, as the owner signed the URL
+    let request = TileRequest {
+        tile_matrix_set_id: &path.tile_matrix_set_id,
+        z: path.z,
+        y: path.y,
+        x: path.x,
+        params: &params,
+        method: &method,
+        headers: &headers,
+    };
+    let mut response = tile_response(
+        &service,
+        &analytics,
+        &config.cache,
+        &collection.owner,
+        &collection,
+        request,
+    )
+    .await?;
+
+    // Caches must not serve the tile after the URL has expired
+    let max_age = remaining_secs.min(MAX_TILE_AGE_SECS);
+    if let Ok(value) = HeaderValue::from_str(&format!("public, max-age={}", max_age)) {
+        response.headers_mut().insert(header::CACHE_CONTROL, value);
+    }
+
+    Ok(response)
+}
+
+fn get_signed_tile_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get signed tile")
+        .description(
+            "Returns a tile of a collection with public tiles through a URL from the sign \
+             endpoint; no credentials are needed. Accepts the same query parameters as the \
+             tile endpoint.",
+        )
+        .tag("Tiles")
+        .response_with::<200, (), _>(|res| {
+            res.description("Tile data (application/vnd.mapbox-vector-tile or image/*)")
+        })
+        .response_with::<403, (), _>(|res| {
+            res.description("Invalid or expired signature, or the tiles are no longer public")
+        })
+        .response_with::<404, (), _>(|res| res.description("Collection or tile not found"))
+}
+
+/// Routes that need authentication
+pub fn routes() -> ApiRouter {
+    ApiRouter::new().api_route(
+        "/collections/{collection_id}/tiles/sign",
+        post_with(sign_tile_urls, sign_tile_urls_docs),
+    )
+}
+
+/// Signed tile routes, served without authentication
+pub fn public_routes(service: Arc<TileService>, analytics: Arc<AnalyticsService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/signed/collections/{collection_id}/tiles/{tile_matrix_set_id}/{z}/{y}/{x}",
+            get_with(get_signed_tile, get_signed_tile_docs),
+        )
+        .with_state(service)
+        .layer(Extension(analytics))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify_tiles() {
+        let path = signed_tiles_path("alice:roads", "WebMercatorQuad");
+        let signature = sign_tiles("secret", &path, 1_700_000_000);
+
+        assert!(verify_tiles("secret", &path, 1_700_000_000, &signature));
+        assert!(!verify_tiles("secret", &path, 1_700_000_001, &signature));
+        assert!(!verify_tiles("other", &path, 1_700_000_000, &signature));
+        assert!(!verify_tiles(
+            "secret",
+            &signed_tiles_path("alice:parcels", "WebMercatorQuad"),
+            1_700_000_000,
+            &signature
+        ));
+        assert!(!verify_tiles("secret", &path, 1_700_000_000, "not base64!"));
+    }
+}
@@ -0,0 +1,591 @@
+/// Raster tile styling: band selection, band math, rescaling and colormaps
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::error::{AppError, AppResult};
+
+/// Styling query parameters for raster tiles
+#[derive(Debug, Default, Clone, Deserialize, JsonSchema)]
+pub struct RasterStyleParams {
+    /// Colormap for single-band output: gray, viridis, magma, blues or rdylgn
+    pub colormap_name: Option<String>,
+    /// Value range mapped onto the output colors, as `min,max` (default `0,255`)
+    pub rescale: Option<String>,
+    /// Comma separated 1-based band indexes to render (one or three)
+    pub bidx: Option<String>,
+    /// Band math over `b1`, `b2`, ...; separate three expressions with `;` for RGB
+    pub expression: Option<String>,
+}
+
+impl RasterStyleParams {
+    /// Parse the parameters, or `None` when no styling was requested
+    pub fn parse(&self) -> AppResult<Option<RasterStyle>> {
+        if self.colormap_name.is_none()
+            && self.rescale.is_none()
+            && self.bidx.is_none()
+            && self.expression.is_none()
+        {
+            return Ok(None);
+        }
+
+        let bands = match (&self.bidx, &self.expression) {
+            (Some(_), Some(_)) => {
+                return Err(AppError::BadRequest(
+                    "bidx and expression cannot be combined".to_string(),
+                ));
+            }
+            (Some(bidx), None) => Bands::Indexes(parse_bidx(bidx)?),
+            (None, Some(expression)) => Bands::Expressions(
+                expression
+                    .split(';')
+                    .map(Expr::parse)
+                    .collect::<AppResult<_>>()?,
+            ),
+            // A colormap needs a single band
+            (None, None) if self.colormap_name.is_some() => Bands::Indexes(vec![1]),
+            (None, None) => Bands::Default,
+        };
+        if let Some(count) = bands.output_count()
+            && count != 1
+            && count != 3
+        {
+            return Err(AppError::BadRequest(
+                "Raster styling produces one or three bands".to_string(),
+            ));
+        }
+
+        let colormap =
+            match &self.colormap_name {
+                Some(name) => {
+                    if bands.output_count() != Some(1) {
+                        return Err(AppError::BadRequest(
+                            "colormap_name requires a single band".to_string(),
+                        ));
+                    }
+                    Some(colormap(name).ok_or_else(|| {
+                        AppError::BadRequest(format!("Unknown colormap: {}", name))
+                    })?)
+                }
+                None => None,
+            };
+
+        let rescale = match &self.rescale {
+            Some(rescale) => parse_rescale(rescale)?,
+            None => (0.0, 255.0),
+        };
+
+        Ok(Some(RasterStyle {
+            bands,
+            rescale,
+            colormap,
+        }))
+    }
+}
+
+/// Bands rendered by a style
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bands {
+    /// RGB for rasters with three or more bands, otherwise the first band
+    Default,
+    /// Band indexes (1-based)
+    Indexes(Vec<usize>),
+    /// One band per expression
+    Expressions(Vec<Expr>),
+}
+
+impl Bands {
+    fn output_count(&self) -> Option<usize> {
+        match self {
+            Bands::Default => None,
+            Bands::Indexes(indexes) => Some(indexes.len()),
+            Bands::Expressions(expressions) => Some(expressions.len()),
+        }
+    }
+}
+
+/// Parsed raster style
+#[derive(Debug, Clone)]
+pub struct RasterStyle {
+    pub bands: Bands,
+    pub rescale: (f64, f64),
+    pub colormap: Option<&'static [[u8; 3]]>,
+}
+
+impl RasterStyle {
+    /// Resolve the default bands and check the style against a raster
+    pub fn for_band_count(mut self, band_count: usize) -> AppResult<Self> {
+        if self.bands == Bands::Default {
+            self.bands = Bands::Indexes(if band_count >= 3 {
+                vec![1, 2, 3]
+            } else {
+                vec![1]
+            });
+        }
+
+        let required = self.source_band_count();
+        if required > band_count {
+            return Err(AppError::BadRequest(format!(
+                "Band {} requested but the raster has {} bands",
+                required, band_count
+            )));
+        }
+        Ok(self)
+    }
+
+    /// Highest band index the style reads
+    pub fn source_band_count(&self) -> usize {
+        match &self.bands {
+            Bands::Default => 0,
+            Bands::Indexes(indexes) => indexes.iter().copied().max().unwrap_or(0),
+            Bands::Expressions(expressions) => {
+                expressions.iter().map(Expr::max_band).max().unwrap_or(0)
+            }
+        }
+    }
+
+    /// RGBA color of a pixel given its band values, or `None` if it has no data
+    ///
+    /// `values[0]` is band 1. NaN marks a band without data at the pixel.
+    pub fn style_pixel(&self, values: &[f64]) -> Option<[u8; 4]> {
+        let outputs: Vec<f64> = match &self.bands {
+            Bands::Default => return None,
+            Bands::Indexes(indexes) => indexes
+                .iter()
+                .map(|index| values.get(index - 1).copied().unwrap_or(f64::NAN))
+                .collect(),
+            Bands::Expressions(expressions) => expressions.iter().map(|e| e.eval(values)).collect(),
+        };
+        if outputs.iter().any(|value| !value.is_finite()) {
+            return None;
+        }
+
+        let (min, max) = self.rescale;
+        let scaled: Vec<f64> = outputs
+            .iter()
+            .map(|value| ((value - min) / (max - min)).clamp(0.0, 1.0))
+            .collect();
+        let to_u8 = |t: f64| (t * 255.0).round() as u8;
+
+        match (scaled.as_slice(), self.colormap) {
+            ([t], Some(stops)) => {
+                let [r, g, b] = color_at(stops, *t);
+                Some([r, g, b, 255])
+            }
+            ([t], None) => {
+                let v = to_u8(*t);
+                Some([v, v, v, 255])
+            }
+            ([r, g, b], _) => Some([to_u8(*r), to_u8(*g), to_u8(*b), 255]),
+            _ => None,
+        }
+    }
+}
+
+fn parse_bidx(bidx: &str) -> AppResult<Vec<usize>> {
+    bidx.split(',')
+        .map(|index| match index.trim().parse::<usize>() {
+            Ok(index) if index > 0 => Ok(index),
+            _ => Err(AppError::BadRequest(format!(
+                "Invalid band index: {}",
+                index
+            ))),
+        })
+        .collect()
+}
+
+fn parse_rescale(rescale: &str) -> AppResult<(f64, f64)> {
+    let invalid = || AppError::BadRequest(format!("Invalid rescale range: {}", rescale));
+    let (min, max) = rescale.split_once(',').ok_or_else(invalid)?;
+    let min: f64 = min.trim().parse().map_err(|_| invalid())?;
+    let max: f64 = max.trim().parse().map_err(|_| invalid())?;
+    if !min.is_finite() || !max.is_finite() || min >= max {
+        return Err(invalid());
+    }
+    Ok((min, max))
+}
+
+/// Colormap stops, evenly spaced from the low to the high end of the range
+fn colormap(name: &str) -> Option<&'static [[u8; 3]]> {
+    const GRAY: &[[u8; 3]] = &[[0, 0, 0], [255, 255, 255]];
+    const VIRIDIS: &[[u8; 3]] = &[
+        [68, 1, 84],
+        [72, 40, 120],
+        [62, 73, 137],
+        [49, 104, 142],
+        [38, 130, 142],
+        [31, 158, 137],
+        [53, 183, 121],
+        [110, 206, 88],
+        [181, 222, 43],
+        [253, 231, 37],
+    ];
+    const MAGMA: &[[u8; 3]] = &[
+        [0, 0, 4],
+        [24, 15, 61],
+        [68, 15, 118],
+        [114, 31, 129],
+        [158, 47, 127],
+        [205, 64, 113],
+        [241, 96, 93],
+        [253, 150, 104],
+        [254, 202, 141],
+        [252, 253, 191],
+    ];
+    const BLUES: &[[u8; 3]] = &[
+        [247, 251, 255],
+        [222, 235, 247],
+        [198, 219, 239],
+        [158, 202, 225],
+        [107, 174, 214],
+        [66, 146, 198],
+        [33, 113, 181],
+        [8, 81, 156],
+        [8, 48, 107],
+    ];
+    const RDYLGN: &[[u8; 3]] = &[
+        [165, 0, 38],
+        [215, 48, 39],
+        [244, 109, 67],
+        [253, 174, 97],
+        [254, 224, 139],
+        [255, 255, 191],
+        [217, 239, 139],
+        [166, 217, 106],
+        [102, 189, 99],
+        [26, 152, 80],
+        [0, 104, 55],
+    ];
+
+    match name.to_ascii_lowercase().as_str() {
+        "gray" | "grey" => Some(GRAY),
+        "viridis" => Some(VIRIDIS),
+        "magma" => Some(MAGMA),
+        "blues" => Some(BLUES),
+        "rdylgn" => Some(RDYLGN),
+        _ => None,
+    }
+}
+
+/// Linearly interpolated color at `t` (0..=1) along the stops
+fn color_at(stops: &[[u8; 3]], t: f64) -> [u8; 3] {
+    let position = t * (stops.len() - 1) as f64;
+    let index = (position.floor() as usize).min(stops.len() - 2);
+    let fraction = position - index as f64;
+    let (low, high) = (stops[index], stops[index + 1]);
+    std::array::from_fn(|i| {
+        (low[i] as f64 + (high[i] as f64 - low[i] as f64) * fraction).round() as u8
+    })
+}
+
+/// Band math expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    /// 1-based band index
+    Band(usize),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, BinaryOp, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Expr {
+    /// Parse an arithmetic expression over `b1`, `b2`, ...
+    pub fn parse(input: &str) -> AppResult<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+        let expr = parser.expr()?;
+        if parser.position != tokens.len() {
+            return Err(AppError::BadRequest(format!(
+                "Unexpected input in expression: {}",
+                input
+            )));
+        }
+        Ok(expr)
+    }
+
+    /// Evaluate against band values (`bands[0]` is `b1`)
+    pub fn eval(&self, bands: &[f64]) -> f64 {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::Band(index) => bands.get(index - 1).copied().unwrap_or(f64::NAN),
+            Expr::Neg(expr) => -expr.eval(bands),
+            Expr::Binary(left, op, right) => {
+                let (left, right) = (left.eval(bands), right.eval(bands));
+                match op {
+                    BinaryOp::Add => left + right,
+                    BinaryOp::Sub => left - right,
+                    BinaryOp::Mul => left * right,
+                    BinaryOp::Div => left / right,
+                }
+            }
+        }
+    }
+
+    /// Highest band index referenced
+    pub fn max_band(&self) -> usize {
+        match self {
+            Expr::Number(_) => 0,
+            Expr::Band(index) => *index,
+            Expr::Neg(expr) => expr.max_band(),
+            Expr::Binary(left, _, right) => left.max_band().max(right.max_band()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Band(usize),
+    Op(char),
+    Open,
+    Close,
+}
+
+fn tokenize(input: &str) -> AppResult<Vec<Token>> {
+    let invalid = || AppError::BadRequest(format!("Invalid expression: {}", input));
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '+' | '-' | '*' | '/' => {
+                tokens.push(Token::Op(c));
+                chars.next();
+            }
+            '(' => {
+                tokens.push(Token::Open);
+                chars.next();
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                chars.next();
+            }
+            'b' | 'B' => {
+                chars.next();
+                let mut digits = String::new();
+                while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    digits.push(d);
+                    chars.next();
+                }
+                match digits.parse::<usize>() {
+                    Ok(index) if index > 0 => tokens.push(Token::Band(index)),
+                    _ => return Err(invalid()),
+                }
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut number = String::new();
+                while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit() || **d == '.') {
+                    number.push(d);
+                    chars.next();
+                }
+                tokens.push(Token::Number(number.parse().map_err(|_| invalid())?));
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    if tokens.is_empty() {
+        return Err(invalid());
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser with the usual arithmetic precedence
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn peek_op(&self, ops: &[char]) -> Option<char> {
+        match self.tokens.get(self.position) {
+            Some(Token::Op(op)) if ops.contains(op) => Some(*op),
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self) -> AppResult<Expr> {
+        let mut left = self.term()?;
+        while let Some(op) = self.peek_op(&['+', '-']) {
+            self.position += 1;
+            let op = if op == '+' {
+                BinaryOp::Add
+            } else {
+                BinaryOp::Sub
+            };
+            left = Expr::Binary(Box::new(left), op, Box::new(self.term()?));
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> AppResult<Expr> {
+        let mut left = self.factor()?;
+        while let Some(op) = self.peek_op(&['*', '/']) {
+            self.position += 1;
+            let op = if op == '*' {
+                BinaryOp::Mul
+            } else {
+                BinaryOp::Div
+            };
+            left = Expr::Binary(Box::new(left), op, Box::new(self.factor()?));
+        }
+        Ok(left)
+    }
+
+    fn factor(&mut self) -> AppResult<Expr> {
+        match self.next().cloned() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::Band(index)) => Ok(Expr::Band(index)),
+            Some(Token::Op('-')) => Ok(Expr::Neg(Box::new(self.factor()?))),
+            Some(Token::Open) => {
+                let expr = self.expr()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err(AppError::BadRequest(
+                        "Unbalanced parentheses in expression".to_string(),
+                    )),
+                }
+            }
+            _ => Err(AppError::BadRequest("Incomplete expression".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(
+        colormap_name: Option<&str>,
+        rescale: Option<&str>,
+        bidx: Option<&str>,
+        expression: Option<&str>,
+    ) -> RasterStyleParams {
+        RasterStyleParams {
+            colormap_name: colormap_name.map(str::to_string),
+            rescale: rescale.map(str::to_string),
+            bidx: bidx.map(str::to_string),
+            expression: expression.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_expression() {
+        let ndvi = Expr::parse("(b4-b3)/(b4+b3)").unwrap();
+        assert_eq!(ndvi.max_band(), 4);
+        assert!((ndvi.eval(&[0.0, 0.0, 0.1, 0.5]) - 0.4 / 0.6).abs() < 1e-9);
+
+        // Precedence and unary minus
+        assert_eq!(Expr::parse("1 + 2 * b1").unwrap().eval(&[3.0]), 7.0);
+        assert_eq!(Expr::parse("-(b1 - 4) / 2").unwrap().eval(&[2.0]), 1.0);
+
+        assert!(Expr::parse("").is_err());
+        assert!(Expr::parse("b0").is_err());
+        assert!(Expr::parse("(b1 + 2").is_err());
+        assert!(Expr::parse("b1 +").is_err());
+        assert!(Expr::parse("b1 b2").is_err());
+        assert!(Expr::parse("sqrt(b1)").is_err());
+    }
+
+    #[test]
+    fn test_parse_style() {
+        assert!(params(None, None, None, None).parse().unwrap().is_none());
+
+        let style = params(Some("viridis"), Some("-1,1"), None, Some("(b4-b3)/(b4+b3)"))
+            .parse()
+            .unwrap()
+            .unwrap();
+        assert_eq!(style.rescale, (-1.0, 1.0));
+        assert_eq!(style.source_band_count(), 4);
+
+        // A colormap alone renders the first band
+        let style = params(Some("gray"), None, None, None)
+            .parse()
+            .unwrap()
+            .unwrap();
+        assert_eq!(style.bands, Bands::Indexes(vec![1]));
+
+        assert!(params(None, None, Some("1"), Some("b1")).parse().is_err());
+        assert!(
+            params(Some("viridis"), None, Some("1,2,3"), None)
+                .parse()
+                .is_err()
+        );
+        assert!(params(Some("nope"), None, None, None).parse().is_err());
+        assert!(params(None, None, Some("1,2"), None).parse().is_err());
+        assert!(params(None, None, Some("0"), None).parse().is_err());
+        assert!(params(None, Some("5,1"), None, None).parse().is_err());
+        assert!(params(None, Some("1"), None, None).parse().is_err());
+    }
+
+    #[test]
+    fn test_for_band_count() {
+        let style = params(None, Some("0,1000"), None, None)
+            .parse()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            style.clone().for_band_count(4).unwrap().bands,
+            Bands::Indexes(vec![1, 2, 3])
+        );
+        assert_eq!(
+            style.for_band_count(1).unwrap().bands,
+            Bands::Indexes(vec![1])
+        );
+
+        let style = params(None, None, Some("5"), None)
+            .parse()
+            .unwrap()
+            .unwrap();
+        assert!(style.for_band_count(4).is_err());
+    }
+
+    #[test]
+    fn test_style_pixel() {
+        let style = params(Some("rdylgn"), Some("-1,1"), None, Some("(b2-b1)/(b2+b1)"))
+            .parse()
+            .unwrap()
+            .unwrap();
+        assert_eq!(style.style_pixel(&[1.0, 0.0]), Some([165, 0, 38, 255]));
+        assert_eq!(style.style_pixel(&[0.0, 1.0]), Some([0, 104, 55, 255]));
+        // 0/0 and nodata are transparent
+        assert_eq!(style.style_pixel(&[0.0, 0.0]), None);
+        assert_eq!(style.style_pixel(&[f64::NAN, 1.0]), None);
+
+        let style = params(None, Some("0,1000"), Some("3,2,1"), None)
+            .parse()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            style.style_pixel(&[0.0, 500.0, 2000.0]),
+            Some([255, 128, 0, 255])
+        );
+    }
+
+    #[test]
+    fn test_color_at() {
+        let stops = colormap("gray").unwrap();
+        assert_eq!(color_at(stops, 0.0), [0, 0, 0]);
+        assert_eq!(color_at(stops, 0.5), [128, 128, 128]);
+        assert_eq!(color_at(stops, 1.0), [255, 255, 255]);
+    }
+}
@@ -0,0 +1,225 @@
+/// Terrain rendering of elevation rasters: Terrain-RGB and hillshade
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::error::{AppError, AppResult};
+
+/// Terrain query parameters for raster tiles
+#[derive(Debug, Default, Clone, Deserialize, JsonSchema)]
+pub struct TerrainParams {
+    /// Render the first band as elevation: `terrain-rgb` or `hillshade`
+    pub algorithm: Option<String>,
+    /// Hillshade sun azimuth in degrees clockwise from north (default 315)
+    pub azimuth: Option<String>,
+    /// Hillshade sun altitude in degrees above the horizon (default 45)
+    pub altitude: Option<String>,
+}
+
+/// How elevation is rendered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TerrainMode {
+    /// Mapbox Terrain-RGB: `height = -10000 + (R * 65536 + G * 256 + B) * 0.1`
+    TerrainRgb,
+    /// Grayscale shaded relief lit from the given sun position (degrees)
+    Hillshade { azimuth: f64, altitude: f64 },
+}
+
+impl TerrainParams {
+    /// Parse the parameters, or `None` when no terrain rendering was requested
+    pub fn parse(&self) -> AppResult<Option<TerrainMode>> {
+        let Some(algorithm) = &self.algorithm else {
+            if self.azimuth.is_some() || self.altitude.is_some() {
+                return Err(AppError::BadRequest(
+                    "azimuth and altitude require algorithm=hillshade".to_string(),
+                ));
+            }
+            return Ok(None);
+        };
+
+        match algorithm.as_str() {
+            "terrain-rgb" => Ok(Some(TerrainMode::TerrainRgb)),
+            "hillshade" => Ok(Some(TerrainMode::Hillshade {
+                azimuth: parse_degrees("azimuth", self.azimuth.as_deref(), 315.0, 360.0)?,
+                altitude: parse_degrees("altitude", self.altitude.as_deref(), 45.0, 90.0)?,
+            })),
+            _ => Err(AppError::BadRequest(format!(
+                "Unknown algorithm: {}",
+                algorithm
+            ))),
+        }
+    }
+}
+
+fn parse_degrees(name: &str, value: Option<&str>, default: f64, max: f64) -> AppResult<f64> {
+    let Some(value) = value else {
+        return Ok(default);
+    };
+    match value.trim().parse::<f64>() {
+        Ok(degrees) if (0.0..=max).contains(&degrees) => Ok(degrees),
+        _ => Err(AppError::BadRequest(format!(
+            "{} must be between 0 and {}",
+            name, max
+        ))),
+    }
+}
+
+/// Render a square elevation grid as RGBA
+///
+/// `grid` holds `size * size` elevations in row-major order, NaN where there
+/// is no data, and `cell_size` is the ground distance between pixels in
+/// meters. No-data pixels are transparent. Hillshade at the tile edges
+/// repeats the edge values, since neighboring tiles are not read.
+pub fn render_terrain(mode: TerrainMode, grid: &[f64], size: usize, cell_size: f64) -> Vec<u8> {
+    let mut rgba = vec![0u8; size * size * 4];
+    for (index, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+        let color = match mode {
+            TerrainMode::TerrainRgb => terrain_rgb(grid[index]),
+            TerrainMode::Hillshade { azimuth, altitude } => {
+                hillshade(grid, size, index, cell_size, azimuth, altitude)
+                    .map(|shade| [shade, shade, shade])
+            }
+        };
+        if let Some([r, g, b]) = color {
+            pixel.copy_from_slice(&[r, g, b, 255]);
+        }
+    }
+    rgba
+}
+
+/// Mapbox Terrain-RGB encoding of an elevation in meters (0.1 m precision)
+pub fn terrain_rgb(elevation: f64) -> Option<[u8; 3]> {
+    if !elevation.is_finite() {
+        return None;
+    }
+    let value = ((elevation + 10000.0) * 10.0)
+        .round()
+        .clamp(0.0, 16_777_215.0) as u32;
+    Some([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
+
+/// Hillshade of one pixel using Horn's slope and aspect
+fn hillshade(
+    grid: &[f64],
+    size: usize,
+    index: usize,
+    cell_size: f64,
+    azimuth: f64,
+    altitude: f64,
+) -> Option<u8> {
+    let center = grid[index];
+    if !center.is_finite() {
+        return None;
+    }
+
+    let (row, col) = ((index / size) as isize, (index % size) as isize);
+    // Neighbors clamped to the grid, falling back to the center without data
+    let at = |dr: isize, dc: isize| {
+        let r = (row + dr).clamp(0, size as isize - 1) as usize;
+        let c = (col + dc).clamp(0, size as isize - 1) as usize;
+        let value = grid[r * size + c];
+        if value.is_finite() { value } else { center }
+    };
+    let (a, b, c) = (at(-1, -1), at(-1, 0), at(-1, 1));
+    let (d, f) = (at(0, -1), at(0, 1));
+    let (g, h, i) = (at(1, -1), at(1, 0), at(1, 1));
+
+    let dz_dx = ((c + 2.0 * f + i) - (a + 2.0 * d + g)) / (8.0 * cell_size);
+    let dz_dy = ((g + 2.0 * h + i) - (a + 2.0 * b + c)) / (8.0 * cell_size);
+
+    let slope = (dz_dx * dz_dx + dz_dy * dz_dy).sqrt().atan();
+    let aspect = dz_dy.atan2(-dz_dx);
+    let zenith = (90.0 - altitude).to_radians();
+    let sun = (360.0 - azimuth + 90.0).rem_euclid(360.0).to_radians();
+
+    let shade = zenith.cos() * slope.cos() + zenith.sin() * slope.sin() * (sun - aspect).cos();
+    Some((255.0 * shade.max(0.0)).round() as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(
+        algorithm: Option<&str>,
+        azimuth: Option<&str>,
+        altitude: Option<&str>,
+    ) -> TerrainParams {
+        TerrainParams {
+            algorithm: algorithm.map(str::to_string),
+            azimuth: azimuth.map(str::to_string),
+            altitude: altitude.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_parse_terrain_params() {
+        assert_eq!(params(None, None, None).parse().unwrap(), None);
+        assert_eq!(
+            params(Some("terrain-rgb"), None, None).parse().unwrap(),
+            Some(TerrainMode::TerrainRgb)
+        );
+        assert_eq!(
+            params(Some("hillshade"), Some("270"), None)
+                .parse()
+                .unwrap(),
+            Some(TerrainMode::Hillshade {
+                azimuth: 270.0,
+                altitude: 45.0
+            })
+        );
+
+        assert!(params(Some("slope"), None, None).parse().is_err());
+        assert!(
+            params(Some("hillshade"), Some("400"), None)
+                .parse()
+                .is_err()
+        );
+        assert!(params(Some("hillshade"), None, Some("-5")).parse().is_err());
+        assert!(params(None, Some("315"), None).parse().is_err());
+    }
+
+    #[test]
+    fn test_terrain_rgb() {
+        assert_eq!(terrain_rgb(0.0), Some([1, 134, 160]));
+
+        let decode = |[r, g, b]: [u8; 3]| {
+            -10000.0 + ((r as u32 * 65536 + g as u32 * 256 + b as u32) as f64) * 0.1
+        };
+        for elevation in [-412.3, 0.0, 8848.8] {
+            let decoded = decode(terrain_rgb(elevation).unwrap());
+            assert!(
+                (decoded - elevation).abs() < 0.05,
+                "{} != {}",
+                decoded,
+                elevation
+            );
+        }
+        assert_eq!(terrain_rgb(f64::NAN), None);
+    }
+
+    #[test]
+    fn test_hillshade() {
+        let sun = TerrainMode::Hillshade {
+            azimuth: 270.0,
+            altitude: 45.0,
+        };
+
+        // Flat terrain is lit by the sine of the sun's altitude
+        let flat = render_terrain(sun, &[100.0; 9], 3, 10.0);
+        assert_eq!(flat[4 * 4], 180);
+
+        // A 45 degree slope rising to the east faces a western sun
+        let ramp: Vec<f64> = (0..9).map(|i| (i % 3) as f64 * 10.0).collect();
+        assert_eq!(render_terrain(sun, &ramp, 3, 10.0)[4 * 4], 255);
+        let eastern_sun = TerrainMode::Hillshade {
+            azimuth: 90.0,
+            altitude: 45.0,
+        };
+        assert_eq!(render_terrain(eastern_sun, &ramp, 3, 10.0)[4 * 4], 0);
+
+        // No data is transparent
+        let mut grid = [100.0; 9];
+        grid[4] = f64::NAN;
+        assert_eq!(render_terrain(sun, &grid, 3, 10.0)[4 * 4 + 3], 0);
+    }
+}
@@ -1,6 +1,14 @@
 /// Vector tile (MVT) generation utilities
+use std::ops::RangeInclusive;
+
 use crate::error::{AppError, AppResult};
 
+/// Highest zoom level tiles are served at
+pub const MAX_ZOOM: u32 = 22;
+
+/// Latitude beyond which Web Mercator is undefined
+const MAX_WEB_MERCATOR_LAT: f64 = 85.051_128_779_806_59;
+
 /// Common TileMatrixSet definitions
 pub mod tile_matrix_sets {
     pub const WEB_MERCATOR_QUAD: &str = "WebMercatorQuad";
@@ -39,7 +47,39 @@ pub fn tile_bounds_wgs84(z: u32, x: u32, y: u32) -> (f64, f64, f64, f64) {
     (lon_min, lat_min, lon_max, lat_max)
 }
 
+/// WebMercatorQuad tiles covering a CRS84 bounding box at zoom `z`
+///
+/// Returns `(min_col, min_row, max_col, max_row)`. A bounding box crossing the
+/// antimeridian covers all columns.
+pub fn tile_range_web_mercator(z: u32, bbox: [f64; 4]) -> (u32, u32, u32, u32) {
+    let n = 2_u32.pow(z);
+    let last = (n - 1) as f64;
+
+    let col = |lon: f64| ((lon + 180.0) / 360.0 * n as f64).floor().clamp(0.0, last) as u32;
+    let row = |lat: f64| {
+        let lat = lat
+            .clamp(-MAX_WEB_MERCATOR_LAT, MAX_WEB_MERCATOR_LAT)
+            .to_radians();
+        ((1.0 - lat.tan().asinh() / std::f64::consts::PI) / 2.0 * n as f64)
+            .floor()
+            .clamp(0.0, last) as u32
+    };
+
+    let [minx, miny, maxx, maxy] = bbox;
+    let (min_col, max_col) = if minx > maxx {
+        (0, n - 1)
+    } else {
+        (col(minx), col(maxx))
+    };
+
+    // Rows count down from the north
+    (min_col, row(maxy), max_col, row(miny))
+}
+
 /// Generate ST_AsMVT SQL for a tile
+///
+/// The layer name is bound as `$1` and the properties to encode as `$2`
+/// (`NULL` for all of them).
 pub fn mvt_sql(
     schema: &str,
     table: &str,
@@ -72,14 +112,19 @@ pub fn mvt_sql(
                     256,
                     true
                 ) AS geom,
-                t.properties
+                CASE WHEN $2::text[] IS NULL THEN t.properties
+                ELSE (
+                    SELECT COALESCE(jsonb_object_agg(key, value), '{{}}'::jsonb)
+                    FROM jsonb_each(t.properties)
+                    WHERE key = ANY($2)
+                ) END AS properties
             FROM "{schema}"."{table}" t, bounds
             WHERE ST_Intersects(
                 {geom_transform},
                 bounds.geom
             )
         )
-        SELECT ST_AsMVT(mvtgeom.*, '{table}', 4096, 'geom') AS mvt
+        SELECT ST_AsMVT(mvtgeom.*, $1, 4096, 'geom') AS mvt
         FROM mvtgeom
         "#,
         minx = minx,
@@ -92,12 +137,21 @@ pub fn mvt_sql(
     )
 }
 
+/// Zoom levels between optional collection overrides
+pub fn zoom_range(min_zoom: Option<i32>, max_zoom: Option<i32>) -> RangeInclusive<u32> {
+    let min = min_zoom.map_or(0, |zoom| zoom as u32);
+    let max = max_zoom.map_or(MAX_ZOOM, |zoom| zoom as u32);
+    min..=max
+}
+
 /// Validate tile coordinates
-pub fn validate_tile_coords(z: u32, x: u32, y: u32, max_zoom: u32) -> AppResult<()> {
-    if z > max_zoom {
-        return Err(AppError::BadRequest(format!(
-            "Zoom level {} exceeds maximum {}",
-            z, max_zoom
+pub fn validate_tile_coords(z: u32, x: u32, y: u32, zooms: &RangeInclusive<u32>) -> AppResult<()> {
+    if !zooms.contains(&z) {
+        return Err(AppError::NotFound(format!(
+            "Zoom level {} outside {}..={}",
+            z,
+            zooms.start(),
+            zooms.end()
         )));
     }
 
@@ -112,6 +166,39 @@ pub fn validate_tile_coords(z: u32, x: u32, y: u32, max_zoom: u32) -> AppResult<
     Ok(())
 }
 
+/// Check that a tile lies within the tile matrix set limits of the data in
+/// `bbox`, as listed in the tileset metadata
+pub fn validate_tile_in_bbox(z: u32, x: u32, y: u32, bbox: [f64; 4]) -> AppResult<()> {
+    let (min_col, min_row, max_col, max_row) = tile_range_web_mercator(z, bbox);
+    if !(min_col..=max_col).contains(&x) || !(min_row..=max_row).contains(&y) {
+        return Err(AppError::NotFound(format!(
+            "Tile {}/{}/{} outside the tile matrix set limits",
+            z, x, y
+        )));
+    }
+
+    Ok(())
+}
+
+/// Smallest tile within `zooms` covering all of `bbox`, as `(z, x, y)`
+///
+/// When no tile at the lowest zoom covers the box, the tile at its center is
+/// returned.
+pub fn preview_tile(bbox: [f64; 4], zooms: &RangeInclusive<u32>) -> (u32, u32, u32) {
+    for z in zooms.clone().rev() {
+        let (min_col, min_row, max_col, max_row) = tile_range_web_mercator(z, bbox);
+        if min_col == max_col && min_row == max_row {
+            return (z, min_col, min_row);
+        }
+    }
+
+    let z = *zooms.start();
+    let [minx, miny, maxx, maxy] = bbox;
+    let (lon, lat) = ((minx + maxx) / 2.0, (miny + maxy) / 2.0);
+    let (x, y, _, _) = tile_range_web_mercator(z, [lon, lat, lon, lat]);
+    (z, x, y)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,9 +212,68 @@ mod tests {
 
     #[test]
     fn test_validate_tile_coords() {
-        assert!(validate_tile_coords(0, 0, 0, 22).is_ok());
-        assert!(validate_tile_coords(1, 1, 1, 22).is_ok());
-        assert!(validate_tile_coords(1, 2, 0, 22).is_err()); // x out of bounds
-        assert!(validate_tile_coords(25, 0, 0, 22).is_err()); // z too high
+        let zooms = 0..=MAX_ZOOM;
+        assert!(validate_tile_coords(0, 0, 0, &zooms).is_ok());
+        assert!(validate_tile_coords(1, 1, 1, &zooms).is_ok());
+        assert!(validate_tile_coords(1, 2, 0, &zooms).is_err()); // x out of bounds
+        assert!(validate_tile_coords(25, 0, 0, &zooms).is_err()); // z too high
+        assert!(matches!(
+            validate_tile_coords(2, 0, 0, &(4..=10)),
+            Err(AppError::NotFound(_))
+        )); // z too low
+    }
+
+    #[test]
+    fn test_validate_tile_in_bbox() {
+        let bbox = [10.0, 10.0, 20.0, 20.0];
+        assert!(validate_tile_in_bbox(0, 0, 0, bbox).is_ok());
+        assert!(validate_tile_in_bbox(1, 1, 0, bbox).is_ok());
+        assert!(matches!(
+            validate_tile_in_bbox(1, 0, 0, bbox),
+            Err(AppError::NotFound(_))
+        )); // western column
+        assert!(validate_tile_in_bbox(1, 1, 1, bbox).is_err()); // southern row
+    }
+
+    #[test]
+    fn test_preview_tile() {
+        let zooms = 0..=MAX_ZOOM;
+        // Split between columns from zoom 5
+        assert_eq!(preview_tile([10.0, 10.0, 20.0, 20.0], &zooms), (4, 8, 7));
+        assert_eq!(preview_tile([10.0, 10.0, 20.0, 20.0], &(0..=2)), (2, 2, 1));
+        assert_eq!(
+            preview_tile([18.06, 59.33, 18.07, 59.34], &(0..=10)),
+            (10, 563, 301)
+        );
+
+        // Boxes too large for a tile at the lowest zoom show their center
+        let world = [-180.0, -90.0, 180.0, 90.0];
+        assert_eq!(preview_tile(world, &zooms), (0, 0, 0));
+        assert_eq!(preview_tile(world, &(2..=5)), (2, 2, 2));
+    }
+
+    #[test]
+    fn test_tile_range_web_mercator() {
+        let world = [-180.0, -90.0, 180.0, 90.0];
+        assert_eq!(tile_range_web_mercator(0, world), (0, 0, 0, 0));
+        assert_eq!(tile_range_web_mercator(3, world), (0, 0, 7, 7));
+
+        // North-eastern quadrant
+        assert_eq!(
+            tile_range_web_mercator(1, [10.0, 10.0, 20.0, 20.0]),
+            (1, 0, 1, 0)
+        );
+
+        // Central Stockholm lies within tile 10/301/563 (z/row/col)
+        assert_eq!(
+            tile_range_web_mercator(10, [18.06, 59.33, 18.07, 59.34]),
+            (563, 301, 563, 301)
+        );
+
+        // Crossing the antimeridian
+        assert_eq!(
+            tile_range_web_mercator(2, [170.0, -10.0, -170.0, 10.0]),
+            (0, 1, 3, 2)
+        );
     }
 }
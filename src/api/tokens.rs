@@ -0,0 +1,151 @@
+//! Exchange of access tokens for scoped tokens
+//!
+//! A scoped token acts as the user but only for the requested collections,
+//! actions and processes, so it can be handed to less trusted clients such
+//! as a web map embed.
+
+use aide::{
+    axum::{ApiRouter, routing::post_with},
+    transform::TransformOperation,
+};
+use axum::{Extension, Json, extract::State};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::api::body::JsonBody;
+use crate::auth::{AccessScope, AuthenticatedUser, ScopedTokens};
+use crate::error::{AppError, AppResult};
+use crate::openapi;
+
+/// Lifetime of a scoped token when the request doesn't give one
+pub const DEFAULT_EXPIRY_SECS: u64 = 60 * 60;
+
+/// Actions a scoped token can be restricted to
+pub(crate) const ACTIONS: [&str; 3] = ["read", "write", "delete"];
+
+/// Request for a scoped token; empty lists don't restrict
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenRequest {
+    /// Collections the token may access, by id; a trailing `*` matches any
+    /// id starting with the rest
+    #[serde(default)]
+    pub collections: Vec<String>,
+    /// Actions the token may perform: `read`, `write` and `delete`
+    #[serde(default)]
+    pub actions: Vec<String>,
+    /// Processes the token may execute
+    #[serde(default)]
+    pub processes: Vec<String>,
+    /// Seconds until the token expires (default 3600)
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+}
+
+/// A scoped token
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopedToken {
+    /// Bearer token to send in the Authorization header
+    pub access_token: String,
+    /// Always `Bearer`
+    pub token_type: String,
+    /// When the token stops working
+    #[schemars(with = "String")]
+    pub expires: DateTime<Utc>,
+}
+
+/// Exchange the access token for a scoped token
+pub async fn create_token(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(tokens): State<Option<Arc<ScopedTokens>>>,
+    JsonBody(request): JsonBody<TokenRequest>,
+) -> AppResult<Json<ScopedToken>> {
+    // The validating instance, whose issuer doesn't vary with the request's
+    // base URL
+    let tokens =
+        tokens.ok_or_else(|| AppError::BadRequest("Scoped tokens are not enabled".to_string()))?;
+
+    // A restricted token can't be widened again
+    if user.scope.is_some() {
+        return Err(AppError::Forbidden(
+            "Scoped tokens and service accounts can't create tokens".to_string(),
+        ));
+    }
+    if let Some(action) = request
+        .actions
+        .iter()
+        .find(|action| !ACTIONS.contains(&action.as_str()))
+    {
+        return Err(AppError::BadRequest(format!(
+            "Unknown action: {} (supported: read, write, delete)",
+            action
+        )));
+    }
+    if request.collections.iter().any(String::is_empty) {
+        return Err(AppError::BadRequest(
+            "Collection ids must not be empty".to_string(),
+        ));
+    }
+
+    let expires_in = request.expires_in.unwrap_or(DEFAULT_EXPIRY_SECS);
+    let max_expiry_secs = tokens.max_expiry_secs();
+    if expires_in == 0 || expires_in > max_expiry_secs {
+        return Err(AppError::BadRequest(format!(
+            "expiresIn must be between 1 and {} seconds",
+            max_expiry_secs
+        )));
+    }
+    let expires = Utc::now() + chrono::Duration::seconds(expires_in as i64);
+
+    let scope = AccessScope {
+        collections: request.collections,
+        actions: request.actions,
+        processes: request.processes,
+    };
+    let access_token = tokens.issue(&user, &scope, expires)?;
+
+    Ok(Json(ScopedToken {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires,
+    }))
+}
+
+fn create_token_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Create scoped token")
+        .description(
+            "Exchanges the access token for a token that acts as the same user, but only \
+             for the given collections, actions and processes, e.g. for a web map embed \
+             that should only read tiles of some collections. The user's own grants still \
+             apply. A token limited to some collections can't list or search across \
+             collections, or read jobs of processes or collections outside its scope. \
+             Scoped tokens can't create further tokens.",
+        )
+        .tag("Sharing")
+        .with(|op| {
+            openapi::request_example(
+                op,
+                serde_json::json!({
+                    "collections": ["roads", "parks"],
+                    "actions": ["read"],
+                    "expiresIn": 86400
+                }),
+            )
+        })
+        .response_with::<200, Json<ScopedToken>, _>(|res| res.description("Scoped token"))
+        .response_with::<400, (), _>(|res| {
+            res.description("Invalid scope or expiry, or scoped tokens are not enabled")
+        })
+        .response_with::<403, (), _>(|res| {
+            res.description("The request was made with a restricted token")
+        })
+}
+
+pub fn routes(tokens: Option<Arc<ScopedTokens>>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route("/tokens", post_with(create_token, create_token_docs))
+        .with_state(tokens)
+}
@@ -0,0 +1,279 @@
+use aide::{
+    axum::{
+        ApiRouter,
+        routing::{get_with, post_with, put_with},
+    },
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::{DefaultBodyLimit, Extension, State},
+    http::{HeaderMap, StatusCode, header},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::common::{Link, media_type, rel};
+use crate::auth::AuthenticatedUser;
+use crate::config::Config;
+use crate::db::{Upload, UploadPart};
+use crate::error::{AppError, AppResult};
+use crate::services::UploadService;
+use crate::services::upload_service::UPLOAD_SCHEME;
+
+/// Request to start an upload
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateUploadRequest {
+    /// Media type of the file, used when it is imported
+    pub media_type: Option<String>,
+}
+
+/// A part received for an upload
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadPartResponse {
+    pub part_number: i32,
+    pub size: i64,
+    pub etag: String,
+}
+
+impl From<UploadPart> for UploadPartResponse {
+    fn from(part: UploadPart) -> Self {
+        Self {
+            part_number: part.part_number,
+            size: part.size,
+            etag: part.etag,
+        }
+    }
+}
+
+/// Upload status
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadResponse {
+    pub id: String,
+    /// Reference to pass as a process input href once the upload is complete
+    pub href: String,
+    /// `open` while parts are accepted, `complete` once assembled
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    /// Total size once complete
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<i64>,
+    /// Parts received so far; resend any that are missing to resume
+    pub parts: Vec<UploadPartResponse>,
+    pub created: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed: Option<String>,
+    pub links: Vec<Link>,
+}
+
+fn upload_response(upload: Upload, parts: Vec<UploadPart>, base_url: &str) -> UploadResponse {
+    UploadResponse {
+        id: upload.id.to_string(),
+        href: format!("{}{}", UPLOAD_SCHEME, upload.id),
+        status: upload.status,
+        media_type: upload.media_type,
+        size: upload.size,
+        parts: parts.into_iter().map(Into::into).collect(),
+        created: upload.created_at.to_rfc3339(),
+        completed: upload.completed_at.map(|dt| dt.to_rfc3339()),
+        links: vec![
+            Link::new(format!("{}/uploads/{}", base_url, upload.id), rel::SELF)
+                .with_type(media_type::JSON),
+        ],
+    }
+}
+
+/// Start a chunked upload
+pub async fn create_upload(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<UploadService>>,
+    Json(request): Json<CreateUploadRequest>,
+) -> AppResult<(StatusCode, HeaderMap, Json<UploadResponse>)> {
+    let upload = service
+        .create_upload(&user.username, request.media_type.as_deref())
+        .await?;
+
+    let mut headers = HeaderMap::new();
+    if let Ok(location) = format!("{}/uploads/{}", config.base_url, upload.id).parse() {
+        headers.insert(header::LOCATION, location);
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        headers,
+        Json(upload_response(upload, Vec::new(), &config.base_url)),
+    ))
+}
+
+fn create_upload_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Start upload")
+        .description(
+            "Starts a chunked upload for a file too large to send in a single request. Send \
+             the file in numbered parts, complete the upload, then pass its `upload://{id}` \
+             href as a process input.",
+        )
+        .tag("Uploads")
+        .response_with::<201, Json<UploadResponse>, _>(|res| res.description("Upload started"))
+}
+
+/// Path parameters for an upload
+#[aide::axum::typed_path]
+#[typed_path("/uploads/{upload_id}")]
+pub struct UploadPath {
+    /// The upload UUID
+    pub upload_id: Uuid,
+}
+
+/// Get the status of an upload and the parts received
+pub async fn get_upload(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<UploadService>>,
+    path: UploadPath,
+) -> AppResult<Json<UploadResponse>> {
+    let upload = service.get_upload(&user.username, path.upload_id).await?;
+    let parts = service.list_parts(upload.id).await?;
+    Ok(Json(upload_response(upload, parts, &config.base_url)))
+}
+
+fn get_upload_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get upload")
+        .description("Returns the status of an upload and the parts received so far")
+        .tag("Uploads")
+        .response_with::<200, Json<UploadResponse>, _>(|res| res.description("Upload status"))
+        .response_with::<404, (), _>(|res| res.description("Upload not found"))
+}
+
+/// Delete an upload
+pub async fn delete_upload(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<UploadService>>,
+    path: UploadPath,
+) -> AppResult<StatusCode> {
+    service
+        .delete_upload(&user.username, path.upload_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn delete_upload_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Delete upload")
+        .description("Aborts an open upload, or deletes the file of a completed one")
+        .tag("Uploads")
+        .response_with::<204, (), _>(|res| res.description("Upload deleted"))
+        .response_with::<404, (), _>(|res| res.description("Upload not found"))
+}
+
+/// Path parameters for an upload part
+#[aide::axum::typed_path]
+#[typed_path("/uploads/{upload_id}/parts/{part_number}")]
+pub struct UploadPartPath {
+    /// The upload UUID
+    pub upload_id: Uuid,
+    /// Part number, from 1
+    pub part_number: i32,
+}
+
+/// Upload one part of a file
+pub async fn put_part(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<UploadService>>,
+    path: UploadPartPath,
+    body: Body,
+) -> AppResult<Json<UploadPartResponse>> {
+    let limit = config.processing.max_upload_part_bytes;
+    let data = axum::body::to_bytes(body, limit).await.map_err(|_| {
+        AppError::BadRequest(format!(
+            "Upload part exceeds the maximum size of {} bytes",
+            limit
+        ))
+    })?;
+
+    let part = service
+        .put_part(
+            &user.username,
+            path.upload_id,
+            path.part_number,
+            data,
+            config.processing.max_upload_bytes,
+        )
+        .await?;
+    Ok(Json(part.into()))
+}
+
+fn put_part_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Upload part")
+        .description(
+            "Uploads one part of the file as the raw request body. Parts are numbered from 1 \
+             in file order; every part but the last must be at least 5 MiB. Sending a part \
+             again replaces it, so failed parts can simply be retried.",
+        )
+        .tag("Uploads")
+        .response_with::<200, Json<UploadPartResponse>, _>(|res| res.description("Part stored"))
+        .response_with::<400, (), _>(|res| {
+            res.description("Invalid part number, or the part or upload is too large")
+        })
+        .response_with::<404, (), _>(|res| res.description("Upload not found"))
+        .response_with::<409, (), _>(|res| res.description("Upload is already complete"))
+}
+
+/// Path parameters for completing an upload
+#[aide::axum::typed_path]
+#[typed_path("/uploads/{upload_id}/complete")]
+pub struct CompleteUploadPath {
+    /// The upload UUID
+    pub upload_id: Uuid,
+}
+
+/// Assemble the parts of an upload
+pub async fn complete_upload(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<UploadService>>,
+    path: CompleteUploadPath,
+) -> AppResult<Json<UploadResponse>> {
+    let upload = service
+        .complete_upload(&user.username, path.upload_id)
+        .await?;
+    let parts = service.list_parts(upload.id).await?;
+    Ok(Json(upload_response(upload, parts, &config.base_url)))
+}
+
+fn complete_upload_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Complete upload")
+        .description(
+            "Assembles the parts into the file. After this the upload's href can be used as a \
+             process input. Completing an upload twice is harmless.",
+        )
+        .tag("Uploads")
+        .response_with::<200, Json<UploadResponse>, _>(|res| res.description("Upload complete"))
+        .response_with::<400, (), _>(|res| res.description("Parts are missing or too small"))
+        .response_with::<404, (), _>(|res| res.description("Upload not found"))
+}
+
+pub fn routes(service: Arc<UploadService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route("/uploads", post_with(create_upload, create_upload_docs))
+        .api_route(
+            "/uploads/{upload_id}",
+            get_with(get_upload, get_upload_docs).delete_with(delete_upload, delete_upload_docs),
+        )
+        .api_route(
+            "/uploads/{upload_id}/parts/{part_number}",
+            put_with(put_part, put_part_docs).layer(DefaultBodyLimit::disable()),
+        )
+        .api_route(
+            "/uploads/{upload_id}/complete",
+            post_with(complete_upload, complete_upload_docs),
+        )
+        .with_state(service)
+}
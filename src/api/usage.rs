@@ -0,0 +1,102 @@
+//! Storage usage of the current user's namespaces
+//!
+//! The namespaces are the user's own and those of the groups the user
+//! belongs to; quotas are configured per namespace, see
+//! [`crate::config::QuotaConfig`].
+
+use aide::{
+    axum::{ApiRouter, routing::get_with},
+    transform::TransformOperation,
+};
+use axum::{
+    Json,
+    extract::{Extension, State},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::auth::AuthenticatedUser;
+use crate::db::StorageUsage;
+use crate::error::AppResult;
+use crate::services::QuotaService;
+
+/// Storage used by a namespace or a collection
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsageResponse {
+    /// Bytes used by assets, as recorded in their `file:size`
+    pub asset_bytes: i64,
+    /// Bytes used by feature tables and their indexes
+    pub table_bytes: i64,
+    /// Bytes used in total
+    pub used_bytes: i64,
+}
+
+impl From<StorageUsage> for StorageUsageResponse {
+    fn from(usage: StorageUsage) -> Self {
+        Self {
+            asset_bytes: usage.asset_bytes,
+            table_bytes: usage.table_bytes,
+            used_bytes: usage.total_bytes(),
+        }
+    }
+}
+
+/// Storage used by a namespace and its quota
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NamespaceUsage {
+    /// User or group name
+    pub namespace: String,
+    #[serde(flatten)]
+    pub usage: StorageUsageResponse,
+    /// Quota in bytes; absent when the namespace is unlimited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quota_bytes: Option<u64>,
+}
+
+/// Storage used by the current user's namespaces
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct UsageResponse {
+    /// The user's own namespace first, then those of the user's groups
+    pub namespaces: Vec<NamespaceUsage>,
+}
+
+/// Storage usage and quotas of the current user's namespaces
+pub async fn get_usage(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(service): State<Arc<QuotaService>>,
+) -> AppResult<Json<UsageResponse>> {
+    let mut namespaces = Vec::with_capacity(1 + user.groups.len());
+    for namespace in std::iter::once(&user.username).chain(&user.groups) {
+        let usage = service.namespace_usage(namespace).await?;
+        namespaces.push(NamespaceUsage {
+            namespace: namespace.clone(),
+            usage: usage.into(),
+            quota_bytes: service.quota(namespace),
+        });
+    }
+    Ok(Json(UsageResponse { namespaces }))
+}
+
+fn get_usage_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get storage usage")
+        .description(
+            "Returns the storage used by the collections owned by the current user and by \
+             each of the user's groups, with the quota of each namespace when one is \
+             configured. Usage is the size of the assets of the collections and their items \
+             plus the size of the feature tables with their indexes. Feature writes and \
+             imports into a namespace at or over its quota are rejected with 507.",
+        )
+        .tag("Collections")
+        .response_with::<200, Json<UsageResponse>, _>(|res| {
+            res.description("Storage usage per namespace")
+        })
+}
+
+pub fn routes(service: Arc<QuotaService>) -> ApiRouter {
+    ApiRouter::new()
+        .api_route("/usage", get_with(get_usage, get_usage_docs))
+        .with_state(service)
+}
@@ -0,0 +1,119 @@
+use aide::OperationInput;
+use axum::{
+    extract::FromRequestParts,
+    http::{HeaderMap, HeaderValue, StatusCode, header, request::Parts},
+    response::Response,
+};
+use std::convert::Infallible;
+
+/// The preference asking for a write to be validated but not committed
+const VALIDATE_ONLY: &str = "handling=validate-only";
+
+/// Whether the client only wants a write validated, from
+/// `Prefer: handling=validate-only` or `dryRun=true`
+///
+/// A validated write runs everything the real write would, including the
+/// database constraints, and is then rolled back.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ValidateOnly(pub bool);
+
+impl ValidateOnly {
+    pub fn from_request(headers: &HeaderMap, query: Option<&str>) -> Self {
+        let preferred = headers
+            .get_all("Prefer")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|preference| {
+                preference
+                    .split(';')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .replace('"', "")
+                    .eq_ignore_ascii_case(VALIDATE_ONLY)
+            });
+        let dry_run = query.is_some_and(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .any(|(key, value)| key == "dryRun" && value == "true")
+        });
+        Self(preferred || dry_run)
+    }
+
+    /// The response to send for a write
+    ///
+    /// Nothing was created by a validated write, so 201 becomes 200 and the
+    /// `Location` header is dropped.
+    pub fn respond(self, mut response: Response) -> Response {
+        if !self.0 {
+            return response;
+        }
+        if response.status() == StatusCode::CREATED {
+            *response.status_mut() = StatusCode::OK;
+        }
+        let headers = response.headers_mut();
+        headers.remove(header::LOCATION);
+        headers.insert(
+            "Preference-Applied",
+            HeaderValue::from_static(VALIDATE_ONLY),
+        );
+        response
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ValidateOnly {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_request(&parts.headers, parts.uri.query()))
+    }
+}
+
+impl OperationInput for ValidateOnly {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+
+    fn prefer(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("Prefer", value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_validate_only_from_request() {
+        assert!(ValidateOnly::from_request(&prefer("handling=validate-only"), None).0);
+        assert!(
+            ValidateOnly::from_request(&prefer("return=minimal, handling=\"validate-only\""), None)
+                .0
+        );
+        assert!(!ValidateOnly::from_request(&prefer("handling=strict"), None).0);
+        assert!(ValidateOnly::from_request(&HeaderMap::new(), Some("f=json&dryRun=true")).0);
+        assert!(!ValidateOnly::from_request(&HeaderMap::new(), Some("dryRun=false")).0);
+        assert!(!ValidateOnly::from_request(&HeaderMap::new(), None).0);
+    }
+
+    #[test]
+    fn test_validate_only_response() {
+        let created = || {
+            (
+                StatusCode::CREATED,
+                [(header::LOCATION, "http://localhost/collections/a")],
+            )
+                .into_response()
+        };
+
+        let response = ValidateOnly(true).respond(created());
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(header::LOCATION).is_none());
+        assert_eq!(
+            response.headers()["Preference-Applied"],
+            "handling=validate-only"
+        );
+
+        let response = ValidateOnly(false).respond(created());
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+}
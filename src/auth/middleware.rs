@@ -1,24 +1,72 @@
 use axum::{
-    extract::{Request, State},
-    http::StatusCode,
+    extract::{MatchedPath, RawPathParams, Request, State, rejection::RawPathParamsRejection},
+    http::{Method, StatusCode},
     middleware::Next,
     response::Response,
 };
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
-use super::{AuthenticatedUser, OidcValidator};
-use crate::error::AppError;
+use super::policy::{self, PolicyEvaluator, PolicyInput};
+use super::{AuthenticatedUser, Claims, OidcValidator, ScopedTokens};
+use crate::config::ServiceAccount;
+use crate::error::{AppError, AppResult};
 
 #[derive(Clone)]
 pub struct AuthState {
     pub validator: Arc<OidcValidator>,
+    /// External policy consulted after authentication, if configured
+    pub policy: Option<Arc<PolicyEvaluator>>,
+    /// Service accounts by OIDC client ID
+    pub service_accounts: Arc<BTreeMap<String, ServiceAccount>>,
+    /// Validator of scoped tokens, if exchange is enabled
+    pub scoped_tokens: Option<Arc<ScopedTokens>>,
 }
 
+impl AuthState {
+    /// User of a bearer token, either a scoped token or an OIDC one
+    pub async fn authenticate(&self, token: &str) -> AppResult<AuthenticatedUser> {
+        match &self.scoped_tokens {
+            Some(scoped_tokens) if ScopedTokens::is_scoped(token) => {
+                scoped_tokens.validate(token).await
+            }
+            _ => {
+                let claims = self.validator.validate_token(token).await?;
+                Ok(self.user_from_claims(&claims))
+            }
+        }
+    }
+
+    /// User of validated claims, acting as a service account if the token
+    /// was issued to one
+    pub fn user_from_claims(&self, claims: &Claims) -> AuthenticatedUser {
+        match claims
+            .client()
+            .and_then(|client_id| self.service_accounts.get_key_value(client_id))
+        {
+            Some((client_id, account)) => {
+                AuthenticatedUser::for_service_account(client_id, account, claims)
+            }
+            None => AuthenticatedUser::from_claims(claims),
+        }
+    }
+}
+
+/// Path parameters naming the collection a request is about
+const COLLECTION_PARAMS: [&str; 2] = ["collection_id", "catalogue_id"];
+
 pub async fn auth_middleware(
     State(auth): State<AuthState>,
+    matched_path: Option<MatchedPath>,
+    params: Result<RawPathParams, RawPathParamsRejection>,
     mut request: Request,
     next: Next,
 ) -> Result<Response, (StatusCode, String)> {
+    // No route handles OPTIONS; let it through so the allowed methods are reported
+    if request.method() == Method::OPTIONS {
+        return Ok(next.run(request).await);
+    }
+
     // Extract Authorization header
     let auth_header = request
         .headers()
@@ -35,23 +83,82 @@ pub async fn auth_middleware(
         }
     };
 
-    // Validate token
-    let claims = auth
-        .validator
-        .validate_token(token)
-        .await
-        .map_err(|e| match e {
-            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
-            _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    // Validate token and create the authenticated user
+    let user = auth.authenticate(token).await.map_err(|e| match e {
+        AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })?;
+    let span = tracing::Span::current();
+    span.record("user", user.username.as_str());
+    if let Some(service_account) = &user.service_account {
+        span.record("service_account", service_account.as_str());
+    }
+
+    let collection = path_collection(&params);
+    check_scope(&user, &request, collection)?;
+
+    if let Some(evaluator) = &auth.policy {
+        let input = PolicyInput {
+            user: (&user).into(),
+            action: policy::action(request.method()),
+            method: request.method().as_str(),
+            path: request.uri().path(),
+            route: matched_path.as_ref().map(MatchedPath::as_str),
+            collection,
+            query: request.uri().query(),
+        };
+        let allowed = evaluator.is_allowed(&input).await.map_err(|e| {
+            tracing::error!("Policy evaluation failed: {}", e);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Authorization policy unavailable".to_string(),
+            )
         })?;
+        if !allowed {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "Denied by authorization policy".to_string(),
+            ));
+        }
+    }
 
-    // Create authenticated user and insert into request extensions
-    let user = AuthenticatedUser::from_claims(&claims);
     request.extensions_mut().insert(user);
 
     Ok(next.run(request).await)
 }
 
+/// Collection a request is about, from its path parameters
+pub fn path_collection(params: &Result<RawPathParams, RawPathParamsRejection>) -> Option<&str> {
+    params.as_ref().ok().and_then(|params| {
+        params
+            .iter()
+            .find(|(key, _)| COLLECTION_PARAMS.contains(key))
+            .map(|(_, value)| value)
+    })
+}
+
+/// Fail unless the user's scope, if any, allows a request
+pub fn check_scope(
+    user: &AuthenticatedUser,
+    request: &Request,
+    collection: Option<&str>,
+) -> Result<(), (StatusCode, String)> {
+    let Some(scope) = &user.scope else {
+        return Ok(());
+    };
+    scope
+        .check_request(
+            &user.username,
+            policy::action(request.method()),
+            request.uri().path(),
+            collection,
+        )
+        .map_err(|e| match e {
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
 /// Optional auth middleware - doesn't fail if no token present
 pub async fn optional_auth_middleware(
     State(auth): State<AuthState>,
@@ -64,8 +171,7 @@ pub async fn optional_auth_middleware(
         .and_then(|h| h.to_str().ok())
     {
         if let Some(token) = auth_header.strip_prefix("Bearer ") {
-            if let Ok(claims) = auth.validator.validate_token(token).await {
-                let user = AuthenticatedUser::from_claims(&claims);
+            if let Ok(user) = auth.authenticate(token).await {
                 request.extensions_mut().insert(user);
             }
         }
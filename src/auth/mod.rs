@@ -1,7 +1,12 @@
 pub mod middleware;
 pub mod oidc;
+pub mod policy;
 pub mod role_manager;
+pub mod scope;
+pub mod scoped_token;
 
 pub use middleware::*;
 pub use oidc::*;
 pub use role_manager::*;
+pub use scope::*;
+pub use scoped_token::*;
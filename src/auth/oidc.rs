@@ -7,7 +7,9 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::config::OidcConfig;
+use super::AccessScope;
+use crate::config::{OidcConfig, ServiceAccount};
+use crate::db::ProcessJob;
 use crate::error::{AppError, AppResult};
 
 // Create an async HTTP client for openidconnect
@@ -27,6 +29,19 @@ pub struct Claims {
     pub email: Option<String>,
     #[serde(default)]
     pub groups: Option<Vec<String>>,
+    /// Client a client credentials token was issued to
+    #[serde(default)]
+    pub client_id: Option<String>,
+}
+
+impl Claims {
+    /// OAuth2 client a client credentials token was issued to
+    ///
+    /// `azp` is not used: providers also set it on tokens of users logging
+    /// in through a client, who must not act as its service account.
+    pub fn client(&self) -> Option<&str> {
+        self.client_id.as_deref()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +68,8 @@ pub struct OidcValidator {
     config: OidcConfig,
     jwks: Arc<RwLock<Option<jsonwebtoken::jwk::JwkSet>>>,
     client: Option<CoreClient>,
+    /// Authorization and token endpoints from discovery
+    endpoints: Option<(String, String)>,
 }
 
 impl OidcValidator {
@@ -69,6 +86,13 @@ impl OidcValidator {
             .await
             .map_err(|e| AppError::Config(format!("OIDC discovery failed: {}", e)))?;
 
+        let endpoints = provider_metadata.token_endpoint().map(|token_url| {
+            (
+                provider_metadata.authorization_endpoint().to_string(),
+                token_url.to_string(),
+            )
+        });
+
         // Fetch JWKS
         let jwks_uri = provider_metadata.jwks_uri();
 
@@ -85,6 +109,7 @@ impl OidcValidator {
             config,
             jwks: Arc::new(RwLock::new(Some(jwks))),
             client: None, // Client not needed for token validation
+            endpoints,
         })
     }
 
@@ -94,9 +119,17 @@ impl OidcValidator {
             config,
             jwks: Arc::new(RwLock::new(None)),
             client: None,
+            endpoints: None,
         }
     }
 
+    /// OAuth2 authorization and token endpoints of the provider
+    pub fn oauth2_endpoints(&self) -> Option<(&str, &str)> {
+        self.endpoints
+            .as_ref()
+            .map(|(authorization_url, token_url)| (authorization_url.as_str(), token_url.as_str()))
+    }
+
     pub async fn validate_token(&self, token: &str) -> AppResult<Claims> {
         let header = decode_header(token)
             .map_err(|e| AppError::Unauthorized(format!("Invalid token header: {}", e)))?;
@@ -142,6 +175,10 @@ pub struct AuthenticatedUser {
     pub username: String,
     pub subject: String,
     pub groups: Vec<String>,
+    /// Client ID of the service account the token belongs to
+    pub service_account: Option<String>,
+    /// Restrictions beyond the user's grants
+    pub scope: Option<AccessScope>,
 }
 
 impl AuthenticatedUser {
@@ -150,6 +187,70 @@ impl AuthenticatedUser {
             username: OidcValidator::extract_username(claims),
             subject: claims.sub.clone(),
             groups: claims.groups.clone().unwrap_or_default(),
+            service_account: None,
+            scope: None,
+        }
+    }
+
+    /// User of a client credentials token of a configured service account
+    pub fn for_service_account(client_id: &str, account: &ServiceAccount, claims: &Claims) -> Self {
+        Self {
+            username: account
+                .username
+                .clone()
+                .unwrap_or_else(|| client_id.to_string()),
+            subject: claims.sub.clone(),
+            groups: account.groups.clone(),
+            service_account: Some(client_id.to_string()),
+            scope: Some(account.scope.clone()),
+        }
+    }
+
+    /// Fail unless the user's scope allows accessing a collection
+    pub fn check_collection(&self, collection: &str) -> AppResult<()> {
+        match &self.scope {
+            Some(scope) if !scope.allows_collection(&self.username, collection) => {
+                Err(AppError::Forbidden(format!(
+                    "The token may not access collection {}",
+                    collection
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail unless the user's scope allows executing a process
+    pub fn check_process(&self, process_id: &str) -> AppResult<()> {
+        match &self.scope {
+            Some(scope) if !scope.allows_process(process_id) => Err(AppError::Forbidden(format!(
+                "The token may not execute process {}",
+                process_id
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail unless the user's scope allows the collections process inputs
+    /// refer to
+    pub fn check_inputs(&self, inputs: &serde_json::Value, base_url: &str) -> AppResult<()> {
+        match &self.scope {
+            Some(scope) => scope.check_inputs(&self.username, inputs, base_url),
+            None => Ok(()),
+        }
+    }
+
+    /// Fail unless the user's scope allows accessing a job
+    pub fn check_job(&self, job: &ProcessJob) -> AppResult<()> {
+        match &self.scope {
+            Some(scope)
+                if !scope.allows_job(&self.username, &job.process_id, job.inputs.as_ref()) =>
+            {
+                Err(AppError::Forbidden(format!(
+                    "The token may not access job {}",
+                    job.id
+                )))
+            }
+            _ => Ok(()),
         }
     }
 }
@@ -0,0 +1,172 @@
+//! External authorization policies
+//!
+//! Owner and share grants decide what a user can reach; a policy engine can
+//! further restrict it. Before each authenticated request, its user, action,
+//! route and collection are posted to an Open Policy Agent data API, and the
+//! request is only handled when the decision allows it.
+
+use axum::http::Method;
+use serde::Serialize;
+use std::time::Duration;
+
+use super::AuthenticatedUser;
+use crate::config::PolicyConfig;
+use crate::error::{AppError, AppResult};
+
+/// Input document of a policy decision
+#[derive(Debug, Serialize)]
+pub struct PolicyInput<'a> {
+    pub user: PolicyUser<'a>,
+    /// `read`, `write` or `delete`
+    pub action: &'static str,
+    pub method: &'a str,
+    /// Request path, e.g. `/collections/alice:roads/items`
+    pub path: &'a str,
+    /// Route template, e.g. `/collections/{collection_id}/items`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route: Option<&'a str>,
+    /// Collection the request is about, as named in the path
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<&'a str>,
+}
+
+/// The user of a policy decision
+#[derive(Debug, Serialize)]
+pub struct PolicyUser<'a> {
+    pub username: &'a str,
+    pub subject: &'a str,
+    pub groups: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_account: Option<&'a str>,
+}
+
+impl<'a> From<&'a AuthenticatedUser> for PolicyUser<'a> {
+    fn from(user: &'a AuthenticatedUser) -> Self {
+        Self {
+            username: &user.username,
+            subject: &user.subject,
+            groups: &user.groups,
+            service_account: user.service_account.as_deref(),
+        }
+    }
+}
+
+/// Action of a request method
+pub fn action(method: &Method) -> &'static str {
+    match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS => "read",
+        Method::DELETE => "delete",
+        _ => "write",
+    }
+}
+
+/// Evaluates requests against the configured policy engine
+pub struct PolicyEvaluator {
+    url: String,
+    token: Option<String>,
+    fail_open: bool,
+    client: reqwest::Client,
+}
+
+impl PolicyEvaluator {
+    /// Evaluator for the configured policy engine, if any
+    pub fn new(config: &PolicyConfig) -> AppResult<Option<Self>> {
+        let Some(url) = &config.url else {
+            return Ok(None);
+        };
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .map_err(|e| AppError::Config(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(Some(Self {
+            url: url.clone(),
+            token: config.token.clone(),
+            fail_open: config.fail_open,
+            client,
+        }))
+    }
+
+    /// Whether the policy allows the request
+    ///
+    /// When the engine can't be asked, the request is allowed only if the
+    /// policy is configured to fail open.
+    pub async fn is_allowed(&self, input: &PolicyInput<'_>) -> AppResult<bool> {
+        match self.evaluate(input).await {
+            Ok(allowed) => Ok(allowed),
+            Err(e) if self.fail_open => {
+                tracing::warn!("Policy evaluation failed, allowing request: {}", e);
+                Ok(true)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn evaluate(&self, input: &PolicyInput<'_>) -> AppResult<bool> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .json(&serde_json::json!({ "input": input }));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AppError::Upstream(format!("Policy engine request failed: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(AppError::Upstream(format!(
+                "Policy engine returned {}",
+                response.status()
+            )));
+        }
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| AppError::Upstream(format!("Invalid policy engine response: {}", e)))?;
+        Ok(decision(&body))
+    }
+}
+
+/// Decision of an OPA data API response
+///
+/// The rule may be a boolean (`{"result": true}`) or an object with an
+/// `allow` boolean; an undefined decision denies.
+pub fn decision(response: &serde_json::Value) -> bool {
+    match response.get("result") {
+        Some(serde_json::Value::Bool(allowed)) => *allowed,
+        Some(result) => result
+            .get("allow")
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decision() {
+        assert!(decision(&serde_json::json!({ "result": true })));
+        assert!(decision(
+            &serde_json::json!({ "result": { "allow": true } })
+        ));
+        assert!(!decision(&serde_json::json!({ "result": false })));
+        assert!(!decision(
+            &serde_json::json!({ "result": { "allow": "yes" } })
+        ));
+        assert!(!decision(&serde_json::json!({})));
+    }
+
+    #[test]
+    fn test_action() {
+        assert_eq!(action(&Method::GET), "read");
+        assert_eq!(action(&Method::HEAD), "read");
+        assert_eq!(action(&Method::POST), "write");
+        assert_eq!(action(&Method::PATCH), "write");
+        assert_eq!(action(&Method::DELETE), "delete");
+    }
+}
@@ -90,6 +90,29 @@ impl<'a> RoleManager<'a> {
         Ok(result.0)
     }
 
+    /// Check if a user is a member of a role, directly or through other roles
+    ///
+    /// A role counts as a member of itself for `pg_has_role`, so a user
+    /// whose name is the role's isn't taken for a member.
+    pub async fn is_member(&self, role: &str, user: &str) -> AppResult<bool> {
+        let result: (bool,) = sqlx::query_as(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM pg_roles r, pg_roles u
+                WHERE r.rolname = $1 AND u.rolname = $2
+                  AND r.oid <> u.oid
+                  AND pg_has_role(u.oid, r.oid, 'MEMBER')
+            )
+            "#,
+        )
+        .bind(role)
+        .bind(user)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.0)
+    }
+
     /// Grant table privileges to a role
     pub async fn grant_table_privileges(
         &self,
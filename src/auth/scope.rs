@@ -0,0 +1,339 @@
+//! Restrictions of identities beyond their grants
+//!
+//! Service accounts and scoped tokens act with narrower rights than their
+//! user would have: only on some collections, with some actions, or
+//! executing some processes.
+//! Restrictions are checked by the auth middleware for the collection in the
+//! path, and by process execution and job access for the collections named
+//! in the inputs. An identity limited to some collections may only use routes
+//! without a collection in the path that are checked like that; listings and
+//! searches across collections are denied.
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::processes::workflow;
+use crate::error::{AppError, AppResult};
+
+/// What a restricted identity may do; empty lists don't restrict
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccessScope {
+    /// Collections it may access, by canonical name or by name among the
+    /// user's own; a trailing `*` matches any name starting with the rest
+    #[serde(default)]
+    pub collections: Vec<String>,
+    /// Actions it may perform: `read`, `write` and `delete`
+    #[serde(default)]
+    pub actions: Vec<String>,
+    /// Processes it may execute
+    #[serde(default)]
+    pub processes: Vec<String>,
+}
+
+impl AccessScope {
+    /// Whether the scope allows accessing a collection of a user
+    pub fn allows_collection(&self, username: &str, collection: &str) -> bool {
+        if self.collections.is_empty() {
+            return true;
+        }
+        let collection = canonical_name(username, collection);
+        self.collections.iter().any(|pattern| {
+            let pattern = canonical_name(username, pattern);
+            match pattern.strip_suffix('*') {
+                Some(prefix) => collection.starts_with(prefix),
+                None => collection == pattern,
+            }
+        })
+    }
+
+    /// Whether the scope allows an action
+    pub fn allows_action(&self, action: &str) -> bool {
+        self.actions.is_empty() || self.actions.iter().any(|allowed| allowed == action)
+    }
+
+    /// Whether the scope allows executing a process
+    pub fn allows_process(&self, process_id: &str) -> bool {
+        self.processes.is_empty() || self.processes.iter().any(|allowed| allowed == process_id)
+    }
+
+    /// Check a request against the scope
+    ///
+    /// `collection` is the collection in the path; process executions are
+    /// recognized by their path.
+    pub fn check_request(
+        &self,
+        username: &str,
+        action: &str,
+        path: &str,
+        collection: Option<&str>,
+    ) -> AppResult<()> {
+        if !self.allows_action(action) {
+            return Err(AppError::Forbidden(format!(
+                "The token may not {} resources",
+                action
+            )));
+        }
+        match collection {
+            Some(collection) if !self.allows_collection(username, collection) => {
+                return Err(AppError::Forbidden(format!(
+                    "The token may not access collection {}",
+                    collection
+                )));
+            }
+            None if !self.collections.is_empty() && !checks_own_collections(path) => {
+                return Err(AppError::Forbidden(format!(
+                    "The token is limited to some collections and may not access {}",
+                    path
+                )));
+            }
+            _ => {}
+        }
+        if let Some(process_id) = executed_process(path)
+            && !self.allows_process(process_id)
+        {
+            return Err(AppError::Forbidden(format!(
+                "The token may not execute process {}",
+                process_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check process inputs against the scope
+    ///
+    /// Collections may be named by the inputs or referenced by href through
+    /// this server's API. A scope limited to some collections can't tell
+    /// whose data an object in storage is, so `s3://` references are refused
+    /// and files must be given through uploads or URLs instead.
+    pub fn check_inputs(
+        &self,
+        username: &str,
+        inputs: &serde_json::Value,
+        base_url: &str,
+    ) -> AppResult<()> {
+        if self.collections.is_empty() {
+            return Ok(());
+        }
+        let hrefs = string_inputs(inputs, &["href"]);
+        if hrefs.iter().any(|href| href.starts_with("s3://")) {
+            return Err(AppError::Forbidden(
+                "The token may not reference objects in storage; upload the file instead"
+                    .to_string(),
+            ));
+        }
+        let referenced = hrefs
+            .into_iter()
+            .filter_map(|href| href_collection(href, base_url));
+        for collection in input_collections(inputs).into_iter().chain(referenced) {
+            if !self.allows_collection(username, collection) {
+                return Err(AppError::Forbidden(format!(
+                    "The token may not access collection {}",
+                    collection
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the scope allows accessing a job: executing its process, or
+    /// each step's for a workflow, and the collections named in its inputs
+    pub fn allows_job(
+        &self,
+        username: &str,
+        process_id: &str,
+        inputs: Option<&serde_json::Value>,
+    ) -> bool {
+        let steps = inputs
+            .filter(|_| process_id == workflow::PROCESS_ID)
+            .and_then(|inputs| inputs.get("steps"))
+            .and_then(|steps| steps.as_array());
+        let processes_allowed = match steps {
+            Some(steps) => steps.iter().all(|step| {
+                step.get("process")
+                    .and_then(|process| process.as_str())
+                    .is_some_and(|process| self.allows_process(process))
+            }),
+            None => self.allows_process(process_id),
+        };
+        processes_allowed
+            && inputs.is_none_or(|inputs| {
+                input_collections(inputs)
+                    .into_iter()
+                    .all(|collection| self.allows_collection(username, collection))
+            })
+    }
+}
+
+/// Input names naming a collection a process reads or writes
+const COLLECTION_INPUTS: [&str; 2] = ["collection", "target"];
+
+/// String values of process inputs with one of `keys`, at any depth so
+/// that the steps of a workflow are included
+fn string_inputs<'a>(inputs: &'a serde_json::Value, keys: &[&str]) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut pending = vec![inputs];
+    while let Some(value) = pending.pop() {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, value) in map {
+                    match value.as_str() {
+                        Some(string) if keys.contains(&key.as_str()) => found.push(string),
+                        _ => pending.push(value),
+                    }
+                }
+            }
+            serde_json::Value::Array(values) => pending.extend(values),
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Collections named in process inputs
+pub fn input_collections(inputs: &serde_json::Value) -> Vec<&str> {
+    string_inputs(inputs, &COLLECTION_INPUTS)
+}
+
+/// Collection an href to this server's collections endpoints is about
+fn href_collection<'a>(href: &'a str, base_url: &str) -> Option<&'a str> {
+    let path = href.strip_prefix(base_url)?.strip_prefix("/collections/")?;
+    path.split(['/', '?', '#'])
+        .next()
+        .filter(|id| !id.is_empty())
+}
+
+/// Whether a path without a collection in it only reaches collections that
+/// its handler checks against the scope: process executions by their
+/// inputs, jobs by theirs, and uploads, which hold no collection data
+fn checks_own_collections(path: &str) -> bool {
+    path == "/processes"
+        || path == "/workflows"
+        || path.starts_with("/processes/")
+        || path.starts_with("/jobs/")
+        || path == "/uploads"
+        || path.starts_with("/uploads/")
+}
+
+/// Canonical name of a collection named by a user
+fn canonical_name(username: &str, collection: &str) -> String {
+    if collection.contains(':') {
+        collection.to_string()
+    } else {
+        format!("{}:{}", username, collection)
+    }
+}
+
+/// Process executed by a request to `/processes/{id}/execution`
+fn executed_process(path: &str) -> Option<&str> {
+    path.strip_prefix("/processes/")?
+        .strip_suffix("/execution")
+        .filter(|process_id| !process_id.contains('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_collections() {
+        let scope = AccessScope {
+            collections: vec!["ingest".to_string(), "shared:sentinel-*".to_string()],
+            ..Default::default()
+        };
+        assert!(scope.allows_collection("pipeline", "ingest"));
+        assert!(scope.allows_collection("pipeline", "pipeline:ingest"));
+        assert!(scope.allows_collection("pipeline", "shared:sentinel-2"));
+        assert!(!scope.allows_collection("pipeline", "roads"));
+        assert!(!scope.allows_collection("pipeline", "other:ingest"));
+        assert!(AccessScope::default().allows_collection("pipeline", "roads"));
+    }
+
+    #[test]
+    fn test_scope_requests() {
+        let scope = AccessScope {
+            collections: vec!["ingest".to_string()],
+            actions: vec!["read".to_string(), "write".to_string()],
+            processes: vec!["import-raster".to_string()],
+        };
+        let allowed = |action, path, collection| {
+            scope
+                .check_request("pipeline", action, path, collection)
+                .is_ok()
+        };
+        assert!(allowed("write", "/processes/import-raster/execution", None));
+        assert!(!allowed(
+            "write",
+            "/processes/export-collection/execution",
+            None
+        ));
+        assert!(allowed("read", "/collections/ingest/items", Some("ingest")));
+        assert!(!allowed("read", "/collections/roads/items", Some("roads")));
+        assert!(!allowed("delete", "/collections/ingest", Some("ingest")));
+
+        // Routes across collections are denied, others are checked by their handlers
+        assert!(!allowed("read", "/collections", None));
+        assert!(!allowed("read", "/jobs", None));
+        assert!(!allowed("read", "/stac/search", None));
+        assert!(allowed("read", "/jobs/6f1c4d0e/results", None));
+        assert!(
+            AccessScope::default()
+                .check_request("pipeline", "read", "/jobs", None)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_scope_jobs() {
+        let scope = AccessScope {
+            collections: vec!["ingest".to_string()],
+            processes: vec!["import-raster".to_string(), "export-collection".to_string()],
+            ..Default::default()
+        };
+        let inputs = serde_json::json!({ "collection": "ingest", "href": "s3://bucket/a.tif" });
+        assert!(scope.allows_job("pipeline", "import-raster", Some(&inputs)));
+        assert!(!scope.allows_job("pipeline", "backup-collection", Some(&inputs)));
+        let inputs = serde_json::json!({ "collection": "other:roads" });
+        assert!(!scope.allows_job("pipeline", "export-collection", Some(&inputs)));
+
+        let workflow = serde_json::json!({
+            "steps": [
+                { "process": "import-raster", "inputs": { "collection": "ingest" } },
+                { "process": "export-collection", "inputs": { "collection": "roads" } }
+            ]
+        });
+        assert!(!scope.allows_job("pipeline", "workflow", Some(&workflow)));
+        assert_eq!(input_collections(&workflow).len(), 2);
+    }
+
+    #[test]
+    fn test_scope_inputs() {
+        let scope = AccessScope {
+            collections: vec!["ingest".to_string()],
+            ..Default::default()
+        };
+        let base_url = "https://maps.example.com";
+        let allowed =
+            |inputs: serde_json::Value| scope.check_inputs("pipeline", &inputs, base_url).is_ok();
+        assert!(allowed(serde_json::json!({
+            "source": { "href": "https://maps.example.com/collections/ingest/items?f=json" },
+            "upload": { "href": "upload://6f1c4d0e" }
+        })));
+        assert!(!allowed(serde_json::json!({
+            "source": { "href": "https://maps.example.com/collections/other:roads/items" }
+        })));
+        assert!(!allowed(serde_json::json!({
+            "source": { "href": "s3://bucket/other/roads/data.parquet" }
+        })));
+        assert!(!allowed(
+            serde_json::json!({ "options": { "target": "roads" } })
+        ));
+        assert!(
+            AccessScope::default()
+                .check_inputs(
+                    "pipeline",
+                    &serde_json::json!({ "href": "s3://b/k" }),
+                    base_url
+                )
+                .is_ok()
+        );
+    }
+}
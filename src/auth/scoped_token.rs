@@ -0,0 +1,252 @@
+//! Scoped access tokens
+//!
+//! A user exchanges their access token for one that only allows some
+//! collections, actions and processes, e.g. for a web map embed that should
+//! only read tiles of two collections. The tokens are JWTs signed by the
+//! service itself (HS256), so the OIDC provider is not involved; they carry
+//! the user's identity and the scope, which the auth middleware enforces.
+//! Each token has an id under which it can be revoked before it expires.
+
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::{AccessScope, AuthenticatedUser};
+use crate::config::ScopedTokenConfig;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+
+/// Audience of scoped tokens, distinguishing them from other JWTs signed
+/// with the same key
+const AUDIENCE: &str = "spatialvault-scoped";
+
+/// Type in the header of scoped tokens, telling them apart from OIDC tokens
+/// before their signature is checked
+const TOKEN_TYPE: &str = "spatialvault-scoped+jwt";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ScopedClaims {
+    jti: Uuid,
+    iss: String,
+    aud: String,
+    sub: String,
+    username: String,
+    #[serde(default)]
+    groups: Vec<String>,
+    exp: i64,
+    iat: i64,
+    scope: AccessScope,
+}
+
+/// A scoped token that was revoked
+#[derive(Debug)]
+pub struct RevokedToken {
+    pub id: Uuid,
+    /// When the token would have expired
+    pub expires: DateTime<Utc>,
+}
+
+/// Issues and validates scoped tokens
+pub struct ScopedTokens {
+    key: String,
+    issuer: String,
+    max_expiry_secs: u64,
+    /// Where revoked tokens are recorded; unchecked without it
+    db: Option<Arc<Database>>,
+}
+
+impl ScopedTokens {
+    /// Scoped tokens of the configured key, if exchange is enabled
+    pub fn new(config: &ScopedTokenConfig, base_url: &str) -> Option<Self> {
+        let key = config.key.as_deref().filter(|key| !key.is_empty())?;
+        Some(Self {
+            key: key.to_string(),
+            issuer: base_url.to_string(),
+            max_expiry_secs: config.max_expiry_secs,
+            db: None,
+        })
+    }
+
+    /// Reject tokens revoked in `db`
+    pub fn with_revocations(mut self, db: Arc<Database>) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    /// Longest lifetime of a scoped token
+    pub fn max_expiry_secs(&self) -> u64 {
+        self.max_expiry_secs
+    }
+
+    /// Whether a bearer token is a scoped token rather than an OIDC one
+    ///
+    /// Only tokens this service issued have its type; other HS256 tokens
+    /// are left to the OIDC validator, which rejects them.
+    pub fn is_scoped(token: &str) -> bool {
+        jsonwebtoken::decode_header(token).is_ok_and(|header| {
+            header.alg == Algorithm::HS256 && header.typ.as_deref() == Some(TOKEN_TYPE)
+        })
+    }
+
+    /// Token acting as `user`, restricted to `scope`, until `expires`
+    pub fn issue(
+        &self,
+        user: &AuthenticatedUser,
+        scope: &AccessScope,
+        expires: DateTime<Utc>,
+    ) -> AppResult<String> {
+        let claims = ScopedClaims {
+            jti: Uuid::new_v4(),
+            iss: self.issuer.clone(),
+            aud: AUDIENCE.to_string(),
+            sub: user.subject.clone(),
+            username: user.username.clone(),
+            groups: user.groups.clone(),
+            exp: expires.timestamp(),
+            iat: Utc::now().timestamp(),
+            scope: scope.clone(),
+        };
+        let header = Header {
+            typ: Some(TOKEN_TYPE.to_string()),
+            ..Header::new(Algorithm::HS256)
+        };
+        encode(
+            &header,
+            &claims,
+            &EncodingKey::from_secret(self.key.as_bytes()),
+        )
+        .map_err(|e| AppError::Internal(format!("Failed to sign token: {}", e)))
+    }
+
+    /// Claims of a token signed with the key, optionally even if expired
+    fn decode(&self, token: &str, allow_expired: bool) -> AppResult<ScopedClaims> {
+        if !Self::is_scoped(token) {
+            return Err(AppError::Unauthorized("Not a scoped token".to_string()));
+        }
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_audience(&[AUDIENCE]);
+        validation.set_issuer(&[&self.issuer]);
+        validation.validate_exp = !allow_expired;
+
+        decode::<ScopedClaims>(
+            token,
+            &DecodingKey::from_secret(self.key.as_bytes()),
+            &validation,
+        )
+        .map(|data| data.claims)
+        .map_err(|e| AppError::Unauthorized(format!("Token validation failed: {}", e)))
+    }
+
+    /// User of a scoped token
+    pub async fn validate(&self, token: &str) -> AppResult<AuthenticatedUser> {
+        let claims = self.decode(token, false)?;
+
+        if let Some(db) = &self.db {
+            let revoked: bool = sqlx::query_scalar(
+                "SELECT EXISTS(SELECT 1 FROM spatialvault.revoked_tokens WHERE id = $1)",
+            )
+            .bind(claims.jti)
+            .fetch_one(db.pool())
+            .await?;
+            if revoked {
+                return Err(AppError::Unauthorized("Token was revoked".to_string()));
+            }
+        }
+
+        Ok(AuthenticatedUser {
+            username: claims.username,
+            subject: claims.sub,
+            groups: claims.groups,
+            service_account: None,
+            scope: Some(claims.scope),
+        })
+    }
+
+    /// Revoke a token signed with the key, also dropping revocations of
+    /// tokens that have expired since
+    pub async fn revoke(&self, db: &Database, token: &str) -> AppResult<RevokedToken> {
+        let claims = self.decode(token, true)?;
+        let expires = DateTime::from_timestamp(claims.exp, 0)
+            .ok_or_else(|| AppError::BadRequest("Invalid token expiry".to_string()))?;
+
+        let mut tx = db.pool().begin().await?;
+        sqlx::query("DELETE FROM spatialvault.revoked_tokens WHERE expires < NOW()")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO spatialvault.revoked_tokens (id, expires)
+            VALUES ($1, $2)
+            ON CONFLICT (id) DO NOTHING
+            "#,
+        )
+        .bind(claims.jti)
+        .bind(expires)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(RevokedToken {
+            id: claims.jti,
+            expires,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(key: &str) -> ScopedTokens {
+        ScopedTokens::new(
+            &ScopedTokenConfig {
+                key: Some(key.to_string()),
+                ..Default::default()
+            },
+            "http://localhost:8080",
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_scoped_token_roundtrip() {
+        let user = AuthenticatedUser {
+            username: "alice".to_string(),
+            subject: "alice-id".to_string(),
+            groups: vec!["mappers".to_string()],
+            service_account: None,
+            scope: None,
+        };
+        let scope = AccessScope {
+            collections: vec!["roads".to_string(), "parks".to_string()],
+            actions: vec!["read".to_string()],
+            processes: vec![],
+        };
+        let expires = Utc::now() + chrono::Duration::minutes(5);
+        let token = tokens("secret").issue(&user, &scope, expires).unwrap();
+        assert!(ScopedTokens::is_scoped(&token));
+        let other = encode(
+            &Header::new(Algorithm::HS256),
+            &serde_json::json!({ "sub": "alice" }),
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        assert!(!ScopedTokens::is_scoped(&other));
+
+        let scoped = tokens("secret").validate(&token).await.unwrap();
+        assert_eq!(scoped.username, "alice");
+        assert_eq!(scoped.groups, vec!["mappers".to_string()]);
+        let scoped = scoped.scope.unwrap();
+        assert!(scoped.allows_collection("alice", "alice:parks"));
+        assert!(!scoped.allows_action("write"));
+
+        assert!(tokens("other").validate(&token).await.is_err());
+        let expired = tokens("secret")
+            .issue(&user, &scope, Utc::now() - chrono::Duration::hours(1))
+            .unwrap();
+        assert!(tokens("secret").validate(&expired).await.is_err());
+        assert!(tokens("secret").decode(&expired, true).is_ok());
+    }
+}
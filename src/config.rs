@@ -1,6 +1,10 @@
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::sync::Arc;
+use std::time::Duration;
+
+use crate::auth::AccessScope;
 
 #[derive(Clone, Deserialize)]
 pub struct Config {
@@ -8,12 +12,73 @@ pub struct Config {
     pub host: String,
     #[serde(default = "default_port")]
     pub port: u16,
+    /// TLS on the `host`:`port` listener; plain HTTP when unset
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    /// Further addresses the API is served on, e.g. a Unix socket for a
+    /// local proxy or an internal port
+    #[serde(default)]
+    pub listeners: Vec<ListenerConfig>,
     pub database: DatabaseConfig,
     pub oidc: OidcConfig,
     #[serde(default)]
     pub s3: S3Config,
     #[serde(default = "default_base_url")]
     pub base_url: String,
+    /// Path the API is mounted under, e.g. `/geo/api/v1`, for ingresses
+    /// that share a hostname and pass the path on unchanged; appended to
+    /// `base_url` unless it already ends with it
+    #[serde(default)]
+    pub path_prefix: String,
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+    #[serde(default)]
+    pub processing: ProcessingConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub features: FeaturesConfig,
+    #[serde(default)]
+    pub discovery: DiscoveryConfig,
+    #[serde(default)]
+    pub metadata: MetadataConfig,
+    #[serde(default)]
+    pub localization: LocalizationConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub analytics: AnalyticsConfig,
+    #[serde(default)]
+    pub tile_signing: TileSigningConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub cdn: CdnConfig,
+    #[serde(default)]
+    pub compression: CompressionConfig,
+    #[serde(default)]
+    pub replication: ReplicationConfig,
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+    #[serde(default)]
+    pub policy: PolicyConfig,
+    /// Service accounts by OIDC client ID
+    #[serde(default)]
+    pub service_accounts: BTreeMap<String, ServiceAccount>,
+    #[serde(default)]
+    pub scoped_tokens: ScopedTokenConfig,
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    #[serde(default)]
+    pub link_check: LinkCheckConfig,
+    #[serde(default)]
+    pub quotas: QuotaConfig,
+    #[serde(default)]
+    pub modules: ModulesConfig,
+    /// Reject all writes with 403, e.g. on a replica fed by replication;
+    /// `spatialvault admin read-only` switches for maintenance instead
+    #[serde(default)]
+    pub read_only: bool,
 }
 
 // Custom Debug implementation to prevent secrets from being logged
@@ -22,10 +87,36 @@ impl fmt::Debug for Config {
         f.debug_struct("Config")
             .field("host", &self.host)
             .field("port", &self.port)
+            .field("tls", &self.tls)
+            .field("listeners", &self.listeners)
             .field("database", &self.database)
             .field("oidc", &self.oidc)
             .field("s3", &self.s3)
             .field("base_url", &self.base_url)
+            .field("path_prefix", &self.path_prefix)
+            .field("proxy", &self.proxy)
+            .field("processing", &self.processing)
+            .field("limits", &self.limits)
+            .field("features", &self.features)
+            .field("discovery", &self.discovery)
+            .field("metadata", &self.metadata)
+            .field("localization", &self.localization)
+            .field("telemetry", &self.telemetry)
+            .field("analytics", &self.analytics)
+            .field("tile_signing", &self.tile_signing)
+            .field("cache", &self.cache)
+            .field("cdn", &self.cdn)
+            .field("compression", &self.compression)
+            .field("replication", &self.replication)
+            .field("webhooks", &self.webhooks)
+            .field("policy", &self.policy)
+            .field("service_accounts", &self.service_accounts)
+            .field("scoped_tokens", &self.scoped_tokens)
+            .field("notifications", &self.notifications)
+            .field("link_check", &self.link_check)
+            .field("quotas", &self.quotas)
+            .field("modules", &self.modules)
+            .field("read_only", &self.read_only)
             .finish()
     }
 }
@@ -42,13 +133,41 @@ fn default_base_url() -> String {
     "http://localhost:8080".to_string()
 }
 
+/// Certificate and key of a TLS listener, as PEM files
+#[derive(Debug, Clone, Deserialize)]
+pub struct TlsConfig {
+    /// Certificate chain, leaf certificate first
+    pub cert_path: String,
+    /// Private key (PKCS#8, PKCS#1 or SEC1)
+    pub key_path: String,
+}
+
+/// An additional listener
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListenerConfig {
+    /// `host:port`, or `unix:` followed by the path of a Unix domain socket
+    pub bind: String,
+    /// TLS on this listener; not supported on Unix sockets
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+}
+
 #[derive(Clone, Deserialize)]
 pub struct DatabaseConfig {
     pub url: String,
     #[serde(default = "default_max_connections")]
     pub max_connections: u32,
+    /// Connections of the separate pool for background and export queries
+    #[serde(default = "default_background_max_connections")]
+    pub background_max_connections: u32,
+    /// How long a request waits for a free connection before it is answered
+    /// with 503 and `Retry-After`
+    #[serde(default = "default_acquire_timeout_ms")]
+    pub acquire_timeout_ms: u64,
     #[serde(default = "default_service_role")]
     pub service_role: String,
+    #[serde(default)]
+    pub statement_timeouts: StatementTimeouts,
 }
 
 // Custom Debug implementation to redact database URL (may contain password)
@@ -57,7 +176,13 @@ impl fmt::Debug for DatabaseConfig {
         f.debug_struct("DatabaseConfig")
             .field("url", &"[REDACTED]")
             .field("max_connections", &self.max_connections)
+            .field(
+                "background_max_connections",
+                &self.background_max_connections,
+            )
+            .field("acquire_timeout_ms", &self.acquire_timeout_ms)
             .field("service_role", &self.service_role)
+            .field("statement_timeouts", &self.statement_timeouts)
             .finish()
     }
 }
@@ -66,21 +191,84 @@ fn default_max_connections() -> u32 {
     10
 }
 
+fn default_background_max_connections() -> u32 {
+    4
+}
+
+fn default_acquire_timeout_ms() -> u64 {
+    5_000
+}
+
 fn default_service_role() -> String {
     "spatialvault_service".to_string()
 }
 
+/// Statement timeouts in milliseconds per query class; 0 disables the timeout
+///
+/// A query running longer is cancelled by PostgreSQL, so one expensive
+/// request can't hold a pooled connection indefinitely.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatementTimeouts {
+    /// Session default for API requests
+    #[serde(default = "default_statement_timeout_ms")]
+    pub default_ms: u64,
+    /// Vector tile rendering
+    #[serde(default = "default_tiles_timeout_ms")]
+    pub tiles_ms: u64,
+    /// Feature and item listing, including CQL2 filters and counts
+    #[serde(default = "default_statement_timeout_ms")]
+    pub features_ms: u64,
+    /// Session default for the background worker (imports and exports)
+    #[serde(default = "default_background_timeout_ms")]
+    pub background_ms: u64,
+}
+
+impl Default for StatementTimeouts {
+    fn default() -> Self {
+        Self {
+            default_ms: default_statement_timeout_ms(),
+            tiles_ms: default_tiles_timeout_ms(),
+            features_ms: default_statement_timeout_ms(),
+            background_ms: default_background_timeout_ms(),
+        }
+    }
+}
+
+fn default_statement_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_tiles_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_background_timeout_ms() -> u64 {
+    30 * 60 * 1000
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct OidcConfig {
     pub issuer_url: String,
     #[serde(default = "default_audience")]
     pub audience: String,
+    /// Public client id used by the API console at `/docs` to log in with
+    /// the authorization code + PKCE flow (`{base_url}/docs` must be an
+    /// allowed redirect URI); the console has no login when unset
+    #[serde(default)]
+    pub console_client_id: Option<String>,
+    /// Space-separated scopes requested by the API console
+    #[serde(default = "default_console_scopes")]
+    pub console_scopes: String,
 }
 
 fn default_audience() -> String {
     "spatialvault".to_string()
 }
 
+fn default_console_scopes() -> String {
+    "openid profile email".to_string()
+}
+
 #[derive(Clone, Default, Deserialize)]
 pub struct S3Config {
     #[serde(default)]
@@ -114,6 +302,933 @@ impl fmt::Debug for S3Config {
     }
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProcessingConfig {
+    /// OIDC group whose members may deploy, replace and undeploy processes
+    #[serde(default = "default_admin_group")]
+    pub admin_group: String,
+    /// Container runtime used to execute deployed processes
+    #[serde(default = "default_container_runtime")]
+    pub container_runtime: String,
+    /// Maximum wall-clock time for a deployed process container
+    #[serde(default = "default_container_timeout_secs")]
+    pub container_timeout_secs: u64,
+    /// Maximum wall-clock time for a PDAL point cloud conversion
+    #[serde(default = "default_pdal_timeout_secs")]
+    pub pdal_timeout_secs: u64,
+    /// Days a successful job and its results are kept after it finishes
+    #[serde(default = "default_successful_job_retention_days")]
+    pub successful_job_retention_days: u32,
+    /// Days a failed or dismissed job is kept after it finishes
+    #[serde(default = "default_failed_job_retention_days")]
+    pub failed_job_retention_days: u32,
+    /// How often the worker purges expired jobs
+    #[serde(default = "default_retention_interval_secs")]
+    pub retention_interval_secs: u64,
+    /// Attempts a job gets before transient failures become final
+    #[serde(default = "default_max_job_attempts")]
+    pub max_job_attempts: u32,
+    /// Delay before the first retry; doubled for every further attempt
+    #[serde(default = "default_retry_backoff_secs")]
+    pub retry_backoff_secs: u64,
+    /// Maximum total size of files uploaded in a multipart execute request
+    #[serde(default = "default_max_upload_bytes")]
+    pub max_upload_bytes: u64,
+    /// Maximum size of a JSON execute request (including base64 inline inputs)
+    #[serde(default = "default_max_execute_json_bytes")]
+    pub max_execute_json_bytes: usize,
+    /// Maximum size of one part of a chunked upload
+    #[serde(default = "default_max_upload_part_bytes")]
+    pub max_upload_part_bytes: usize,
+    /// Days a chunked upload is kept after it was started
+    #[serde(default = "default_upload_retention_days")]
+    pub upload_retention_days: u32,
+    /// Store rasters that cannot be converted to COG (e.g. without GDAL) as
+    /// plain GeoTIFFs instead of failing the import
+    #[serde(default)]
+    pub allow_non_cog_rasters: bool,
+    /// Keep the original file of converted imports as a `source` asset;
+    /// import requests can override this with `keepSource`
+    #[serde(default)]
+    pub keep_source_files: bool,
+}
+
+impl Default for ProcessingConfig {
+    fn default() -> Self {
+        Self {
+            admin_group: default_admin_group(),
+            container_runtime: default_container_runtime(),
+            container_timeout_secs: default_container_timeout_secs(),
+            pdal_timeout_secs: default_pdal_timeout_secs(),
+            successful_job_retention_days: default_successful_job_retention_days(),
+            failed_job_retention_days: default_failed_job_retention_days(),
+            retention_interval_secs: default_retention_interval_secs(),
+            max_job_attempts: default_max_job_attempts(),
+            retry_backoff_secs: default_retry_backoff_secs(),
+            max_upload_bytes: default_max_upload_bytes(),
+            max_execute_json_bytes: default_max_execute_json_bytes(),
+            max_upload_part_bytes: default_max_upload_part_bytes(),
+            upload_retention_days: default_upload_retention_days(),
+            allow_non_cog_rasters: false,
+            keep_source_files: false,
+        }
+    }
+}
+
+fn default_admin_group() -> String {
+    "spatialvault-admins".to_string()
+}
+
+fn default_container_runtime() -> String {
+    "docker".to_string()
+}
+
+fn default_container_timeout_secs() -> u64 {
+    3600
+}
+
+fn default_pdal_timeout_secs() -> u64 {
+    3600
+}
+
+fn default_successful_job_retention_days() -> u32 {
+    30
+}
+
+fn default_failed_job_retention_days() -> u32 {
+    90
+}
+
+fn default_retention_interval_secs() -> u64 {
+    3600
+}
+
+fn default_max_job_attempts() -> u32 {
+    3
+}
+
+fn default_retry_backoff_secs() -> u64 {
+    30
+}
+
+fn default_max_upload_bytes() -> u64 {
+    10 * 1024 * 1024 * 1024
+}
+
+fn default_max_execute_json_bytes() -> usize {
+    64 * 1024 * 1024
+}
+
+fn default_max_upload_part_bytes() -> usize {
+    256 * 1024 * 1024
+}
+
+fn default_upload_retention_days() -> u32 {
+    7
+}
+
+impl ProcessingConfig {
+    /// Backoff before retrying after the given (1-based) failed attempt
+    pub fn retry_delay(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(10);
+        Duration::from_secs(self.retry_backoff_secs.saturating_mul(factor))
+    }
+}
+
+/// Request body size limits
+///
+/// Process execution has its own limits in [`ProcessingConfig`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct LimitsConfig {
+    /// Maximum request body size for ordinary requests
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// Maximum request body size for feature ingest (FeatureCollection uploads)
+    #[serde(default = "default_max_ingest_bytes")]
+    pub max_ingest_bytes: u64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: default_max_body_bytes(),
+            max_ingest_bytes: default_max_ingest_bytes(),
+        }
+    }
+}
+
+fn default_max_body_bytes() -> usize {
+    2 * 1024 * 1024
+}
+
+fn default_max_ingest_bytes() -> u64 {
+    4 * 1024 * 1024 * 1024
+}
+
+/// Feature listing settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeaturesConfig {
+    /// Above this many (estimated) matches, `numberMatched` is the planner
+    /// estimate instead of an exact count; 0 always counts exactly
+    #[serde(default = "default_count_estimate_threshold")]
+    pub count_estimate_threshold: u64,
+    /// Page size when the request has no `limit` (collections may override)
+    #[serde(default = "default_page_limit")]
+    pub default_limit: u32,
+    /// Largest accepted `limit` (collections may override)
+    #[serde(default = "default_max_page_limit")]
+    pub max_limit: u32,
+}
+
+impl Default for FeaturesConfig {
+    fn default() -> Self {
+        Self {
+            count_estimate_threshold: default_count_estimate_threshold(),
+            default_limit: default_page_limit(),
+            max_limit: default_max_page_limit(),
+        }
+    }
+}
+
+fn default_count_estimate_threshold() -> u64 {
+    1_000_000
+}
+
+fn default_page_limit() -> u32 {
+    10
+}
+
+fn default_max_page_limit() -> u32 {
+    10000
+}
+
+/// Collection search settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscoveryConfig {
+    /// How often the cached extents that collections are searched by with
+    /// `bbox` and `datetime` are recomputed after their data changed
+    #[serde(default = "default_extent_interval_secs")]
+    pub extent_interval_secs: u64,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            extent_interval_secs: default_extent_interval_secs(),
+        }
+    }
+}
+
+fn default_extent_interval_secs() -> u64 {
+    60
+}
+
+/// Settings of the ISO 19139 metadata documents of collections
+#[derive(Debug, Clone, Deserialize)]
+pub struct MetadataConfig {
+    /// Organisation named as point of contact; defaults to the collection owner
+    #[serde(default)]
+    pub organisation: Option<String>,
+    /// Email address of the point of contact
+    #[serde(default)]
+    pub contact_email: Option<String>,
+    /// ISO 639-2 code of the language of the metadata and the data
+    #[serde(default = "default_metadata_language")]
+    pub language: String,
+    /// ISO topic category of collections whose keywords name none, e.g.
+    /// `transportation`
+    #[serde(default)]
+    pub topic_category: Option<String>,
+}
+
+impl Default for MetadataConfig {
+    fn default() -> Self {
+        Self {
+            organisation: None,
+            contact_email: None,
+            language: default_metadata_language(),
+            topic_category: None,
+        }
+    }
+}
+
+fn default_metadata_language() -> String {
+    "eng".to_string()
+}
+
+/// Tile usage analytics settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnalyticsConfig {
+    /// How often buffered tile usage counts are written to the database
+    #[serde(default = "default_analytics_flush_interval_secs")]
+    pub flush_interval_secs: u64,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            flush_interval_secs: default_analytics_flush_interval_secs(),
+        }
+    }
+}
+
+fn default_analytics_flush_interval_secs() -> u64 {
+    60
+}
+
+/// HTTP caching of tiles and coverage data
+#[derive(Debug, Clone, Deserialize)]
+pub struct CacheConfig {
+    /// How long clients and CDNs may reuse a response without revalidating
+    #[serde(default = "default_cache_max_age_secs")]
+    pub max_age_secs: u64,
+    /// How long a stale response may still be served while it is revalidated
+    #[serde(default = "default_stale_while_revalidate_secs")]
+    pub stale_while_revalidate_secs: u64,
+    /// How long responses to URLs naming the current collection version
+    /// (`v`) may be reused; they never change, so they are also `immutable`
+    #[serde(default = "default_immutable_max_age_secs")]
+    pub immutable_max_age_secs: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_age_secs: default_cache_max_age_secs(),
+            stale_while_revalidate_secs: default_stale_while_revalidate_secs(),
+            immutable_max_age_secs: default_immutable_max_age_secs(),
+        }
+    }
+}
+
+fn default_cache_max_age_secs() -> u64 {
+    3600
+}
+
+fn default_stale_while_revalidate_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_immutable_max_age_secs() -> u64 {
+    365 * 24 * 60 * 60
+}
+
+/// Reverse proxies in front of the service
+///
+/// `X-Forwarded-For`, `X-Forwarded-Proto` and `X-Forwarded-Host` are only
+/// honored on connections from trusted proxies, which includes every
+/// connection over a Unix socket.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProxyConfig {
+    /// Addresses or networks of trusted proxies, e.g. `10.0.0.0/8`
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+    /// Derive the base URL of links from the forwarded protocol and host
+    /// rather than using `base_url`, whose path is kept; for services
+    /// reachable under several hostnames
+    #[serde(default)]
+    pub derive_base_url: bool,
+    /// Hosts the base URL may be derived for; any when empty. Requests for
+    /// other hosts get links with `base_url`
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+}
+
+/// Response compression settings
+///
+/// Responses are compressed with zstd, brotli or gzip as the client accepts;
+/// formats that are already compressed, such as PNG tiles, are sent as is.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompressionConfig {
+    /// Compression level for all encodings, clamped to each encoding's range
+    /// (gzip 1-9, brotli 0-11, zstd 1-22); by default each encoding uses a
+    /// fast level (gzip 6, brotli 4, zstd 3)
+    #[serde(default)]
+    pub level: Option<i32>,
+    /// Responses smaller than this are not worth compressing
+    #[serde(default = "default_compression_min_size_bytes")]
+    pub min_size_bytes: u16,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            level: None,
+            min_size_bytes: default_compression_min_size_bytes(),
+        }
+    }
+}
+
+fn default_compression_min_size_bytes() -> u16 {
+    256
+}
+
+/// CDN in front of the service, purged when collections change
+///
+/// Tile paths of a collection are purged once its version has changed, so
+/// edits show on public maps before cached tiles expire. Purging is off
+/// without a provider.
+#[derive(Clone, Deserialize)]
+pub struct CdnConfig {
+    #[serde(default)]
+    pub provider: Option<CdnProvider>,
+    /// Fastly service, Cloudflare zone or CloudFront distribution ID
+    #[serde(default)]
+    pub target_id: Option<String>,
+    /// Fastly or Cloudflare API token
+    #[serde(default)]
+    pub api_token: Option<String>,
+    /// AWS credentials for CloudFront
+    #[serde(default)]
+    pub access_key_id: Option<String>,
+    #[serde(default)]
+    pub secret_access_key: Option<String>,
+    /// Public URL the CDN serves the API at (default `base_url`)
+    #[serde(default)]
+    pub public_url: Option<String>,
+    /// How often changed collections are looked for
+    #[serde(default = "default_cdn_purge_interval_secs")]
+    pub purge_interval_secs: u64,
+}
+
+/// CDN whose purge API is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CdnProvider {
+    /// Purges the collection's surrogate key
+    Fastly,
+    /// Purges the prefixes of the collection's tile paths
+    Cloudflare,
+    /// Invalidates the collection's tile paths
+    Cloudfront,
+}
+
+impl Default for CdnConfig {
+    fn default() -> Self {
+        Self {
+            provider: None,
+            target_id: None,
+            api_token: None,
+            access_key_id: None,
+            secret_access_key: None,
+            public_url: None,
+            purge_interval_secs: default_cdn_purge_interval_secs(),
+        }
+    }
+}
+
+// Custom Debug implementation to redact CDN credentials
+impl fmt::Debug for CdnConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CdnConfig")
+            .field("provider", &self.provider)
+            .field("target_id", &self.target_id)
+            .field("api_token", &self.api_token.as_ref().map(|_| "[REDACTED]"))
+            .field(
+                "access_key_id",
+                &self.access_key_id.as_ref().map(|_| "[REDACTED]"),
+            )
+            .field(
+                "secret_access_key",
+                &self.secret_access_key.as_ref().map(|_| "[REDACTED]"),
+            )
+            .field("public_url", &self.public_url)
+            .field("purge_interval_secs", &self.purge_interval_secs)
+            .finish()
+    }
+}
+
+fn default_cdn_purge_interval_secs() -> u64 {
+    10
+}
+
+/// Other SpatialVault instances collections can be published to
+///
+/// Owners publish a collection to a remote by name; its features are then
+/// pushed there whenever the collection changes. Remotes are configured here
+/// so that their keys stay out of the database.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplicationConfig {
+    /// Remote instances by name
+    #[serde(default)]
+    pub remotes: BTreeMap<String, RemoteInstance>,
+    /// How often changed collections are looked for
+    #[serde(default = "default_replication_interval_secs")]
+    pub interval_secs: u64,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            remotes: BTreeMap::new(),
+            interval_secs: default_replication_interval_secs(),
+        }
+    }
+}
+
+fn default_replication_interval_secs() -> u64 {
+    60
+}
+
+/// Delivery of collection metadata and schema events to webhooks
+///
+/// Owners register URLs the events of their collections are posted to. As
+/// the server then makes requests to URLs chosen by users, webhooks are off
+/// unless enabled.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Also allow plain `http` URLs
+    #[serde(default)]
+    pub allow_http: bool,
+    /// How often pending events are looked for
+    #[serde(default = "default_webhook_interval_secs")]
+    pub interval_secs: u64,
+    /// Days events are kept for listing and delivery
+    #[serde(default = "default_event_retention_days")]
+    pub event_retention_days: u32,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allow_http: false,
+            interval_secs: default_webhook_interval_secs(),
+            event_retention_days: default_event_retention_days(),
+        }
+    }
+}
+
+fn default_webhook_interval_secs() -> u64 {
+    30
+}
+
+fn default_event_retention_days() -> u32 {
+    30
+}
+
+/// External authorization policy consulted before each authenticated request
+///
+/// The request's user, action, route and collection are posted as `input`
+/// to an Open Policy Agent data API (or a compatible engine, e.g. a Cedar
+/// agent behind an adapter). Owner and share grants still apply; the policy
+/// can only deny more. Off when no URL is set.
+#[derive(Clone, Deserialize)]
+pub struct PolicyConfig {
+    /// Decision URL, e.g. `http://opa:8181/v1/data/spatialvault/allow`
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Bearer token the policy engine requires
+    #[serde(default)]
+    pub token: Option<String>,
+    /// How long a decision may take before the engine counts as unavailable
+    #[serde(default = "default_policy_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Allow requests while the engine is unavailable instead of answering
+    /// them with 503
+    #[serde(default)]
+    pub fail_open: bool,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            token: None,
+            timeout_ms: default_policy_timeout_ms(),
+            fail_open: false,
+        }
+    }
+}
+
+// Custom Debug implementation to redact the token
+impl fmt::Debug for PolicyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PolicyConfig")
+            .field("url", &self.url)
+            .field("token", &self.token.as_ref().map(|_| "[REDACTED]"))
+            .field("timeout_ms", &self.timeout_ms)
+            .field("fail_open", &self.fail_open)
+            .finish()
+    }
+}
+
+fn default_policy_timeout_ms() -> u64 {
+    2_000
+}
+
+/// An automated pipeline authenticating with the OAuth2 client credentials
+/// grant
+///
+/// Tokens issued to the client (by `client_id` claim) act as the
+/// account rather than as a person, with the account's restrictions, and are
+/// logged with a `service_account` field. The client must not also be used
+/// for user logins.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceAccount {
+    /// User the account acts as and whose collections it reaches (default:
+    /// the client ID)
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Groups the account is a member of
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Collections, actions and processes the account is restricted to
+    #[serde(flatten)]
+    pub scope: AccessScope,
+}
+
+/// Notifications of users about collections shared with them and their
+/// finished jobs
+///
+/// Users opt in at `/notifications/settings`. Notifications are posted as
+/// JSON to a notification service, which informs the user, e.g. by email.
+/// Off when no URL is set.
+#[derive(Clone, Deserialize)]
+pub struct NotificationConfig {
+    /// URL of the notification service
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Bearer token the notification service requires
+    #[serde(default)]
+    pub token: Option<String>,
+    /// How often pending notifications are looked for
+    #[serde(default = "default_notification_interval_secs")]
+    pub interval_secs: u64,
+    /// Attempts to send a notification before giving up on it
+    #[serde(default = "default_notification_max_attempts")]
+    pub max_attempts: u32,
+    /// Days notifications are kept
+    #[serde(default = "default_notification_retention_days")]
+    pub retention_days: u32,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            token: None,
+            interval_secs: default_notification_interval_secs(),
+            max_attempts: default_notification_max_attempts(),
+            retention_days: default_notification_retention_days(),
+        }
+    }
+}
+
+// Custom Debug implementation to redact the token
+impl fmt::Debug for NotificationConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NotificationConfig")
+            .field("url", &self.url)
+            .field("token", &self.token.as_ref().map(|_| "[REDACTED]"))
+            .field("interval_secs", &self.interval_secs)
+            .field("max_attempts", &self.max_attempts)
+            .field("retention_days", &self.retention_days)
+            .finish()
+    }
+}
+
+fn default_notification_interval_secs() -> u64 {
+    30
+}
+
+fn default_notification_max_attempts() -> u32 {
+    5
+}
+
+fn default_notification_retention_days() -> u32 {
+    7
+}
+
+/// Periodic checks that the hrefs of item and collection assets still
+/// resolve
+///
+/// Hrefs are checked with an S3 head request or an HTTP HEAD request and
+/// broken assets are listed for their owners at `/broken-assets`. As the
+/// server then makes requests to URLs chosen by users, checks are off unless
+/// enabled, and hrefs on loopback, private or link-local addresses are never
+/// requested.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LinkCheckConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// How often assets due for a check are looked for
+    #[serde(default = "default_link_check_interval_secs")]
+    pub interval_secs: u64,
+    /// Hours after which an asset is checked again
+    #[serde(default = "default_link_recheck_hours")]
+    pub recheck_hours: u32,
+}
+
+impl Default for LinkCheckConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_link_check_interval_secs(),
+            recheck_hours: default_link_recheck_hours(),
+        }
+    }
+}
+
+fn default_link_check_interval_secs() -> u64 {
+    60
+}
+
+fn default_link_recheck_hours() -> u32 {
+    24
+}
+
+/// Storage quotas of user and group namespaces
+///
+/// A namespace is the user or group owning collections. Its usage is the
+/// size of the assets of its collections and items plus the size of its
+/// feature tables with their indexes. Feature writes and imports into a
+/// namespace at or over its quota are rejected with 507 until data is
+/// deleted; reads and deletes keep working.
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuotaConfig {
+    /// Quota of namespaces without their own, in bytes; unlimited when unset
+    #[serde(default)]
+    pub default_bytes: Option<u64>,
+    /// Quotas by user or group name, in bytes
+    #[serde(default)]
+    pub namespaces: BTreeMap<String, u64>,
+    /// How long quota checks reuse the usage of a namespace, in seconds
+    #[serde(default = "default_quota_cache_secs")]
+    pub cache_secs: u64,
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        Self {
+            default_bytes: None,
+            namespaces: BTreeMap::new(),
+            cache_secs: default_quota_cache_secs(),
+        }
+    }
+}
+
+fn default_quota_cache_secs() -> u64 {
+    30
+}
+
+impl QuotaConfig {
+    /// Quota of a namespace in bytes, if it has one
+    pub fn limit(&self, namespace: &str) -> Option<u64> {
+        self.namespaces
+            .get(namespace)
+            .copied()
+            .or(self.default_bytes)
+    }
+}
+
+/// API groups served by this instance
+///
+/// Disabled groups are not routed, left out of the OpenAPI definition and
+/// the links of the landing page and collections, and their conformance
+/// classes are not declared. Collections and features are always served, so
+/// e.g. a minimal read-only replica disables everything else and
+/// transactions.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModulesConfig {
+    /// Vector tiles and tile matrix sets (OGC API Tiles)
+    #[serde(default = "default_module_enabled")]
+    pub tiles: bool,
+    /// Coverages and EDR queries of raster collections
+    #[serde(default = "default_module_enabled")]
+    pub coverages: bool,
+    /// Processes, jobs and uploads (OGC API Processes)
+    #[serde(default = "default_module_enabled")]
+    pub processes: bool,
+    /// 3D Tiles of point cloud collections
+    #[serde(default = "default_module_enabled")]
+    pub pointclouds: bool,
+    /// Metadata records of the collections (OGC API Records)
+    #[serde(default = "default_module_enabled")]
+    pub records: bool,
+    /// STAC catalog, item search and queryables
+    #[serde(default = "default_module_enabled")]
+    pub stac: bool,
+    /// Creating, replacing and deleting collections and features (OGC API
+    /// Features Part 4, STAC transactions); reads only when disabled
+    #[serde(default = "default_module_enabled")]
+    pub transactions: bool,
+}
+
+impl Default for ModulesConfig {
+    fn default() -> Self {
+        Self {
+            tiles: true,
+            coverages: true,
+            processes: true,
+            pointclouds: true,
+            records: true,
+            stac: true,
+            transactions: true,
+        }
+    }
+}
+
+fn default_module_enabled() -> bool {
+    true
+}
+
+/// A SpatialVault instance collections are pushed to
+#[derive(Clone, Deserialize)]
+pub struct RemoteInstance {
+    /// Base URL of the remote API, e.g. `https://public.example.com`
+    pub url: String,
+    /// API key or token the remote accepts as a bearer token
+    pub api_key: String,
+}
+
+// Custom Debug implementation to redact the API key
+impl fmt::Debug for RemoteInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteInstance")
+            .field("url", &self.url)
+            .field("api_key", &"[REDACTED]")
+            .finish()
+    }
+}
+
+/// Signed tile URL settings
+#[derive(Clone, Deserialize)]
+pub struct TileSigningConfig {
+    /// Secret the HMAC signatures of signed tile URLs are made with; signed
+    /// URLs are disabled without it. All servers must share the same key.
+    pub key: Option<String>,
+    /// Longest lifetime of a signed tile URL
+    #[serde(default = "default_max_signed_url_secs")]
+    pub max_expiry_secs: u64,
+}
+
+impl Default for TileSigningConfig {
+    fn default() -> Self {
+        Self {
+            key: None,
+            max_expiry_secs: default_max_signed_url_secs(),
+        }
+    }
+}
+
+// Custom Debug implementation to prevent the key from being logged
+impl fmt::Debug for TileSigningConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TileSigningConfig")
+            .field("key", &self.key.as_ref().map(|_| "[REDACTED]"))
+            .field("max_expiry_secs", &self.max_expiry_secs)
+            .finish()
+    }
+}
+
+fn default_max_signed_url_secs() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+/// Scoped token settings
+///
+/// Users exchange their access token at `/tokens` for one restricted to some
+/// collections and actions, signed by the service.
+/// Tokens are revoked before they expire with `spatialvault admin
+/// revoke-api-key`.
+#[derive(Clone, Deserialize)]
+pub struct ScopedTokenConfig {
+    /// Secret the tokens are signed with; exchange is disabled without it.
+    /// All servers must share the same key.
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Longest lifetime of a scoped token
+    #[serde(default = "default_max_scoped_token_secs")]
+    pub max_expiry_secs: u64,
+}
+
+impl Default for ScopedTokenConfig {
+    fn default() -> Self {
+        Self {
+            key: None,
+            max_expiry_secs: default_max_scoped_token_secs(),
+        }
+    }
+}
+
+// Custom Debug implementation to prevent the key from being logged
+impl fmt::Debug for ScopedTokenConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScopedTokenConfig")
+            .field("key", &self.key.as_ref().map(|_| "[REDACTED]"))
+            .field("max_expiry_secs", &self.max_expiry_secs)
+            .finish()
+    }
+}
+
+fn default_max_scoped_token_secs() -> u64 {
+    30 * 24 * 60 * 60
+}
+
+/// Translations of the service's own texts
+///
+/// Collections carry their own translations; these cover the landing page
+/// and the STAC catalog root. Keys are language tags, e.g. `sv` or `en-GB`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LocalizationConfig {
+    /// Title of the service per language
+    #[serde(default)]
+    pub title: BTreeMap<String, String>,
+    /// Description of the service per language
+    #[serde(default)]
+    pub description: BTreeMap<String, String>,
+}
+
+/// OpenTelemetry export settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP collector endpoint, e.g. `http://otel-collector:4318`; export is off when unset
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+    /// `service.name` reported with traces and metrics
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+    /// Fraction of new traces to sample (requests with a sampled `traceparent` are always kept)
+    #[serde(default = "default_sample_ratio")]
+    pub sample_ratio: f64,
+    /// Format of the log output on stdout
+    #[serde(default)]
+    pub log_format: LogFormat,
+}
+
+/// Log output format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// One JSON object per event, including the fields of the request span
+    Json,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: default_service_name(),
+            sample_ratio: default_sample_ratio(),
+            log_format: LogFormat::default(),
+        }
+    }
+}
+
+fn default_service_name() -> String {
+    "spatialvault".to_string()
+}
+
+fn default_sample_ratio() -> f64 {
+    1.0
+}
+
 impl Config {
     pub fn load() -> Result<Arc<Self>, config::ConfigError> {
         let config = config::Config::builder()
@@ -125,9 +1240,40 @@ impl Config {
             )
             .build()?;
 
-        let settings: Config = config.try_deserialize()?;
+        let mut settings: Config = config.try_deserialize()?;
+        settings.apply_path_prefix()?;
         Ok(Arc::new(settings))
     }
+
+    /// Normalize `path_prefix` to `/segment/...` and make `base_url` end
+    /// with it, so generated links include it
+    fn apply_path_prefix(&mut self) -> Result<(), config::ConfigError> {
+        let prefix = self.path_prefix.trim_matches('/');
+        if prefix.is_empty() {
+            self.path_prefix = String::new();
+            return Ok(());
+        }
+        if prefix.split('/').any(|segment| {
+            segment.is_empty()
+                || !segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~'))
+        }) {
+            return Err(config::ConfigError::Message(format!(
+                "Invalid path_prefix: {}",
+                self.path_prefix
+            )));
+        }
+
+        self.path_prefix = format!("/{}", prefix);
+        let base_url = self.base_url.trim_end_matches('/');
+        self.base_url = if base_url.ends_with(&self.path_prefix) {
+            base_url.to_string()
+        } else {
+            format!("{}{}", base_url, self.path_prefix)
+        };
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -139,5 +1285,55 @@ mod tests {
         assert_eq!(default_host(), "0.0.0.0");
         assert_eq!(default_port(), 8080);
         assert_eq!(default_service_role(), "spatialvault_service");
+        assert_eq!(ProcessingConfig::default().container_runtime, "docker");
+        assert_eq!(
+            ProcessingConfig::default().successful_job_retention_days,
+            30
+        );
+        assert_eq!(ProcessingConfig::default().failed_job_retention_days, 90);
+    }
+
+    #[test]
+    fn test_apply_path_prefix() {
+        let mut config: Config = serde_json::from_value(serde_json::json!({
+            "database": { "url": "postgres://localhost/test" },
+            "oidc": { "issuer_url": "http://localhost" },
+            "base_url": "https://example.com/",
+            "path_prefix": "geo/api/v1/"
+        }))
+        .unwrap();
+        config.apply_path_prefix().unwrap();
+        assert_eq!(config.path_prefix, "/geo/api/v1");
+        assert_eq!(config.base_url, "https://example.com/geo/api/v1");
+
+        // Already part of the base URL
+        config.apply_path_prefix().unwrap();
+        assert_eq!(config.base_url, "https://example.com/geo/api/v1");
+
+        config.path_prefix = "/geo//v1".to_string();
+        assert!(config.apply_path_prefix().is_err());
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let processing = ProcessingConfig::default();
+        assert_eq!(processing.retry_delay(1), Duration::from_secs(30));
+        assert_eq!(processing.retry_delay(2), Duration::from_secs(60));
+        assert_eq!(processing.retry_delay(3), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn test_quota_limit() {
+        let quotas = QuotaConfig::default();
+        assert_eq!(quotas.limit("alice"), None);
+
+        let quotas: QuotaConfig = serde_json::from_value(serde_json::json!({
+            "default_bytes": 1000,
+            "namespaces": { "survey": 5000 }
+        }))
+        .unwrap();
+        assert_eq!(quotas.limit("alice"), Some(1000));
+        assert_eq!(quotas.limit("survey"), Some(5000));
+        assert_eq!(quotas.cache_secs, 30);
     }
 }
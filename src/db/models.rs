@@ -3,6 +3,9 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use crate::auth::quote_ident;
+use crate::error::{AppError, AppResult};
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Collection {
     pub id: Uuid,
@@ -16,6 +19,84 @@ pub struct Collection {
     pub version: i64,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
+    /// When the features or items last changed, as opposed to the metadata;
+    /// set as the writing transaction commits
+    pub data_updated_at: Option<DateTime<Utc>>,
+    /// Page size override for item listings
+    pub default_limit: Option<i32>,
+    /// Maximum page size override for item listings
+    pub max_limit: Option<i32>,
+    /// Lowest zoom level tiles are served at
+    pub min_zoom: Option<i32>,
+    /// Highest zoom level tiles are served at
+    pub max_zoom: Option<i32>,
+    /// Vector tile layer name override
+    pub tile_layer: Option<String>,
+    /// Properties encoded into vector tiles per zoom range
+    pub tile_properties: Option<serde_json::Value>,
+    /// Import options used when an execute request leaves them out
+    pub processing_defaults: Option<serde_json::Value>,
+    /// Keywords/themes used for catalog discovery
+    pub keywords: Vec<String>,
+    /// License of the data (SPDX identifier, `proprietary` or `various`)
+    pub license: Option<String>,
+    /// Translations of the title by language tag
+    pub title_i18n: Option<serde_json::Value>,
+    /// Translations of the description by language tag
+    pub description_i18n: Option<serde_json::Value>,
+    /// Relations to other collections usable in filters
+    pub relations: Option<serde_json::Value>,
+    /// Read-only properties computed from expressions
+    pub computed_properties: Option<serde_json::Value>,
+    /// Properties whose values must be unique among the features
+    pub unique_properties: Vec<String>,
+    /// Secondary geometry columns, generated from the property of the same name
+    pub geometry_columns: Vec<String>,
+    /// Whether signed tile URLs may serve the tiles without credentials
+    pub public_tiles: bool,
+    /// When a scratch collection is deleted
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Collection {
+    /// Name of the collection's layer in vector tiles
+    ///
+    /// Defaults to the last segment of the canonical name.
+    pub fn tile_layer_name(&self) -> &str {
+        self.tile_layer.as_deref().unwrap_or_else(|| {
+            self.canonical_name
+                .rsplit(':')
+                .next()
+                .unwrap_or(&self.canonical_name)
+        })
+    }
+
+    /// Whether features are stored in a table of the owner's schema
+    pub fn has_feature_table(&self) -> bool {
+        matches!(self.collection_type.as_str(), "vector" | "table")
+    }
+
+    /// Whether the feature table has a geometry column
+    ///
+    /// Attribute-only `table` collections have none; their features are
+    /// returned with a null geometry.
+    pub fn has_geometry(&self) -> bool {
+        self.collection_type == "vector"
+    }
+
+    /// Quoted name of the geometry column selected by a `geom` parameter
+    ///
+    /// Defaults to the primary `geometry` column.
+    pub fn geometry_column(&self, geom: Option<&str>) -> AppResult<String> {
+        match geom {
+            None | Some("geometry") => Ok(quote_ident("geometry")),
+            Some(name) if self.geometry_columns.iter().any(|c| c == name) => Ok(quote_ident(name)),
+            Some(name) => Err(AppError::BadRequest(format!(
+                "Collection {} has no geometry column {}",
+                self.canonical_name, name
+            ))),
+        }
+    }
 }
 
 /// Collection with storage CRS included (used when fetching with metadata)
@@ -32,6 +113,28 @@ pub struct CollectionWithCrs {
     pub version: i64,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
+    pub data_updated_at: Option<DateTime<Utc>>,
+    pub default_limit: Option<i32>,
+    pub max_limit: Option<i32>,
+    pub min_zoom: Option<i32>,
+    pub max_zoom: Option<i32>,
+    pub tile_layer: Option<String>,
+    pub tile_properties: Option<serde_json::Value>,
+    pub processing_defaults: Option<serde_json::Value>,
+    pub keywords: Vec<String>,
+    pub license: Option<String>,
+    pub title_i18n: Option<serde_json::Value>,
+    pub description_i18n: Option<serde_json::Value>,
+    pub relations: Option<serde_json::Value>,
+    pub computed_properties: Option<serde_json::Value>,
+    pub unique_properties: Vec<String>,
+    pub geometry_columns: Vec<String>,
+    pub public_tiles: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// PostGIS type of the geometry column, e.g. `POINT`
+    pub geometry_type: Option<String>,
+    /// Coordinate dimension of the geometry column
+    pub geometry_dimension: Option<i32>,
     pub storage_crs: i32,
 }
 
@@ -49,6 +152,24 @@ impl CollectionWithCrs {
             version: self.version,
             created_at: self.created_at,
             updated_at: self.updated_at,
+            data_updated_at: self.data_updated_at,
+            default_limit: self.default_limit,
+            max_limit: self.max_limit,
+            min_zoom: self.min_zoom,
+            max_zoom: self.max_zoom,
+            tile_layer: self.tile_layer.clone(),
+            tile_properties: self.tile_properties.clone(),
+            processing_defaults: self.processing_defaults.clone(),
+            keywords: self.keywords.clone(),
+            license: self.license.clone(),
+            title_i18n: self.title_i18n.clone(),
+            description_i18n: self.description_i18n.clone(),
+            relations: self.relations.clone(),
+            computed_properties: self.computed_properties.clone(),
+            unique_properties: self.unique_properties.clone(),
+            geometry_columns: self.geometry_columns.clone(),
+            public_tiles: self.public_tiles,
+            expires_at: self.expires_at,
         }
     }
 }
@@ -58,6 +179,8 @@ pub enum CollectionType {
     Vector,
     Raster,
     PointCloud,
+    /// Attribute-only features without geometry
+    Table,
 }
 
 impl CollectionType {
@@ -66,6 +189,7 @@ impl CollectionType {
             CollectionType::Vector => "vector",
             CollectionType::Raster => "raster",
             CollectionType::PointCloud => "pointcloud",
+            CollectionType::Table => "table",
         }
     }
 
@@ -74,6 +198,7 @@ impl CollectionType {
             "vector" => Some(CollectionType::Vector),
             "raster" => Some(CollectionType::Raster),
             "pointcloud" => Some(CollectionType::PointCloud),
+            "table" => Some(CollectionType::Table),
             _ => None,
         }
     }
@@ -113,6 +238,123 @@ pub struct Asset {
     pub created_at: Option<DateTime<Utc>>,
 }
 
+/// Asset attached to a collection itself
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CollectionAsset {
+    pub id: Uuid,
+    pub collection_id: Uuid,
+    pub key: String,
+    pub href: String,
+    #[sqlx(rename = "type")]
+    pub media_type: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub roles: Option<Vec<String>>,
+    pub file_size: Option<i64>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Item or collection asset whose href was found broken by the link check
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BrokenAsset {
+    /// Canonical name of the collection
+    pub collection: String,
+    /// Item the asset belongs to; none for assets of the collection itself
+    pub item_id: Option<Uuid>,
+    pub key: String,
+    pub href: String,
+    pub link_checked_at: Option<DateTime<Utc>>,
+}
+
+/// Storage used by a namespace or a collection, in bytes
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, FromRow)]
+pub struct StorageUsage {
+    /// Size of the assets of the collections and their items
+    pub asset_bytes: i64,
+    /// Size of the feature tables with their indexes
+    pub table_bytes: i64,
+}
+
+impl StorageUsage {
+    pub fn total_bytes(&self) -> i64 {
+        self.asset_bytes + self.table_bytes
+    }
+}
+
+/// Point-in-time copy of a collection's feature table
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CollectionSnapshot {
+    pub collection_id: Uuid,
+    pub name: String,
+    /// Table in the `spatialvault_snapshots` schema holding the copy
+    pub table_name: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub collection_version: i64,
+    pub feature_count: i64,
+}
+
+/// Publication of a collection to another SpatialVault instance
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CollectionReplication {
+    pub id: Uuid,
+    pub collection_id: Uuid,
+    /// Name of the remote instance in the configuration
+    pub remote: String,
+    /// Collection id on the remote
+    pub remote_collection: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub synced_version: i64,
+    /// Changes up to this time have been pushed
+    pub synced_until: Option<DateTime<Utc>>,
+    /// When the last push finished
+    pub synced_at: Option<DateTime<Utc>>,
+    pub claimed_until: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+/// A change to the metadata or schema of a collection
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CollectionEvent {
+    pub id: i64,
+    pub collection_id: Uuid,
+    /// `metadata` or `schema`
+    pub event_type: String,
+    /// Collection version after the change
+    pub collection_version: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A URL the events of a collection are posted to
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CollectionWebhook {
+    pub id: Uuid,
+    pub collection_id: Uuid,
+    pub url: String,
+    /// Key of the signature header; never returned by the API
+    #[serde(skip_serializing)]
+    pub secret: Option<String>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    /// Time and id of the last event delivered
+    pub delivered_until: DateTime<Utc>,
+    pub delivered_event_id: i64,
+    /// When an event was last delivered
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub claimed_until: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+/// What a user wants to be notified about
+#[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow)]
+pub struct NotificationSettings {
+    pub email: Option<String>,
+    pub collection_shared: bool,
+    pub job_finished: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct ProcessJob {
     pub id: Uuid,
@@ -129,6 +371,60 @@ pub struct ProcessJob {
     pub started: Option<DateTime<Utc>>,
     pub finished: Option<DateTime<Utc>>,
     pub updated: Option<DateTime<Utc>>,
+    /// Parent workflow job, if this job is a workflow step
+    pub parent_job_id: Option<Uuid>,
+    /// Position of this job within its parent workflow
+    pub step_index: Option<i32>,
+    /// Explicit expiry overriding the retention policy
+    pub expires: Option<DateTime<Utc>>,
+    /// Number of times the job has been started
+    pub attempts: i32,
+    /// Attempt limit, fixed when the job is first started
+    pub max_attempts: Option<i32>,
+    /// Earliest time the next retry may start
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    /// Errors of failed attempts, oldest first
+    pub errors: serde_json::Value,
+}
+
+/// Chunked upload of a process input, assembled with an S3 multipart upload
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Upload {
+    pub id: Uuid,
+    pub owner: String,
+    /// S3 key of the assembled file
+    pub object_key: String,
+    /// S3 multipart upload id
+    pub multipart_id: String,
+    pub media_type: Option<String>,
+    /// `open` while parts are accepted, `complete` once assembled
+    pub status: String,
+    /// Total size once complete
+    pub size: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// A part received for an upload
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UploadPart {
+    pub part_number: i32,
+    pub etag: String,
+    pub size: i64,
+}
+
+/// User-defined process deployed via OGC API Processes Part 2
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DeployedProcess {
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub version: String,
+    pub owner: String,
+    pub process_description: serde_json::Value,
+    pub execution_unit: serde_json::Value,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -163,3 +459,64 @@ impl JobStatus {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collection(canonical_name: &str, tile_layer: Option<&str>) -> Collection {
+        Collection {
+            id: Uuid::nil(),
+            canonical_name: canonical_name.to_string(),
+            owner: "alice".to_string(),
+            schema_name: "alice".to_string(),
+            table_name: "roads".to_string(),
+            collection_type: "vector".to_string(),
+            title: "Roads".to_string(),
+            description: None,
+            version: 1,
+            created_at: None,
+            updated_at: None,
+            data_updated_at: None,
+            default_limit: None,
+            max_limit: None,
+            min_zoom: None,
+            max_zoom: None,
+            tile_layer: tile_layer.map(str::to_string),
+            tile_properties: None,
+            processing_defaults: None,
+            keywords: Vec::new(),
+            license: None,
+            title_i18n: None,
+            description_i18n: None,
+            relations: None,
+            computed_properties: None,
+            unique_properties: Vec::new(),
+            geometry_columns: Vec::new(),
+            public_tiles: false,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_tile_layer_name() {
+        assert_eq!(
+            collection("alice:maps:roads", None).tile_layer_name(),
+            "roads"
+        );
+        assert_eq!(collection("roads", None).tile_layer_name(), "roads");
+        assert_eq!(
+            collection("alice:roads", Some("streets")).tile_layer_name(),
+            "streets"
+        );
+    }
+
+    #[test]
+    fn test_geometry_column() {
+        let mut roads = collection("alice:roads", None);
+        roads.geometry_columns = vec!["label".to_string()];
+        assert_eq!(roads.geometry_column(None).unwrap(), "\"geometry\"");
+        assert_eq!(roads.geometry_column(Some("label")).unwrap(), "\"label\"");
+        assert!(roads.geometry_column(Some("properties")).is_err());
+    }
+}
@@ -1,35 +1,140 @@
+use opentelemetry::{KeyValue, global};
 use sqlx::{
-    Executor,
-    postgres::{PgPool, PgPoolOptions},
+    Executor, Postgres, Transaction,
+    postgres::{PgConnectOptions, PgPool, PgPoolOptions},
 };
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::config::DatabaseConfig;
+use crate::config::{DatabaseConfig, StatementTimeouts};
 use crate::error::{AppError, AppResult};
 
+/// Classes of queries with their own statement timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryClass {
+    /// Vector tile rendering, which should be quick
+    Tiles,
+    /// Feature and item listing with filters and counts
+    Features,
+    /// Long running work such as exports, run on the background pool
+    Background,
+}
+
+impl StatementTimeouts {
+    /// Timeout in milliseconds for a query class
+    pub fn for_class(&self, class: QueryClass) -> u64 {
+        match class {
+            QueryClass::Tiles => self.tiles_ms,
+            QueryClass::Features => self.features_ms,
+            QueryClass::Background => self.background_ms,
+        }
+    }
+}
+
+/// Commit a write, or roll it back when the client only asked to validate it
+///
+/// Everything up to the commit runs as usual, so a validated write fails the
+/// same way the real write would.
+pub async fn finish_write(tx: Transaction<'_, Postgres>, validate_only: bool) -> AppResult<()> {
+    if validate_only {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+/// Connection pools for interactive and background queries
+///
+/// The background pool is separate so that long exports can't take the
+/// connections interactive requests need. Both are bounded: a request that
+/// can't get a connection within `acquire_timeout_ms` fails with 503 instead
+/// of queueing.
 #[derive(Clone)]
 pub struct Database {
     pool: PgPool,
+    background: PgPool,
     service_role: String,
+    timeouts: StatementTimeouts,
 }
 
 impl Database {
+    /// Connect for serving API requests
+    ///
+    /// The background pool connects lazily, on its first use.
     pub async fn connect(config: &DatabaseConfig) -> AppResult<Self> {
-        let pool = PgPoolOptions::new()
-            .max_connections(config.max_connections)
-            .connect(&config.url)
+        let pool = pool_options(config, config.max_connections)
+            .connect_with(connect_options(
+                config,
+                config.statement_timeouts.default_ms,
+            )?)
             .await?;
+        let background = pool_options(config, config.background_max_connections).connect_lazy_with(
+            connect_options(config, config.statement_timeouts.background_ms)?,
+        );
+        register_pool_metrics("interactive", &pool);
+        register_pool_metrics("background", &background);
+        Ok(Self::with_pools(config, pool, background))
+    }
 
-        Ok(Self {
+    /// Connect for the background worker, whose imports may run long
+    ///
+    /// All of the worker's queries are background queries, so both pools are
+    /// the same.
+    pub async fn connect_background(config: &DatabaseConfig) -> AppResult<Self> {
+        let pool = pool_options(config, config.max_connections)
+            .connect_with(connect_options(
+                config,
+                config.statement_timeouts.background_ms,
+            )?)
+            .await?;
+        register_pool_metrics("background", &pool);
+        Ok(Self::with_pools(config, pool.clone(), pool))
+    }
+
+    fn with_pools(config: &DatabaseConfig, pool: PgPool, background: PgPool) -> Self {
+        Self {
             pool,
+            background,
             service_role: config.service_role.clone(),
-        })
+            timeouts: config.statement_timeouts.clone(),
+        }
     }
 
+    /// Pool for interactive API requests
     pub fn pool(&self) -> &PgPool {
         &self.pool
     }
 
+    /// Pool for background and export queries
+    pub fn background_pool(&self) -> &PgPool {
+        &self.background
+    }
+
+    /// Begin a transaction whose statements are limited to the budget of `class`
+    ///
+    /// A statement over budget fails with `query_canceled`, answered with 504.
+    pub async fn begin_with_budget(
+        &self,
+        class: QueryClass,
+    ) -> AppResult<Transaction<'static, Postgres>> {
+        let pool = match class {
+            QueryClass::Background => &self.background,
+            QueryClass::Tiles | QueryClass::Features => &self.pool,
+        };
+        let mut tx = pool.begin().await?;
+        tx.execute(
+            format!(
+                "SET LOCAL statement_timeout = {}",
+                self.timeouts.for_class(class)
+            )
+            .as_str(),
+        )
+        .await?;
+        Ok(tx)
+    }
+
     /// Execute a database operation with SET ROLE for the given user.
     /// This provides PostgreSQL-level access control based on OIDC identity.
     pub async fn with_role<F, T>(&self, username: &str, operation: F) -> AppResult<T>
@@ -96,6 +201,80 @@ impl Database {
             .map_err(|e| AppError::Internal(format!("Migration failed: {}", e)))?;
         Ok(())
     }
+
+    /// Number of migrations not yet applied to the database
+    pub async fn pending_migrations(&self) -> AppResult<usize> {
+        let migrator = sqlx::migrate!("./migrations");
+        let initialized: bool =
+            sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+                .fetch_one(&self.pool)
+                .await?;
+        let applied: Vec<i64> = if initialized {
+            sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+                .fetch_all(&self.pool)
+                .await?
+        } else {
+            Vec::new()
+        };
+
+        Ok(migrator
+            .iter()
+            .filter(|migration| {
+                !migration.migration_type.is_down_migration()
+                    && !applied.contains(&migration.version)
+            })
+            .count())
+    }
+}
+
+fn pool_options(config: &DatabaseConfig, max_connections: u32) -> PgPoolOptions {
+    PgPoolOptions::new()
+        .max_connections(max_connections)
+        .acquire_timeout(Duration::from_millis(config.acquire_timeout_ms))
+}
+
+fn connect_options(config: &DatabaseConfig, timeout_ms: u64) -> AppResult<PgConnectOptions> {
+    Ok(PgConnectOptions::from_str(&config.url)?
+        .options([("statement_timeout", timeout_ms.to_string())]))
+}
+
+/// Report connection counts of a pool as OpenTelemetry gauges
+fn register_pool_metrics(name: &'static str, pool: &PgPool) {
+    let meter = global::meter("spatialvault");
+    let observed = pool.clone();
+    meter
+        .u64_observable_gauge("db.client.connection.count")
+        .with_description("Connections of the pool by state")
+        .with_callback(move |observer| {
+            let idle = observed.num_idle() as u64;
+            let size = u64::from(observed.size());
+            observer.observe(
+                idle,
+                &[
+                    KeyValue::new("db.client.connection.pool.name", name),
+                    KeyValue::new("db.client.connection.state", "idle"),
+                ],
+            );
+            observer.observe(
+                size.saturating_sub(idle),
+                &[
+                    KeyValue::new("db.client.connection.pool.name", name),
+                    KeyValue::new("db.client.connection.state", "used"),
+                ],
+            );
+        })
+        .build();
+    let observed = pool.clone();
+    meter
+        .u64_observable_gauge("db.client.connection.max")
+        .with_description("Largest number of connections the pool may open")
+        .with_callback(move |observer| {
+            observer.observe(
+                u64::from(observed.options().get_max_connections()),
+                &[KeyValue::new("db.client.connection.pool.name", name)],
+            );
+        })
+        .build();
 }
 
 /// Validate that a role name is safe to use in SQL
@@ -140,6 +319,18 @@ mod tests {
         assert!(!is_valid_role_name("user name"));
     }
 
+    #[test]
+    fn test_statement_timeout_per_class() {
+        let timeouts = StatementTimeouts {
+            default_ms: 30_000,
+            tiles_ms: 2_000,
+            features_ms: 15_000,
+            background_ms: 0,
+        };
+        assert_eq!(timeouts.for_class(QueryClass::Tiles), 2_000);
+        assert_eq!(timeouts.for_class(QueryClass::Features), 15_000);
+    }
+
     #[test]
     fn test_quote_ident() {
         assert_eq!(quote_ident("simple"), "\"simple\"");
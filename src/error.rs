@@ -2,7 +2,7 @@ use aide::OperationOutput;
 use aide::openapi::{MediaType, Response as AideResponse};
 use axum::{
     Json,
-    http::StatusCode,
+    http::{StatusCode, header},
     response::{IntoResponse, Response},
 };
 use indexmap::IndexMap;
@@ -30,6 +30,12 @@ pub enum AppError {
     #[error("Precondition failed: {0}")]
     PreconditionFailed(String),
 
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+
+    #[error("Insufficient storage: {0}")]
+    InsufficientStorage(String),
+
     #[error("Internal server error: {0}")]
     Internal(String),
 
@@ -50,6 +56,72 @@ pub enum AppError {
 
     #[error("Processing error: {0}")]
     Processing(String),
+
+    #[error("Upstream error: {0}")]
+    Upstream(String),
+
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
+}
+
+impl AppError {
+    /// Whether the error is likely temporary, so that retrying may succeed
+    pub fn is_transient(&self) -> bool {
+        match self {
+            AppError::Storage(_) | AppError::Io(_) | AppError::Upstream(_) => true,
+            AppError::Database(e) => matches!(
+                e,
+                sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed
+            ),
+            _ => false,
+        }
+    }
+}
+
+/// Seconds clients are asked to wait when the connection pool is exhausted
+const RETRY_AFTER_SECS: &str = "2";
+
+/// Whether PostgreSQL cancelled the statement, e.g. for exceeding `statement_timeout`
+fn is_query_canceled(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == "57014")
+}
+
+/// Whether a write violated a unique constraint
+fn is_unique_violation(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == "23505")
+}
+
+/// The client-facing description of PostGIS rejecting a geometry whose
+/// type, dimension or SRID doesn't match the geometry column; other invalid
+/// parameter values are server errors
+fn geometry_mismatch(error: &sqlx::Error) -> Option<&'static str> {
+    let error = error.as_database_error()?;
+    if error.code().as_deref() != Some("22023") {
+        return None;
+    }
+    geometry_mismatch_description(error.message())
+}
+
+fn geometry_mismatch_description(message: &str) -> Option<&'static str> {
+    if message.starts_with("Geometry type (") && message.contains("does not match column type") {
+        Some("Geometry type does not match the geometry type of the collection")
+    } else if message.ends_with("dimension but geometry does not")
+        || message.ends_with("dimension but column does not")
+    {
+        Some("Geometry dimension does not match the geometry dimension of the collection")
+    } else if message.starts_with("Geometry SRID (")
+        && message.contains("does not match column SRID")
+    {
+        Some("Geometry CRS does not match the storage CRS of the collection")
+    } else {
+        None
+    }
 }
 
 #[derive(Debug, Serialize, JsonSchema)]
@@ -71,6 +143,16 @@ impl IntoResponse for AppError {
                 "PreconditionFailed",
                 msg.clone(),
             ),
+            AppError::UnsupportedMediaType(msg) => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "UnsupportedMediaType",
+                msg.clone(),
+            ),
+            AppError::InsufficientStorage(msg) => (
+                StatusCode::INSUFFICIENT_STORAGE,
+                "InsufficientStorage",
+                msg.clone(),
+            ),
             AppError::Internal(msg) => {
                 tracing::error!("Internal error: {}", msg);
                 (
@@ -79,6 +161,39 @@ impl IntoResponse for AppError {
                     "An internal error occurred".to_string(),
                 )
             }
+            AppError::Database(sqlx::Error::PoolTimedOut) => {
+                tracing::warn!("No database connection available; shedding request");
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "ServiceUnavailable",
+                    "The service is busy; retry later".to_string(),
+                )
+            }
+            AppError::Database(e) if is_query_canceled(e) => {
+                tracing::warn!("Query exceeded its time budget: {}", e);
+                (
+                    StatusCode::GATEWAY_TIMEOUT,
+                    "QueryTimeout",
+                    "The query took too long; narrow it down with bbox, filter or limit"
+                        .to_string(),
+                )
+            }
+            AppError::Database(e) if is_unique_violation(e) => {
+                let constraint = e
+                    .as_database_error()
+                    .and_then(|e| e.constraint())
+                    .unwrap_or("unique constraint");
+                (
+                    StatusCode::CONFLICT,
+                    "Conflict",
+                    format!("Duplicate value violates {}", constraint),
+                )
+            }
+            AppError::Database(e) if geometry_mismatch(e).is_some() => (
+                StatusCode::BAD_REQUEST,
+                "BadRequest",
+                geometry_mismatch(e).unwrap_or_default().to_string(),
+            ),
             AppError::Database(e) => {
                 tracing::error!("Database error: {}", e);
                 (
@@ -127,6 +242,19 @@ impl IntoResponse for AppError {
                     "A processing error occurred".to_string(),
                 )
             }
+            AppError::Upstream(msg) => {
+                tracing::error!("Upstream error: {}", msg);
+                (
+                    StatusCode::BAD_GATEWAY,
+                    "UpstreamError",
+                    "An upstream service failed".to_string(),
+                )
+            }
+            AppError::Unavailable(msg) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "ServiceUnavailable",
+                msg.clone(),
+            ),
         };
 
         let body = Json(ErrorResponse {
@@ -134,6 +262,9 @@ impl IntoResponse for AppError {
             description,
         });
 
+        if status == StatusCode::SERVICE_UNAVAILABLE {
+            return (status, [(header::RETRY_AFTER, RETRY_AFTER_SECS)], body).into_response();
+        }
         (status, body).into_response()
     }
 }
@@ -169,3 +300,50 @@ impl OperationOutput for AppError {
 }
 
 pub type AppResult<T> = Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_exhaustion_asks_to_retry() {
+        let response = AppError::Database(sqlx::Error::PoolTimedOut).into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response.headers().get(header::RETRY_AFTER).unwrap(),
+            RETRY_AFTER_SECS
+        );
+
+        let response = AppError::Unavailable("maintenance".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(response.headers().get(header::RETRY_AFTER).is_some());
+
+        let response = AppError::Database(sqlx::Error::RowNotFound).into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(response.headers().get(header::RETRY_AFTER).is_none());
+    }
+
+    #[test]
+    fn test_geometry_mismatch_description() {
+        assert_eq!(
+            geometry_mismatch_description(
+                "Geometry type (LineString) does not match column type (Point)"
+            ),
+            Some("Geometry type does not match the geometry type of the collection")
+        );
+        assert!(
+            geometry_mismatch_description("Column has Z dimension but geometry does not").is_some()
+        );
+        assert!(
+            geometry_mismatch_description("Geometry has M dimension but column does not").is_some()
+        );
+        assert!(
+            geometry_mismatch_description("Geometry SRID (4326) does not match column SRID (3006)")
+                .is_some()
+        );
+        assert_eq!(
+            geometry_mismatch_description("invalid value for parameter \"work_mem\": \"-1\""),
+            None
+        );
+    }
+}
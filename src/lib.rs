@@ -1,9 +1,16 @@
+pub mod admin;
 pub mod api;
 pub mod auth;
 pub mod config;
 pub mod db;
 pub mod error;
+pub mod net;
 pub mod openapi;
 pub mod processing;
+pub mod seed;
+pub mod server;
 pub mod services;
 pub mod storage;
+pub mod telemetry;
+#[cfg(feature = "testing")]
+pub mod testing;
@@ -1,50 +1,64 @@
 use aide::axum::ApiRouter;
-use axum::{Extension, Router, middleware};
+use axum::{Extension, Router, extract::DefaultBodyLimit, http::header, middleware};
 use std::env;
 use std::sync::Arc;
-use tokio::net::TcpListener;
-use tower_http::{
-    compression::CompressionLayer,
-    cors::{Any, CorsLayer},
-    trace::TraceLayer,
-};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use std::time::Duration;
+use tower_http::cors::{Any, CorsLayer};
 
 use spatialvault::{
-    api::{collections, conformance, coverages, features, landing, processes, stac, tiles},
-    auth::{AuthState, OidcValidator},
+    admin,
+    api::{
+        allow, broken_assets, collections, compression, conformance, coverages, edr, features,
+        forwarded::{self, Forwarding},
+        landing, modules, notifications, pointclouds, processes, quota,
+        read_only::{self, ReadOnly},
+        records, stac, tiles, tokens, uploads, usage,
+    },
+    auth::{AuthState, OidcValidator, ScopedTokens, policy::PolicyEvaluator},
     config::Config,
     db::Database,
     openapi,
     processing::JobWorker,
+    seed, server,
     services::{
-        CollectionService, CoverageService, FeatureService, ItemService, ProcessService,
-        StacService, TileService,
+        AnalyticsService, CdnService, CollectionService, CoverageService, FeatureService,
+        ItemService, LinkCheckService, NotificationService, PointCloudService, ProcessService,
+        QuotaService, ReplicationService, StacService, TileService, UploadService, WebhookService,
     },
     storage::S3Storage,
+    telemetry,
 };
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-                "spatialvault=debug,tower_http=debug,axum::rejection=trace".into()
-            }),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    // Check for worker mode
     let args: Vec<String> = env::args().collect();
-    let worker_mode = args.iter().any(|arg| arg == "--worker" || arg == "-w");
+
+    // Provisioning commands and the self-check keep stdout free of logs for scripts
+    if args.get(1).is_some_and(|arg| arg == "admin") {
+        return admin::run(&args[2..]).await;
+    }
+    if args.get(1).is_some_and(|arg| arg == "seed") {
+        return seed::run(&args[2..]).await;
+    }
+    if args.iter().any(|arg| arg == "--check") {
+        return admin::check().await;
+    }
 
     // Load configuration
     let config = Config::load()?;
 
-    // Connect to database
-    let db = Arc::new(Database::connect(&config.database).await?);
+    // Initialize tracing, with OpenTelemetry export when configured
+    let _telemetry = telemetry::init(&config.telemetry)?;
+
+    // Check for worker mode
+    let worker_mode = args.iter().any(|arg| arg == "--worker" || arg == "-w");
+
+    // Connect to database; the worker gets the longer background statement timeout
+    let db = Arc::new(if worker_mode {
+        Database::connect_background(&config.database).await?
+    } else {
+        Database::connect(&config.database).await?
+    });
     tracing::info!("Connected to database");
 
     // Run migrations
@@ -59,10 +73,16 @@ async fn main() -> anyhow::Result<()> {
     let collection_service = Arc::new(CollectionService::new(db.clone()));
     let feature_service = Arc::new(FeatureService::new(db.clone()));
     let tile_service = Arc::new(TileService::new(db.clone()));
+    let analytics_service = Arc::new(AnalyticsService::new(db.clone()));
     let coverage_service = Arc::new(CoverageService::new(db.clone()));
     let process_service = Arc::new(ProcessService::new(db.clone()));
-    let stac_service = Arc::new(StacService::new(db.clone(), config.base_url.clone()));
+    let stac_service = Arc::new(StacService::new(db.clone()));
     let item_service = Arc::new(ItemService::new(db.clone()));
+    let pointcloud_service = Arc::new(PointCloudService::new(db.clone(), storage.clone()));
+    let upload_service = Arc::new(UploadService::new(db.clone(), storage.clone()));
+    let notification_service =
+        Arc::new(NotificationService::new(db.clone(), &config.notifications));
+    let quota_service = Arc::new(QuotaService::new(db.clone(), &config.quotas));
 
     if worker_mode {
         // Run as background job worker
@@ -74,6 +94,8 @@ async fn main() -> anyhow::Result<()> {
             process_service,
             item_service,
             collection_service,
+            quota_service,
+            config.processing.clone(),
         );
 
         worker.run().await?;
@@ -85,11 +107,73 @@ async fn main() -> anyhow::Result<()> {
         let oidc_validator = Arc::new(OidcValidator::new(config.oidc.clone()).await?);
         tracing::info!("OIDC validator initialized");
 
+        // Consult the external authorization policy, when configured
+        let policy = PolicyEvaluator::new(&config.policy)?.map(Arc::new);
+        if policy.is_some() {
+            tracing::info!("Authorization policy enabled");
+        }
+
         // Build auth state
         let auth_state = AuthState {
             validator: oidc_validator,
+            policy,
+            service_accounts: Arc::new(config.service_accounts.clone()),
+            scoped_tokens: ScopedTokens::new(&config.scoped_tokens, &config.base_url)
+                .map(|tokens| Arc::new(tokens.with_revocations(db.clone()))),
         };
 
+        // Recompute the cached extents of collections whose data changed
+        let extent_interval = Duration::from_secs(config.discovery.extent_interval_secs);
+        let extents = collection_service.clone();
+        tokio::spawn(async move { extents.run(extent_interval).await });
+
+        // Write buffered tile usage counts periodically
+        let flush_interval = Duration::from_secs(config.analytics.flush_interval_secs);
+        let analytics = analytics_service.clone();
+        tokio::spawn(async move { analytics.run(flush_interval).await });
+
+        // Purge cached tiles of changed collections from the CDN
+        if let Some(cdn) = CdnService::new(db.clone(), &config.cdn, &config.base_url) {
+            let purge_interval = Duration::from_secs(config.cdn.purge_interval_secs);
+            tokio::spawn(async move { cdn.run(purge_interval).await });
+        }
+
+        // Push changes of published collections to remote instances
+        if let Some(replication) = ReplicationService::new(db.clone(), &config.replication) {
+            let replication_interval = Duration::from_secs(config.replication.interval_secs);
+            tokio::spawn(async move { replication.run(replication_interval).await });
+        }
+
+        // Post collection metadata and schema events to webhooks
+        if let Some(webhooks) = WebhookService::new(db.clone(), &config.webhooks) {
+            let webhook_interval = Duration::from_secs(config.webhooks.interval_secs);
+            tokio::spawn(async move { webhooks.run(webhook_interval).await });
+        }
+
+        // Send queued share and job notifications to the notification service
+        if notification_service.is_enabled() {
+            let notification_interval = Duration::from_secs(config.notifications.interval_secs);
+            let notifications = notification_service.clone();
+            tokio::spawn(async move { notifications.run(notification_interval).await });
+        }
+
+        // Check that the hrefs of assets still resolve
+        if let Some(link_check) =
+            LinkCheckService::new(db.clone(), storage.clone(), &config.link_check)
+        {
+            let link_check_interval = Duration::from_secs(config.link_check.interval_secs);
+            tokio::spawn(async move { link_check.run(link_check_interval).await });
+        }
+
+        // Honor forwarded headers of trusted proxies
+        let forwarding = Arc::new(Forwarding::new(config.clone())?);
+
+        // Reject writes on read-only instances and during maintenance
+        let read_only = Arc::new(ReadOnly::new(db.clone(), &config));
+        if config.read_only {
+            tracing::info!("Serving read-only");
+        }
+
         // Build router with OpenAPI generation
         let app = build_router(
             config.clone(),
@@ -97,17 +181,21 @@ async fn main() -> anyhow::Result<()> {
             collection_service,
             feature_service,
             tile_service,
+            analytics_service,
             coverage_service,
+            pointcloud_service,
             process_service,
             stac_service,
+            upload_service,
+            notification_service,
+            quota_service,
+            storage,
+            forwarding,
+            read_only,
         );
 
-        // Start server
-        let addr = format!("{}:{}", config.host, config.port);
-        let listener = TcpListener::bind(&addr).await?;
-        tracing::info!("Listening on {}", addr);
-
-        axum::serve(listener, app).await?;
+        // Start server on all configured listeners
+        server::serve(&config, app).await?;
     }
 
     Ok(())
@@ -119,38 +207,134 @@ fn build_router(
     collection_service: Arc<CollectionService>,
     feature_service: Arc<FeatureService>,
     tile_service: Arc<TileService>,
+    analytics_service: Arc<AnalyticsService>,
     coverage_service: Arc<CoverageService>,
+    pointcloud_service: Arc<PointCloudService>,
     process_service: Arc<ProcessService>,
     stac_service: Arc<StacService>,
+    upload_service: Arc<UploadService>,
+    notification_service: Arc<NotificationService>,
+    quota_service: Arc<QuotaService>,
+    storage: Arc<S3Storage>,
+    forwarding: Arc<Forwarding>,
+    read_only: Arc<ReadOnly>,
 ) -> Router {
     // Create base OpenAPI spec with metadata
     let mut openapi = openapi::create_openapi(&config);
+    if let (Some(client_id), Some((authorization_url, token_url))) = (
+        config.oidc.console_client_id.as_ref(),
+        auth_state.validator.oauth2_endpoints(),
+    ) {
+        openapi::add_console_auth(
+            &mut openapi,
+            &openapi::ConsoleAuth {
+                authorization_url: authorization_url.to_string(),
+                token_url: token_url.to_string(),
+                client_id: client_id.clone(),
+                scopes: config
+                    .oidc
+                    .console_scopes
+                    .split_whitespace()
+                    .map(str::to_string)
+                    .collect(),
+                redirect_url: format!("{}/docs", config.base_url),
+            },
+        );
+    }
 
     // Public routes (no auth required)
-    let public_routes = ApiRouter::new()
+    let mut public_routes = ApiRouter::new()
         .merge(landing::routes())
         .merge(conformance::routes())
-        .merge(openapi::docs_routes())
-        .merge(stac::catalog::routes());
+        .merge(openapi::docs_routes());
 
-    // Protected routes (auth required)
-    let protected_routes = ApiRouter::new()
+    // Creating, changing and deleting collections and features
+    let mut transaction_routes = ApiRouter::new()
         .merge(collections::handlers::routes(collection_service.clone()))
+        .merge(features::handlers::routes(feature_service.clone()))
+        .merge(features::locks::routes(feature_service.clone()))
+        .merge(features::assets::routes(
+            feature_service.clone(),
+            storage.clone(),
+        ));
+    if !config.modules.transactions {
+        transaction_routes =
+            transaction_routes.layer(middleware::from_fn(modules::reject_transactions));
+    }
+
+    // Protected routes (auth required)
+    let mut protected_routes = ApiRouter::new()
+        .merge(transaction_routes)
         .merge(collections::sharing::routes(collection_service.clone()))
-        .merge(features::handlers::routes(
-            feature_service,
+        .merge(collections::relations::routes(collection_service.clone()))
+        .merge(collections::snapshots::routes(collection_service.clone()))
+        .merge(collections::replications::routes(
             collection_service.clone(),
         ))
-        .merge(tiles::handlers::routes(
-            tile_service,
-            collection_service.clone(),
-        ))
-        .merge(coverages::handlers::routes(
-            coverage_service,
-            collection_service.clone(),
+        .merge(collections::events::routes(collection_service.clone()))
+        .merge(collections::computed::routes(collection_service.clone()))
+        .merge(collections::assets::routes(collection_service.clone()))
+        .merge(collections::metadata::routes(collection_service.clone()))
+        .merge(collections::analytics::routes(analytics_service.clone()))
+        .merge(broken_assets::routes(collection_service.clone()))
+        .merge(usage::routes(quota_service.clone()))
+        .merge(features::export::routes(feature_service.clone()))
+        .merge(features::gml::routes(feature_service))
+        .merge(tokens::routes(auth_state.scoped_tokens.clone()))
+        .merge(notifications::routes(notification_service));
+
+    // Optional API groups
+    if config.modules.tiles {
+        public_routes = public_routes.merge(tiles::signed::public_routes(
+            tile_service.clone(),
+            analytics_service.clone(),
+        ));
+        if config.modules.coverages {
+            protected_routes = protected_routes.merge(coverages::preview::routes(
+                tile_service.clone(),
+                analytics_service.clone(),
+            ));
+        }
+        protected_routes = protected_routes
+            .merge(tiles::handlers::routes(tile_service, analytics_service))
+            .merge(tiles::signed::routes());
+    }
+    if config.modules.coverages {
+        protected_routes = protected_routes
+            .merge(coverages::handlers::routes(coverage_service.clone()))
+            .merge(coverages::mosaic::routes(coverage_service.clone()))
+            .merge(edr::handlers::routes(coverage_service));
+    }
+    if config.modules.processes {
+        protected_routes = protected_routes
+            .merge(processes::handlers::routes(
+                process_service,
+                storage,
+                upload_service.clone(),
+            ))
+            .merge(uploads::handlers::routes(upload_service));
+    }
+    if config.modules.pointclouds {
+        protected_routes =
+            protected_routes.merge(pointclouds::handlers::routes(pointcloud_service));
+    }
+    if config.modules.records {
+        protected_routes = protected_routes.merge(records::routes(collection_service.clone()));
+    }
+    if config.modules.stac {
+        public_routes = public_routes.merge(stac::catalog::routes());
+        protected_routes = protected_routes
+            .merge(stac::item::routes(stac_service.clone()))
+            .merge(stac::queryables::routes(stac_service));
+    }
+
+    // Quotas are checked after authentication, so anonymous requests don't
+    // compute usage and collections are resolved for the user
+    let protected_routes = protected_routes
+        .layer(middleware::from_fn_with_state(
+            quota_service.clone(),
+            quota::middleware,
         ))
-        .merge(processes::handlers::routes(process_service))
-        .merge(stac::item::routes(stac_service))
         .layer(middleware::from_fn_with_state(
             auth_state.clone(),
             spatialvault::auth::auth_middleware,
@@ -165,16 +349,43 @@ fn build_router(
     // Wrap OpenAPI in Arc for sharing
     let openapi = Arc::new(openapi);
 
+    let compression_layer = compression::layer(&config.compression);
+    let path_prefix = config.path_prefix.clone();
+
     // Convert to regular Router and add extensions/layers
-    Router::from(api_router)
-        .layer(Extension(config))
-        .layer(Extension(openapi))
-        .layer(CompressionLayer::new())
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
-        )
-        .layer(TraceLayer::new_for_http())
+    let router = allow::with_allowed_methods(
+        Router::from(api_router)
+            .route_layer(middleware::from_fn_with_state(
+                read_only,
+                read_only::middleware,
+            ))
+            .route_layer(middleware::from_fn(telemetry::record_route)),
+    )
+    .layer(DefaultBodyLimit::max(config.limits.max_body_bytes))
+    // Inside the configuration extension, which it replaces for derived base URLs
+    .layer(middleware::from_fn_with_state(
+        forwarding,
+        forwarded::middleware,
+    ))
+    .layer(Extension(config))
+    .layer(Extension(openapi))
+    .layer(Extension(collection_service))
+    .layer(Extension(quota_service))
+    .layer(compression_layer)
+    .layer(
+        CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods(Any)
+            .allow_headers(Any)
+            .expose_headers([header::HeaderName::from_static("x-request-id")]),
+    );
+
+    // Mount under the path prefix; the layers above see paths without it
+    let router = if path_prefix.is_empty() {
+        router
+    } else {
+        Router::new().nest(&path_prefix, router)
+    };
+
+    telemetry::with_request_tracing(router)
 }
@@ -0,0 +1,179 @@
+//! Outgoing requests to URLs supplied by users
+//!
+//! Link checks and webhook deliveries request URLs that users give, so they
+//! must not reach the server's own network: loopback, private, link-local
+//! (which includes cloud metadata endpoints) and other non-public addresses
+//! are refused. Host names are checked as they are resolved for each
+//! connection, so a name that later resolves elsewhere (DNS rebinding) is
+//! still caught; hosts given as IP addresses are checked with [`check_url`]
+//! before requesting them, as they are never resolved.
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+
+/// Whether an IPv4 address is reachable on the public internet
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // "This network"
+        || a == 0
+        // Shared address space used for carrier-grade NAT
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking
+        || (a == 198 && (18..20).contains(&b))
+        // Reserved
+        || a >= 240)
+}
+
+/// Whether an address is reachable on the public internet
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ipv4(ip);
+            }
+            let segments = ip.segments();
+            // NAT64 addresses embed the IPv4 address they translate to
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [a, b] = segments[6].to_be_bytes();
+                let [c, d] = segments[7].to_be_bytes();
+                return is_public_ipv4(Ipv4Addr::new(a, b, c, d));
+            }
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // IPv4-compatible addresses, deprecated
+                || segments[..6] == [0; 6]
+                // Unique local
+                || (segments[0] & 0xfe00) == 0xfc00
+                // Link-local
+                || (segments[0] & 0xffc0) == 0xfe80
+                // Documentation
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8))
+        }
+    }
+}
+
+/// Check that a URL doesn't name a non-public address directly
+///
+/// Host names are checked when they are resolved, see [`PublicResolver`].
+pub fn check_url(url: &reqwest::Url) -> Result<(), String> {
+    let ip = match url.host() {
+        Some(url::Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(url::Host::Ipv6(ip)) => IpAddr::V6(ip),
+        Some(url::Host::Domain(_)) => return Ok(()),
+        None => return Err("URL has no host".to_string()),
+    };
+    if is_public_ip(ip) {
+        Ok(())
+    } else {
+        Err(format!("{} is not a public address", ip))
+    }
+}
+
+/// Check that a URL's host doesn't resolve to a non-public address
+///
+/// Used when a URL is registered, so that it is rejected up front rather
+/// than failing on every request. Names that don't resolve yet pass; they
+/// are checked again on each connection.
+pub async fn check_url_resolved(url: &reqwest::Url) -> Result<(), String> {
+    check_url(url)?;
+    let Some(url::Host::Domain(host)) = url.host() else {
+        return Ok(());
+    };
+    let port = url.port_or_known_default().unwrap_or(0);
+    let Ok(mut addrs) = tokio::net::lookup_host((host, port)).await else {
+        return Ok(());
+    };
+    if addrs.any(|addr| !is_public_ip(addr.ip())) {
+        return Err(format!("{} resolves to a non-public address", host));
+    }
+    Ok(())
+}
+
+/// Resolver connecting only to the public addresses of a host
+///
+/// The addresses checked are the ones connected to, as hyper connects to
+/// what the resolver returns; non-public ones are dropped and a host with
+/// none left fails to resolve.
+#[derive(Debug, Default)]
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Client builder for requests to URLs supplied by users
+///
+/// Connects through [`PublicResolver`] and doesn't use proxies from the
+/// environment, which would resolve hosts themselves.
+pub fn client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .dns_resolver(Arc::new(PublicResolver))
+        .no_proxy()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn public(ip: &str) -> bool {
+        is_public_ip(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        assert!(public("93.184.216.34"));
+        assert!(public("2606:2800:220:1:248:1893:25c8:1946"));
+
+        assert!(!public("127.0.0.1"));
+        assert!(!public("10.1.2.3"));
+        assert!(!public("172.16.0.1"));
+        assert!(!public("192.168.1.1"));
+        assert!(!public("169.254.169.254"));
+        assert!(!public("100.64.0.1"));
+        assert!(!public("0.0.0.0"));
+        assert!(!public("255.255.255.255"));
+        assert!(!public("::1"));
+        assert!(!public("::"));
+        assert!(!public("fd00:ec2::254"));
+        assert!(!public("fe80::1"));
+        assert!(!public("::ffff:127.0.0.1"));
+        assert!(!public("::ffff:169.254.169.254"));
+        assert!(!public("64:ff9b::a9fe:a9fe"));
+        assert!(public("64:ff9b::5db8:d822"));
+    }
+
+    #[test]
+    fn test_check_url() {
+        let url = |s: &str| reqwest::Url::parse(s).unwrap();
+        assert!(check_url(&url("https://example.com/a.tif")).is_ok());
+        assert!(check_url(&url("https://93.184.216.34/a.tif")).is_ok());
+        assert!(check_url(&url("http://169.254.169.254/latest/meta-data/")).is_err());
+        assert!(check_url(&url("http://[::1]:8080/")).is_err());
+        assert!(check_url(&url("http://127.1/")).is_err());
+        assert!(check_url(&url("http://0x7f000001/")).is_err());
+    }
+}
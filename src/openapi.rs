@@ -1,22 +1,30 @@
 use aide::{
     axum::{ApiRouter, routing::get_with},
     openapi::{
-        Components, Contact, ExternalDocumentation, Info, License, OpenApi, ReferenceOr, Server,
-        Tag,
+        Components, Contact, ExternalDocumentation, Info, License, OAuth2Flow, OAuth2Flows,
+        OpenApi, ReferenceOr, SecurityScheme, Server, Tag,
     },
+    scalar::Scalar,
     transform::TransformOperation,
 };
-use axum::{Extension, Json};
+use axum::{
+    Extension, Json,
+    extract::Query,
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+};
 use indexmap::IndexMap;
-use schemars::schema_for;
+use schemars::{JsonSchema, schema_for};
+use serde::Deserialize;
 use std::sync::Arc;
 
 use crate::api::collections::schemas::{
     CollectionResponse, CollectionsResponse, CreateCollectionRequest, UpdateCollectionRequest,
 };
-use crate::api::common::{Extent, Link};
+use crate::api::common::{Extent, Link, media_type};
 use crate::api::features::handlers::{Feature, FeatureCollection};
-use crate::config::Config;
+use crate::config::{Config, ModulesConfig};
+use crate::error::{AppError, AppResult};
 
 /// Create the base OpenAPI specification with metadata
 pub fn create_openapi(config: &Config) -> OpenApi {
@@ -67,6 +75,118 @@ pub fn create_openapi(config: &Config) -> OpenApi {
         ..Default::default()
     };
 
+    let mut tags = vec![
+        Tag {
+            name: "Core".to_string(),
+            description: Some(
+                "Core OGC API endpoints (landing page, conformance, API definition)".to_string(),
+            ),
+            external_docs: None,
+            extensions: IndexMap::new(),
+        },
+        Tag {
+            name: "Collections".to_string(),
+            description: Some("Collection management operations".to_string()),
+            external_docs: Some(ExternalDocumentation {
+                url: "https://docs.ogc.org/is/17-069r4/17-069r4.html".to_string(),
+                description: Some("OGC API - Features specification".to_string()),
+                extensions: IndexMap::new(),
+            }),
+            extensions: IndexMap::new(),
+        },
+        Tag {
+            name: "Features".to_string(),
+            description: Some("Feature/item CRUD operations".to_string()),
+            external_docs: Some(ExternalDocumentation {
+                url: "https://docs.ogc.org/is/17-069r4/17-069r4.html".to_string(),
+                description: Some("OGC API - Features specification".to_string()),
+                extensions: IndexMap::new(),
+            }),
+            extensions: IndexMap::new(),
+        },
+        Tag {
+            name: "Tiles".to_string(),
+            description: Some("Tile access for vector and raster data".to_string()),
+            external_docs: Some(ExternalDocumentation {
+                url: "https://docs.ogc.org/is/20-057/20-057.html".to_string(),
+                description: Some("OGC API - Tiles specification".to_string()),
+                extensions: IndexMap::new(),
+            }),
+            extensions: IndexMap::new(),
+        },
+        Tag {
+            name: "3D Tiles".to_string(),
+            description: Some("3D Tiles streaming of point cloud collections".to_string()),
+            external_docs: Some(ExternalDocumentation {
+                url: "https://docs.ogc.org/cs/22-025r4/22-025r4.html".to_string(),
+                description: Some("OGC 3D Tiles specification".to_string()),
+                extensions: IndexMap::new(),
+            }),
+            extensions: IndexMap::new(),
+        },
+        Tag {
+            name: "Coverages".to_string(),
+            description: Some("Coverage data access for raster collections".to_string()),
+            external_docs: Some(ExternalDocumentation {
+                url: "https://docs.ogc.org/is/19-087r1/19-087r1.html".to_string(),
+                description: Some("OGC API - Coverages specification".to_string()),
+                extensions: IndexMap::new(),
+            }),
+            extensions: IndexMap::new(),
+        },
+        Tag {
+            name: "Processes".to_string(),
+            description: Some(
+                "Async processing jobs (import-raster, import-pointcloud)".to_string(),
+            ),
+            external_docs: Some(ExternalDocumentation {
+                url: "https://docs.ogc.org/is/18-062r2/18-062r2.html".to_string(),
+                description: Some("OGC API - Processes specification".to_string()),
+                extensions: IndexMap::new(),
+            }),
+            extensions: IndexMap::new(),
+        },
+        Tag {
+            name: "Uploads".to_string(),
+            description: Some("Chunked uploads of large process inputs".to_string()),
+            external_docs: None,
+            extensions: IndexMap::new(),
+        },
+        Tag {
+            name: "Records".to_string(),
+            description: Some("Metadata records of the collections".to_string()),
+            external_docs: Some(ExternalDocumentation {
+                url: "https://docs.ogc.org/is/20-004r1/20-004r1.html".to_string(),
+                description: Some("OGC API - Records specification".to_string()),
+                extensions: IndexMap::new(),
+            }),
+            extensions: IndexMap::new(),
+        },
+        Tag {
+            name: "STAC".to_string(),
+            description: Some("SpatioTemporal Asset Catalog endpoints".to_string()),
+            external_docs: Some(ExternalDocumentation {
+                url: "https://stacspec.org/".to_string(),
+                description: Some("STAC Specification".to_string()),
+                extensions: IndexMap::new(),
+            }),
+            extensions: IndexMap::new(),
+        },
+        Tag {
+            name: "Sharing".to_string(),
+            description: Some("Collection sharing and permissions".to_string()),
+            external_docs: None,
+            extensions: IndexMap::new(),
+        },
+        Tag {
+            name: "Notifications".to_string(),
+            description: Some("Notifications about shares and finished jobs".to_string()),
+            external_docs: None,
+            extensions: IndexMap::new(),
+        },
+    ];
+    tags.retain(|tag| tag_enabled(&tag.name, &config.modules));
+
     OpenApi {
         openapi: "3.0.3".into(),
         info: Info {
@@ -110,84 +230,7 @@ pub fn create_openapi(config: &Config) -> OpenApi {
             extensions: IndexMap::new(),
         }],
         components: Some(components),
-        tags: vec![
-            Tag {
-                name: "Core".to_string(),
-                description: Some(
-                    "Core OGC API endpoints (landing page, conformance, API definition)".to_string(),
-                ),
-                external_docs: None,
-                extensions: IndexMap::new(),
-            },
-            Tag {
-                name: "Collections".to_string(),
-                description: Some("Collection management operations".to_string()),
-                external_docs: Some(ExternalDocumentation {
-                    url: "https://docs.ogc.org/is/17-069r4/17-069r4.html".to_string(),
-                    description: Some("OGC API - Features specification".to_string()),
-                    extensions: IndexMap::new(),
-                }),
-                extensions: IndexMap::new(),
-            },
-            Tag {
-                name: "Features".to_string(),
-                description: Some("Feature/item CRUD operations".to_string()),
-                external_docs: Some(ExternalDocumentation {
-                    url: "https://docs.ogc.org/is/17-069r4/17-069r4.html".to_string(),
-                    description: Some("OGC API - Features specification".to_string()),
-                    extensions: IndexMap::new(),
-                }),
-                extensions: IndexMap::new(),
-            },
-            Tag {
-                name: "Tiles".to_string(),
-                description: Some("Tile access for vector and raster data".to_string()),
-                external_docs: Some(ExternalDocumentation {
-                    url: "https://docs.ogc.org/is/20-057/20-057.html".to_string(),
-                    description: Some("OGC API - Tiles specification".to_string()),
-                    extensions: IndexMap::new(),
-                }),
-                extensions: IndexMap::new(),
-            },
-            Tag {
-                name: "Coverages".to_string(),
-                description: Some("Coverage data access for raster collections".to_string()),
-                external_docs: Some(ExternalDocumentation {
-                    url: "https://docs.ogc.org/is/19-087r1/19-087r1.html".to_string(),
-                    description: Some("OGC API - Coverages specification".to_string()),
-                    extensions: IndexMap::new(),
-                }),
-                extensions: IndexMap::new(),
-            },
-            Tag {
-                name: "Processes".to_string(),
-                description: Some(
-                    "Async processing jobs (import-raster, import-pointcloud)".to_string(),
-                ),
-                external_docs: Some(ExternalDocumentation {
-                    url: "https://docs.ogc.org/is/18-062r2/18-062r2.html".to_string(),
-                    description: Some("OGC API - Processes specification".to_string()),
-                    extensions: IndexMap::new(),
-                }),
-                extensions: IndexMap::new(),
-            },
-            Tag {
-                name: "STAC".to_string(),
-                description: Some("SpatioTemporal Asset Catalog endpoints".to_string()),
-                external_docs: Some(ExternalDocumentation {
-                    url: "https://stacspec.org/".to_string(),
-                    description: Some("STAC Specification".to_string()),
-                    extensions: IndexMap::new(),
-                }),
-                extensions: IndexMap::new(),
-            },
-            Tag {
-                name: "Sharing".to_string(),
-                description: Some("Collection sharing and permissions".to_string()),
-                external_docs: None,
-                extensions: IndexMap::new(),
-            },
-        ],
+        tags,
         paths: None, // Will be populated by ApiRouter
         webhooks: IndexMap::new(),
         external_docs: None,
@@ -197,6 +240,19 @@ pub fn create_openapi(config: &Config) -> OpenApi {
     }
 }
 
+/// Whether the API group of a tag is enabled; disabled groups are not routed
+fn tag_enabled(tag: &str, modules: &ModulesConfig) -> bool {
+    match tag {
+        "Tiles" => modules.tiles,
+        "3D Tiles" => modules.pointclouds,
+        "Coverages" => modules.coverages,
+        "Processes" | "Uploads" => modules.processes,
+        "Records" => modules.records,
+        "STAC" => modules.stac,
+        _ => true,
+    }
+}
+
 /// Convert a schemars schema to an aide SchemaObject
 fn schemars_to_openapi_schema<T: schemars::JsonSchema>() -> aide::openapi::SchemaObject {
     let root = schema_for!(T);
@@ -207,20 +263,309 @@ fn schemars_to_openapi_schema<T: schemars::JsonSchema>() -> aide::openapi::Schem
     }
 }
 
+/// Browser login for the API console
+#[derive(Debug, Clone)]
+pub struct ConsoleAuth {
+    pub authorization_url: String,
+    pub token_url: String,
+    pub client_id: String,
+    pub scopes: Vec<String>,
+    /// Where the provider redirects back to (the console page)
+    pub redirect_url: String,
+}
+
+/// Name of the OAuth2 security scheme used by the API console
+pub const CONSOLE_SECURITY_SCHEME: &str = "oidc";
+
+/// Add an OAuth2 authorization code + PKCE security scheme for the console
+pub fn add_console_auth(api: &mut OpenApi, auth: &ConsoleAuth) {
+    let scopes = auth
+        .scopes
+        .iter()
+        .map(|scope| (scope.clone(), String::new()))
+        .collect();
+
+    // Scalar reads these from the flow; aide's flow type has no extensions,
+    // so they are moved there when the spec is rendered
+    let mut extensions = IndexMap::new();
+    extensions.insert(
+        "x-scalar-client-id".to_string(),
+        auth.client_id.clone().into(),
+    );
+    extensions.insert(
+        "x-scalar-redirect-uri".to_string(),
+        auth.redirect_url.clone().into(),
+    );
+    extensions.insert("x-usePkce".to_string(), "SHA-256".into());
+    extensions.insert("x-default-scopes".to_string(), auth.scopes.clone().into());
+
+    let components = api.components.get_or_insert_with(Default::default);
+    components.security_schemes.insert(
+        CONSOLE_SECURITY_SCHEME.to_string(),
+        ReferenceOr::Item(SecurityScheme::OAuth2 {
+            flows: OAuth2Flows {
+                implicit: None,
+                password: None,
+                client_credentials: None,
+                authorization_code: Some(OAuth2Flow::AuthorizationCode {
+                    authorization_url: auth.authorization_url.clone(),
+                    token_url: auth.token_url.clone(),
+                    refresh_url: None,
+                    scopes,
+                }),
+            },
+            description: Some(
+                "Log in with the OIDC provider (authorization code + PKCE)".to_string(),
+            ),
+            extensions,
+        }),
+    );
+
+    // Either a bearer token or an interactive login authenticates requests
+    api.security = vec![
+        IndexMap::from([("bearerAuth".to_string(), Vec::new())]),
+        IndexMap::from([(CONSOLE_SECURITY_SCHEME.to_string(), auth.scopes.clone())]),
+    ];
+}
+
+/// Attach an example to the JSON request body of an operation
+pub fn request_example(
+    mut op: TransformOperation,
+    example: serde_json::Value,
+) -> TransformOperation {
+    if let Some(ReferenceOr::Item(body)) = &mut op.inner_mut().request_body {
+        for (content_type, media) in body.content.iter_mut() {
+            if content_type.ends_with("json") {
+                media.example = Some(example.clone());
+            }
+        }
+    }
+    op
+}
+
+/// Query parameters for the API definition
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct ApiDefinitionParams {
+    /// Output format: `json` (default) or `yaml`
+    pub f: Option<String>,
+    /// OpenAPI version: `3.0` (default) or `3.1`
+    pub version: Option<String>,
+}
+
+/// OpenAPI version of the served definition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecVersion {
+    V3_0,
+    V3_1,
+}
+
 /// Handler to serve the OpenAPI specification
-pub async fn openapi_handler(Extension(api): Extension<Arc<OpenApi>>) -> Json<OpenApi> {
-    Json((*api).clone())
+///
+/// The format and version are taken from the `f` and `version` query
+/// parameters, falling back to the `Accept` header. The server URL is the
+/// base URL of the request, which may be derived from forwarded headers.
+pub async fn openapi_handler(
+    Extension(config): Extension<Arc<Config>>,
+    Extension(api): Extension<Arc<OpenApi>>,
+    headers: HeaderMap,
+    Query(params): Query<ApiDefinitionParams>,
+) -> AppResult<Response> {
+    let accept = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    let yaml = match params.f.as_deref() {
+        Some("json") => false,
+        Some("yaml") => true,
+        Some(other) => {
+            return Err(AppError::BadRequest(format!(
+                "Unsupported format: {} (expected json or yaml)",
+                other
+            )));
+        }
+        None => accepts_yaml(accept),
+    };
+
+    let version = match params.version.as_deref() {
+        Some("3.0") => SpecVersion::V3_0,
+        Some("3.1") => SpecVersion::V3_1,
+        Some(other) => {
+            return Err(AppError::BadRequest(format!(
+                "Unsupported OpenAPI version: {} (expected 3.0 or 3.1)",
+                other
+            )));
+        }
+        None if accept.contains("version=3.1") => SpecVersion::V3_1,
+        None => SpecVersion::V3_0,
+    };
+
+    let mut spec = render_spec(&api, version)?;
+    if let Some(server) = spec.pointer_mut("/servers/0/url") {
+        *server = serde_json::Value::String(config.base_url.clone());
+    }
+
+    if yaml {
+        let body = serde_norway::to_string(&spec)
+            .map_err(|e| AppError::Internal(format!("Failed to render YAML: {}", e)))?;
+        Ok(([(header::CONTENT_TYPE, media_type::YAML)], body).into_response())
+    } else {
+        Ok(Json(spec).into_response())
+    }
 }
 
 fn openapi_handler_docs(op: TransformOperation) -> TransformOperation {
     op.summary("OpenAPI specification")
-        .description("Returns the OpenAPI 3.0 specification for this API")
+        .description(
+            "Returns the OpenAPI specification for this API as JSON or YAML (`f=yaml`), \
+            in OpenAPI 3.0 (default) or 3.1 (`version=3.1`)",
+        )
+        .tag("Core")
+}
+
+/// Whether an `Accept` header prefers YAML over JSON
+fn accepts_yaml(accept: &str) -> bool {
+    accept.split(',').any(|media| {
+        let mime = media.split(';').next().unwrap_or("").trim();
+        mime == media_type::YAML || mime == "application/vnd.oai.openapi" || mime == "text/yaml"
+    })
+}
+
+/// Serialize the specification for the requested OpenAPI version
+///
+/// Schemas are generated as JSON Schema 2020-12, which OpenAPI 3.1 uses
+/// directly; for 3.0 they are rewritten to the OpenAPI 3.0 schema dialect.
+pub fn render_spec(api: &OpenApi, version: SpecVersion) -> AppResult<serde_json::Value> {
+    let mut spec = serde_json::to_value(api)?;
+    move_flow_extensions(&mut spec);
+    match version {
+        SpecVersion::V3_0 => {
+            spec["openapi"] = "3.0.3".into();
+            downgrade_schemas(&mut spec);
+        }
+        SpecVersion::V3_1 => {
+            spec["openapi"] = "3.1.0".into();
+        }
+    }
+    Ok(spec)
+}
+
+/// Move the console's OAuth2 extensions from the security scheme into its flows
+fn move_flow_extensions(spec: &mut serde_json::Value) {
+    let Some(scheme) = spec
+        .pointer_mut(&format!(
+            "/components/securitySchemes/{}",
+            CONSOLE_SECURITY_SCHEME
+        ))
+        .and_then(|scheme| scheme.as_object_mut())
+    else {
+        return;
+    };
+
+    let extensions: Vec<_> = [
+        "x-scalar-client-id",
+        "x-scalar-redirect-uri",
+        "x-usePkce",
+        "x-default-scopes",
+    ]
+    .into_iter()
+    .filter_map(|key| scheme.remove(key).map(|value| (key, value)))
+    .collect();
+
+    if let Some(flows) = scheme.get_mut("flows").and_then(|f| f.as_object_mut()) {
+        for flow in flows.values_mut().filter_map(|f| f.as_object_mut()) {
+            for (key, value) in &extensions {
+                flow.insert(key.to_string(), value.clone());
+            }
+        }
+    }
+}
+
+/// Rewrite JSON Schema 2020-12 constructs that OpenAPI 3.0 does not support
+fn downgrade_schemas(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.remove("$schema");
+
+            // `anyOf: [S, {type: null}]` becomes S with `nullable: true`; a
+            // reference is wrapped in `allOf`, as siblings of `$ref` are ignored
+            for key in ["anyOf", "oneOf"] {
+                let Some(serde_json::Value::Array(variants)) = map.get(key) else {
+                    continue;
+                };
+                let is_null =
+                    |variant: &serde_json::Value| variant.get("type") == Some(&"null".into());
+                if !variants.iter().any(is_null) {
+                    continue;
+                }
+                let mut variants: Vec<_> =
+                    variants.iter().filter(|v| !is_null(v)).cloned().collect();
+                map.remove(key);
+                match variants.pop() {
+                    Some(serde_json::Value::Object(variant))
+                        if variants.is_empty() && !variant.contains_key("$ref") =>
+                    {
+                        for (name, value) in variant {
+                            map.entry(name).or_insert(value);
+                        }
+                    }
+                    Some(variant) if variants.is_empty() => {
+                        map.insert("allOf".to_string(), serde_json::Value::Array(vec![variant]));
+                    }
+                    Some(variant) => {
+                        variants.push(variant);
+                        map.insert(key.to_string(), serde_json::Value::Array(variants));
+                    }
+                    None => {}
+                }
+                map.insert("nullable".to_string(), true.into());
+            }
+
+            // `type: [T, "null"]` becomes `type: T, nullable: true`
+            if let Some(serde_json::Value::Array(types)) = map.get("type") {
+                let nullable = types.iter().any(|t| t == "null");
+                let types: Vec<_> = types.iter().filter(|t| *t != "null").cloned().collect();
+                if types.len() == 1 {
+                    map.insert("type".to_string(), types[0].clone());
+                    if nullable {
+                        map.insert("nullable".to_string(), true.into());
+                    }
+                }
+            }
+
+            // `const: v` becomes `enum: [v]`
+            if map.get("const").is_some_and(|c| !c.is_object()) {
+                let value = map.remove("const").unwrap();
+                map.insert("enum".to_string(), serde_json::Value::Array(vec![value]));
+            }
+
+            map.values_mut().for_each(downgrade_schemas);
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(downgrade_schemas),
+        _ => {}
+    }
+}
+
+fn console_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("API console")
+        .description("Interactive API documentation; log in to try authenticated endpoints")
         .tag("Core")
 }
 
-/// Create the docs route that serves the OpenAPI spec
+/// Create the docs routes that serve the OpenAPI spec and the API console
 pub fn docs_routes() -> ApiRouter {
-    ApiRouter::new().api_route("/api", get_with(openapi_handler, openapi_handler_docs))
+    ApiRouter::new()
+        .api_route("/api", get_with(openapi_handler, openapi_handler_docs))
+        .api_route(
+            "/docs",
+            get_with(
+                // Relative so the console also works behind a path prefix
+                Scalar::new("api?version=3.1")
+                    .with_title("SpatialVault API")
+                    .axum_handler(),
+                console_docs,
+            ),
+        )
 }
 
 #[cfg(test)]
@@ -232,17 +577,48 @@ mod tests {
         Config {
             host: "127.0.0.1".to_string(),
             port: 8080,
+            tls: None,
+            listeners: Vec::new(),
             database: crate::config::DatabaseConfig {
                 url: "postgres://localhost/test".to_string(),
                 max_connections: 5,
+                background_max_connections: 2,
+                acquire_timeout_ms: 5_000,
                 service_role: "test".to_string(),
+                statement_timeouts: Default::default(),
             },
             oidc: crate::config::OidcConfig {
                 issuer_url: "http://localhost".to_string(),
                 audience: "test".to_string(),
+                console_client_id: None,
+                console_scopes: "openid".to_string(),
             },
             s3: crate::config::S3Config::default(),
             base_url: "http://localhost:8080".to_string(),
+            path_prefix: String::new(),
+            proxy: crate::config::ProxyConfig::default(),
+            processing: crate::config::ProcessingConfig::default(),
+            limits: crate::config::LimitsConfig::default(),
+            features: crate::config::FeaturesConfig::default(),
+            discovery: crate::config::DiscoveryConfig::default(),
+            metadata: crate::config::MetadataConfig::default(),
+            localization: crate::config::LocalizationConfig::default(),
+            telemetry: crate::config::TelemetryConfig::default(),
+            analytics: crate::config::AnalyticsConfig::default(),
+            cache: crate::config::CacheConfig::default(),
+            cdn: crate::config::CdnConfig::default(),
+            compression: crate::config::CompressionConfig::default(),
+            replication: crate::config::ReplicationConfig::default(),
+            webhooks: crate::config::WebhookConfig::default(),
+            tile_signing: crate::config::TileSigningConfig::default(),
+            policy: crate::config::PolicyConfig::default(),
+            service_accounts: Default::default(),
+            scoped_tokens: crate::config::ScopedTokenConfig::default(),
+            notifications: crate::config::NotificationConfig::default(),
+            link_check: crate::config::LinkCheckConfig::default(),
+            quotas: crate::config::QuotaConfig::default(),
+            modules: crate::config::ModulesConfig::default(),
+            read_only: false,
         }
     }
 
@@ -311,6 +687,122 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_render_spec_versions() {
+        let config = test_config();
+        let mut openapi = create_openapi(&config);
+        let _router = ApiRouter::new()
+            .merge(landing::routes())
+            .merge(stac::catalog::routes())
+            .finish_api(&mut openapi);
+
+        let v31 = render_spec(&openapi, SpecVersion::V3_1).unwrap();
+        assert_eq!(v31["openapi"], "3.1.0");
+        assert!(v31.to_string().contains("\"null\""));
+
+        let v30 = render_spec(&openapi, SpecVersion::V3_0).unwrap();
+        assert_eq!(v30["openapi"], "3.0.3");
+        assert!(!v30.to_string().contains("\"$schema\""));
+        assert!(!v30.to_string().contains("\"null\""));
+
+        // YAML rendering round-trips
+        let yaml = serde_norway::to_string(&v30).unwrap();
+        let parsed: serde_json::Value = serde_norway::from_str(&yaml).unwrap();
+        assert_eq!(parsed, v30);
+    }
+
+    #[test]
+    fn test_console_auth() {
+        let config = test_config();
+        let mut openapi = create_openapi(&config);
+        add_console_auth(
+            &mut openapi,
+            &ConsoleAuth {
+                authorization_url: "https://idp.example.com/authorize".to_string(),
+                token_url: "https://idp.example.com/token".to_string(),
+                client_id: "spatialvault-console".to_string(),
+                scopes: vec!["openid".to_string()],
+                redirect_url: "http://localhost:8080/docs".to_string(),
+            },
+        );
+
+        let spec = render_spec(&openapi, SpecVersion::V3_1).unwrap();
+        let scheme = &spec["components"]["securitySchemes"]["oidc"];
+        assert_eq!(scheme["type"], "oauth2");
+        assert!(scheme.get("x-usePkce").is_none());
+
+        let flow = &scheme["flows"]["authorizationCode"];
+        assert_eq!(
+            flow["authorizationUrl"],
+            "https://idp.example.com/authorize"
+        );
+        assert_eq!(flow["x-scalar-client-id"], "spatialvault-console");
+        assert_eq!(flow["x-usePkce"], "SHA-256");
+        assert_eq!(flow["x-default-scopes"][0], "openid");
+        assert!(flow.get("selectedScopes").is_none());
+        assert_eq!(spec["security"][1]["oidc"][0], "openid");
+    }
+
+    #[test]
+    fn test_downgrade_schemas() {
+        let mut schema = serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "properties": {
+                "description": { "type": ["string", "null"] },
+                "type": { "const": "Feature" },
+                "extent": {
+                    "description": "Extent",
+                    "anyOf": [{ "$ref": "#/components/schemas/Extent" }, { "type": "null" }]
+                },
+                "limit": {
+                    "anyOf": [{ "type": "integer", "minimum": 1 }, { "type": "null" }]
+                },
+                "bbox": {
+                    "oneOf": [
+                        { "type": "string" },
+                        { "type": "array", "items": { "type": "number" } },
+                        { "type": "null" }
+                    ]
+                }
+            }
+        });
+        downgrade_schemas(&mut schema);
+        assert_eq!(
+            schema,
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "description": { "type": "string", "nullable": true },
+                    "type": { "enum": ["Feature"] },
+                    "extent": {
+                        "description": "Extent",
+                        "allOf": [{ "$ref": "#/components/schemas/Extent" }],
+                        "nullable": true
+                    },
+                    "limit": { "type": "integer", "minimum": 1, "nullable": true },
+                    "bbox": {
+                        "oneOf": [
+                            { "type": "string" },
+                            { "type": "array", "items": { "type": "number" } }
+                        ],
+                        "nullable": true
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_accepts_yaml() {
+        assert!(accepts_yaml("application/yaml"));
+        assert!(accepts_yaml("application/vnd.oai.openapi;version=3.0"));
+        assert!(!accepts_yaml(
+            "application/vnd.oai.openapi+json;version=3.0"
+        ));
+        assert!(!accepts_yaml("application/json"));
+    }
+
     #[test]
     fn test_openapi_spec_has_info() {
         let config = test_config();
@@ -337,6 +829,22 @@ mod tests {
         assert!(tag_names.contains(&"STAC"));
     }
 
+    #[test]
+    fn test_openapi_spec_omits_disabled_tags() {
+        let mut config = test_config();
+        config.modules.tiles = false;
+        config.modules.processes = false;
+        let openapi = create_openapi(&config);
+
+        let tag_names: Vec<&str> = openapi.tags.iter().map(|t| t.name.as_str()).collect();
+
+        assert!(!tag_names.contains(&"Tiles"));
+        assert!(!tag_names.contains(&"Processes"));
+        assert!(!tag_names.contains(&"Uploads"));
+        assert!(tag_names.contains(&"Coverages"));
+        assert!(tag_names.contains(&"3D Tiles"));
+    }
+
     #[test]
     fn test_openapi_spec_has_security_scheme() {
         let config = test_config();
@@ -0,0 +1,306 @@
+use chrono::{Datelike, Timelike};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::error::{AppError, AppResult};
+
+const LOCAL_HEADER_SIGNATURE: &[u8; 4] = b"PK\x03\x04";
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: &[u8; 4] = b"PK\x05\x06";
+
+/// Maximum number of entries read from an archive
+pub const MAX_ARCHIVE_ENTRIES: usize = 10_000;
+
+/// A file extracted from an archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveEntry {
+    /// Path of the file within the archive
+    pub name: String,
+    /// Where the file was extracted to
+    pub path: PathBuf,
+}
+
+fn invalid(error: impl std::fmt::Display) -> AppError {
+    AppError::Processing(format!("Invalid ZIP archive: {}", error))
+}
+
+/// Check if a file is a ZIP archive
+pub fn is_zip(path: &Path) -> AppResult<bool> {
+    let mut signature = [0u8; 4];
+    let mut file = File::open(path)?;
+    match file.read_exact(&mut signature) {
+        Ok(()) => Ok(&signature == LOCAL_HEADER_SIGNATURE
+            || &signature == END_OF_CENTRAL_DIRECTORY_SIGNATURE),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Whether an entry is a regular file worth importing
+///
+/// Directories, macOS resource forks and hidden files are skipped.
+fn is_importable(name: &str, extensions: &[&str]) -> bool {
+    let file_name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    if name.ends_with('/') || name.starts_with("__MACOSX/") || file_name.starts_with('.') {
+        return false;
+    }
+    Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.iter().any(|ext| e.eq_ignore_ascii_case(ext)))
+}
+
+/// Extract the files with one of the given extensions from a ZIP archive
+///
+/// Files are written to `dest_dir` under generated names, so entry paths
+/// never escape it. ZIP64 archives are supported; the total extracted size
+/// is limited to `max_total_bytes`.
+pub fn extract_zip(
+    path: &Path,
+    dest_dir: &Path,
+    extensions: &[&str],
+    max_total_bytes: u64,
+) -> AppResult<Vec<ArchiveEntry>> {
+    let mut archive = ZipArchive::new(File::open(path)?).map_err(invalid)?;
+    if archive.len() > MAX_ARCHIVE_ENTRIES {
+        return Err(AppError::Processing(format!(
+            "Archive has more than {} entries",
+            MAX_ARCHIVE_ENTRIES
+        )));
+    }
+    std::fs::create_dir_all(dest_dir)?;
+
+    let mut total: u64 = 0;
+    let mut extracted = Vec::new();
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(invalid)?;
+        let name = entry.name().to_string();
+        if !is_importable(&name, extensions) {
+            continue;
+        }
+        if entry.encrypted() {
+            return Err(AppError::Processing(format!(
+                "Encrypted archive entries are not supported: {}",
+                name
+            )));
+        }
+        // Sizes come from the archive; one near u64::MAX must not wrap around
+        total = total.saturating_add(entry.size());
+        if total > max_total_bytes {
+            return Err(AppError::Processing(format!(
+                "Archive contents exceed the {} byte limit",
+                max_total_bytes
+            )));
+        }
+
+        let extension = Path::new(&name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("bin")
+            .to_ascii_lowercase();
+        let output_path = dest_dir.join(format!("{}.{}", extracted.len(), extension));
+        let size = entry.size();
+        // Never write more than the declared size, whatever the data says.
+        // The CRC is checked once the entry has been read to its end.
+        let written = std::io::copy(
+            &mut (&mut entry).take(size + 1),
+            &mut File::create(&output_path)?,
+        )
+        .map_err(|e| invalid(format!("{} is corrupt: {}", name, e)))?;
+        if written != size {
+            return Err(invalid(format!("{} is corrupt", name)));
+        }
+
+        extracted.push(ArchiveEntry {
+            name,
+            path: output_path,
+        });
+    }
+    Ok(extracted)
+}
+
+/// Write a ZIP archive of files on disk, compressing them with DEFLATE
+///
+/// Entries are copied from their files a buffer at a time, and ZIP64
+/// records are used for files over 4 GiB.
+pub fn write_zip(files: &[(String, PathBuf)], output: &Path) -> AppResult<()> {
+    let now = chrono::Local::now();
+    let modified = zip::DateTime::from_date_and_time(
+        now.year().clamp(1980, 2107) as u16,
+        now.month() as u8,
+        now.day() as u8,
+        now.hour() as u8,
+        now.minute() as u8,
+        now.second() as u8,
+    )
+    .unwrap_or_default();
+
+    let mut writer = ZipWriter::new(File::create(output)?);
+    for (name, path) in files {
+        let mut file = File::open(path)?;
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .last_modified_time(modified)
+            .large_file(file.metadata()?.len() >= u32::MAX as u64);
+        writer.start_file(name.as_str(), options).map_err(invalid)?;
+        std::io::copy(&mut file, &mut writer)?;
+    }
+    writer.finish().map_err(invalid)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::DeflateEncoder;
+    use std::io::Write;
+
+    const CENTRAL_HEADER_SIGNATURE: &[u8; 4] = b"PK\x01\x02";
+
+    /// Build a ZIP archive, compressing entries whose flag is set
+    fn zip(files: &[(&str, &[u8], bool)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut directory = Vec::new();
+        for &(name, content, compress) in files {
+            let mut crc = flate2::Crc::new();
+            crc.update(content);
+            let (method, stored) = if compress {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(content).unwrap();
+                (8u16, encoder.finish().unwrap())
+            } else {
+                (0u16, content.to_vec())
+            };
+
+            let mut fields = Vec::new();
+            fields.extend_from_slice(&method.to_le_bytes());
+            fields.extend_from_slice(&[0; 4]); // modification time and date
+            fields.extend_from_slice(&crc.sum().to_le_bytes());
+            fields.extend_from_slice(&(stored.len() as u32).to_le_bytes());
+            fields.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            fields.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+
+            let offset = data.len() as u32;
+            data.extend_from_slice(LOCAL_HEADER_SIGNATURE);
+            data.extend_from_slice(&[20, 0, 0, 0]); // version, flags
+            data.extend_from_slice(&fields);
+            data.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(&stored);
+
+            directory.extend_from_slice(CENTRAL_HEADER_SIGNATURE);
+            directory.extend_from_slice(&[20, 0, 20, 0, 0, 0]); // versions, flags
+            directory.extend_from_slice(&fields);
+            directory.extend_from_slice(&[0; 6]); // comment length, disk, internal attributes
+            directory.extend_from_slice(&[0; 4]); // external attributes
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+
+        let directory_offset = data.len() as u32;
+        data.extend_from_slice(&directory);
+        data.extend_from_slice(END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        data.extend_from_slice(&[0; 4]); // disk numbers
+        data.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        data.extend_from_slice(&directory_offset.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        data
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_extract_zip() {
+        let dir = temp_dir("archive-test");
+        let archive = dir.join("input.zip");
+        let tile = vec![7u8; 4096];
+        std::fs::write(
+            &archive,
+            zip(&[
+                ("tiles/a.TIF", &tile, true),
+                ("tiles/b.tif", b"plain", false),
+                ("tiles/a.tfw", b"1.0", false),
+                ("__MACOSX/tiles/._a.TIF", b"fork", false),
+                ("tiles/", b"", false),
+            ]),
+        )
+        .unwrap();
+        assert!(is_zip(&archive).unwrap());
+
+        let entries = extract_zip(&archive, &dir.join("out"), &["tif", "tiff"], 1 << 20).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["tiles/a.TIF", "tiles/b.tif"]);
+        assert_eq!(std::fs::read(&entries[0].path).unwrap(), tile);
+        assert_eq!(std::fs::read(&entries[1].path).unwrap(), b"plain");
+        assert_eq!(entries[0].path.extension().unwrap(), "tif");
+
+        assert!(extract_zip(&archive, &dir.join("small"), &["tif"], 1000).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_extract_corrupt_zip() {
+        let dir = temp_dir("archive-corrupt-test");
+        let archive = dir.join("input.zip");
+        let mut data = zip(&[("a.laz", b"points", false)]);
+        data[30 + "a.laz".len()] = b'P';
+        std::fs::write(&archive, data).unwrap();
+
+        assert!(extract_zip(&archive, &dir.join("out"), &["laz"], 1 << 20).is_err());
+
+        std::fs::write(&archive, b"LASF").unwrap();
+        assert!(!is_zip(&archive).unwrap());
+        assert!(extract_zip(&archive, &dir.join("out"), &["laz"], 1 << 20).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_extract_zip64() {
+        let dir = temp_dir("archive-zip64-test");
+        let archive = dir.join("input.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default().large_file(true);
+        writer.start_file("cloud.laz", options).unwrap();
+        writer.write_all(b"points").unwrap();
+        writer.finish().unwrap();
+
+        let entries = extract_zip(&archive, &dir.join("out"), &["laz"], 1 << 20).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(std::fs::read(&entries[0].path).unwrap(), b"points");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_zip_round_trip() {
+        let dir = temp_dir("archive-write-test");
+        let archive = dir.join("output.zip");
+        let shapes = vec![3u8; 10_000];
+        std::fs::write(dir.join("roads.shp"), &shapes).unwrap();
+        std::fs::write(dir.join("roads.dbf"), b"attributes").unwrap();
+        write_zip(
+            &[
+                ("roads.shp".to_string(), dir.join("roads.shp")),
+                ("vägar.dbf".to_string(), dir.join("roads.dbf")),
+            ],
+            &archive,
+        )
+        .unwrap();
+        assert!(is_zip(&archive).unwrap());
+
+        let entries = extract_zip(&archive, &dir.join("out"), &["shp", "dbf"], 1 << 20).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["roads.shp", "vägar.dbf"]);
+        assert_eq!(std::fs::read(&entries[0].path).unwrap(), shapes);
+        assert_eq!(std::fs::read(&entries[1].path).unwrap(), b"attributes");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
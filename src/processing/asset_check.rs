@@ -0,0 +1,404 @@
+//! Checking assets against the objects they reference
+//!
+//! The first bytes of the object are read to detect its media type and, for
+//! assets claiming to be a COG or COPC, its layout. Mismatches with what the
+//! asset claims are reported rather than rejected, so catalogs can flag
+//! broken references instead of silently serving them.
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::processing::{cog, copc};
+use crate::storage::{S3Storage, s3_key_from_uri};
+use crate::telemetry;
+
+/// Bytes read from objects that don't claim to be a TIFF
+const PROBE_BYTES: usize = 1024;
+
+/// Outcome of an asset check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationStatus {
+    /// The object matches the asset
+    Valid,
+    /// The object doesn't match the media type, size or layout the asset claims
+    Invalid,
+    /// The object could not be read
+    Unreachable,
+}
+
+/// Result of checking an asset against the object it references
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetValidation {
+    pub status: ValidationStatus,
+    /// When the object was checked
+    #[schemars(with = "String")]
+    pub checked: DateTime<Utc>,
+    /// Media type detected from the content of the object
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detected_type: Option<String>,
+    /// Size of the object in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<i64>,
+    /// Mismatches between the asset and the object, or why it could not be read
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<String>,
+}
+
+impl AssetValidation {
+    /// Validation of an object that could not be read
+    pub fn unreachable(reason: String) -> Self {
+        Self {
+            status: ValidationStatus::Unreachable,
+            checked: Utc::now(),
+            detected_type: None,
+            size: None,
+            issues: vec![reason],
+        }
+    }
+}
+
+/// Size and leading bytes of an object
+pub struct ObjectProbe {
+    pub size: Option<i64>,
+    pub header: Bytes,
+}
+
+/// Media type detected from the leading bytes of a file
+pub fn sniff_media_type(header: &[u8]) -> Option<&'static str> {
+    let starts = |magic: &[u8]| header.starts_with(magic);
+    if starts(b"II*\0") || starts(b"MM\0*") || starts(b"II+\0") || starts(b"MM\0+") {
+        Some("image/tiff")
+    } else if starts(b"LASF") {
+        if copc::is_copc_header(header) {
+            Some("application/vnd.laszip+copc")
+        } else if header.get(104).is_some_and(|format| format & 0x80 != 0) {
+            // LAZ sets the high bit of the point data format
+            Some("application/vnd.laszip")
+        } else {
+            Some("application/vnd.las")
+        }
+    } else if starts(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if starts(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if starts(b"GIF87a") || starts(b"GIF89a") {
+        Some("image/gif")
+    } else if starts(b"RIFF") && header.get(8..12) == Some(&b"WEBP"[..]) {
+        Some("image/webp")
+    } else if starts(b"%PDF-") {
+        Some("application/pdf")
+    } else if starts(b"PK\x03\x04") {
+        Some("application/zip")
+    } else if starts(b"\x1f\x8b") {
+        Some("application/gzip")
+    } else {
+        let text = header.trim_ascii_start();
+        if text.starts_with(b"{") || text.starts_with(b"[") {
+            Some("application/json")
+        } else if text.starts_with(b"<?xml") {
+            Some("application/xml")
+        } else {
+            None
+        }
+    }
+}
+
+/// Media type without parameters, lowercased
+fn essence(media_type: &str) -> String {
+    media_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// Media type that detected types are compared with, so variants such as
+/// GeoJSON (`+json` suffix) or COPC match the generic type of their content
+fn family(media_type: &str) -> String {
+    let essence = essence(media_type);
+    if essence.ends_with("+json") {
+        "application/json".to_string()
+    } else if essence.ends_with("+xml") || essence == "text/xml" {
+        "application/xml".to_string()
+    } else if essence.ends_with("+zip") {
+        "application/zip".to_string()
+    } else if essence == "application/vnd.laszip+copc" {
+        "application/vnd.laszip".to_string()
+    } else {
+        essence
+    }
+}
+
+fn claims_cog(media_type: &str) -> bool {
+    media_type
+        .to_ascii_lowercase()
+        .replace(' ', "")
+        .contains("profile=cloud-optimized")
+}
+
+fn claims_copc(media_type: &str) -> bool {
+    essence(media_type) == "application/vnd.laszip+copc"
+}
+
+/// Number of leading bytes to read for an asset of the given media type
+pub fn probe_size(media_type: Option<&str>) -> usize {
+    match media_type {
+        Some(media_type) if essence(media_type) == "image/tiff" => cog::COG_HEADER_BYTES as usize,
+        _ => PROBE_BYTES,
+    }
+}
+
+/// Compare what an asset claims with the object it references
+pub fn check_asset(
+    media_type: Option<&str>,
+    file_size: Option<i64>,
+    probe: &ObjectProbe,
+) -> AssetValidation {
+    let detected_type = sniff_media_type(&probe.header);
+    let mut issues = Vec::new();
+
+    if let (Some(claimed), Some(actual)) = (file_size, probe.size)
+        && claimed != actual
+    {
+        issues.push(format!(
+            "file:size is {} but the object has {} bytes",
+            claimed, actual
+        ));
+    }
+
+    if let Some(claimed) = media_type {
+        if let Some(detected) = detected_type
+            && family(claimed) != family(detected)
+        {
+            issues.push(format!(
+                "type is {} but the content is {}",
+                essence(claimed),
+                detected
+            ));
+        }
+        if claims_cog(claimed)
+            && let Err(reason) = cog::validate_cog_header(&probe.header)
+        {
+            issues.push(format!("claims to be a COG but {}", reason));
+        }
+        if claims_copc(claimed) && !copc::is_copc_header(&probe.header) {
+            issues.push("claims to be COPC but has no COPC info VLR".to_string());
+        }
+    }
+
+    AssetValidation {
+        status: if issues.is_empty() {
+            ValidationStatus::Valid
+        } else {
+            ValidationStatus::Invalid
+        },
+        checked: Utc::now(),
+        detected_type: detected_type.map(str::to_string),
+        size: probe.size,
+        issues,
+    }
+}
+
+/// Read the size and the first `header_bytes` bytes of an S3 or HTTP(S) object
+pub async fn probe_object(
+    storage: &S3Storage,
+    href: &str,
+    header_bytes: usize,
+) -> AppResult<ObjectProbe> {
+    if href.starts_with("s3://") {
+        let key = s3_key_from_uri(href);
+        let meta = storage.head(key).await?;
+        let header = match header_bytes.min(meta.size) {
+            0 => Bytes::new(),
+            end => storage.get_range(key, 0..end).await?,
+        };
+        return Ok(ObjectProbe {
+            size: Some(meta.size as i64),
+            header,
+        });
+    }
+    if !href.starts_with("http://") && !href.starts_with("https://") {
+        return Err(AppError::BadRequest(format!(
+            "Unsupported URL scheme: {}",
+            href
+        )));
+    }
+
+    let response = reqwest::Client::new()
+        .get(href)
+        .headers(telemetry::trace_headers())
+        .header(
+            reqwest::header::RANGE,
+            format!("bytes=0-{}", header_bytes.saturating_sub(1)),
+        )
+        .send()
+        .await
+        .map_err(|e| AppError::Upstream(format!("Failed to fetch object: {}", e)))?;
+    if !response.status().is_success() {
+        return Err(AppError::Upstream(format!(
+            "Fetching the object failed with status: {}",
+            response.status()
+        )));
+    }
+
+    // A partial response reports the full size in Content-Range
+    let size = response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.rsplit('/').next())
+        .and_then(|total| total.parse().ok())
+        .or_else(|| match response.status() {
+            reqwest::StatusCode::OK => response.content_length().map(|len| len as i64),
+            _ => None,
+        });
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| AppError::Upstream(format!("Failed to read object: {}", e)))?;
+    Ok(ObjectProbe {
+        size,
+        header: bytes.slice(..header_bytes.min(bytes.len())),
+    })
+}
+
+/// Check an asset, reporting objects that cannot be read as unreachable
+pub async fn validate_asset(
+    storage: &S3Storage,
+    href: &str,
+    media_type: Option<&str>,
+    file_size: Option<i64>,
+) -> AssetValidation {
+    match probe_object(storage, href, probe_size(media_type)).await {
+        Ok(probe) => check_asset(media_type, file_size, &probe),
+        Err(e) => AssetValidation::unreachable(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(header: &[u8], size: i64) -> ObjectProbe {
+        ObjectProbe {
+            size: Some(size),
+            header: Bytes::copy_from_slice(header),
+        }
+    }
+
+    /// A little-endian TIFF with a single 1024x1024 image in strips
+    fn striped_tiff() -> Vec<u8> {
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        tiff.extend_from_slice(&2u16.to_le_bytes());
+        for tag in [256u16, 257] {
+            tiff.extend_from_slice(&tag.to_le_bytes());
+            tiff.extend_from_slice(&3u16.to_le_bytes());
+            tiff.extend_from_slice(&1u32.to_le_bytes());
+            tiff.extend_from_slice(&1024u32.to_le_bytes());
+        }
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff
+    }
+
+    #[test]
+    fn test_sniff_media_type() {
+        assert_eq!(sniff_media_type(&striped_tiff()), Some("image/tiff"));
+        assert_eq!(
+            sniff_media_type(b"\x89PNG\r\n\x1a\n...."),
+            Some("image/png")
+        );
+        assert_eq!(sniff_media_type(b"\xff\xd8\xff\xe0"), Some("image/jpeg"));
+        assert_eq!(
+            sniff_media_type(b"  {\"type\": \"FeatureCollection\"}"),
+            Some("application/json")
+        );
+        assert_eq!(sniff_media_type(b"hello"), None);
+        assert_eq!(sniff_media_type(b""), None);
+
+        let mut las = vec![0u8; copc::COPC_HEADER_PROBE_SIZE];
+        las[0..4].copy_from_slice(b"LASF");
+        assert_eq!(sniff_media_type(&las), Some("application/vnd.las"));
+        las[104] = 0x83;
+        assert_eq!(sniff_media_type(&las), Some("application/vnd.laszip"));
+        las[377..381].copy_from_slice(b"copc");
+        assert_eq!(sniff_media_type(&las), Some("application/vnd.laszip+copc"));
+    }
+
+    #[test]
+    fn test_check_asset() {
+        let png = probe(b"\x89PNG\r\n\x1a\n....", 2048);
+
+        let validation = check_asset(Some("image/png"), Some(2048), &png);
+        assert_eq!(validation.status, ValidationStatus::Valid);
+        assert_eq!(validation.detected_type.as_deref(), Some("image/png"));
+        assert!(validation.issues.is_empty());
+
+        let validation = check_asset(Some("image/jpeg"), Some(1000), &png);
+        assert_eq!(validation.status, ValidationStatus::Invalid);
+        assert_eq!(validation.issues.len(), 2);
+
+        // Unrecognised content and unclaimed types are not flagged
+        let text = probe(b"id,name\n1,a\n", 12);
+        assert_eq!(
+            check_asset(Some("text/csv"), None, &text).status,
+            ValidationStatus::Valid
+        );
+        assert_eq!(
+            check_asset(None, None, &png).status,
+            ValidationStatus::Valid
+        );
+
+        // Media type variants match the type of their content
+        let json = probe(b"{\"type\": \"Feature\"}", 19);
+        assert_eq!(
+            check_asset(Some("application/geo+json"), None, &json).status,
+            ValidationStatus::Valid
+        );
+    }
+
+    #[test]
+    fn test_check_cog_and_copc() {
+        let tiff = probe(&striped_tiff(), 4 << 20);
+        assert_eq!(
+            check_asset(Some("image/tiff; application=geotiff"), None, &tiff).status,
+            ValidationStatus::Valid
+        );
+
+        let validation = check_asset(
+            Some("image/tiff; application=geotiff; profile=cloud-optimized"),
+            None,
+            &tiff,
+        );
+        assert_eq!(validation.status, ValidationStatus::Invalid);
+        assert!(validation.issues[0].contains("not tiled"));
+
+        let mut las = vec![0u8; copc::COPC_HEADER_PROBE_SIZE];
+        las[0..4].copy_from_slice(b"LASF");
+        las[104] = 0x83;
+        let laz = probe(&las, 1 << 20);
+        assert_eq!(
+            check_asset(Some("application/vnd.laszip"), None, &laz).status,
+            ValidationStatus::Valid
+        );
+        let validation = check_asset(Some("application/vnd.laszip+copc"), None, &laz);
+        assert_eq!(validation.status, ValidationStatus::Invalid);
+        assert!(validation.issues[0].contains("COPC"));
+    }
+
+    #[test]
+    fn test_probe_size() {
+        assert_eq!(
+            probe_size(Some("image/tiff; profile=cloud-optimized")),
+            cog::COG_HEADER_BYTES as usize
+        );
+        assert_eq!(probe_size(Some("image/png")), PROBE_BYTES);
+        assert_eq!(probe_size(None), PROBE_BYTES);
+    }
+}
@@ -0,0 +1,527 @@
+//! Logical backups of single collections
+//!
+//! A backup is a gzipped file of JSON lines: a header with the metadata of a
+//! collection and the manifest of its assets, followed by one line per
+//! feature or item. It is written and read a line at a time, so collections
+//! of any size are backed up and restored without holding them in memory. It
+//! is restored by creating a new collection from it, so a tenant can back up
+//! and restore their collections without a dump of the whole database.
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use flate2::Compression;
+use flate2::write::{GzDecoder, GzEncoder};
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::Write;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::api::collections::schemas::{CollectionGeometry, CollectionLimits, CollectionMetadata};
+use crate::error::{AppError, AppResult};
+
+/// Version of the backup format written by this server
+///
+/// Bumped on changes older servers can't read; backups of any earlier
+/// version can still be restored. Version 1 backups are a single JSON
+/// document ([`CollectionBackup`]).
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Media type of backup files
+pub const MEDIA_TYPE: &str = "application/gzip";
+
+/// Compressed bytes collected before they are passed on for storage, at
+/// least the smallest part of a multipart upload
+const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// First line of a backup, everything but its features and items
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupHeader {
+    pub format_version: u32,
+    /// When the backup was taken
+    pub created: DateTime<Utc>,
+    pub collection: BackupCollection,
+    /// Assets attached to the collection itself
+    #[serde(default)]
+    pub assets: Vec<BackupAsset>,
+}
+
+/// A line of a backup after the header
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BackupRecord {
+    /// Feature of a vector or table collection
+    Feature(BackupFeature),
+    /// Item of a raster or point cloud collection
+    Item(BackupItem),
+}
+
+/// A collection serialized for backup in format version 1
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionBackup {
+    pub format_version: u32,
+    /// When the backup was taken
+    pub created: DateTime<Utc>,
+    pub collection: BackupCollection,
+    /// Features of vector and table collections
+    #[serde(default)]
+    pub features: Vec<BackupFeature>,
+    /// Items of raster and point cloud collections
+    #[serde(default)]
+    pub items: Vec<BackupItem>,
+    /// Assets attached to the collection itself
+    #[serde(default)]
+    pub assets: Vec<BackupAsset>,
+}
+
+/// Definition and metadata of a backed up collection
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupCollection {
+    /// Id of the collection the backup was taken of
+    pub id: String,
+    pub collection_type: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// EPSG code geometries are stored in
+    pub crs: i32,
+    /// Collection version the backup was taken at
+    pub version: i64,
+    #[serde(default)]
+    pub limits: CollectionLimits,
+    #[serde(default)]
+    pub metadata: CollectionMetadata,
+    #[serde(default)]
+    pub unique_properties: Vec<String>,
+    #[serde(default)]
+    pub geometry_columns: Vec<String>,
+    /// Declared geometry type and dimension of a vector collection
+    #[serde(default)]
+    pub geometry: CollectionGeometry,
+    #[serde(default)]
+    pub tile_properties: Option<serde_json::Value>,
+    #[serde(default)]
+    pub processing_defaults: Option<serde_json::Value>,
+    #[serde(default)]
+    pub relations: Option<serde_json::Value>,
+    #[serde(default)]
+    pub computed_properties: Option<serde_json::Value>,
+    #[serde(default)]
+    pub public_tiles: bool,
+}
+
+/// A feature as stored, with its geometry in the collection's CRS
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupFeature {
+    pub id: Uuid,
+    /// GeoJSON geometry, null for table collections
+    #[serde(default)]
+    pub geometry: Option<serde_json::Value>,
+    pub properties: serde_json::Value,
+    pub version: i64,
+    #[serde(default)]
+    pub created: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub updated: Option<DateTime<Utc>>,
+}
+
+/// An item of a raster or point cloud collection with its asset manifest
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupItem {
+    pub id: Uuid,
+    /// GeoJSON footprint in WGS 84
+    pub geometry: serde_json::Value,
+    #[serde(default)]
+    pub datetime: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub properties: Option<serde_json::Value>,
+    #[serde(default)]
+    pub assets: Vec<BackupAsset>,
+}
+
+/// An entry of the asset manifest
+///
+/// Only the reference is backed up, not the file it refers to.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupAsset {
+    pub key: String,
+    pub href: String,
+    #[serde(rename = "type", default)]
+    pub media_type: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub roles: Option<Vec<String>>,
+    #[serde(default)]
+    pub file_size: Option<i64>,
+    #[serde(default)]
+    pub extra_fields: Option<serde_json::Value>,
+}
+
+/// Numbers of features and items in a backup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BackupCounts {
+    pub features: usize,
+    pub items: usize,
+}
+
+impl BackupCounts {
+    fn count(&mut self, record: &BackupRecord) {
+        match record {
+            BackupRecord::Feature(_) => self.features += 1,
+            BackupRecord::Item(_) => self.items += 1,
+        }
+    }
+}
+
+/// Writes a backup a line at a time
+///
+/// The compressed backup is sent to `output` in chunks as it grows, e.g. to
+/// be uploaded while the rest is written.
+pub struct BackupWriter {
+    encoder: GzEncoder<Vec<u8>>,
+    output: mpsc::Sender<Bytes>,
+    counts: BackupCounts,
+}
+
+impl BackupWriter {
+    /// Start a backup with its header
+    pub async fn new(header: &BackupHeader, output: mpsc::Sender<Bytes>) -> AppResult<Self> {
+        let mut writer = Self {
+            encoder: GzEncoder::new(Vec::new(), Compression::default()),
+            output,
+            counts: BackupCounts::default(),
+        };
+        writer.write_line(header).await?;
+        Ok(writer)
+    }
+
+    /// Add a feature or item
+    pub async fn write(&mut self, record: &BackupRecord) -> AppResult<()> {
+        self.counts.count(record);
+        self.write_line(record).await
+    }
+
+    async fn write_line(&mut self, line: &impl Serialize) -> AppResult<()> {
+        serde_json::to_writer(&mut self.encoder, line)?;
+        self.encoder.write_all(b"\n")?;
+        if self.encoder.get_ref().len() >= CHUNK_SIZE {
+            let chunk = std::mem::take(self.encoder.get_mut());
+            self.send(chunk).await?;
+        }
+        Ok(())
+    }
+
+    async fn send(&self, chunk: Vec<u8>) -> AppResult<()> {
+        self.output
+            .send(Bytes::from(chunk))
+            .await
+            .map_err(|_| AppError::Processing("Backup output was closed".to_string()))
+    }
+
+    /// Complete the backup, sending the rest of it
+    pub async fn finish(self) -> AppResult<BackupCounts> {
+        let rest = self.encoder.finish()?;
+        if !rest.is_empty() {
+            self.output
+                .send(Bytes::from(rest))
+                .await
+                .map_err(|_| AppError::Processing("Backup output was closed".to_string()))?;
+        }
+        Ok(self.counts)
+    }
+}
+
+/// Reads a backup from a stream a line at a time
+pub struct BackupReader {
+    input: BoxStream<'static, AppResult<Bytes>>,
+    decoder: GzDecoder<Vec<u8>>,
+    /// Decompressed bytes after the last complete line
+    partial: Vec<u8>,
+    /// Records of a version 1 backup, which are all read with the header
+    records: VecDeque<BackupRecord>,
+    done: bool,
+    counts: BackupCounts,
+}
+
+impl BackupReader {
+    /// Open a backup, reading its header
+    ///
+    /// Fails for backups written in a newer format than this server knows.
+    pub async fn open(
+        input: BoxStream<'static, AppResult<Bytes>>,
+    ) -> AppResult<(BackupHeader, Self)> {
+        let mut reader = Self {
+            input,
+            decoder: GzDecoder::new(Vec::new()),
+            partial: Vec::new(),
+            records: VecDeque::new(),
+            done: false,
+            counts: BackupCounts::default(),
+        };
+        let line = reader
+            .next_line()
+            .await?
+            .ok_or_else(|| AppError::BadRequest("Backup is empty".to_string()))?;
+
+        // Check the version before the rest, whose shape may have changed
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Version {
+            format_version: u32,
+        }
+        let version: Version = serde_json::from_slice(&line)
+            .map_err(|e| AppError::BadRequest(format!("Invalid backup: {}", e)))?;
+        if version.format_version == 0 || version.format_version > FORMAT_VERSION {
+            return Err(AppError::BadRequest(format!(
+                "Unsupported backup format version {} (supported: 1 to {})",
+                version.format_version, FORMAT_VERSION
+            )));
+        }
+
+        let header = if version.format_version == 1 {
+            let backup: CollectionBackup = serde_json::from_slice(&line)
+                .map_err(|e| AppError::BadRequest(format!("Invalid backup: {}", e)))?;
+            reader.records.extend(
+                (backup.features.into_iter().map(BackupRecord::Feature))
+                    .chain(backup.items.into_iter().map(BackupRecord::Item)),
+            );
+            BackupHeader {
+                format_version: backup.format_version,
+                created: backup.created,
+                collection: backup.collection,
+                assets: backup.assets,
+            }
+        } else {
+            serde_json::from_slice(&line)
+                .map_err(|e| AppError::BadRequest(format!("Invalid backup: {}", e)))?
+        };
+        Ok((header, reader))
+    }
+
+    /// The next feature or item, if any
+    pub async fn next(&mut self) -> AppResult<Option<BackupRecord>> {
+        let record = match self.records.pop_front() {
+            Some(record) => Some(record),
+            None => match self.next_line().await? {
+                Some(line) => Some(
+                    serde_json::from_slice(&line)
+                        .map_err(|e| AppError::BadRequest(format!("Invalid backup: {}", e)))?,
+                ),
+                None => None,
+            },
+        };
+        if let Some(record) = &record {
+            self.counts.count(record);
+        }
+        Ok(record)
+    }
+
+    /// Numbers of features and items read so far
+    pub fn counts(&self) -> BackupCounts {
+        self.counts
+    }
+
+    /// The next non-empty line of the decompressed backup
+    async fn next_line(&mut self) -> AppResult<Option<Vec<u8>>> {
+        loop {
+            let decompressed = self.decoder.get_ref();
+            if let Some(end) = decompressed.iter().position(|&b| b == b'\n') {
+                let mut line = std::mem::take(&mut self.partial);
+                line.extend_from_slice(&decompressed[..end]);
+                self.decoder.get_mut().drain(..=end);
+                if line.is_empty() {
+                    continue;
+                }
+                return Ok(Some(line));
+            }
+            // Keep the start of the line apart, so the decoder's buffer
+            // only holds what was decompressed since
+            let rest = std::mem::take(self.decoder.get_mut());
+            self.partial.extend(rest);
+
+            if self.done {
+                let line = std::mem::take(&mut self.partial);
+                return Ok((!line.is_empty()).then_some(line));
+            }
+            match self.input.next().await {
+                Some(chunk) => self.decoder.write_all(&chunk?).map_err(invalid_gzip)?,
+                None => {
+                    self.decoder.try_finish().map_err(invalid_gzip)?;
+                    self.done = true;
+                }
+            }
+        }
+    }
+}
+
+fn invalid_gzip(e: std::io::Error) -> AppError {
+    AppError::BadRequest(format!("Backup is not a gzip file: {}", e))
+}
+
+/// File name of a backup of a collection taken at `created`
+pub fn file_name(collection_id: &str, created: DateTime<Utc>) -> String {
+    format!(
+        "{}-{}.backup.json.gz",
+        collection_id.replace(':', "_"),
+        created.format("%Y%m%dT%H%M%SZ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn backup(format_version: u32) -> CollectionBackup {
+        CollectionBackup {
+            format_version,
+            created: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            collection: BackupCollection {
+                id: "alice:roads".to_string(),
+                collection_type: "vector".to_string(),
+                title: "Roads".to_string(),
+                description: None,
+                crs: 3006,
+                version: 7,
+                limits: CollectionLimits::default(),
+                metadata: CollectionMetadata::default(),
+                unique_properties: vec!["ref".to_string()],
+                geometry_columns: Vec::new(),
+                geometry: CollectionGeometry::default(),
+                tile_properties: None,
+                processing_defaults: None,
+                relations: None,
+                computed_properties: None,
+                public_tiles: false,
+            },
+            features: vec![BackupFeature {
+                id: Uuid::nil(),
+                geometry: Some(json!({"type": "Point", "coordinates": [1.0, 2.0]})),
+                properties: json!({"ref": "E4"}),
+                version: 3,
+                created: None,
+                updated: None,
+            }],
+            items: Vec::new(),
+            assets: vec![BackupAsset {
+                key: "thumbnail".to_string(),
+                href: "s3://bucket/alice/roads.png".to_string(),
+                media_type: Some("image/png".to_string()),
+                title: None,
+                description: None,
+                roles: Some(vec!["thumbnail".to_string()]),
+                file_size: Some(1024),
+                extra_fields: None,
+            }],
+        }
+    }
+
+    /// Gzip a backup written in format version 1
+    fn encode_v1(backup: &CollectionBackup) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        serde_json::to_writer(&mut encoder, backup).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// Read a backup from chunks of `chunk_size` bytes
+    async fn open(data: Vec<u8>, chunk_size: usize) -> AppResult<(BackupHeader, BackupReader)> {
+        let chunks: Vec<AppResult<Bytes>> = data
+            .chunks(chunk_size)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        BackupReader::open(futures::stream::iter(chunks).boxed()).await
+    }
+
+    #[tokio::test]
+    async fn test_backup_round_trip() {
+        let CollectionBackup {
+            collection,
+            features,
+            assets,
+            ..
+        } = backup(1);
+        let header = BackupHeader {
+            format_version: FORMAT_VERSION,
+            created: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            collection,
+            assets,
+        };
+
+        let (sender, mut receiver) = mpsc::channel(4);
+        let write = async {
+            let mut writer = BackupWriter::new(&header, sender).await?;
+            for feature in features {
+                writer.write(&BackupRecord::Feature(feature)).await?;
+            }
+            writer.finish().await
+        };
+        let (counts, mut data) = tokio::join!(write, async {
+            let mut data = Vec::new();
+            while let Some(chunk) = receiver.recv().await {
+                data.extend_from_slice(&chunk);
+            }
+            data
+        });
+        assert_eq!(counts.unwrap().features, 1);
+
+        let (decoded, mut reader) = open(data.clone(), 7).await.unwrap();
+        assert_eq!(decoded.collection.id, "alice:roads");
+        assert_eq!(decoded.collection.crs, 3006);
+        assert_eq!(decoded.collection.unique_properties, vec!["ref"]);
+        assert_eq!(decoded.assets[0].media_type.as_deref(), Some("image/png"));
+        let Some(BackupRecord::Feature(feature)) = reader.next().await.unwrap() else {
+            panic!("Expected a feature");
+        };
+        assert_eq!(feature.properties, json!({"ref": "E4"}));
+        assert_eq!(feature.version, 3);
+        assert!(reader.next().await.unwrap().is_none());
+        assert_eq!(reader.counts().features, 1);
+
+        // A truncated backup fails rather than ending early
+        data.truncate(data.len() - 10);
+        let (_, mut reader) = open(data, 7).await.unwrap();
+        assert!(reader.next().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_version_1() {
+        let (header, mut reader) = open(encode_v1(&backup(1)), 16).await.unwrap();
+        assert_eq!(header.format_version, 1);
+        assert_eq!(header.collection.id, "alice:roads");
+        assert!(matches!(
+            reader.next().await.unwrap(),
+            Some(BackupRecord::Feature(_))
+        ));
+        assert!(reader.next().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_open_rejects_unknown_versions() {
+        let data = encode_v1(&backup(FORMAT_VERSION + 1));
+        assert!(matches!(open(data, 64).await, Err(AppError::BadRequest(_))));
+        assert!(matches!(
+            open(b"not gzip".to_vec(), 64).await,
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_file_name() {
+        let created = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        assert_eq!(
+            file_name("alice:roads", created),
+            "alice_roads-20231114T221320Z.backup.json.gz"
+        );
+    }
+}
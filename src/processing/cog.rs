@@ -1,52 +1,224 @@
+use std::io::Read;
 use std::path::Path;
 
+use crate::api::coverages::range_subset::CoverageWindow;
 use crate::error::{AppError, AppResult};
 
+/// Bytes read from the start of a file when checking its COG layout
+///
+/// A COG keeps all of its IFDs ahead of the image data, so they are
+/// expected within this prefix of the file.
+pub const COG_HEADER_BYTES: u64 = 1024 * 1024;
+
+/// Upper bound on the number of IFDs (full resolution, overviews and masks)
+const MAX_IFDS: usize = 64;
+
 /// Check if a file is a valid Cloud Optimized GeoTIFF
+///
+/// Only the TIFF structure is inspected, so this works without GDAL.
 pub fn is_cog(path: &Path) -> AppResult<bool> {
-    // In a full implementation, we would use GDAL to:
-    // 1. Open the file
-    // 2. Check for COG structure (overviews, tiling, etc.)
-    // 3. Validate internal structure
-
-    // For now, check file extension
-    match path.extension().and_then(|s| s.to_str()) {
-        Some("tif") | Some("tiff") => {
-            // Would use GDAL to verify COG structure
+    let mut header = Vec::new();
+    std::fs::File::open(path)?
+        .take(COG_HEADER_BYTES)
+        .read_to_end(&mut header)?;
+
+    match validate_cog_header(&header) {
+        Ok(()) => Ok(true),
+        Err(reason) => {
+            tracing::debug!("{} is not a COG: {}", path.display(), reason);
             Ok(false)
         }
-        _ => Ok(false),
+    }
+}
+
+/// Check the start of a TIFF file against the COG layout requirements
+///
+/// Every IFD must be tiled and lie ahead of the image data, and a full
+/// resolution image larger than one tile must have overviews. Returns the
+/// reason the file is not a COG.
+pub fn validate_cog_header(header: &[u8]) -> Result<(), String> {
+    let tiff = TiffHeader::parse(header).ok_or("not a TIFF file")?;
+
+    let mut ifds: Vec<Ifd> = Vec::new();
+    let mut offset = tiff.first_ifd;
+    while offset != 0 {
+        if ifds.len() == MAX_IFDS {
+            return Err("too many IFDs".to_string());
+        }
+        if ifds.last().is_some_and(|ifd| offset <= ifd.offset) {
+            return Err("IFDs are not in file order".to_string());
+        }
+        let (ifd, next) = tiff
+            .read_ifd(header, offset)
+            .ok_or_else(|| format!("IFD at offset {} is not ahead of the image data", offset))?;
+        ifds.push(ifd);
+        offset = next;
+    }
+
+    let main = ifds.first().ok_or("no images")?;
+    for ifd in &ifds {
+        if ifd.tag(TAG_TILE_WIDTH).is_none() || ifd.tag(TAG_TILE_LENGTH).is_none() {
+            return Err(format!("image at offset {} is not tiled", ifd.offset));
+        }
+    }
+
+    let larger_than_tile = main.tag(TAG_IMAGE_WIDTH) > main.tag(TAG_TILE_WIDTH)
+        || main.tag(TAG_IMAGE_LENGTH) > main.tag(TAG_TILE_LENGTH);
+    let has_overviews = ifds[1..]
+        .iter()
+        .any(|ifd| ifd.tag(TAG_NEW_SUBFILE_TYPE).unwrap_or(0) & SUBFILE_REDUCED_IMAGE != 0);
+    if larger_than_tile && !has_overviews {
+        return Err("image is larger than one tile but has no overviews".to_string());
+    }
+
+    Ok(())
+}
+
+const TAG_NEW_SUBFILE_TYPE: u16 = 254;
+const TAG_IMAGE_WIDTH: u16 = 256;
+const TAG_IMAGE_LENGTH: u16 = 257;
+const TAG_TILE_WIDTH: u16 = 322;
+const TAG_TILE_LENGTH: u16 = 323;
+
+/// NewSubfileType flag of reduced resolution images (overviews)
+const SUBFILE_REDUCED_IMAGE: u64 = 1;
+
+/// Byte order and offset size of a classic TIFF or BigTIFF file
+struct TiffHeader {
+    big_endian: bool,
+    bigtiff: bool,
+    first_ifd: u64,
+}
+
+/// An image file directory with the inline values of its tags
+struct Ifd {
+    offset: u64,
+    tags: Vec<(u16, Option<u64>)>,
+}
+
+impl Ifd {
+    /// Single SHORT, LONG or LONG8 value of a tag, if present
+    fn tag(&self, tag: u16) -> Option<u64> {
+        self.tags
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, value)| value.unwrap_or(0))
+    }
+}
+
+impl TiffHeader {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let big_endian = match data.get(0..2)? {
+            b"II" => false,
+            b"MM" => true,
+            _ => return None,
+        };
+        let mut tiff = Self {
+            big_endian,
+            bigtiff: false,
+            first_ifd: 0,
+        };
+        match tiff.uint(data, 2, 2)? {
+            42 => tiff.first_ifd = tiff.uint(data, 4, 4)?,
+            43 if tiff.uint(data, 4, 2)? == 8 => {
+                tiff.bigtiff = true;
+                tiff.first_ifd = tiff.uint(data, 8, 8)?;
+            }
+            _ => return None,
+        }
+        Some(tiff)
+    }
+
+    /// Unsigned integer of `size` bytes at `offset`
+    fn uint(&self, data: &[u8], offset: u64, size: usize) -> Option<u64> {
+        let start = usize::try_from(offset).ok()?;
+        let bytes = data.get(start..start.checked_add(size)?)?;
+        let fold = |value: u64, byte: &u8| (value << 8) | u64::from(*byte);
+        Some(if self.big_endian {
+            bytes.iter().fold(0, fold)
+        } else {
+            bytes.iter().rev().fold(0, fold)
+        })
+    }
+
+    /// Read the IFD at `offset`, returning it and the offset of the next one
+    fn read_ifd(&self, data: &[u8], offset: u64) -> Option<(Ifd, u64)> {
+        let (count_size, entry_size, offset_size) =
+            if self.bigtiff { (8, 20, 8) } else { (2, 12, 4) };
+        let count = self.uint(data, offset, count_size)?;
+        let entries = offset + count_size as u64;
+
+        let mut tags = Vec::new();
+        for index in 0..count.min(u16::MAX as u64) {
+            let entry = entries + index * entry_size;
+            let tag = self.uint(data, entry, 2)? as u16;
+            let field_type = self.uint(data, entry + 2, 2)?;
+            let values = self.uint(data, entry + 4, offset_size)?;
+            let value_offset = entry + 4 + offset_size as u64;
+            let value = match field_type {
+                3 if values == 1 => self.uint(data, value_offset, 2),
+                4 if values == 1 => self.uint(data, value_offset, 4),
+                16 if values == 1 => self.uint(data, value_offset, 8),
+                _ => None,
+            };
+            tags.push((tag, value));
+        }
+
+        let next = self.uint(data, entries + count * entry_size, offset_size)?;
+        Some((Ifd { offset, tags }, next))
     }
 }
 
 /// Convert a raster file to Cloud Optimized GeoTIFF
+///
+/// Uses GDAL's COG driver: 512x512 tiles, DEFLATE compression and averaged
+/// overviews.
 pub async fn convert_to_cog(input_path: &Path, output_path: &Path) -> AppResult<()> {
-    // In a full implementation, we would use GDAL to:
-    // 1. Open the input file
-    // 2. Create a COG with proper options:
-    //    - Internal tiling (512x512)
-    //    - Overviews
-    //    - Proper compression (LZW, DEFLATE, or ZSTD)
-    //    - Predictor for better compression
-    //
-    // Example GDAL command equivalent:
-    // gdal_translate input.tif output.tif \
-    //   -of COG \
-    //   -co TILING_SCHEME=GoogleMapsCompatible \
-    //   -co COMPRESS=LZW \
-    //   -co PREDICTOR=2 \
-    //   -co OVERVIEW_RESAMPLING=CUBIC
-
     tracing::info!(
         "Converting {} to COG at {}",
         input_path.display(),
         output_path.display()
     );
 
-    // Placeholder - would use gdal crate
-    Err(AppError::Processing(
-        "COG conversion not yet implemented".to_string(),
-    ))
+    #[cfg(feature = "gdal-support")]
+    {
+        let (input, output) = (input_path.to_path_buf(), output_path.to_path_buf());
+        tokio::task::spawn_blocking(move || convert_to_cog_gdal(&input, &output))
+            .await
+            .map_err(|e| AppError::Processing(format!("Task join error: {}", e)))?
+    }
+    #[cfg(not(feature = "gdal-support"))]
+    {
+        Err(AppError::Processing(
+            "COG conversion requires the 'gdal-support' feature. \
+            Build with: cargo build --features gdal-support"
+                .to_string(),
+        ))
+    }
+}
+
+#[cfg(feature = "gdal-support")]
+fn convert_to_cog_gdal(input_path: &Path, output_path: &Path) -> AppResult<()> {
+    use gdal::raster::RasterCreationOptions;
+    use gdal::{Dataset, DriverManager};
+
+    let dataset = Dataset::open(input_path)
+        .map_err(|e| AppError::Processing(format!("Failed to open raster: {}", e)))?;
+    let driver = DriverManager::get_driver_by_name("COG")
+        .map_err(|e| AppError::Processing(format!("GDAL COG driver not available: {}", e)))?;
+    let options: RasterCreationOptions = [
+        "BLOCKSIZE=512",
+        "COMPRESS=DEFLATE",
+        "PREDICTOR=YES",
+        "OVERVIEW_RESAMPLING=AVERAGE",
+    ]
+    .into_iter()
+    .collect();
+
+    dataset
+        .create_copy(&driver, output_path, &options)
+        .map_err(|e| AppError::Processing(format!("COG conversion failed: {}", e)))?;
+    Ok(())
 }
 
 /// Extract metadata from a raster file
@@ -63,6 +235,153 @@ pub async fn extract_raster_metadata(path: &Path) -> AppResult<RasterMetadata> {
     ))
 }
 
+/// Extract metadata from a raster at a remote location (S3 or HTTP(S))
+///
+/// GDAL's /vsis3/ and /vsicurl/ handlers only fetch the header and the
+/// byte ranges needed, so the file is never downloaded in full.
+pub async fn extract_remote_raster_metadata(href: &str) -> AppResult<RasterMetadata> {
+    #[cfg(feature = "gdal-support")]
+    {
+        let vsi_path = crate::api::tiles::raster::href_to_vsi_path(href);
+        tokio::task::spawn_blocking(move || read_raster_metadata_gdal(&vsi_path))
+            .await
+            .map_err(|e| AppError::Processing(format!("Task join error: {}", e)))?
+    }
+    #[cfg(not(feature = "gdal-support"))]
+    {
+        Err(AppError::Processing(format!(
+            "Remote raster metadata extraction for {} requires the 'gdal-support' feature",
+            href
+        )))
+    }
+}
+
+#[cfg(feature = "gdal-support")]
+fn read_raster_metadata_gdal(path: &str) -> AppResult<RasterMetadata> {
+    use gdal::{Dataset, Metadata};
+
+    let dataset = Dataset::open(path)
+        .map_err(|e| AppError::Processing(format!("Failed to open raster: {}", e)))?;
+
+    let (width, height) = dataset.raster_size();
+    let gt = dataset
+        .geo_transform()
+        .map_err(|e| AppError::Processing(format!("Failed to get geotransform: {}", e)))?;
+
+    let minx = gt[0];
+    let maxy = gt[3];
+    let maxx = minx + width as f64 * gt[1];
+    let miny = maxy + height as f64 * gt[5];
+
+    let srid = dataset
+        .spatial_ref()
+        .and_then(|srs| srs.auth_code())
+        .map_err(|e| AppError::Processing(format!("Failed to determine raster CRS: {}", e)))?;
+
+    let band = dataset
+        .rasterband(1)
+        .map_err(|e| AppError::Processing(format!("Failed to get band: {}", e)))?;
+
+    let band_info = (1..=dataset.raster_count())
+        .map(|index| {
+            let band = dataset
+                .rasterband(index)
+                .map_err(|e| AppError::Processing(format!("Failed to get band: {}", e)))?;
+            Ok(BandMetadata {
+                description: band.description().unwrap_or_default(),
+                data_type: band.band_type().name(),
+                nodata: band.no_data_value(),
+                unit: band.unit(),
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+    Ok(RasterMetadata {
+        bounds: [minx, miny.min(maxy), maxx, miny.max(maxy)],
+        srid,
+        width: width as u32,
+        height: height as u32,
+        bands: dataset.raster_count() as u32,
+        dtype: format!("{:?}", band.band_type()),
+        nodata: band.no_data_value(),
+        resolution: [gt[1].abs(), gt[5].abs()],
+        band_info,
+    })
+}
+
+/// Read selected bands of a window of a raster at a remote location (S3 or
+/// HTTP(S)) into a Cloud Optimized GeoTIFF of the window's size
+///
+/// `bands` are 1-based indexes in the order of the output bands. Only the
+/// blocks of the window, or of an overview when scaling down, are fetched.
+pub async fn read_window(
+    href: &str,
+    bands: Vec<usize>,
+    window: CoverageWindow,
+) -> AppResult<Vec<u8>> {
+    #[cfg(feature = "gdal-support")]
+    {
+        let vsi_path = crate::api::tiles::raster::href_to_vsi_path(href);
+        tokio::task::spawn_blocking(move || read_window_gdal(&vsi_path, &bands, window))
+            .await
+            .map_err(|e| AppError::Processing(format!("Task join error: {}", e)))?
+    }
+    #[cfg(not(feature = "gdal-support"))]
+    {
+        let _ = (bands, window);
+        Err(AppError::Processing(format!(
+            "Reading raster data of {} requires the 'gdal-support' feature",
+            href
+        )))
+    }
+}
+
+#[cfg(feature = "gdal-support")]
+fn read_window_gdal(path: &str, bands: &[usize], window: CoverageWindow) -> AppResult<Vec<u8>> {
+    use gdal::programs::raster::{BuildVRTOptions, build_vrt};
+    use gdal::raster::RasterCreationOptions;
+    use gdal::{Dataset, DriverManager, vsi};
+
+    let dataset = Dataset::open(path)
+        .map_err(|e| AppError::Processing(format!("Failed to open raster: {}", e)))?;
+
+    // A virtual raster of the selected bands over the window at the pixel
+    // size giving the requested size, copied into an in-memory COG
+    let [minx, miny, maxx, maxy] = window.bounds;
+    let [width, height] = window.size;
+    let mut args: Vec<String> = bands
+        .iter()
+        .flat_map(|band| ["-b".to_string(), band.to_string()])
+        .collect();
+    let resolution = [
+        (maxx - minx) / f64::from(width),
+        (maxy - miny) / f64::from(height),
+    ];
+    args.push("-te".to_string());
+    args.extend([minx, miny, maxx, maxy].iter().map(f64::to_string));
+    args.push("-tr".to_string());
+    args.extend(resolution.iter().map(f64::to_string));
+    let options = BuildVRTOptions::new(args)
+        .map_err(|e| AppError::Processing(format!("Invalid band selection or window: {}", e)))?;
+    let vrt = build_vrt(None, &[dataset], Some(options))
+        .map_err(|e| AppError::Processing(format!("Failed to select bands: {}", e)))?;
+
+    let driver = DriverManager::get_driver_by_name("COG")
+        .map_err(|e| AppError::Processing(format!("GDAL COG driver not available: {}", e)))?;
+    let options: RasterCreationOptions =
+        ["COMPRESS=DEFLATE", "PREDICTOR=YES"].into_iter().collect();
+    let output = format!("/vsimem/{}.tif", uuid::Uuid::new_v4());
+    let copy = vrt
+        .create_copy(&driver, &output, &options)
+        .map_err(|e| AppError::Processing(format!("Failed to write raster: {}", e)));
+    // The copy must be closed before its bytes are complete
+    drop(copy?);
+
+    // Taking the bytes also removes the in-memory file
+    vsi::get_vsi_mem_file_bytes_owned(&output)
+        .map_err(|e| AppError::Processing(format!("Failed to read raster: {}", e)))
+}
+
 #[derive(Debug)]
 pub struct RasterMetadata {
     pub bounds: [f64; 4], // minx, miny, maxx, maxy
@@ -72,4 +391,124 @@ pub struct RasterMetadata {
     pub bands: u32,
     pub dtype: String,
     pub nodata: Option<f64>,
+    /// Pixel size along x and y in CRS units
+    pub resolution: [f64; 2],
+    pub band_info: Vec<BandMetadata>,
+}
+
+#[derive(Debug)]
+pub struct BandMetadata {
+    /// Band description, empty if unset
+    pub description: String,
+    /// GDAL data type name, e.g. `UInt16` or `Float32`
+    pub data_type: String,
+    pub nodata: Option<f64>,
+    /// Unit of the values, empty if unset
+    pub unit: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHORT: u16 = 3;
+    const LONG: u16 = 4;
+
+    /// Little-endian classic TIFF with the given IFDs, written back to back
+    fn tiff(ifds: &[Vec<(u16, u16, u32)>]) -> Vec<u8> {
+        let mut data = b"II".to_vec();
+        data.extend_from_slice(&42u16.to_le_bytes());
+        data.extend_from_slice(&8u32.to_le_bytes());
+        for (index, entries) in ifds.iter().enumerate() {
+            data.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+            for &(tag, field_type, value) in entries {
+                data.extend_from_slice(&tag.to_le_bytes());
+                data.extend_from_slice(&field_type.to_le_bytes());
+                data.extend_from_slice(&1u32.to_le_bytes());
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            let next = if index + 1 < ifds.len() {
+                data.len() as u32 + 4
+            } else {
+                0
+            };
+            data.extend_from_slice(&next.to_le_bytes());
+        }
+        data
+    }
+
+    fn image(subfile_type: u32, size: u32, tiled: bool) -> Vec<(u16, u16, u32)> {
+        let mut entries = vec![
+            (TAG_NEW_SUBFILE_TYPE, LONG, subfile_type),
+            (TAG_IMAGE_WIDTH, LONG, size),
+            (TAG_IMAGE_LENGTH, LONG, size),
+        ];
+        if tiled {
+            entries.push((TAG_TILE_WIDTH, SHORT, 512));
+            entries.push((TAG_TILE_LENGTH, SHORT, 512));
+        }
+        entries
+    }
+
+    #[test]
+    fn test_validate_cog() {
+        let cog = tiff(&[
+            image(0, 2048, true),
+            image(1, 1024, true),
+            image(1, 512, true),
+        ]);
+        assert_eq!(validate_cog_header(&cog), Ok(()));
+
+        // A single tile needs no overviews
+        assert_eq!(validate_cog_header(&tiff(&[image(0, 300, true)])), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_not_cog() {
+        assert!(validate_cog_header(b"\x89PNG\r\n\x1a\n").is_err());
+
+        let stripped = tiff(&[image(0, 2048, false), image(1, 1024, false)]);
+        assert!(
+            validate_cog_header(&stripped)
+                .unwrap_err()
+                .contains("not tiled")
+        );
+
+        let no_overviews = tiff(&[image(0, 2048, true)]);
+        assert!(
+            validate_cog_header(&no_overviews)
+                .unwrap_err()
+                .contains("no overviews")
+        );
+
+        // IFD at the end of the file, behind the image data
+        let mut trailing = tiff(&[image(0, 300, true)]);
+        trailing[4..8].copy_from_slice(&(COG_HEADER_BYTES as u32).to_le_bytes());
+        assert!(validate_cog_header(&trailing).is_err());
+    }
+
+    #[test]
+    fn test_validate_big_endian_bigtiff() {
+        let mut data = b"MM".to_vec();
+        data.extend_from_slice(&43u16.to_be_bytes());
+        data.extend_from_slice(&8u16.to_be_bytes());
+        data.extend_from_slice(&0u16.to_be_bytes());
+        data.extend_from_slice(&16u64.to_be_bytes());
+        let entries = [
+            (TAG_IMAGE_WIDTH, SHORT, 256u64 << 48),
+            (TAG_IMAGE_LENGTH, SHORT, 256 << 48),
+            (TAG_TILE_WIDTH, SHORT, 256 << 48),
+            (TAG_TILE_LENGTH, SHORT, 256 << 48),
+        ];
+        data.extend_from_slice(&(entries.len() as u64).to_be_bytes());
+        for (tag, field_type, value) in entries {
+            data.extend_from_slice(&tag.to_be_bytes());
+            data.extend_from_slice(&field_type.to_be_bytes());
+            data.extend_from_slice(&1u64.to_be_bytes());
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        data.extend_from_slice(&0u64.to_be_bytes());
+
+        assert_eq!(validate_cog_header(&data), Ok(()));
+    }
 }
@@ -0,0 +1,369 @@
+//! Temporal composites of raster items
+//!
+//! The items are stacked on the grid of the first one and each output pixel
+//! is a statistic of the valid values of the items at that pixel, either of
+//! each band or of a normalized difference of two bands such as NDVI.
+
+use std::path::Path;
+
+use crate::error::{AppError, AppResult};
+
+/// Largest composite, in pixels per band
+#[cfg(feature = "gdal-support")]
+const MAX_COMPOSITE_PIXELS: usize = 100_000_000;
+
+/// Rows of the stack read at once
+#[cfg(feature = "gdal-support")]
+const BLOCK_ROWS: usize = 256;
+
+/// Statistic of a temporal composite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompositeMethod {
+    Median,
+    Mean,
+    Min,
+    Max,
+}
+
+impl CompositeMethod {
+    pub fn from_param(value: &str) -> AppResult<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "median" => Ok(Self::Median),
+            "mean" => Ok(Self::Mean),
+            "min" => Ok(Self::Min),
+            "max" => Ok(Self::Max),
+            other => Err(AppError::BadRequest(format!(
+                "Unsupported composite method: {} (supported: median, mean, min, max)",
+                other
+            ))),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Median => "median",
+            Self::Mean => "mean",
+            Self::Min => "min",
+            Self::Max => "max",
+        }
+    }
+}
+
+/// Normalized difference `(a - b) / (a + b)` of two band values, NaN where
+/// either is missing or both are zero
+pub fn normalized_difference(a: f64, b: f64) -> f64 {
+    let sum = a + b;
+    if sum == 0.0 { f64::NAN } else { (a - b) / sum }
+}
+
+/// Composite of the valid values of one pixel, NaN without any
+///
+/// `values` is reordered.
+pub fn composite_value(values: &mut [f64], method: CompositeMethod) -> f64 {
+    if values.is_empty() {
+        return f64::NAN;
+    }
+    match method {
+        CompositeMethod::Median => {
+            values.sort_unstable_by(f64::total_cmp);
+            let middle = values.len() / 2;
+            if values.len().is_multiple_of(2) {
+                (values[middle - 1] + values[middle]) / 2.0
+            } else {
+                values[middle]
+            }
+        }
+        CompositeMethod::Mean => values.iter().sum::<f64>() / values.len() as f64,
+        CompositeMethod::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+        CompositeMethod::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+    }
+}
+
+/// Write the composite of rasters at remote locations (S3 or HTTP(S)) as a
+/// Cloud Optimized GeoTIFF of Float32 bands, with NaN for no data
+///
+/// The rasters must share CRS and bands; the composite has the resolution of
+/// the first one and covers `bbox` (CRS84) or all rasters. With
+/// `normalized_difference` bands `[a, b]` (1-based) the composite is of the
+/// single band `(a - b) / (a + b)` of each raster.
+pub async fn composite_rasters(
+    hrefs: Vec<String>,
+    bbox: Option<[f64; 4]>,
+    method: CompositeMethod,
+    normalized_difference: Option<[usize; 2]>,
+    output_path: &Path,
+) -> AppResult<()> {
+    #[cfg(feature = "gdal-support")]
+    {
+        let output_path = output_path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            composite_rasters_gdal(&hrefs, bbox, method, normalized_difference, &output_path)
+        })
+        .await
+        .map_err(|e| AppError::Processing(format!("Task join error: {}", e)))?
+    }
+    #[cfg(not(feature = "gdal-support"))]
+    {
+        let _ = (hrefs, bbox, method, normalized_difference, output_path);
+        Err(AppError::Processing(
+            "Temporal composites require the 'gdal-support' feature. \
+            Build with: cargo build --features gdal-support"
+                .to_string(),
+        ))
+    }
+}
+
+#[cfg(feature = "gdal-support")]
+fn composite_rasters_gdal(
+    hrefs: &[String],
+    bbox: Option<[f64; 4]>,
+    method: CompositeMethod,
+    normalized_difference: Option<[usize; 2]>,
+    output_path: &Path,
+) -> AppResult<()> {
+    use gdal::programs::raster::{BuildVRTOptions, build_vrt};
+    use gdal::raster::{Buffer, RasterCreationOptions};
+    use gdal::spatial_ref::{AxisMappingStrategy, CoordTransform, SpatialRef};
+    use gdal::{Dataset, DriverManager};
+
+    let datasets = hrefs
+        .iter()
+        .map(|href| Dataset::open(crate::api::tiles::raster::href_to_vsi_path(href)))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Processing(format!("Failed to open raster: {}", e)))?;
+    let first = datasets
+        .first()
+        .ok_or_else(|| AppError::Processing("No rasters to composite".to_string()))?;
+    let band_count = first.raster_count();
+    let gt = first
+        .geo_transform()
+        .map_err(|e| AppError::Processing(format!("Failed to get geotransform: {}", e)))?;
+    let mut spatial_ref = first
+        .spatial_ref()
+        .map_err(|e| AppError::Processing(format!("Failed to determine raster CRS: {}", e)))?;
+
+    // Stacking reprojects nothing, so the rasters must match
+    for (href, dataset) in hrefs.iter().zip(&datasets).skip(1) {
+        let same_crs = dataset
+            .spatial_ref()
+            .is_ok_and(|other| other == spatial_ref);
+        if !same_crs {
+            return Err(AppError::Processing(format!(
+                "{} has another CRS than {}; composite items of one CRS",
+                href, hrefs[0]
+            )));
+        }
+        if dataset.raster_count() != band_count {
+            return Err(AppError::Processing(format!(
+                "{} has {} bands and {} has {}; composite items of the same bands",
+                href,
+                dataset.raster_count(),
+                hrefs[0],
+                band_count
+            )));
+        }
+    }
+    if let Some(bands) = normalized_difference
+        && bands.iter().any(|band| !(1..=band_count).contains(band))
+    {
+        return Err(AppError::Processing(format!(
+            "normalizedDifference bands must be between 1 and {}",
+            band_count
+        )));
+    }
+
+    // Stack the items as bands of a virtual raster on a common grid
+    let mut grid_args = vec![
+        "-separate".to_string(),
+        "-tr".to_string(),
+        gt[1].abs().to_string(),
+        gt[5].abs().to_string(),
+    ];
+    if let Some(bbox) = bbox {
+        let mut source = SpatialRef::from_epsg(4326)
+            .map_err(|e| AppError::Processing(format!("Failed to create CRS: {}", e)))?;
+        source.set_axis_mapping_strategy(AxisMappingStrategy::TraditionalGisOrder);
+        spatial_ref.set_axis_mapping_strategy(AxisMappingStrategy::TraditionalGisOrder);
+        let extent = CoordTransform::new(&source, &spatial_ref)
+            .and_then(|transform| transform.transform_bounds(&bbox, 21))
+            .map_err(|e| AppError::Processing(format!("Failed to transform bbox: {}", e)))?;
+        grid_args.push("-te".to_string());
+        grid_args.extend(extent.iter().map(f64::to_string));
+    }
+
+    // One stack per band read, with a band per item
+    let source_bands = match normalized_difference {
+        Some(bands) => bands.to_vec(),
+        None => (1..=band_count).collect(),
+    };
+    let output_bands = if normalized_difference.is_some() {
+        1
+    } else {
+        band_count
+    };
+    let stacks = source_bands
+        .iter()
+        .map(|band_index| {
+            let mut args = grid_args.clone();
+            args.extend(["-b".to_string(), band_index.to_string()]);
+            BuildVRTOptions::new(args)
+                .and_then(|options| build_vrt(None, &datasets, Some(options)))
+                .map_err(|e| AppError::Processing(format!("Failed to stack rasters: {}", e)))
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+    let first_stack = stacks
+        .first()
+        .ok_or_else(|| AppError::Processing("Rasters have no bands".to_string()))?;
+    let (width, height) = first_stack.raster_size();
+    if width * height > MAX_COMPOSITE_PIXELS {
+        return Err(AppError::Processing(format!(
+            "The composite would have more than {} pixels per band; narrow the bbox",
+            MAX_COMPOSITE_PIXELS
+        )));
+    }
+
+    let mut composite = DriverManager::get_driver_by_name("MEM")
+        .and_then(|driver| driver.create_with_band_type::<f32, _>("", width, height, output_bands))
+        .map_err(|e| AppError::Processing(format!("Failed to create composite: {}", e)))?;
+    let stack_gt = first_stack
+        .geo_transform()
+        .map_err(|e| AppError::Processing(format!("Failed to get geotransform: {}", e)))?;
+    composite
+        .set_geo_transform(&stack_gt)
+        .map_err(|e| AppError::Processing(format!("Failed to georeference composite: {}", e)))?;
+    composite
+        .set_spatial_ref(&spatial_ref)
+        .map_err(|e| AppError::Processing(format!("Failed to georeference composite: {}", e)))?;
+
+    let mut values = Vec::with_capacity(datasets.len());
+    for band_index in 1..=output_bands {
+        let mut output_band = composite
+            .rasterband(band_index)
+            .map_err(|e| AppError::Processing(format!("Failed to get band: {}", e)))?;
+
+        for row in (0..height).step_by(BLOCK_ROWS) {
+            let rows = BLOCK_ROWS.min(height - row);
+            let slices = match normalized_difference {
+                Some(_) => {
+                    let a = read_slices(&stacks[0], row, width, rows)?;
+                    let b = read_slices(&stacks[1], row, width, rows)?;
+                    a.iter()
+                        .zip(&b)
+                        .map(|(a, b)| {
+                            a.iter()
+                                .zip(b)
+                                .map(|(&a, &b)| normalized_difference_value(a, b))
+                                .collect()
+                        })
+                        .collect()
+                }
+                None => read_slices(&stacks[band_index - 1], row, width, rows)?,
+            };
+
+            let pixels = (0..width * rows)
+                .map(|pixel| {
+                    values.clear();
+                    values.extend(
+                        slices
+                            .iter()
+                            .map(|slice| slice[pixel])
+                            .filter(|v| !v.is_nan()),
+                    );
+                    composite_value(&mut values, method) as f32
+                })
+                .collect();
+            output_band
+                .write(
+                    (0, row as isize),
+                    (width, rows),
+                    &mut Buffer::new((width, rows), pixels),
+                )
+                .map_err(|e| AppError::Processing(format!("Failed to write composite: {}", e)))?;
+        }
+        output_band
+            .set_no_data_value(Some(f64::NAN))
+            .map_err(|e| AppError::Processing(format!("Failed to set no data value: {}", e)))?;
+    }
+
+    let cog_driver = DriverManager::get_driver_by_name("COG")
+        .map_err(|e| AppError::Processing(format!("GDAL COG driver not available: {}", e)))?;
+    let options: RasterCreationOptions = [
+        "BLOCKSIZE=512",
+        "COMPRESS=DEFLATE",
+        "PREDICTOR=YES",
+        "OVERVIEW_RESAMPLING=AVERAGE",
+    ]
+    .into_iter()
+    .collect();
+    composite
+        .create_copy(&cog_driver, output_path, &options)
+        .map_err(|e| AppError::Processing(format!("Failed to write composite: {}", e)))?;
+    Ok(())
+}
+
+#[cfg(feature = "gdal-support")]
+fn normalized_difference_value(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else {
+        normalized_difference(a, b)
+    }
+}
+
+/// Rows of each raster of a stack, with NaN for no data
+#[cfg(feature = "gdal-support")]
+fn read_slices(
+    stack: &gdal::Dataset,
+    row: usize,
+    width: usize,
+    rows: usize,
+) -> AppResult<Vec<Vec<f64>>> {
+    (1..=stack.raster_count())
+        .map(|slice| {
+            let band = stack.rasterband(slice)?;
+            let nodata = band.no_data_value();
+            let data =
+                band.read_as::<f64>((0, row as isize), (width, rows), (width, rows), None)?;
+            Ok(data
+                .data()
+                .iter()
+                .map(|&v| if Some(v) == nodata { f64::NAN } else { v })
+                .collect::<Vec<f64>>())
+        })
+        .collect::<Result<Vec<_>, gdal::errors::GdalError>>()
+        .map_err(|e| AppError::Processing(format!("Failed to read raster: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_composite_value() {
+        let values = [0.4, 0.1, 0.8, 0.3];
+        let composite = |method| composite_value(&mut values.clone(), method);
+        assert_eq!(composite(CompositeMethod::Median), 0.35);
+        assert_eq!(composite(CompositeMethod::Mean), 0.4);
+        assert_eq!(composite(CompositeMethod::Min), 0.1);
+        assert_eq!(composite(CompositeMethod::Max), 0.8);
+        assert_eq!(
+            composite_value(&mut [3.0, 1.0, 2.0], CompositeMethod::Median),
+            2.0
+        );
+        assert!(composite_value(&mut [], CompositeMethod::Mean).is_nan());
+
+        assert_eq!(
+            CompositeMethod::from_param("MEDIAN").unwrap(),
+            CompositeMethod::Median
+        );
+        assert!(CompositeMethod::from_param("mode").is_err());
+    }
+
+    #[test]
+    fn test_normalized_difference() {
+        // NDVI of vegetation and of water
+        assert!((normalized_difference(0.5, 0.1) - 0.6666666666666666).abs() < 1e-12);
+        assert!(normalized_difference(0.02, 0.06) < 0.0);
+        assert!(normalized_difference(0.0, 0.0).is_nan());
+    }
+}
@@ -1,5 +1,9 @@
 use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
+use crate::api::processes::import_pointcloud::PipelineOptions;
 use crate::error::{AppError, AppResult};
 
 /// Check if a file is a valid Cloud Optimized Point Cloud
@@ -20,34 +24,108 @@ pub fn is_copc(path: &Path) -> AppResult<bool> {
     }
 }
 
-/// Convert a point cloud file to Cloud Optimized Point Cloud
-pub async fn convert_to_copc(input_path: &Path, output_path: &Path) -> AppResult<()> {
-    // In a full implementation, we would use PDAL to:
-    // 1. Read the input file
-    // 2. Build an octree structure
-    // 3. Write as COPC
-    //
-    // Example PDAL pipeline:
-    // {
-    //   "pipeline": [
-    //     { "type": "readers.las", "filename": "input.laz" },
-    //     {
-    //       "type": "writers.copc",
-    //       "filename": "output.copc.laz"
-    //     }
-    //   ]
-    // }
+/// Build the PDAL pipeline converting a LAS/LAZ file to COPC
+///
+/// Optional stages run in a fixed order: reprojection, decimation, outlier
+/// removal (noise is classified, then dropped) and ground classification.
+pub fn build_copc_pipeline(
+    input_path: &Path,
+    output_path: &Path,
+    options: &PipelineOptions,
+) -> AppResult<serde_json::Value> {
+    let mut stages = vec![serde_json::json!({
+        "type": "readers.las",
+        "filename": input_path.display().to_string(),
+    })];
+
+    if let Some(srid) = options.target_srid()? {
+        stages.push(serde_json::json!({
+            "type": "filters.reprojection",
+            "out_srs": format!("EPSG:{}", srid),
+        }));
+    }
+    if let Some(step) = options.decimation_step {
+        stages.push(serde_json::json!({ "type": "filters.decimation", "step": step }));
+    }
+    if options.remove_outliers {
+        stages.push(serde_json::json!({
+            "type": "filters.outlier",
+            "method": "statistical",
+            "mean_k": 8,
+            "multiplier": 2.5,
+        }));
+        stages.push(serde_json::json!({
+            "type": "filters.range",
+            "limits": "Classification![7:7]",
+        }));
+    }
+    if options.classify_ground {
+        stages.push(serde_json::json!({ "type": "filters.smrf" }));
+    }
+
+    stages.push(serde_json::json!({
+        "type": "writers.copc",
+        "filename": output_path.display().to_string(),
+    }));
+    Ok(serde_json::json!({ "pipeline": stages }))
+}
 
+/// Convert a point cloud file to Cloud Optimized Point Cloud
+///
+/// Runs `pdal pipeline`, which must be on the PATH. PDAL is killed when it
+/// runs longer than `timeout`.
+pub async fn convert_to_copc(
+    input_path: &Path,
+    output_path: &Path,
+    options: &PipelineOptions,
+    timeout: Duration,
+) -> AppResult<()> {
     tracing::info!(
         "Converting {} to COPC at {}",
         input_path.display(),
         output_path.display()
     );
 
-    // Placeholder - would use PDAL or untwine
-    Err(AppError::Processing(
-        "COPC conversion not yet implemented".to_string(),
-    ))
+    let pipeline = build_copc_pipeline(input_path, output_path, options)?;
+    let mut child = tokio::process::Command::new("pdal")
+        .args(["pipeline", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| AppError::Processing(format!("PDAL is not available: {}", e)))?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    stdin.write_all(&serde_json::to_vec(&pipeline)?).await?;
+    drop(stdin);
+
+    let mut stderr_pipe = child.stderr.take().expect("stderr is piped");
+    let mut stderr = Vec::new();
+    let finished = tokio::time::timeout(timeout, async {
+        tokio::try_join!(child.wait(), stderr_pipe.read_to_end(&mut stderr))
+    })
+    .await;
+    let status = match finished {
+        Ok(result) => result?.0,
+        Err(_) => {
+            child.kill().await.ok();
+            return Err(AppError::Processing(format!(
+                "PDAL pipeline exceeded the {}s time limit",
+                timeout.as_secs()
+            )));
+        }
+    };
+
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        return Err(AppError::Processing(format!(
+            "PDAL pipeline exited with {}: {}",
+            status,
+            stderr.trim()
+        )));
+    }
+    Ok(())
 }
 
 /// Extract metadata from a point cloud file
@@ -64,6 +142,240 @@ pub async fn extract_pointcloud_metadata(path: &Path) -> AppResult<PointCloudMet
     ))
 }
 
+/// Number of bytes needed to read the LAS public header block and the
+/// COPC info VLR header that immediately follows it
+pub const COPC_HEADER_PROBE_SIZE: usize = 375 + 54;
+
+/// Most leading bytes of a remote LAS/COPC file read for the VLRs holding
+/// its CRS
+pub const MAX_LAS_VLR_BYTES: usize = 1 << 20;
+
+/// Fields of the LAS public header block
+#[derive(Debug, Clone)]
+pub struct LasHeader {
+    /// minx, miny, minz, maxx, maxy, maxz in the file's coordinate system
+    pub bounds: [f64; 6],
+    pub point_count: u64,
+    pub point_format: u8,
+    /// Whether the point records are LAZ-compressed (including COPC)
+    pub compressed: bool,
+    /// Size of the public header block, where the VLRs start
+    pub header_size: usize,
+    /// Number of VLRs following the public header block
+    pub vlr_count: u32,
+    /// Byte offset of the first point record
+    pub point_offset: usize,
+    pub record_length: usize,
+    pub scale: [f64; 3],
+    pub offset: [f64; 3],
+}
+
+impl LasHeader {
+    /// Parse the public header block at the start of a LAS/LAZ file
+    pub fn parse(header: &[u8]) -> AppResult<Self> {
+        if header.len() < 227 || &header[0..4] != b"LASF" {
+            return Err(AppError::Processing(
+                "Not a LAS/LAZ file (missing LASF signature)".to_string(),
+            ));
+        }
+
+        let f64_at = |offset: usize| {
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(&header[offset..offset + 8]);
+            f64::from_le_bytes(buf)
+        };
+        let u32_at = |offset: usize| {
+            let mut buf = [0u8; 4];
+            buf.copy_from_slice(&header[offset..offset + 4]);
+            u32::from_le_bytes(buf)
+        };
+
+        let (max_x, min_x) = (f64_at(179), f64_at(187));
+        let (max_y, min_y) = (f64_at(195), f64_at(203));
+        let (max_z, min_z) = (f64_at(211), f64_at(219));
+
+        let version_minor = header[25];
+        let point_count = if version_minor >= 4 && header.len() >= 255 {
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(&header[247..255]);
+            u64::from_le_bytes(buf)
+        } else {
+            u32_at(107) as u64
+        };
+
+        Ok(Self {
+            bounds: [min_x, min_y, min_z, max_x, max_y, max_z],
+            point_count,
+            point_format: header[104] & 0x3f,
+            compressed: header[104] & 0xc0 != 0,
+            header_size: u16::from_le_bytes([header[94], header[95]]) as usize,
+            vlr_count: u32_at(100),
+            point_offset: u32_at(96) as usize,
+            record_length: u16::from_le_bytes([header[105], header[106]]) as usize,
+            scale: [f64_at(131), f64_at(139), f64_at(147)],
+            offset: [f64_at(155), f64_at(163), f64_at(171)],
+        })
+    }
+
+    /// Whether the bounds are plausibly longitudes and latitudes
+    pub fn is_geographic(&self) -> bool {
+        let [min_x, min_y, _, max_x, max_y, _] = self.bounds;
+        (-180.0..=180.0).contains(&min_x)
+            && (-180.0..=180.0).contains(&max_x)
+            && (-90.0..=90.0).contains(&min_y)
+            && (-90.0..=90.0).contains(&max_y)
+    }
+
+    /// Number of leading bytes of the file holding the header and its VLRs,
+    /// up to [`MAX_LAS_VLR_BYTES`]
+    pub fn vlr_end(&self) -> usize {
+        self.point_offset
+            .clamp(COPC_HEADER_PROBE_SIZE, MAX_LAS_VLR_BYTES)
+    }
+
+    /// EPSG code of the CRS recorded in the OGC WKT or GeoTIFF VLR among
+    /// `data`, the leading bytes of the file
+    ///
+    /// The WKT takes precedence, as LAS 1.4 requires; VLRs beyond `data`
+    /// are not read.
+    pub fn vlr_srid(&self, data: &[u8]) -> Option<i32> {
+        let mut offset = self.header_size;
+        let mut geokeys = None;
+        for _ in 0..self.vlr_count {
+            let Some(vlr) = data.get(offset..offset + 54) else {
+                break;
+            };
+            let record_id = u16::from_le_bytes([vlr[18], vlr[19]]);
+            let length = u16::from_le_bytes([vlr[20], vlr[21]]) as usize;
+            let Some(body) = data.get(offset + 54..offset + 54 + length) else {
+                break;
+            };
+            if vlr[2..18].split(|&b| b == 0).next() == Some(b"LASF_Projection") {
+                match record_id {
+                    2112 => {
+                        if let Some(srid) = wkt_epsg(&String::from_utf8_lossy(body)) {
+                            return Some(srid);
+                        }
+                    }
+                    34735 => geokeys = geokey_epsg(body),
+                    _ => {}
+                }
+            }
+            offset += 54 + length;
+        }
+        geokeys
+    }
+}
+
+/// Split a WKT element such as `PROJCS["name",GEOGCS[...],...]` into its
+/// keyword and top-level values
+fn wkt_element(wkt: &str) -> Option<(&str, Vec<&str>)> {
+    let wkt = wkt.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    let open = wkt.find(['[', '('])?;
+    let keyword = wkt[..open].trim();
+    if keyword.is_empty()
+        || !keyword
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return None;
+    }
+    let body = wkt[open + 1..].strip_suffix([']', ')'])?;
+
+    let (mut values, mut start, mut depth, mut quoted) = (Vec::new(), 0, 0, false);
+    for (index, c) in body.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '[' | '(' if !quoted => depth += 1,
+            ']' | ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                values.push(body[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    values.push(body[start..].trim());
+    Some((keyword, values))
+}
+
+/// EPSG code of the horizontal CRS of an OGC WKT 1 or 2 definition
+fn wkt_epsg(wkt: &str) -> Option<i32> {
+    let (keyword, values) = wkt_element(wkt)?;
+    if matches!(
+        keyword.to_ascii_uppercase().as_str(),
+        "COMPD_CS" | "COMPOUNDCRS"
+    ) {
+        // The horizontal CRS comes before the vertical one
+        return values.iter().find_map(|value| wkt_epsg(value));
+    }
+    // The CRS's own identifier comes after those of its parts
+    values.iter().rev().find_map(|value| {
+        let (keyword, values) = wkt_element(value)?;
+        let is_epsg = matches!(keyword.to_ascii_uppercase().as_str(), "AUTHORITY" | "ID")
+            && values
+                .first()?
+                .trim_matches('"')
+                .eq_ignore_ascii_case("EPSG");
+        is_epsg
+            .then(|| values.get(1)?.trim_matches('"').parse().ok())
+            .flatten()
+    })
+}
+
+/// EPSG code of the projected, or else geographic, CRS of a GeoTIFF
+/// GeoKeyDirectoryTag
+fn geokey_epsg(body: &[u8]) -> Option<i32> {
+    let shorts: Vec<u16> = body
+        .chunks_exact(2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .collect();
+    let count = *shorts.get(3)? as usize;
+    let keys = shorts.get(4..4 + count * 4)?;
+    // Keys stored in place (location 0); 32767 is user-defined
+    let code = |key_id: u16| {
+        keys.chunks_exact(4)
+            .find(|key| key[0] == key_id && key[1] == 0)
+            .map(|key| key[3])
+            .filter(|code| !matches!(code, 0 | 32767))
+    };
+    code(3072).or_else(|| code(2048)).map(i32::from)
+}
+
+/// Parse point cloud metadata from the leading bytes of a LAS/COPC file
+///
+/// This lets remote files be cataloged from range reads. The CRS is taken
+/// from the WKT or GeoTIFF VLR, so `data` should extend to
+/// [`LasHeader::vlr_end`]; without either, it is only inferred when the
+/// bounds are plausibly geographic.
+pub fn parse_las_header(data: &[u8]) -> AppResult<PointCloudMetadata> {
+    let header = LasHeader::parse(data)?;
+    let srid = header
+        .vlr_srid(data)
+        .or(header.is_geographic().then_some(4326))
+        .ok_or_else(|| {
+            AppError::Processing(
+                "Point cloud CRS cannot be determined: the file has no WKT or GeoTIFF CRS record"
+                    .to_string(),
+            )
+        })?;
+
+    Ok(PointCloudMetadata {
+        bounds: header.bounds,
+        srid,
+        point_count: header.point_count,
+        point_format: header.point_format,
+        dimensions: Vec::new(),
+    })
+}
+
+/// Check whether the leading bytes of a file contain a COPC info VLR
+pub fn is_copc_header(header: &[u8]) -> bool {
+    header.len() >= COPC_HEADER_PROBE_SIZE
+        && &header[0..4] == b"LASF"
+        && &header[377..381] == b"copc"
+}
+
 #[derive(Debug)]
 pub struct PointCloudMetadata {
     pub bounds: [f64; 6], // minx, miny, minz, maxx, maxy, maxz
@@ -72,3 +384,325 @@ pub struct PointCloudMetadata {
     pub point_format: u8,
     pub dimensions: Vec<String>,
 }
+
+/// A point read from a LAS file
+#[derive(Debug, Clone, PartialEq)]
+pub struct LasPoint {
+    /// X, Y, Z in the file's coordinate system (scale and offset applied)
+    pub position: [f64; 3],
+    /// 16-bit RGB, for point formats that carry color
+    pub color: Option<[u16; 3]>,
+}
+
+/// Byte offset of the RGB fields within a point record
+fn las_color_offset(point_format: u8) -> Option<usize> {
+    match point_format {
+        2 => Some(20),
+        3 | 5 => Some(28),
+        7 | 8 | 10 => Some(30),
+        _ => None,
+    }
+}
+
+/// Samples the points of an uncompressed LAS file while it is read
+///
+/// The file is pushed in chunks from its first byte, so it never has to be
+/// held in memory; only the sampled points are kept. At most `max_points`
+/// points are kept, larger files are thinned by taking every n-th point.
+pub struct LasPointSampler {
+    header: LasHeader,
+    count: usize,
+    stride: usize,
+    color_offset: Option<usize>,
+    /// Bytes of the file pushed so far
+    position: usize,
+    /// Point records seen so far
+    index: usize,
+    /// Start of a record split across chunks
+    partial: Vec<u8>,
+    points: Vec<LasPoint>,
+}
+
+impl LasPointSampler {
+    /// Sampler of a file with the given header
+    ///
+    /// LAZ-compressed files (including COPC) are rejected, as decoding them
+    /// needs a LAZ decompressor.
+    pub fn new(header: LasHeader, max_points: usize) -> AppResult<Self> {
+        if header.compressed {
+            return Err(AppError::Conflict(
+                "LAZ-compressed point clouds cannot be read; only uncompressed LAS is supported"
+                    .to_string(),
+            ));
+        }
+        if header.record_length < 12 {
+            return Err(AppError::Processing(
+                "LAS file has an invalid point record length".to_string(),
+            ));
+        }
+
+        let count = usize::try_from(header.point_count).unwrap_or(usize::MAX);
+        let max_points = max_points.max(1);
+        Ok(Self {
+            color_offset: las_color_offset(header.point_format)
+                .filter(|o| o + 6 <= header.record_length),
+            stride: count.div_ceil(max_points).max(1),
+            points: Vec::with_capacity(count.min(max_points)),
+            count,
+            header,
+            position: 0,
+            index: 0,
+            partial: Vec::new(),
+        })
+    }
+
+    /// Read the next chunk of the file
+    pub fn push(&mut self, chunk: &[u8]) {
+        let skip = self
+            .header
+            .point_offset
+            .saturating_sub(self.position)
+            .min(chunk.len());
+        self.position += chunk.len();
+        let mut chunk = &chunk[skip..];
+        let record_length = self.header.record_length;
+
+        if !self.partial.is_empty() {
+            let take = (record_length - self.partial.len()).min(chunk.len());
+            self.partial.extend_from_slice(&chunk[..take]);
+            chunk = &chunk[take..];
+            if self.partial.len() < record_length {
+                return;
+            }
+            let record = std::mem::take(&mut self.partial);
+            self.record(&record);
+        }
+        for record in chunk.chunks(record_length) {
+            if record.len() == record_length {
+                self.record(record);
+            } else {
+                self.partial.extend_from_slice(record);
+            }
+        }
+    }
+
+    fn record(&mut self, record: &[u8]) {
+        if self.index < self.count && self.index.is_multiple_of(self.stride) {
+            let coordinate = |axis: usize| {
+                let mut buf = [0u8; 4];
+                buf.copy_from_slice(&record[axis * 4..axis * 4 + 4]);
+                i32::from_le_bytes(buf) as f64 * self.header.scale[axis] + self.header.offset[axis]
+            };
+            let channel = |o: usize| u16::from_le_bytes([record[o], record[o + 1]]);
+            self.points.push(LasPoint {
+                position: [coordinate(0), coordinate(1), coordinate(2)],
+                color: self
+                    .color_offset
+                    .map(|o| [channel(o), channel(o + 2), channel(o + 4)]),
+            });
+        }
+        self.index += 1;
+    }
+
+    /// The sampled points, once the whole file was pushed
+    pub fn finish(self) -> AppResult<Vec<LasPoint>> {
+        if self.index < self.count {
+            return Err(AppError::Processing(
+                "LAS file is truncated or has an invalid point record length".to_string(),
+            ));
+        }
+        Ok(self.points)
+    }
+}
+
+/// Read the points of an uncompressed LAS file held in memory
+///
+/// See [`LasPointSampler`] for files that are read as a stream.
+pub fn read_las_points(data: &[u8], max_points: usize) -> AppResult<Vec<LasPoint>> {
+    let mut sampler = LasPointSampler::new(LasHeader::parse(data)?, max_points)?;
+    sampler.push(data);
+    sampler.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_copc_pipeline() {
+        let (input, output) = (Path::new("/tmp/in.laz"), Path::new("/tmp/out.copc.laz"));
+        let pipeline = build_copc_pipeline(input, output, &PipelineOptions::default()).unwrap();
+        let types: Vec<&str> = pipeline["pipeline"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|stage| stage["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(types, ["readers.las", "writers.copc"]);
+        assert_eq!(pipeline["pipeline"][1]["filename"], "/tmp/out.copc.laz");
+
+        let options = PipelineOptions {
+            target_crs: Some("http://www.opengis.net/def/crs/EPSG/0/3006".to_string()),
+            decimation_step: Some(4),
+            remove_outliers: true,
+            classify_ground: true,
+        };
+        let pipeline = build_copc_pipeline(input, output, &options).unwrap();
+        let stages = pipeline["pipeline"].as_array().unwrap();
+        assert_eq!(stages.len(), 7);
+        assert_eq!(stages[1]["out_srs"], "EPSG:3006");
+        assert_eq!(stages[2]["step"], 4);
+        assert_eq!(stages[5]["type"], "filters.smrf");
+    }
+
+    /// A LAS 1.2 file with point format 2 (XYZ + RGB)
+    fn las_file(points: &[([i32; 3], [u16; 3])]) -> Vec<u8> {
+        let mut data = vec![0u8; 227];
+        data[0..4].copy_from_slice(b"LASF");
+        data[24] = 1;
+        data[25] = 2;
+        data[94..96].copy_from_slice(&227u16.to_le_bytes());
+        data[96..100].copy_from_slice(&227u32.to_le_bytes());
+        data[104] = 2;
+        data[105..107].copy_from_slice(&26u16.to_le_bytes());
+        data[107..111].copy_from_slice(&(points.len() as u32).to_le_bytes());
+        for (offset, value) in [(131, 1e-7), (139, 1e-7), (147, 0.01)] {
+            data[offset..offset + 8].copy_from_slice(&f64::to_le_bytes(value));
+        }
+        for (offset, value) in [(155, 18.0), (163, 59.0), (171, 0.0)] {
+            data[offset..offset + 8].copy_from_slice(&f64::to_le_bytes(value));
+        }
+        for (offset, value) in [(179, 18.1), (187, 18.0), (195, 59.1), (203, 59.0)] {
+            data[offset..offset + 8].copy_from_slice(&f64::to_le_bytes(value));
+        }
+
+        for (xyz, rgb) in points {
+            let mut record = vec![0u8; 26];
+            for (axis, value) in xyz.iter().enumerate() {
+                record[axis * 4..axis * 4 + 4].copy_from_slice(&value.to_le_bytes());
+            }
+            for (channel, value) in rgb.iter().enumerate() {
+                record[20 + channel * 2..22 + channel * 2].copy_from_slice(&value.to_le_bytes());
+            }
+            data.extend(record);
+        }
+        data
+    }
+
+    #[test]
+    fn test_read_las_points() {
+        let data = las_file(&[
+            ([0, 0, 1000], [65535, 0, 0]),
+            ([500_000, 250_000, 2550], [0, 0, 256]),
+        ]);
+        let points = read_las_points(&data, 10).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].position, [18.0, 59.0, 10.0]);
+        assert_eq!(points[0].color, Some([65535, 0, 0]));
+        assert!((points[1].position[0] - 18.05).abs() < 1e-9);
+        assert!((points[1].position[1] - 59.025).abs() < 1e-9);
+        assert!((points[1].position[2] - 25.5).abs() < 1e-9);
+        assert_eq!(points[1].color, Some([0, 0, 256]));
+    }
+
+    #[test]
+    fn test_read_las_points_thins_large_files() {
+        let points: Vec<_> = (0..10).map(|i| ([i, 0, 0], [0, 0, 0])).collect();
+        let data = las_file(&points);
+        let read = read_las_points(&data, 4).unwrap();
+        assert_eq!(read.len(), 4);
+        assert!((read[1].position[0] - (18.0 + 3e-7)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_read_las_points_rejects_laz_and_truncated_files() {
+        let mut data = las_file(&[([0, 0, 0], [0, 0, 0])]);
+        data[104] |= 0x80;
+        assert!(matches!(
+            read_las_points(&data, 10),
+            Err(AppError::Conflict(_))
+        ));
+
+        let mut data = las_file(&[([0, 0, 0], [0, 0, 0])]);
+        data.truncate(240);
+        assert!(read_las_points(&data, 10).is_err());
+    }
+
+    #[test]
+    fn test_las_point_sampler_chunks() {
+        let points: Vec<_> = (0..10).map(|i| ([i, 0, 0], [0, 0, i as u16])).collect();
+        let data = las_file(&points);
+        let mut sampler = LasPointSampler::new(LasHeader::parse(&data).unwrap(), 5).unwrap();
+        // Chunks that split the header and the point records
+        for chunk in data.chunks(7) {
+            sampler.push(chunk);
+        }
+        let sampled = sampler.finish().unwrap();
+        assert_eq!(sampled, read_las_points(&data, 5).unwrap());
+        assert_eq!(sampled.len(), 5);
+        assert_eq!(sampled[4].color, Some([0, 0, 8]));
+
+        // Projected coordinates are read as they are
+        let mut data = las_file(&[([0, 0, 0], [0, 0, 0])]);
+        data[155..163].copy_from_slice(&f64::to_le_bytes(674_000.0));
+        data[187..195].copy_from_slice(&f64::to_le_bytes(674_000.0));
+        let header = LasHeader::parse(&data).unwrap();
+        assert!(!header.is_geographic());
+        assert_eq!(read_las_points(&data, 1).unwrap()[0].position[0], 674_000.0);
+    }
+
+    /// A LAS file without points whose VLRs are `(record id, body)` of
+    /// LASF_Projection records
+    fn las_file_with_crs(vlrs: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut data = las_file(&[]);
+        for (record_id, body) in vlrs {
+            let mut vlr = vec![0u8; 54];
+            vlr[2..17].copy_from_slice(b"LASF_Projection");
+            vlr[18..20].copy_from_slice(&record_id.to_le_bytes());
+            vlr[20..22].copy_from_slice(&(body.len() as u16).to_le_bytes());
+            data.extend(vlr);
+            data.extend(body);
+        }
+        let point_offset = data.len() as u32;
+        data[96..100].copy_from_slice(&point_offset.to_le_bytes());
+        data[100..104].copy_from_slice(&(vlrs.len() as u32).to_le_bytes());
+        // Projected bounds, so the CRS can't be inferred from them
+        data[187..195].copy_from_slice(&f64::to_le_bytes(674_000.0));
+        data
+    }
+
+    #[test]
+    fn test_parse_las_header_crs() {
+        let wkt = r#"COMPD_CS["SWEREF99 TM + RH2000",PROJCS["SWEREF99 TM",GEOGCS["SWEREF99",DATUM["SWEREF99",SPHEROID["GRS 1980",6378137,298.257222101,AUTHORITY["EPSG","7019"]],AUTHORITY["EPSG","6619"]],AUTHORITY["EPSG","4619"]],PROJECTION["Transverse_Mercator"],UNIT["metre",1,AUTHORITY["EPSG","9001"]],AUTHORITY["EPSG","3006"]],VERT_CS["RH2000 height",VERT_DATUM["Rikets hojdsystem 2000",2005,AUTHORITY["EPSG","5208"]],AUTHORITY["EPSG","5613"]]]"#;
+        let mut body = wkt.as_bytes().to_vec();
+        body.push(0);
+        let data = las_file_with_crs(&[(2112, body)]);
+        assert_eq!(parse_las_header(&data).unwrap().srid, 3006);
+
+        let wkt2 = r#"PROJCRS["ETRS89 / UTM zone 33N",BASEGEOGCRS["ETRS89",ID["EPSG",4258]],CONVERSION["UTM zone 33N",ID["EPSG",16033]],ID["EPSG",25833]]"#;
+        let data = las_file_with_crs(&[(2112, wkt2.as_bytes().to_vec())]);
+        assert_eq!(parse_las_header(&data).unwrap().srid, 25833);
+
+        // GeoKeyDirectoryTag with GTModelTypeGeoKey and ProjectedCSTypeGeoKey
+        let geokeys: Vec<u8> = [1u16, 1, 0, 2, 1024, 0, 1, 1, 3072, 0, 1, 32633]
+            .iter()
+            .flat_map(|short| short.to_le_bytes())
+            .collect();
+        let data = las_file_with_crs(&[(34735, geokeys.clone())]);
+        assert_eq!(parse_las_header(&data).unwrap().srid, 32633);
+
+        // The WKT takes precedence over the GeoKeys
+        let data = las_file_with_crs(&[(34735, geokeys), (2112, wkt2.as_bytes().to_vec())]);
+        assert_eq!(parse_las_header(&data).unwrap().srid, 25833);
+
+        // Without a CRS record projected bounds leave the CRS unknown, and
+        // so do VLRs past the bytes read
+        assert!(parse_las_header(&las_file_with_crs(&[])).is_err());
+        let data = las_file_with_crs(&[(2112, wkt2.as_bytes().to_vec())]);
+        assert!(parse_las_header(&data[..260]).is_err());
+        assert_eq!(
+            LasHeader::parse(&data).unwrap().vlr_end(),
+            COPC_HEADER_PROBE_SIZE
+        );
+    }
+}
@@ -0,0 +1,489 @@
+//! Export of vector collections to desktop GIS formats
+//!
+//! Features are exported in WGS 84 as zipped Shapefiles, KML/KMZ,
+//! newline-delimited GeoJSON or GeoPackages. The encoders work on GeoJSON
+//! geometries as returned by PostGIS, and write to files as the features are
+//! read, so exports are not limited by memory. GeoPackages are only written
+//! by the `export-collection` process.
+
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use tokio::io::AsyncWriteExt;
+
+use super::archive::write_zip;
+use super::kml;
+use super::shapefile::{self, ShapefileSchema};
+use crate::api::common::media_type;
+use crate::api::features::Feature;
+use crate::error::{AppError, AppResult};
+
+/// A file format collections can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// ESRI Shapefile, zipped with its sidecar files
+    Shapefile,
+    /// Keyhole Markup Language
+    Kml,
+    /// Zipped KML
+    Kmz,
+    /// Newline-delimited GeoJSON, one feature per line
+    NdJson,
+    /// OGC GeoPackage
+    GeoPackage,
+}
+
+impl ExportFormat {
+    /// Parse the `f` query parameter
+    pub fn from_param(value: &str) -> AppResult<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "shapefile" | "shp" => Ok(Self::Shapefile),
+            "kml" => Ok(Self::Kml),
+            "kmz" => Ok(Self::Kmz),
+            "ndjson" => Ok(Self::NdJson),
+            "gpkg" | "geopackage" => Ok(Self::GeoPackage),
+            other => Err(AppError::BadRequest(format!(
+                "Unsupported export format: {} (supported: shapefile, kml, kmz, ndjson, gpkg)",
+                other
+            ))),
+        }
+    }
+
+    pub fn media_type(self) -> &'static str {
+        match self {
+            Self::Shapefile => "application/zip",
+            Self::Kml => "application/vnd.google-earth.kml+xml",
+            Self::Kmz => "application/vnd.google-earth.kmz",
+            Self::NdJson => media_type::NDJSON,
+            Self::GeoPackage => "application/geopackage+sqlite3",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Shapefile => "zip",
+            Self::Kml => "kml",
+            Self::Kmz => "kmz",
+            Self::NdJson => "ndjson",
+            Self::GeoPackage => "gpkg",
+        }
+    }
+}
+
+/// Character encoding of Shapefile attributes
+///
+/// KML is always UTF-8; Shapefile attributes default to UTF-8 too, but older
+/// tools may need Latin-1. The encoding is declared in the `.cpg` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    Latin1,
+}
+
+impl TextEncoding {
+    /// Parse the `encoding` query parameter
+    pub fn from_param(value: &str) -> AppResult<Self> {
+        match value.to_ascii_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Ok(Self::Utf8),
+            "iso-8859-1" | "latin1" | "latin-1" => Ok(Self::Latin1),
+            other => Err(AppError::BadRequest(format!(
+                "Unsupported encoding: {} (supported: utf-8, iso-8859-1)",
+                other
+            ))),
+        }
+    }
+
+    /// Code page name for the `.cpg` sidecar file
+    pub fn code_page(self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Latin1 => "ISO-8859-1",
+        }
+    }
+
+    /// Encode text, replacing characters the encoding cannot represent with `?`
+    ///
+    /// The result is truncated to at most `max_len` bytes without splitting
+    /// a character.
+    pub fn encode(self, text: &str, max_len: usize) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(text.len().min(max_len));
+        for c in text.chars() {
+            let mut buffer = [0u8; 4];
+            let encoded: &[u8] = match self {
+                Self::Utf8 => c.encode_utf8(&mut buffer).as_bytes(),
+                Self::Latin1 => {
+                    buffer[0] = u8::try_from(u32::from(c)).unwrap_or(b'?');
+                    &buffer[..1]
+                }
+            };
+            if bytes.len() + encoded.len() > max_len {
+                break;
+            }
+            bytes.extend_from_slice(encoded);
+        }
+        bytes
+    }
+}
+
+/// A coordinate; the third value, if any, is the height
+pub type Position = Vec<f64>;
+
+/// A parsed GeoJSON geometry
+#[derive(Debug, Clone, PartialEq)]
+pub enum Geometry {
+    Point(Position),
+    MultiPoint(Vec<Position>),
+    LineString(Vec<Position>),
+    MultiLineString(Vec<Vec<Position>>),
+    Polygon(Vec<Vec<Position>>),
+    MultiPolygon(Vec<Vec<Vec<Position>>>),
+    GeometryCollection(Vec<Geometry>),
+}
+
+fn invalid_geometry(reason: &str) -> AppError {
+    AppError::Processing(format!("Invalid geometry: {}", reason))
+}
+
+fn parse_position(value: &Value) -> AppResult<Position> {
+    let position = value
+        .as_array()
+        .map(|values| {
+            values
+                .iter()
+                .map(Value::as_f64)
+                .collect::<Option<Position>>()
+        })
+        .unwrap_or_default()
+        .filter(|position| position.len() >= 2)
+        .ok_or_else(|| invalid_geometry("positions need at least two numbers"))?;
+    Ok(position)
+}
+
+fn parse_array<T>(value: &Value, parse: impl Fn(&Value) -> AppResult<T>) -> AppResult<Vec<T>> {
+    value
+        .as_array()
+        .ok_or_else(|| invalid_geometry("coordinates must be arrays"))?
+        .iter()
+        .map(parse)
+        .collect()
+}
+
+impl Geometry {
+    /// Parse a GeoJSON geometry object; `null` has no geometry
+    pub fn from_geojson(value: &Value) -> AppResult<Option<Self>> {
+        if value.is_null() {
+            return Ok(None);
+        }
+        let geometry_type = value
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid_geometry("missing type"))?;
+        if geometry_type == "GeometryCollection" {
+            let geometries = parse_array(
+                value.get("geometries").unwrap_or(&Value::Null),
+                |geometry| {
+                    Self::from_geojson(geometry)?
+                        .ok_or_else(|| invalid_geometry("null member of a collection"))
+                },
+            )?;
+            return Ok(Some(Self::GeometryCollection(geometries)));
+        }
+
+        let coordinates = value.get("coordinates").unwrap_or(&Value::Null);
+        let lines = |value: &Value| parse_array(value, parse_position);
+        let polygon = |value: &Value| parse_array(value, lines);
+        let geometry = match geometry_type {
+            "Point" => Self::Point(parse_position(coordinates)?),
+            "MultiPoint" => Self::MultiPoint(lines(coordinates)?),
+            "LineString" => Self::LineString(lines(coordinates)?),
+            "MultiLineString" => Self::MultiLineString(polygon(coordinates)?),
+            "Polygon" => Self::Polygon(polygon(coordinates)?),
+            "MultiPolygon" => Self::MultiPolygon(parse_array(coordinates, polygon)?),
+            other => return Err(invalid_geometry(&format!("unknown type {}", other))),
+        };
+        Ok(Some(geometry))
+    }
+}
+
+/// Make a collection name safe for use as a file name and in headers
+pub fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if stem.is_empty() {
+        "export".to_string()
+    } else {
+        stem
+    }
+}
+
+/// Write features to a file in an export format, returning how many were
+/// written
+///
+/// `name` is used for file names inside archives, `title` as the KML
+/// document name. KML and newline-delimited GeoJSON are written as the
+/// features arrive. Shapefile fields and layers depend on all features, so
+/// the features are spooled to disk before the layers are written; KMZ
+/// archives zip the finished KML document. Work files are kept next to
+/// `path`. GeoPackages are written with
+/// [`super::geopackage::GeoPackageWriter`].
+pub async fn write_export<S>(
+    format: ExportFormat,
+    name: &str,
+    title: &str,
+    features: S,
+    encoding: TextEncoding,
+    path: &Path,
+) -> AppResult<usize>
+where
+    S: Stream<Item = AppResult<Feature>>,
+{
+    match format {
+        ExportFormat::Kml | ExportFormat::NdJson => {
+            write_text(format, title, features, path, |_| Ok(())).await
+        }
+        ExportFormat::Kmz => {
+            let document = path.with_extension("kml.part");
+            let written =
+                write_text(ExportFormat::Kml, title, features, &document, |_| Ok(())).await;
+            let zipped = match written {
+                Ok(count) => {
+                    let files = vec![("doc.kml".to_string(), document.clone())];
+                    let path = path.to_path_buf();
+                    blocking(move || write_zip(&files, &path))
+                        .await
+                        .map(|_| count)
+                }
+                Err(e) => Err(e),
+            };
+            tokio::fs::remove_file(&document).await.ok();
+            zipped
+        }
+        ExportFormat::Shapefile => {
+            let spool = path.with_extension("ndjson.part");
+            let dir = path.with_extension("shp.part");
+            let mut schema = ShapefileSchema::new(encoding);
+            let written = write_text(ExportFormat::NdJson, title, features, &spool, |feature| {
+                schema.add(feature)
+            })
+            .await;
+            let zipped = match written {
+                Ok(count) => {
+                    let (stem, spool, dir, path) = (
+                        file_stem(name),
+                        spool.clone(),
+                        dir.clone(),
+                        path.to_path_buf(),
+                    );
+                    blocking(move || {
+                        std::fs::create_dir_all(&dir)?;
+                        let features = BufReader::new(std::fs::File::open(&spool)?)
+                            .lines()
+                            .map(|line| Ok(serde_json::from_str::<Feature>(&line?)?));
+                        let files = shapefile::write_shapefiles(&stem, &schema, features, &dir)?;
+                        write_zip(&files, &path)
+                    })
+                    .await
+                    .map(|_| count)
+                }
+                Err(e) => Err(e),
+            };
+            tokio::fs::remove_file(&spool).await.ok();
+            tokio::fs::remove_dir_all(&dir).await.ok();
+            zipped
+        }
+        ExportFormat::GeoPackage => Err(AppError::BadRequest(
+            "GeoPackage exports run as the export-collection process".to_string(),
+        )),
+    }
+}
+
+/// Write features as a KML document or newline-delimited GeoJSON, passing
+/// each to `inspect` on the way
+async fn write_text<S>(
+    format: ExportFormat,
+    title: &str,
+    features: S,
+    path: &Path,
+    mut inspect: impl FnMut(&Feature) -> AppResult<()>,
+) -> AppResult<usize>
+where
+    S: Stream<Item = AppResult<Feature>>,
+{
+    let mut file = tokio::io::BufWriter::new(tokio::fs::File::create(path).await?);
+    let mut features = std::pin::pin!(features);
+    let mut count = 0;
+    if format == ExportFormat::Kml {
+        file.write_all(kml::kml_start(title).as_bytes()).await?;
+    }
+    while let Some(feature) = features.next().await {
+        let feature = feature?;
+        inspect(&feature)?;
+        if format == ExportFormat::Kml {
+            let mut placemark = String::new();
+            kml::write_placemark(&mut placemark, &feature)?;
+            file.write_all(placemark.as_bytes()).await?;
+        } else {
+            let mut line = serde_json::to_vec(&feature)?;
+            line.push(b'\n');
+            file.write_all(&line).await?;
+        }
+        count += 1;
+    }
+    if format == ExportFormat::Kml {
+        file.write_all(kml::KML_END.as_bytes()).await?;
+    }
+    file.flush().await?;
+    Ok(count)
+}
+
+/// Run file encoding off the async runtime
+async fn blocking(task: impl FnOnce() -> AppResult<()> + Send + 'static) -> AppResult<()> {
+    tokio::task::spawn_blocking(task)
+        .await
+        .map_err(|e| AppError::Processing(format!("Export task failed: {}", e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_geometry() {
+        assert_eq!(Geometry::from_geojson(&Value::Null).unwrap(), None);
+        assert_eq!(
+            Geometry::from_geojson(&json!({"type": "Point", "coordinates": [1, 2.5, 3]})).unwrap(),
+            Some(Geometry::Point(vec![1.0, 2.5, 3.0]))
+        );
+        assert_eq!(
+            Geometry::from_geojson(&json!({
+                "type": "GeometryCollection",
+                "geometries": [{"type": "LineString", "coordinates": [[0, 0], [1, 1]]}]
+            }))
+            .unwrap(),
+            Some(Geometry::GeometryCollection(vec![Geometry::LineString(
+                vec![vec![0.0, 0.0], vec![1.0, 1.0]]
+            )]))
+        );
+        assert!(Geometry::from_geojson(&json!({"type": "Point", "coordinates": [1]})).is_err());
+        assert!(Geometry::from_geojson(&json!({"type": "Curve", "coordinates": []})).is_err());
+    }
+
+    #[test]
+    fn test_encode_text() {
+        assert_eq!(
+            TextEncoding::Utf8.encode("Göteborg", 20),
+            "Göteborg".as_bytes()
+        );
+        // Never splits a multi-byte character
+        assert_eq!(TextEncoding::Utf8.encode("Gö", 2), b"G");
+        assert_eq!(TextEncoding::Latin1.encode("Göteborg", 20), b"G\xf6teborg");
+        assert_eq!(TextEncoding::Latin1.encode("東京", 20), b"??");
+        assert_eq!(TextEncoding::Latin1.encode("abc", 2), b"ab");
+    }
+
+    #[test]
+    fn test_parse_params() {
+        assert_eq!(
+            ExportFormat::from_param("SHP").unwrap(),
+            ExportFormat::Shapefile
+        );
+        assert_eq!(
+            ExportFormat::from_param("ndjson").unwrap(),
+            ExportFormat::NdJson
+        );
+        assert_eq!(
+            ExportFormat::from_param("gpkg").unwrap(),
+            ExportFormat::GeoPackage
+        );
+        assert!(ExportFormat::from_param("geoparquet").is_err());
+        assert_eq!(
+            TextEncoding::from_param("latin1").unwrap(),
+            TextEncoding::Latin1
+        );
+        assert!(TextEncoding::from_param("utf-16").is_err());
+        assert_eq!(file_stem("alice:roads"), "alice_roads");
+    }
+
+    #[tokio::test]
+    async fn test_write_export() {
+        let feature = |id: &str| Feature {
+            feature_type: "Feature".to_string(),
+            id: id.to_string(),
+            geometry: serde_json::json!({"type": "Point", "coordinates": [1.0, 2.0]}),
+            properties: serde_json::json!({"name": "a"}),
+            links: None,
+            bbox: None,
+            assets: None,
+            collection: None,
+            stac_version: None,
+            stac_extensions: None,
+        };
+        let dir = std::env::temp_dir().join(format!("export-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let export = |format: ExportFormat| {
+            let path = dir.join(format!("roads.{}", format.extension()));
+            let features = futures::stream::iter([Ok(feature("1")), Ok(feature("2"))]);
+            async move {
+                let count = write_export(
+                    format,
+                    "roads",
+                    "Roads",
+                    features,
+                    TextEncoding::default(),
+                    &path,
+                )
+                .await
+                .unwrap();
+                assert_eq!(count, 2);
+                path
+            }
+        };
+
+        let text = std::fs::read_to_string(export(ExportFormat::NdJson).await).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let second: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(second["id"], "2");
+
+        let entries = super::super::archive::extract_zip(
+            &export(ExportFormat::Shapefile).await,
+            &dir.join("shapefile"),
+            &["shp", "dbf", "cpg"],
+            1 << 20,
+        )
+        .unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["roads.shp", "roads.dbf", "roads.cpg"]);
+
+        let entries = super::super::archive::extract_zip(
+            &export(ExportFormat::Kmz).await,
+            &dir.join("kmz"),
+            &["kml"],
+            1 << 20,
+        )
+        .unwrap();
+        let kml = std::fs::read_to_string(&entries[0].path).unwrap();
+        assert_eq!(kml.matches("<Placemark>").count(), 2);
+
+        // Only the exports are left; work files are removed
+        let mut files: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.contains('.'))
+            .collect();
+        files.sort();
+        assert_eq!(files, ["roads.kmz", "roads.ndjson", "roads.zip"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,508 @@
+//! GeoPackage writer
+//!
+//! A GeoPackage is a SQLite database with a few metadata tables. Features are
+//! written in WGS 84 to one feature table with a column per property, and
+//! geometries in the GeoPackage binary format: a header with the SRS and
+//! envelope followed by WKB. Objects and arrays are stored as JSON text.
+//!
+//! Features are inserted one at a time as they are read, so exports of any
+//! size are written to disk rather than held in memory.
+
+use serde_json::Value;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous};
+use sqlx::{Connection, SqliteConnection};
+use std::collections::HashSet;
+use std::path::Path;
+
+use super::export::{Geometry, Position};
+use crate::api::features::Feature;
+use crate::error::{AppError, AppResult};
+use crate::services::PropertyType;
+
+/// `application_id` of GeoPackage files, "GPKG" in ASCII
+const APPLICATION_ID: i32 = 0x4750_4B47;
+
+/// `user_version` of GeoPackage 1.3
+const USER_VERSION: i32 = 10300;
+
+/// Definition of WGS 84 in the EPSG registry
+const WGS84_WKT: &str = "GEOGCS[\"WGS 84\",DATUM[\"WGS_1984\",SPHEROID[\"WGS 84\",6378137,298.257223563,AUTHORITY[\"EPSG\",\"7030\"]],AUTHORITY[\"EPSG\",\"6326\"]],PRIMEM[\"Greenwich\",0,AUTHORITY[\"EPSG\",\"8901\"]],UNIT[\"degree\",0.0174532925199433,AUTHORITY[\"EPSG\",\"9122\"]],AUTHORITY[\"EPSG\",\"4326\"]]";
+
+/// Columns of the feature table besides the properties
+const RESERVED_COLUMNS: &[&str] = &["fid", "geom", "id"];
+
+/// Metadata tables of GeoPackage 1.3, with the SRS required by the standard
+const METADATA_TABLES: &str = r#"
+CREATE TABLE gpkg_spatial_ref_sys (
+    srs_name TEXT NOT NULL,
+    srs_id INTEGER NOT NULL PRIMARY KEY,
+    organization TEXT NOT NULL,
+    organization_coordsys_id INTEGER NOT NULL,
+    definition TEXT NOT NULL,
+    description TEXT
+);
+CREATE TABLE gpkg_contents (
+    table_name TEXT NOT NULL PRIMARY KEY,
+    data_type TEXT NOT NULL,
+    identifier TEXT UNIQUE,
+    description TEXT DEFAULT '',
+    last_change DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    min_x DOUBLE,
+    min_y DOUBLE,
+    max_x DOUBLE,
+    max_y DOUBLE,
+    srs_id INTEGER,
+    CONSTRAINT fk_gc_r_srs_id FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys (srs_id)
+);
+CREATE TABLE gpkg_geometry_columns (
+    table_name TEXT NOT NULL,
+    column_name TEXT NOT NULL,
+    geometry_type_name TEXT NOT NULL,
+    srs_id INTEGER NOT NULL,
+    z TINYINT NOT NULL,
+    m TINYINT NOT NULL,
+    CONSTRAINT pk_geom_cols PRIMARY KEY (table_name, column_name),
+    CONSTRAINT uk_gc_table_name UNIQUE (table_name),
+    CONSTRAINT fk_gc_tn FOREIGN KEY (table_name) REFERENCES gpkg_contents (table_name),
+    CONSTRAINT fk_gc_srs FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys (srs_id)
+);
+INSERT INTO gpkg_spatial_ref_sys VALUES
+    ('Undefined cartesian SRS', -1, 'NONE', -1, 'undefined',
+     'undefined cartesian coordinate reference system'),
+    ('Undefined geographic SRS', 0, 'NONE', 0, 'undefined',
+     'undefined geographic coordinate reference system');
+"#;
+
+fn write_error(e: sqlx::Error) -> AppError {
+    AppError::Processing(format!("Failed to write GeoPackage: {}", e))
+}
+
+/// Quote a SQLite identifier
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Column names of the properties, unique regardless of case (as SQLite
+/// compares them) and clear of the reserved columns, e.g. a property `id`
+/// becomes `id_1`
+fn column_names(properties: &[(String, PropertyType)]) -> Vec<String> {
+    let mut taken: HashSet<String> = RESERVED_COLUMNS.iter().map(|c| c.to_string()).collect();
+    properties
+        .iter()
+        .map(|(name, _)| {
+            let mut column = name.clone();
+            let mut suffix = 1;
+            while !taken.insert(column.to_lowercase()) {
+                column = format!("{}_{}", name, suffix);
+                suffix += 1;
+            }
+            column
+        })
+        .collect()
+}
+
+fn column_type(property_type: PropertyType) -> &'static str {
+    match property_type {
+        PropertyType::String => "TEXT",
+        PropertyType::Integer => "INTEGER",
+        PropertyType::Number => "REAL",
+        PropertyType::Boolean => "BOOLEAN",
+    }
+}
+
+/// Writes the features of one collection to a GeoPackage file
+pub struct GeoPackageWriter {
+    connection: SqliteConnection,
+    table: String,
+    insert: String,
+    properties: Vec<(String, PropertyType)>,
+    extent: Option<[f64; 4]>,
+    count: usize,
+}
+
+impl GeoPackageWriter {
+    /// Create a GeoPackage at `path` with the feature table `table`, having
+    /// a column for each of `properties`
+    pub async fn create(
+        path: &Path,
+        table: &str,
+        title: &str,
+        properties: &[(String, PropertyType)],
+    ) -> AppResult<Self> {
+        // The file is written once from scratch, so durability isn't needed
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Off)
+            .synchronous(SqliteSynchronous::Off);
+        let mut connection = SqliteConnection::connect_with(&options)
+            .await
+            .map_err(write_error)?;
+
+        let columns = column_names(properties);
+        let mut create = format!(
+            "CREATE TABLE {} (fid INTEGER PRIMARY KEY AUTOINCREMENT, geom GEOMETRY, id TEXT",
+            quote(table)
+        );
+        for (column, (_, property_type)) in columns.iter().zip(properties) {
+            create.push_str(&format!(
+                ", {} {}",
+                quote(column),
+                column_type(*property_type)
+            ));
+        }
+        create.push(')');
+
+        let sql = format!(
+            "PRAGMA application_id = {}; PRAGMA user_version = {}; {} {};",
+            APPLICATION_ID, USER_VERSION, METADATA_TABLES, create
+        );
+        sqlx::raw_sql(&sql)
+            .execute(&mut connection)
+            .await
+            .map_err(write_error)?;
+        sqlx::query(
+            "INSERT INTO gpkg_spatial_ref_sys VALUES ('WGS 84 geodetic', 4326, 'EPSG', 4326, ?1, \
+             'longitude/latitude coordinates in decimal degrees on the WGS 84 spheroid')",
+        )
+        .bind(WGS84_WKT)
+        .execute(&mut connection)
+        .await
+        .map_err(write_error)?;
+        sqlx::query(
+            "INSERT INTO gpkg_contents (table_name, data_type, identifier, srs_id) \
+             VALUES (?1, 'features', ?2, 4326)",
+        )
+        .bind(table)
+        .bind(title)
+        .execute(&mut connection)
+        .await
+        .map_err(write_error)?;
+        // Heights are optional: z = 2
+        sqlx::query(
+            "INSERT INTO gpkg_geometry_columns VALUES (?1, 'geom', 'GEOMETRY', 4326, 2, 0)",
+        )
+        .bind(table)
+        .execute(&mut connection)
+        .await
+        .map_err(write_error)?;
+
+        // All features go in one transaction, committed by finish
+        sqlx::raw_sql("BEGIN")
+            .execute(&mut connection)
+            .await
+            .map_err(write_error)?;
+
+        let placeholders: Vec<String> =
+            (1..=columns.len() + 2).map(|i| format!("?{}", i)).collect();
+        let insert = format!(
+            "INSERT INTO {} (geom, id{}) VALUES ({})",
+            quote(table),
+            columns
+                .iter()
+                .map(|column| format!(", {}", quote(column)))
+                .collect::<String>(),
+            placeholders.join(", ")
+        );
+
+        Ok(Self {
+            connection,
+            table: table.to_string(),
+            insert,
+            properties: properties.to_vec(),
+            extent: None,
+            count: 0,
+        })
+    }
+
+    /// Add a feature
+    pub async fn write(&mut self, feature: &Feature) -> AppResult<()> {
+        let geometry = Geometry::from_geojson(&feature.geometry)?;
+        if let Some(bounds) = geometry.as_ref().and_then(envelope) {
+            self.extent = Some(match self.extent {
+                Some(extent) => [
+                    extent[0].min(bounds[0]),
+                    extent[1].min(bounds[1]),
+                    extent[2].max(bounds[2]),
+                    extent[3].max(bounds[3]),
+                ],
+                None => bounds,
+            });
+        }
+
+        let mut query = sqlx::query(&self.insert)
+            .bind(geometry.as_ref().map(geometry_blob))
+            .bind(&feature.id);
+        for (name, property_type) in &self.properties {
+            let value = feature.properties.get(name).unwrap_or(&Value::Null);
+            query = match (property_type, value) {
+                (_, Value::Null) => query.bind(None::<String>),
+                (PropertyType::Integer, value) => query.bind(value.as_i64()),
+                (PropertyType::Number, value) => query.bind(value.as_f64()),
+                (PropertyType::Boolean, value) => query.bind(value.as_bool()),
+                (PropertyType::String, Value::String(text)) => query.bind(text.as_str()),
+                (PropertyType::String, value) => query.bind(value.to_string()),
+            };
+        }
+        query
+            .execute(&mut self.connection)
+            .await
+            .map_err(write_error)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Record the extent of the features and close the file, returning the
+    /// number of features written
+    pub async fn finish(mut self) -> AppResult<usize> {
+        if let Some([min_x, min_y, max_x, max_y]) = self.extent {
+            sqlx::query(
+                "UPDATE gpkg_contents SET min_x = ?1, min_y = ?2, max_x = ?3, max_y = ?4 \
+                 WHERE table_name = ?5",
+            )
+            .bind(min_x)
+            .bind(min_y)
+            .bind(max_x)
+            .bind(max_y)
+            .bind(&self.table)
+            .execute(&mut self.connection)
+            .await
+            .map_err(write_error)?;
+        }
+        sqlx::raw_sql("COMMIT")
+            .execute(&mut self.connection)
+            .await
+            .map_err(write_error)?;
+        self.connection.close().await.map_err(write_error)?;
+        Ok(self.count)
+    }
+}
+
+/// Call `f` with each position of a geometry
+fn for_each_position(geometry: &Geometry, f: &mut impl FnMut(&Position)) {
+    match geometry {
+        Geometry::Point(position) => f(position),
+        Geometry::MultiPoint(positions) | Geometry::LineString(positions) => {
+            positions.iter().for_each(f)
+        }
+        Geometry::MultiLineString(lines) | Geometry::Polygon(lines) => {
+            lines.iter().flatten().for_each(f)
+        }
+        Geometry::MultiPolygon(polygons) => polygons.iter().flatten().flatten().for_each(f),
+        Geometry::GeometryCollection(geometries) => geometries
+            .iter()
+            .for_each(|geometry| for_each_position(geometry, f)),
+    }
+}
+
+/// `[minx, miny, maxx, maxy]` of a geometry, or `None` when it is empty
+fn envelope(geometry: &Geometry) -> Option<[f64; 4]> {
+    let mut envelope: Option<[f64; 4]> = None;
+    for_each_position(geometry, &mut |position| {
+        let (x, y) = (position[0], position[1]);
+        envelope = Some(match envelope {
+            Some([minx, miny, maxx, maxy]) => [minx.min(x), miny.min(y), maxx.max(x), maxy.max(y)],
+            None => [x, y, x, y],
+        });
+    });
+    envelope
+}
+
+/// A geometry in the GeoPackage binary format
+fn geometry_blob(geometry: &Geometry) -> Vec<u8> {
+    let mut has_z = false;
+    for_each_position(geometry, &mut |position| has_z |= position.len() > 2);
+
+    let mut blob = b"GP\0".to_vec();
+    match envelope(geometry) {
+        // Little-endian, with an [minx, maxx, miny, maxy] envelope
+        Some([minx, miny, maxx, maxy]) => {
+            blob.push(0b0000_0011);
+            blob.extend_from_slice(&4326i32.to_le_bytes());
+            for value in [minx, maxx, miny, maxy] {
+                blob.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        // Little-endian, empty
+        None => {
+            blob.push(0b0001_0001);
+            blob.extend_from_slice(&4326i32.to_le_bytes());
+        }
+    }
+    write_wkb(&mut blob, geometry, has_z);
+    blob
+}
+
+fn write_wkb_header(wkb: &mut Vec<u8>, code: u32, has_z: bool) {
+    wkb.push(1);
+    let code = if has_z { code + 1000 } else { code };
+    wkb.extend_from_slice(&code.to_le_bytes());
+}
+
+fn write_count(wkb: &mut Vec<u8>, count: usize) {
+    wkb.extend_from_slice(&(count as u32).to_le_bytes());
+}
+
+/// Write a position; positions without a height get 0 in 3D geometries
+fn write_position(wkb: &mut Vec<u8>, position: &Position, has_z: bool) {
+    wkb.extend_from_slice(&position[0].to_le_bytes());
+    wkb.extend_from_slice(&position[1].to_le_bytes());
+    if has_z {
+        let z = position.get(2).copied().unwrap_or(0.0);
+        wkb.extend_from_slice(&z.to_le_bytes());
+    }
+}
+
+fn write_positions(wkb: &mut Vec<u8>, positions: &[Position], has_z: bool) {
+    write_count(wkb, positions.len());
+    for position in positions {
+        write_position(wkb, position, has_z);
+    }
+}
+
+fn write_rings(wkb: &mut Vec<u8>, rings: &[Vec<Position>], has_z: bool) {
+    write_count(wkb, rings.len());
+    for ring in rings {
+        write_positions(wkb, ring, has_z);
+    }
+}
+
+/// Write a geometry as little-endian ISO WKB
+fn write_wkb(wkb: &mut Vec<u8>, geometry: &Geometry, has_z: bool) {
+    match geometry {
+        Geometry::Point(position) => {
+            write_wkb_header(wkb, 1, has_z);
+            write_position(wkb, position, has_z);
+        }
+        Geometry::LineString(positions) => {
+            write_wkb_header(wkb, 2, has_z);
+            write_positions(wkb, positions, has_z);
+        }
+        Geometry::Polygon(rings) => {
+            write_wkb_header(wkb, 3, has_z);
+            write_rings(wkb, rings, has_z);
+        }
+        Geometry::MultiPoint(positions) => {
+            write_wkb_header(wkb, 4, has_z);
+            write_count(wkb, positions.len());
+            for position in positions {
+                write_wkb_header(wkb, 1, has_z);
+                write_position(wkb, position, has_z);
+            }
+        }
+        Geometry::MultiLineString(lines) => {
+            write_wkb_header(wkb, 5, has_z);
+            write_count(wkb, lines.len());
+            for line in lines {
+                write_wkb_header(wkb, 2, has_z);
+                write_positions(wkb, line, has_z);
+            }
+        }
+        Geometry::MultiPolygon(polygons) => {
+            write_wkb_header(wkb, 6, has_z);
+            write_count(wkb, polygons.len());
+            for rings in polygons {
+                write_wkb_header(wkb, 3, has_z);
+                write_rings(wkb, rings, has_z);
+            }
+        }
+        Geometry::GeometryCollection(geometries) => {
+            write_wkb_header(wkb, 7, has_z);
+            write_count(wkb, geometries.len());
+            for geometry in geometries {
+                write_wkb(wkb, geometry, has_z);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_geometry_blob() {
+        let blob = geometry_blob(&Geometry::Point(vec![11.5, 57.5]));
+        // Header, srs_id 4326 and the envelope
+        assert_eq!(&blob[..4], b"GP\x00\x03");
+        assert_eq!(i32::from_le_bytes(blob[4..8].try_into().unwrap()), 4326);
+        assert_eq!(f64::from_le_bytes(blob[8..16].try_into().unwrap()), 11.5);
+        assert_eq!(f64::from_le_bytes(blob[24..32].try_into().unwrap()), 57.5);
+        // WKB point
+        assert_eq!(&blob[40..45], &[1, 1, 0, 0, 0]);
+        assert_eq!(blob.len(), 40 + 21);
+
+        // A height anywhere makes the whole geometry 3D
+        let line = Geometry::LineString(vec![vec![0.0, 0.0], vec![1.0, 1.0, 5.0]]);
+        let blob = geometry_blob(&line);
+        assert_eq!(u32::from_le_bytes(blob[41..45].try_into().unwrap()), 1002);
+        assert_eq!(blob.len(), 40 + 9 + 2 * 24);
+
+        let empty = geometry_blob(&Geometry::MultiPoint(vec![]));
+        assert_eq!(&empty[..4], b"GP\x00\x11");
+        assert_eq!(empty.len(), 8 + 9);
+    }
+
+    #[test]
+    fn test_column_names() {
+        let properties = [
+            ("name".to_string(), PropertyType::String),
+            ("Name".to_string(), PropertyType::String),
+            ("id".to_string(), PropertyType::Integer),
+        ];
+        assert_eq!(column_names(&properties), ["name", "Name_1", "id_1"]);
+    }
+
+    #[tokio::test]
+    async fn test_write_geopackage() {
+        let path = std::env::temp_dir().join(format!("{}.gpkg", uuid::Uuid::new_v4()));
+        let properties = [
+            ("name".to_string(), PropertyType::String),
+            ("lanes".to_string(), PropertyType::Integer),
+        ];
+        let mut writer = GeoPackageWriter::create(&path, "roads", "Roads", &properties)
+            .await
+            .unwrap();
+        for (id, x) in [("a", 11.0), ("b", 12.0)] {
+            let feature = Feature {
+                feature_type: "Feature".to_string(),
+                id: id.to_string(),
+                geometry: json!({"type": "Point", "coordinates": [x, 57.0]}),
+                properties: json!({"name": "E6", "lanes": 4}),
+                links: None,
+                bbox: None,
+                assets: None,
+                collection: None,
+                stac_version: None,
+                stac_extensions: None,
+            };
+            writer.write(&feature).await.unwrap();
+        }
+        assert_eq!(writer.finish().await.unwrap(), 2);
+
+        let mut connection = SqliteConnection::connect_with(
+            &SqliteConnectOptions::new().filename(&path).read_only(true),
+        )
+        .await
+        .unwrap();
+        let application_id: i32 = sqlx::query_scalar("PRAGMA application_id")
+            .fetch_one(&mut connection)
+            .await
+            .unwrap();
+        assert_eq!(application_id, APPLICATION_ID);
+        let extent: (f64, f64) =
+            sqlx::query_as("SELECT min_x, max_x FROM gpkg_contents WHERE table_name = 'roads'")
+                .fetch_one(&mut connection)
+                .await
+                .unwrap();
+        assert_eq!(extent, (11.0, 12.0));
+        let lanes: Vec<i64> = sqlx::query_scalar("SELECT lanes FROM roads ORDER BY fid")
+            .fetch_all(&mut connection)
+            .await
+            .unwrap();
+        assert_eq!(lanes, [4, 4]);
+        connection.close().await.unwrap();
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,216 @@
+//! KML writer
+//!
+//! Each feature becomes a placemark named after its `name` property, or its
+//! id if it has none, with the properties as extended data. Multi-geometries
+//! and geometry collections become `MultiGeometry` elements.
+
+use serde_json::Value;
+use std::fmt::Write;
+
+use super::export::{Geometry, Position};
+use crate::api::collections::metadata::xml_escape;
+use crate::api::features::Feature;
+use crate::error::AppResult;
+
+fn write_coordinates(kml: &mut String, positions: &[Position]) {
+    kml.push_str("<coordinates>");
+    for (index, position) in positions.iter().enumerate() {
+        if index > 0 {
+            kml.push(' ');
+        }
+        let values: Vec<String> = position.iter().take(3).map(f64::to_string).collect();
+        kml.push_str(&values.join(","));
+    }
+    kml.push_str("</coordinates>");
+}
+
+fn write_polygon(kml: &mut String, rings: &[Vec<Position>]) {
+    kml.push_str("<Polygon>");
+    for (index, ring) in rings.iter().enumerate() {
+        let boundary = if index == 0 {
+            "outerBoundaryIs"
+        } else {
+            "innerBoundaryIs"
+        };
+        let _ = write!(kml, "<{}><LinearRing>", boundary);
+        write_coordinates(kml, ring);
+        let _ = write!(kml, "</LinearRing></{}>", boundary);
+    }
+    kml.push_str("</Polygon>");
+}
+
+fn write_geometry(kml: &mut String, geometry: &Geometry) {
+    match geometry {
+        Geometry::Point(position) => {
+            kml.push_str("<Point>");
+            write_coordinates(kml, std::slice::from_ref(position));
+            kml.push_str("</Point>");
+        }
+        Geometry::LineString(line) => {
+            kml.push_str("<LineString>");
+            write_coordinates(kml, line);
+            kml.push_str("</LineString>");
+        }
+        Geometry::Polygon(rings) => write_polygon(kml, rings),
+        Geometry::MultiPoint(positions) => {
+            kml.push_str("<MultiGeometry>");
+            for position in positions {
+                write_geometry(kml, &Geometry::Point(position.clone()));
+            }
+            kml.push_str("</MultiGeometry>");
+        }
+        Geometry::MultiLineString(lines) => {
+            kml.push_str("<MultiGeometry>");
+            for line in lines {
+                kml.push_str("<LineString>");
+                write_coordinates(kml, line);
+                kml.push_str("</LineString>");
+            }
+            kml.push_str("</MultiGeometry>");
+        }
+        Geometry::MultiPolygon(polygons) => {
+            kml.push_str("<MultiGeometry>");
+            for rings in polygons {
+                write_polygon(kml, rings);
+            }
+            kml.push_str("</MultiGeometry>");
+        }
+        Geometry::GeometryCollection(geometries) => {
+            kml.push_str("<MultiGeometry>");
+            for geometry in geometries {
+                write_geometry(kml, geometry);
+            }
+            kml.push_str("</MultiGeometry>");
+        }
+    }
+}
+
+/// Text of a property value in extended data
+fn data_value(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// End of a KML document
+pub const KML_END: &str = "</Document></kml>";
+
+/// Start of a KML document titled `title`, to be followed by placemarks and
+/// [`KML_END`]
+pub fn kml_start(title: &str) -> String {
+    let mut kml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <kml xmlns=\"http://www.opengis.net/kml/2.2\"><Document>",
+    );
+    let _ = write!(kml, "<name>{}</name>", xml_escape(title));
+    kml
+}
+
+/// Append a feature as a placemark
+pub fn write_placemark(kml: &mut String, feature: &Feature) -> AppResult<()> {
+    let geometry = Geometry::from_geojson(&feature.geometry)?;
+    let name = feature
+        .properties
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or(&feature.id);
+
+    kml.push_str("<Placemark>");
+    let _ = write!(kml, "<name>{}</name>", xml_escape(name));
+    kml.push_str("<ExtendedData>");
+    let _ = write!(
+        kml,
+        "<Data name=\"id\"><value>{}</value></Data>",
+        xml_escape(&feature.id)
+    );
+    if let Some(properties) = feature.properties.as_object() {
+        for (key, value) in properties.iter().filter(|(_, value)| !value.is_null()) {
+            let _ = write!(
+                kml,
+                "<Data name=\"{}\"><value>{}</value></Data>",
+                xml_escape(key),
+                xml_escape(&data_value(value))
+            );
+        }
+    }
+    kml.push_str("</ExtendedData>");
+    if let Some(geometry) = &geometry {
+        write_geometry(kml, geometry);
+    }
+    kml.push_str("</Placemark>");
+    Ok(())
+}
+
+/// Write the features as a KML document titled `title`
+pub fn write_kml(title: &str, features: &[Feature]) -> AppResult<String> {
+    let mut kml = kml_start(title);
+    for feature in features {
+        write_placemark(&mut kml, feature)?;
+    }
+    kml.push_str(KML_END);
+    Ok(kml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn feature(id: &str, geometry: Value, properties: Value) -> Feature {
+        Feature {
+            feature_type: "Feature".to_string(),
+            id: id.to_string(),
+            geometry,
+            properties,
+            links: None,
+            bbox: None,
+            assets: None,
+            collection: None,
+            stac_version: None,
+            stac_extensions: None,
+        }
+    }
+
+    #[test]
+    fn test_write_kml() {
+        let features = vec![
+            feature(
+                "a",
+                json!({"type": "Point", "coordinates": [11.5, 57.7, 12]}),
+                json!({"name": "Göteborg <GBG>", "population": 600000, "note": null}),
+            ),
+            feature(
+                "b",
+                json!({"type": "Polygon", "coordinates": [
+                    [[0, 0], [4, 0], [4, 4], [0, 0]],
+                    [[1, 1], [2, 1], [2, 2], [1, 1]]
+                ]}),
+                json!({}),
+            ),
+            feature(
+                "c",
+                json!({"type": "MultiLineString", "coordinates": [[[0, 0], [1, 1]], [[2, 2], [3, 3]]]}),
+                json!({}),
+            ),
+            feature("d", Value::Null, json!({})),
+        ];
+        let kml = write_kml("Cities & towns", &features).unwrap();
+
+        assert!(kml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?><kml"));
+        assert!(kml.contains("<Document><name>Cities &amp; towns</name>"));
+        assert!(kml.contains("<name>Göteborg &lt;GBG&gt;</name>"));
+        assert!(kml.contains("<Data name=\"population\"><value>600000</value></Data>"));
+        assert!(!kml.contains("\"note\""));
+        assert!(kml.contains("<Point><coordinates>11.5,57.7,12</coordinates></Point>"));
+        assert!(kml.contains(
+            "<outerBoundaryIs><LinearRing><coordinates>0,0 4,0 4,4 0,0</coordinates></LinearRing></outerBoundaryIs>\
+             <innerBoundaryIs><LinearRing><coordinates>1,1 2,1 2,2 1,1</coordinates>"
+        ));
+        assert!(kml.contains(
+            "<MultiGeometry><LineString><coordinates>0,0 1,1</coordinates></LineString><LineString>"
+        ));
+        assert!(kml.contains("<Placemark><name>d</name><ExtendedData><Data name=\"id\"><value>d</value></Data></ExtendedData></Placemark>"));
+        assert!(kml.ends_with("</Document></kml>"));
+    }
+}
@@ -1,5 +1,13 @@
+pub mod archive;
+pub mod asset_check;
+pub mod backup;
 pub mod cog;
+pub mod composite;
 pub mod copc;
+pub mod export;
+pub mod geopackage;
+pub mod kml;
+pub mod shapefile;
 pub mod worker;
 
 pub use worker::JobWorker;
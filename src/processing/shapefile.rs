@@ -0,0 +1,913 @@
+//! ESRI Shapefile writer
+//!
+//! A Shapefile holds a single shape type, so features are split into one
+//! layer per type (points, multipoints, lines and polygons). Each layer is
+//! written as `.shp`, `.shx`, `.dbf`, `.prj` and `.cpg` files. Geometries are
+//! expected in WGS 84; layers with heights use the Z shape types, where
+//! positions without a height get 0.
+//!
+//! dBase limits attribute names to 10 ASCII characters, so longer property
+//! names are truncated and made unique, e.g. `population_2020` and
+//! `population_2021` become `population` and `populati_1`.
+//!
+//! The attribute fields and layers must be known before the first record is
+//! written, so features are passed twice: once to a [`ShapefileSchema`], then
+//! to [`write_shapefiles`], which writes the records straight to disk.
+
+use indexmap::IndexMap;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::export::{Geometry, Position, TextEncoding};
+use crate::api::features::Feature;
+use crate::error::{AppError, AppResult};
+
+/// Projection file contents for WGS 84
+const WGS84_PRJ: &str = "GEOGCS[\"GCS_WGS_1984\",DATUM[\"D_WGS_1984\",SPHEROID[\"WGS_1984\",6378137.0,298.257223563]],PRIMEM[\"Greenwich\",0.0],UNIT[\"Degree\",0.0174532925199433]]";
+
+/// Maximum length of a dBase field name
+const MAX_FIELD_NAME_LEN: usize = 10;
+
+/// Maximum width of a dBase character field
+const MAX_CHARACTER_WIDTH: usize = 254;
+
+/// Maximum number of fields in a dBase table
+const MAX_FIELDS: usize = 255;
+
+/// Width and precision of fields holding non-integral numbers
+const REAL_WIDTH: usize = 24;
+const REAL_DECIMALS: usize = 15;
+
+/// Widest integer field; larger integers no longer fit an `i64`
+const MAX_INTEGER_WIDTH: usize = 19;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum ShapeType {
+    Null = 0,
+    Point = 1,
+    PolyLine = 3,
+    Polygon = 5,
+    MultiPoint = 8,
+}
+
+impl ShapeType {
+    fn of(geometry: &Geometry) -> Option<Self> {
+        match geometry {
+            Geometry::Point(_) => Some(Self::Point),
+            Geometry::MultiPoint(_) => Some(Self::MultiPoint),
+            Geometry::LineString(_) | Geometry::MultiLineString(_) => Some(Self::PolyLine),
+            Geometry::Polygon(_) | Geometry::MultiPolygon(_) => Some(Self::Polygon),
+            Geometry::GeometryCollection(_) => None,
+        }
+    }
+
+    /// Type code in the files; the Z variant of a type is 10 higher
+    fn code(self, has_z: bool) -> i32 {
+        if has_z && self != Self::Null {
+            self as i32 + 10
+        } else {
+            self as i32
+        }
+    }
+
+    /// Suffix of the layer file names when features are split by type
+    fn suffix(self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Point => "point",
+            Self::PolyLine => "line",
+            Self::Polygon => "polygon",
+            Self::MultiPoint => "multipoint",
+        }
+    }
+}
+
+/// A geometry in Shapefile terms: parts of points with a height
+#[derive(Debug)]
+struct Shape {
+    parts: Vec<Vec<[f64; 3]>>,
+}
+
+impl Shape {
+    fn points(&self) -> impl Iterator<Item = &[f64; 3]> {
+        self.parts.iter().flatten()
+    }
+
+    fn point_count(&self) -> usize {
+        self.parts.iter().map(Vec::len).sum()
+    }
+
+    /// Bounds as minx, miny, maxx, maxy, minz, maxz
+    fn bbox(&self) -> Option<[f64; 6]> {
+        self.points().fold(None, |bbox, &[x, y, z]| {
+            Some(match bbox {
+                None => [x, y, x, y, z, z],
+                Some(b) => [
+                    b[0].min(x),
+                    b[1].min(y),
+                    b[2].max(x),
+                    b[3].max(y),
+                    b[4].min(z),
+                    b[5].max(z),
+                ],
+            })
+        })
+    }
+}
+
+fn xyz(position: &Position) -> [f64; 3] {
+    [
+        position[0],
+        position[1],
+        position.get(2).copied().unwrap_or(0.0),
+    ]
+}
+
+/// Twice the signed area of a ring; positive when counter-clockwise
+fn signed_area(ring: &[[f64; 3]]) -> f64 {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| a[0] * b[1] - b[0] * a[1])
+        .sum()
+}
+
+/// Rings of a polygon, outer ring clockwise and holes counter-clockwise
+fn oriented_rings(rings: &[Vec<Position>]) -> impl Iterator<Item = Vec<[f64; 3]>> + '_ {
+    rings.iter().enumerate().map(|(index, ring)| {
+        let mut ring: Vec<[f64; 3]> = ring.iter().map(xyz).collect();
+        let clockwise = signed_area(&ring) < 0.0;
+        if clockwise != (index == 0) {
+            ring.reverse();
+        }
+        ring
+    })
+}
+
+/// Whether any position of a geometry has a height
+fn has_height(geometry: &Geometry) -> bool {
+    let any = |positions: &[Position]| positions.iter().any(|p| p.len() > 2);
+    match geometry {
+        Geometry::Point(position) => position.len() > 2,
+        Geometry::MultiPoint(positions) | Geometry::LineString(positions) => any(positions),
+        Geometry::MultiLineString(lines) | Geometry::Polygon(lines) => {
+            lines.iter().any(|line| any(line))
+        }
+        Geometry::MultiPolygon(polygons) => polygons.iter().flatten().any(|ring| any(ring)),
+        Geometry::GeometryCollection(geometries) => geometries.iter().any(has_height),
+    }
+}
+
+impl Shape {
+    fn from_geometry(geometry: &Geometry) -> Self {
+        let parts = match geometry {
+            Geometry::Point(position) => vec![vec![xyz(position)]],
+            Geometry::MultiPoint(positions) => vec![positions.iter().map(xyz).collect()],
+            Geometry::LineString(line) => vec![line.iter().map(xyz).collect()],
+            Geometry::MultiLineString(lines) => lines
+                .iter()
+                .map(|line| line.iter().map(xyz).collect())
+                .collect(),
+            Geometry::Polygon(rings) => oriented_rings(rings).collect(),
+            Geometry::MultiPolygon(polygons) => polygons
+                .iter()
+                .flat_map(|rings| oriented_rings(rings))
+                .collect(),
+            Geometry::GeometryCollection(_) => Vec::new(),
+        };
+        Self {
+            parts: parts.into_iter().filter(|part| !part.is_empty()).collect(),
+        }
+    }
+}
+
+/// dBase field types used for feature properties
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FieldType {
+    Character,
+    Integer,
+    Real,
+    Logical,
+}
+
+#[derive(Debug)]
+struct Field {
+    /// Property the field holds; `None` for the feature id
+    property: Option<String>,
+    name: String,
+    field_type: FieldType,
+    width: usize,
+}
+
+/// Truncate a property name to a unique dBase field name
+fn field_name(property: &str, taken: &[String]) -> String {
+    let mut base: String = property
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(MAX_FIELD_NAME_LEN)
+        .collect();
+    if base.is_empty() {
+        base = "field".to_string();
+    }
+    let is_taken = |name: &str| taken.iter().any(|t| t.eq_ignore_ascii_case(name));
+    if !is_taken(&base) {
+        return base;
+    }
+    (1..)
+        .map(|n| {
+            let suffix = format!("_{}", n);
+            let keep = base.len().min(MAX_FIELD_NAME_LEN - suffix.len());
+            format!("{}{}", &base[..keep], suffix)
+        })
+        .find(|name| !is_taken(name))
+        .expect("unbounded suffixes")
+}
+
+/// Text stored for a property in a character field
+fn property_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn format_real(value: f64) -> String {
+    let text = format!("{:.*}", REAL_DECIMALS, value);
+    if text.len() <= REAL_WIDTH {
+        text
+    } else {
+        format!("{:e}", value)
+    }
+}
+
+/// What the non-null values of a property have in common
+#[derive(Debug)]
+struct PropertyValues {
+    count: usize,
+    all_boolean: bool,
+    all_integer: bool,
+    all_number: bool,
+    integer_width: usize,
+    text_width: usize,
+}
+
+impl Default for PropertyValues {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            all_boolean: true,
+            all_integer: true,
+            all_number: true,
+            integer_width: 1,
+            text_width: 1,
+        }
+    }
+}
+
+/// Layers and attribute fields of a Shapefile export, gathered from the
+/// features before any is written
+#[derive(Debug)]
+pub struct ShapefileSchema {
+    encoding: TextEncoding,
+    id_width: usize,
+    properties: IndexMap<String, PropertyValues>,
+    /// Shape types present, and whether any of their geometries has heights
+    shape_types: IndexMap<ShapeType, bool>,
+}
+
+impl ShapefileSchema {
+    pub fn new(encoding: TextEncoding) -> Self {
+        Self {
+            encoding,
+            id_width: 1,
+            properties: IndexMap::new(),
+            shape_types: IndexMap::new(),
+        }
+    }
+
+    /// Take a feature into account
+    pub fn add(&mut self, feature: &Feature) -> AppResult<()> {
+        if let Some(geometry) = Geometry::from_geojson(&feature.geometry)?
+            && let Some(shape_type) = ShapeType::of(&geometry)
+        {
+            *self.shape_types.entry(shape_type).or_default() |= has_height(&geometry);
+        }
+
+        self.id_width = self.id_width.max(feature.id.len()).min(MAX_CHARACTER_WIDTH);
+        let Some(object) = feature.properties.as_object() else {
+            return Ok(());
+        };
+        for (key, value) in object {
+            if !self.properties.contains_key(key) && self.properties.len() + 1 >= MAX_FIELDS {
+                return Err(AppError::BadRequest(format!(
+                    "Shapefiles support at most {} attributes",
+                    MAX_FIELDS - 1
+                )));
+            }
+            let values = self.properties.entry(key.clone()).or_default();
+            if value.is_null() {
+                continue;
+            }
+            values.count += 1;
+            values.all_boolean &= value.is_boolean();
+            values.all_integer &= value.is_i64() || value.is_u64();
+            values.all_number &= value.is_number();
+            if values.all_integer {
+                values.integer_width = values.integer_width.max(value.to_string().len());
+            }
+            let text = self
+                .encoding
+                .encode(&property_text(value), MAX_CHARACTER_WIDTH);
+            values.text_width = values.text_width.max(text.len());
+        }
+        Ok(())
+    }
+
+    /// Layers to write, in file order. Features without a shape of their
+    /// own go to the first.
+    fn layers(&self) -> Vec<(ShapeType, bool)> {
+        let mut layers: Vec<(ShapeType, bool)> =
+            self.shape_types.iter().map(|(&t, &z)| (t, z)).collect();
+        layers.sort();
+        if layers.is_empty() {
+            layers.push((ShapeType::Null, false));
+        }
+        layers
+    }
+
+    /// A field for the id and every property, in order of first appearance
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = vec![Field {
+            property: None,
+            name: "id".to_string(),
+            field_type: FieldType::Character,
+            width: self.id_width,
+        }];
+        for (property, values) in &self.properties {
+            let (field_type, width) = if values.count == 0 {
+                (FieldType::Character, 1)
+            } else if values.all_boolean {
+                (FieldType::Logical, 1)
+            } else if values.all_integer && values.integer_width <= MAX_INTEGER_WIDTH {
+                (FieldType::Integer, values.integer_width)
+            } else if values.all_number {
+                (FieldType::Real, REAL_WIDTH)
+            } else {
+                (FieldType::Character, values.text_width)
+            };
+
+            let taken: Vec<String> = fields.iter().map(|f| f.name.clone()).collect();
+            fields.push(Field {
+                property: Some(property.clone()),
+                name: field_name(property, &taken),
+                field_type,
+                width,
+            });
+        }
+        fields
+    }
+}
+
+/// Value of a field for one feature, padded to the field width
+fn field_value(field: &Field, feature: &Feature, encoding: TextEncoding) -> Vec<u8> {
+    let value = match &field.property {
+        None => Some(Value::String(feature.id.clone())),
+        Some(property) => feature
+            .properties
+            .get(property)
+            .filter(|v| !v.is_null())
+            .cloned(),
+    };
+    let mut bytes = match (&value, field.field_type) {
+        (None, FieldType::Logical) => b"?".to_vec(),
+        (None, _) => Vec::new(),
+        (Some(value), FieldType::Logical) => {
+            if value.as_bool() == Some(true) {
+                b"T".to_vec()
+            } else {
+                b"F".to_vec()
+            }
+        }
+        (Some(value), FieldType::Integer) => value.to_string().into_bytes(),
+        (Some(value), FieldType::Real) => {
+            format_real(value.as_f64().unwrap_or_default()).into_bytes()
+        }
+        (Some(value), FieldType::Character) => encoding.encode(&property_text(value), field.width),
+    };
+    bytes.truncate(field.width);
+
+    let padding = vec![b' '; field.width - bytes.len()];
+    if matches!(field.field_type, FieldType::Integer | FieldType::Real) {
+        [padding, bytes].concat()
+    } else {
+        [bytes, padding].concat()
+    }
+}
+
+/// Header of the dBase table; the record count is filled in when done
+fn dbf_header(fields: &[Field], record_count: u32) -> AppResult<Vec<u8>> {
+    let record_len = 1 + fields.iter().map(|f| f.width).sum::<usize>();
+    let record_len = u16::try_from(record_len).map_err(|_| {
+        AppError::BadRequest("Feature attributes are too wide for a Shapefile".to_string())
+    })?;
+    let header_len = (32 + 32 * fields.len() + 1) as u16;
+
+    let today = chrono::Utc::now().date_naive();
+    let mut dbf = Vec::with_capacity(header_len as usize);
+    dbf.push(0x03); // dBase III without memo
+    dbf.push((chrono::Datelike::year(&today) - 1900) as u8);
+    dbf.push(chrono::Datelike::month(&today) as u8);
+    dbf.push(chrono::Datelike::day(&today) as u8);
+    dbf.extend_from_slice(&record_count.to_le_bytes());
+    dbf.extend_from_slice(&header_len.to_le_bytes());
+    dbf.extend_from_slice(&record_len.to_le_bytes());
+    dbf.extend_from_slice(&[0; 20]); // reserved; the code page is in the .cpg file
+
+    for field in fields {
+        let mut name = [0u8; 11];
+        name[..field.name.len()].copy_from_slice(field.name.as_bytes());
+        dbf.extend_from_slice(&name);
+        dbf.push(match field.field_type {
+            FieldType::Character => b'C',
+            FieldType::Integer | FieldType::Real => b'N',
+            FieldType::Logical => b'L',
+        });
+        dbf.extend_from_slice(&[0; 4]);
+        dbf.push(field.width as u8);
+        dbf.push(match field.field_type {
+            FieldType::Real => REAL_DECIMALS as u8,
+            _ => 0,
+        });
+        dbf.extend_from_slice(&[0; 14]);
+    }
+    dbf.push(0x0D);
+    Ok(dbf)
+}
+
+/// Content of a shape record, without the record header
+fn shape_content(shape_type: ShapeType, has_z: bool, shape: &Shape) -> Vec<u8> {
+    let mut content = Vec::new();
+    let Some(bbox) = shape.bbox() else {
+        content.extend_from_slice(&(ShapeType::Null as i32).to_le_bytes());
+        return content;
+    };
+    content.extend_from_slice(&shape_type.code(has_z).to_le_bytes());
+    if shape_type == ShapeType::Point {
+        let [x, y, z] = shape.parts[0][0];
+        let values: &[f64] = if has_z { &[x, y, z, 0.0] } else { &[x, y] };
+        for value in values {
+            content.extend_from_slice(&value.to_le_bytes());
+        }
+        return content;
+    }
+
+    for value in &bbox[..4] {
+        content.extend_from_slice(&value.to_le_bytes());
+    }
+    if shape_type != ShapeType::MultiPoint {
+        content.extend_from_slice(&(shape.parts.len() as i32).to_le_bytes());
+    }
+    content.extend_from_slice(&(shape.point_count() as i32).to_le_bytes());
+    if shape_type != ShapeType::MultiPoint {
+        let mut start = 0;
+        for part in &shape.parts {
+            content.extend_from_slice(&(start as i32).to_le_bytes());
+            start += part.len();
+        }
+    }
+    for [x, y, _] in shape.points() {
+        content.extend_from_slice(&x.to_le_bytes());
+        content.extend_from_slice(&y.to_le_bytes());
+    }
+    if has_z {
+        for value in &bbox[4..] {
+            content.extend_from_slice(&value.to_le_bytes());
+        }
+        for [_, _, z] in shape.points() {
+            content.extend_from_slice(&z.to_le_bytes());
+        }
+    }
+    content
+}
+
+/// The 100 byte header shared by `.shp` and `.shx` files
+fn file_header(shape_type: ShapeType, has_z: bool, words: i32, bbox: [f64; 6]) -> Vec<u8> {
+    let mut header = Vec::with_capacity(100);
+    header.extend_from_slice(&9994i32.to_be_bytes());
+    header.extend_from_slice(&[0; 20]);
+    header.extend_from_slice(&words.to_be_bytes());
+    header.extend_from_slice(&1000i32.to_le_bytes());
+    header.extend_from_slice(&shape_type.code(has_z).to_le_bytes());
+    for value in bbox {
+        header.extend_from_slice(&value.to_le_bytes());
+    }
+    header.extend_from_slice(&[0; 16]); // M range
+    header
+}
+
+/// The files of one layer, written a record at a time
+struct LayerWriter {
+    shape_type: ShapeType,
+    has_z: bool,
+    shp: BufWriter<File>,
+    shx: BufWriter<File>,
+    dbf: BufWriter<File>,
+    /// Length of the `.shp` file in 16-bit words
+    shp_words: i32,
+    records: i32,
+    bbox: Option<[f64; 6]>,
+}
+
+impl LayerWriter {
+    fn create(
+        base: &Path,
+        shape_type: ShapeType,
+        has_z: bool,
+        fields: &[Field],
+    ) -> AppResult<Self> {
+        let create = |extension: &str| -> AppResult<BufWriter<File>> {
+            Ok(BufWriter::new(File::create(
+                base.with_extension(extension),
+            )?))
+        };
+        let (mut shp, mut shx, mut dbf) = (create("shp")?, create("shx")?, create("dbf")?);
+        // Headers are rewritten with the real lengths and bounds when done
+        shp.write_all(&[0; 100])?;
+        shx.write_all(&[0; 100])?;
+        dbf.write_all(&dbf_header(fields, 0)?)?;
+        Ok(Self {
+            shape_type,
+            has_z,
+            shp,
+            shx,
+            dbf,
+            shp_words: 50,
+            records: 0,
+            bbox: None,
+        })
+    }
+
+    fn write(
+        &mut self,
+        shape: &Shape,
+        feature: &Feature,
+        fields: &[Field],
+        encoding: TextEncoding,
+    ) -> AppResult<()> {
+        let content = shape_content(self.shape_type, self.has_z, shape);
+        let content_words = (content.len() / 2) as i32;
+        let next_words = self
+            .shp_words
+            .checked_add(4 + content_words)
+            .ok_or_else(|| AppError::BadRequest("Shapefile would exceed 4 GiB".to_string()))?;
+
+        self.records += 1;
+        self.shx.write_all(&self.shp_words.to_be_bytes())?;
+        self.shx.write_all(&content_words.to_be_bytes())?;
+        self.shp.write_all(&self.records.to_be_bytes())?;
+        self.shp.write_all(&content_words.to_be_bytes())?;
+        self.shp.write_all(&content)?;
+        self.shp_words = next_words;
+
+        if let Some(b) = shape.bbox() {
+            self.bbox = Some(match self.bbox {
+                None => b,
+                Some(a) => [
+                    a[0].min(b[0]),
+                    a[1].min(b[1]),
+                    a[2].max(b[2]),
+                    a[3].max(b[3]),
+                    a[4].min(b[4]),
+                    a[5].max(b[5]),
+                ],
+            });
+        }
+
+        self.dbf.write_all(b" ")?; // not deleted
+        for field in fields {
+            self.dbf.write_all(&field_value(field, feature, encoding))?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self, fields: &[Field]) -> AppResult<()> {
+        let bbox = self.bbox.unwrap_or_default();
+        let shx_words = 50 + 4 * self.records;
+        for (file, words) in [(&mut self.shp, self.shp_words), (&mut self.shx, shx_words)] {
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&file_header(self.shape_type, self.has_z, words, bbox))?;
+            file.flush()?;
+        }
+        self.dbf.write_all(&[0x1A])?;
+        self.dbf.seek(SeekFrom::Start(0))?;
+        self.dbf
+            .write_all(&dbf_header(fields, self.records as u32)?)?;
+        self.dbf.flush()?;
+        Ok(())
+    }
+}
+
+/// Write the features as Shapefile layers named after `stem` into `dir`
+///
+/// `schema` must have seen every feature. Returns the names and paths of
+/// the files to be zipped together. When features have several shape
+/// types, each layer's files get a suffix such as `_point`. Features
+/// without a geometry are kept as null shapes in the first layer, and
+/// geometry collections, which Shapefiles cannot hold, become null shapes
+/// too.
+pub fn write_shapefiles(
+    stem: &str,
+    schema: &ShapefileSchema,
+    features: impl Iterator<Item = AppResult<Feature>>,
+    dir: &Path,
+) -> AppResult<Vec<(String, PathBuf)>> {
+    let fields = schema.fields();
+    let layers = schema.layers();
+    let split = layers.len() > 1;
+    let names: Vec<String> = layers
+        .iter()
+        .map(|(shape_type, _)| {
+            if split {
+                format!("{}_{}", stem, shape_type.suffix())
+            } else {
+                stem.to_string()
+            }
+        })
+        .collect();
+
+    let mut writers = layers
+        .iter()
+        .zip(&names)
+        .map(|(&(shape_type, has_z), name)| {
+            LayerWriter::create(&dir.join(name), shape_type, has_z, &fields)
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+    for feature in features {
+        let feature = feature?;
+        let geometry = Geometry::from_geojson(&feature.geometry)?;
+        let shape_type = geometry.as_ref().and_then(ShapeType::of);
+        let (writer, shape) = match writers
+            .iter_mut()
+            .find(|writer| Some(writer.shape_type) == shape_type)
+        {
+            Some(writer) => {
+                let shape = Shape::from_geometry(geometry.as_ref().expect("has a shape type"));
+                (writer, shape)
+            }
+            None => (&mut writers[0], Shape { parts: Vec::new() }),
+        };
+        writer.write(&shape, &feature, &fields, schema.encoding)?;
+    }
+
+    let mut files = Vec::new();
+    for (writer, name) in writers.into_iter().zip(&names) {
+        writer.finish(&fields)?;
+        let base = dir.join(name);
+        std::fs::write(base.with_extension("prj"), WGS84_PRJ)?;
+        std::fs::write(base.with_extension("cpg"), schema.encoding.code_page())?;
+        for extension in ["shp", "shx", "dbf", "prj", "cpg"] {
+            files.push((
+                format!("{}.{}", name, extension),
+                base.with_extension(extension),
+            ));
+        }
+    }
+    Ok(files)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn feature(id: &str, geometry: Value, properties: Value) -> Feature {
+        Feature {
+            feature_type: "Feature".to_string(),
+            id: id.to_string(),
+            geometry,
+            properties,
+            links: None,
+            bbox: None,
+            assets: None,
+            collection: None,
+            stac_version: None,
+            stac_extensions: None,
+        }
+    }
+
+    /// Write the features, returning the names and contents of the files
+    fn write(stem: &str, features: &[Feature], encoding: TextEncoding) -> Vec<(String, Vec<u8>)> {
+        let mut schema = ShapefileSchema::new(encoding);
+        for feature in features {
+            schema.add(feature).unwrap();
+        }
+        let dir = std::env::temp_dir().join(format!("shapefile-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let files = write_shapefiles(stem, &schema, features.iter().cloned().map(Ok), &dir)
+            .unwrap()
+            .into_iter()
+            .map(|(name, path)| (name, std::fs::read(path).unwrap()))
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+        files
+    }
+
+    fn i32_be(data: &[u8], offset: usize) -> i32 {
+        i32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn i32_le(data: &[u8], offset: usize) -> i32 {
+        i32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn f64_le(data: &[u8], offset: usize) -> f64 {
+        f64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_field_names() {
+        let taken = vec!["id".to_string(), "population".to_string()];
+        assert_eq!(field_name("name", &taken), "name");
+        assert_eq!(field_name("population_2021", &taken), "populati_1");
+        assert_eq!(field_name("ID", &taken), "ID_1");
+        assert_eq!(field_name("höjd över hav", &taken), "h_jd__ver_");
+        assert_eq!(field_name("", &taken), "field");
+    }
+
+    #[test]
+    fn test_ring_orientation() {
+        let counter_clockwise = vec![
+            vec![0.0, 0.0],
+            vec![1.0, 0.0, 5.0],
+            vec![1.0, 1.0],
+            vec![0.0, 0.0],
+        ];
+        let rings: Vec<_> =
+            oriented_rings(&[counter_clockwise.clone(), counter_clockwise]).collect();
+        assert!(signed_area(&rings[0]) < 0.0);
+        assert!(signed_area(&rings[1]) > 0.0);
+        assert_eq!(rings[1][1], [1.0, 0.0, 5.0]);
+    }
+
+    #[test]
+    fn test_write_points() {
+        let features = vec![
+            feature(
+                "a",
+                json!({"type": "Point", "coordinates": [11.5, 57.7]}),
+                json!({"name": "Göteborg", "population_total": 600000, "area": 447.8, "coastal": true}),
+            ),
+            feature(
+                "b",
+                Value::Null,
+                json!({"name": null, "population_total": 12}),
+            ),
+        ];
+        let files = write("cities", &features, TextEncoding::Latin1);
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "cities.shp",
+                "cities.shx",
+                "cities.dbf",
+                "cities.prj",
+                "cities.cpg"
+            ]
+        );
+
+        let shp = &files[0].1;
+        assert_eq!(i32_be(shp, 0), 9994);
+        assert_eq!(i32_be(shp, 24) as usize * 2, shp.len());
+        assert_eq!(i32_le(shp, 32), ShapeType::Point as i32);
+        assert_eq!(f64_le(shp, 36), 11.5);
+        // First record: header, type and coordinates
+        assert_eq!(i32_be(shp, 100), 1);
+        assert_eq!(i32_be(shp, 104), 10);
+        assert_eq!(f64_le(shp, 112), 11.5);
+        assert_eq!(f64_le(shp, 120), 57.7);
+        // Second record is a null shape
+        assert_eq!(i32_be(shp, 128), 2);
+        assert_eq!(i32_le(shp, 136), ShapeType::Null as i32);
+
+        let shx = &files[1].1;
+        assert_eq!(shx.len(), 100 + 2 * 8);
+        assert_eq!(i32_be(shx, 100), 50);
+        assert_eq!(i32_be(shx, 108), 64);
+
+        let dbf = &files[2].1;
+        assert_eq!(u32::from_le_bytes(dbf[4..8].try_into().unwrap()), 2);
+        let field_names: Vec<String> = dbf[32..]
+            .chunks(32)
+            .take_while(|chunk| chunk[0] != 0x0D)
+            .map(|chunk| {
+                String::from_utf8_lossy(&chunk[..11])
+                    .trim_end_matches('\0')
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(field_names, ["id", "name", "population", "area", "coastal"]);
+        let header_len = u16::from_le_bytes([dbf[8], dbf[9]]) as usize;
+        let record_len = u16::from_le_bytes([dbf[10], dbf[11]]) as usize;
+        let record = &dbf[header_len..header_len + record_len];
+        assert_eq!(&record[..2], b" a");
+        assert!(record.windows(8).any(|w| w == b"G\xf6teborg"));
+        assert!(record.ends_with(b"T"));
+        assert_eq!(dbf.len(), header_len + 2 * record_len + 1);
+        assert_eq!(files[4].1, b"ISO-8859-1");
+    }
+
+    #[test]
+    fn test_write_mixed_layers() {
+        let features = vec![
+            feature(
+                "line",
+                json!({"type": "LineString", "coordinates": [[0, 0], [1, 1], [2, 0]]}),
+                json!({}),
+            ),
+            feature(
+                "polygon",
+                json!({"type": "MultiPolygon", "coordinates": [[[[0, 0], [1, 0], [1, 1], [0, 0]]]]}),
+                json!({}),
+            ),
+        ];
+        let files = write("mixed", &features, TextEncoding::Utf8);
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert!(names.contains(&"mixed_line.shp"));
+        assert!(names.contains(&"mixed_polygon.dbf"));
+        assert_eq!(files.len(), 10);
+
+        let polygon = &files[5].1;
+        assert_eq!(i32_le(polygon, 32), ShapeType::Polygon as i32);
+        // Record: type, bbox, one part of four points
+        assert_eq!(
+            i32_be(polygon, 104) as usize * 2,
+            4 + 32 + 4 + 4 + 4 + 4 * 16
+        );
+        assert_eq!(i32_le(polygon, 144), 1);
+        assert_eq!(i32_le(polygon, 148), 4);
+        let ring: Vec<[f64; 3]> = (0..4)
+            .map(|i| {
+                [
+                    f64_le(polygon, 156 + i * 16),
+                    f64_le(polygon, 164 + i * 16),
+                    0.0,
+                ]
+            })
+            .collect();
+        assert!(signed_area(&ring) < 0.0);
+        assert_eq!(files[9].1, b"UTF-8");
+    }
+
+    #[test]
+    fn test_write_heights() {
+        let features = vec![
+            feature(
+                "a",
+                json!({"type": "LineString", "coordinates": [[0, 0, 10], [1, 1, 20]]}),
+                json!({}),
+            ),
+            feature(
+                "b",
+                json!({"type": "LineString", "coordinates": [[2, 2], [3, 3]]}),
+                json!({}),
+            ),
+        ];
+        let files = write("heights", &features, TextEncoding::Utf8);
+        let shp = &files[0].1;
+        assert_eq!(i32_le(shp, 32), 13);
+        assert_eq!(f64_le(shp, 68), 0.0);
+        assert_eq!(f64_le(shp, 76), 20.0);
+
+        // Record: type, bbox, one part of two points, Z range and Z values
+        let content_len = 4 + 32 + 4 + 4 + 4 + 2 * 16 + 16 + 2 * 8;
+        assert_eq!(i32_be(shp, 104) as usize * 2, content_len);
+        assert_eq!(i32_le(shp, 108), 13);
+        assert_eq!(f64_le(shp, 108 + content_len - 8), 20.0);
+        assert_eq!(i32_be(shp, 24) as usize * 2, shp.len());
+        assert_eq!(shp.len(), 100 + 2 * (8 + content_len));
+        // Positions without a height get 0
+        assert_eq!(f64_le(shp, shp.len() - 16), 0.0);
+
+        let points = write(
+            "peaks",
+            &[feature(
+                "kebnekaise",
+                json!({"type": "Point", "coordinates": [18.5, 67.9, 2097]}),
+                json!({}),
+            )],
+            TextEncoding::Utf8,
+        );
+        let shp = &points[0].1;
+        assert_eq!(i32_le(shp, 32), 11);
+        assert_eq!(i32_be(shp, 104), 18);
+        assert_eq!(f64_le(shp, 128), 2097.0);
+    }
+}
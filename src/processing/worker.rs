@@ -1,17 +1,48 @@
 use base64::Engine;
 use bytes::Bytes;
-use std::path::PathBuf;
+use futures::StreamExt;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
+use tokio_util::io::ReaderStream;
 use uuid::Uuid;
 
+use crate::api::collections::schemas::{
+    CollectionGeometry, CollectionLimits, CollectionMetadata, ProcessingDefaults,
+    validate_asset_location,
+};
 use crate::api::processes::InputValue;
-use crate::db::{Collection, Database};
+use crate::api::processes::backup_collection::{
+    self, BackupCollectionInputs, BackupCollectionOutputs, BackupReference,
+};
+use crate::api::processes::deploy::ExecutionUnit;
+use crate::api::processes::export_collection::{
+    self, ExportCollectionInputs, ExportCollectionOutputs, ExportReference,
+};
+use crate::api::processes::import_pointcloud::ImportPointCloudInputs;
+use crate::api::processes::import_raster::{AdditionalAsset, ImportRasterInputs};
+use crate::api::processes::restore_collection::{
+    self, RestoreCollectionInputs, RestoreCollectionOutputs,
+};
+use crate::api::processes::temporal_composite::{
+    self, MAX_COMPOSITE_ITEMS, TemporalCompositeInputs, TemporalCompositeOutputs,
+};
+use crate::api::processes::workflow;
+use crate::api::read_only;
+use crate::config::ProcessingConfig;
+use crate::db::{Collection, CollectionWithCrs, Database, DeployedProcess};
 use crate::error::{AppError, AppResult};
-use crate::processing::{cog, copc};
-use crate::services::{CollectionService, ItemService, ProcessService};
-use crate::storage::S3Storage;
+use crate::processing::backup::BackupReader;
+use crate::processing::export::{ExportFormat, TextEncoding};
+use crate::processing::geopackage::GeoPackageWriter;
+use crate::processing::{archive, backup, cog, composite, copc, export};
+use crate::services::notification_service::{NotificationType, queue_notification};
+use crate::services::{
+    CollectionService, FeatureService, ItemService, ProcessService, QuotaService, UploadService,
+};
+use crate::storage::{S3Storage, s3_key_from_uri};
+use crate::{net, telemetry};
 
 pub struct JobWorker {
     db: Arc<Database>,
@@ -19,6 +50,10 @@ pub struct JobWorker {
     process_service: Arc<ProcessService>,
     item_service: Arc<ItemService>,
     collection_service: Arc<CollectionService>,
+    quotas: Arc<QuotaService>,
+    feature_service: FeatureService,
+    upload_service: UploadService,
+    processing: ProcessingConfig,
     temp_dir: PathBuf,
 }
 
@@ -29,16 +64,25 @@ impl JobWorker {
         process_service: Arc<ProcessService>,
         item_service: Arc<ItemService>,
         collection_service: Arc<CollectionService>,
+        quotas: Arc<QuotaService>,
+        processing: ProcessingConfig,
     ) -> Self {
         let temp_dir = std::env::temp_dir().join("spatialvault");
         std::fs::create_dir_all(&temp_dir).ok();
 
+        let upload_service = UploadService::new(db.clone(), storage.clone());
+        let feature_service = FeatureService::new(db.clone());
+
         Self {
             db,
             storage,
             process_service,
             item_service,
             collection_service,
+            quotas,
+            feature_service,
+            upload_service,
+            processing,
             temp_dir,
         }
     }
@@ -47,7 +91,29 @@ impl JobWorker {
     pub async fn run(&self) -> AppResult<()> {
         tracing::info!("Starting job worker");
 
+        let retention_interval = Duration::from_secs(self.processing.retention_interval_secs);
+        let mut last_retention_run: Option<std::time::Instant> = None;
+
         loop {
+            // Jobs write collections, so they wait out maintenance
+            if read_only::paused(&self.db).await {
+                sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+
+            if last_retention_run.is_none_or(|last| last.elapsed() >= retention_interval) {
+                last_retention_run = Some(std::time::Instant::now());
+                if let Err(e) = self.purge_expired_jobs().await {
+                    tracing::error!("Job retention cleanup failed: {}", e);
+                }
+                if let Err(e) = self.purge_expired_uploads().await {
+                    tracing::error!("Upload retention cleanup failed: {}", e);
+                }
+                if let Err(e) = self.purge_expired_collections().await {
+                    tracing::error!("Scratch collection cleanup failed: {}", e);
+                }
+            }
+
             match self.poll_and_process_job().await {
                 Ok(true) => {
                     // Processed a job, immediately check for more
@@ -65,27 +131,123 @@ impl JobWorker {
         }
     }
 
+    /// Delete scratch collections whose time to live has passed
+    async fn purge_expired_collections(&self) -> AppResult<()> {
+        let expired = self
+            .collection_service
+            .list_expired_collections(100)
+            .await?;
+
+        for (collection_id, owner) in expired {
+            match self
+                .collection_service
+                .delete_collection(&owner, &collection_id, None)
+                .await
+            {
+                Ok(()) => tracing::info!("Deleted expired collection {}", collection_id),
+                // Deleted concurrently
+                Err(AppError::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Delete expired jobs together with their result files and temp artifacts
+    async fn purge_expired_jobs(&self) -> AppResult<()> {
+        let expired = self
+            .process_service
+            .list_expired_jobs(
+                self.processing.successful_job_retention_days,
+                self.processing.failed_job_retention_days,
+                100,
+            )
+            .await?;
+
+        for (job_id, owner) in expired {
+            // Workflow steps store their outputs under their own IDs
+            let steps = self.process_service.list_workflow_steps(job_id).await?;
+            let job_ids = std::iter::once(job_id).chain(steps.iter().map(|step| step.id));
+
+            // Remove artifacts first so a failure leaves the job to be retried
+            let mut removed = 0;
+            for id in job_ids {
+                removed += self
+                    .storage
+                    .delete_prefix(&format!("{}/jobs/{}/", owner, id))
+                    .await?;
+                self.remove_temp_artifacts(id).await;
+            }
+            self.process_service.delete_job(job_id).await?;
+
+            tracing::info!("Deleted expired job {} ({} result files)", job_id, removed);
+        }
+
+        Ok(())
+    }
+
+    /// Delete chunked uploads older than the upload retention period
+    async fn purge_expired_uploads(&self) -> AppResult<()> {
+        let removed = self
+            .upload_service
+            .purge_expired_uploads(self.processing.upload_retention_days, 100)
+            .await?;
+        if removed > 0 {
+            tracing::info!("Deleted {} expired uploads", removed);
+        }
+        Ok(())
+    }
+
+    /// Remove leftover temp files and work directories of a job
+    async fn remove_temp_artifacts(&self, job_id: Uuid) {
+        let prefix = job_id.to_string();
+        let Ok(mut entries) = tokio::fs::read_dir(&self.temp_dir).await else {
+            return;
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if !entry.file_name().to_string_lossy().starts_with(&prefix) {
+                continue;
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                tokio::fs::remove_dir_all(&path).await.ok();
+            } else {
+                tokio::fs::remove_file(&path).await.ok();
+            }
+        }
+    }
+
     /// Poll for a pending job and process it
     async fn poll_and_process_job(&self) -> AppResult<bool> {
-        // Get next pending job (with row locking)
-        let job: Option<(Uuid, String, String, serde_json::Value)> = sqlx::query_as(
+        // Get next pending job whose retry backoff has passed (with row locking)
+        let job: Option<(Uuid, String, String, serde_json::Value, i32, i32)> = sqlx::query_as(
             r#"
             UPDATE spatialvault.processes_jobs
-            SET status = 'running', started = NOW(), updated = NOW()
+            SET
+                status = 'running',
+                started = NOW(),
+                updated = NOW(),
+                attempts = attempts + 1,
+                max_attempts = COALESCE(max_attempts, $1),
+                next_attempt_at = NULL
             WHERE id = (
                 SELECT id FROM spatialvault.processes_jobs
-                WHERE status = 'accepted'
+                WHERE status = 'accepted' AND parent_job_id IS NULL
+                  AND (next_attempt_at IS NULL OR next_attempt_at <= NOW())
                 ORDER BY created
                 LIMIT 1
                 FOR UPDATE SKIP LOCKED
             )
-            RETURNING id, process_id, owner, inputs
+            RETURNING id, process_id, owner, inputs, attempts, max_attempts
             "#,
         )
+        .bind(self.processing.max_job_attempts as i32)
         .fetch_optional(self.db.pool())
         .await?;
 
-        let (job_id, process_id, owner, inputs) = match job {
+        let (job_id, process_id, owner, inputs, attempt, max_attempts) = match job {
             Some(j) => j,
             None => return Ok(false),
         };
@@ -97,18 +259,9 @@ impl JobWorker {
             owner
         );
 
-        // Process based on type
-        let result = match process_id.as_str() {
-            "import-raster" => self.process_import_raster(job_id, &owner, &inputs).await,
-            "import-pointcloud" => {
-                self.process_import_pointcloud(job_id, &owner, &inputs)
-                    .await
-            }
-            _ => Err(AppError::Processing(format!(
-                "Unknown process: {}",
-                process_id
-            ))),
-        };
+        let result = self
+            .execute_process(job_id, &process_id, &owner, &inputs)
+            .await;
 
         match result {
             Ok(outputs) => {
@@ -116,27 +269,230 @@ impl JobWorker {
                     .set_job_outputs(job_id, &outputs)
                     .await?;
                 tracing::info!("Job {} completed successfully", job_id);
+                self.notify_job_finished(job_id, &process_id, &owner, "successful", None)
+                    .await;
+            }
+            Err(e) if e.is_transient() && attempt < max_attempts => {
+                let delay = self.processing.retry_delay(attempt as u32);
+                self.process_service
+                    .schedule_retry(job_id, attempt, &e.to_string(), delay)
+                    .await?;
+                tracing::warn!(
+                    "Job {} attempt {}/{} failed, retrying in {:?}: {}",
+                    job_id,
+                    attempt,
+                    max_attempts,
+                    delay,
+                    e
+                );
             }
             Err(e) => {
                 self.process_service
-                    .update_job_status(job_id, "failed", Some(&e.to_string()), None)
+                    .fail_job(job_id, attempt, &e.to_string())
                     .await?;
-                tracing::error!("Job {} failed: {}", job_id, e);
+                tracing::error!("Job {} failed after {} attempt(s): {}", job_id, attempt, e);
+                self.notify_job_finished(
+                    job_id,
+                    &process_id,
+                    &owner,
+                    "failed",
+                    Some(&e.to_string()),
+                )
+                .await;
             }
         }
 
         Ok(true)
     }
 
-    async fn process_import_raster(
+    /// Notify the owner of a job that it finished, if they want to be
+    ///
+    /// The job's outcome is already recorded, so failing to notify only
+    /// gets logged.
+    async fn notify_job_finished(
         &self,
         job_id: Uuid,
+        process_id: &str,
+        owner: &str,
+        status: &str,
+        error: Option<&str>,
+    ) {
+        let data = serde_json::json!({
+            "jobId": job_id,
+            "processId": process_id,
+            "status": status,
+            "error": error
+        });
+        if let Err(e) =
+            queue_notification(self.db.pool(), owner, NotificationType::JobFinished, &data).await
+        {
+            tracing::warn!("Failed to queue notification of job {}: {}", job_id, e);
+        }
+    }
+
+    /// Run a job based on its process type
+    #[tracing::instrument(name = "job", skip(self, inputs))]
+    async fn execute_process(
+        &self,
+        job_id: Uuid,
+        process_id: &str,
         owner: &str,
         inputs: &serde_json::Value,
     ) -> AppResult<serde_json::Value> {
-        use crate::api::processes::import_raster::ImportRasterInputs;
+        self.check_quota(process_id, owner, inputs).await?;
+        match process_id {
+            "import-raster" => self.process_import_raster(job_id, owner, inputs).await,
+            "import-pointcloud" => self.process_import_pointcloud(job_id, owner, inputs).await,
+            export_collection::PROCESS_ID => {
+                self.process_export_collection(job_id, owner, inputs).await
+            }
+            temporal_composite::PROCESS_ID => {
+                self.process_temporal_composite(job_id, owner, inputs).await
+            }
+            backup_collection::PROCESS_ID => {
+                self.process_backup_collection(job_id, owner, inputs).await
+            }
+            restore_collection::PROCESS_ID => {
+                self.process_restore_collection(job_id, owner, inputs).await
+            }
+            workflow::PROCESS_ID => self.process_workflow(job_id, owner).await,
+            _ => match self
+                .process_service
+                .get_deployed_process(process_id)
+                .await?
+            {
+                Some(process) => self.process_deployed(job_id, owner, &process, inputs).await,
+                None => Err(AppError::Processing(format!(
+                    "Unknown process: {}",
+                    process_id
+                ))),
+            },
+        }
+    }
+
+    /// Fail when a process would write to a namespace over its storage quota
+    ///
+    /// Checked before every run, so workflow steps and retried jobs are held
+    /// to the quota like jobs submitted on their own. Collections are
+    /// created in the owner's namespace unless they exist.
+    async fn check_quota(
+        &self,
+        process_id: &str,
+        owner: &str,
+        inputs: &serde_json::Value,
+    ) -> AppResult<()> {
+        if !self.quotas.is_enabled() {
+            return Ok(());
+        }
+        let target = match process_id {
+            "import-raster" | "import-pointcloud" => inputs.get("collection"),
+            temporal_composite::PROCESS_ID => inputs.get("target"),
+            restore_collection::PROCESS_ID => return self.quotas.check(owner).await,
+            _ => return Ok(()),
+        };
+        // Missing targets fail with the process's own validation
+        let Some(target) = target.and_then(serde_json::Value::as_str) else {
+            return Ok(());
+        };
+        let namespace = match self
+            .collection_service
+            .get_collection(owner, target)
+            .await?
+        {
+            Some(existing) => existing.owner,
+            None => owner.to_string(),
+        };
+        self.quotas.check(&namespace).await
+    }
+
+    /// Run the steps of a workflow in order, feeding each step the outputs
+    /// of the previous one. Returns the outputs of the last step.
+    #[tracing::instrument(skip(self))]
+    async fn process_workflow(&self, job_id: Uuid, owner: &str) -> AppResult<serde_json::Value> {
+        let steps = self.process_service.list_workflow_steps(job_id).await?;
+        let total = steps.len();
+        let mut previous_outputs = serde_json::json!({});
+
+        for (index, step) in steps.iter().enumerate() {
+            // Steps that succeeded in an earlier attempt aren't run again
+            if step.status == "successful" {
+                previous_outputs = step
+                    .outputs
+                    .clone()
+                    .unwrap_or_else(|| serde_json::json!({}));
+                continue;
+            }
+
+            // Stop early if the workflow was dismissed while running
+            let dismissed = self
+                .process_service
+                .get_job(owner, job_id)
+                .await?
+                .is_none_or(|job| job.status == "dismissed");
+            if dismissed {
+                self.process_service
+                    .dismiss_pending_steps(job_id, "Skipped as the workflow was dismissed")
+                    .await?;
+                return Err(AppError::Processing("Workflow was dismissed".to_string()));
+            }
 
-        let inputs: ImportRasterInputs = serde_json::from_value(inputs.clone())?;
+            self.process_service
+                .update_job_status(
+                    job_id,
+                    "running",
+                    Some(&format!(
+                        "Step {}/{}: {}",
+                        index + 1,
+                        total,
+                        step.process_id
+                    )),
+                    Some((index * 100 / total) as i32),
+                )
+                .await?;
+            self.process_service
+                .update_job_status(step.id, "running", Some("Starting"), Some(0))
+                .await?;
+
+            let step_inputs = step.inputs.clone().unwrap_or_else(|| serde_json::json!({}));
+            let result = match workflow::resolve_step_inputs(&step_inputs, &previous_outputs) {
+                Ok(inputs) => {
+                    Box::pin(self.execute_process(step.id, &step.process_id, owner, &inputs)).await
+                }
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(outputs) => {
+                    self.process_service
+                        .set_job_outputs(step.id, &outputs)
+                        .await?;
+                    previous_outputs = outputs;
+                }
+                Err(e) => {
+                    self.process_service
+                        .update_job_status(step.id, "failed", Some(&e.to_string()), None)
+                        .await?;
+                    self.process_service
+                        .dismiss_pending_steps(job_id, "Skipped after an earlier step failed")
+                        .await?;
+                    return Err(step_failed(
+                        e,
+                        &format!("Step {} ({}) failed", index + 1, step.process_id),
+                    ));
+                }
+            }
+        }
+
+        Ok(previous_outputs)
+    }
+
+    async fn process_import_raster(
+        &self,
+        job_id: Uuid,
+        owner: &str,
+        inputs: &serde_json::Value,
+    ) -> AppResult<serde_json::Value> {
+        let mut inputs: ImportRasterInputs = serde_json::from_value(inputs.clone())?;
 
         // 1. Validate/get collection
         self.process_service
@@ -146,6 +502,106 @@ impl JobWorker {
         let collection = self
             .get_or_create_collection(owner, &inputs.collection, "raster")
             .await?;
+        if let Some(defaults) =
+            ProcessingDefaults::from_stored(collection.processing_defaults.as_ref())
+        {
+            inputs.apply_defaults(&defaults);
+        }
+
+        // Reference-only import: catalog the source in place without copying it
+        if !inputs.copy {
+            let InputValue::Reference(reference) = &inputs.data else {
+                return Err(AppError::BadRequest(
+                    "copy=false requires a reference (href) input".to_string(),
+                ));
+            };
+            self.check_reference(&reference.href, &[owner, collection.owner.as_str()])?;
+
+            self.process_service
+                .update_job_status(job_id, "running", Some("Reading remote metadata"), Some(30))
+                .await?;
+
+            // Without real bounds the item would claim to cover the whole
+            // world, so a reference that can't be read fails the import
+            let meta = cog::extract_remote_raster_metadata(&reference.href).await?;
+            let (geometry_wkt, srid) = (bounds_to_wkt(&meta.bounds), meta.srid);
+            let file_size = self.remote_file_size(&reference.href).await;
+
+            self.process_service
+                .update_job_status(
+                    job_id,
+                    "running",
+                    Some("Creating database records"),
+                    Some(90),
+                )
+                .await?;
+
+            let datetime = inputs
+                .datetime
+                .as_ref()
+                .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+                .map(|dt| dt.with_timezone(&chrono::Utc));
+
+            let item = self
+                .item_service
+                .create_item(
+                    collection.id,
+                    &geometry_wkt,
+                    srid,
+                    datetime,
+                    inputs.properties.as_ref(),
+                )
+                .await?;
+
+            let registered = async {
+                self.item_service
+                    .create_asset(
+                        item.id,
+                        "data",
+                        &reference.href,
+                        Some(
+                            reference
+                                .media_type
+                                .as_deref()
+                                .unwrap_or("image/tiff; application=geotiff"),
+                        ),
+                        inputs.title.as_deref(),
+                        None,
+                        Some(&["data"]),
+                        file_size,
+                        None,
+                    )
+                    .await?;
+                self.create_additional_assets(
+                    job_id,
+                    item.id,
+                    None,
+                    &[owner, collection.owner.as_str()],
+                    &inputs.assets,
+                )
+                .await
+            }
+            .await;
+            let assets = match registered {
+                Ok(assets) => assets,
+                Err(e) => {
+                    self.discard_item(Some(item.id), None).await;
+                    return Err(e);
+                }
+            };
+
+            let mut output = serde_json::json!({
+                "item_id": item.id.to_string(),
+                "collection": inputs.collection,
+                "asset_href": reference.href,
+                "converted": false,
+                "copied": false
+            });
+            if !assets.is_empty() {
+                output["assets"] = serde_json::Value::Object(assets);
+            }
+            return Ok(output);
+        }
 
         // 2. Get source file (download from URL or decode from base64)
         self.process_service
@@ -154,54 +610,423 @@ impl JobWorker {
 
         let source_path = self.get_input_file(&inputs.data, job_id, "tif").await?;
 
+        if archive::is_zip(&source_path)? {
+            if !inputs.assets.is_empty() {
+                tokio::fs::remove_file(&source_path).await.ok();
+                return Err(AppError::BadRequest(
+                    "assets are not supported for ZIP archive imports".to_string(),
+                ));
+            }
+            return self
+                .import_archive(
+                    job_id,
+                    &inputs.collection,
+                    &source_path,
+                    RASTER_EXTENSIONS,
+                    async |path| {
+                        self.import_raster_file(job_id, owner, &collection, &inputs, path, false)
+                            .await
+                    },
+                )
+                .await;
+        }
+
+        self.import_raster_file(job_id, owner, &collection, &inputs, &source_path, true)
+            .await
+    }
+
+    /// Convert, upload and catalog one raster file as a new item
+    ///
+    /// Progress is only reported for single-file imports; archive imports
+    /// report it per file.
+    #[tracing::instrument(skip(self, owner, collection, inputs, source_path), fields(source = %source_path.display()))]
+    async fn import_raster_file(
+        &self,
+        job_id: Uuid,
+        owner: &str,
+        collection: &Collection,
+        inputs: &ImportRasterInputs,
+        source_path: &Path,
+        single_file: bool,
+    ) -> AppResult<serde_json::Value> {
+        let source_path = source_path.to_path_buf();
+
         // 3. Check if conversion is needed
-        self.process_service
-            .update_job_status(job_id, "running", Some("Checking file format"), Some(30))
+        self.report_step(job_id, single_file, "Checking file format", 30)
             .await?;
 
         let is_already_cog = inputs.skip_if_cog && cog::is_cog(&source_path)?;
-        let (final_path, converted) = if is_already_cog {
+        let (final_path, converted, is_cog) = if is_already_cog {
             tracing::info!("File is already a valid COG, skipping conversion");
-            (source_path.clone(), false)
+            (source_path.clone(), false, true)
         } else {
             // 4. Convert to COG
-            self.process_service
-                .update_job_status(job_id, "running", Some("Converting to COG"), Some(40))
+            self.report_step(job_id, single_file, "Converting to COG", 40)
                 .await?;
 
-            let output_path = self.temp_dir.join(format!("{}.cog.tif", job_id));
+            let output_path = source_path.with_extension("cog.tif");
 
-            // Try conversion, fall back to pass-through if not implemented
             match cog::convert_to_cog(&source_path, &output_path).await {
-                Ok(()) => (output_path, true),
+                Ok(()) => (output_path, true, true),
+                Err(e) if cog::is_cog(&source_path)? => {
+                    tracing::warn!("COG conversion failed: {}, source is already a COG", e);
+                    (source_path.clone(), false, true)
+                }
+                Err(e) if self.processing.allow_non_cog_rasters => {
+                    tracing::warn!("COG conversion failed: {}, storing source as GeoTIFF", e);
+                    (source_path.clone(), false, false)
+                }
                 Err(e) => {
-                    tracing::warn!("COG conversion not available: {}, using source file", e);
-                    (source_path.clone(), false)
+                    tokio::fs::remove_file(&source_path).await.ok();
+                    return Err(AppError::Processing(format!(
+                        "Source is not a Cloud Optimized GeoTIFF and could not be converted: {}. \
+                         Import a COG, or set processing.allow_non_cog_rasters to store it as is",
+                        e
+                    )));
                 }
             }
         };
 
         // 5. Extract metadata (bounds for geometry)
-        self.process_service
-            .update_job_status(job_id, "running", Some("Extracting metadata"), Some(60))
+        self.report_step(job_id, single_file, "Extracting metadata", 60)
             .await?;
 
         let (geometry_wkt, srid) = self.extract_raster_bounds(&final_path).await?;
 
         // 6. Upload to S3
-        self.process_service
-            .update_job_status(job_id, "running", Some("Uploading to storage"), Some(70))
+        self.report_step(job_id, single_file, "Uploading to storage", 70)
             .await?;
 
         let item_id = Uuid::new_v4();
 
-        let s3_key = format!("{}/{}/{}.tif", owner, collection.table_name, item_id);
+        let key_prefix = format!("{}/{}/{}", owner, collection.table_name, item_id);
+        let s3_key = format!("{}.tif", key_prefix);
         let file_data = tokio::fs::read(&final_path).await?;
         let file_size = file_data.len() as i64;
         self.storage.put(&s3_key, Bytes::from(file_data)).await?;
         let asset_href = self.storage.s3_uri(&s3_key);
 
         // 7. Create item and asset records
+        self.report_step(job_id, single_file, "Creating database records", 90)
+            .await?;
+
+        let datetime = inputs
+            .datetime
+            .as_ref()
+            .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+            .map(|dt| dt.with_timezone(&chrono::Utc));
+
+        let media_type = if is_cog {
+            "image/tiff; application=geotiff; profile=cloud-optimized"
+        } else {
+            "image/tiff; application=geotiff"
+        };
+
+        // The stored file is the original unless it was converted
+        let keep_source = inputs
+            .keep_source
+            .unwrap_or(self.processing.keep_source_files);
+
+        let mut created_item = None;
+        let registered = async {
+            let item = self
+                .item_service
+                .create_item(
+                    collection.id,
+                    &geometry_wkt,
+                    srid,
+                    datetime,
+                    inputs.properties.as_ref(),
+                )
+                .await?;
+            created_item = Some(item.id);
+
+            self.item_service
+                .create_asset(
+                    item.id,
+                    "data",
+                    &asset_href,
+                    Some(media_type),
+                    inputs.title.as_deref(),
+                    None,
+                    Some(&["data"]),
+                    Some(file_size),
+                    None,
+                )
+                .await?;
+
+            let source_href = if converted && keep_source {
+                Some(
+                    self.store_source_asset(item.id, &key_prefix, &source_path)
+                        .await?,
+                )
+            } else {
+                None
+            };
+
+            let assets = self
+                .create_additional_assets(
+                    job_id,
+                    item.id,
+                    Some(&key_prefix),
+                    &[owner, collection.owner.as_str()],
+                    &inputs.assets,
+                )
+                .await?;
+            Ok::<_, AppError>((item, source_href, assets))
+        }
+        .await;
+
+        // Cleanup temp files
+        tokio::fs::remove_file(&source_path).await.ok();
+        if converted && final_path != source_path {
+            tokio::fs::remove_file(&final_path).await.ok();
+        }
+
+        let (item, source_href, assets) = match registered {
+            Ok(registered) => registered,
+            Err(e) => {
+                self.discard_item(created_item, Some(&key_prefix)).await;
+                return Err(e);
+            }
+        };
+
+        let mut output = serde_json::json!({
+            "item_id": item.id.to_string(),
+            "collection": inputs.collection,
+            "asset_href": asset_href,
+            "converted": converted,
+            "copied": true
+        });
+        if let Some(source_href) = source_href {
+            output["source_href"] = serde_json::Value::String(source_href);
+        }
+        if !assets.is_empty() {
+            output["assets"] = serde_json::Value::Object(assets);
+        }
+        Ok(output)
+    }
+
+    async fn process_export_collection(
+        &self,
+        job_id: Uuid,
+        owner: &str,
+        inputs: &serde_json::Value,
+    ) -> AppResult<serde_json::Value> {
+        let inputs: ExportCollectionInputs = serde_json::from_value(inputs.clone())?;
+        let (format, encoding) = inputs.export_format()?;
+
+        // 1. Write the file to disk, feature by feature where the format
+        // allows it
+        self.process_service
+            .update_job_status(job_id, "running", Some("Writing export"), Some(10))
+            .await?;
+
+        let collection = self
+            .collection_service
+            .get_readable_collection(owner, &inputs.collection)
+            .await?
+            .ok_or_else(|| {
+                AppError::Processing(format!("Collection not found: {}", inputs.collection))
+            })?;
+
+        let file_name = format!(
+            "{}.{}",
+            export::file_stem(&collection.table_name),
+            format.extension()
+        );
+        let output_path = self.temp_dir.join(format!("{}_{}", job_id, file_name));
+        let written = self
+            .write_export(&collection, format, encoding, &output_path)
+            .await;
+        let number_of_features = match written {
+            Ok(count) => count,
+            Err(e) => {
+                tokio::fs::remove_file(&output_path).await.ok();
+                return Err(e);
+            }
+        };
+
+        // 2. Store it with the job, so it is purged along with it
+        self.process_service
+            .update_job_status(job_id, "running", Some("Storing export"), Some(90))
+            .await?;
+
+        let s3_key = format!("{}/jobs/{}/{}", owner, job_id, file_name);
+        let uploaded = match tokio::fs::File::open(&output_path).await {
+            Ok(file) => {
+                self.storage
+                    .put_stream(&s3_key, ReaderStream::new(file), u64::MAX)
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        };
+        tokio::fs::remove_file(&output_path).await.ok();
+        uploaded?;
+
+        let outputs = ExportCollectionOutputs {
+            export: ExportReference {
+                href: self.storage.s3_uri(&s3_key),
+                media_type: format.media_type().to_string(),
+            },
+            collection: collection.canonical_name,
+            file_name,
+            number_of_features,
+        };
+        Ok(serde_json::to_value(outputs)?)
+    }
+
+    /// Write the features of a collection to a file in an export format,
+    /// returning how many were written
+    ///
+    /// Features are written as they are read, so exports have no size
+    /// limit; see [`export::write_export`].
+    async fn write_export(
+        &self,
+        collection: &CollectionWithCrs,
+        format: ExportFormat,
+        encoding: TextEncoding,
+        path: &Path,
+    ) -> AppResult<usize> {
+        let name = &collection.canonical_name;
+        match format {
+            ExportFormat::GeoPackage => {
+                let properties = self.feature_service.property_types(name).await?;
+                let mut writer = GeoPackageWriter::create(
+                    path,
+                    &collection.table_name,
+                    &collection.title,
+                    &properties,
+                )
+                .await?;
+                let mut features = std::pin::pin!(
+                    self.feature_service
+                        .export_feature_stream(name, None, false)
+                        .await?
+                );
+                while let Some(feature) = features.next().await {
+                    writer.write(&feature?).await?;
+                }
+                writer.finish().await
+            }
+            _ => {
+                let features = self
+                    .feature_service
+                    .export_feature_stream(name, None, false)
+                    .await?;
+                export::write_export(
+                    format,
+                    &collection.table_name,
+                    &collection.title,
+                    features,
+                    encoding,
+                    path,
+                )
+                .await
+            }
+        }
+    }
+
+    async fn process_temporal_composite(
+        &self,
+        job_id: Uuid,
+        owner: &str,
+        inputs: &serde_json::Value,
+    ) -> AppResult<serde_json::Value> {
+        let inputs: TemporalCompositeInputs = serde_json::from_value(inputs.clone())?;
+        let (start, end) = inputs.interval()?;
+        let method = inputs.composite_method()?;
+
+        // 1. Find the items in the interval and area
+        self.process_service
+            .update_job_status(job_id, "running", Some("Finding items"), Some(5))
+            .await?;
+
+        let collection = self
+            .collection_service
+            .get_readable_collection(owner, &inputs.collection)
+            .await?
+            .ok_or_else(|| {
+                AppError::Processing(format!("Collection not found: {}", inputs.collection))
+            })?;
+        if collection.collection_type != "raster" {
+            return Err(AppError::Processing(format!(
+                "Only raster collections can be composited, {} is a {} collection",
+                collection.canonical_name, collection.collection_type
+            )));
+        }
+        let hrefs = self
+            .item_service
+            .data_asset_hrefs(
+                collection.id,
+                start,
+                end,
+                inputs.bbox,
+                MAX_COMPOSITE_ITEMS + 1,
+            )
+            .await?;
+        if hrefs.is_empty() {
+            return Err(AppError::Processing(
+                "No items in the interval and area to composite".to_string(),
+            ));
+        }
+        if hrefs.len() as i64 > MAX_COMPOSITE_ITEMS {
+            return Err(AppError::Processing(format!(
+                "More than {} items in the interval and area; narrow them",
+                MAX_COMPOSITE_ITEMS
+            )));
+        }
+        let number_of_items = hrefs.len();
+
+        // 2. Composite them
+        self.process_service
+            .update_job_status(
+                job_id,
+                "running",
+                Some(&format!("Compositing {} items", number_of_items)),
+                Some(20),
+            )
+            .await?;
+
+        let output_path = self.temp_dir.join(format!("{}_composite.tif", job_id));
+        let result = composite::composite_rasters(
+            hrefs,
+            inputs.bbox,
+            method,
+            inputs.normalized_difference,
+            &output_path,
+        )
+        .await;
+        if let Err(e) = result {
+            tokio::fs::remove_file(&output_path).await.ok();
+            return Err(e);
+        }
+
+        // 3. Store it as an item of the target collection
+        self.process_service
+            .update_job_status(job_id, "running", Some("Uploading to storage"), Some(80))
+            .await?;
+
+        let target = self
+            .get_or_create_collection(owner, &inputs.target, "raster")
+            .await?;
+        let (geometry_wkt, srid) = self.extract_raster_bounds(&output_path).await?;
+
+        let item_id = Uuid::new_v4();
+        let s3_key = format!("{}/{}/{}.tif", owner, target.table_name, item_id);
+        // Stream the composite from disk rather than buffering it
+        let uploaded = match tokio::fs::File::open(&output_path).await {
+            Ok(file) => {
+                self.storage
+                    .put_stream(&s3_key, ReaderStream::new(file), u64::MAX)
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        };
+        tokio::fs::remove_file(&output_path).await.ok();
+        let file_size = uploaded? as i64;
+        let asset_href = self.storage.s3_uri(&s3_key);
+
         self.process_service
             .update_job_status(
                 job_id,
@@ -211,35 +1036,32 @@ impl JobWorker {
             )
             .await?;
 
-        let datetime = inputs
-            .datetime
-            .as_ref()
-            .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
-            .map(|dt| dt.with_timezone(&chrono::Utc));
-
+        let properties = serde_json::json!({
+            "start_datetime": start.to_rfc3339(),
+            "end_datetime": end.to_rfc3339(),
+            "composite": {
+                "method": method.as_str(),
+                "normalized_difference": inputs.normalized_difference,
+                "collection": collection.canonical_name,
+                "number_of_items": number_of_items
+            }
+        });
         let item = self
             .item_service
             .create_item(
-                collection.id,
+                target.id,
                 &geometry_wkt,
                 srid,
-                datetime,
-                inputs.properties.as_ref(),
+                Some(start),
+                Some(&properties),
             )
             .await?;
-
-        let media_type = if converted {
-            "image/tiff; application=geotiff; profile=cloud-optimized"
-        } else {
-            "image/tiff; application=geotiff"
-        };
-
         self.item_service
             .create_asset(
                 item.id,
                 "data",
                 &asset_href,
-                Some(media_type),
+                Some("image/tiff; application=geotiff; profile=cloud-optimized"),
                 inputs.title.as_deref(),
                 None,
                 Some(&["data"]),
@@ -248,18 +1070,118 @@ impl JobWorker {
             )
             .await?;
 
-        // Cleanup temp files
-        tokio::fs::remove_file(&source_path).await.ok();
-        if converted && final_path != source_path {
-            tokio::fs::remove_file(&final_path).await.ok();
-        }
+        let outputs = TemporalCompositeOutputs {
+            item_id: item.id.to_string(),
+            collection: target.canonical_name,
+            asset_href,
+            number_of_items,
+        };
+        Ok(serde_json::to_value(outputs)?)
+    }
 
-        Ok(serde_json::json!({
-            "item_id": item.id.to_string(),
-            "collection": inputs.collection,
-            "asset_href": asset_href,
-            "converted": converted
-        }))
+    async fn process_backup_collection(
+        &self,
+        job_id: Uuid,
+        owner: &str,
+        inputs: &serde_json::Value,
+    ) -> AppResult<serde_json::Value> {
+        let inputs: BackupCollectionInputs = serde_json::from_value(inputs.clone())?;
+
+        // 1. Write the backup as the collection is read, storing it outside
+        // the job, so it outlives the job
+        self.process_service
+            .update_job_status(job_id, "running", Some("Writing backup"), Some(10))
+            .await?;
+
+        let created = chrono::Utc::now();
+        let s3_key = format!(
+            "{}{}/{}",
+            backup_collection::backup_prefix(owner),
+            job_id,
+            backup::file_name(&inputs.collection, created)
+        );
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<Bytes>(2);
+        let chunks = futures::stream::poll_fn(move |cx| receiver.poll_recv(cx))
+            .map(Ok::<_, std::convert::Infallible>);
+        let (written, stored) = tokio::join!(
+            self.collection_service
+                .backup_collection(owner, &inputs.collection, created, sender),
+            self.storage.put_stream(&s3_key, chunks, u64::MAX),
+        );
+        let (header, counts) = match (written, stored) {
+            (Ok(written), Ok(_)) => written,
+            (written, stored) => {
+                // The upload completes when the backup stops, even part way
+                if stored.is_ok()
+                    && let Err(e) = self.storage.delete(&s3_key).await
+                {
+                    tracing::warn!("Could not remove incomplete backup {}: {}", s3_key, e);
+                }
+                stored?;
+                return Err(written.err().unwrap_or_else(|| {
+                    AppError::Internal("Backup failed without an error".to_string())
+                }));
+            }
+        };
+
+        let outputs = BackupCollectionOutputs {
+            backup: BackupReference {
+                href: self.storage.s3_uri(&s3_key),
+                media_type: backup::MEDIA_TYPE.to_string(),
+            },
+            collection: header.collection.id,
+            collection_version: header.collection.version,
+            number_of_features: counts.features,
+            number_of_items: counts.items,
+        };
+        Ok(serde_json::to_value(outputs)?)
+    }
+
+    async fn process_restore_collection(
+        &self,
+        job_id: Uuid,
+        owner: &str,
+        inputs: &serde_json::Value,
+    ) -> AppResult<serde_json::Value> {
+        let inputs: RestoreCollectionInputs = serde_json::from_value(inputs.clone())?;
+
+        // 1. Open the backup
+        self.process_service
+            .update_job_status(job_id, "running", Some("Reading backup"), Some(10))
+            .await?;
+
+        let key = inputs
+            .backup
+            .strip_prefix(&self.storage.s3_uri(""))
+            .filter(|key| key.starts_with(&backup_collection::backup_prefix(owner)))
+            .ok_or_else(|| {
+                AppError::BadRequest(format!("Not one of your backups: {}", inputs.backup))
+            })?;
+        let (header, mut reader) = BackupReader::open(self.storage.get_stream(key).await?).await?;
+
+        // 2. Create the collection, reading its contents as they are inserted
+        self.process_service
+            .update_job_status(job_id, "running", Some("Restoring collection"), Some(40))
+            .await?;
+
+        let target = inputs
+            .collection
+            .as_deref()
+            .unwrap_or(&header.collection.id);
+        let canonical_name = restore_collection::target_collection(owner, target)?;
+        let collection = self
+            .collection_service
+            .restore_collection(owner, &canonical_name, &header, &mut reader)
+            .await?;
+
+        let counts = reader.counts();
+        let outputs = RestoreCollectionOutputs {
+            collection: collection.canonical_name,
+            source_collection: header.collection.id,
+            number_of_features: counts.features,
+            number_of_items: counts.items,
+        };
+        Ok(serde_json::to_value(outputs)?)
     }
 
     async fn process_import_pointcloud(
@@ -268,18 +1190,109 @@ impl JobWorker {
         owner: &str,
         inputs: &serde_json::Value,
     ) -> AppResult<serde_json::Value> {
-        use crate::api::processes::import_pointcloud::ImportPointCloudInputs;
+        let mut inputs: ImportPointCloudInputs = serde_json::from_value(inputs.clone())?;
+
+        // 1. Validate/get collection
+        self.process_service
+            .update_job_status(job_id, "running", Some("Validating collection"), Some(5))
+            .await?;
+
+        let collection = self
+            .get_or_create_collection(owner, &inputs.collection, "pointcloud")
+            .await?;
+        if let Some(defaults) =
+            ProcessingDefaults::from_stored(collection.processing_defaults.as_ref())
+        {
+            inputs.apply_defaults(&defaults);
+        }
+
+        // Reference-only import: catalog the source in place without copying it
+        if !inputs.copy {
+            let InputValue::Reference(reference) = &inputs.data else {
+                return Err(AppError::BadRequest(
+                    "copy=false requires a reference (href) input".to_string(),
+                ));
+            };
+            self.check_reference(&reference.href, &[owner, collection.owner.as_str()])?;
+
+            self.process_service
+                .update_job_status(job_id, "running", Some("Reading remote metadata"), Some(30))
+                .await?;
+
+            // The LAS header and COPC info VLR fit in a single small range
+            // read; the VLR with the CRS may take another up to the points
+            let mut header = self
+                .fetch_range(&reference.href, 0..copc::COPC_HEADER_PROBE_SIZE)
+                .await?;
+            let vlr_end = copc::LasHeader::parse(&header)?.vlr_end();
+            if vlr_end > header.len() {
+                header = self.fetch_range(&reference.href, 0..vlr_end).await?;
+            }
+            let is_copc = copc::is_copc_header(&header);
+            let meta = copc::parse_las_header(&header)?;
+            let geometry_wkt = bounds_to_wkt(&[
+                meta.bounds[0],
+                meta.bounds[1],
+                meta.bounds[3],
+                meta.bounds[4],
+            ]);
+            let srid = meta.srid;
+            let file_size = self.remote_file_size(&reference.href).await;
+
+            self.process_service
+                .update_job_status(
+                    job_id,
+                    "running",
+                    Some("Creating database records"),
+                    Some(90),
+                )
+                .await?;
+
+            let datetime = inputs
+                .datetime
+                .as_ref()
+                .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+                .map(|dt| dt.with_timezone(&chrono::Utc));
+
+            let item = self
+                .item_service
+                .create_item(
+                    collection.id,
+                    &geometry_wkt,
+                    srid,
+                    datetime,
+                    inputs.properties.as_ref(),
+                )
+                .await?;
 
-        let inputs: ImportPointCloudInputs = serde_json::from_value(inputs.clone())?;
+            let media_type = if is_copc {
+                "application/vnd.laszip+copc"
+            } else {
+                "application/vnd.laszip"
+            };
 
-        // 1. Validate/get collection
-        self.process_service
-            .update_job_status(job_id, "running", Some("Validating collection"), Some(5))
-            .await?;
+            self.item_service
+                .create_asset(
+                    item.id,
+                    "data",
+                    &reference.href,
+                    Some(reference.media_type.as_deref().unwrap_or(media_type)),
+                    inputs.title.as_deref(),
+                    None,
+                    Some(&["data"]),
+                    file_size,
+                    None,
+                )
+                .await?;
 
-        let collection = self
-            .get_or_create_collection(owner, &inputs.collection, "pointcloud")
-            .await?;
+            return Ok(serde_json::json!({
+                "item_id": item.id.to_string(),
+                "collection": inputs.collection,
+                "asset_href": reference.href,
+                "converted": false,
+                "copied": false
+            }));
+        }
 
         // 2. Get source file (download from URL or decode from base64)
         self.process_service
@@ -288,25 +1301,73 @@ impl JobWorker {
 
         let source_path = self.get_input_file(&inputs.data, job_id, "laz").await?;
 
+        if archive::is_zip(&source_path)? {
+            return self
+                .import_archive(
+                    job_id,
+                    &inputs.collection,
+                    &source_path,
+                    POINTCLOUD_EXTENSIONS,
+                    async |path| {
+                        self.import_pointcloud_file(
+                            job_id,
+                            owner,
+                            &collection,
+                            &inputs,
+                            path,
+                            false,
+                        )
+                        .await
+                    },
+                )
+                .await;
+        }
+
+        self.import_pointcloud_file(job_id, owner, &collection, &inputs, &source_path, true)
+            .await
+    }
+
+    /// Convert, upload and catalog one point cloud file as a new item
+    ///
+    /// Progress is only reported for single-file imports; archive imports
+    /// report it per file.
+    #[tracing::instrument(skip(self, owner, collection, inputs, source_path), fields(source = %source_path.display()))]
+    async fn import_pointcloud_file(
+        &self,
+        job_id: Uuid,
+        owner: &str,
+        collection: &Collection,
+        inputs: &ImportPointCloudInputs,
+        source_path: &Path,
+        single_file: bool,
+    ) -> AppResult<serde_json::Value> {
+        let source_path = source_path.to_path_buf();
+
         // 3. Check if conversion is needed
-        self.process_service
-            .update_job_status(job_id, "running", Some("Checking file format"), Some(30))
+        self.report_step(job_id, single_file, "Checking file format", 30)
             .await?;
 
-        let is_already_copc = inputs.skip_if_copc && copc::is_copc(&source_path)?;
+        let is_already_copc =
+            inputs.skip_if_copc && inputs.pipeline.is_empty() && copc::is_copc(&source_path)?;
         let (final_path, converted) = if is_already_copc {
             tracing::info!("File is already a valid COPC, skipping conversion");
             (source_path.clone(), false)
         } else {
             // 4. Convert to COPC
-            self.process_service
-                .update_job_status(job_id, "running", Some("Converting to COPC"), Some(40))
+            self.report_step(job_id, single_file, "Converting to COPC", 40)
                 .await?;
 
-            let output_path = self.temp_dir.join(format!("{}.copc.laz", job_id));
+            let output_path = source_path.with_extension("copc.laz");
 
-            match copc::convert_to_copc(&source_path, &output_path).await {
+            let timeout = Duration::from_secs(self.processing.pdal_timeout_secs);
+            match copc::convert_to_copc(&source_path, &output_path, &inputs.pipeline, timeout).await
+            {
                 Ok(()) => (output_path, true),
+                // Requested processing must not be skipped silently
+                Err(e) if !inputs.pipeline.is_empty() => {
+                    tokio::fs::remove_file(&source_path).await.ok();
+                    return Err(e);
+                }
                 Err(e) => {
                     tracing::warn!("COPC conversion not available: {}, using source file", e);
                     (source_path.clone(), false)
@@ -315,15 +1376,13 @@ impl JobWorker {
         };
 
         // 5. Extract metadata (bounds for geometry)
-        self.process_service
-            .update_job_status(job_id, "running", Some("Extracting metadata"), Some(60))
+        self.report_step(job_id, single_file, "Extracting metadata", 60)
             .await?;
 
         let (geometry_wkt, srid) = self.extract_pointcloud_bounds(&final_path).await?;
 
         // 6. Upload to S3
-        self.process_service
-            .update_job_status(job_id, "running", Some("Uploading to storage"), Some(70))
+        self.report_step(job_id, single_file, "Uploading to storage", 70)
             .await?;
 
         let item_id = Uuid::new_v4();
@@ -339,13 +1398,7 @@ impl JobWorker {
         let asset_href = self.storage.s3_uri(&s3_key);
 
         // 7. Create item and asset records
-        self.process_service
-            .update_job_status(
-                job_id,
-                "running",
-                Some("Creating database records"),
-                Some(90),
-            )
+        self.report_step(job_id, single_file, "Creating database records", 90)
             .await?;
 
         let datetime = inputs
@@ -381,20 +1434,472 @@ impl JobWorker {
             )
             .await?;
 
+        // The stored file is the original unless it was converted
+        let keep_source = inputs
+            .keep_source
+            .unwrap_or(self.processing.keep_source_files);
+        let source_href = if converted && keep_source {
+            let key_prefix = format!("{}/{}/{}", owner, collection.table_name, item_id);
+            Some(
+                self.store_source_asset(item.id, &key_prefix, &source_path)
+                    .await?,
+            )
+        } else {
+            None
+        };
+
         // Cleanup temp files
         tokio::fs::remove_file(&source_path).await.ok();
         if converted && final_path != source_path {
             tokio::fs::remove_file(&final_path).await.ok();
         }
 
-        Ok(serde_json::json!({
+        let mut output = serde_json::json!({
             "item_id": item.id.to_string(),
             "collection": inputs.collection,
             "asset_href": asset_href,
-            "converted": converted
+            "converted": converted,
+            "copied": true
+        });
+        if let Some(source_href) = source_href {
+            output["source_href"] = serde_json::Value::String(source_href);
+        }
+        Ok(output)
+    }
+
+    /// Upload the original file of a converted import as a `source` asset
+    ///
+    /// Returns the href of the stored file.
+    #[tracing::instrument(skip(self, source_path))]
+    async fn store_source_asset(
+        &self,
+        item_id: Uuid,
+        key_prefix: &str,
+        source_path: &Path,
+    ) -> AppResult<String> {
+        let extension = source_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("bin");
+        let s3_key = format!("{}.source.{}", key_prefix, extension);
+        let file_data = tokio::fs::read(source_path).await?;
+        let file_size = file_data.len() as i64;
+        self.storage.put(&s3_key, Bytes::from(file_data)).await?;
+        let href = self.storage.s3_uri(&s3_key);
+
+        self.item_service
+            .create_asset(
+                item_id,
+                "source",
+                &href,
+                source_media_type(extension),
+                Some("Original file"),
+                None,
+                Some(&["source"]),
+                Some(file_size),
+                None,
+            )
+            .await?;
+
+        Ok(href)
+    }
+
+    /// Remove an item whose registration failed part way, with the files
+    /// stored under its key prefix, so a failed import leaves neither a
+    /// partial item nor orphaned objects behind
+    async fn discard_item(&self, item_id: Option<Uuid>, key_prefix: Option<&str>) {
+        if let Some(item_id) = item_id
+            && let Err(e) = self.item_service.delete_item(item_id).await
+        {
+            tracing::warn!(
+                "Could not remove partially registered item {}: {}",
+                item_id,
+                e
+            );
+        }
+        let Some((dir, _)) = key_prefix.and_then(|prefix| prefix.rsplit_once('/')) else {
+            return;
+        };
+        // Files of the item are named `<key prefix>.<suffix>`, next to those
+        // of the other items
+        let key_prefix = format!("{}.", key_prefix.unwrap_or_default());
+        let removed = async {
+            for key in self.storage.list(dir).await? {
+                if key.starts_with(&key_prefix) {
+                    self.storage.delete(&key).await?;
+                }
+            }
+            Ok::<_, AppError>(())
+        }
+        .await;
+        if let Err(e) = removed {
+            tracing::warn!("Could not remove stored files {}*: {}", key_prefix, e);
+        }
+    }
+
+    /// Register further files of an item as assets with their keys and roles
+    ///
+    /// With a storage key prefix the files are copied into managed storage
+    /// next to the item's data; without one they are referenced in place.
+    /// Either way, S3 references must be in one of `namespaces`. Returns the
+    /// href of each asset by key.
+    async fn create_additional_assets(
+        &self,
+        job_id: Uuid,
+        item_id: Uuid,
+        key_prefix: Option<&str>,
+        namespaces: &[&str],
+        assets: &[AdditionalAsset],
+    ) -> AppResult<serde_json::Map<String, serde_json::Value>> {
+        for asset in assets {
+            if let InputValue::Reference(reference) = &asset.data {
+                self.check_reference(&reference.href, namespaces)?;
+            }
+        }
+
+        let mut hrefs = serde_json::Map::new();
+        for asset in assets {
+            let (href, file_size, extension) = match (key_prefix, &asset.data) {
+                (Some(key_prefix), data) => {
+                    let (file_data, extension) = match data {
+                        InputValue::Inline(inline) => (
+                            base64::engine::general_purpose::STANDARD
+                                .decode(&inline.value)
+                                .map_err(|e| {
+                                    AppError::BadRequest(format!("Invalid base64: {}", e))
+                                })?,
+                            "bin",
+                        ),
+                        InputValue::Reference(reference) => {
+                            let local_path = self
+                                .temp_dir
+                                .join(format!("{}_asset_{}", job_id, asset.key));
+                            self.download_to(&reference.href, &local_path).await?;
+                            let file_data = tokio::fs::read(&local_path).await;
+                            tokio::fs::remove_file(&local_path).await.ok();
+                            (file_data?, safe_extension(&reference.href))
+                        }
+                    };
+                    let s3_key = format!("{}.{}.{}", key_prefix, asset.key, extension);
+                    let file_size = file_data.len() as i64;
+                    self.storage.put(&s3_key, Bytes::from(file_data)).await?;
+                    (self.storage.s3_uri(&s3_key), Some(file_size), extension)
+                }
+                (None, InputValue::Reference(reference)) => (
+                    reference.href.clone(),
+                    self.remote_file_size(&reference.href).await,
+                    safe_extension(&reference.href),
+                ),
+                (None, InputValue::Inline(_)) => {
+                    return Err(AppError::BadRequest(
+                        "copy=false requires a reference (href) input".to_string(),
+                    ));
+                }
+            };
+
+            let roles: Vec<&str> = asset.roles.iter().map(String::as_str).collect();
+            self.item_service
+                .create_asset(
+                    item_id,
+                    &asset.key,
+                    &href,
+                    asset.media_type().or(source_media_type(extension)),
+                    asset.title.as_deref(),
+                    None,
+                    (!roles.is_empty()).then_some(roles.as_slice()),
+                    file_size,
+                    None,
+                )
+                .await?;
+            hrefs.insert(asset.key.clone(), serde_json::Value::String(href));
+        }
+        Ok(hrefs)
+    }
+
+    /// Import every matching file of a ZIP archive as a separate item
+    ///
+    /// A file that fails is reported in the outputs instead of failing the
+    /// job; the job only fails when no file could be imported.
+    #[tracing::instrument(skip(self, archive_path, extensions, import_file))]
+    async fn import_archive(
+        &self,
+        job_id: Uuid,
+        collection: &str,
+        archive_path: &Path,
+        extensions: &'static [&'static str],
+        import_file: impl AsyncFn(&Path) -> AppResult<serde_json::Value>,
+    ) -> AppResult<serde_json::Value> {
+        self.process_service
+            .update_job_status(job_id, "running", Some("Extracting archive"), Some(15))
+            .await?;
+
+        let extract_dir = self.temp_dir.join(format!("{}-archive", job_id));
+        let (path, dir) = (archive_path.to_path_buf(), extract_dir.clone());
+        let max_bytes = self.processing.max_upload_bytes;
+        let extracted = tokio::task::spawn_blocking(move || {
+            archive::extract_zip(&path, &dir, extensions, max_bytes)
+        })
+        .await
+        .map_err(|e| AppError::Processing(format!("Task join error: {}", e)))?;
+        tokio::fs::remove_file(archive_path).await.ok();
+        let entries = match extracted.and_then(|entries| {
+            if entries.is_empty() {
+                Err(AppError::Processing(format!(
+                    "Archive contains no .{} files",
+                    extensions.join(", .")
+                )))
+            } else {
+                Ok(entries)
+            }
+        }) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tokio::fs::remove_dir_all(&extract_dir).await.ok();
+                return Err(e);
+            }
+        };
+
+        let total = entries.len();
+        let mut files = Vec::with_capacity(total);
+        let mut imported = 0;
+        for (index, entry) in entries.iter().enumerate() {
+            self.process_service
+                .update_job_status(
+                    job_id,
+                    "running",
+                    Some(&format!(
+                        "Importing {} ({} of {})",
+                        entry.name,
+                        index + 1,
+                        total
+                    )),
+                    Some(20 + (75 * index / total) as i32),
+                )
+                .await?;
+
+            match import_file(&entry.path).await {
+                Ok(mut output) => {
+                    output["file"] = serde_json::json!(entry.name);
+                    imported += 1;
+                    files.push(output);
+                }
+                Err(e) => {
+                    tracing::warn!("Job {}: failed to import {}: {}", job_id, entry.name, e);
+                    files.push(serde_json::json!({
+                        "file": entry.name,
+                        "error": e.to_string()
+                    }));
+                }
+            }
+        }
+        tokio::fs::remove_dir_all(&extract_dir).await.ok();
+
+        if imported == 0 {
+            return Err(AppError::Processing(format!(
+                "None of the {} files in the archive could be imported",
+                total
+            )));
+        }
+
+        Ok(serde_json::json!({
+            "collection": collection,
+            "files": files,
+            "imported": imported,
+            "failed": total - imported,
+            "copied": true
         }))
     }
 
+    /// Update the status of a single-file import step
+    async fn report_step(
+        &self,
+        job_id: Uuid,
+        single_file: bool,
+        message: &str,
+        progress: i32,
+    ) -> AppResult<()> {
+        if single_file {
+            self.process_service
+                .update_job_status(job_id, "running", Some(message), Some(progress))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Run a deployed process in a sandboxed container
+    ///
+    /// Reference inputs are staged into `/work/inputs`, the remaining inputs
+    /// are written to `/work/inputs.json`, and every file the container writes
+    /// to `/work/outputs` is uploaded to storage. If the container writes
+    /// `/work/outputs/outputs.json`, its values are returned as literal outputs.
+    #[tracing::instrument(skip(self, owner, process, inputs), fields(process = %process.id))]
+    async fn process_deployed(
+        &self,
+        job_id: Uuid,
+        owner: &str,
+        process: &DeployedProcess,
+        inputs: &serde_json::Value,
+    ) -> AppResult<serde_json::Value> {
+        let unit: ExecutionUnit = serde_json::from_value(process.execution_unit.clone())?;
+
+        let work_dir = self.temp_dir.join(job_id.to_string());
+        let inputs_dir = work_dir.join("inputs");
+        let outputs_dir = work_dir.join("outputs");
+        tokio::fs::create_dir_all(&inputs_dir).await?;
+        tokio::fs::create_dir_all(&outputs_dir).await?;
+
+        let result = self
+            .run_container(job_id, owner, &unit, inputs, &work_dir)
+            .await;
+
+        tokio::fs::remove_dir_all(&work_dir).await.ok();
+        result
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn run_container(
+        &self,
+        job_id: Uuid,
+        owner: &str,
+        unit: &ExecutionUnit,
+        inputs: &serde_json::Value,
+        work_dir: &std::path::Path,
+    ) -> AppResult<serde_json::Value> {
+        // Packages deployed before env names were validated are still stored
+        unit.validate_env().map_err(AppError::BadRequest)?;
+
+        // 1. Stage reference inputs into the work directory
+        self.process_service
+            .update_job_status(job_id, "running", Some("Staging inputs"), Some(10))
+            .await?;
+
+        let mut staged = inputs.as_object().cloned().unwrap_or_default();
+        for (name, value) in staged.iter_mut() {
+            let Some(href) = value.get("href").and_then(|h| h.as_str()) else {
+                continue;
+            };
+            let file_name = format!("{}.{}", sanitize_file_name(name), safe_extension(href));
+            self.download_input(href, owner, &work_dir.join("inputs").join(&file_name))
+                .await?;
+            value["href"] = serde_json::json!(format!("/work/inputs/{}", file_name));
+        }
+        tokio::fs::write(
+            work_dir.join("inputs.json"),
+            serde_json::to_vec(&serde_json::Value::Object(staged))?,
+        )
+        .await?;
+
+        // 2. Run the container without network access or extra privileges
+        self.process_service
+            .update_job_status(job_id, "running", Some("Running container"), Some(30))
+            .await?;
+
+        // Named after the job so it can be removed if the time limit is hit;
+        // a container left by an earlier attempt of the job goes first
+        let container_name = format!("spatialvault-{}", job_id);
+        self.remove_container(&container_name).await;
+
+        let mut command = tokio::process::Command::new(&self.processing.container_runtime);
+        command
+            .arg("run")
+            .arg("--rm")
+            .args(["--name", &container_name])
+            .args(["--network", "none"])
+            .args(["--cap-drop", "ALL"])
+            .args(["--security-opt", "no-new-privileges"])
+            .args(["--read-only", "--tmpfs", "/tmp"])
+            .arg("-v")
+            .arg(format!("{}:/work", work_dir.display()))
+            .args(["-w", "/work"])
+            .args(["-e", "SPATIALVAULT_INPUTS=/work/inputs.json"])
+            .args(["-e", "SPATIALVAULT_OUTPUTS=/work/outputs"]);
+        for (key, value) in &unit.env {
+            command.arg("-e").arg(format!("{}={}", key, value));
+        }
+        command
+            .arg(&unit.image)
+            .args(&unit.command)
+            .kill_on_drop(true);
+
+        let timeout = Duration::from_secs(self.processing.container_timeout_secs);
+        let output = match tokio::time::timeout(timeout, command.output()).await {
+            Ok(output) => output
+                .map_err(|e| AppError::Processing(format!("Failed to start container: {}", e)))?,
+            Err(_) => {
+                // Killing the runtime CLI leaves the container running
+                self.remove_container(&container_name).await;
+                return Err(AppError::Processing(format!(
+                    "Container exceeded the {}s time limit",
+                    self.processing.container_timeout_secs
+                )));
+            }
+        };
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let tail: String = stderr
+                .chars()
+                .rev()
+                .take(1000)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .collect();
+            return Err(AppError::Processing(format!(
+                "Container exited with {}: {}",
+                output.status,
+                tail.trim()
+            )));
+        }
+
+        // 3. Upload output files and collect results
+        self.process_service
+            .update_job_status(job_id, "running", Some("Uploading outputs"), Some(80))
+            .await?;
+
+        let outputs_dir = work_dir.join("outputs");
+        let mut outputs = match tokio::fs::read(outputs_dir.join("outputs.json")).await {
+            Ok(data) => serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(&data)
+                .map_err(|e| AppError::Processing(format!("Invalid outputs.json: {}", e)))?,
+            Err(_) => serde_json::Map::new(),
+        };
+
+        let mut entries = tokio::fs::read_dir(&outputs_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if file_name == "outputs.json" || !entry.file_type().await?.is_file() {
+                continue;
+            }
+
+            let s3_key = format!("{}/jobs/{}/{}", owner, job_id, file_name);
+            let data = tokio::fs::read(entry.path()).await?;
+            self.storage.put(&s3_key, Bytes::from(data)).await?;
+
+            let output_name = file_name
+                .split_once('.')
+                .map(|(stem, _)| stem)
+                .unwrap_or(&file_name)
+                .to_string();
+            outputs.insert(
+                output_name,
+                serde_json::json!({ "href": self.storage.s3_uri(&s3_key) }),
+            );
+        }
+
+        Ok(serde_json::Value::Object(outputs))
+    }
+
+    /// Force-remove a container by name, if it exists
+    async fn remove_container(&self, name: &str) {
+        let result = tokio::process::Command::new(&self.processing.container_runtime)
+            .args(["rm", "-f", name])
+            .output()
+            .await;
+        if let Err(e) = result {
+            tracing::warn!("Failed to remove container {}: {}", name, e);
+        }
+    }
+
     /// Get existing collection or create a new one
     async fn get_or_create_collection(
         &self,
@@ -434,6 +1939,12 @@ impl JobWorker {
                 None,
                 collection_type,
                 4326, // Default to WGS84
+                &CollectionLimits::default(),
+                &CollectionMetadata::default(),
+                &[],
+                &[],
+                &CollectionGeometry::default(),
+                None,
             )
             .await
     }
@@ -481,50 +1992,22 @@ impl JobWorker {
 
     /// Download a file from URL (HTTP or S3)
     async fn download_file(&self, url: &str, job_id: Uuid) -> AppResult<PathBuf> {
-        let extension = url
-            .rsplit('/')
-            .next()
-            .and_then(|f| f.rsplit('.').next())
-            .unwrap_or("bin");
-
-        // Validate extension to prevent path traversal - must be alphanumeric only
-        let safe_extension =
-            if extension.chars().all(|c| c.is_ascii_alphanumeric()) && extension.len() <= 10 {
-                extension
-            } else {
-                "bin"
-            };
-
         let local_path = self
             .temp_dir
-            .join(format!("{}_source.{}", job_id, safe_extension));
+            .join(format!("{}_source.{}", job_id, safe_extension(url)));
 
-        if url.starts_with("s3://") {
-            // Parse S3 URL: s3://bucket/key
-            let path = url.strip_prefix("s3://").unwrap();
-            let key = path.split_once('/').map(|(_, k)| k).unwrap_or(path);
+        self.download_to(url, &local_path).await?;
+        Ok(local_path)
+    }
 
-            let data = self.storage.get(key).await?;
-            tokio::fs::write(&local_path, &data).await?;
+    /// Download a file from URL (HTTP or S3) to a local path
+    #[tracing::instrument(skip(self, local_path))]
+    async fn download_to(&self, url: &str, local_path: &std::path::Path) -> AppResult<()> {
+        if url.starts_with("s3://") {
+            let data = self.storage.get(s3_key_from_uri(url)).await?;
+            tokio::fs::write(local_path, &data).await?;
         } else if url.starts_with("http://") || url.starts_with("https://") {
-            // HTTP download
-            let response = reqwest::get(url)
-                .await
-                .map_err(|e| AppError::Processing(format!("Failed to download: {}", e)))?;
-
-            if !response.status().is_success() {
-                return Err(AppError::Processing(format!(
-                    "Download failed with status: {}",
-                    response.status()
-                )));
-            }
-
-            let bytes = response
-                .bytes()
-                .await
-                .map_err(|e| AppError::Processing(format!("Failed to read response: {}", e)))?;
-
-            tokio::fs::write(&local_path, &bytes).await?;
+            download_http(&reqwest::Client::new(), url, local_path).await?;
         } else {
             return Err(AppError::BadRequest(format!(
                 "Unsupported URL scheme: {}",
@@ -533,34 +2016,55 @@ impl JobWorker {
         }
 
         tracing::info!("Downloaded {} to {:?}", url, local_path);
-        Ok(local_path)
+        Ok(())
+    }
+
+    /// Download an input of a deployed process to a local path
+    ///
+    /// The owner may point inputs anywhere, so S3 objects must be in their
+    /// namespace and HTTP(S) URLs on public addresses, redirects included.
+    #[tracing::instrument(skip(self, local_path))]
+    async fn download_input(
+        &self,
+        href: &str,
+        owner: &str,
+        local_path: &std::path::Path,
+    ) -> AppResult<()> {
+        if !(href.starts_with("http://") || href.starts_with("https://")) {
+            self.check_reference(href, &[owner])?;
+            return self.download_to(href, local_path).await;
+        }
+
+        let url = reqwest::Url::parse(href)
+            .map_err(|e| AppError::BadRequest(format!("Invalid input URL '{}': {}", href, e)))?;
+        net::check_url(&url).map_err(AppError::BadRequest)?;
+        let client = net::client_builder()
+            .redirect(reqwest::redirect::Policy::custom(|attempt| {
+                if attempt.previous().len() >= MAX_INPUT_REDIRECTS
+                    || net::check_url(attempt.url()).is_err()
+                {
+                    attempt.stop()
+                } else {
+                    attempt.follow()
+                }
+            }))
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to build HTTP client: {}", e)))?;
+        download_http(&client, href, local_path).await?;
+
+        tracing::info!("Downloaded {} to {:?}", href, local_path);
+        Ok(())
     }
 
     /// Extract bounds from raster file (returns WKT POLYGON and SRID)
     async fn extract_raster_bounds(&self, path: &PathBuf) -> AppResult<(String, i32)> {
         // Try to use GDAL for proper metadata extraction
         match cog::extract_raster_metadata(path).await {
-            Ok(meta) => {
-                let wkt = format!(
-                    "POLYGON(({} {}, {} {}, {} {}, {} {}, {} {}))",
-                    meta.bounds[0],
-                    meta.bounds[1], // minx, miny
-                    meta.bounds[2],
-                    meta.bounds[1], // maxx, miny
-                    meta.bounds[2],
-                    meta.bounds[3], // maxx, maxy
-                    meta.bounds[0],
-                    meta.bounds[3], // minx, maxy
-                    meta.bounds[0],
-                    meta.bounds[1], // close polygon
-                );
-                Ok((wkt, meta.srid))
-            }
+            Ok(meta) => Ok((bounds_to_wkt(&meta.bounds), meta.srid)),
             Err(_) => {
                 // Fallback: use a placeholder global extent
                 tracing::warn!("Could not extract raster bounds, using placeholder");
-                let wkt = "POLYGON((-180 -90, 180 -90, 180 90, -180 90, -180 -90))".to_string();
-                Ok((wkt, 4326))
+                Ok((PLACEHOLDER_EXTENT_WKT.to_string(), 4326))
             }
         }
     }
@@ -570,27 +2074,194 @@ impl JobWorker {
         match copc::extract_pointcloud_metadata(path).await {
             Ok(meta) => {
                 // Use 2D bounds (ignore Z)
-                let wkt = format!(
-                    "POLYGON(({} {}, {} {}, {} {}, {} {}, {} {}))",
+                let bounds = [
                     meta.bounds[0],
-                    meta.bounds[1], // minx, miny
+                    meta.bounds[1],
                     meta.bounds[3],
-                    meta.bounds[1], // maxx, miny
-                    meta.bounds[3],
-                    meta.bounds[4], // maxx, maxy
-                    meta.bounds[0],
-                    meta.bounds[4], // minx, maxy
-                    meta.bounds[0],
-                    meta.bounds[1], // close polygon
-                );
-                Ok((wkt, meta.srid))
+                    meta.bounds[4],
+                ];
+                Ok((bounds_to_wkt(&bounds), meta.srid))
             }
             Err(_) => {
                 // Fallback: use a placeholder global extent
                 tracing::warn!("Could not extract point cloud bounds, using placeholder");
-                let wkt = "POLYGON((-180 -90, 180 -90, 180 90, -180 90, -180 -90))".to_string();
-                Ok((wkt, 4326))
+                Ok((PLACEHOLDER_EXTENT_WKT.to_string(), 4326))
+            }
+        }
+    }
+
+    /// Read a byte range from a remote file (HTTP range request or S3 range read)
+    #[tracing::instrument(skip(self))]
+    async fn fetch_range(&self, url: &str, range: std::ops::Range<usize>) -> AppResult<Bytes> {
+        if url.starts_with("s3://") {
+            self.storage.get_range(s3_key_from_uri(url), range).await
+        } else if url.starts_with("http://") || url.starts_with("https://") {
+            let response = reqwest::Client::new()
+                .get(url)
+                .headers(telemetry::trace_headers())
+                .header(
+                    reqwest::header::RANGE,
+                    format!("bytes={}-{}", range.start, range.end.saturating_sub(1)),
+                )
+                .send()
+                .await
+                .map_err(|e| AppError::Upstream(format!("Failed to fetch range: {}", e)))?;
+
+            if !response.status().is_success() {
+                return Err(http_status_error("Range request", response.status()));
             }
+
+            // Servers that ignore Range return the whole body; keep only what was asked for
+            let bytes = response
+                .bytes()
+                .await
+                .map_err(|e| AppError::Upstream(format!("Failed to read response: {}", e)))?;
+            let end = range.end.min(bytes.len());
+            Ok(bytes.slice(range.start.min(end)..end))
+        } else {
+            Err(AppError::BadRequest(format!(
+                "Unsupported URL scheme: {}",
+                url
+            )))
+        }
+    }
+
+    /// Check that an `s3://` reference is an object in one of `namespaces`,
+    /// as it is read with the server's own credentials
+    fn check_reference(&self, href: &str, namespaces: &[&str]) -> AppResult<()> {
+        if href.starts_with("s3://") {
+            validate_asset_location(href, &self.storage.s3_uri(""), namespaces)?;
+        }
+        Ok(())
+    }
+
+    /// Size of a remote file in bytes, if the source reports it
+    async fn remote_file_size(&self, url: &str) -> Option<i64> {
+        if url.starts_with("s3://") {
+            self.storage
+                .head(s3_key_from_uri(url))
+                .await
+                .ok()
+                .map(|meta| meta.size as i64)
+        } else {
+            reqwest::Client::new()
+                .head(url)
+                .headers(telemetry::trace_headers())
+                .send()
+                .await
+                .ok()
+                .filter(|response| response.status().is_success())
+                .and_then(|response| response.content_length())
+                .map(|len| len as i64)
+        }
+    }
+}
+
+/// Redirects followed when downloading inputs of deployed processes
+const MAX_INPUT_REDIRECTS: usize = 5;
+
+/// File extensions imported from raster archives
+const RASTER_EXTENSIONS: &[&str] = &["tif", "tiff"];
+
+/// File extensions imported from point cloud archives
+const POINTCLOUD_EXTENSIONS: &[&str] = &["las", "laz"];
+
+/// Global extent used when the real bounds of a file cannot be determined
+const PLACEHOLDER_EXTENT_WKT: &str = "POLYGON((-180 -90, 180 -90, 180 90, -180 90, -180 -90))";
+
+/// Media type of a kept source file, from its extension
+fn source_media_type(extension: &str) -> Option<&'static str> {
+    match extension.to_ascii_lowercase().as_str() {
+        "tif" | "tiff" => Some("image/tiff"),
+        "laz" => Some("application/vnd.laszip"),
+        "las" => Some("application/vnd.las"),
+        _ => None,
+    }
+}
+
+/// Error of a workflow whose step failed with `error`
+///
+/// It stays transient if the step's error is, so the workflow is retried.
+fn step_failed(error: AppError, context: &str) -> AppError {
+    match error {
+        AppError::Storage(message) => AppError::Storage(format!("{}: {}", context, message)),
+        AppError::Upstream(message) => AppError::Upstream(format!("{}: {}", context, message)),
+        AppError::Io(e) => {
+            AppError::Io(std::io::Error::new(e.kind(), format!("{}: {}", context, e)))
         }
+        // Keeps its source to stay transient, so it goes without the context
+        e @ AppError::Database(_) if e.is_transient() => e,
+        e => AppError::Processing(format!("{}: {}", context, e)),
+    }
+}
+
+/// Error for a failed HTTP request; server errors and throttling are retryable
+/// Download an HTTP(S) URL to a local path
+async fn download_http(
+    client: &reqwest::Client,
+    url: &str,
+    local_path: &std::path::Path,
+) -> AppResult<()> {
+    let response = client
+        .get(url)
+        .headers(telemetry::trace_headers())
+        .send()
+        .await
+        .map_err(|e| AppError::Upstream(format!("Failed to download: {}", e)))?;
+
+    if !response.status().is_success() {
+        return Err(http_status_error("Download", response.status()));
+    }
+
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| AppError::Upstream(format!("Failed to read response: {}", e)))?;
+
+    tokio::fs::write(local_path, &bytes).await?;
+    Ok(())
+}
+
+fn http_status_error(action: &str, status: reqwest::StatusCode) -> AppError {
+    let message = format!("{} failed with status: {}", action, status);
+    if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        AppError::Upstream(message)
+    } else {
+        AppError::Processing(message)
+    }
+}
+
+/// Build a WKT POLYGON from [minx, miny, maxx, maxy]
+fn bounds_to_wkt(bounds: &[f64; 4]) -> String {
+    let [minx, miny, maxx, maxy] = *bounds;
+    format!("POLYGON(({minx} {miny}, {maxx} {miny}, {maxx} {maxy}, {minx} {maxy}, {minx} {miny}))")
+}
+
+/// File extension of a URL, restricted to short alphanumeric values to
+/// prevent path traversal
+pub(crate) fn safe_extension(url: &str) -> &str {
+    let extension = url
+        .rsplit('/')
+        .next()
+        .and_then(|f| f.rsplit('.').next())
+        .unwrap_or("bin");
+
+    if extension.chars().all(|c| c.is_ascii_alphanumeric()) && extension.len() <= 10 {
+        extension
+    } else {
+        "bin"
     }
 }
+
+/// Replace characters that are not safe in a file name
+pub(crate) fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,207 @@
+//! `spatialvault seed`
+//!
+//! Loads a small demo dataset into a namespace, so new deployments and the
+//! documentation examples work out of the box: coarse outlines of the Nordic
+//! countries as a vector collection, and import jobs for a sample COG and
+//! COPC, which the worker runs. Collections that already exist are left
+//! alone, so seeding again only adds what is missing.
+
+use anyhow::{Context, bail};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::admin::{Options, connect};
+use crate::api::collections::schemas::{CollectionGeometry, CollectionLimits, CollectionMetadata};
+use crate::api::processes::{import_pointcloud, import_raster};
+use crate::auth::RoleManager;
+use crate::config::Config;
+use crate::services::{CollectionService, FeatureService, ProcessService};
+
+const USAGE: &str = "\
+Usage: spatialvault seed [--namespace <name>] [--raster <href>]
+                         [--pointcloud <href>] [--no-imports]
+
+Loads demo collections into the namespace (default: demo): countries, and
+imports of a sample COG (imagery) and COPC (pointcloud) run by the worker.
+";
+
+const DEFAULT_NAMESPACE: &str = "demo";
+
+/// Aerial imagery as a Cloud Optimized GeoTIFF, from OpenAerialMap
+const SAMPLE_RASTER: &str =
+    "https://oin-hotosm.s3.amazonaws.com/5afeda152b6a08001185f11b/0/5afeda152b6a08001185f11c.tif";
+
+/// The Autzen Stadium lidar survey as a Cloud Optimized Point Cloud
+const SAMPLE_POINTCLOUD: &str = "https://s3.amazonaws.com/hobu-lidar/autzen-classified.copc.laz";
+
+/// Coarse outlines of the Nordic countries, good enough for map examples
+const COUNTRIES: &str = include_str!("seed/countries.geojson");
+
+/// Run the seed command; `args` follow `seed` on the command line
+pub async fn run(args: &[String]) -> anyhow::Result<()> {
+    if args
+        .iter()
+        .any(|arg| matches!(arg.as_str(), "help" | "--help" | "-h"))
+    {
+        print!("{}", USAGE);
+        return Ok(());
+    }
+    let options = Options::parse(args, &["no-imports"])?;
+    options.only(&["namespace", "raster", "pointcloud", "no-imports"])?;
+    let namespace = options.last("namespace").unwrap_or(DEFAULT_NAMESPACE);
+
+    let config = Config::load()?;
+    let db = Arc::new(connect(&config).await?);
+    if db.pending_migrations().await? > 0 {
+        bail!("The database is not up to date; run `spatialvault admin migrate` first");
+    }
+    RoleManager::new(db.pool())
+        .ensure_user_role(namespace)
+        .await?;
+
+    let collections = CollectionService::new(db.clone());
+    let countries = format!("{}:countries", namespace);
+    if collections
+        .get_collection(namespace, &countries)
+        .await?
+        .is_some()
+    {
+        eprintln!("{} exists; skipped", countries);
+    } else {
+        let count = seed_countries(&collections, &FeatureService::new(db.clone()), namespace)
+            .await
+            .with_context(|| format!("Failed to seed {}", countries))?;
+        eprintln!("Loaded {} countries", count);
+        println!("{}", countries);
+    }
+
+    if options.flag("no-imports") {
+        return Ok(());
+    }
+    let processes = ProcessService::new(db.clone());
+    let imports = [
+        (
+            import_raster::PROCESS_ID,
+            "imagery",
+            options.last("raster").unwrap_or(SAMPLE_RASTER),
+        ),
+        (
+            import_pointcloud::PROCESS_ID,
+            "pointcloud",
+            options.last("pointcloud").unwrap_or(SAMPLE_POINTCLOUD),
+        ),
+    ];
+    for (process_id, name, href) in imports {
+        let collection = format!("{}:{}", namespace, name);
+        if collections
+            .get_collection(namespace, &collection)
+            .await?
+            .is_some()
+        {
+            eprintln!("{} exists; skipped", collection);
+            continue;
+        }
+        let inputs = import_inputs(process_id, &collection, href)?;
+        let job_id = processes
+            .create_job(Uuid::new_v4(), namespace, process_id, &inputs, None)
+            .await?;
+        eprintln!("Queued {} of {} as job {}", process_id, collection, job_id);
+        println!("{}", collection);
+    }
+    eprintln!("Imports finish once a worker (`spatialvault --worker`) has run them");
+
+    Ok(())
+}
+
+/// Create the countries collection and load the outlines into it
+async fn seed_countries(
+    collections: &CollectionService,
+    features: &FeatureService,
+    namespace: &str,
+) -> anyhow::Result<u64> {
+    let canonical_name = format!("{}:countries", namespace);
+    let metadata = CollectionMetadata {
+        keywords: Some(vec!["demo".to_string(), "boundaries".to_string()]),
+        license: Some("CC0-1.0".to_string()),
+        ..Default::default()
+    };
+    collections
+        .create_collection(
+            namespace,
+            &canonical_name,
+            namespace,
+            "Countries",
+            Some("Coarse outlines of the Nordic countries, with approximate populations"),
+            "vector",
+            4326,
+            &CollectionLimits::default(),
+            &metadata,
+            &[],
+            &[],
+            &CollectionGeometry::default(),
+            None,
+        )
+        .await?;
+
+    let mut insert = features
+        .begin_bulk_insert(&canonical_name, None, None)
+        .await?;
+    insert.insert(country_features()?).await?;
+    Ok(insert.commit().await?.inserted)
+}
+
+/// (geometry, properties) pairs of the bundled countries
+fn country_features() -> anyhow::Result<Vec<(serde_json::Value, serde_json::Value)>> {
+    let collection: serde_json::Value = serde_json::from_str(COUNTRIES)?;
+    let features = collection["features"]
+        .as_array()
+        .context("Countries have no features")?;
+    Ok(features
+        .iter()
+        .map(|feature| (feature["geometry"].clone(), feature["properties"].clone()))
+        .collect())
+}
+
+/// Validated inputs of an import referencing the sample in place
+fn import_inputs(
+    process_id: &str,
+    collection: &str,
+    href: &str,
+) -> anyhow::Result<serde_json::Value> {
+    let inputs = serde_json::json!({
+        "collection": collection,
+        "data": { "href": href },
+        "title": "Demo sample",
+        "copy": false,
+    });
+    if process_id == import_raster::PROCESS_ID {
+        serde_json::from_value::<import_raster::ImportRasterInputs>(inputs.clone())?.validate()?;
+    } else {
+        serde_json::from_value::<import_pointcloud::ImportPointCloudInputs>(inputs.clone())?
+            .validate()?;
+    }
+    Ok(inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_country_features() {
+        let features = country_features().unwrap();
+        assert_eq!(features.len(), 6);
+        for (geometry, properties) in &features {
+            assert!(geometry["coordinates"].is_array());
+            assert!(properties["name"].is_string());
+        }
+    }
+
+    #[test]
+    fn test_import_inputs() {
+        let inputs =
+            import_inputs(import_raster::PROCESS_ID, "demo:imagery", SAMPLE_RASTER).unwrap();
+        assert_eq!(inputs["data"]["href"], SAMPLE_RASTER);
+        assert!(import_inputs(import_pointcloud::PROCESS_ID, "demo:pointcloud", "").is_err());
+    }
+}
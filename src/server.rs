@@ -0,0 +1,270 @@
+//! Listeners the API is served on
+//!
+//! Besides the main `host`:`port` listener, further listeners can be
+//! configured, on TCP or on Unix domain sockets, so deployments without a
+//! fronting proxy can terminate TLS themselves or expose an internal port.
+
+use anyhow::Context;
+use axum::{
+    Router,
+    extract::connect_info::Connected,
+    serve::{IncomingStream, Listener},
+};
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::{TlsAcceptor, rustls, server::TlsStream};
+
+use crate::config::{Config, TlsConfig};
+
+/// How long a client may take for the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Handshaken connections waiting to be served
+const ACCEPT_QUEUE: usize = 64;
+
+/// Peer of a connection, kept in the request extensions as
+/// `ConnectInfo<Peer>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Peer {
+    /// Client or proxy connected over TCP, with or without TLS
+    Tcp(SocketAddr),
+    /// Local process connected over a Unix domain socket
+    Unix,
+}
+
+impl Connected<IncomingStream<'_, TcpListener>> for Peer {
+    fn connect_info(stream: IncomingStream<'_, TcpListener>) -> Self {
+        Peer::Tcp(*stream.remote_addr())
+    }
+}
+
+impl Connected<IncomingStream<'_, TlsListener>> for Peer {
+    fn connect_info(stream: IncomingStream<'_, TlsListener>) -> Self {
+        Peer::Tcp(*stream.remote_addr())
+    }
+}
+
+#[cfg(unix)]
+impl Connected<IncomingStream<'_, tokio::net::UnixListener>> for Peer {
+    fn connect_info(_stream: IncomingStream<'_, tokio::net::UnixListener>) -> Self {
+        Peer::Unix
+    }
+}
+
+/// Where a listener binds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bind {
+    /// `host:port`
+    Tcp(String),
+    /// Path of a Unix domain socket
+    Unix(PathBuf),
+}
+
+impl Bind {
+    /// Parse `host:port` or `unix:/path/to/socket`
+    pub fn parse(bind: &str) -> anyhow::Result<Self> {
+        match bind.strip_prefix("unix:") {
+            Some("") => anyhow::bail!("Missing socket path in {}", bind),
+            Some(path) => Ok(Self::Unix(PathBuf::from(path))),
+            None if bind.contains(':') => Ok(Self::Tcp(bind.to_string())),
+            None => anyhow::bail!("Expected host:port or unix:<path>, got {}", bind),
+        }
+    }
+}
+
+/// Serve `app` on the main listener and all additional ones, until one of
+/// them fails
+pub async fn serve(config: &Config, app: Router) -> anyhow::Result<()> {
+    let mut listeners = vec![(
+        Bind::Tcp(format!("{}:{}", config.host, config.port)),
+        config.tls.clone(),
+    )];
+    for listener in &config.listeners {
+        listeners.push((Bind::parse(&listener.bind)?, listener.tls.clone()));
+    }
+
+    let mut servers = tokio::task::JoinSet::new();
+    for (bind, tls) in listeners {
+        let app = app.clone();
+        match (bind, tls) {
+            (Bind::Tcp(addr), None) => {
+                let listener = TcpListener::bind(&addr)
+                    .await
+                    .with_context(|| format!("Failed to bind {}", addr))?;
+                tracing::info!("Listening on {}", addr);
+                servers.spawn(async move {
+                    axum::serve(listener, app.into_make_service_with_connect_info::<Peer>()).await
+                });
+            }
+            (Bind::Tcp(addr), Some(tls)) => {
+                let acceptor = tls_acceptor(&tls)?;
+                let listener = TcpListener::bind(&addr)
+                    .await
+                    .with_context(|| format!("Failed to bind {}", addr))?;
+                tracing::info!("Listening on {} (TLS)", addr);
+                let listener = TlsListener::new(listener, acceptor)?;
+                servers.spawn(async move {
+                    axum::serve(listener, app.into_make_service_with_connect_info::<Peer>()).await
+                });
+            }
+            (Bind::Unix(path), None) => {
+                let listener = bind_unix(&path)?;
+                tracing::info!("Listening on unix:{}", path.display());
+                servers.spawn(async move {
+                    axum::serve(listener, app.into_make_service_with_connect_info::<Peer>()).await
+                });
+            }
+            (Bind::Unix(path), Some(_)) => {
+                anyhow::bail!("TLS is not supported on unix:{}", path.display())
+            }
+        }
+    }
+
+    // Servers only return on failure
+    if let Some(result) = servers.join_next().await {
+        result??;
+    }
+    Ok(())
+}
+
+/// Bind a Unix socket, replacing the socket file of an earlier run
+#[cfg(unix)]
+fn bind_unix(path: &std::path::Path) -> anyhow::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path)
+        && metadata.file_type().is_socket()
+    {
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    }
+    tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("Failed to bind unix:{}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn bind_unix(path: &std::path::Path) -> anyhow::Result<TcpListener> {
+    anyhow::bail!(
+        "Unix sockets are not supported on this platform: {}",
+        path.display()
+    )
+}
+
+/// TLS acceptor of the configured certificate chain and key
+fn tls_acceptor(tls: &TlsConfig) -> anyhow::Result<TlsAcceptor> {
+    let certs = rustls_pemfile::certs(&mut io::BufReader::new(
+        std::fs::File::open(&tls.cert_path)
+            .with_context(|| format!("Failed to open certificate {}", tls.cert_path))?,
+    ))
+    .collect::<Result<Vec<_>, _>>()
+    .with_context(|| format!("Invalid certificate {}", tls.cert_path))?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificate in {}", tls.cert_path);
+    }
+    let key = rustls_pemfile::private_key(&mut io::BufReader::new(
+        std::fs::File::open(&tls.key_path)
+            .with_context(|| format!("Failed to open private key {}", tls.key_path))?,
+    ))
+    .with_context(|| format!("Invalid private key {}", tls.key_path))?
+    .with_context(|| format!("No private key in {}", tls.key_path))?;
+
+    let mut server_config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)
+    .context("Certificate and private key don't match")?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+/// TCP listener completing TLS handshakes
+///
+/// Handshakes run in their own tasks, so a slow client doesn't hold up the
+/// others; the server is handed the connections once they are encrypted.
+struct TlsListener {
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    fn new(listener: TcpListener, acceptor: TlsAcceptor) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (sender, connections) = mpsc::channel(ACCEPT_QUEUE);
+        tokio::spawn(async move {
+            loop {
+                let (stream, addr) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        // Usually out of file descriptors; give others a chance to close
+                        tracing::warn!("Failed to accept connection: {}", e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+                let acceptor = acceptor.clone();
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = sender.send((stream, addr)).await;
+                        }
+                        Ok(Err(e)) => tracing::debug!(%addr, "TLS handshake failed: {}", e),
+                        Err(_) => tracing::debug!(%addr, "TLS handshake timed out"),
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            connections,
+            local_addr,
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // The accept loop never ends while the listener exists
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bind() {
+        assert_eq!(
+            Bind::parse("127.0.0.1:9090").unwrap(),
+            Bind::Tcp("127.0.0.1:9090".to_string())
+        );
+        assert_eq!(
+            Bind::parse("[::1]:9090").unwrap(),
+            Bind::Tcp("[::1]:9090".to_string())
+        );
+        assert_eq!(
+            Bind::parse("unix:/run/spatialvault.sock").unwrap(),
+            Bind::Unix(PathBuf::from("/run/spatialvault.sock"))
+        );
+        assert!(Bind::parse("unix:").is_err());
+        assert!(Bind::parse("localhost").is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::db::Database;
+use crate::error::AppResult;
+
+/// Tile requests and bytes served in one period
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UsageCounts {
+    pub requests: i64,
+    pub bytes: i64,
+}
+
+/// Usage of a collection's tiles in one period
+#[derive(Debug, sqlx::FromRow)]
+pub struct TileUsagePeriod {
+    pub period_start: DateTime<Utc>,
+    pub requests: i64,
+    pub bytes: i64,
+}
+
+/// Granularity of usage reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageInterval {
+    Hour,
+    Day,
+}
+
+impl UsageInterval {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UsageInterval::Hour => "hour",
+            UsageInterval::Day => "day",
+        }
+    }
+}
+
+type UsageBuffer = HashMap<(Uuid, DateTime<Utc>), UsageCounts>;
+
+/// Counts tile requests per collection and hour
+///
+/// Requests are counted in memory so serving a tile never waits for the
+/// database; the counts are added to the `tile_usage` table when flushed.
+pub struct AnalyticsService {
+    db: Arc<Database>,
+    buffer: Mutex<UsageBuffer>,
+}
+
+/// Start of the hour `time` falls in
+fn period_start(time: DateTime<Utc>) -> DateTime<Utc> {
+    time.duration_trunc(TimeDelta::hours(1)).unwrap_or(time)
+}
+
+/// Add `counts` to the counts of `key`
+fn add_counts(buffer: &mut UsageBuffer, key: (Uuid, DateTime<Utc>), counts: UsageCounts) {
+    let entry = buffer.entry(key).or_default();
+    entry.requests += counts.requests;
+    entry.bytes += counts.bytes;
+}
+
+impl AnalyticsService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self {
+            db,
+            buffer: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count a tile of `bytes` served from a collection
+    pub fn record_tile(&self, collection_id: Uuid, bytes: usize) {
+        let counts = UsageCounts {
+            requests: 1,
+            bytes: bytes as i64,
+        };
+        let mut buffer = self.buffer.lock().unwrap_or_else(|e| e.into_inner());
+        add_counts(
+            &mut buffer,
+            (collection_id, period_start(Utc::now())),
+            counts,
+        );
+    }
+
+    /// Write the buffered counts to the database
+    ///
+    /// Counts that could not be written are kept for the next flush.
+    pub async fn flush(&self) -> AppResult<()> {
+        let pending = std::mem::take(&mut *self.buffer.lock().unwrap_or_else(|e| e.into_inner()));
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut collection_ids = Vec::with_capacity(pending.len());
+        let mut periods = Vec::with_capacity(pending.len());
+        let mut requests = Vec::with_capacity(pending.len());
+        let mut bytes = Vec::with_capacity(pending.len());
+        for ((collection_id, period), counts) in &pending {
+            collection_ids.push(*collection_id);
+            periods.push(*period);
+            requests.push(counts.requests);
+            bytes.push(counts.bytes);
+        }
+
+        // Counts of collections deleted since the tile was served are dropped
+        let result = sqlx::query(
+            r#"
+            INSERT INTO spatialvault.tile_usage (collection_id, period_start, requests, bytes)
+            SELECT u.collection_id, u.period_start, u.requests, u.bytes
+            FROM UNNEST($1::uuid[], $2::timestamptz[], $3::bigint[], $4::bigint[])
+                AS u(collection_id, period_start, requests, bytes)
+            WHERE EXISTS (
+                SELECT 1 FROM spatialvault.collections c WHERE c.id = u.collection_id
+            )
+            ON CONFLICT (collection_id, period_start) DO UPDATE SET
+                requests = tile_usage.requests + EXCLUDED.requests,
+                bytes = tile_usage.bytes + EXCLUDED.bytes
+            "#,
+        )
+        .bind(&collection_ids)
+        .bind(&periods)
+        .bind(&requests)
+        .bind(&bytes)
+        .execute(self.db.pool())
+        .await;
+
+        if let Err(e) = result {
+            let mut buffer = self.buffer.lock().unwrap_or_else(|e| e.into_inner());
+            for (key, counts) in pending {
+                add_counts(&mut buffer, key, counts);
+            }
+            return Err(e.into());
+        }
+
+        Ok(())
+    }
+
+    /// Flush the buffered counts every `interval`, for as long as the server
+    /// runs
+    pub async fn run(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.flush().await {
+                tracing::warn!("Failed to flush tile usage: {}", e);
+            }
+        }
+    }
+
+    /// Tile usage of a collection per `interval`, oldest first
+    pub async fn get_tile_usage(
+        &self,
+        collection_id: Uuid,
+        interval: UsageInterval,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> AppResult<Vec<TileUsagePeriod>> {
+        let usage = sqlx::query_as(
+            r#"
+            SELECT date_trunc($2, period_start, 'UTC') AS period_start,
+                   SUM(requests)::bigint AS requests,
+                   SUM(bytes)::bigint AS bytes
+            FROM spatialvault.tile_usage
+            WHERE collection_id = $1
+              AND ($3::timestamptz IS NULL OR period_start >= date_trunc($2, $3::timestamptz, 'UTC'))
+              AND ($4::timestamptz IS NULL OR period_start <= $4)
+            GROUP BY 1
+            ORDER BY 1
+            "#,
+        )
+        .bind(collection_id)
+        .bind(interval.as_str())
+        .bind(start)
+        .bind(end)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(usage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_period_start() {
+        let time = DateTime::parse_from_rfc3339("2024-05-01T13:45:12.5Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(period_start(time).to_rfc3339(), "2024-05-01T13:00:00+00:00");
+    }
+
+    #[test]
+    fn test_add_counts() {
+        let key = (Uuid::nil(), period_start(Utc::now()));
+        let mut buffer = UsageBuffer::new();
+        add_counts(
+            &mut buffer,
+            key,
+            UsageCounts {
+                requests: 1,
+                bytes: 100,
+            },
+        );
+        add_counts(
+            &mut buffer,
+            key,
+            UsageCounts {
+                requests: 2,
+                bytes: 50,
+            },
+        );
+        assert_eq!(
+            buffer[&key],
+            UsageCounts {
+                requests: 3,
+                bytes: 150
+            }
+        );
+    }
+}
@@ -0,0 +1,469 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::config::{CdnConfig, CdnProvider};
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+
+/// Most collections purged in one round
+const PURGE_BATCH_SIZE: i64 = 100;
+
+/// Host of the CloudFront API
+const CLOUDFRONT_HOST: &str = "cloudfront.amazonaws.com";
+
+/// Wildcard paths CloudFront allows in progress at once
+const CLOUDFRONT_WILDCARD_LIMIT: usize = 15;
+
+/// Surrogate key tile responses of a collection are tagged with, so a CDN
+/// can purge all of them at once
+pub fn surrogate_key(collection_id: Uuid) -> String {
+    format!("collection-{}", collection_id)
+}
+
+/// A collection whose version changed since its tiles were last purged
+#[derive(Debug, sqlx::FromRow)]
+struct PendingPurge {
+    id: Uuid,
+    canonical_name: String,
+    /// Version the tiles were purged at before this round
+    previous_version: i64,
+    /// Version this round purges up to
+    version: i64,
+}
+
+/// Paths of a collection's tiles, relative to the service URL
+fn tile_paths(canonical_name: &str) -> [String; 2] {
+    [
+        format!("/collections/{}/tiles/", canonical_name),
+        format!("/signed/collections/{}/tiles/", canonical_name),
+    ]
+}
+
+/// Purges cached tiles of changed collections from a CDN
+///
+/// Collections remember the version their tiles were last purged at, so
+/// every server may run the purge loop and each change is purged once.
+pub struct CdnService {
+    db: Arc<Database>,
+    config: CdnConfig,
+    public_url: String,
+    client: reqwest::Client,
+}
+
+impl CdnService {
+    /// Purger for the configured CDN, if any
+    pub fn new(db: Arc<Database>, config: &CdnConfig, base_url: &str) -> Option<Self> {
+        config.provider?;
+        Some(Self {
+            db,
+            public_url: config
+                .public_url
+                .clone()
+                .unwrap_or_else(|| base_url.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            config: config.clone(),
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// Purge every `interval`, for as long as the server runs
+    pub async fn run(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.purge_changed().await {
+                tracing::warn!("Failed to purge CDN: {}", e);
+            }
+        }
+    }
+
+    /// Purge the tiles of collections changed since their last purge
+    ///
+    /// The collections are claimed before the CDN is called so other servers
+    /// skip them; if the purge fails they are released for the next round.
+    pub async fn purge_changed(&self) -> AppResult<()> {
+        let pending: Vec<PendingPurge> = sqlx::query_as(
+            r#"
+            WITH pending AS (
+                SELECT id, cdn_purged_version AS previous_version
+                FROM spatialvault.collections
+                WHERE version > cdn_purged_version
+                ORDER BY updated_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE spatialvault.collections c
+            SET cdn_purged_version = c.version
+            FROM pending
+            WHERE c.id = pending.id
+            RETURNING c.id, c.canonical_name, pending.previous_version, c.version
+            "#,
+        )
+        .bind(self.batch_size())
+        .fetch_all(self.db.pool())
+        .await?;
+
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        if let Err(e) = self.purge(&pending).await {
+            let ids: Vec<Uuid> = pending.iter().map(|p| p.id).collect();
+            let previous: Vec<i64> = pending.iter().map(|p| p.previous_version).collect();
+            let claimed: Vec<i64> = pending.iter().map(|p| p.version).collect();
+            sqlx::query(
+                r#"
+                UPDATE spatialvault.collections c
+                SET cdn_purged_version = u.previous_version
+                FROM UNNEST($1::uuid[], $2::bigint[], $3::bigint[])
+                    AS u(id, previous_version, claimed_version)
+                WHERE c.id = u.id AND c.cdn_purged_version = u.claimed_version
+                "#,
+            )
+            .bind(&ids)
+            .bind(&previous)
+            .bind(&claimed)
+            .execute(self.db.pool())
+            .await?;
+            return Err(e);
+        }
+
+        tracing::debug!("Purged CDN tiles of {} collections", pending.len());
+        Ok(())
+    }
+
+    /// Collections purged in one round; a CloudFront invalidation has a
+    /// wildcard path per tile path of each collection, and CloudFront refuses
+    /// invalidations past its limit of wildcards in progress
+    fn batch_size(&self) -> i64 {
+        match self.config.provider {
+            Some(CdnProvider::Cloudfront) => {
+                (CLOUDFRONT_WILDCARD_LIMIT / tile_paths("").len()) as i64
+            }
+            _ => PURGE_BATCH_SIZE,
+        }
+    }
+
+    async fn purge(&self, pending: &[PendingPurge]) -> AppResult<()> {
+        let target_id = self
+            .config
+            .target_id
+            .as_deref()
+            .ok_or_else(|| AppError::Internal("cdn.target_id is not set".to_string()))?;
+
+        let request = match self.config.provider {
+            Some(CdnProvider::Fastly) => {
+                let keys: Vec<String> = pending.iter().map(|p| surrogate_key(p.id)).collect();
+                self.client
+                    .post(format!(
+                        "https://api.fastly.com/service/{}/purge",
+                        target_id
+                    ))
+                    .header("Fastly-Key", self.api_token()?)
+                    .json(&serde_json::json!({ "surrogate_keys": keys }))
+            }
+            Some(CdnProvider::Cloudflare) => {
+                let host = self
+                    .public_url
+                    .split_once("://")
+                    .map_or(self.public_url.as_str(), |(_, rest)| rest);
+                let prefixes: Vec<String> = pending
+                    .iter()
+                    .flat_map(|p| tile_paths(&p.canonical_name))
+                    .map(|path| format!("{}{}", host, path))
+                    .collect();
+                self.client
+                    .post(format!(
+                        "https://api.cloudflare.com/client/v4/zones/{}/purge_cache",
+                        target_id
+                    ))
+                    .bearer_auth(self.api_token()?)
+                    .json(&serde_json::json!({ "prefixes": prefixes }))
+            }
+            Some(CdnProvider::Cloudfront) => self.cloudfront_invalidation(target_id, pending)?,
+            None => return Ok(()),
+        };
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AppError::Internal(format!("CDN purge request failed: {}", e)))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::Internal(format!(
+                "CDN purge failed with {}: {}",
+                status, body
+            )));
+        }
+        Ok(())
+    }
+
+    fn api_token(&self) -> AppResult<&str> {
+        self.config
+            .api_token
+            .as_deref()
+            .ok_or_else(|| AppError::Internal("cdn.api_token is not set".to_string()))
+    }
+
+    /// CloudFront invalidation of the collections' tile paths, signed with
+    /// AWS Signature Version 4
+    fn cloudfront_invalidation(
+        &self,
+        distribution_id: &str,
+        pending: &[PendingPurge],
+    ) -> AppResult<reqwest::RequestBuilder> {
+        let (Some(access_key_id), Some(secret_access_key)) = (
+            self.config.access_key_id.as_deref(),
+            self.config.secret_access_key.as_deref(),
+        ) else {
+            return Err(AppError::Internal(
+                "cdn.access_key_id and cdn.secret_access_key are required for CloudFront"
+                    .to_string(),
+            ));
+        };
+
+        // Invalidation paths are relative to the distribution's origin
+        let base_path = self
+            .public_url
+            .split_once("://")
+            .and_then(|(_, rest)| rest.find('/').map(|i| &rest[i..]))
+            .unwrap_or("");
+        let paths: Vec<String> = pending
+            .iter()
+            .flat_map(|p| tile_paths(&p.canonical_name))
+            .map(|path| format!("{}{}*", base_path, path))
+            .collect();
+        let caller_reference = pending
+            .iter()
+            .map(|p| format!("{}@{}", p.id, p.version))
+            .collect::<Vec<_>>()
+            .join(",");
+        let body = invalidation_batch(&paths, &caller_reference);
+
+        let path = format!("/2020-05-31/distribution/{}/invalidation", distribution_id);
+        let authorization =
+            sigv4_authorization(access_key_id, secret_access_key, &path, &body, Utc::now());
+
+        Ok(self
+            .client
+            .post(format!("https://{}{}", CLOUDFRONT_HOST, path))
+            .header(reqwest::header::CONTENT_TYPE, "application/xml")
+            .header("x-amz-date", authorization.amz_date)
+            .header(reqwest::header::AUTHORIZATION, authorization.header)
+            .body(body))
+    }
+}
+
+/// Body of a CloudFront CreateInvalidation request
+fn invalidation_batch(paths: &[String], caller_reference: &str) -> String {
+    let items: String = paths
+        .iter()
+        .map(|path| format!("<Path>{}</Path>", xml_escape(path)))
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <InvalidationBatch xmlns=\"http://cloudfront.amazonaws.com/doc/2020-05-31/\">\
+         <Paths><Quantity>{}</Quantity><Items>{}</Items></Paths>\
+         <CallerReference>{}</CallerReference></InvalidationBatch>",
+        paths.len(),
+        items,
+        xml_escape(caller_reference)
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+struct SignedRequest {
+    amz_date: String,
+    header: String,
+}
+
+/// Request to sign with AWS Signature Version 4
+struct SigV4Request<'a> {
+    method: &'a str,
+    path: &'a str,
+    /// Canonical query string, with sorted and encoded parameters
+    query: &'a str,
+    /// Lowercase names and values of the headers to sign, apart from
+    /// x-amz-date
+    headers: &'a [(&'a str, &'a str)],
+    body: &'a [u8],
+}
+
+/// Authorization of a CloudFront POST of an XML `body` to `path`
+fn sigv4_authorization(
+    access_key_id: &str,
+    secret_access_key: &str,
+    path: &str,
+    body: &str,
+    now: DateTime<Utc>,
+) -> SignedRequest {
+    // CloudFront is a global service signed in us-east-1
+    sigv4_sign(
+        access_key_id,
+        secret_access_key,
+        "us-east-1",
+        "cloudfront",
+        &SigV4Request {
+            method: "POST",
+            path,
+            query: "",
+            headers: &[
+                ("content-type", "application/xml"),
+                ("host", CLOUDFRONT_HOST),
+            ],
+            body: body.as_bytes(),
+        },
+        now,
+    )
+}
+
+/// Sign a request to an AWS service in a region
+fn sigv4_sign(
+    access_key_id: &str,
+    secret_access_key: &str,
+    region: &str,
+    service: &str,
+    request: &SigV4Request,
+    now: DateTime<Utc>,
+) -> SignedRequest {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+
+    let mut headers: Vec<(&str, &str)> = request.headers.to_vec();
+    headers.push(("x-amz-date", &amz_date));
+    headers.sort_unstable();
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        request.method,
+        request.path,
+        request.query,
+        canonical_headers,
+        signed_headers,
+        hex(&Sha256::digest(request.body))
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = [date.as_str(), region, service, "aws4_request"]
+        .iter()
+        .fold(
+            format!("AWS4{}", secret_access_key).into_bytes(),
+            |key, part| hmac_sha256(&key, part),
+        );
+    let signature = hex(&hmac_sha256(&key, &string_to_sign));
+
+    SignedRequest {
+        header: format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            access_key_id, scope, signed_headers, signature
+        ),
+        amz_date,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalidation_batch() {
+        let paths: Vec<String> = tile_paths("alice:roads")
+            .iter()
+            .map(|path| format!("{}*", path))
+            .collect();
+        let body = invalidation_batch(&paths, "a&b");
+        assert!(body.contains("<Quantity>2</Quantity>"));
+        assert!(body.contains("<Path>/collections/alice:roads/tiles/*</Path>"));
+        assert!(body.contains("<Path>/signed/collections/alice:roads/tiles/*</Path>"));
+        assert!(body.contains("<CallerReference>a&amp;b</CallerReference>"));
+    }
+
+    #[test]
+    fn test_sigv4_authorization() {
+        let now = DateTime::parse_from_rfc3339("2024-05-01T13:45:12Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let signed = sigv4_authorization("AKID", "secret", "/2020-05-31/x", "<x/>", now);
+        assert_eq!(signed.amz_date, "20240501T134512Z");
+        assert!(signed.header.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKID/20240501/us-east-1/cloudfront/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, Signature="
+        ));
+
+        // The signature covers the body
+        let other = sigv4_authorization("AKID", "secret", "/2020-05-31/x", "<y/>", now);
+        assert_ne!(signed.header, other.header);
+    }
+
+    #[test]
+    fn test_sigv4_sign() {
+        // Example request of the AWS Signature Version 4 documentation
+        let now = DateTime::parse_from_rfc3339("2015-08-30T12:36:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let signed = sigv4_sign(
+            "AKIDEXAMPLE",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "us-east-1",
+            "iam",
+            &SigV4Request {
+                method: "GET",
+                path: "/",
+                query: "Action=ListUsers&Version=2010-05-08",
+                headers: &[
+                    (
+                        "content-type",
+                        "application/x-www-form-urlencoded; charset=utf-8",
+                    ),
+                    ("host", "iam.amazonaws.com"),
+                ],
+                body: b"",
+            },
+            now,
+        );
+        assert_eq!(signed.amz_date, "20150830T123600Z");
+        assert_eq!(
+            signed.header,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, \
+             Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+    }
+}
@@ -1,58 +1,300 @@
+use bytes::Bytes;
+use futures::TryStreamExt;
+use sqlx::Postgres;
+use sqlx::postgres::PgArguments;
+use sqlx::query::QueryAs;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
 use uuid::Uuid;
 
-use crate::api::collections::schemas::CollectionSchema;
+use crate::api::collections::computed::{ComputedProperty, computed_properties_sql};
+use crate::api::collections::relations::CollectionRelation;
+use crate::api::collections::schemas::{
+    AssetObject, CollectionFacets, CollectionFilter, CollectionGeometry, CollectionLimits,
+    CollectionLimitsPatch, CollectionMetadata, CollectionSchema, GeometryType, ProcessingDefaults,
+};
 use crate::api::collections::sharing::{PermissionLevel, ShareEntry};
 use crate::api::common::{Bbox, Extent, SpatialExtent, TemporalExtent};
+use crate::api::read_only;
+use crate::api::tiles::properties::TilePropertyRule;
 use crate::auth::{RoleManager, is_valid_role_name, quote_ident};
-use crate::db::{Collection, CollectionWithCrs, Database};
+use crate::db::{
+    BrokenAsset, Collection, CollectionAsset, CollectionEvent, CollectionReplication,
+    CollectionSnapshot, CollectionWebhook, CollectionWithCrs, Database, QueryClass, finish_write,
+};
 use crate::error::{AppError, AppResult};
+use crate::processing::backup::{
+    self, BackupAsset, BackupCollection, BackupCounts, BackupFeature, BackupHeader, BackupItem,
+    BackupReader, BackupRecord, BackupWriter,
+};
+use crate::services::feature_service::scan_property_types;
+use crate::services::notification_service::{NotificationType, queue_notification};
+use crate::services::webhook_service::{CollectionEventType, record_collection_event};
+
+/// Schema holding the copies of feature tables taken by snapshots
+const SNAPSHOT_SCHEMA: &str = "spatialvault_snapshots";
+
+/// Features inserted per statement when restoring a backup
+const RESTORE_BATCH_SIZE: usize = 1000;
+
+/// Stale collection extents recomputed per round
+const EXTENT_REFRESH_BATCH: i64 = 100;
+
+/// A feature row read for backup
+type BackupFeatureRow = (
+    Uuid,
+    Option<serde_json::Value>,
+    Option<serde_json::Value>,
+    i64,
+    Option<chrono::DateTime<chrono::Utc>>,
+    Option<chrono::DateTime<chrono::Utc>>,
+);
+
+/// An item row read for backup, with its assets
+type BackupItemRow = (
+    Uuid,
+    serde_json::Value,
+    Option<chrono::DateTime<chrono::Utc>>,
+    Option<serde_json::Value>,
+    sqlx::types::Json<Vec<BackupAsset>>,
+);
+
+/// Collections accessible to `$1` that match a [`CollectionFilter`] bound with
+/// [`bind_collection_filter`]
+const COLLECTION_FILTER_SQL: &str = r#"
+    (c.owner = $1
+     OR pg_catalog.has_table_privilege($1, c.schema_name || '.' || c.table_name, 'SELECT'))
+    AND ($2::text IS NULL
+         OR c.title ILIKE $2
+         OR c.description ILIKE $2
+         OR EXISTS (SELECT 1 FROM unnest(c.keywords) AS k WHERE k ILIKE $2))
+    AND ($3::text[] IS NULL OR c.keywords @> $3)
+    AND ($4::text IS NULL OR c.collection_type = $4)
+    AND ($5::text IS NULL OR c.owner = $5)
+    AND (($6::float8[] IS NULL AND $7::timestamptz IS NULL AND $8::timestamptz IS NULL)
+         OR EXISTS (
+             SELECT 1 FROM spatialvault.collection_extent_cache e
+             WHERE e.collection_id = c.id
+               AND ($6::float8[] IS NULL
+                    OR ST_Intersects(e.bbox, ST_MakeEnvelope($6[1], $6[2], $6[3], $6[4], 4326)))
+               AND ($7::timestamptz IS NULL OR e.end_time >= $7)
+               AND ($8::timestamptz IS NULL OR e.start_time <= $8)))
+"#;
+
+fn bind_collection_filter<'q, O>(
+    query: QueryAs<'q, Postgres, O, PgArguments>,
+    username: &'q str,
+    filter: &'q CollectionFilter,
+) -> QueryAs<'q, Postgres, O, PgArguments> {
+    query
+        .bind(username)
+        .bind(filter.pattern.as_deref())
+        .bind(filter.keywords.as_deref())
+        .bind(filter.collection_type.as_deref())
+        .bind(filter.owner.as_deref())
+        .bind(filter.bbox.as_deref())
+        .bind(filter.start)
+        .bind(filter.end)
+}
 
 pub struct CollectionService {
     db: Arc<Database>,
+    /// Roll writes back instead of committing them
+    validate_only: bool,
 }
 
 impl CollectionService {
     pub fn new(db: Arc<Database>) -> Self {
-        Self { db }
+        Self {
+            db,
+            validate_only: false,
+        }
+    }
+
+    /// A service that validates writes without committing them
+    pub fn validate_only(&self) -> Self {
+        Self {
+            db: self.db.clone(),
+            validate_only: true,
+        }
     }
 
+    #[tracing::instrument(skip(self, username, filter))]
     pub async fn list_collections(
         &self,
         username: &str,
+        filter: &CollectionFilter,
         limit: u32,
         offset: u32,
-    ) -> AppResult<Vec<CollectionWithCrs>> {
+    ) -> AppResult<(Vec<CollectionWithCrs>, i64)> {
         // List collections accessible to this user with storage CRS included
         // This includes owned collections and shared collections
-        let collections: Vec<CollectionWithCrs> = sqlx::query_as(
+        let sql = format!(
             r#"
             SELECT c.*,
-                COALESCE(
-                    (SELECT srid FROM geometry_columns 
-                     WHERE f_table_schema = c.schema_name 
-                     AND f_table_name = c.table_name 
-                     AND f_geometry_column = 'geometry'
-                     LIMIT 1),
-                    4326
-                ) as storage_crs
+                COALESCE(gc.srid, 4326) as storage_crs,
+                gc.type as geometry_type,
+                gc.coord_dimension as geometry_dimension
             FROM spatialvault.collections c
-            WHERE c.owner = $1
-               OR EXISTS (
-                   SELECT 1 FROM pg_catalog.has_table_privilege($1, c.schema_name || '.' || c.table_name, 'SELECT')
-                   WHERE pg_catalog.has_table_privilege($1, c.schema_name || '.' || c.table_name, 'SELECT')
-               )
+            LEFT JOIN LATERAL (
+                SELECT srid, type, coord_dimension FROM geometry_columns
+                WHERE f_table_schema = c.schema_name
+                AND f_table_name = c.table_name
+                AND f_geometry_column = 'geometry'
+                LIMIT 1
+            ) gc ON true
+            WHERE {}
             ORDER BY c.created_at DESC
-            LIMIT $2 OFFSET $3
+            LIMIT $9 OFFSET $10
+            "#,
+            COLLECTION_FILTER_SQL
+        );
+        let collections: Vec<CollectionWithCrs> =
+            bind_collection_filter(sqlx::query_as(&sql), username, filter)
+                .bind(limit as i64)
+                .bind(offset as i64)
+                .fetch_all(self.db.pool())
+                .await?;
+
+        let count_sql = format!(
+            "SELECT COUNT(*) FROM spatialvault.collections c WHERE {}",
+            COLLECTION_FILTER_SQL
+        );
+        let (total,): (i64,) = bind_collection_filter(sqlx::query_as(&count_sql), username, filter)
+            .fetch_one(self.db.pool())
+            .await?;
+
+        Ok((collections, total))
+    }
+
+    /// Count the collections matching a filter per keyword and per type
+    pub async fn collection_facets(
+        &self,
+        username: &str,
+        filter: &CollectionFilter,
+    ) -> AppResult<CollectionFacets> {
+        let sql = format!(
+            r#"
+            WITH matched AS (
+                SELECT c.collection_type, c.keywords
+                FROM spatialvault.collections c
+                WHERE {}
+            )
+            SELECT 'keyword', keyword, COUNT(*) FROM matched, unnest(keywords) AS keyword
+            GROUP BY keyword
+            UNION ALL
+            SELECT 'type', collection_type, COUNT(*) FROM matched
+            GROUP BY collection_type
+            "#,
+            COLLECTION_FILTER_SQL
+        );
+        let counts: Vec<(String, String, i64)> =
+            bind_collection_filter(sqlx::query_as(&sql), username, filter)
+                .fetch_all(self.db.pool())
+                .await?;
+
+        let mut facets = CollectionFacets::default();
+        for (facet, value, count) in counts {
+            let values = if facet == "keyword" {
+                &mut facets.keywords
+            } else {
+                &mut facets.collection_type
+            };
+            values.insert(value, count as u64);
+        }
+
+        Ok(facets)
+    }
+
+    /// Recompute stale cached extents periodically
+    ///
+    /// An extent is stale when it was never computed or the features or
+    /// items of the collection changed since; the triggers keeping
+    /// `data_updated_at` current see every write. Until recomputed, searches
+    /// by bbox and datetime use the previous extent.
+    pub async fn run(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if read_only::paused(&self.db).await {
+                continue;
+            }
+            if let Err(e) = self.refresh_stale_extents().await {
+                tracing::warn!("Failed to refresh collection extents: {}", e);
+            }
+        }
+    }
+
+    /// Recompute a batch of stale cached extents, returning how many were
+    /// refreshed
+    ///
+    /// The extents are kept in `collection_extent_cache`, apart from the
+    /// collections. They are claimed by moving `computed_at` to the claim
+    /// time first, so other servers skip them and writes made while
+    /// computing leave the extent stale for the next round.
+    pub async fn refresh_stale_extents(&self) -> AppResult<usize> {
+        // New collections have yet to be computed
+        sqlx::query(
+            r#"
+            INSERT INTO spatialvault.collection_extent_cache (collection_id)
+            SELECT id FROM spatialvault.collections c
+            WHERE NOT EXISTS (
+                SELECT 1 FROM spatialvault.collection_extent_cache e WHERE e.collection_id = c.id
+            )
+            ON CONFLICT (collection_id) DO NOTHING
             "#,
         )
-        .bind(username)
-        .bind(limit as i64)
-        .bind(offset as i64)
+        .execute(self.db.pool())
+        .await?;
+
+        let stale: Vec<Collection> = sqlx::query_as(
+            r#"
+            WITH stale AS (
+                SELECT e.collection_id
+                FROM spatialvault.collection_extent_cache e
+                JOIN spatialvault.collections c ON c.id = e.collection_id
+                WHERE e.computed_at IS NULL OR e.computed_at < c.data_updated_at
+                ORDER BY e.computed_at NULLS FIRST
+                LIMIT $1
+                FOR UPDATE OF e SKIP LOCKED
+            ),
+            claimed AS (
+                UPDATE spatialvault.collection_extent_cache e
+                SET computed_at = NOW()
+                FROM stale
+                WHERE e.collection_id = stale.collection_id
+                RETURNING e.collection_id
+            )
+            SELECT c.* FROM spatialvault.collections c
+            JOIN claimed ON claimed.collection_id = c.id
+            "#,
+        )
+        .bind(EXTENT_REFRESH_BATCH)
         .fetch_all(self.db.pool())
         .await?;
 
-        Ok(collections)
+        for collection in &stale {
+            if let Err(e) = self.refresh_extent(collection).await {
+                tracing::warn!(
+                    "Failed to compute the extent of {}: {}",
+                    collection.canonical_name,
+                    e
+                );
+                sqlx::query(
+                    r#"
+                    UPDATE spatialvault.collection_extent_cache SET computed_at = NULL
+                    WHERE collection_id = $1
+                    "#,
+                )
+                .bind(collection.id)
+                .execute(self.db.pool())
+                .await?;
+            }
+        }
+
+        Ok(stale.len())
     }
 
     pub async fn get_collection(
@@ -63,230 +305,1935 @@ impl CollectionService {
         let collection: Option<CollectionWithCrs> = sqlx::query_as(
             r#"
             SELECT c.*,
-                COALESCE(
-                    (SELECT srid FROM geometry_columns 
-                     WHERE f_table_schema = c.schema_name 
-                     AND f_table_name = c.table_name 
-                     AND f_geometry_column = 'geometry'
-                     LIMIT 1),
-                    4326
-                ) as storage_crs
+                COALESCE(gc.srid, 4326) as storage_crs,
+                gc.type as geometry_type,
+                gc.coord_dimension as geometry_dimension
             FROM spatialvault.collections c
+            LEFT JOIN LATERAL (
+                SELECT srid, type, coord_dimension FROM geometry_columns
+                WHERE f_table_schema = c.schema_name
+                AND f_table_name = c.table_name
+                AND f_geometry_column = 'geometry'
+                LIMIT 1
+            ) gc ON true
             WHERE canonical_name = $1
             "#,
         )
         .bind(collection_id)
-        .fetch_optional(self.db.pool())
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(collection)
+    }
+
+    /// Get a collection the user can read, treating one they can't read as
+    /// missing so its existence doesn't leak
+    pub async fn get_readable_collection(
+        &self,
+        username: &str,
+        collection_id: &str,
+    ) -> AppResult<Option<CollectionWithCrs>> {
+        let Some(collection) = self.get_collection(username, collection_id).await? else {
+            return Ok(None);
+        };
+        if !self.can_read(username, &collection.as_collection()).await? {
+            return Ok(None);
+        }
+        Ok(Some(collection))
+    }
+
+    pub async fn get_alias(&self, name: &str) -> AppResult<Option<String>> {
+        let alias: Option<(String,)> = sqlx::query_as(
+            "SELECT new_name FROM spatialvault.collection_aliases WHERE old_name = $1",
+        )
+        .bind(name)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(alias.map(|(new_name,)| new_name))
+    }
+
+    /// Whether `username` owns the collection or it is shared with them
+    pub async fn can_read(&self, username: &str, collection: &Collection) -> AppResult<bool> {
+        if collection.owner == username {
+            return Ok(true);
+        }
+        // Raster and point cloud collections have no feature table to grant
+        let can_read: bool = sqlx::query_scalar(
+            r#"
+            SELECT CASE
+                WHEN to_regrole(quote_ident($1)) IS NULL
+                     OR to_regclass(format('%I.%I', $2::text, $3::text)) IS NULL THEN false
+                ELSE pg_catalog.has_table_privilege($1, format('%I.%I', $2::text, $3::text), 'SELECT')
+            END
+            "#,
+        )
+        .bind(username)
+        .bind(&collection.schema_name)
+        .bind(&collection.table_name)
+        .fetch_one(self.db.pool())
+        .await?;
+        Ok(can_read)
+    }
+
+    /// Check if collection_id is an alias that should redirect.
+    /// Returns Some(new_name) if:
+    /// 1. There is NO currently active collection with the exact name
+    /// 2. AND there is an alias mapping from this name to another name
+    /// Otherwise returns None.
+    pub async fn check_alias_redirect(&self, collection_id: &str) -> AppResult<Option<String>> {
+        // First check if there's an active collection with this exact name
+        let active_collection = self.get_collection("", collection_id).await?;
+
+        if active_collection.is_some() {
+            // There is an active collection with this name, no redirect
+            return Ok(None);
+        }
+
+        // No active collection, check for alias
+        self.get_alias(collection_id).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_collection(
+        &self,
+        username: &str,
+        canonical_name: &str,
+        owner: &str,
+        title: &str,
+        description: Option<&str>,
+        collection_type: &str,
+        crs: i32,
+        limits: &CollectionLimits,
+        metadata: &CollectionMetadata,
+        unique_properties: &[String],
+        geometry_columns: &[String],
+        geometry: &CollectionGeometry,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> AppResult<Collection> {
+        geometry.validate(collection_type)?;
+
+        // Ensure user role exists
+        let role_manager = RoleManager::new(self.db.pool());
+        role_manager.ensure_user_role(owner).await?;
+
+        // Parse canonical name to get schema and table name
+        let parts: Vec<&str> = canonical_name.split(':').collect();
+        let schema_name = parts
+            .first()
+            .ok_or_else(|| AppError::BadRequest("Invalid collection name".to_string()))?;
+        let table_name = parts[1..].join("_");
+
+        if table_name.is_empty() {
+            return Err(AppError::BadRequest(
+                "Collection name must have at least two segments".to_string(),
+            ));
+        }
+
+        // Validate schema and table names to prevent SQL injection
+        if !is_valid_role_name(schema_name) {
+            return Err(AppError::BadRequest(format!(
+                "Invalid schema name: {}",
+                schema_name
+            )));
+        }
+        if !is_valid_role_name(&table_name) {
+            return Err(AppError::BadRequest(format!(
+                "Invalid table name: {}",
+                table_name
+            )));
+        }
+
+        let id = Uuid::new_v4();
+        let is_vector = collection_type == "vector";
+
+        // Start transaction
+        let mut tx = self.db.pool().begin().await?;
+
+        // Insert collection metadata
+        let collection: Collection = sqlx::query_as(
+            r#"
+            INSERT INTO spatialvault.collections
+            (id, canonical_name, owner, schema_name, table_name, collection_type, title, description,
+             default_limit, max_limit, min_zoom, max_zoom, tile_layer, keywords, license,
+             title_i18n, description_i18n, unique_properties, geometry_columns, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15,
+                    NULLIF($16::jsonb, '{}'::jsonb), NULLIF($17::jsonb, '{}'::jsonb), $18, $19, $20)
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(canonical_name)
+        .bind(owner)
+        .bind(schema_name)
+        .bind(&table_name)
+        .bind(collection_type)
+        .bind(title)
+        .bind(description)
+        .bind(limits.default_limit.map(|limit| limit as i32))
+        .bind(limits.max_limit.map(|limit| limit as i32))
+        .bind(limits.min_zoom.map(|zoom| zoom as i32))
+        .bind(limits.max_zoom.map(|zoom| zoom as i32))
+        .bind(&limits.tile_layer)
+        .bind(metadata.keywords.as_deref().unwrap_or_default())
+        .bind(&metadata.license)
+        .bind(metadata.titles.as_ref().map(sqlx::types::Json))
+        .bind(metadata.descriptions.as_ref().map(sqlx::types::Json))
+        .bind(unique_properties)
+        .bind(geometry_columns)
+        .bind(expires_at)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if !unique_properties.is_empty() && !matches!(collection_type, "vector" | "table") {
+            return Err(AppError::BadRequest(
+                "Unique properties are only supported for vector and table collections".to_string(),
+            ));
+        }
+        if !geometry_columns.is_empty() && collection_type != "vector" {
+            return Err(AppError::BadRequest(
+                "Secondary geometry columns are only supported for vector collections".to_string(),
+            ));
+        }
+
+        // For vector and table collections, create the feature table
+        if matches!(collection_type, "vector" | "table") {
+            // Use quote_ident for safe identifier quoting (belt and suspenders with validation)
+            let quoted_schema = quote_ident(schema_name);
+            let quoted_table = quote_ident(&table_name);
+
+            // Attribute-only tables have no geometry column; the type
+            // modifier makes PostGIS reject geometries of other types
+            let geometry_column = if is_vector {
+                format!(
+                    "geometry geometry({}, {}) NOT NULL,",
+                    geometry.type_modifier(),
+                    crs
+                )
+            } else {
+                String::new()
+            };
+            let create_table_sql = format!(
+                r#"
+                CREATE TABLE {}.{} (
+                    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                    {}
+                    properties JSONB DEFAULT '{{}}',
+                    version BIGINT NOT NULL DEFAULT 1,
+                    created_at TIMESTAMPTZ DEFAULT NOW(),
+                    updated_at TIMESTAMPTZ DEFAULT NOW()
+                )
+                "#,
+                quoted_schema, quoted_table, geometry_column
+            );
+            sqlx::query(&create_table_sql).execute(&mut *tx).await?;
+
+            // Track when the features last changed, whatever writes them
+            let create_trigger_sql = format!(
+                r#"
+                CREATE TRIGGER touch_collection_data
+                AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON {}.{}
+                FOR EACH STATEMENT EXECUTE FUNCTION spatialvault.touch_collection_data('{}')
+                "#,
+                quoted_schema, quoted_table, id
+            );
+            sqlx::query(&create_trigger_sql).execute(&mut *tx).await?;
+
+            // Record deletions for replicas, whatever deletes the features
+            let deletions_trigger_sql = format!(
+                r#"
+                CREATE TRIGGER record_feature_deletions
+                AFTER DELETE ON {}.{}
+                REFERENCING OLD TABLE AS old_rows
+                FOR EACH STATEMENT EXECUTE FUNCTION spatialvault.record_feature_deletions('{}')
+                "#,
+                quoted_schema, quoted_table, id
+            );
+            sqlx::query(&deletions_trigger_sql)
+                .execute(&mut *tx)
+                .await?;
+
+            // Create spatial index
+            if collection_type == "vector" {
+                let create_index_sql = format!(
+                    r#"CREATE INDEX ON {}.{} USING GIST(geometry)"#,
+                    quoted_schema, quoted_table
+                );
+                sqlx::query(&create_index_sql).execute(&mut *tx).await?;
+            }
+
+            for property in unique_properties {
+                create_unique_index(&mut tx, schema_name, &table_name, property).await?;
+            }
+            for column in geometry_columns {
+                add_geometry_column(&mut tx, schema_name, &table_name, column, crs).await?;
+            }
+        }
+
+        finish_write(tx, self.validate_only).await?;
+
+        Ok(collection)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update_collection(
+        &self,
+        username: &str,
+        collection_id: &str,
+        expected_version: Option<i64>,
+        title: Option<&str>,
+        description: Option<&str>,
+        new_name: Option<&str>,
+        limits: &CollectionLimitsPatch,
+        processing_defaults: Option<&ProcessingDefaults>,
+        metadata: &CollectionMetadata,
+    ) -> AppResult<Collection> {
+        let mut tx = self.db.pool().begin().await?;
+
+        // Get current collection with version check
+        let current: Collection = sqlx::query_as(
+            "SELECT * FROM spatialvault.collections WHERE canonical_name = $1 FOR UPDATE",
+        )
+        .bind(collection_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Collection not found: {}", collection_id)))?;
+
+        // Check version if If-Match header was provided
+        if let Some(version) = expected_version {
+            if current.version != version {
+                return Err(AppError::PreconditionFailed(
+                    "Collection has been modified".to_string(),
+                ));
+            }
+        }
+
+        // Check ownership
+        if current.owner != username {
+            return Err(AppError::Forbidden(
+                "Only owner can update collection".to_string(),
+            ));
+        }
+
+        // Validate the limits as they will be stored
+        let limits = limits.apply(&CollectionLimits {
+            default_limit: current.default_limit.map(|limit| limit as u32),
+            max_limit: current.max_limit.map(|limit| limit as u32),
+            min_zoom: current.min_zoom.map(|zoom| zoom as u32),
+            max_zoom: current.max_zoom.map(|zoom| zoom as u32),
+            tile_layer: current.tile_layer.clone(),
+        });
+        limits.validate()?;
+
+        // Handle rename
+        let final_name = if let Some(new_canonical_name) = new_name {
+            // Create alias from old name
+            sqlx::query(
+                "INSERT INTO spatialvault.collection_aliases (old_name, new_name) VALUES ($1, $2)",
+            )
+            .bind(collection_id)
+            .bind(new_canonical_name)
+            .execute(&mut *tx)
+            .await?;
+
+            new_canonical_name
+        } else {
+            collection_id
+        };
+
+        // Update collection
+        let collection: Collection = sqlx::query_as(
+            r#"
+            UPDATE spatialvault.collections
+            SET
+                canonical_name = $1,
+                title = COALESCE($2, title),
+                description = COALESCE($3, description),
+                default_limit = $4,
+                max_limit = $5,
+                min_zoom = $6,
+                max_zoom = $7,
+                tile_layer = COALESCE($8, tile_layer),
+                processing_defaults = CASE
+                    WHEN $9::jsonb IS NULL THEN processing_defaults
+                    WHEN $9::jsonb = '{}'::jsonb THEN NULL
+                    ELSE $9::jsonb
+                END,
+                keywords = COALESCE($10, keywords),
+                license = COALESCE($11, license),
+                title_i18n = CASE
+                    WHEN $12::jsonb IS NULL THEN title_i18n
+                    ELSE NULLIF($12::jsonb, '{}'::jsonb)
+                END,
+                description_i18n = CASE
+                    WHEN $13::jsonb IS NULL THEN description_i18n
+                    ELSE NULLIF($13::jsonb, '{}'::jsonb)
+                END,
+                version = version + 1,
+                updated_at = NOW()
+            WHERE id = $14
+            RETURNING *
+            "#,
+        )
+        .bind(final_name)
+        .bind(title)
+        .bind(description)
+        .bind(limits.default_limit.map(|limit| limit as i32))
+        .bind(limits.max_limit.map(|limit| limit as i32))
+        .bind(limits.min_zoom.map(|zoom| zoom as i32))
+        .bind(limits.max_zoom.map(|zoom| zoom as i32))
+        .bind(&limits.tile_layer)
+        .bind(processing_defaults.map(sqlx::types::Json))
+        .bind(&metadata.keywords)
+        .bind(&metadata.license)
+        .bind(metadata.titles.as_ref().map(sqlx::types::Json))
+        .bind(metadata.descriptions.as_ref().map(sqlx::types::Json))
+        .bind(current.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        record_collection_event(
+            &mut tx,
+            collection.id,
+            CollectionEventType::Metadata,
+            collection.version,
+        )
+        .await?;
+
+        finish_write(tx, self.validate_only).await?;
+
+        Ok(collection)
+    }
+
+    /// Set the properties encoded into a collection's vector tiles
+    ///
+    /// An empty rule list encodes all properties at every zoom level.
+    pub async fn update_tile_properties(
+        &self,
+        username: &str,
+        collection_id: &str,
+        expected_version: Option<i64>,
+        rules: &[TilePropertyRule],
+    ) -> AppResult<Collection> {
+        let mut tx = self.db.pool().begin().await?;
+
+        let current: Collection = sqlx::query_as(
+            "SELECT * FROM spatialvault.collections WHERE canonical_name = $1 FOR UPDATE",
+        )
+        .bind(collection_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Collection not found: {}", collection_id)))?;
+
+        if let Some(version) = expected_version
+            && current.version != version
+        {
+            return Err(AppError::PreconditionFailed(
+                "Collection has been modified".to_string(),
+            ));
+        }
+
+        if current.owner != username {
+            return Err(AppError::Forbidden(
+                "Only owner can update collection".to_string(),
+            ));
+        }
+
+        let collection: Collection = sqlx::query_as(
+            r#"
+            UPDATE spatialvault.collections
+            SET
+                tile_properties = $1,
+                version = version + 1,
+                updated_at = NOW()
+            WHERE id = $2
+            RETURNING *
+            "#,
+        )
+        .bind((!rules.is_empty()).then_some(sqlx::types::Json(rules)))
+        .bind(current.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        record_collection_event(
+            &mut tx,
+            collection.id,
+            CollectionEventType::Metadata,
+            collection.version,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(collection)
+    }
+
+    /// Allow or disallow signed tile URLs for a collection
+    pub async fn update_public_tiles(
+        &self,
+        username: &str,
+        collection_id: &str,
+        expected_version: Option<i64>,
+        public_tiles: bool,
+    ) -> AppResult<Collection> {
+        let mut tx = self.db.pool().begin().await?;
+
+        let current: Collection = sqlx::query_as(
+            "SELECT * FROM spatialvault.collections WHERE canonical_name = $1 FOR UPDATE",
+        )
+        .bind(collection_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Collection not found: {}", collection_id)))?;
+
+        if let Some(version) = expected_version
+            && current.version != version
+        {
+            return Err(AppError::PreconditionFailed(
+                "Collection has been modified".to_string(),
+            ));
+        }
+
+        if current.owner != username {
+            return Err(AppError::Forbidden(
+                "Only owner can update collection".to_string(),
+            ));
+        }
+
+        let collection: Collection = sqlx::query_as(
+            r#"
+            UPDATE spatialvault.collections
+            SET
+                public_tiles = $1,
+                version = version + 1,
+                updated_at = NOW()
+            WHERE id = $2
+            RETURNING *
+            "#,
+        )
+        .bind(public_tiles)
+        .bind(current.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        record_collection_event(
+            &mut tx,
+            collection.id,
+            CollectionEventType::Metadata,
+            collection.version,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(collection)
+    }
+
+    /// Replace the relations of a collection to other collections
+    ///
+    /// Related collections must be vector or table collections of the same
+    /// owner, so that filters cannot read collections the owner has not
+    /// published.
+    pub async fn update_relations(
+        &self,
+        username: &str,
+        collection_id: &str,
+        expected_version: Option<i64>,
+        relations: &[CollectionRelation],
+    ) -> AppResult<Collection> {
+        let mut tx = self.db.pool().begin().await?;
+
+        let current: Collection = sqlx::query_as(
+            "SELECT * FROM spatialvault.collections WHERE canonical_name = $1 FOR UPDATE",
+        )
+        .bind(collection_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Collection not found: {}", collection_id)))?;
+
+        if let Some(version) = expected_version
+            && current.version != version
+        {
+            return Err(AppError::PreconditionFailed(
+                "Collection has been modified".to_string(),
+            ));
+        }
+
+        if current.owner != username {
+            return Err(AppError::Forbidden(
+                "Only owner can update collection".to_string(),
+            ));
+        }
+
+        for relation in relations {
+            let target: Option<(String, String)> = sqlx::query_as(
+                "SELECT owner, collection_type FROM spatialvault.collections WHERE canonical_name = $1",
+            )
+            .bind(&relation.collection)
+            .fetch_optional(&mut *tx)
+            .await?;
+            match target {
+                Some((owner, collection_type))
+                    if owner == current.owner
+                        && matches!(collection_type.as_str(), "vector" | "table") => {}
+                _ => {
+                    return Err(AppError::BadRequest(format!(
+                        "Relation {} must refer to a vector or table collection of the same owner",
+                        relation.name
+                    )));
+                }
+            }
+        }
+
+        let collection: Collection = sqlx::query_as(
+            r#"
+            UPDATE spatialvault.collections
+            SET
+                relations = $1,
+                version = version + 1,
+                updated_at = NOW()
+            WHERE id = $2
+            RETURNING *
+            "#,
+        )
+        .bind((!relations.is_empty()).then_some(sqlx::types::Json(relations)))
+        .bind(current.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        record_collection_event(
+            &mut tx,
+            collection.id,
+            CollectionEventType::Schema,
+            collection.version,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(collection)
+    }
+
+    /// Replace the computed properties of a collection
+    pub async fn update_computed_properties(
+        &self,
+        username: &str,
+        collection_id: &str,
+        expected_version: Option<i64>,
+        properties: &[ComputedProperty],
+    ) -> AppResult<Collection> {
+        let mut tx = self.db.pool().begin().await?;
+
+        let current: Collection = sqlx::query_as(
+            "SELECT * FROM spatialvault.collections WHERE canonical_name = $1 FOR UPDATE",
+        )
+        .bind(collection_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Collection not found: {}", collection_id)))?;
+
+        if let Some(version) = expected_version
+            && current.version != version
+        {
+            return Err(AppError::PreconditionFailed(
+                "Collection has been modified".to_string(),
+            ));
+        }
+
+        if current.owner != username {
+            return Err(AppError::Forbidden(
+                "Only owner can update collection".to_string(),
+            ));
+        }
+
+        let collection: Collection = sqlx::query_as(
+            r#"
+            UPDATE spatialvault.collections
+            SET
+                computed_properties = $1,
+                version = version + 1,
+                updated_at = NOW()
+            WHERE id = $2
+            RETURNING *
+            "#,
+        )
+        .bind((!properties.is_empty()).then_some(sqlx::types::Json(properties)))
+        .bind(current.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        record_collection_event(
+            &mut tx,
+            collection.id,
+            CollectionEventType::Schema,
+            collection.version,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(collection)
+    }
+
+    /// Replace the properties whose values must be unique
+    ///
+    /// Unique indexes are created for added properties and dropped for
+    /// removed ones. Creating an index fails with a conflict if existing
+    /// features already share a value.
+    pub async fn update_unique_properties(
+        &self,
+        username: &str,
+        collection_id: &str,
+        expected_version: Option<i64>,
+        properties: &[String],
+    ) -> AppResult<Collection> {
+        let mut tx = self.db.pool().begin().await?;
+
+        let current: Collection = sqlx::query_as(
+            "SELECT * FROM spatialvault.collections WHERE canonical_name = $1 FOR UPDATE",
+        )
+        .bind(collection_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Collection not found: {}", collection_id)))?;
+
+        if let Some(version) = expected_version
+            && current.version != version
+        {
+            return Err(AppError::PreconditionFailed(
+                "Collection has been modified".to_string(),
+            ));
+        }
+
+        if current.owner != username {
+            return Err(AppError::Forbidden(
+                "Only owner can update collection".to_string(),
+            ));
+        }
+
+        if !current.has_feature_table() {
+            return Err(AppError::BadRequest(
+                "Unique properties are only supported for vector and table collections".to_string(),
+            ));
+        }
+
+        for property in current
+            .unique_properties
+            .iter()
+            .filter(|p| !properties.contains(p))
+        {
+            let drop_sql = format!(
+                "DROP INDEX IF EXISTS {}.{}",
+                quote_ident(&current.schema_name),
+                quote_ident(&unique_index_name(&current.table_name, property))
+            );
+            sqlx::query(&drop_sql).execute(&mut *tx).await?;
+        }
+        for property in properties
+            .iter()
+            .filter(|p| !current.unique_properties.contains(p))
+        {
+            create_unique_index(&mut tx, &current.schema_name, &current.table_name, property)
+                .await?;
+        }
+
+        let collection: Collection = sqlx::query_as(
+            r#"
+            UPDATE spatialvault.collections
+            SET
+                unique_properties = $1,
+                version = version + 1,
+                updated_at = NOW()
+            WHERE id = $2
+            RETURNING *
+            "#,
+        )
+        .bind(properties)
+        .bind(current.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        record_collection_event(
+            &mut tx,
+            collection.id,
+            CollectionEventType::Schema,
+            collection.version,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(collection)
+    }
+
+    /// Replace the secondary geometry columns of a vector collection
+    ///
+    /// Generated columns are added for new names and dropped for removed
+    /// ones.
+    pub async fn update_geometry_columns(
+        &self,
+        username: &str,
+        collection_id: &str,
+        expected_version: Option<i64>,
+        columns: &[String],
+    ) -> AppResult<Collection> {
+        let mut tx = self.db.pool().begin().await?;
+
+        let current: Collection = sqlx::query_as(
+            "SELECT * FROM spatialvault.collections WHERE canonical_name = $1 FOR UPDATE",
+        )
+        .bind(collection_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Collection not found: {}", collection_id)))?;
+
+        if let Some(version) = expected_version
+            && current.version != version
+        {
+            return Err(AppError::PreconditionFailed(
+                "Collection has been modified".to_string(),
+            ));
+        }
+
+        if current.owner != username {
+            return Err(AppError::Forbidden(
+                "Only owner can update collection".to_string(),
+            ));
+        }
+
+        if !current.has_geometry() {
+            return Err(AppError::BadRequest(
+                "Secondary geometry columns are only supported for vector collections".to_string(),
+            ));
+        }
+
+        let quoted_table = format!(
+            "{}.{}",
+            quote_ident(&current.schema_name),
+            quote_ident(&current.table_name)
+        );
+        for column in current
+            .geometry_columns
+            .iter()
+            .filter(|c| !columns.contains(c))
+        {
+            let drop_sql = format!(
+                "ALTER TABLE {} DROP COLUMN IF EXISTS {}",
+                quoted_table,
+                quote_ident(column)
+            );
+            sqlx::query(&drop_sql).execute(&mut *tx).await?;
+        }
+
+        let (srid,): (i32,) = sqlx::query_as(
+            r#"
+            SELECT srid FROM geometry_columns
+            WHERE f_table_schema = $1 AND f_table_name = $2 AND f_geometry_column = 'geometry'
+            "#,
+        )
+        .bind(&current.schema_name)
+        .bind(&current.table_name)
+        .fetch_one(&mut *tx)
+        .await?;
+        for column in columns
+            .iter()
+            .filter(|c| !current.geometry_columns.contains(c))
+        {
+            add_geometry_column(
+                &mut tx,
+                &current.schema_name,
+                &current.table_name,
+                column,
+                srid,
+            )
+            .await?;
+        }
+
+        let collection: Collection = sqlx::query_as(
+            r#"
+            UPDATE spatialvault.collections
+            SET
+                geometry_columns = $1,
+                version = version + 1,
+                updated_at = NOW()
+            WHERE id = $2
+            RETURNING *
+            "#,
+        )
+        .bind(columns)
+        .bind(current.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        record_collection_event(
+            &mut tx,
+            collection.id,
+            CollectionEventType::Schema,
+            collection.version,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(collection)
+    }
+
+    /// Assets attached to a collection itself, by key
+    pub async fn list_collection_assets(
+        &self,
+        collection_id: Uuid,
+    ) -> AppResult<Vec<CollectionAsset>> {
+        let assets = sqlx::query_as(
+            "SELECT * FROM spatialvault.collection_assets WHERE collection_id = $1 ORDER BY key",
+        )
+        .bind(collection_id)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(assets)
+    }
+
+    /// Assets of an owner's collections and their items whose hrefs the
+    /// link check found broken, optionally of a single collection
+    pub async fn list_broken_assets(
+        &self,
+        owner: &str,
+        collection_id: Option<&str>,
+        limit: i64,
+    ) -> AppResult<Vec<BrokenAsset>> {
+        let assets = sqlx::query_as(
+            r#"
+            SELECT c.canonical_name AS collection, NULL::uuid AS item_id, ca.key, ca.href,
+                   ca.link_checked_at
+            FROM spatialvault.collection_assets ca
+            JOIN spatialvault.collections c ON c.id = ca.collection_id
+            WHERE c.owner = $1 AND ca.link_status = 'broken'
+              AND ($2::text IS NULL OR c.canonical_name = $2)
+            UNION ALL
+            SELECT c.canonical_name, a.item_id, a.key, a.href, a.link_checked_at
+            FROM spatialvault.assets a
+            JOIN spatialvault.items i ON i.id = a.item_id
+            JOIN spatialvault.collections c ON c.id = i.collection_id
+            WHERE c.owner = $1 AND a.link_status = 'broken'
+              AND ($2::text IS NULL OR c.canonical_name = $2)
+            ORDER BY collection, item_id NULLS FIRST, key
+            LIMIT $3
+            "#,
+        )
+        .bind(owner)
+        .bind(collection_id)
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(assets)
+    }
+
+    /// Create or replace an asset of a collection
+    ///
+    /// Bumps the collection version, since the asset is part of the
+    /// collection document. Returns the asset and whether it is new.
+    pub async fn put_collection_asset(
+        &self,
+        username: &str,
+        collection_id: &str,
+        key: &str,
+        asset: &AssetObject,
+    ) -> AppResult<(CollectionAsset, bool)> {
+        let mut tx = self.db.pool().begin().await?;
+        let collection = self
+            .lock_collection_for_owner(&mut tx, username, collection_id)
+            .await?;
+
+        let exists: Option<(Uuid,)> = sqlx::query_as(
+            "SELECT id FROM spatialvault.collection_assets WHERE collection_id = $1 AND key = $2",
+        )
+        .bind(collection.id)
+        .bind(key)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let stored: CollectionAsset = sqlx::query_as(
+            r#"
+            INSERT INTO spatialvault.collection_assets
+                (collection_id, key, href, type, title, description, roles, file_size)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (collection_id, key) DO UPDATE SET
+                href = EXCLUDED.href,
+                type = EXCLUDED.type,
+                title = EXCLUDED.title,
+                description = EXCLUDED.description,
+                roles = EXCLUDED.roles,
+                file_size = EXCLUDED.file_size,
+                link_status = NULL,
+                link_checked_at = NULL,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(collection.id)
+        .bind(key)
+        .bind(&asset.href)
+        .bind(&asset.media_type)
+        .bind(&asset.title)
+        .bind(&asset.description)
+        .bind(&asset.roles)
+        .bind(asset.file_size)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        self.touch_collection(&mut tx, collection.id).await?;
+        tx.commit().await?;
+
+        Ok((stored, exists.is_none()))
+    }
+
+    /// Remove an asset from a collection
+    pub async fn delete_collection_asset(
+        &self,
+        username: &str,
+        collection_id: &str,
+        key: &str,
+    ) -> AppResult<()> {
+        let mut tx = self.db.pool().begin().await?;
+        let collection = self
+            .lock_collection_for_owner(&mut tx, username, collection_id)
+            .await?;
+
+        let result = sqlx::query(
+            "DELETE FROM spatialvault.collection_assets WHERE collection_id = $1 AND key = $2",
+        )
+        .bind(collection.id)
+        .bind(key)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!("Asset not found: {}", key)));
+        }
+
+        self.touch_collection(&mut tx, collection.id).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Lock a collection for a change only its owner may make
+    async fn lock_collection_for_owner(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        username: &str,
+        collection_id: &str,
+    ) -> AppResult<Collection> {
+        let collection: Collection = sqlx::query_as(
+            "SELECT * FROM spatialvault.collections WHERE canonical_name = $1 FOR UPDATE",
+        )
+        .bind(collection_id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Collection not found: {}", collection_id)))?;
+
+        if collection.owner != username {
+            return Err(AppError::Forbidden(
+                "Only owner can update collection".to_string(),
+            ));
+        }
+
+        Ok(collection)
+    }
+
+    /// Bump the version of a collection whose document changed
+    async fn touch_collection(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        id: Uuid,
+    ) -> AppResult<()> {
+        let version: i64 = sqlx::query_scalar(
+            "UPDATE spatialvault.collections SET version = version + 1, updated_at = NOW() WHERE id = $1 RETURNING version",
+        )
+        .bind(id)
+        .fetch_one(&mut **tx)
+        .await?;
+        record_collection_event(tx, id, CollectionEventType::Metadata, version).await
+    }
+
+    pub async fn list_snapshots(&self, collection_id: Uuid) -> AppResult<Vec<CollectionSnapshot>> {
+        let snapshots = sqlx::query_as(
+            "SELECT * FROM spatialvault.collection_snapshots WHERE collection_id = $1 ORDER BY created_at",
+        )
+        .bind(collection_id)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(snapshots)
+    }
+
+    pub async fn get_snapshot(
+        &self,
+        collection_id: Uuid,
+        name: &str,
+    ) -> AppResult<Option<CollectionSnapshot>> {
+        let snapshot = sqlx::query_as(
+            "SELECT * FROM spatialvault.collection_snapshots WHERE collection_id = $1 AND name = $2",
+        )
+        .bind(collection_id)
+        .bind(name)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(snapshot)
+    }
+
+    /// Copy the feature table of a collection into a named snapshot
+    pub async fn create_snapshot(
+        &self,
+        username: &str,
+        collection_id: &str,
+        name: &str,
+    ) -> AppResult<CollectionSnapshot> {
+        let mut tx = self.db.pool().begin().await?;
+        let collection = self
+            .lock_collection_for_owner(&mut tx, username, collection_id)
+            .await?;
+
+        if !collection.has_feature_table() {
+            return Err(AppError::BadRequest(
+                "Snapshots are only supported for vector and table collections".to_string(),
+            ));
+        }
+
+        let exists: Option<(String,)> = sqlx::query_as(
+            "SELECT name FROM spatialvault.collection_snapshots WHERE collection_id = $1 AND name = $2",
+        )
+        .bind(collection.id)
+        .bind(name)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if exists.is_some() {
+            return Err(AppError::Conflict(format!(
+                "Snapshot already exists: {}",
+                name
+            )));
+        }
+
+        let table_name = format!("snapshot_{}", Uuid::new_v4().simple());
+        let copy_sql = format!(
+            "CREATE TABLE {}.{} AS TABLE {}.{}",
+            quote_ident(SNAPSHOT_SCHEMA),
+            quote_ident(&table_name),
+            quote_ident(&collection.schema_name),
+            quote_ident(&collection.table_name)
+        );
+        sqlx::query(&copy_sql).execute(&mut *tx).await?;
+
+        let count_sql = format!(
+            "SELECT COUNT(*) FROM {}.{}",
+            quote_ident(SNAPSHOT_SCHEMA),
+            quote_ident(&table_name)
+        );
+        let (feature_count,): (i64,) = sqlx::query_as(&count_sql).fetch_one(&mut *tx).await?;
+
+        let snapshot: CollectionSnapshot = sqlx::query_as(
+            r#"
+            INSERT INTO spatialvault.collection_snapshots
+                (collection_id, name, table_name, created_by, collection_version, feature_count)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(collection.id)
+        .bind(name)
+        .bind(&table_name)
+        .bind(username)
+        .bind(collection.version)
+        .bind(feature_count)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(snapshot)
+    }
+
+    /// Replace the features of a collection with those of a snapshot
+    ///
+    /// Restored features get versions above any the collection had, so that
+    /// ETags from before the restore don't match them, and count as updated
+    /// now.
+    pub async fn restore_snapshot(
+        &self,
+        username: &str,
+        collection_id: &str,
+        name: &str,
+        expected_version: Option<i64>,
+    ) -> AppResult<(CollectionSnapshot, Collection)> {
+        let mut tx = self.db.pool().begin().await?;
+        let collection = self
+            .lock_collection_for_owner(&mut tx, username, collection_id)
+            .await?;
+
+        if let Some(version) = expected_version
+            && collection.version != version
+        {
+            return Err(AppError::PreconditionFailed(
+                "Collection has been modified".to_string(),
+            ));
+        }
+
+        let snapshot: CollectionSnapshot = sqlx::query_as(
+            "SELECT * FROM spatialvault.collection_snapshots WHERE collection_id = $1 AND name = $2",
+        )
+        .bind(collection.id)
+        .bind(name)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Snapshot not found: {}", name)))?;
+
+        let live_table = format!(
+            "{}.{}",
+            quote_ident(&collection.schema_name),
+            quote_ident(&collection.table_name)
+        );
+        let snapshot_table = format!(
+            "{}.{}",
+            quote_ident(SNAPSHOT_SCHEMA),
+            quote_ident(&snapshot.table_name)
+        );
+
+        // Columns added since the snapshot keep their defaults; generated
+        // columns are computed again
+        let columns: Vec<(String,)> = sqlx::query_as(
+            r#"
+            SELECT live.attname::text
+            FROM pg_attribute live
+            JOIN pg_attribute snap
+              ON snap.attname = live.attname
+             AND snap.attrelid = to_regclass($2)
+             AND snap.attnum > 0 AND NOT snap.attisdropped
+            WHERE live.attrelid = to_regclass($1)
+              AND live.attnum > 0 AND NOT live.attisdropped
+              AND live.attgenerated = ''
+            ORDER BY live.attnum
+            "#,
+        )
+        .bind(&live_table)
+        .bind(&snapshot_table)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let max_version_sql = format!("SELECT COALESCE(MAX(version), 0) FROM {}", live_table);
+        let (max_version,): (i64,) = sqlx::query_as(&max_version_sql).fetch_one(&mut *tx).await?;
+
+        let column_list = columns
+            .iter()
+            .map(|(column,)| quote_ident(column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let select_list = columns
+            .iter()
+            .map(|(column,)| match column.as_str() {
+                "version" => "version + $1".to_string(),
+                "updated_at" => "NOW()".to_string(),
+                column => quote_ident(column),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        // Replicas drop the features, through the deletion trigger, and get
+        // the restored ones as new
+        sqlx::query(&format!("DELETE FROM {}", live_table))
+            .execute(&mut *tx)
+            .await?;
+        let restore_sql = format!(
+            "INSERT INTO {} ({}) SELECT {} FROM {}",
+            live_table, column_list, select_list, snapshot_table
+        );
+        sqlx::query(&restore_sql)
+            .bind(max_version)
+            .execute(&mut *tx)
+            .await?;
+
+        let collection: Collection = sqlx::query_as(
+            r#"
+            UPDATE spatialvault.collections
+            SET version = version + 1, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(collection.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok((snapshot, collection))
+    }
+
+    pub async fn delete_snapshot(
+        &self,
+        username: &str,
+        collection_id: &str,
+        name: &str,
+    ) -> AppResult<()> {
+        let mut tx = self.db.pool().begin().await?;
+        let collection = self
+            .lock_collection_for_owner(&mut tx, username, collection_id)
+            .await?;
+
+        let (table_name,): (String,) = sqlx::query_as(
+            r#"
+            DELETE FROM spatialvault.collection_snapshots
+            WHERE collection_id = $1 AND name = $2
+            RETURNING table_name
+            "#,
+        )
+        .bind(collection.id)
+        .bind(name)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Snapshot not found: {}", name)))?;
+
+        drop_snapshot_table(&mut tx, &table_name).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Scratch collections past their expiry, as (canonical name, owner)
+    pub async fn list_expired_collections(&self, limit: i64) -> AppResult<Vec<(String, String)>> {
+        let expired = sqlx::query_as(
+            r#"
+            SELECT canonical_name, owner FROM spatialvault.collections
+            WHERE expires_at <= NOW()
+            ORDER BY expires_at
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(expired)
+    }
+
+    /// Write a backup of a collection with its features or items to `output`
+    ///
+    /// Only the owner may back up a collection. Everything is read in one
+    /// repeatable read transaction, so the backup is consistent; rows are
+    /// written as they are read.
+    pub async fn backup_collection(
+        &self,
+        username: &str,
+        collection_id: &str,
+        created: chrono::DateTime<chrono::Utc>,
+        output: mpsc::Sender<Bytes>,
+    ) -> AppResult<(BackupHeader, BackupCounts)> {
+        let mut tx = self.db.begin_with_budget(QueryClass::Background).await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ")
+            .execute(&mut *tx)
+            .await?;
+
+        let collection: Collection =
+            sqlx::query_as("SELECT * FROM spatialvault.collections WHERE canonical_name = $1")
+                .bind(collection_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(|| {
+                    AppError::NotFound(format!("Collection not found: {}", collection_id))
+                })?;
+        if collection.owner != username {
+            return Err(AppError::Forbidden(
+                "Only the owner may back up a collection".to_string(),
+            ));
+        }
+
+        let (crs, geometry_type, geometry_dimension): (i32, Option<String>, Option<i32>) =
+            sqlx::query_as(
+                r#"
+            SELECT COALESCE(MAX(srid), 4326), MAX(type), MAX(coord_dimension)
+            FROM geometry_columns
+            WHERE f_table_schema = $1 AND f_table_name = $2
+            AND f_geometry_column = 'geometry'
+            "#,
+            )
+            .bind(&collection.schema_name)
+            .bind(&collection.table_name)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let assets: Vec<CollectionAsset> = sqlx::query_as(
+            "SELECT * FROM spatialvault.collection_assets WHERE collection_id = $1 ORDER BY key",
+        )
+        .bind(collection.id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let header = BackupHeader {
+            format_version: backup::FORMAT_VERSION,
+            created,
+            collection: BackupCollection {
+                limits: CollectionLimits {
+                    default_limit: collection.default_limit.map(|limit| limit as u32),
+                    max_limit: collection.max_limit.map(|limit| limit as u32),
+                    min_zoom: collection.min_zoom.map(|zoom| zoom as u32),
+                    max_zoom: collection.max_zoom.map(|zoom| zoom as u32),
+                    tile_layer: collection.tile_layer.clone(),
+                },
+                metadata: CollectionMetadata::from_stored(
+                    &collection.keywords,
+                    collection.license.as_deref(),
+                    collection.title_i18n.as_ref(),
+                    collection.description_i18n.as_ref(),
+                ),
+                id: collection.canonical_name.clone(),
+                collection_type: collection.collection_type.clone(),
+                title: collection.title.clone(),
+                description: collection.description.clone(),
+                crs,
+                version: collection.version,
+                unique_properties: collection.unique_properties.clone(),
+                geometry_columns: collection.geometry_columns.clone(),
+                geometry: CollectionGeometry::from_postgis(
+                    geometry_type.as_deref(),
+                    geometry_dimension,
+                ),
+                tile_properties: collection.tile_properties.clone(),
+                processing_defaults: collection.processing_defaults.clone(),
+                relations: collection.relations.clone(),
+                computed_properties: collection.computed_properties.clone(),
+                public_tiles: collection.public_tiles,
+            },
+            assets: assets
+                .into_iter()
+                .map(|asset| BackupAsset {
+                    key: asset.key,
+                    href: asset.href,
+                    media_type: asset.media_type,
+                    title: asset.title,
+                    description: asset.description,
+                    roles: asset.roles,
+                    file_size: asset.file_size,
+                    extra_fields: None,
+                })
+                .collect(),
+        };
+        let mut writer = BackupWriter::new(&header, output).await?;
+
+        if collection.has_feature_table() {
+            let geometry = if collection.has_geometry() {
+                "ST_AsGeoJSON(geometry)::jsonb"
+            } else {
+                "NULL::jsonb"
+            };
+            let sql = format!(
+                r#"
+                SELECT id, {}, properties, version, created_at, updated_at
+                FROM {}.{}
+                ORDER BY created_at, id
+                "#,
+                geometry,
+                quote_ident(&collection.schema_name),
+                quote_ident(&collection.table_name)
+            );
+            let mut rows = sqlx::query_as::<_, BackupFeatureRow>(&sql).fetch(&mut *tx);
+            while let Some((id, geometry, properties, version, created, updated)) =
+                rows.try_next().await?
+            {
+                writer
+                    .write(&BackupRecord::Feature(BackupFeature {
+                        id,
+                        geometry,
+                        properties: properties.unwrap_or_else(|| serde_json::json!({})),
+                        version,
+                        created,
+                        updated,
+                    }))
+                    .await?;
+            }
+        }
+
+        let mut rows = sqlx::query_as::<_, BackupItemRow>(
+            r#"
+            SELECT i.id, ST_AsGeoJSON(i.geometry)::jsonb, i.datetime, i.properties,
+                   COALESCE(
+                       (SELECT jsonb_agg(jsonb_build_object(
+                                'key', a.key, 'href', a.href, 'type', a.type,
+                                'title', a.title, 'description', a.description,
+                                'roles', a.roles, 'fileSize', a.file_size,
+                                'extraFields', a.extra_fields
+                            ) ORDER BY a.key)
+                        FROM spatialvault.assets a WHERE a.item_id = i.id),
+                       '[]'::jsonb
+                   )
+            FROM spatialvault.items i
+            WHERE i.collection_id = $1
+            ORDER BY i.created_at, i.id
+            "#,
+        )
+        .bind(collection.id)
+        .fetch(&mut *tx);
+        while let Some((id, geometry, datetime, properties, assets)) = rows.try_next().await? {
+            writer
+                .write(&BackupRecord::Item(BackupItem {
+                    id,
+                    geometry,
+                    datetime,
+                    properties,
+                    assets: assets.0,
+                }))
+                .await?;
+        }
+        drop(rows);
+
+        tx.commit().await?;
+
+        let counts = writer.finish().await?;
+        Ok((header, counts))
+    }
+
+    /// Create a collection from a backup under a new or the original id
+    ///
+    /// Features keep their ids and versions; items get new ids, since item
+    /// ids are unique across collections. The features and items are read
+    /// from `reader` as they are inserted. If the restore fails part way, the
+    /// new collection is removed again.
+    pub async fn restore_collection(
+        &self,
+        username: &str,
+        canonical_name: &str,
+        header: &BackupHeader,
+        reader: &mut BackupReader,
+    ) -> AppResult<Collection> {
+        let source = &header.collection;
+        let collection = self
+            .create_collection(
+                username,
+                canonical_name,
+                username,
+                &source.title,
+                source.description.as_deref(),
+                &source.collection_type,
+                source.crs,
+                &source.limits,
+                &source.metadata,
+                &source.unique_properties,
+                &source.geometry_columns,
+                &source.geometry,
+                None,
+            )
+            .await?;
+
+        match self.restore_contents(&collection, header, reader).await {
+            Ok(collection) => Ok(collection),
+            Err(e) => {
+                if let Err(cleanup) = self.delete_collection(username, canonical_name, None).await {
+                    tracing::warn!(
+                        "Could not remove partially restored collection {}: {}",
+                        canonical_name,
+                        cleanup
+                    );
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Insert the features, items and assets of a backup into a new collection
+    async fn restore_contents(
+        &self,
+        collection: &Collection,
+        header: &BackupHeader,
+        reader: &mut BackupReader,
+    ) -> AppResult<Collection> {
+        let source = &header.collection;
+        let mut tx = self.db.pool().begin().await?;
+
+        let table = format!(
+            "{}.{}",
+            quote_ident(&collection.schema_name),
+            quote_ident(&collection.table_name)
+        );
+        let feature_sql = if collection.has_geometry() {
+            format!(
+                r#"
+                INSERT INTO {} (id, geometry, properties, version, created_at, updated_at)
+                SELECT i, ST_SetSRID(ST_GeomFromGeoJSON(g), {}), p, v, c, COALESCE(u, c)
+                FROM UNNEST($1::uuid[], $2::text[], $3::jsonb[], $4::bigint[],
+                            $5::timestamptz[], $6::timestamptz[]) AS f(i, g, p, v, c, u)
+                "#,
+                table, source.crs
+            )
+        } else {
+            format!(
+                r#"
+                INSERT INTO {} (id, properties, version, created_at, updated_at)
+                SELECT i, p, v, c, COALESCE(u, c)
+                FROM UNNEST($1::uuid[], $2::text[], $3::jsonb[], $4::bigint[],
+                            $5::timestamptz[], $6::timestamptz[]) AS f(i, g, p, v, c, u)
+                "#,
+                table
+            )
+        };
+
+        let mut features = Vec::with_capacity(RESTORE_BATCH_SIZE);
+        let mut done = false;
+        while !done {
+            let mut item = None;
+            match reader.next().await? {
+                Some(BackupRecord::Feature(feature)) => {
+                    if !collection.has_feature_table() {
+                        return Err(AppError::BadRequest(format!(
+                            "A {} collection can't hold features",
+                            collection.collection_type
+                        )));
+                    }
+                    features.push(feature);
+                    if features.len() < RESTORE_BATCH_SIZE {
+                        continue;
+                    }
+                }
+                Some(BackupRecord::Item(record)) => item = Some(record),
+                None => done = true,
+            }
+
+            if !features.is_empty() {
+                let batch = std::mem::take(&mut features);
+                let ids: Vec<Uuid> = batch.iter().map(|feature| feature.id).collect();
+                let geometries: Vec<Option<String>> = batch
+                    .iter()
+                    .map(|feature| feature.geometry.as_ref().map(|g| g.to_string()))
+                    .collect();
+                let properties: Vec<&serde_json::Value> =
+                    batch.iter().map(|feature| &feature.properties).collect();
+                let versions: Vec<i64> = batch.iter().map(|feature| feature.version).collect();
+                let created: Vec<chrono::DateTime<chrono::Utc>> = batch
+                    .iter()
+                    .map(|feature| feature.created.unwrap_or(header.created))
+                    .collect();
+                let updated: Vec<Option<chrono::DateTime<chrono::Utc>>> =
+                    batch.iter().map(|feature| feature.updated).collect();
+
+                sqlx::query(&feature_sql)
+                    .bind(&ids)
+                    .bind(&geometries)
+                    .bind(&properties)
+                    .bind(&versions)
+                    .bind(&created)
+                    .bind(&updated)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            if let Some(item) = item {
+                self.restore_item(&mut tx, collection.id, &item).await?;
+            }
+        }
+
+        for asset in &header.assets {
+            sqlx::query(
+                r#"
+                INSERT INTO spatialvault.collection_assets
+                    (collection_id, key, href, type, title, description, roles, file_size)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                "#,
+            )
+            .bind(collection.id)
+            .bind(&asset.key)
+            .bind(&asset.href)
+            .bind(&asset.media_type)
+            .bind(&asset.title)
+            .bind(&asset.description)
+            .bind(&asset.roles)
+            .bind(asset.file_size)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Settings create_collection doesn't take
+        let collection: Collection = sqlx::query_as(
+            r#"
+            UPDATE spatialvault.collections
+            SET tile_properties = $2, processing_defaults = $3, relations = $4,
+                computed_properties = $5, public_tiles = $6,
+                version = version + 1, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(collection.id)
+        .bind(&source.tile_properties)
+        .bind(&source.processing_defaults)
+        .bind(&source.relations)
+        .bind(&source.computed_properties)
+        .bind(source.public_tiles)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(collection)
+    }
+
+    /// Insert a backed up item with its assets under a new id
+    async fn restore_item(
+        &self,
+        tx: &mut sqlx::PgConnection,
+        collection_id: Uuid,
+        item: &BackupItem,
+    ) -> AppResult<()> {
+        let (item_id,): (Uuid,) = sqlx::query_as(
+            r#"
+            INSERT INTO spatialvault.items (collection_id, geometry, datetime, properties)
+            VALUES ($1, ST_SetSRID(ST_GeomFromGeoJSON($2), 4326), $3, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(collection_id)
+        .bind(item.geometry.to_string())
+        .bind(item.datetime)
+        .bind(&item.properties)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        for asset in &item.assets {
+            sqlx::query(
+                r#"
+                INSERT INTO spatialvault.assets
+                (item_id, key, href, type, title, description, roles, file_size, extra_fields)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                "#,
+            )
+            .bind(item_id)
+            .bind(&asset.key)
+            .bind(&asset.href)
+            .bind(&asset.media_type)
+            .bind(&asset.title)
+            .bind(&asset.description)
+            .bind(&asset.roles)
+            .bind(asset.file_size)
+            .bind(&asset.extra_fields)
+            .execute(&mut *tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn list_replications(
+        &self,
+        collection_id: Uuid,
+    ) -> AppResult<Vec<CollectionReplication>> {
+        let replications = sqlx::query_as(
+            "SELECT * FROM spatialvault.collection_replications WHERE collection_id = $1 ORDER BY created_at",
+        )
+        .bind(collection_id)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(replications)
+    }
+
+    pub async fn get_replication(
+        &self,
+        collection_id: Uuid,
+        replication_id: Uuid,
+    ) -> AppResult<Option<CollectionReplication>> {
+        let replication = sqlx::query_as(
+            "SELECT * FROM spatialvault.collection_replications WHERE collection_id = $1 AND id = $2",
+        )
+        .bind(collection_id)
+        .bind(replication_id)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(replication)
+    }
+
+    /// Publish a collection to a collection on a remote instance
+    ///
+    /// Changes are found by the features' `updated_at`, which gets an index
+    /// so that looking for them stays cheap.
+    pub async fn create_replication(
+        &self,
+        username: &str,
+        collection_id: &str,
+        remote: &str,
+        remote_collection: &str,
+    ) -> AppResult<CollectionReplication> {
+        let mut tx = self.db.pool().begin().await?;
+        let collection = self
+            .lock_collection_for_owner(&mut tx, username, collection_id)
+            .await?;
+
+        if !collection.has_feature_table() {
+            return Err(AppError::BadRequest(
+                "Replication is only supported for vector and table collections".to_string(),
+            ));
+        }
+
+        let index_sql = format!(
+            "CREATE INDEX IF NOT EXISTS {} ON {}.{} (updated_at)",
+            quote_ident(&format!("{}_updated_at", collection.table_name)),
+            quote_ident(&collection.schema_name),
+            quote_ident(&collection.table_name)
+        );
+        sqlx::query(&index_sql).execute(&mut *tx).await?;
+
+        let replication: CollectionReplication = sqlx::query_as(
+            r#"
+            INSERT INTO spatialvault.collection_replications
+                (collection_id, remote, remote_collection, created_by)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+        )
+        .bind(collection.id)
+        .bind(remote)
+        .bind(remote_collection)
+        .bind(username)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(replication)
+    }
+
+    /// Stop publishing a collection to a remote; what was pushed stays there
+    pub async fn delete_replication(
+        &self,
+        username: &str,
+        collection_id: &str,
+        replication_id: Uuid,
+    ) -> AppResult<()> {
+        let mut tx = self.db.pool().begin().await?;
+        let collection = self
+            .lock_collection_for_owner(&mut tx, username, collection_id)
+            .await?;
+
+        let result = sqlx::query(
+            "DELETE FROM spatialvault.collection_replications WHERE collection_id = $1 AND id = $2",
+        )
+        .bind(collection.id)
+        .bind(replication_id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "Replication not found: {}",
+                replication_id
+            )));
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Metadata and schema changes of a collection after an event id, oldest
+    /// first
+    pub async fn list_events(
+        &self,
+        collection_id: Uuid,
+        after: i64,
+        limit: i64,
+    ) -> AppResult<Vec<CollectionEvent>> {
+        let events = sqlx::query_as(
+            r#"
+            SELECT * FROM spatialvault.collection_events
+            WHERE collection_id = $1 AND id > $2
+            ORDER BY id
+            LIMIT $3
+            "#,
+        )
+        .bind(collection_id)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(self.db.pool())
         .await?;
 
-        Ok(collection)
+        Ok(events)
     }
 
-    pub async fn get_alias(&self, name: &str) -> AppResult<Option<String>> {
-        let alias: Option<(String,)> = sqlx::query_as(
-            "SELECT new_name FROM spatialvault.collection_aliases WHERE old_name = $1",
+    pub async fn list_webhooks(&self, collection_id: Uuid) -> AppResult<Vec<CollectionWebhook>> {
+        let webhooks = sqlx::query_as(
+            "SELECT * FROM spatialvault.collection_webhooks WHERE collection_id = $1 ORDER BY created_at",
         )
-        .bind(name)
-        .fetch_optional(self.db.pool())
+        .bind(collection_id)
+        .fetch_all(self.db.pool())
         .await?;
 
-        Ok(alias.map(|(new_name,)| new_name))
+        Ok(webhooks)
     }
 
-    /// Check if collection_id is an alias that should redirect.
-    /// Returns Some(new_name) if:
-    /// 1. There is NO currently active collection with the exact name
-    /// 2. AND there is an alias mapping from this name to another name
-    /// Otherwise returns None.
-    pub async fn check_alias_redirect(&self, collection_id: &str) -> AppResult<Option<String>> {
-        // First check if there's an active collection with this exact name
-        let active_collection = self.get_collection("", collection_id).await?;
-
-        if active_collection.is_some() {
-            // There is an active collection with this name, no redirect
-            return Ok(None);
-        }
+    pub async fn get_webhook(
+        &self,
+        collection_id: Uuid,
+        webhook_id: Uuid,
+    ) -> AppResult<Option<CollectionWebhook>> {
+        let webhook = sqlx::query_as(
+            "SELECT * FROM spatialvault.collection_webhooks WHERE collection_id = $1 AND id = $2",
+        )
+        .bind(collection_id)
+        .bind(webhook_id)
+        .fetch_optional(self.db.pool())
+        .await?;
 
-        // No active collection, check for alias
-        self.get_alias(collection_id).await
+        Ok(webhook)
     }
 
-    pub async fn create_collection(
+    /// Post the events of a collection to a URL from now on
+    pub async fn create_webhook(
         &self,
         username: &str,
-        canonical_name: &str,
-        owner: &str,
-        title: &str,
-        description: Option<&str>,
-        collection_type: &str,
-        crs: i32,
-    ) -> AppResult<Collection> {
-        // Ensure user role exists
-        let role_manager = RoleManager::new(self.db.pool());
-        role_manager.ensure_user_role(owner).await?;
-
-        // Parse canonical name to get schema and table name
-        let parts: Vec<&str> = canonical_name.split(':').collect();
-        let schema_name = parts
-            .first()
-            .ok_or_else(|| AppError::BadRequest("Invalid collection name".to_string()))?;
-        let table_name = parts[1..].join("_");
-
-        if table_name.is_empty() {
-            return Err(AppError::BadRequest(
-                "Collection name must have at least two segments".to_string(),
-            ));
-        }
-
-        // Validate schema and table names to prevent SQL injection
-        if !is_valid_role_name(schema_name) {
-            return Err(AppError::BadRequest(format!(
-                "Invalid schema name: {}",
-                schema_name
-            )));
-        }
-        if !is_valid_role_name(&table_name) {
-            return Err(AppError::BadRequest(format!(
-                "Invalid table name: {}",
-                table_name
-            )));
-        }
-
-        let id = Uuid::new_v4();
-
-        // Start transaction
+        collection_id: &str,
+        url: &str,
+        secret: Option<&str>,
+    ) -> AppResult<CollectionWebhook> {
         let mut tx = self.db.pool().begin().await?;
+        let collection = self
+            .lock_collection_for_owner(&mut tx, username, collection_id)
+            .await?;
 
-        // Insert collection metadata
-        let collection: Collection = sqlx::query_as(
+        let webhook: CollectionWebhook = sqlx::query_as(
             r#"
-            INSERT INTO spatialvault.collections
-            (id, canonical_name, owner, schema_name, table_name, collection_type, title, description)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            INSERT INTO spatialvault.collection_webhooks (collection_id, url, secret, created_by)
+            VALUES ($1, $2, $3, $4)
             RETURNING *
             "#,
         )
-        .bind(id)
-        .bind(canonical_name)
-        .bind(owner)
-        .bind(schema_name)
-        .bind(&table_name)
-        .bind(collection_type)
-        .bind(title)
-        .bind(description)
+        .bind(collection.id)
+        .bind(url)
+        .bind(secret)
+        .bind(username)
         .fetch_one(&mut *tx)
         .await?;
 
-        // For vector collections, create the feature table
-        if collection_type == "vector" {
-            // Use quote_ident for safe identifier quoting (belt and suspenders with validation)
-            let quoted_schema = quote_ident(schema_name);
-            let quoted_table = quote_ident(&table_name);
-
-            let create_table_sql = format!(
-                r#"
-                CREATE TABLE {}.{} (
-                    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-                    geometry geometry(Geometry, {}) NOT NULL,
-                    properties JSONB DEFAULT '{{}}',
-                    version BIGINT NOT NULL DEFAULT 1,
-                    created_at TIMESTAMPTZ DEFAULT NOW(),
-                    updated_at TIMESTAMPTZ DEFAULT NOW()
-                )
-                "#,
-                quoted_schema, quoted_table, crs
-            );
-            sqlx::query(&create_table_sql).execute(&mut *tx).await?;
-
-            // Create spatial index
-            let create_index_sql = format!(
-                r#"CREATE INDEX ON {}.{} USING GIST(geometry)"#,
-                quoted_schema, quoted_table
-            );
-            sqlx::query(&create_index_sql).execute(&mut *tx).await?;
-        }
-
         tx.commit().await?;
 
-        Ok(collection)
+        Ok(webhook)
     }
 
-    pub async fn update_collection(
+    pub async fn delete_webhook(
         &self,
         username: &str,
         collection_id: &str,
-        expected_version: Option<i64>,
-        title: Option<&str>,
-        description: Option<&str>,
-        new_name: Option<&str>,
-    ) -> AppResult<Collection> {
+        webhook_id: Uuid,
+    ) -> AppResult<()> {
         let mut tx = self.db.pool().begin().await?;
-
-        // Get current collection with version check
-        let current: Collection = sqlx::query_as(
-            "SELECT * FROM spatialvault.collections WHERE canonical_name = $1 FOR UPDATE",
-        )
-        .bind(collection_id)
-        .fetch_optional(&mut *tx)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Collection not found: {}", collection_id)))?;
-
-        // Check version if If-Match header was provided
-        if let Some(version) = expected_version {
-            if current.version != version {
-                return Err(AppError::PreconditionFailed(
-                    "Collection has been modified".to_string(),
-                ));
-            }
-        }
-
-        // Check ownership
-        if current.owner != username {
-            return Err(AppError::Forbidden(
-                "Only owner can update collection".to_string(),
-            ));
-        }
-
-        // Handle rename
-        let final_name = if let Some(new_canonical_name) = new_name {
-            // Create alias from old name
-            sqlx::query(
-                "INSERT INTO spatialvault.collection_aliases (old_name, new_name) VALUES ($1, $2)",
-            )
-            .bind(collection_id)
-            .bind(new_canonical_name)
-            .execute(&mut *tx)
+        let collection = self
+            .lock_collection_for_owner(&mut tx, username, collection_id)
             .await?;
 
-            new_canonical_name
-        } else {
-            collection_id
-        };
-
-        // Update collection
-        let collection: Collection = sqlx::query_as(
-            r#"
-            UPDATE spatialvault.collections
-            SET
-                canonical_name = $1,
-                title = COALESCE($2, title),
-                description = COALESCE($3, description),
-                version = version + 1,
-                updated_at = NOW()
-            WHERE id = $4
-            RETURNING *
-            "#,
+        let result = sqlx::query(
+            "DELETE FROM spatialvault.collection_webhooks WHERE collection_id = $1 AND id = $2",
         )
-        .bind(final_name)
-        .bind(title)
-        .bind(description)
-        .bind(current.id)
-        .fetch_one(&mut *tx)
+        .bind(collection.id)
+        .bind(webhook_id)
+        .execute(&mut *tx)
         .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "Webhook not found: {}",
+                webhook_id
+            )));
+        }
 
         tx.commit().await?;
 
-        Ok(collection)
+        Ok(())
     }
 
     /// Replace a collection (PUT semantics - full replacement of mutable fields)
@@ -297,6 +2244,8 @@ impl CollectionService {
         expected_version: Option<i64>,
         title: &str,
         description: Option<&str>,
+        limits: &CollectionLimits,
+        metadata: &CollectionMetadata,
     ) -> AppResult<Collection> {
         let mut tx = self.db.pool().begin().await?;
 
@@ -325,26 +2274,51 @@ impl CollectionService {
             ));
         }
 
-        // Replace collection (title and description are the only mutable fields)
+        // Replace collection (title, description, metadata and limits are the mutable fields)
         let collection: Collection = sqlx::query_as(
             r#"
             UPDATE spatialvault.collections
             SET
                 title = $1,
                 description = $2,
+                default_limit = $3,
+                max_limit = $4,
+                min_zoom = $5,
+                max_zoom = $6,
+                tile_layer = $7,
+                keywords = $8,
+                license = $9,
+                title_i18n = NULLIF($10::jsonb, '{}'::jsonb),
+                description_i18n = NULLIF($11::jsonb, '{}'::jsonb),
                 version = version + 1,
                 updated_at = NOW()
-            WHERE id = $3
+            WHERE id = $12
             RETURNING *
             "#,
         )
         .bind(title)
         .bind(description)
+        .bind(limits.default_limit.map(|limit| limit as i32))
+        .bind(limits.max_limit.map(|limit| limit as i32))
+        .bind(limits.min_zoom.map(|zoom| zoom as i32))
+        .bind(limits.max_zoom.map(|zoom| zoom as i32))
+        .bind(&limits.tile_layer)
+        .bind(metadata.keywords.as_deref().unwrap_or_default())
+        .bind(&metadata.license)
+        .bind(metadata.titles.as_ref().map(sqlx::types::Json))
+        .bind(metadata.descriptions.as_ref().map(sqlx::types::Json))
         .bind(current.id)
         .fetch_one(&mut *tx)
         .await?;
+        record_collection_event(
+            &mut tx,
+            collection.id,
+            CollectionEventType::Metadata,
+            collection.version,
+        )
+        .await?;
 
-        tx.commit().await?;
+        finish_write(tx, self.validate_only).await?;
 
         Ok(collection)
     }
@@ -380,8 +2354,8 @@ impl CollectionService {
             ));
         }
 
-        // Drop the table for vector collections
-        if collection.collection_type == "vector" {
+        // Drop the feature table for vector and table collections
+        if collection.has_feature_table() {
             let drop_sql = format!(
                 r#"DROP TABLE IF EXISTS {}.{} CASCADE"#,
                 quote_ident(&collection.schema_name),
@@ -390,6 +2364,16 @@ impl CollectionService {
             sqlx::query(&drop_sql).execute(&mut *tx).await?;
         }
 
+        let snapshots: Vec<(String,)> = sqlx::query_as(
+            "SELECT table_name FROM spatialvault.collection_snapshots WHERE collection_id = $1",
+        )
+        .bind(collection.id)
+        .fetch_all(&mut *tx)
+        .await?;
+        for (table_name,) in snapshots {
+            drop_snapshot_table(&mut tx, &table_name).await?;
+        }
+
         // Delete items and assets for raster/pointcloud collections (cascades)
         sqlx::query("DELETE FROM spatialvault.collections WHERE id = $1")
             .bind(collection.id)
@@ -401,6 +2385,7 @@ impl CollectionService {
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, fields(collection = %collection.canonical_name))]
     pub async fn compute_extent(&self, collection: &Collection) -> AppResult<Option<Extent>> {
         let spatial = self.compute_spatial_extent(collection).await?;
         let temporal = self.compute_temporal_extent(collection).await?;
@@ -412,7 +2397,60 @@ impl CollectionService {
         Ok(Some(Extent { spatial, temporal }))
     }
 
-    async fn compute_spatial_extent(
+    /// Compute and store the extent of a claimed collection for collection
+    /// searches by bbox and datetime
+    async fn refresh_extent(&self, collection: &Collection) -> AppResult<()> {
+        let spatial = self.compute_spatial_extent(collection).await?;
+        let temporal = self.compute_temporal_extent(collection).await?;
+        let bbox = spatial
+            .as_ref()
+            .and_then(|spatial| spatial.bbox.first())
+            .map(|bbox| match bbox {
+                Bbox::TwoD(coords) => coords.to_vec(),
+                Bbox::ThreeD([minx, miny, _, maxx, maxy, _]) => vec![*minx, *miny, *maxx, *maxy],
+            });
+        let parse = |dt: &Option<String>| {
+            dt.as_deref()
+                .and_then(|dt| chrono::DateTime::parse_from_rfc3339(dt).ok())
+                .map(|dt| dt.with_timezone(&chrono::Utc))
+        };
+        let (start, end) = temporal
+            .as_ref()
+            .and_then(|temporal| temporal.interval.first())
+            .map(|[start, end]| (parse(start), parse(end)))
+            .unwrap_or_default();
+
+        // Only write when the extent changed, so the row doesn't churn
+        sqlx::query(
+            r#"
+            WITH computed AS (
+                SELECT
+                    CASE WHEN $2::float8[] IS NULL THEN NULL
+                         ELSE ST_MakeEnvelope($2[1], $2[2], $2[3], $2[4], 4326)
+                    END AS bbox
+            )
+            UPDATE spatialvault.collection_extent_cache e
+            SET bbox = computed.bbox,
+                start_time = $3,
+                end_time = $4
+            FROM computed
+            WHERE e.collection_id = $1
+              AND (e.bbox IS DISTINCT FROM computed.bbox
+                   OR e.start_time IS DISTINCT FROM $3
+                   OR e.end_time IS DISTINCT FROM $4)
+            "#,
+        )
+        .bind(collection.id)
+        .bind(bbox)
+        .bind(start)
+        .bind(end)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn compute_spatial_extent(
         &self,
         collection: &Collection,
     ) -> AppResult<Option<SpatialExtent>> {
@@ -522,10 +2560,37 @@ impl CollectionService {
         Ok(result.map(|(srid,)| srid))
     }
 
+    /// Geometry type and dimension of a vector collection, as recorded by
+    /// PostGIS for its geometry column
+    pub async fn get_geometry(&self, collection: &Collection) -> AppResult<CollectionGeometry> {
+        let result: Option<(String, i32)> = sqlx::query_as(
+            r#"
+            SELECT type, coord_dimension FROM geometry_columns
+            WHERE f_table_schema = $1 AND f_table_name = $2
+            AND f_geometry_column = 'geometry'
+            LIMIT 1
+            "#,
+        )
+        .bind(&collection.schema_name)
+        .bind(&collection.table_name)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(match result {
+            Some((geometry_type, dimension)) => {
+                CollectionGeometry::from_postgis(Some(&geometry_type), Some(dimension))
+            }
+            None => CollectionGeometry::default(),
+        })
+    }
+
+    /// JSON Schema of the features of a collection, with the roles and
+    /// read-only properties of OGC API Features Part 5
     pub async fn get_collection_schema(
         &self,
         username: &str,
         collection_id: &str,
+        base_url: &str,
     ) -> AppResult<CollectionSchema> {
         let collection = self
             .get_collection(username, collection_id)
@@ -534,76 +2599,95 @@ impl CollectionService {
                 AppError::NotFound(format!("Collection not found: {}", collection_id))
             })?;
 
-        // Get column information from PostgreSQL
-        let columns: Vec<(String, String, String, Option<i32>)> = sqlx::query_as(
-            r#"
-            SELECT
-                c.column_name,
-                c.data_type,
-                c.is_nullable,
-                CASE WHEN c.data_type = 'USER-DEFINED' THEN
-                    (SELECT srid FROM geometry_columns
-                     WHERE f_table_schema = $1 AND f_table_name = $2 AND f_geometry_column = c.column_name)
-                ELSE NULL END as srid
-            FROM information_schema.columns c
-            WHERE c.table_schema = $1 AND c.table_name = $2
-            ORDER BY c.ordinal_position
-            "#,
-        )
-        .bind(&collection.schema_name)
-        .bind(&collection.table_name)
-        .fetch_all(self.db.pool())
-        .await?;
-
-        // Build JSON Schema properties
+        // Features have an id and, in vector collections, a geometry besides
+        // their properties
         let mut properties = serde_json::Map::new();
-        let mut required = Vec::new();
-
-        for (column_name, data_type, is_nullable, srid) in columns {
-            let column_schema = match data_type.as_str() {
-                "uuid" => serde_json::json!({ "type": "string", "format": "uuid" }),
-                "text" | "character varying" => serde_json::json!({ "type": "string" }),
-                "integer" | "bigint" | "smallint" => serde_json::json!({ "type": "integer" }),
-                "real" | "double precision" | "numeric" => serde_json::json!({ "type": "number" }),
-                "boolean" => serde_json::json!({ "type": "boolean" }),
-                "timestamp with time zone" | "timestamp without time zone" => {
-                    serde_json::json!({ "type": "string", "format": "date-time" })
-                }
-                "date" => serde_json::json!({ "type": "string", "format": "date" }),
-                "jsonb" | "json" => serde_json::json!({ "type": "object" }),
-                "USER-DEFINED" => {
-                    // This is likely a geometry column
-                    let mut geom_schema = serde_json::json!({
-                        "type": "object",
-                        "description": "GeoJSON geometry"
-                    });
-                    if let Some(s) = srid {
-                        geom_schema["x-srid"] = serde_json::json!(s);
-                    }
-                    geom_schema
-                }
-                "ARRAY" => serde_json::json!({ "type": "array" }),
-                _ => serde_json::json!({ "type": "string" }),
-            };
+        let mut required = vec!["id".to_string()];
+        properties.insert(
+            "id".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "format": "uuid",
+                "x-ogc-role": "id",
+                "readOnly": true,
+            }),
+        );
+        if collection.collection_type == "vector" {
+            let (geometry_type, dimension) = CollectionGeometry::from_postgis(
+                collection.geometry_type.as_deref(),
+                collection.geometry_dimension,
+            )
+            .resolved();
+            properties.insert(
+                "geometry".to_string(),
+                serde_json::json!({
+                    "format": geometry_type.schema_format(),
+                    "x-ogc-role": "primary-geometry",
+                    "x-geometryDimension": dimension,
+                    "x-srid": collection.storage_crs,
+                }),
+            );
+            required.push("geometry".to_string());
+        } else if !collection.as_collection().has_feature_table() {
+            // Items have a footprint and the time they were taken
+            properties.insert(
+                "geometry".to_string(),
+                serde_json::json!({
+                    "format": GeometryType::Geometry.schema_format(),
+                    "x-ogc-role": "primary-geometry",
+                }),
+            );
+            properties.insert(
+                "datetime".to_string(),
+                serde_json::json!({
+                    "type": "string",
+                    "format": "date-time",
+                    "x-ogc-role": "primary-instant",
+                }),
+            );
+        }
 
-            properties.insert(column_name.clone(), column_schema);
+        // The properties as found in the stored features
+        for (name, property_type) in
+            scan_property_types(&self.db, &collection.as_collection()).await?
+        {
+            let schema = if collection.geometry_columns.contains(&name) {
+                serde_json::json!({
+                    "format": GeometryType::Geometry.schema_format(),
+                    "description": format!("GeoJSON geometry, selectable with geom={}", name),
+                })
+            } else {
+                serde_json::json!({ "type": property_type.json_type() })
+            };
+            properties.entry(name).or_insert(schema);
+        }
+        // Secondary geometries no feature has yet
+        for name in &collection.geometry_columns {
+            properties.entry(name.clone()).or_insert_with(|| {
+                serde_json::json!({
+                    "format": GeometryType::Geometry.schema_format(),
+                    "description": format!("GeoJSON geometry, selectable with geom={}", name),
+                })
+            });
+        }
 
-            if is_nullable == "NO" {
-                required.push(column_name);
-            }
+        // Computed properties are returned but not stored
+        for computed in computed_properties_sql(collection.computed_properties.as_ref(), "")? {
+            properties.insert(
+                computed.name.clone(),
+                serde_json::json!({ "type": computed.value_type.json_type(), "readOnly": true }),
+            );
         }
 
         let schema = CollectionSchema {
             schema: "https://json-schema.org/draft/2020-12/schema".to_string(),
-            id: format!("/collections/{}/schema", collection_id),
+            id: format!("{}/collections/{}/schema", base_url, collection_id),
             schema_type: "object".to_string(),
             title: collection.title.clone(),
             properties: serde_json::Value::Object(properties),
-            required: if required.is_empty() {
-                None
-            } else {
-                Some(required)
-            },
+            required: Some(required),
+            unique_properties: collection.unique_properties.clone(),
+            geometry_columns: collection.geometry_columns.clone(),
         };
 
         Ok(schema)
@@ -696,7 +2780,7 @@ impl CollectionService {
         username: &str,
         collection_id: &str,
         principal: &str,
-        _principal_type: &str,
+        principal_type: &str,
         permission: PermissionLevel,
     ) -> AppResult<()> {
         let collection = self
@@ -733,6 +2817,31 @@ impl CollectionService {
             )
             .await?;
 
+        // Group members aren't known, so only users are notified; the share
+        // stands even if the notification can't be queued
+        if principal_type == "user" {
+            let data = serde_json::json!({
+                "collection": collection.canonical_name,
+                "title": collection.title,
+                "permission": permission.as_str(),
+                "sharedBy": username
+            });
+            if let Err(e) = queue_notification(
+                self.db.pool(),
+                principal,
+                NotificationType::CollectionShared,
+                &data,
+            )
+            .await
+            {
+                tracing::warn!(
+                    "Failed to queue share notification for {}: {}",
+                    principal,
+                    e
+                );
+            }
+        }
+
         Ok(())
     }
 
@@ -763,3 +2872,81 @@ impl CollectionService {
         Ok(())
     }
 }
+
+/// Name of the unique index on a property of a feature table
+fn unique_index_name(table_name: &str, property: &str) -> String {
+    format!("{}_{}_unique", table_name, property)
+}
+
+/// Drop the table holding a snapshot's copy of the features
+async fn drop_snapshot_table(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    table_name: &str,
+) -> AppResult<()> {
+    let sql = format!(
+        "DROP TABLE IF EXISTS {}.{}",
+        quote_ident(SNAPSHOT_SCHEMA),
+        quote_ident(table_name)
+    );
+    sqlx::query(&sql).execute(&mut **tx).await?;
+    Ok(())
+}
+
+/// Create the unique index enforcing that a property's values are unique
+///
+/// Features without the property are not constrained.
+async fn create_unique_index(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    schema_name: &str,
+    table_name: &str,
+    property: &str,
+) -> AppResult<()> {
+    let sql = format!(
+        "CREATE UNIQUE INDEX {} ON {}.{} ((properties->>'{}'))",
+        quote_ident(&unique_index_name(table_name, property)),
+        quote_ident(schema_name),
+        quote_ident(table_name),
+        // Validated to be an identifier, see validate_unique_properties
+        property
+    );
+    sqlx::query(&sql).execute(&mut **tx).await?;
+    Ok(())
+}
+
+/// Add a secondary geometry column with a spatial index
+///
+/// The column is generated from the GeoJSON geometry in the property of the
+/// same name, so writes need not know about it; other values are null.
+async fn add_geometry_column(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    schema_name: &str,
+    table_name: &str,
+    column: &str,
+    srid: i32,
+) -> AppResult<()> {
+    let quoted_table = format!("{}.{}", quote_ident(schema_name), quote_ident(table_name));
+    let quoted_column = quote_ident(column);
+    let sql = format!(
+        r#"
+        ALTER TABLE {table} ADD COLUMN {column} geometry(Geometry, {srid})
+        GENERATED ALWAYS AS (
+            CASE WHEN jsonb_typeof(properties->'{name}') = 'object'
+            THEN ST_SetSRID(ST_GeomFromGeoJSON(properties->'{name}'), {srid})
+            END
+        ) STORED
+        "#,
+        table = quoted_table,
+        column = quoted_column,
+        // Validated to be an identifier, see validate_geometry_columns
+        name = column,
+        srid = srid
+    );
+    sqlx::query(&sql).execute(&mut **tx).await?;
+
+    let index_sql = format!(
+        "CREATE INDEX ON {} USING GIST({})",
+        quoted_table, quoted_column
+    );
+    sqlx::query(&index_sql).execute(&mut **tx).await?;
+    Ok(())
+}
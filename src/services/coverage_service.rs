@@ -1,17 +1,43 @@
-use std::sync::Arc;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
+use crate::api::common::crs;
 use crate::api::coverages::handlers::{
-    DomainSet, GeneralGrid, GridAxis, RangeField, RangeType, UnitOfMeasure,
+    DomainAxis, DomainSet, GeneralGrid, GridAxis, GridLimits, IndexAxis, IrregularAxis, NilValue,
+    RangeField, RangeType, UnitOfMeasure,
 };
-use crate::api::coverages::range_subset::CoverageSubsetParams;
+use crate::api::coverages::range_subset::{CoverageSubsetParams, MAX_COVERAGE_BYTES};
+use crate::api::edr::query::{Coords, DateTimeBounds};
+use crate::api::edr::sample::{SampledItem, sample_raster};
 use crate::db::{Collection, Database};
 use crate::error::{AppError, AppResult};
+use crate::processing::cog::{self, BandMetadata, RasterMetadata};
+
+/// Definition of raster band values
+const QUANTITY_DEFINITION: &str = "http://www.opengis.net/def/property/OGC/0/Radiance";
+
+/// Temporal CRS of the time axis of coverages
+const GREGORIAN_TIME_CRS: &str = "http://www.opengis.net/def/uom/ISO-8601/0/Gregorian";
+
+/// Most items sampled by a single EDR query
+pub const MAX_SAMPLED_ITEMS: usize = 100;
+
+/// Raster headers kept before the cache starts over
+const MAX_CACHED_METADATA: usize = 1000;
+
+/// Raster metadata with the `updated_at` of the item it was read for
+type CachedMetadata = (Option<DateTime<Utc>>, Arc<RasterMetadata>);
 
 pub struct CoverageService {
     db: Arc<Database>,
+    /// Raster metadata by asset href, with the `updated_at` of the item it
+    /// was read for
+    metadata: RwLock<HashMap<String, CachedMetadata>>,
 }
 
 /// Collection extent derived from items
+#[derive(Debug)]
 struct CollectionExtent {
     minx: f64,
     miny: f64,
@@ -21,7 +47,37 @@ struct CollectionExtent {
 
 impl CoverageService {
     pub fn new(db: Arc<Database>) -> Self {
-        Self { db }
+        Self {
+            db,
+            metadata: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Metadata of a raster asset, read from its header once per change of
+    /// the item
+    async fn raster_metadata(
+        &self,
+        href: &str,
+        updated_at: Option<DateTime<Utc>>,
+    ) -> AppResult<Arc<RasterMetadata>> {
+        let cached = self
+            .metadata
+            .read()
+            .ok()
+            .and_then(|metadata| metadata.get(href).cloned())
+            .filter(|(at, _)| *at == updated_at);
+        if let Some((_, metadata)) = cached {
+            return Ok(metadata);
+        }
+
+        let metadata = Arc::new(cog::extract_remote_raster_metadata(href).await?);
+        if let Ok(mut cache) = self.metadata.write() {
+            if cache.len() >= MAX_CACHED_METADATA {
+                cache.clear();
+            }
+            cache.insert(href.to_string(), (updated_at, metadata.clone()));
+        }
+        Ok(metadata)
     }
 
     pub async fn get_collection(
@@ -38,17 +94,19 @@ impl CoverageService {
         Ok(collection)
     }
 
-    /// Get the spatial extent of a collection by aggregating item geometries
+    /// Get the spatial extent of a collection by aggregating item geometries,
+    /// in the CRS with the given SRID
     async fn get_collection_extent(
         &self,
         collection_id: uuid::Uuid,
+        srid: i32,
     ) -> AppResult<CollectionExtent> {
         let sql = r#"
             SELECT
-                ST_XMin(ST_Extent(geometry)) as minx,
-                ST_YMin(ST_Extent(geometry)) as miny,
-                ST_XMax(ST_Extent(geometry)) as maxx,
-                ST_YMax(ST_Extent(geometry)) as maxy
+                ST_XMin(ST_Extent(ST_Transform(geometry, $2))) as minx,
+                ST_YMin(ST_Extent(ST_Transform(geometry, $2))) as miny,
+                ST_XMax(ST_Extent(ST_Transform(geometry, $2))) as maxx,
+                ST_YMax(ST_Extent(ST_Transform(geometry, $2))) as maxy
             FROM spatialvault.items
             WHERE collection_id = $1
         "#;
@@ -56,6 +114,7 @@ impl CoverageService {
         let extent: Option<(Option<f64>, Option<f64>, Option<f64>, Option<f64>)> =
             sqlx::query_as(sql)
                 .bind(collection_id)
+                .bind(srid)
                 .fetch_optional(self.db.pool())
                 .await?;
 
@@ -78,6 +137,36 @@ impl CoverageService {
         }
     }
 
+    /// Metadata of the newest item's data asset, read from its header
+    ///
+    /// Items of a collection are assumed to share bands and grid. Returns
+    /// `None` when there are no items, so callers can fall back to estimates;
+    /// headers that cannot be read (such as without the `gdal-support`
+    /// feature) are an error.
+    async fn get_raster_metadata(
+        &self,
+        collection_id: uuid::Uuid,
+    ) -> AppResult<Option<Arc<RasterMetadata>>> {
+        let asset: Option<(String, Option<DateTime<Utc>>)> = sqlx::query_as(
+            r#"
+            SELECT a.href, i.updated_at
+            FROM spatialvault.assets a
+            JOIN spatialvault.items i ON a.item_id = i.id
+            WHERE i.collection_id = $1 AND a.key = 'data'
+            ORDER BY i.datetime DESC NULLS LAST, i.created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(collection_id)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        match asset {
+            Some((href, updated_at)) => self.raster_metadata(&href, updated_at).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
     pub async fn get_domainset(&self, username: &str, collection_id: &str) -> AppResult<DomainSet> {
         let collection = self
             .get_collection(username, collection_id)
@@ -92,40 +181,44 @@ impl CoverageService {
             ));
         }
 
-        // Get actual extent from items
-        let extent = self.get_collection_extent(collection.id).await?;
-
-        // Estimate resolution based on extent (this would ideally come from COG metadata)
-        let x_range = extent.maxx - extent.minx;
-        let y_range = extent.maxy - extent.miny;
-        let estimated_resolution = f64::min(x_range, y_range) / 1000.0; // Rough estimate
-
-        Ok(DomainSet {
-            domain_type: "DomainSet".to_string(),
-            general_grid: GeneralGrid {
-                grid_type: "GeneralGridCoverage".to_string(),
-                srs_name: "http://www.opengis.net/def/crs/EPSG/0/4326".to_string(),
-                axis_labels: vec!["Lat".to_string(), "Long".to_string()],
-                axis: vec![
-                    GridAxis {
-                        axis_type: "RegularAxis".to_string(),
-                        axis_label: "Lat".to_string(),
-                        lower_bound: extent.miny,
-                        upper_bound: extent.maxy,
-                        resolution: estimated_resolution.max(0.0001),
-                        uom_label: "deg".to_string(),
-                    },
-                    GridAxis {
-                        axis_type: "RegularAxis".to_string(),
-                        axis_label: "Long".to_string(),
-                        lower_bound: extent.minx,
-                        upper_bound: extent.maxx,
-                        resolution: estimated_resolution.max(0.0001),
-                        uom_label: "deg".to_string(),
-                    },
-                ],
-            },
-        })
+        let domainset = if let Some(metadata) = self.get_raster_metadata(collection.id).await? {
+            let extent = self
+                .get_collection_extent(collection.id, metadata.srid)
+                .await?;
+            grid_domainset(metadata.srid, &extent, metadata.resolution, true)
+        } else {
+            // Without readable metadata, estimate the resolution from the extent
+            let extent = self.get_collection_extent(collection.id, 4326).await?;
+            let x_range = extent.maxx - extent.minx;
+            let y_range = extent.maxy - extent.miny;
+            let estimated_resolution = (f64::min(x_range, y_range) / 1000.0).max(0.0001);
+
+            grid_domainset(
+                4326,
+                &extent,
+                [estimated_resolution, estimated_resolution],
+                false,
+            )
+        };
+
+        let times = self.get_item_datetimes(collection.id).await?;
+        Ok(with_time_axis(domainset, &times))
+    }
+
+    /// Distinct datetimes of the items of a collection, oldest first
+    async fn get_item_datetimes(&self, collection_id: uuid::Uuid) -> AppResult<Vec<DateTime<Utc>>> {
+        let times: Vec<DateTime<Utc>> = sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT datetime FROM spatialvault.items
+            WHERE collection_id = $1 AND datetime IS NOT NULL
+            ORDER BY datetime
+            "#,
+        )
+        .bind(collection_id)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(times)
     }
 
     pub async fn get_rangetype(&self, username: &str, collection_id: &str) -> AppResult<RangeType> {
@@ -142,15 +235,28 @@ impl CoverageService {
             ));
         }
 
+        if let Some(metadata) = self.get_raster_metadata(collection.id).await?
+            && !metadata.band_info.is_empty()
+        {
+            return Ok(RangeType {
+                range_type: "DataRecord".to_string(),
+                field: metadata
+                    .band_info
+                    .iter()
+                    .enumerate()
+                    .map(|(index, band)| band_field(index + 1, band))
+                    .collect(),
+            });
+        }
+
         // Get number of items to use as hint for band count
-        // In a full implementation, we would read the COG metadata
         let item_count: (i64,) =
             sqlx::query_as("SELECT COUNT(*) FROM spatialvault.items WHERE collection_id = $1")
                 .bind(collection.id)
                 .fetch_one(self.db.pool())
                 .await?;
 
-        // Default to single band description (would be enhanced with GDAL metadata)
+        // Without readable metadata, describe a single band
         Ok(RangeType {
             range_type: "DataRecord".to_string(),
             field: vec![RangeField {
@@ -158,66 +264,157 @@ impl CoverageService {
                 id: "band1".to_string(),
                 name: "Band 1".to_string(),
                 description: Some(format!("Raster data from {} items", item_count.0)),
-                definition: "http://www.opengis.net/def/property/OGC/0/Radiance".to_string(),
+                definition: QUANTITY_DEFINITION.to_string(),
                 uom: UnitOfMeasure {
                     uom_type: "UnitReference".to_string(),
                     code: "1".to_string(), // Dimensionless by default
                 },
+                data_type: None,
+                nil_values: Vec::new(),
             }],
         })
     }
 
+    /// Data of the newest item's data asset in the requested time as a COG,
+    /// with the bands selected by the range subset
+    ///
+    /// Items of a collection are assumed to share bands and grid, as for
+    /// the range type.
+    #[tracing::instrument(skip(self, params))]
     pub async fn get_coverage_data(
         &self,
-        username: &str,
-        collection_id: &str,
-        _params: &CoverageSubsetParams,
+        collection_id: uuid::Uuid,
+        params: &CoverageSubsetParams,
     ) -> AppResult<Vec<u8>> {
-        let collection = self
-            .get_collection(username, collection_id)
-            .await?
-            .ok_or_else(|| {
-                AppError::NotFound(format!("Collection not found: {}", collection_id))
-            })?;
-
-        if collection.collection_type != "raster" {
+        if params.crs.is_some() || params.subset_crs.is_some() {
             return Err(AppError::BadRequest(
-                "Coverages only available for raster collections".to_string(),
+                "Coverage data is only available in its native CRS".to_string(),
             ));
         }
+        if !matches!(
+            params.output_format(),
+            "tif" | "tiff" | "geotiff" | "image/tiff" | "image/geotiff"
+        ) {
+            return Err(AppError::BadRequest(format!(
+                "Unsupported coverage format: {}",
+                params.output_format()
+            )));
+        }
 
-        // Get the primary asset for the first item in the collection
-        // In a full implementation, we would:
-        // 1. Select items based on subsetting parameters
-        // 2. Use GDAL to read and transform the COG data
-        // 3. Apply any requested subsetting/resampling
-
-        let asset: Option<(String,)> = sqlx::query_as(
+        // The time slice is the newest item in the requested time, so
+        // undated items are only returned without one
+        let (start, end) = params.datetime_bounds()?;
+        let asset: Option<(String, Option<DateTime<Utc>>)> = sqlx::query_as(
             r#"
-            SELECT a.href
+            SELECT a.href, i.updated_at
             FROM spatialvault.assets a
             JOIN spatialvault.items i ON a.item_id = i.id
             WHERE i.collection_id = $1 AND a.key = 'data'
+              AND ($2::timestamptz IS NULL OR i.datetime >= $2)
+              AND ($3::timestamptz IS NULL OR i.datetime <= $3)
+            ORDER BY i.datetime DESC NULLS LAST, i.created_at DESC
             LIMIT 1
             "#,
         )
-        .bind(collection.id)
+        .bind(collection_id)
+        .bind(start)
+        .bind(end)
         .fetch_optional(self.db.pool())
         .await?;
+        let Some((href, updated_at)) = asset else {
+            return Err(AppError::NotFound(match params.datetime {
+                Some(_) => "No raster data available at this datetime".to_string(),
+                None => "No raster data available for this collection".to_string(),
+            }));
+        };
 
-        match asset {
-            Some((href,)) => {
-                // For now, return a redirect hint in the error
-                // A full implementation would use GDAL to read the COG
-                Err(AppError::Internal(format!(
-                    "Coverage data available at: {}. Direct access requires GDAL integration.",
-                    href
-                )))
-            }
-            None => Err(AppError::NotFound(
-                "No raster data available for this collection".to_string(),
-            )),
+        // Bands are selected by the names the range type gives them
+        let metadata = self.raster_metadata(&href, updated_at).await?;
+        let band_names: Vec<String> = metadata
+            .band_info
+            .iter()
+            .enumerate()
+            .map(|(index, band)| band_field(index + 1, band).name)
+            .collect();
+        let bands = params.selected_bands(&band_names)?;
+
+        // Only the window is read, and only if it fits the budget
+        let window = params.window(metadata.bounds, metadata.resolution)?;
+        let sample_bytes: u64 = bands
+            .iter()
+            .map(|band| sample_bytes(&metadata.band_info[band - 1].data_type))
+            .sum();
+        let bytes = u64::from(window.size[0]) * u64::from(window.size[1]) * sample_bytes;
+        if bytes > MAX_COVERAGE_BYTES {
+            return Err(AppError::BadRequest(format!(
+                "Coverage of {} x {} pixels is too large ({} MiB, at most {} MiB); request a \
+                 subset, fewer properties or scale it down",
+                window.size[0],
+                window.size[1],
+                bytes / (1024 * 1024),
+                MAX_COVERAGE_BYTES / (1024 * 1024)
+            )));
+        }
+
+        cog::read_window(&href, bands, window).await
+    }
+
+    /// Sample the data assets of the items intersecting `coords` at CRS84 points
+    ///
+    /// Items are returned newest first. Undated items are excluded when a
+    /// datetime filter is given.
+    pub async fn sample_items(
+        &self,
+        collection_id: uuid::Uuid,
+        coords: &Coords,
+        datetime: DateTimeBounds,
+        points: Vec<[f64; 2]>,
+    ) -> AppResult<Vec<SampledItem>> {
+        let (start, end) = datetime;
+        let items: Vec<(Option<chrono::DateTime<chrono::Utc>>, String)> = sqlx::query_as(
+            r#"
+            SELECT i.datetime, a.href
+            FROM spatialvault.items i
+            JOIN spatialvault.assets a ON a.item_id = i.id AND a.key = 'data'
+            WHERE i.collection_id = $1
+              AND ST_Intersects(i.geometry, ST_GeomFromText($2, 4326))
+              AND ($3::timestamptz IS NULL OR i.datetime >= $3)
+              AND ($4::timestamptz IS NULL OR i.datetime <= $4)
+            ORDER BY i.datetime DESC NULLS LAST, i.created_at DESC
+            LIMIT $5
+            "#,
+        )
+        .bind(collection_id)
+        .bind(coords.to_wkt())
+        .bind(start)
+        .bind(end)
+        .bind(MAX_SAMPLED_ITEMS as i64 + 1)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        if items.is_empty() {
+            return Err(AppError::NotFound(
+                "No raster data at the requested location".to_string(),
+            ));
+        }
+        if items.len() > MAX_SAMPLED_ITEMS {
+            return Err(AppError::BadRequest(format!(
+                "The query matches more than {} items; narrow it with datetime",
+                MAX_SAMPLED_ITEMS
+            )));
+        }
+
+        let points = std::sync::Arc::new(points);
+        let mut samples = Vec::with_capacity(items.len());
+        for (datetime, href) in items {
+            let points = points.clone();
+            let values = tokio::task::spawn_blocking(move || sample_raster(&href, &points))
+                .await
+                .map_err(|e| AppError::Processing(format!("Task join error: {}", e)))??;
+            samples.push(SampledItem { datetime, values });
         }
+
+        Ok(samples)
     }
 
     /// Get asset URLs for a collection (useful for clients that can read COGs directly)
@@ -250,4 +447,287 @@ impl CoverageService {
 
         Ok(assets)
     }
+
+    /// Footprint bounds and asset href of every item with the asset, newest
+    /// first
+    pub async fn get_mosaic_items(
+        &self,
+        collection_id: uuid::Uuid,
+        asset_key: &str,
+    ) -> AppResult<Vec<MosaicItem>> {
+        let items: Vec<MosaicItem> = sqlx::query_as(
+            r#"
+            SELECT a.href,
+                   ST_XMin(i.geometry) AS minx, ST_YMin(i.geometry) AS miny,
+                   ST_XMax(i.geometry) AS maxx, ST_YMax(i.geometry) AS maxy
+            FROM spatialvault.assets a
+            JOIN spatialvault.items i ON a.item_id = i.id
+            WHERE i.collection_id = $1 AND a.key = $2
+            ORDER BY i.datetime DESC NULLS LAST, i.created_at DESC
+            "#,
+        )
+        .bind(collection_id)
+        .bind(asset_key)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(items)
+    }
+}
+
+/// Asset of an item placed in a mosaic, with the item's bounds in WGS 84
+#[derive(Debug, sqlx::FromRow)]
+pub struct MosaicItem {
+    pub href: String,
+    pub minx: f64,
+    pub miny: f64,
+    pub maxx: f64,
+    pub maxy: f64,
+}
+
+/// Domain set of a regular grid covering `extent`
+///
+/// Geographic grids use the EPSG:4326 axis order (latitude first); other
+/// CRSs are assumed to be projected with easting first. Grid limits are
+/// only given when the resolution is known rather than estimated.
+/// Bytes of a sample of a GDAL data type
+fn sample_bytes(data_type: &str) -> u64 {
+    match data_type {
+        "Byte" | "Int8" => 1,
+        "UInt16" | "Int16" | "Float16" => 2,
+        "UInt32" | "Int32" | "Float32" | "CInt16" => 4,
+        "CInt32" | "CFloat32" | "UInt64" | "Int64" | "Float64" => 8,
+        _ => 16,
+    }
+}
+
+fn grid_domainset(
+    srid: i32,
+    extent: &CollectionExtent,
+    resolution: [f64; 2],
+    with_limits: bool,
+) -> DomainSet {
+    let axis = |label: &str, lower: f64, upper: f64, resolution: f64, uom: &str| GridAxis {
+        axis_type: "RegularAxis".to_string(),
+        axis_label: label.to_string(),
+        lower_bound: lower,
+        upper_bound: upper,
+        resolution,
+        uom_label: uom.to_string(),
+    };
+    let x_axis = |label: &str, uom: &str| axis(label, extent.minx, extent.maxx, resolution[0], uom);
+    let y_axis = |label: &str, uom: &str| axis(label, extent.miny, extent.maxy, resolution[1], uom);
+
+    let (srs_name, axes) = if srid == 4326 {
+        (
+            crs::EPSG_4326.to_string(),
+            vec![y_axis("Lat", "deg"), x_axis("Long", "deg")],
+        )
+    } else {
+        (
+            crs::srid_to_uri(srid),
+            vec![x_axis("E", "m"), y_axis("N", "m")],
+        )
+    };
+
+    let index_axis = |label: &str, span: f64, resolution: f64| IndexAxis {
+        axis_type: "IndexAxis".to_string(),
+        axis_label: label.to_string(),
+        lower_bound: 0,
+        upper_bound: ((span / resolution).ceil() as i64 - 1).max(0),
+    };
+    let grid_limits = with_limits.then(|| GridLimits {
+        limits_type: "GridLimits".to_string(),
+        srs_name: "http://www.opengis.net/def/crs/OGC/0/Index2D".to_string(),
+        axis_labels: vec!["i".to_string(), "j".to_string()],
+        axis: vec![
+            index_axis("i", extent.maxx - extent.minx, resolution[0]),
+            index_axis("j", extent.maxy - extent.miny, resolution[1]),
+        ],
+    });
+
+    DomainSet {
+        domain_type: "DomainSet".to_string(),
+        general_grid: GeneralGrid {
+            grid_type: "GeneralGridCoverage".to_string(),
+            srs_name,
+            axis_labels: axes.iter().map(|a| a.axis_label.clone()).collect(),
+            axis: axes.into_iter().map(DomainAxis::Regular).collect(),
+            grid_limits,
+        },
+    }
+}
+
+/// Domain set with a time axis of the dates of the items, making the
+/// collection a time series; unchanged without dated items
+fn with_time_axis(mut domainset: DomainSet, times: &[DateTime<Utc>]) -> DomainSet {
+    if times.is_empty() {
+        return domainset;
+    }
+    let grid = &mut domainset.general_grid;
+    grid.srs_name = format!(
+        "http://www.opengis.net/def/crs-compound?1={}&2={}",
+        grid.srs_name, GREGORIAN_TIME_CRS
+    );
+    grid.axis_labels.push("time".to_string());
+    grid.axis.push(DomainAxis::Irregular(IrregularAxis {
+        axis_type: "IrregularAxis".to_string(),
+        axis_label: "time".to_string(),
+        uom_label: "s".to_string(),
+        coordinate: times
+            .iter()
+            .map(|time| time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+            .collect(),
+    }));
+    if let Some(limits) = &mut grid.grid_limits {
+        limits.srs_name = "http://www.opengis.net/def/crs/OGC/0/Index3D".to_string();
+        limits.axis_labels.push("k".to_string());
+        limits.axis.push(IndexAxis {
+            axis_type: "IndexAxis".to_string(),
+            axis_label: "k".to_string(),
+            lower_bound: 0,
+            upper_bound: times.len() as i64 - 1,
+        });
+    }
+    domainset
+}
+
+/// Range field describing one band
+fn band_field(index: usize, band: &BandMetadata) -> RangeField {
+    let name = if band.description.is_empty() {
+        format!("Band {}", index)
+    } else {
+        band.description.clone()
+    };
+    let unit = if band.unit.is_empty() {
+        "1"
+    } else {
+        band.unit.as_str()
+    };
+
+    RangeField {
+        field_type: "Quantity".to_string(),
+        id: format!("band{}", index),
+        name,
+        description: None,
+        definition: QUANTITY_DEFINITION.to_string(),
+        uom: UnitOfMeasure {
+            uom_type: "UnitReference".to_string(),
+            code: unit.to_string(),
+        },
+        data_type: Some(band.data_type.clone()),
+        nil_values: band
+            .nodata
+            .map(|value| NilValue {
+                reason: "http://www.opengis.net/def/nil/OGC/0/missing".to_string(),
+                value,
+            })
+            .into_iter()
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_domainset() {
+        let extent = CollectionExtent {
+            minx: 500_000.0,
+            miny: 6_000_000.0,
+            maxx: 510_000.0,
+            maxy: 6_005_000.0,
+        };
+        let domainset = grid_domainset(3006, &extent, [10.0, 10.0], true);
+        let grid = &domainset.general_grid;
+        assert_eq!(grid.srs_name, "http://www.opengis.net/def/crs/EPSG/0/3006");
+        assert_eq!(grid.axis_labels, ["E", "N"]);
+        assert_eq!(regular(&grid.axis[1]).lower_bound, 6_000_000.0);
+
+        let limits = grid.grid_limits.as_ref().unwrap();
+        assert_eq!(limits.axis[0].upper_bound, 999);
+        assert_eq!(limits.axis[1].upper_bound, 499);
+
+        let extent = CollectionExtent {
+            minx: 10.0,
+            miny: 50.0,
+            maxx: 11.0,
+            maxy: 51.0,
+        };
+        let domainset = grid_domainset(4326, &extent, [0.01, 0.01], false);
+        let grid = &domainset.general_grid;
+        assert_eq!(grid.srs_name, crs::EPSG_4326);
+        assert_eq!(grid.axis_labels, ["Lat", "Long"]);
+        assert_eq!(regular(&grid.axis[0]).upper_bound, 51.0);
+        assert!(grid.grid_limits.is_none());
+    }
+
+    fn regular(axis: &DomainAxis) -> &GridAxis {
+        match axis {
+            DomainAxis::Regular(axis) => axis,
+            DomainAxis::Irregular(_) => panic!("Expected a regular axis"),
+        }
+    }
+
+    #[test]
+    fn test_with_time_axis() {
+        let extent = CollectionExtent {
+            minx: 10.0,
+            miny: 50.0,
+            maxx: 11.0,
+            maxy: 51.0,
+        };
+        let domainset = with_time_axis(grid_domainset(4326, &extent, [0.01, 0.01], true), &[]);
+        assert_eq!(domainset.general_grid.srs_name, crs::EPSG_4326);
+        assert_eq!(domainset.general_grid.axis.len(), 2);
+
+        let times = ["2024-05-01T00:00:00Z", "2024-06-01T00:00:00Z"]
+            .map(|time| time.parse::<DateTime<Utc>>().unwrap());
+        let domainset = with_time_axis(grid_domainset(4326, &extent, [0.01, 0.01], true), &times);
+        let grid = &domainset.general_grid;
+        assert!(
+            grid.srs_name
+                .starts_with("http://www.opengis.net/def/crs-compound?1=")
+        );
+        assert_eq!(grid.axis_labels, ["Lat", "Long", "time"]);
+        let DomainAxis::Irregular(time) = &grid.axis[2] else {
+            panic!("Expected an irregular time axis");
+        };
+        assert_eq!(
+            time.coordinate,
+            ["2024-05-01T00:00:00Z", "2024-06-01T00:00:00Z"]
+        );
+
+        let limits = grid.grid_limits.as_ref().unwrap();
+        assert_eq!(limits.axis_labels, ["i", "j", "k"]);
+        assert_eq!(limits.axis[2].upper_bound, 1);
+    }
+
+    #[test]
+    fn test_band_field() {
+        let band = BandMetadata {
+            description: "elevation".to_string(),
+            data_type: "Float32".to_string(),
+            nodata: Some(-9999.0),
+            unit: "metre".to_string(),
+        };
+        let field = band_field(2, &band);
+        assert_eq!(field.id, "band2");
+        assert_eq!(field.name, "elevation");
+        assert_eq!(field.uom.code, "metre");
+        assert_eq!(field.data_type.as_deref(), Some("Float32"));
+        assert_eq!(field.nil_values[0].value, -9999.0);
+
+        let band = BandMetadata {
+            description: String::new(),
+            data_type: "Byte".to_string(),
+            nodata: None,
+            unit: String::new(),
+        };
+        let field = band_field(1, &band);
+        assert_eq!(field.name, "Band 1");
+        assert_eq!(field.uom.code, "1");
+        assert!(field.nil_values.is_empty());
+    }
 }
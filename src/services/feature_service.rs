@@ -1,23 +1,427 @@
-use std::collections::HashMap;
+use futures::{StreamExt, TryStreamExt};
+use sqlx::Arguments;
+use sqlx::postgres::PgArguments;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::api::collections::computed::{computed_properties_sql, parse_computed_properties};
+use crate::api::collections::relations::parse_relations;
+use crate::api::collections::schemas::RegisteredAsset;
 use crate::api::features::Feature;
-use crate::api::features::crs::transform_geometry_sql;
-use crate::api::features::query::Cql2Parser;
+use crate::api::features::crs::{input_geometry_sql, transform_geometry_sql};
+use crate::api::features::query::{
+    ComputedSql, Cql2Parser, JoinRelation, properties_with_computed_sql,
+};
 use crate::auth::quote_ident;
-use crate::db::{Collection, Database};
+use crate::db::{Collection, Database, QueryClass, finish_write};
 use crate::error::{AppError, AppResult};
+use crate::processing::asset_check::AssetValidation;
 
 pub struct FeatureService {
     db: Arc<Database>,
+    /// Roll writes back instead of committing them
+    validate_only: bool,
+    /// CRS of the geometries of writes, when not the storage CRS
+    input_srid: Option<i32>,
+}
+
+/// Features read per query by streamed exports
+pub const EXPORT_PAGE_SIZE: i64 = 1000;
+
+/// Type of a feature property, as found in the stored features
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropertyType {
+    String,
+    Integer,
+    Number,
+    Boolean,
+}
+
+impl PropertyType {
+    /// Type of a property from the distinct `jsonb_typeof` of its values,
+    /// and whether all its numbers are integral
+    ///
+    /// Mixed types, objects and arrays are reported as strings.
+    pub fn from_json_types(types: &[String], integral: bool) -> Self {
+        match types {
+            [t] if t == "number" && integral => Self::Integer,
+            [t] if t == "number" => Self::Number,
+            [t] if t == "boolean" => Self::Boolean,
+            _ => Self::String,
+        }
+    }
+
+    /// JSON Schema `type` of the property
+    pub fn json_type(self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Integer => "integer",
+            Self::Number => "number",
+            Self::Boolean => "boolean",
+        }
+    }
+}
+
+/// Types of the stored properties of the features or items of a collection,
+/// scanning all of them on the background pool
+pub(crate) async fn scan_property_types(
+    db: &Database,
+    collection: &Collection,
+) -> AppResult<Vec<(String, PropertyType)>> {
+    let (source, filter) = if collection.has_feature_table() {
+        let table = format!(
+            "{}.{}",
+            quote_ident(&collection.schema_name),
+            quote_ident(&collection.table_name)
+        );
+        (table, "true")
+    } else {
+        ("spatialvault.items".to_string(), "collection_id = $1")
+    };
+    let sql = format!(
+        r#"
+        SELECT
+            key,
+            array_agg(DISTINCT jsonb_typeof(value)),
+            bool_and(jsonb_typeof(value) <> 'number' OR value::text ~ '^-?[0-9]+$')
+        FROM {}, jsonb_each(properties)
+        WHERE jsonb_typeof(value) <> 'null' AND {}
+        GROUP BY key
+        ORDER BY key
+        "#,
+        source, filter
+    );
+
+    let mut tx = db.begin_with_budget(QueryClass::Background).await?;
+    let mut query = sqlx::query_as(&sql);
+    if !collection.has_feature_table() {
+        query = query.bind(collection.id);
+    }
+    let rows: Vec<(String, Vec<String>, bool)> = query.fetch_all(&mut *tx).await?;
+    tx.commit().await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(key, types, integral)| (key, PropertyType::from_json_types(&types, integral)))
+        .collect())
+}
+
+/// How `numberMatched` is determined when listing features
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountMode {
+    /// Always run an exact `COUNT(*)`
+    Exact,
+    /// Use the planner's row estimate (derived from `pg_class.reltuples`)
+    /// when it exceeds the threshold, otherwise count exactly
+    EstimateAbove(u64),
+    /// Do not count; `numberMatched` is omitted
+    Skip,
+}
+
+/// What a listing returns of the matching features
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResultType {
+    /// The features
+    #[default]
+    Results,
+    /// Only the ids of the features; geometry and properties are left out
+    Ids,
+    /// Only `numberMatched`; no features are fetched
+    Hits,
+}
+
+/// What a bulk insert does with features whose key property matches an
+/// existing feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictAction {
+    /// Replace the geometry and properties of the existing features
+    Update,
+    /// Keep the existing features and drop the new one
+    Skip,
+}
+
+/// Match features of a bulk insert to existing features by a property
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnConflict {
+    pub property: String,
+    pub action: ConflictAction,
+}
+
+/// An advisory lock on a feature or item, held until it expires or is released
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct FeatureLock {
+    pub feature_id: Uuid,
+    pub locked_by: String,
+    pub locked_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Conflict for a write to a feature someone else has locked
+fn lock_conflict(lock: &FeatureLock) -> AppError {
+    AppError::Conflict(format!(
+        "Feature is locked by {} until {}",
+        lock.locked_by,
+        lock.expires_at.to_rfc3339()
+    ))
+}
+
+/// Number of features a bulk insert inserted, updated and skipped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BulkInsertCounts {
+    pub inserted: u64,
+    pub updated: u64,
+    /// Features matching an existing feature that was left unchanged, or
+    /// superseded by a later feature with the same key
+    pub skipped: u64,
+}
+
+/// Bulk insert of vector features into one collection, in a single transaction
+///
+/// Nothing is visible until [`FeatureBulkInsert::commit`]; dropping the
+/// insert rolls everything back.
+pub struct FeatureBulkInsert {
+    tx: sqlx::Transaction<'static, sqlx::Postgres>,
+    sql: String,
+    collection_id: String,
+    collection_uuid: Uuid,
+    /// Collection version the insert is conditional on (If-Match)
+    expected_version: Option<i64>,
+    has_geometry: bool,
+    /// Names of computed properties, which are not stored
+    computed: Vec<String>,
+    on_conflict: Option<OnConflict>,
+    counts: BulkInsertCounts,
+    validate_only: bool,
+}
+
+impl FeatureBulkInsert {
+    /// Insert a batch of (geometry, properties) pairs
+    pub async fn insert(
+        &mut self,
+        features: Vec<(serde_json::Value, serde_json::Value)>,
+    ) -> AppResult<()> {
+        if !self.has_geometry {
+            for (geometry, _) in &features {
+                check_no_geometry(&self.collection_id, geometry)?;
+            }
+        }
+        let received = features.len() as u64;
+        let features = match &self.on_conflict {
+            Some(on_conflict) => last_per_key(features, &on_conflict.property),
+            None => features,
+        };
+        let (geometries, properties): (Vec<String>, Vec<serde_json::Value>) = features
+            .into_iter()
+            .map(|(geometry, mut properties)| {
+                if let Some(properties) = properties.as_object_mut() {
+                    properties.retain(|key, _| !self.computed.contains(key));
+                }
+                (geometry.to_string(), properties)
+            })
+            .unzip();
+
+        match &self.on_conflict {
+            Some(on_conflict) => {
+                let (inserted, updated): (i64, i64) = sqlx::query_as(&self.sql)
+                    .bind(&geometries)
+                    .bind(&properties)
+                    .bind(&on_conflict.property)
+                    .fetch_one(&mut *self.tx)
+                    .await?;
+                self.counts.inserted += inserted as u64;
+                self.counts.updated += updated as u64;
+                // Features superseded by a later one with the same key in
+                // the batch are skipped too
+                self.counts.skipped += received.saturating_sub((inserted + updated) as u64);
+            }
+            None => {
+                let result = sqlx::query(&self.sql)
+                    .bind(&geometries)
+                    .bind(&properties)
+                    .execute(&mut *self.tx)
+                    .await?;
+                self.counts.inserted += result.rows_affected();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Commit the insert and bump the collection version
+    ///
+    /// If the collection changed since the insert began although it was
+    /// conditional on its version, everything is rolled back.
+    pub async fn commit(mut self) -> AppResult<BulkInsertCounts> {
+        bump_collection_version(&mut self.tx, self.collection_uuid, self.expected_version).await?;
+
+        finish_write(self.tx, self.validate_only).await?;
+        Ok(self.counts)
+    }
+}
+
+/// Bump the version of a collection after a write to it
+///
+/// With `expected_version` (If-Match), the write is only valid if the
+/// collection has not changed in the meantime; the caller's transaction must
+/// then be rolled back, which dropping it does.
+async fn bump_collection_version(
+    conn: &mut sqlx::PgConnection,
+    collection_id: Uuid,
+    expected_version: Option<i64>,
+) -> AppResult<()> {
+    sqlx::query_scalar::<_, i64>(
+        r#"
+        UPDATE spatialvault.collections SET version = version + 1
+        WHERE id = $1 AND ($2::bigint IS NULL OR version = $2)
+        RETURNING version
+        "#,
+    )
+    .bind(collection_id)
+    .bind(expected_version)
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| AppError::PreconditionFailed("Collection has been modified".to_string()))?;
+    Ok(())
+}
+
+/// Fail early if a collection has changed since the client read it
+fn check_collection_version(
+    collection: &Collection,
+    expected_version: Option<i64>,
+) -> AppResult<()> {
+    if let Some(version) = expected_version
+        && collection.version != version
+    {
+        return Err(AppError::PreconditionFailed(
+            "Collection has been modified".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// SQL inserting a batch of features, matching them to existing features by
+/// the property bound as `$3`; returns the numbers of inserted features and
+/// of features that updated existing ones
+///
+/// A feature updates all existing features with its key, but counts once.
+fn upsert_sql(
+    collection: &Collection,
+    input_srid: Option<i32>,
+    storage_srid: i32,
+    action: ConflictAction,
+) -> String {
+    let table = format!(
+        "{}.{}",
+        quote_ident(&collection.schema_name),
+        quote_ident(&collection.table_name)
+    );
+    let (geometry, columns, assignments, changed) = if collection.has_geometry() {
+        (
+            input_geometry_sql("g", input_srid, storage_srid),
+            "geometry, properties",
+            "geometry = input.geometry, properties = input.properties",
+            "(existing.geometry, existing.properties) \
+             IS DISTINCT FROM (input.geometry, input.properties)",
+        )
+    } else {
+        (
+            "NULL::geometry".to_string(),
+            "properties",
+            "properties = input.properties",
+            "existing.properties IS DISTINCT FROM input.properties",
+        )
+    };
+    let updated = match action {
+        ConflictAction::Update => format!(
+            r#"
+            UPDATE {table} AS existing
+            SET {assignments}, version = existing.version + 1, updated_at = NOW()
+            FROM input
+            WHERE existing.properties->>$3 = input.properties->>$3 AND {changed}
+            RETURNING existing.properties->>$3 AS key
+            "#
+        ),
+        ConflictAction::Skip => "SELECT NULL::text AS key WHERE false".to_string(),
+    };
+    format!(
+        r#"
+        WITH input AS (
+            SELECT {geometry} AS geometry, p AS properties
+            FROM UNNEST($1::text[], $2::jsonb[]) AS f(g, p)
+        ),
+        updated AS ({updated}),
+        inserted AS (
+            INSERT INTO {table} ({columns})
+            SELECT {columns} FROM input
+            WHERE NOT EXISTS (
+                SELECT 1 FROM {table} AS existing
+                WHERE existing.properties->>$3 = input.properties->>$3
+            )
+            RETURNING id
+        )
+        SELECT (SELECT COUNT(*) FROM inserted), (SELECT COUNT(DISTINCT key) FROM updated)
+        "#
+    )
+}
+
+/// Keep only the last of the features sharing a value of `property`
+///
+/// Features without the property are all kept.
+fn last_per_key(
+    features: Vec<(serde_json::Value, serde_json::Value)>,
+    property: &str,
+) -> Vec<(serde_json::Value, serde_json::Value)> {
+    // Compare as PostgreSQL's `->>` does, so 1 and "1" are the same key
+    let key = |properties: &serde_json::Value| match properties.get(property) {
+        None | Some(serde_json::Value::Null) => None,
+        Some(serde_json::Value::String(text)) => Some(text.clone()),
+        Some(other) => Some(other.to_string()),
+    };
+    let mut last = HashMap::new();
+    for (index, (_, properties)) in features.iter().enumerate() {
+        if let Some(key) = key(properties) {
+            last.insert(key, index);
+        }
+    }
+    features
+        .into_iter()
+        .enumerate()
+        .filter(|(index, (_, properties))| key(properties).is_none_or(|key| last[&key] == *index))
+        .map(|(_, feature)| feature)
+        .collect()
 }
 
 impl FeatureService {
     pub fn new(db: Arc<Database>) -> Self {
-        Self { db }
+        Self {
+            db,
+            validate_only: false,
+            input_srid: None,
+        }
     }
 
+    /// A service that validates writes without committing them
+    pub fn validate_only(&self) -> Self {
+        Self {
+            db: self.db.clone(),
+            validate_only: true,
+            input_srid: self.input_srid,
+        }
+    }
+
+    /// A service reading the geometries of writes in a CRS, e.g. the one of
+    /// `Content-Crs`, and transforming them into the storage CRS
+    pub fn with_input_crs(&self, srid: i32) -> Self {
+        Self {
+            db: self.db.clone(),
+            validate_only: self.validate_only,
+            input_srid: Some(srid),
+        }
+    }
+
+    #[tracing::instrument(skip(self, username, filter, ids))]
+    #[allow(clippy::too_many_arguments)]
     pub async fn list_features(
         &self,
         username: &str,
@@ -26,28 +430,61 @@ impl FeatureService {
         offset: u32,
         bbox: Option<&str>,
         bbox_crs: Option<i32>,
+        intersects: Option<&str>,
+        within_distance: Option<[f64; 3]>,
+        clip: bool,
         target_crs: Option<i32>,
         datetime: Option<&str>,
         filter: Option<&str>,
-    ) -> AppResult<(Vec<Feature>, usize, i32)> {
+        ids: Option<&[Uuid]>,
+        count: CountMode,
+        geom: Option<&str>,
+        result_type: ResultType,
+    ) -> AppResult<(Vec<Feature>, Option<usize>, i32)> {
         let collection = self.get_collection(collection_id).await?;
+        let geometry_column = collection.geometry_column(geom)?;
+        if clip && !collection.has_geometry() {
+            return Err(AppError::BadRequest(
+                "clip is only supported for vector collections".to_string(),
+            ));
+        }
 
         match collection.collection_type.as_str() {
-            "vector" => {
+            "vector" | "table" => {
                 self.list_vector_features(
+                    username,
                     &collection,
                     limit,
                     offset,
                     bbox,
                     bbox_crs,
+                    intersects,
+                    within_distance,
+                    clip,
                     target_crs,
                     filter,
+                    ids,
+                    count,
+                    &geometry_column,
+                    result_type,
                 )
                 .await
             }
             "raster" | "pointcloud" => {
-                self.list_items(&collection, collection_id, limit, offset, bbox, datetime)
-                    .await
+                self.list_items(
+                    &collection,
+                    collection_id,
+                    limit,
+                    offset,
+                    bbox,
+                    intersects,
+                    within_distance,
+                    datetime,
+                    ids,
+                    count,
+                    result_type,
+                )
+                .await
             }
             _ => Err(AppError::BadRequest(format!(
                 "Unknown collection type: {}",
@@ -57,23 +494,46 @@ impl FeatureService {
     }
 
     /// List vector features from user-schema tables
+    #[allow(clippy::too_many_arguments)]
     async fn list_vector_features(
         &self,
+        username: &str,
         collection: &Collection,
         limit: u32,
         offset: u32,
         bbox: Option<&str>,
         bbox_crs: Option<i32>,
+        intersects: Option<&str>,
+        within_distance: Option<[f64; 3]>,
+        clip: bool,
         target_crs: Option<i32>,
         filter: Option<&str>,
-    ) -> AppResult<(Vec<Feature>, usize, i32)> {
+        ids: Option<&[Uuid]>,
+        count: CountMode,
+        geometry_column: &str,
+        result_type: ResultType,
+    ) -> AppResult<(Vec<Feature>, Option<usize>, i32)> {
         let storage_srid = self.get_storage_srid(collection).await?;
-        let geometry_expr = transform_geometry_sql("geometry", storage_srid, target_crs);
 
         let mut where_clauses = Vec::new();
+        // Area of the bbox or intersects filter in the storage CRS
+        let mut filter_area = None;
+        let mut args = PgArguments::default();
+
+        // Restrict to the requested ids
+        if let Some(ids) = ids {
+            where_clauses.push("id = ANY($1)".to_string());
+            bind_arg(&mut args, ids.to_vec())?;
+        }
 
         // Add bbox filter
         if let Some(bbox_str) = bbox {
+            if !collection.has_geometry() {
+                return Err(AppError::BadRequest(format!(
+                    "Collection {} has no geometry to filter by bbox",
+                    collection.canonical_name
+                )));
+            }
             let parts: Vec<f64> = bbox_str.split(',').filter_map(|s| s.parse().ok()).collect();
             if parts.len() == 4 {
                 let bbox_srid = bbox_crs.unwrap_or(storage_srid);
@@ -81,20 +541,83 @@ impl FeatureService {
                     "ST_MakeEnvelope({}, {}, {}, {}, {})",
                     parts[0], parts[1], parts[2], parts[3], bbox_srid
                 );
-                if bbox_srid != storage_srid {
-                    where_clauses.push(format!(
-                        "ST_Intersects(geometry, ST_Transform({}, {}))",
-                        bbox_geom, storage_srid
-                    ));
-                } else {
-                    where_clauses.push(format!("ST_Intersects(geometry, {})", bbox_geom));
-                }
+                let area = transform_geometry_sql(&bbox_geom, bbox_srid, Some(storage_srid));
+                where_clauses.push(format!("ST_Intersects({}, {})", geometry_column, area));
+                filter_area = Some(area);
             }
         }
 
-        // Add CQL2 filter
+        // Add intersects filter; the geometry is in WGS 84
+        if let Some(intersects) = intersects {
+            if !collection.has_geometry() {
+                return Err(AppError::BadRequest(format!(
+                    "Collection {} has no geometry to filter by intersects",
+                    collection.canonical_name
+                )));
+            }
+            let intersects_geom = format!(
+                "ST_SetSRID(ST_GeomFromGeoJSON(${}::text), 4326)",
+                args.len() + 1
+            );
+            let area = transform_geometry_sql(&intersects_geom, 4326, Some(storage_srid));
+            where_clauses.push(format!("ST_Intersects({}, {})", geometry_column, area));
+            filter_area = Some(area);
+            bind_arg(&mut args, intersects.to_string())?;
+        }
+
+        // Clip the returned geometries to the filter area, repairing invalid
+        // geometries first as ST_Intersection fails on them
+        let returned_geometry = match (clip, filter_area) {
+            (false, _) => geometry_column.to_string(),
+            (true, Some(area)) => {
+                format!(
+                    "ST_Intersection(ST_MakeValid({}), {})",
+                    geometry_column, area
+                )
+            }
+            (true, None) => {
+                return Err(AppError::BadRequest(
+                    "clip requires a bbox or intersects filter".to_string(),
+                ));
+            }
+        };
+        let geometry_json = geometry_json_sql(
+            collection,
+            &transform_geometry_sql(&returned_geometry, storage_srid, target_crs),
+        );
+
+        // Add distance filter, measured on the spheroid whatever the storage CRS
+        if let Some([lon, lat, meters]) = within_distance {
+            if !collection.has_geometry() {
+                return Err(AppError::BadRequest(format!(
+                    "Collection {} has no geometry to filter by distance",
+                    collection.canonical_name
+                )));
+            }
+            let param = args.len() + 1;
+            where_clauses.push(format!(
+                "ST_DWithin({}::geography, ST_SetSRID(ST_MakePoint(${}, ${}), 4326)::geography, ${})",
+                transform_geometry_sql(geometry_column, storage_srid, Some(4326)),
+                param,
+                param + 1,
+                param + 2
+            ));
+            for value in [lon, lat, meters] {
+                bind_arg(&mut args, value)?;
+            }
+        }
+
+        let quoted_schema = quote_ident(&collection.schema_name);
+        let quoted_table = quote_ident(&collection.table_name);
+
+        // Add CQL2 filter, which may refer to related collections and
+        // computed properties
         if let Some(filter_expr) = filter {
-            let sql_filter = Cql2Parser::parse_to_sql(filter_expr, "")?;
+            let table = format!("{}.{}", quoted_schema, quoted_table);
+            let relations = self.join_relations(collection, username).await?;
+            let computed = computed_sql(collection, &format!("{}.", table))?;
+            let sql_filter =
+                Cql2Parser::parse_to_sql_for_table(filter_expr, &table, &relations, &computed)?;
             where_clauses.push(sql_filter);
         }
 
@@ -104,23 +627,31 @@ impl FeatureService {
             where_clauses.join(" AND ")
         };
 
-        let quoted_schema = quote_ident(&collection.schema_name);
-        let quoted_table = quote_ident(&collection.table_name);
-
-        // Count query
-        let count_sql = format!(
-            r#"SELECT COUNT(*) FROM {}.{} WHERE {}"#,
-            quoted_schema, quoted_table, where_clause
-        );
-        let count: (i64,) = sqlx::query_as(&count_sql).fetch_one(self.db.pool()).await?;
+        let matched = self
+            .count_matches(
+                &format!("{}.{} WHERE {}", quoted_schema, quoted_table, where_clause),
+                &args,
+                count,
+            )
+            .await?;
+        if result_type == ResultType::Hits {
+            return Ok((Vec::new(), matched, target_crs.unwrap_or(storage_srid)));
+        }
 
-        // Data query
+        // Data query; only the ids are selected for a listing of ids
+        let (geometry_json, properties) = match result_type {
+            ResultType::Ids => ("NULL::jsonb".to_string(), "NULL::jsonb".to_string()),
+            _ => (
+                geometry_json,
+                properties_with_computed_sql("", &computed_sql(collection, "")?),
+            ),
+        };
         let sql = format!(
             r#"
             SELECT
                 id::text,
-                ST_AsGeoJSON({geometry_expr})::jsonb as geometry,
-                properties,
+                {geometry_json} as geometry,
+                {properties} as properties,
                 version
             FROM {}.{}
             WHERE {}
@@ -132,18 +663,25 @@ impl FeatureService {
             where_clause,
             limit,
             offset,
-            geometry_expr = geometry_expr
+            geometry_json = geometry_json,
+            properties = properties
         );
 
-        let rows: Vec<(String, serde_json::Value, Option<serde_json::Value>, i64)> =
-            sqlx::query_as(&sql).fetch_all(self.db.pool()).await?;
+        let mut tx = self.db.begin_with_budget(QueryClass::Features).await?;
+        let rows: Vec<(
+            String,
+            Option<serde_json::Value>,
+            Option<serde_json::Value>,
+            i64,
+        )> = sqlx::query_as_with(&sql, args).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
 
         let features: Vec<Feature> = rows
             .into_iter()
             .map(|(id, geometry, properties, _version)| Feature {
                 feature_type: "Feature".to_string(),
                 id,
-                geometry,
+                geometry: geometry.unwrap_or(serde_json::Value::Null),
                 properties: properties.unwrap_or(serde_json::json!({})),
                 links: None,
                 bbox: None,
@@ -154,14 +692,11 @@ impl FeatureService {
             })
             .collect();
 
-        Ok((
-            features,
-            count.0 as usize,
-            target_crs.unwrap_or(storage_srid),
-        ))
+        Ok((features, matched, target_crs.unwrap_or(storage_srid)))
     }
 
     /// List raster/pointcloud items from spatialvault.items (with assets)
+    #[allow(clippy::too_many_arguments)]
     async fn list_items(
         &self,
         collection: &Collection,
@@ -169,8 +704,13 @@ impl FeatureService {
         limit: u32,
         offset: u32,
         bbox: Option<&str>,
+        intersects: Option<&str>,
+        within_distance: Option<[f64; 3]>,
         datetime: Option<&str>,
-    ) -> AppResult<(Vec<Feature>, usize, i32)> {
+        ids: Option<&[Uuid]>,
+        count: CountMode,
+        result_type: ResultType,
+    ) -> AppResult<(Vec<Feature>, Option<usize>, i32)> {
         // Build parameterized query with dynamic conditions
         let mut where_clauses = vec!["collection_id = $1".to_string()];
         let mut param_index = 2u32;
@@ -196,6 +736,24 @@ impl FeatureService {
             param_index += 4;
         }
 
+        if intersects.is_some() {
+            where_clauses.push(format!(
+                "ST_Intersects(geometry, ST_SetSRID(ST_GeomFromGeoJSON(${}::text), 4326))",
+                param_index
+            ));
+            param_index += 1;
+        }
+
+        if within_distance.is_some() {
+            where_clauses.push(format!(
+                "ST_DWithin(geometry::geography, ST_SetSRID(ST_MakePoint(${}, ${}), 4326)::geography, ${})",
+                param_index,
+                param_index + 1,
+                param_index + 2
+            ));
+            param_index += 3;
+        }
+
         // Parse datetime filter - validate before using
         let datetime_start: Option<chrono::DateTime<chrono::FixedOffset>>;
         let datetime_end: Option<chrono::DateTime<chrono::FixedOffset>>;
@@ -250,36 +808,74 @@ impl FeatureService {
             datetime_exact = None;
         }
 
-        let where_clause = where_clauses.join(" AND ");
-
-        // Count query with parameterized bindings
-        let count_sql = format!(
-            "SELECT COUNT(*) FROM spatialvault.items WHERE {}",
-            where_clause
-        );
+        if ids.is_some() {
+            where_clauses.push(format!("id = ANY(${})", param_index));
+            param_index += 1;
+        }
 
-        // Build and execute count query
-        let mut count_query = sqlx::query_as::<_, (i64,)>(&count_sql).bind(collection.id);
+        let where_clause = where_clauses.join(" AND ");
 
+        // Bind the filter parameters once; they are shared by the count and data queries
+        let mut args = PgArguments::default();
+        bind_arg(&mut args, collection.id)?;
         if let Some(coords) = &bbox_coords {
-            count_query = count_query
-                .bind(coords[0])
-                .bind(coords[1])
-                .bind(coords[2])
-                .bind(coords[3]);
+            for coord in coords {
+                bind_arg(&mut args, *coord)?;
+            }
         }
-
-        if let Some(dt) = &datetime_start {
-            count_query = count_query.bind(dt.with_timezone(&chrono::Utc));
+        if let Some(intersects) = intersects {
+            bind_arg(&mut args, intersects.to_string())?;
         }
-        if let Some(dt) = &datetime_end {
-            count_query = count_query.bind(dt.with_timezone(&chrono::Utc));
+        for value in within_distance.into_iter().flatten() {
+            bind_arg(&mut args, value)?;
+        }
+        for dt in [&datetime_start, &datetime_end, &datetime_exact]
+            .into_iter()
+            .flatten()
+        {
+            bind_arg(&mut args, dt.with_timezone(&chrono::Utc))?;
         }
-        if let Some(dt) = &datetime_exact {
-            count_query = count_query.bind(dt.with_timezone(&chrono::Utc));
+        if let Some(ids) = ids {
+            bind_arg(&mut args, ids.to_vec())?;
         }
 
-        let count: (i64,) = count_query.fetch_one(self.db.pool()).await?;
+        let matched = self
+            .count_matches(
+                &format!("spatialvault.items WHERE {}", where_clause),
+                &args,
+                count,
+            )
+            .await?;
+        match result_type {
+            ResultType::Results => {}
+            ResultType::Hits => return Ok((Vec::new(), matched, 4326)),
+            ResultType::Ids => {
+                let sql = format!(
+                    r#"
+                    SELECT id
+                    FROM spatialvault.items
+                    WHERE {}
+                    ORDER BY datetime DESC NULLS LAST, created_at DESC
+                    LIMIT ${} OFFSET ${}
+                    "#,
+                    where_clause,
+                    param_index,
+                    param_index + 1
+                );
+                bind_arg(&mut args, limit as i64)?;
+                bind_arg(&mut args, offset as i64)?;
+                let mut tx = self.db.begin_with_budget(QueryClass::Features).await?;
+                let ids: Vec<Uuid> = sqlx::query_scalar_with(&sql, args)
+                    .fetch_all(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+                let features = ids
+                    .into_iter()
+                    .map(|id| Feature::id_only(id.to_string()))
+                    .collect();
+                return Ok((features, matched, 4326));
+            }
+        }
 
         // Data query
         let sql = format!(
@@ -304,7 +900,9 @@ impl FeatureService {
         );
 
         // Build and execute data query
-        let mut data_query = sqlx::query_as::<
+        bind_arg(&mut args, limit as i64)?;
+        bind_arg(&mut args, offset as i64)?;
+        let data_query = sqlx::query_as_with::<
             _,
             (
                 Uuid,
@@ -316,30 +914,12 @@ impl FeatureService {
                 Option<chrono::DateTime<chrono::Utc>>,
                 Option<serde_json::Value>,
             ),
-        >(&sql)
-        .bind(collection.id);
-
-        if let Some(coords) = &bbox_coords {
-            data_query = data_query
-                .bind(coords[0])
-                .bind(coords[1])
-                .bind(coords[2])
-                .bind(coords[3]);
-        }
-
-        if let Some(dt) = &datetime_start {
-            data_query = data_query.bind(dt.with_timezone(&chrono::Utc));
-        }
-        if let Some(dt) = &datetime_end {
-            data_query = data_query.bind(dt.with_timezone(&chrono::Utc));
-        }
-        if let Some(dt) = &datetime_exact {
-            data_query = data_query.bind(dt.with_timezone(&chrono::Utc));
-        }
-
-        data_query = data_query.bind(limit as i64).bind(offset as i64);
+            _,
+        >(&sql, args);
 
-        let rows = data_query.fetch_all(self.db.pool()).await?;
+        let mut tx = self.db.begin_with_budget(QueryClass::Features).await?;
+        let rows = data_query.fetch_all(&mut *tx).await?;
+        tx.commit().await?;
 
         // Get assets for all items
         let item_ids: Vec<Uuid> = rows.iter().map(|(id, ..)| *id).collect();
@@ -377,10 +957,49 @@ impl FeatureService {
             )
             .collect();
 
-        Ok((features, count.0 as usize, 4326))
+        Ok((features, matched, 4326))
+    }
+
+    /// Count the rows of `from_where` (a table followed by its WHERE clause)
+    #[tracing::instrument(skip_all)]
+    async fn count_matches(
+        &self,
+        from_where: &str,
+        args: &PgArguments,
+        mode: CountMode,
+    ) -> AppResult<Option<usize>> {
+        if mode == CountMode::Skip {
+            return Ok(None);
+        }
+
+        let mut tx = self.db.begin_with_budget(QueryClass::Features).await?;
+        if let CountMode::EstimateAbove(threshold) = mode {
+            let plan: serde_json::Value = sqlx::query_scalar_with(
+                &format!("EXPLAIN (FORMAT JSON) SELECT 1 FROM {}", from_where),
+                args.clone(),
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            let estimate = plan_row_estimate(&plan);
+            if estimate > threshold {
+                return Ok(Some(estimate as usize));
+            }
+        }
+
+        let count: i64 = sqlx::query_scalar_with(
+            &format!("SELECT COUNT(*) FROM {}", from_where),
+            args.clone(),
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(Some(count as usize))
     }
 
     /// Build a JSON object from asset fields
+    ///
+    /// Extra STAC fields are added first, so the stored columns win over
+    /// fields of the same name.
     fn build_asset_json(
         href: &str,
         media_type: Option<&str>,
@@ -388,8 +1007,12 @@ impl FeatureService {
         description: Option<&str>,
         roles: Option<&[String]>,
         file_size: Option<i64>,
+        extra_fields: Option<&serde_json::Value>,
     ) -> serde_json::Map<String, serde_json::Value> {
         let mut asset = serde_json::Map::new();
+        if let Some(serde_json::Value::Object(extra)) = extra_fields {
+            asset.extend(extra.clone());
+        }
         asset.insert("href".to_string(), serde_json::json!(href));
         if let Some(mt) = media_type {
             asset.insert("type".to_string(), serde_json::json!(mt));
@@ -426,7 +1049,8 @@ impl FeatureService {
 
         let sql = format!(
             r#"
-            SELECT item_id, key, href, type, title, description, roles, file_size
+            SELECT item_id, key, href, type, title, description, roles, file_size, extra_fields,
+                   validation
             FROM spatialvault.assets
             WHERE item_id IN ({})
             "#,
@@ -444,6 +1068,8 @@ impl FeatureService {
                 Option<String>,
                 Option<Vec<String>>,
                 Option<i64>,
+                Option<serde_json::Value>,
+                Option<serde_json::Value>,
             ),
         >(&sql);
 
@@ -457,15 +1083,31 @@ impl FeatureService {
         let mut assets_map: HashMap<Uuid, serde_json::Map<String, serde_json::Value>> =
             HashMap::new();
 
-        for (item_id, key, href, media_type, title, description, roles, file_size) in rows {
-            let asset = Self::build_asset_json(
+        for (
+            item_id,
+            key,
+            href,
+            media_type,
+            title,
+            description,
+            roles,
+            file_size,
+            extra_fields,
+            validation,
+        ) in rows
+        {
+            let mut asset = Self::build_asset_json(
                 &href,
                 media_type.as_deref(),
                 title.as_deref(),
                 description.as_deref(),
                 roles.as_deref(),
                 file_size,
+                extra_fields.as_ref(),
             );
+            if let Some(validation) = validation {
+                asset.insert("validation".to_string(), validation);
+            }
 
             assets_map
                 .entry(item_id)
@@ -490,18 +1132,160 @@ impl FeatureService {
             .unwrap_or_else(|| serde_json::json!({})))
     }
 
+    /// Types of the properties of a vector collection's features
+    ///
+    /// Properties holding values of different JSON types, or objects and
+    /// arrays, are reported as strings. Scans the whole table on the
+    /// background pool.
+    #[tracing::instrument(skip(self))]
+    pub async fn property_types(
+        &self,
+        collection_id: &str,
+    ) -> AppResult<Vec<(String, PropertyType)>> {
+        let collection = self.get_collection(collection_id).await?;
+        if !collection.has_feature_table() {
+            return Ok(Vec::new());
+        }
+        scan_property_types(&self.db, &collection).await
+    }
+
+    /// Stream all features of a vector collection in WGS 84 as
+    /// newline-delimited GeoJSON
+    ///
+    /// There is no limit on the number of features, as they are written as
+    /// they are read. An error after the first feature ends the stream early.
+    #[tracing::instrument(skip(self))]
+    pub async fn stream_export_features(
+        &self,
+        collection_id: &str,
+        bbox: Option<[f64; 4]>,
+        clip: bool,
+    ) -> AppResult<impl futures::Stream<Item = Result<bytes::Bytes, std::io::Error>> + use<>> {
+        let features = self
+            .export_feature_stream(collection_id, bbox, clip)
+            .await?;
+        Ok(features.map(|feature| {
+            let feature = feature.map_err(std::io::Error::other)?;
+            let mut line = serde_json::to_vec(&feature)?;
+            line.push(b'\n');
+            Ok(bytes::Bytes::from(line))
+        }))
+    }
+
+    /// Stream all features of a vector collection in WGS 84, for exports
+    /// written as they are read
+    ///
+    /// Features are read in pages of [`EXPORT_PAGE_SIZE`], each in a short
+    /// transaction of its own, so a slow reader holds no connection. Pages
+    /// are not one snapshot: features changed during the export may or may
+    /// not be included. The stream ends after the first error.
+    pub async fn export_feature_stream(
+        &self,
+        collection_id: &str,
+        bbox: Option<[f64; 4]>,
+        clip: bool,
+    ) -> AppResult<impl futures::Stream<Item = AppResult<Feature>> + use<>> {
+        let sql = self.export_sql(collection_id, bbox, clip).await?;
+        let db = self.db.clone();
+
+        // The state is the ID the next page starts after, None when done
+        let pages = futures::stream::try_unfold(Some(None), move |after| {
+            let (db, sql) = (db.clone(), sql.clone());
+            async move {
+                let Some(after) = after else {
+                    return Ok::<_, AppError>(None);
+                };
+                let args = export_args(bbox, after, EXPORT_PAGE_SIZE)?;
+                let mut tx = db.begin_with_budget(QueryClass::Background).await?;
+                let rows: Vec<ExportRow> =
+                    sqlx::query_as_with(&sql, args).fetch_all(&mut *tx).await?;
+                tx.commit().await?;
+
+                let next = match rows.last() {
+                    Some((id, _, _)) if rows.len() as i64 == EXPORT_PAGE_SIZE => Some(Some(*id)),
+                    Some(_) => None,
+                    None => return Ok(None),
+                };
+                let features = rows.into_iter().map(|row| Ok(export_feature(row)));
+                Ok(Some((
+                    futures::stream::iter(features.collect::<Vec<AppResult<Feature>>>()),
+                    next,
+                )))
+            }
+        });
+        Ok(pages.try_flatten())
+    }
+
+    /// Query selecting a page of the features of a vector collection for
+    /// export in ID order, bound with [`export_args`]
+    async fn export_sql(
+        &self,
+        collection_id: &str,
+        bbox: Option<[f64; 4]>,
+        clip: bool,
+    ) -> AppResult<String> {
+        let collection = self.get_collection(collection_id).await?;
+        if collection.collection_type != "vector" {
+            return Err(AppError::BadRequest(format!(
+                "Only vector collections can be exported, {} is a {} collection",
+                collection_id, collection.collection_type
+            )));
+        }
+
+        let mut geometry = "geometry".to_string();
+        let mut where_clause = "TRUE".to_string();
+        if bbox.is_some() {
+            let storage_srid = self.get_storage_srid(&collection).await?;
+            let area = transform_geometry_sql(
+                "ST_MakeEnvelope($1, $2, $3, $4, 4326)",
+                4326,
+                Some(storage_srid),
+            );
+            where_clause = format!("ST_Intersects(geometry, {})", area);
+            if clip {
+                geometry = format!("ST_Intersection(ST_MakeValid(geometry), {})", area);
+            }
+        }
+        // Parameters after the bbox, if any
+        let after = if bbox.is_some() { 5 } else { 1 };
+
+        let sql = format!(
+            r#"
+            SELECT
+                id,
+                ST_AsGeoJSON(ST_Transform({}, 4326))::jsonb as geometry,
+                {} as properties
+            FROM {}.{}
+            WHERE {} AND (${after}::uuid IS NULL OR id > ${after})
+            ORDER BY id
+            LIMIT ${limit}
+            "#,
+            geometry,
+            properties_with_computed_sql("", &computed_sql(&collection, "")?),
+            quote_ident(&collection.schema_name),
+            quote_ident(&collection.table_name),
+            where_clause,
+            after = after,
+            limit = after + 1,
+        );
+        Ok(sql)
+    }
+
+    #[tracing::instrument(skip(self, username))]
     pub async fn get_feature(
         &self,
         username: &str,
         collection_id: &str,
         feature_id: Uuid,
         target_crs: Option<i32>,
+        geom: Option<&str>,
     ) -> AppResult<Option<(Feature, i64, i32)>> {
         let collection = self.get_collection(collection_id).await?;
+        let geometry_column = collection.geometry_column(geom)?;
 
         match collection.collection_type.as_str() {
-            "vector" => {
-                self.get_vector_feature(&collection, feature_id, target_crs)
+            "vector" | "table" => {
+                self.get_vector_feature(&collection, feature_id, target_crs, &geometry_column)
                     .await
             }
             "raster" | "pointcloud" => self.get_item(&collection, collection_id, feature_id).await,
@@ -518,37 +1302,46 @@ impl FeatureService {
         collection: &Collection,
         feature_id: Uuid,
         target_crs: Option<i32>,
+        geometry_column: &str,
     ) -> AppResult<Option<(Feature, i64, i32)>> {
         let storage_srid = self.get_storage_srid(collection).await?;
-        let geometry_expr = transform_geometry_sql("geometry", storage_srid, target_crs);
+        let geometry_json = geometry_json_sql(
+            collection,
+            &transform_geometry_sql(geometry_column, storage_srid, target_crs),
+        );
 
         let sql = format!(
             r#"
             SELECT
                 id::text,
-                ST_AsGeoJSON({geometry_expr})::jsonb as geometry,
-                properties,
+                {geometry_json} as geometry,
+                {properties} as properties,
                 version
             FROM {}.{}
             WHERE id = $1
             "#,
             quote_ident(&collection.schema_name),
             quote_ident(&collection.table_name),
-            geometry_expr = geometry_expr
+            geometry_json = geometry_json,
+            properties = properties_with_computed_sql("", &computed_sql(collection, "")?)
         );
 
-        let row: Option<(String, serde_json::Value, Option<serde_json::Value>, i64)> =
-            sqlx::query_as(&sql)
-                .bind(feature_id)
-                .fetch_optional(self.db.pool())
-                .await?;
+        let row: Option<(
+            String,
+            Option<serde_json::Value>,
+            Option<serde_json::Value>,
+            i64,
+        )> = sqlx::query_as(&sql)
+            .bind(feature_id)
+            .fetch_optional(self.db.pool())
+            .await?;
 
         Ok(row.map(|(id, geometry, properties, version)| {
             (
                 Feature {
                     feature_type: "Feature".to_string(),
                     id,
-                    geometry,
+                    geometry: geometry.unwrap_or(serde_json::Value::Null),
                     properties: properties.unwrap_or(serde_json::json!({})),
                     links: None,
                     bbox: None,
@@ -642,54 +1435,68 @@ impl FeatureService {
         &self,
         username: &str,
         collection_id: &str,
+        expected_version: Option<i64>,
         geometry: &serde_json::Value,
         properties: &serde_json::Value,
     ) -> AppResult<(Feature, i64)> {
         let collection = self.get_collection(collection_id).await?;
+        check_collection_version(&collection, expected_version)?;
 
-        if collection.collection_type != "vector" {
+        if !collection.has_feature_table() {
             return Err(AppError::BadRequest(
                 "Feature creation only available for vector collections. Use processes API for raster/pointcloud.".to_string(),
             ));
         }
 
         let storage_srid = self.get_storage_srid(&collection).await?;
+        let returned_properties = properties_with_computed_sql("", &computed_sql(&collection, "")?);
+
+        let sql = if collection.has_geometry() {
+            format!(
+                r#"
+                INSERT INTO {}.{} (geometry, properties)
+                VALUES ({}, $2)
+                RETURNING id::text, ST_AsGeoJSON(geometry)::jsonb, {}, version
+                "#,
+                quote_ident(&collection.schema_name),
+                quote_ident(&collection.table_name),
+                input_geometry_sql("$1", self.input_srid, storage_srid),
+                returned_properties
+            )
+        } else {
+            check_no_geometry(collection_id, geometry)?;
+            format!(
+                r#"
+                INSERT INTO {}.{} (properties)
+                VALUES ($2)
+                RETURNING id::text, NULL::jsonb, {}, version
+                "#,
+                quote_ident(&collection.schema_name),
+                quote_ident(&collection.table_name),
+                returned_properties
+            )
+        };
 
-        let sql = format!(
-            r#"
-            INSERT INTO {}.{} (geometry, properties)
-            VALUES (ST_SetSRID(ST_GeomFromGeoJSON($1), {}), $2)
-            RETURNING id::text, ST_AsGeoJSON(geometry)::jsonb, properties, version
-            "#,
-            quote_ident(&collection.schema_name),
-            quote_ident(&collection.table_name),
-            storage_srid
-        );
-
+        let mut tx = self.db.pool().begin().await?;
         let (id, geom, props, version): (
             String,
-            serde_json::Value,
+            Option<serde_json::Value>,
             Option<serde_json::Value>,
             i64,
         ) = sqlx::query_as(&sql)
             .bind(geometry.to_string())
-            .bind(properties)
-            .fetch_one(self.db.pool())
+            .bind(without_computed(&collection, properties.clone()))
+            .fetch_one(&mut *tx)
             .await?;
 
-        // Increment collection version
-        sqlx::query(
-            "UPDATE spatialvault.collections SET version = version + 1 WHERE canonical_name = $1",
-        )
-        .bind(collection_id)
-        .execute(self.db.pool())
-        .await?;
+        bump_collection_version(&mut tx, collection.id, expected_version).await?;
+        finish_write(tx, self.validate_only).await?;
 
         Ok((
             Feature {
                 feature_type: "Feature".to_string(),
                 id,
-                geometry: geom,
+                geometry: geom.unwrap_or(serde_json::Value::Null),
                 properties: props.unwrap_or(serde_json::json!({})),
                 links: None,
                 bbox: None,
@@ -702,6 +1509,74 @@ impl FeatureService {
         ))
     }
 
+    /// Start a bulk insert into a vector collection
+    ///
+    /// With `on_conflict`, features whose key property matches an existing
+    /// feature update or skip it instead of being inserted.
+    pub async fn begin_bulk_insert(
+        &self,
+        collection_id: &str,
+        expected_version: Option<i64>,
+        on_conflict: Option<OnConflict>,
+    ) -> AppResult<FeatureBulkInsert> {
+        let collection = self.get_collection(collection_id).await?;
+        check_collection_version(&collection, expected_version)?;
+
+        if !collection.has_feature_table() {
+            return Err(AppError::BadRequest(
+                "Bulk feature ingest is only available for vector collections".to_string(),
+            ));
+        }
+
+        let storage_srid = self.get_storage_srid(&collection).await?;
+
+        let sql = if let Some(on_conflict) = &on_conflict {
+            upsert_sql(
+                &collection,
+                self.input_srid,
+                storage_srid,
+                on_conflict.action,
+            )
+        } else if collection.has_geometry() {
+            format!(
+                r#"
+                INSERT INTO {}.{} (geometry, properties)
+                SELECT {}, p
+                FROM UNNEST($1::text[], $2::jsonb[]) AS f(g, p)
+                "#,
+                quote_ident(&collection.schema_name),
+                quote_ident(&collection.table_name),
+                input_geometry_sql("g", self.input_srid, storage_srid)
+            )
+        } else {
+            format!(
+                r#"
+                INSERT INTO {}.{} (properties)
+                SELECT p
+                FROM UNNEST($1::text[], $2::jsonb[]) AS f(g, p)
+                "#,
+                quote_ident(&collection.schema_name),
+                quote_ident(&collection.table_name)
+            )
+        };
+
+        Ok(FeatureBulkInsert {
+            tx: self.db.pool().begin().await?,
+            sql,
+            collection_id: collection_id.to_string(),
+            collection_uuid: collection.id,
+            expected_version,
+            has_geometry: collection.has_geometry(),
+            computed: parse_computed_properties(collection.computed_properties.as_ref())
+                .into_iter()
+                .map(|property| property.name)
+                .collect(),
+            on_conflict,
+            counts: BulkInsertCounts::default(),
+            validate_only: self.validate_only,
+        })
+    }
+
     pub async fn update_feature(
         &self,
         username: &str,
@@ -712,9 +1587,11 @@ impl FeatureService {
         properties: Option<&serde_json::Value>,
     ) -> AppResult<(Feature, i64)> {
         let collection = self.get_collection(collection_id).await?;
+        self.check_feature_lock(&collection, feature_id, username)
+            .await?;
 
         match collection.collection_type.as_str() {
-            "vector" => {
+            "vector" | "table" => {
                 self.update_vector_feature(
                     &collection,
                     feature_id,
@@ -758,17 +1635,16 @@ impl FeatureService {
 
         // Lock and check version
         let check_sql = format!(
-            r#"SELECT version FROM {}.{} WHERE id = $1 FOR UPDATE"#,
+            r#"SELECT version, properties FROM {}.{} WHERE id = $1 FOR UPDATE"#,
             quoted_schema, quoted_table
         );
-        let current: Option<(i64,)> = sqlx::query_as(&check_sql)
+        let current: Option<(i64, Option<serde_json::Value>)> = sqlx::query_as(&check_sql)
             .bind(feature_id)
             .fetch_optional(&mut *tx)
             .await?;
 
-        let current_version = current
-            .ok_or_else(|| AppError::NotFound("Feature not found".to_string()))?
-            .0;
+        let (current_version, current_properties) =
+            current.ok_or_else(|| AppError::NotFound("Feature not found".to_string()))?;
 
         // Check version if If-Match header was provided
         if let Some(version) = expected_version {
@@ -780,46 +1656,61 @@ impl FeatureService {
         }
 
         // Build update
+        let geometry_update = format!(
+            "geometry = {}",
+            input_geometry_sql("$2", self.input_srid, storage_srid)
+        );
         let mut updates = vec!["version = version + 1", "updated_at = NOW()"];
-        let mut binds: Vec<String> = Vec::new();
 
-        if let Some(geom) = geometry {
-            binds.push(geom.to_string());
-            updates.push("geometry = ST_SetSRID(ST_GeomFromGeoJSON($2), storage_srid)");
+        if let Some(geometry) = geometry {
+            if collection.has_geometry() {
+                updates.push(&geometry_update);
+            } else {
+                check_no_geometry(&collection.canonical_name, geometry)?;
+            }
+        }
+
+        let properties = properties.map(|patch| {
+            without_computed(collection, merged_properties(current_properties, patch))
+        });
+        if properties.is_some() {
+            updates.push("properties = $3");
         }
 
-        // Simplified: in production, use proper parameter binding
         let update_sql = format!(
             r#"
             UPDATE {}.{}
             SET {}
             WHERE id = $1
-            RETURNING id::text, ST_AsGeoJSON(geometry)::jsonb, properties, version
+            RETURNING id::text, {}, {}, version
             "#,
             quoted_schema,
             quoted_table,
-            updates
-                .join(", ")
-                .replace("storage_srid", &storage_srid.to_string())
+            updates.join(", "),
+            geometry_json_sql(collection, "geometry"),
+            properties_with_computed_sql("", &computed_sql(collection, "")?)
         );
 
         let (id, geom, props, version): (
             String,
-            serde_json::Value,
+            Option<serde_json::Value>,
             Option<serde_json::Value>,
             i64,
         ) = sqlx::query_as(&update_sql)
             .bind(feature_id)
+            .bind(geometry.map(|g| g.to_string()))
+            .bind(properties)
             .fetch_one(&mut *tx)
             .await?;
 
-        tx.commit().await?;
+        bump_collection_version(&mut tx, collection.id, None).await?;
+        finish_write(tx, self.validate_only).await?;
 
         Ok((
             Feature {
                 feature_type: "Feature".to_string(),
                 id,
-                geometry: geom,
+                geometry: geom.unwrap_or(serde_json::Value::Null),
                 properties: props.unwrap_or(serde_json::json!({})),
                 links: None,
                 bbox: None,
@@ -845,19 +1736,18 @@ impl FeatureService {
 
         // Lock and check version
         let check_sql = r#"
-            SELECT version FROM spatialvault.items
+            SELECT version, properties FROM spatialvault.items
             WHERE id = $1 AND collection_id = $2
             FOR UPDATE
         "#;
-        let current: Option<(i64,)> = sqlx::query_as(check_sql)
+        let current: Option<(i64, Option<serde_json::Value>)> = sqlx::query_as(check_sql)
             .bind(item_id)
             .bind(&collection.id)
             .fetch_optional(&mut *tx)
             .await?;
 
-        let current_version = current
-            .ok_or_else(|| AppError::NotFound("Item not found".to_string()))?
-            .0;
+        let (current_version, current_properties) =
+            current.ok_or_else(|| AppError::NotFound("Item not found".to_string()))?;
 
         // Check version if If-Match header was provided
         if let Some(version) = expected_version {
@@ -869,15 +1759,19 @@ impl FeatureService {
         }
 
         // Build dynamic update
+        let geometry_update = format!(
+            "geometry = {}",
+            input_geometry_sql("$3", self.input_srid, 4326)
+        );
         let mut set_parts = vec!["version = version + 1", "updated_at = NOW()"];
 
         if geometry.is_some() {
-            set_parts.push("geometry = ST_SetSRID(ST_GeomFromGeoJSON($3), 4326)");
+            set_parts.push(&geometry_update);
         }
 
+        let properties = properties.map(|patch| merged_properties(current_properties, patch));
         if properties.is_some() {
-            // Merge properties using JSON concatenation
-            set_parts.push("properties = COALESCE(properties, '{}'::jsonb) || $4");
+            set_parts.push("properties = $4");
         }
 
         let update_sql = format!(
@@ -899,11 +1793,12 @@ impl FeatureService {
             .bind(item_id)
             .bind(&collection.id)
             .bind(geometry.map(|g| g.to_string()).unwrap_or_default())
-            .bind(properties)
+            .bind(&properties)
             .fetch_one(&mut *tx)
             .await?;
 
-        tx.commit().await?;
+        bump_collection_version(&mut tx, collection.id, None).await?;
+        finish_write(tx, self.validate_only).await?;
 
         // Fetch assets for the response
         let assets = self.get_item_assets(&item_id).await?;
@@ -935,9 +1830,11 @@ impl FeatureService {
         properties: &serde_json::Value,
     ) -> AppResult<(Feature, i64)> {
         let collection = self.get_collection(collection_id).await?;
+        self.check_feature_lock(&collection, feature_id, username)
+            .await?;
 
         match collection.collection_type.as_str() {
-            "vector" => {
+            "vector" | "table" => {
                 self.replace_vector_feature(
                     &collection,
                     feature_id,
@@ -1003,39 +1900,53 @@ impl FeatureService {
             }
         }
 
+        let geometry_update = if collection.has_geometry() {
+            format!(
+                "geometry = {},",
+                input_geometry_sql("$2", self.input_srid, storage_srid)
+            )
+        } else {
+            check_no_geometry(&collection.canonical_name, geometry)?;
+            String::new()
+        };
         let sql = format!(
             r#"
             UPDATE {}.{}
             SET
-                geometry = ST_SetSRID(ST_GeomFromGeoJSON($2), {}),
+                {}
                 properties = $3,
                 version = version + 1,
                 updated_at = NOW()
             WHERE id = $1
-            RETURNING id::text, ST_AsGeoJSON(geometry)::jsonb, properties, version
+            RETURNING id::text, {}, {}, version
             "#,
-            quoted_schema, quoted_table, storage_srid
+            quoted_schema,
+            quoted_table,
+            geometry_update,
+            geometry_json_sql(collection, "geometry"),
+            properties_with_computed_sql("", &computed_sql(collection, "")?)
         );
 
         let (id, geom, props, version): (
             String,
-            serde_json::Value,
+            Option<serde_json::Value>,
             Option<serde_json::Value>,
             i64,
         ) = sqlx::query_as(&sql)
             .bind(feature_id)
             .bind(geometry.to_string())
-            .bind(properties)
+            .bind(without_computed(collection, properties.clone()))
             .fetch_one(&mut *tx)
             .await?;
 
-        tx.commit().await?;
+        bump_collection_version(&mut tx, collection.id, None).await?;
+        finish_write(tx, self.validate_only).await?;
 
         Ok((
             Feature {
                 feature_type: "Feature".to_string(),
                 id,
-                geometry: geom,
+                geometry: geom.unwrap_or(serde_json::Value::Null),
                 properties: props.unwrap_or(serde_json::json!({})),
                 links: None,
                 bbox: None,
@@ -1091,24 +2002,27 @@ impl FeatureService {
             .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
             .map(|dt| dt.with_timezone(&chrono::Utc));
 
-        let sql = r#"
+        let sql = format!(
+            r#"
             UPDATE spatialvault.items
             SET
-                geometry = ST_SetSRID(ST_GeomFromGeoJSON($3), 4326),
+                geometry = {},
                 properties = $4,
                 datetime = $5,
                 version = version + 1,
                 updated_at = NOW()
             WHERE id = $1 AND collection_id = $2
             RETURNING id::text, ST_AsGeoJSON(geometry)::jsonb, properties, version
-        "#;
+            "#,
+            input_geometry_sql("$3", self.input_srid, 4326)
+        );
 
         let (id, geom, props, version): (
             String,
             serde_json::Value,
             Option<serde_json::Value>,
             i64,
-        ) = sqlx::query_as(sql)
+        ) = sqlx::query_as(&sql)
             .bind(item_id)
             .bind(&collection.id)
             .bind(geometry.to_string())
@@ -1117,7 +2031,8 @@ impl FeatureService {
             .fetch_one(&mut *tx)
             .await?;
 
-        tx.commit().await?;
+        bump_collection_version(&mut tx, collection.id, None).await?;
+        finish_write(tx, self.validate_only).await?;
 
         // Fetch assets for the response
         let assets = self.get_item_assets(&item_id).await?;
@@ -1147,21 +2062,27 @@ impl FeatureService {
         expected_version: Option<i64>,
     ) -> AppResult<()> {
         let collection = self.get_collection(collection_id).await?;
+        self.check_feature_lock(&collection, feature_id, username)
+            .await?;
 
         match collection.collection_type.as_str() {
-            "vector" => {
+            "vector" | "table" => {
                 self.delete_vector_feature(&collection, feature_id, expected_version)
-                    .await
+                    .await?
             }
             "raster" | "pointcloud" => {
                 self.delete_item_internal(&collection, feature_id, expected_version)
-                    .await
+                    .await?
+            }
+            _ => {
+                return Err(AppError::BadRequest(format!(
+                    "Unknown collection type: {}",
+                    collection.collection_type
+                )));
             }
-            _ => Err(AppError::BadRequest(format!(
-                "Unknown collection type: {}",
-                collection.collection_type
-            ))),
         }
+
+        self.remove_feature_lock(&collection, feature_id).await
     }
 
     async fn delete_vector_feature(
@@ -1207,6 +2128,7 @@ impl FeatureService {
             .execute(&mut *tx)
             .await?;
 
+        bump_collection_version(&mut tx, collection.id, None).await?;
         tx.commit().await?;
 
         Ok(())
@@ -1257,12 +2179,14 @@ impl FeatureService {
             .execute(&mut *tx)
             .await?;
 
+        bump_collection_version(&mut tx, collection.id, None).await?;
         tx.commit().await?;
 
         Ok(())
     }
 
     /// Create a STAC item (for raster/pointcloud collections)
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_item(
         &self,
         _username: &str,
@@ -1271,10 +2195,12 @@ impl FeatureService {
         properties: &serde_json::Value,
         datetime: Option<chrono::DateTime<chrono::Utc>>,
         assets: Option<&serde_json::Value>,
+        expected_version: Option<i64>,
     ) -> AppResult<(Feature, i64)> {
         let collection = self.get_collection(collection_id).await?;
+        check_collection_version(&collection, expected_version)?;
 
-        if collection.collection_type == "vector" {
+        if collection.has_feature_table() {
             return Err(AppError::BadRequest(
                 "Item creation with assets requires raster/pointcloud collection. Use feature creation for vector collections.".to_string(),
             ));
@@ -1285,12 +2211,15 @@ impl FeatureService {
         let mut tx = self.db.pool().begin().await?;
 
         // Insert item
-        let sql = r#"
+        let sql = format!(
+            r#"
             INSERT INTO spatialvault.items (id, collection_id, geometry, datetime, properties)
-            VALUES ($1, $2, ST_SetSRID(ST_GeomFromGeoJSON($3), 4326), $4, $5)
+            VALUES ($1, $2, {}, $4, $5)
             RETURNING id, ST_AsGeoJSON(geometry)::jsonb, ST_XMin(geometry), ST_YMin(geometry),
                       ST_XMax(geometry), ST_YMax(geometry), datetime, properties, version
-        "#;
+            "#,
+            input_geometry_sql("$3", self.input_srid, 4326)
+        );
 
         let (id, geom, minx, miny, maxx, maxy, dt, props, version): (
             Uuid,
@@ -1302,7 +2231,7 @@ impl FeatureService {
             Option<chrono::DateTime<chrono::Utc>>,
             Option<serde_json::Value>,
             i64,
-        ) = sqlx::query_as(sql)
+        ) = sqlx::query_as(&sql)
             .bind(item_id)
             .bind(collection.id)
             .bind(geometry.to_string())
@@ -1349,13 +2278,9 @@ impl FeatureService {
             }
         }
 
-        // Increment collection version
-        sqlx::query("UPDATE spatialvault.collections SET version = version + 1 WHERE id = $1")
-            .bind(collection.id)
-            .execute(&mut *tx)
-            .await?;
+        bump_collection_version(&mut tx, collection.id, expected_version).await?;
 
-        tx.commit().await?;
+        finish_write(tx, self.validate_only).await?;
 
         // Get assets for response
         let assets_map = self.get_assets_for_items(&[id]).await?;
@@ -1391,7 +2316,7 @@ impl FeatureService {
     /// Update a STAC item (PATCH - JSON Merge Patch)
     pub async fn update_item(
         &self,
-        _username: &str,
+        username: &str,
         collection_id: &str,
         item_id: Uuid,
         expected_version: Option<i64>,
@@ -1401,21 +2326,22 @@ impl FeatureService {
         assets: Option<&serde_json::Value>,
     ) -> AppResult<(Feature, i64)> {
         let collection = self.get_collection(collection_id).await?;
+        self.check_feature_lock(&collection, item_id, username)
+            .await?;
 
         let mut tx = self.db.pool().begin().await?;
 
         // Lock and check version
-        let current: Option<(i64,)> = sqlx::query_as(
-            "SELECT version FROM spatialvault.items WHERE collection_id = $1 AND id = $2 FOR UPDATE",
+        let current: Option<(i64, Option<serde_json::Value>)> = sqlx::query_as(
+            "SELECT version, properties FROM spatialvault.items WHERE collection_id = $1 AND id = $2 FOR UPDATE",
         )
         .bind(collection.id)
         .bind(item_id)
         .fetch_optional(&mut *tx)
         .await?;
 
-        let current_version = current
-            .ok_or_else(|| AppError::NotFound("Item not found".to_string()))?
-            .0;
+        let (current_version, current_properties) =
+            current.ok_or_else(|| AppError::NotFound("Item not found".to_string()))?;
 
         // Check version if If-Match header was provided
         if let Some(version) = expected_version {
@@ -1427,13 +2353,18 @@ impl FeatureService {
         }
 
         // Build update dynamically
+        let geometry_update = format!(
+            "geometry = {}",
+            input_geometry_sql("$3", self.input_srid, 4326)
+        );
         let mut set_clauses = vec!["version = version + 1", "updated_at = NOW()"];
 
         if geometry.is_some() {
-            set_clauses.push("geometry = ST_SetSRID(ST_GeomFromGeoJSON($3), 4326)");
+            set_clauses.push(&geometry_update);
         }
+        let properties = properties.map(|patch| merged_properties(current_properties, patch));
         if properties.is_some() {
-            set_clauses.push("properties = COALESCE(properties, '{}'::jsonb) || $4");
+            set_clauses.push("properties = $4");
         }
         if datetime.is_some() {
             set_clauses.push("datetime = $5");
@@ -1473,13 +2404,7 @@ impl FeatureService {
             query = query.bind(Option::<String>::None);
         }
 
-        if let Some(props) = properties {
-            query = query.bind(props);
-        } else {
-            query = query.bind(Option::<serde_json::Value>::None);
-        }
-
-        query = query.bind(datetime);
+        query = query.bind(properties).bind(datetime);
 
         let (id, geom, minx, miny, maxx, maxy, dt, props, version) =
             query.fetch_one(&mut *tx).await?;
@@ -1520,7 +2445,10 @@ impl FeatureService {
                                 title = EXCLUDED.title,
                                 description = EXCLUDED.description,
                                 roles = EXCLUDED.roles,
-                                file_size = EXCLUDED.file_size
+                                file_size = EXCLUDED.file_size,
+                                validation = NULL,
+                                link_status = NULL,
+                                link_checked_at = NULL
                             "#,
                         )
                         .bind(item_id)
@@ -1538,7 +2466,7 @@ impl FeatureService {
             }
         }
 
-        tx.commit().await?;
+        finish_write(tx, self.validate_only).await?;
 
         // Get assets for response
         let assets_map = self.get_assets_for_items(&[id]).await?;
@@ -1574,7 +2502,7 @@ impl FeatureService {
     /// Replace a STAC item (PUT)
     pub async fn replace_item(
         &self,
-        _username: &str,
+        username: &str,
         collection_id: &str,
         item_id: Uuid,
         expected_version: Option<i64>,
@@ -1584,6 +2512,8 @@ impl FeatureService {
         assets: Option<&serde_json::Value>,
     ) -> AppResult<(Feature, i64)> {
         let collection = self.get_collection(collection_id).await?;
+        self.check_feature_lock(&collection, item_id, username)
+            .await?;
 
         let mut tx = self.db.pool().begin().await?;
 
@@ -1610,9 +2540,10 @@ impl FeatureService {
         }
 
         // Replace item
-        let sql = r#"
+        let sql = format!(
+            r#"
             UPDATE spatialvault.items
-            SET geometry = ST_SetSRID(ST_GeomFromGeoJSON($3), 4326),
+            SET geometry = {},
                 datetime = $4,
                 properties = $5,
                 version = version + 1,
@@ -1620,7 +2551,9 @@ impl FeatureService {
             WHERE collection_id = $1 AND id = $2
             RETURNING id, ST_AsGeoJSON(geometry)::jsonb, ST_XMin(geometry), ST_YMin(geometry),
                       ST_XMax(geometry), ST_YMax(geometry), datetime, properties, version
-        "#;
+            "#,
+            input_geometry_sql("$3", self.input_srid, 4326)
+        );
 
         let (id, geom, minx, miny, maxx, maxy, dt, props, version): (
             Uuid,
@@ -1632,7 +2565,7 @@ impl FeatureService {
             Option<chrono::DateTime<chrono::Utc>>,
             Option<serde_json::Value>,
             i64,
-        ) = sqlx::query_as(sql)
+        ) = sqlx::query_as(&sql)
             .bind(collection.id)
             .bind(item_id)
             .bind(geometry.to_string())
@@ -1683,7 +2616,7 @@ impl FeatureService {
             }
         }
 
-        tx.commit().await?;
+        finish_write(tx, self.validate_only).await?;
 
         // Get assets for response
         let assets_map = self.get_assets_for_items(&[id]).await?;
@@ -1716,15 +2649,118 @@ impl FeatureService {
         ))
     }
 
+    /// Register assets of a STAC item whose files are already in storage
+    ///
+    /// All assets are written in one transaction, replacing assets with the
+    /// same keys; either all of them are registered or none. `validations`
+    /// holds the outcome of checking assets against their objects, by key.
+    /// Returns all assets of the item and its new version.
+    pub async fn register_item_assets(
+        &self,
+        username: &str,
+        collection_id: &str,
+        item_id: Uuid,
+        expected_version: Option<i64>,
+        assets: &BTreeMap<String, RegisteredAsset>,
+        validations: &BTreeMap<String, AssetValidation>,
+    ) -> AppResult<(serde_json::Value, i64)> {
+        let collection = self.get_collection(collection_id).await?;
+        if collection.has_feature_table() {
+            return Err(AppError::BadRequest(
+                "Assets can only be registered on items of raster/pointcloud collections"
+                    .to_string(),
+            ));
+        }
+        self.check_feature_lock(&collection, item_id, username)
+            .await?;
+
+        let mut tx = self.db.pool().begin().await?;
+
+        let current_version: i64 = sqlx::query_scalar(
+            "SELECT version FROM spatialvault.items WHERE collection_id = $1 AND id = $2 FOR UPDATE",
+        )
+        .bind(collection.id)
+        .bind(item_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Item not found".to_string()))?;
+
+        if let Some(version) = expected_version
+            && current_version != version
+        {
+            return Err(AppError::PreconditionFailed(
+                "Item has been modified".to_string(),
+            ));
+        }
+
+        for (key, registered) in assets {
+            let asset = &registered.asset;
+            let extra_fields = (!registered.extra_fields.is_empty())
+                .then(|| serde_json::Value::Object(registered.extra_fields.clone()));
+
+            sqlx::query(
+                r#"
+                INSERT INTO spatialvault.assets
+                    (item_id, key, href, type, title, description, roles, file_size,
+                     extra_fields, validation)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                ON CONFLICT (item_id, key) DO UPDATE SET
+                    href = EXCLUDED.href,
+                    type = EXCLUDED.type,
+                    title = EXCLUDED.title,
+                    description = EXCLUDED.description,
+                    roles = EXCLUDED.roles,
+                    file_size = EXCLUDED.file_size,
+                    extra_fields = EXCLUDED.extra_fields,
+                    validation = EXCLUDED.validation,
+                    link_status = NULL,
+                    link_checked_at = NULL
+                "#,
+            )
+            .bind(item_id)
+            .bind(key)
+            .bind(&asset.href)
+            .bind(&asset.media_type)
+            .bind(&asset.title)
+            .bind(&asset.description)
+            .bind(&asset.roles)
+            .bind(asset.file_size)
+            .bind(extra_fields)
+            .bind(validations.get(key).map(sqlx::types::Json))
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let version: i64 = sqlx::query_scalar(
+            r#"
+            UPDATE spatialvault.items SET version = version + 1, updated_at = NOW()
+            WHERE collection_id = $1 AND id = $2
+            RETURNING version
+            "#,
+        )
+        .bind(collection.id)
+        .bind(item_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        bump_collection_version(&mut tx, collection.id, None).await?;
+
+        finish_write(tx, self.validate_only).await?;
+
+        Ok((self.get_item_assets(&item_id).await?, version))
+    }
+
     /// Delete a STAC item
     pub async fn delete_item(
         &self,
-        _username: &str,
+        username: &str,
         collection_id: &str,
         item_id: Uuid,
         expected_version: Option<i64>,
     ) -> AppResult<()> {
         let collection = self.get_collection(collection_id).await?;
+        self.check_feature_lock(&collection, item_id, username)
+            .await?;
 
         let mut tx = self.db.pool().begin().await?;
 
@@ -1758,9 +2794,266 @@ impl FeatureService {
 
         tx.commit().await?;
 
+        self.remove_feature_lock(&collection, item_id).await
+    }
+
+    /// Resolve the relations of a collection to the tables of the related
+    /// collections
+    async fn join_relations(
+        &self,
+        collection: &Collection,
+        username: &str,
+    ) -> AppResult<Vec<JoinRelation>> {
+        let mut relations = Vec::new();
+        for relation in parse_relations(collection.relations.as_ref()) {
+            // Relations to deleted collections are left out, and so are those
+            // to collections the user can't read, which filters would
+            // otherwise let them probe
+            let related = match self.get_collection(&relation.collection).await {
+                Ok(related) => related,
+                Err(AppError::NotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            if !self.has_read_access(&related, username).await? {
+                continue;
+            }
+            relations.push(JoinRelation {
+                name: relation.name,
+                table: format!(
+                    "{}.{}",
+                    quote_ident(&related.schema_name),
+                    quote_ident(&related.table_name)
+                ),
+                property: relation.property,
+                references: relation.references,
+            });
+        }
+        Ok(relations)
+    }
+
+    /// Lock a feature for `username` for `duration`
+    ///
+    /// Locking a feature the user already holds renews the lock; a feature
+    /// locked by someone else can't be locked until that lock expires.
+    pub async fn lock_feature(
+        &self,
+        username: &str,
+        collection_id: &str,
+        feature_id: Uuid,
+        duration: chrono::Duration,
+    ) -> AppResult<FeatureLock> {
+        let collection = self.get_collection(collection_id).await?;
+        // A lock blocks writes by others, so only writers may take one
+        self.check_write_access(&collection, username).await?;
+        if !self.feature_exists(&collection, feature_id).await? {
+            return Err(AppError::NotFound(format!(
+                "Feature not found: {}",
+                feature_id
+            )));
+        }
+
+        let lock: Option<FeatureLock> = sqlx::query_as(
+            r#"
+            INSERT INTO spatialvault.feature_locks (collection_id, feature_id, locked_by, expires_at)
+            VALUES ($1, $2, $3, NOW() + $4 * INTERVAL '1 second')
+            ON CONFLICT (collection_id, feature_id) DO UPDATE SET
+                locked_by = EXCLUDED.locked_by,
+                locked_at = CASE
+                    WHEN feature_locks.locked_by = EXCLUDED.locked_by
+                         AND feature_locks.expires_at > NOW()
+                    THEN feature_locks.locked_at
+                    ELSE NOW()
+                END,
+                expires_at = EXCLUDED.expires_at
+            WHERE feature_locks.locked_by = EXCLUDED.locked_by
+               OR feature_locks.expires_at <= NOW()
+            RETURNING feature_id, locked_by, locked_at, expires_at
+            "#,
+        )
+        .bind(collection.id)
+        .bind(feature_id)
+        .bind(username)
+        .bind(duration.num_seconds() as f64)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        match lock {
+            Some(lock) => Ok(lock),
+            None => Err(self.locked_error(&collection, feature_id).await?),
+        }
+    }
+
+    /// The current lock on a feature, if any
+    pub async fn get_feature_lock(
+        &self,
+        collection_id: &str,
+        feature_id: Uuid,
+    ) -> AppResult<Option<FeatureLock>> {
+        let collection = self.get_collection(collection_id).await?;
+        self.current_lock(&collection, feature_id).await
+    }
+
+    /// Release a feature lock held by `username`, or any lock when
+    /// `break_lock` is set (for the collection owner)
+    pub async fn unlock_feature(
+        &self,
+        username: &str,
+        collection_id: &str,
+        feature_id: Uuid,
+        break_lock: bool,
+    ) -> AppResult<()> {
+        let collection = self.get_collection(collection_id).await?;
+        let lock = self
+            .current_lock(&collection, feature_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Feature is not locked: {}", feature_id)))?;
+        if lock.locked_by != username && !break_lock {
+            return Err(AppError::Forbidden(format!(
+                "Feature is locked by {}",
+                lock.locked_by
+            )));
+        }
+
+        sqlx::query(
+            "DELETE FROM spatialvault.feature_locks WHERE collection_id = $1 AND feature_id = $2",
+        )
+        .bind(collection.id)
+        .bind(feature_id)
+        .execute(self.db.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// Whether `username` owns the collection or it is shared with them
+    async fn has_read_access(&self, collection: &Collection, username: &str) -> AppResult<bool> {
+        if collection.owner == username {
+            return Ok(true);
+        }
+        let can_read: bool = sqlx::query_scalar(
+            r#"
+            SELECT CASE
+                WHEN to_regrole(quote_ident($1)) IS NULL
+                     OR to_regclass(format('%I.%I', $2::text, $3::text)) IS NULL THEN false
+                ELSE pg_catalog.has_table_privilege($1, format('%I.%I', $2::text, $3::text), 'SELECT')
+            END
+            "#,
+        )
+        .bind(username)
+        .bind(&collection.schema_name)
+        .bind(&collection.table_name)
+        .fetch_one(self.db.pool())
+        .await?;
+        Ok(can_read)
+    }
+
+    /// Fail unless `username` owns the collection or it is shared with them
+    /// for writing
+    async fn check_write_access(&self, collection: &Collection, username: &str) -> AppResult<()> {
+        if collection.owner == username {
+            return Ok(());
+        }
+        // Raster and point cloud collections have no feature table to grant
+        let can_write: bool = sqlx::query_scalar(
+            r#"
+            SELECT CASE
+                WHEN to_regclass(format('%I.%I', $2::text, $3::text)) IS NULL THEN false
+                ELSE pg_catalog.has_table_privilege($1, format('%I.%I', $2::text, $3::text), 'UPDATE')
+            END
+            "#,
+        )
+        .bind(username)
+        .bind(&collection.schema_name)
+        .bind(&collection.table_name)
+        .fetch_one(self.db.pool())
+        .await?;
+        if !can_write {
+            return Err(AppError::Forbidden(format!(
+                "Write access to {} is required",
+                collection.canonical_name
+            )));
+        }
+        Ok(())
+    }
+
+    async fn current_lock(
+        &self,
+        collection: &Collection,
+        feature_id: Uuid,
+    ) -> AppResult<Option<FeatureLock>> {
+        let lock = sqlx::query_as(
+            r#"
+            SELECT feature_id, locked_by, locked_at, expires_at
+            FROM spatialvault.feature_locks
+            WHERE collection_id = $1 AND feature_id = $2 AND expires_at > NOW()
+            "#,
+        )
+        .bind(collection.id)
+        .bind(feature_id)
+        .fetch_optional(self.db.pool())
+        .await?;
+        Ok(lock)
+    }
+
+    /// Conflict for a feature locked by someone else
+    async fn locked_error(&self, collection: &Collection, feature_id: Uuid) -> AppResult<AppError> {
+        Ok(match self.current_lock(collection, feature_id).await? {
+            Some(lock) => lock_conflict(&lock),
+            // The lock expired in the meantime
+            None => AppError::Conflict(format!("Feature {} was locked, try again", feature_id)),
+        })
+    }
+
+    /// Fail if a feature is locked by someone other than `username`
+    async fn check_feature_lock(
+        &self,
+        collection: &Collection,
+        feature_id: Uuid,
+        username: &str,
+    ) -> AppResult<()> {
+        match self.current_lock(collection, feature_id).await? {
+            Some(lock) if lock.locked_by != username => Err(lock_conflict(&lock)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Drop the lock of a deleted feature
+    async fn remove_feature_lock(
+        &self,
+        collection: &Collection,
+        feature_id: Uuid,
+    ) -> AppResult<()> {
+        sqlx::query(
+            "DELETE FROM spatialvault.feature_locks WHERE collection_id = $1 AND feature_id = $2",
+        )
+        .bind(collection.id)
+        .bind(feature_id)
+        .execute(self.db.pool())
+        .await?;
         Ok(())
     }
 
+    async fn feature_exists(&self, collection: &Collection, feature_id: Uuid) -> AppResult<bool> {
+        let exists = if collection.has_feature_table() {
+            sqlx::query_scalar(&format!(
+                "SELECT EXISTS (SELECT 1 FROM {}.{} WHERE id = $1)",
+                quote_ident(&collection.schema_name),
+                quote_ident(&collection.table_name)
+            ))
+            .bind(feature_id)
+            .fetch_one(self.db.pool())
+            .await?
+        } else {
+            sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM spatialvault.items WHERE id = $1 AND collection_id = $2)",
+            )
+            .bind(feature_id)
+            .bind(collection.id)
+            .fetch_one(self.db.pool())
+            .await?
+        };
+        Ok(exists)
+    }
+
     async fn get_collection(&self, collection_id: &str) -> AppResult<Collection> {
         sqlx::query_as("SELECT * FROM spatialvault.collections WHERE canonical_name = $1")
             .bind(collection_id)
@@ -1774,7 +3067,7 @@ impl FeatureService {
         let sql = format!(
             r#"
             SELECT srid FROM geometry_columns
-            WHERE f_table_schema = $1 AND f_table_name = $2
+            WHERE f_table_schema = $1 AND f_table_name = $2 AND f_geometry_column = 'geometry'
             "#
         );
 
@@ -1787,3 +3080,205 @@ impl FeatureService {
         Ok(result.map(|(srid,)| srid).unwrap_or(4326))
     }
 }
+
+fn bind_arg<'q, T>(args: &mut PgArguments, value: T) -> AppResult<()>
+where
+    T: sqlx::Encode<'q, sqlx::Postgres> + sqlx::Type<sqlx::Postgres> + 'q,
+{
+    args.add(value)
+        .map_err(|e| AppError::Internal(format!("Failed to bind query parameter: {}", e)))
+}
+
+/// Apply a JSON Merge Patch (RFC 7386) to `target`
+///
+/// Object members set to `null` in the patch are removed, nested objects are
+/// merged recursively and any other value replaces the target.
+pub fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let serde_json::Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::json!({});
+    }
+    let target = target.as_object_mut().expect("target is an object");
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(
+                target.entry(key.clone()).or_insert(serde_json::Value::Null),
+                value,
+            );
+        }
+    }
+}
+
+/// SQL of a collection's computed properties, with columns prefixed by `prefix`
+fn computed_sql(collection: &Collection, prefix: &str) -> AppResult<Vec<ComputedSql>> {
+    computed_properties_sql(collection.computed_properties.as_ref(), prefix)
+}
+
+/// Properties to store, without computed properties, which are read-only
+fn without_computed(
+    collection: &Collection,
+    mut properties: serde_json::Value,
+) -> serde_json::Value {
+    if let Some(object) = properties.as_object_mut() {
+        for property in parse_computed_properties(collection.computed_properties.as_ref()) {
+            object.remove(&property.name);
+        }
+    }
+    properties
+}
+
+/// Properties of a feature after applying a merge patch to them
+fn merged_properties(
+    current: Option<serde_json::Value>,
+    patch: &serde_json::Value,
+) -> serde_json::Value {
+    let mut properties = current.unwrap_or_else(|| serde_json::json!({}));
+    merge_patch(&mut properties, patch);
+    properties
+}
+
+/// Estimated row count of the top plan node of `EXPLAIN (FORMAT JSON)` output
+fn plan_row_estimate(plan: &serde_json::Value) -> u64 {
+    plan[0]["Plan"]["Plan Rows"]
+        .as_f64()
+        .unwrap_or(0.0)
+        .max(0.0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_row_estimate() {
+        let plan =
+            serde_json::json!([{ "Plan": { "Node Type": "Seq Scan", "Plan Rows": 2500000 } }]);
+        assert_eq!(plan_row_estimate(&plan), 2_500_000);
+        assert_eq!(plan_row_estimate(&serde_json::json!([])), 0);
+    }
+
+    #[test]
+    fn test_last_per_key() {
+        let feature = |properties: serde_json::Value| (serde_json::Value::Null, properties);
+        let features = vec![
+            feature(serde_json::json!({"external_id": 1, "v": "old"})),
+            feature(serde_json::json!({"v": "no key"})),
+            feature(serde_json::json!({"external_id": "2"})),
+            feature(serde_json::json!({"external_id": "1", "v": "new"})),
+            feature(serde_json::json!({"v": "no key either"})),
+        ];
+        let kept: Vec<serde_json::Value> = last_per_key(features, "external_id")
+            .into_iter()
+            .map(|(_, properties)| properties)
+            .collect();
+        assert_eq!(
+            kept,
+            vec![
+                serde_json::json!({"v": "no key"}),
+                serde_json::json!({"external_id": "2"}),
+                serde_json::json!({"external_id": "1", "v": "new"}),
+                serde_json::json!({"v": "no key either"}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        let mut target = serde_json::json!({
+            "name": "a",
+            "remove": 1,
+            "nested": { "keep": true, "drop": "x" },
+            "list": [1, 2]
+        });
+        merge_patch(
+            &mut target,
+            &serde_json::json!({
+                "remove": null,
+                "nested": { "drop": null, "new": 2 },
+                "list": [3],
+                "missing": null
+            }),
+        );
+        assert_eq!(
+            target,
+            serde_json::json!({
+                "name": "a",
+                "nested": { "keep": true, "new": 2 },
+                "list": [3]
+            })
+        );
+
+        // A non-object patch replaces the target
+        let mut target = serde_json::json!({ "a": 1 });
+        merge_patch(&mut target, &serde_json::json!("b"));
+        assert_eq!(target, serde_json::json!("b"));
+
+        // RFC 7386 appendix A: nulls inside new objects are dropped
+        let mut target = serde_json::json!({ "a": "b" });
+        merge_patch(&mut target, &serde_json::json!({ "c": { "d": null } }));
+        assert_eq!(target, serde_json::json!({ "a": "b", "c": {} }));
+    }
+
+    #[test]
+    fn test_merged_properties() {
+        let merged = merged_properties(None, &serde_json::json!({ "a": 1, "b": null }));
+        assert_eq!(merged, serde_json::json!({ "a": 1 }));
+    }
+}
+
+/// GeoJSON of a feature table's geometry expression, or null for
+/// attribute-only tables
+fn geometry_json_sql(collection: &Collection, geometry_expr: &str) -> String {
+    if collection.has_geometry() {
+        format!("ST_AsGeoJSON({})::jsonb", geometry_expr)
+    } else {
+        "NULL::jsonb".to_string()
+    }
+}
+
+/// Id, WGS 84 GeoJSON geometry and properties of an exported feature
+type ExportRow = (Uuid, Option<serde_json::Value>, Option<serde_json::Value>);
+
+/// Arguments of [`FeatureService::export_sql`]: the bbox if any, the ID the
+/// page starts after and the page size
+fn export_args(bbox: Option<[f64; 4]>, after: Option<Uuid>, limit: i64) -> AppResult<PgArguments> {
+    let mut args = PgArguments::default();
+    for coord in bbox.into_iter().flatten() {
+        bind_arg(&mut args, coord)?;
+    }
+    bind_arg(&mut args, after)?;
+    bind_arg(&mut args, limit)?;
+    Ok(args)
+}
+
+fn export_feature((id, geometry, properties): ExportRow) -> Feature {
+    Feature {
+        feature_type: "Feature".to_string(),
+        id: id.to_string(),
+        geometry: geometry.unwrap_or(serde_json::Value::Null),
+        properties: properties.unwrap_or(serde_json::json!({})),
+        links: None,
+        bbox: None,
+        assets: None,
+        collection: None,
+        stac_version: None,
+        stac_extensions: None,
+    }
+}
+
+/// Attribute-only collections only take features with a null geometry
+fn check_no_geometry(collection_id: &str, geometry: &serde_json::Value) -> AppResult<()> {
+    if geometry.is_null() {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(format!(
+            "Collection {} has no geometry; features must have a null geometry",
+            collection_id
+        )))
+    }
+}
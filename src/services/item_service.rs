@@ -156,6 +156,43 @@ impl ItemService {
         Ok(items)
     }
 
+    /// Hrefs of the `data` assets of the items of a collection in a closed
+    /// interval, intersecting `bbox` (CRS84) if given, newest first
+    pub async fn data_asset_hrefs(
+        &self,
+        collection_id: Uuid,
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
+        bbox: Option<[f64; 4]>,
+        limit: i64,
+    ) -> AppResult<Vec<String>> {
+        let [minx, miny, maxx, maxy] = bbox.map_or([None; 4], |bbox| bbox.map(Some));
+        let hrefs: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT a.href
+            FROM spatialvault.items i
+            JOIN spatialvault.assets a ON a.item_id = i.id AND a.key = 'data'
+            WHERE i.collection_id = $1 AND i.datetime >= $2 AND i.datetime <= $3
+              AND ($4::float8 IS NULL
+                   OR i.geometry && ST_MakeEnvelope($4, $5, $6, $7, 4326))
+            ORDER BY i.datetime DESC, i.created_at DESC
+            LIMIT $8
+            "#,
+        )
+        .bind(collection_id)
+        .bind(start)
+        .bind(end)
+        .bind(minx)
+        .bind(miny)
+        .bind(maxx)
+        .bind(maxy)
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(hrefs)
+    }
+
     /// Get assets for an item
     pub async fn get_item_assets(&self, item_id: Uuid) -> AppResult<Vec<Asset>> {
         let assets: Vec<Asset> = sqlx::query_as(
@@ -0,0 +1,233 @@
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::api::read_only;
+use crate::config::LinkCheckConfig;
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::net;
+use crate::storage::{S3Storage, s3_key_from_uri};
+use crate::telemetry;
+
+/// Most assets of each table checked in one round
+const CHECK_BATCH_SIZE: i64 = 100;
+
+/// Hrefs checked at the same time
+const CHECK_CONCURRENCY: usize = 8;
+
+/// How long a server may take to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most redirects followed for an href
+const MAX_REDIRECTS: usize = 5;
+
+/// Value of `link_status` for hrefs that resolve
+pub const LINK_OK: &str = "ok";
+
+/// Value of `link_status` for hrefs that don't resolve
+pub const LINK_BROKEN: &str = "broken";
+
+/// An asset claimed for checking
+#[derive(Debug, sqlx::FromRow)]
+struct ClaimedAsset {
+    id: Uuid,
+    href: String,
+}
+
+/// Tables holding assets with hrefs to check
+const ASSET_TABLES: [&str; 2] = ["spatialvault.assets", "spatialvault.collection_assets"];
+
+/// Checks that the hrefs of item and collection assets still resolve
+///
+/// Assets never checked, or not checked for `recheck_hours`, are claimed by
+/// setting their check time before the requests are made, so every server
+/// may run the loop and each asset is checked once per period.
+///
+/// Only whether an href resolves is recorded, not why it doesn't, and hrefs
+/// on non-public addresses are never requested, also after a redirect; see
+/// [`crate::net`].
+pub struct LinkCheckService {
+    db: Arc<Database>,
+    storage: Arc<S3Storage>,
+    config: LinkCheckConfig,
+    client: reqwest::Client,
+}
+
+impl LinkCheckService {
+    /// Checker of asset hrefs, if link checks are enabled
+    pub fn new(
+        db: Arc<Database>,
+        storage: Arc<S3Storage>,
+        config: &LinkCheckConfig,
+    ) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let client = net::client_builder()
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::custom(|attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS
+                    || net::check_url(attempt.url()).is_err()
+                {
+                    attempt.stop()
+                } else {
+                    attempt.follow()
+                }
+            }))
+            .build()
+            .ok()?;
+        Some(Self {
+            db,
+            storage,
+            config: config.clone(),
+            client,
+        })
+    }
+
+    /// Check every `interval`, for as long as the server runs
+    pub async fn run(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if read_only::paused(&self.db).await {
+                continue;
+            }
+            for table in ASSET_TABLES {
+                if let Err(e) = self.check_due(table).await {
+                    tracing::warn!("Failed to check asset links in {}: {}", table, e);
+                }
+            }
+        }
+    }
+
+    /// Check the assets of a table that are due, returning how many were
+    /// checked
+    async fn check_due(&self, table: &str) -> AppResult<usize> {
+        let claimed: Vec<ClaimedAsset> = sqlx::query_as(&format!(
+            r#"
+            WITH due AS (
+                SELECT id FROM {table}
+                WHERE link_checked_at IS NULL
+                   OR link_checked_at < NOW() - make_interval(hours => $1)
+                ORDER BY link_checked_at NULLS FIRST
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE {table} a
+            SET link_checked_at = NOW()
+            FROM due
+            WHERE a.id = due.id
+            RETURNING a.id, a.href
+            "#
+        ))
+        .bind(self.config.recheck_hours as i32)
+        .bind(CHECK_BATCH_SIZE)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        if claimed.is_empty() {
+            return Ok(0);
+        }
+
+        let results: Vec<(Uuid, bool)> = futures::stream::iter(claimed)
+            .map(|asset| async move { (asset.id, self.check_href(&asset.href).await) })
+            .buffer_unordered(CHECK_CONCURRENCY)
+            .collect()
+            .await;
+
+        let ids: Vec<Uuid> = results.iter().map(|(id, _)| *id).collect();
+        let statuses: Vec<&str> = results
+            .iter()
+            .map(|(_, ok)| if *ok { LINK_OK } else { LINK_BROKEN })
+            .collect();
+        sqlx::query(&format!(
+            r#"
+            UPDATE {table} a
+            SET link_status = u.status, link_checked_at = NOW()
+            FROM UNNEST($1::uuid[], $2::text[]) AS u(id, status)
+            WHERE a.id = u.id
+            "#
+        ))
+        .bind(&ids)
+        .bind(&statuses)
+        .execute(self.db.pool())
+        .await?;
+
+        let broken = results.iter().filter(|(_, ok)| !ok).count();
+        tracing::debug!(
+            "Checked {} asset links in {}, {} broken",
+            results.len(),
+            table,
+            broken
+        );
+        Ok(results.len())
+    }
+
+    /// Whether an href resolves
+    async fn check_href(&self, href: &str) -> bool {
+        if href.starts_with("s3://") {
+            return self.storage.head(s3_key_from_uri(href)).await.is_ok();
+        }
+        let Ok(url) = reqwest::Url::parse(href) else {
+            return false;
+        };
+        if !matches!(url.scheme(), "http" | "https") || net::check_url(&url).is_err() {
+            return false;
+        }
+
+        let Ok(response) = self
+            .client
+            .head(url.clone())
+            .headers(telemetry::trace_headers())
+            .send()
+            .await
+        else {
+            return false;
+        };
+        let mut status = response.status();
+
+        // Some servers don't answer HEAD; ask for the first byte instead
+        if head_unsupported(status) {
+            let Ok(response) = self
+                .client
+                .get(url)
+                .headers(telemetry::trace_headers())
+                .header(reqwest::header::RANGE, "bytes=0-0")
+                .send()
+                .await
+            else {
+                return false;
+            };
+            status = response.status();
+        }
+
+        status.is_success()
+    }
+}
+
+/// Whether a response to a HEAD request says the method isn't supported
+fn head_unsupported(status: reqwest::StatusCode) -> bool {
+    matches!(
+        status,
+        reqwest::StatusCode::METHOD_NOT_ALLOWED
+            | reqwest::StatusCode::NOT_IMPLEMENTED
+            | reqwest::StatusCode::FORBIDDEN
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_head_unsupported() {
+        assert!(head_unsupported(reqwest::StatusCode::METHOD_NOT_ALLOWED));
+        // Presigned S3 URLs are only valid for GET
+        assert!(head_unsupported(reqwest::StatusCode::FORBIDDEN));
+        assert!(!head_unsupported(reqwest::StatusCode::NOT_FOUND));
+        assert!(!head_unsupported(reqwest::StatusCode::OK));
+    }
+}
@@ -1,15 +1,36 @@
+pub mod analytics_service;
+pub mod cdn_service;
 pub mod collection_service;
 pub mod coverage_service;
 pub mod feature_service;
 pub mod item_service;
+pub mod link_check_service;
+pub mod notification_service;
+pub mod pointcloud_service;
 pub mod process_service;
+pub mod quota_service;
+pub mod replication_service;
 pub mod stac_service;
 pub mod tile_service;
+pub mod upload_service;
+pub mod webhook_service;
 
+pub use analytics_service::{AnalyticsService, UsageInterval};
+pub use cdn_service::CdnService;
 pub use collection_service::CollectionService;
 pub use coverage_service::CoverageService;
-pub use feature_service::FeatureService;
+pub use feature_service::{
+    BulkInsertCounts, ConflictAction, CountMode, FeatureBulkInsert, FeatureLock, FeatureService,
+    OnConflict, PropertyType, ResultType,
+};
 pub use item_service::ItemService;
-pub use process_service::ProcessService;
+pub use link_check_service::LinkCheckService;
+pub use notification_service::{NotificationService, NotificationType};
+pub use pointcloud_service::PointCloudService;
+pub use process_service::{JobListFilter, ProcessService};
+pub use quota_service::QuotaService;
+pub use replication_service::ReplicationService;
 pub use stac_service::StacService;
 pub use tile_service::TileService;
+pub use upload_service::UploadService;
+pub use webhook_service::WebhookService;
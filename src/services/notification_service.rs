@@ -0,0 +1,288 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::api::read_only;
+use crate::config::NotificationConfig;
+use crate::db::{Database, NotificationSettings};
+use crate::error::{AppError, AppResult};
+
+/// Most notifications sent in one round
+const NOTIFICATION_BATCH_SIZE: i64 = 100;
+
+/// How long a server may send a notification before others may take it over
+const CLAIM_SECS: f64 = 5.0 * 60.0;
+
+/// How long the notification service may take to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a user is notified about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationType {
+    /// A collection was shared with the user
+    CollectionShared,
+    /// A job of the user succeeded or failed for good
+    JobFinished,
+}
+
+impl NotificationType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::CollectionShared => "collection_shared",
+            Self::JobFinished => "job_finished",
+        }
+    }
+}
+
+/// Queue a notification, if the recipient wants to be notified about it
+pub async fn queue_notification<'e, E>(
+    executor: E,
+    recipient: &str,
+    notification_type: NotificationType,
+    data: &serde_json::Value,
+) -> AppResult<()>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO spatialvault.notifications (recipient, notification_type, data)
+        SELECT s.username, $2, $3
+        FROM spatialvault.notification_settings s
+        WHERE s.username = $1
+          AND CASE $2
+              WHEN 'collection_shared' THEN s.collection_shared
+              WHEN 'job_finished' THEN s.job_finished
+              ELSE FALSE
+          END
+        "#,
+    )
+    .bind(recipient)
+    .bind(notification_type.as_str())
+    .bind(data)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// A notification claimed for sending
+#[derive(Debug, sqlx::FromRow)]
+struct ClaimedNotification {
+    id: i64,
+    recipient: String,
+    notification_type: String,
+    data: serde_json::Value,
+    created_at: DateTime<Utc>,
+    email: Option<String>,
+}
+
+/// Body posted to the notification service
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NotificationBody<'a> {
+    id: i64,
+    #[serde(rename = "type")]
+    notification_type: &'a str,
+    recipient: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<&'a str>,
+    time: DateTime<Utc>,
+    data: &'a serde_json::Value,
+}
+
+/// Notification preferences of users, and the sending of their notifications
+///
+/// Notifications are queued in the database by whatever causes them and
+/// posted to the configured notification service, which informs the user
+/// (e.g. by email). Failed notifications are retried in later rounds, up to
+/// the configured number of attempts. Notifications are claimed for a while
+/// before sending, so every server may run the loop.
+pub struct NotificationService {
+    db: Arc<Database>,
+    config: NotificationConfig,
+    client: reqwest::Client,
+}
+
+impl NotificationService {
+    pub fn new(db: Arc<Database>, config: &NotificationConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            db,
+            config: config.clone(),
+            client,
+        }
+    }
+
+    /// Whether a notification service is configured
+    pub fn is_enabled(&self) -> bool {
+        self.config.url.is_some()
+    }
+
+    /// Notification preferences of a user
+    pub async fn get_settings(&self, username: &str) -> AppResult<NotificationSettings> {
+        let settings: Option<NotificationSettings> = sqlx::query_as(
+            r#"
+            SELECT email, collection_shared, job_finished
+            FROM spatialvault.notification_settings
+            WHERE username = $1
+            "#,
+        )
+        .bind(username)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(settings.unwrap_or_default())
+    }
+
+    /// Replace the notification preferences of a user
+    pub async fn update_settings(
+        &self,
+        username: &str,
+        settings: &NotificationSettings,
+    ) -> AppResult<NotificationSettings> {
+        let settings: NotificationSettings = sqlx::query_as(
+            r#"
+            INSERT INTO spatialvault.notification_settings
+                (username, email, collection_shared, job_finished)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (username) DO UPDATE
+            SET email = EXCLUDED.email,
+                collection_shared = EXCLUDED.collection_shared,
+                job_finished = EXCLUDED.job_finished,
+                updated_at = NOW()
+            RETURNING email, collection_shared, job_finished
+            "#,
+        )
+        .bind(username)
+        .bind(&settings.email)
+        .bind(settings.collection_shared)
+        .bind(settings.job_finished)
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok(settings)
+    }
+
+    /// Send every `interval`, for as long as the server runs
+    pub async fn run(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if read_only::paused(&self.db).await {
+                continue;
+            }
+            if let Err(e) = self.send_pending().await {
+                tracing::warn!("Failed to send notifications: {}", e);
+            }
+        }
+    }
+
+    /// Send the pending notifications no other server is sending
+    pub async fn send_pending(&self) -> AppResult<()> {
+        let Some(url) = &self.config.url else {
+            return Ok(());
+        };
+
+        let claimed: Vec<ClaimedNotification> = sqlx::query_as(
+            r#"
+            WITH due AS (
+                SELECT id FROM spatialvault.notifications
+                WHERE sent_at IS NULL AND attempts < $2
+                  AND (claimed_until IS NULL OR claimed_until < NOW())
+                ORDER BY id
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE spatialvault.notifications n
+            SET claimed_until = NOW() + make_interval(secs => $3), attempts = n.attempts + 1
+            FROM due
+            WHERE n.id = due.id
+            RETURNING n.id, n.recipient, n.notification_type, n.data, n.created_at,
+                (SELECT s.email FROM spatialvault.notification_settings s
+                 WHERE s.username = n.recipient) AS email
+            "#,
+        )
+        .bind(NOTIFICATION_BATCH_SIZE)
+        .bind(self.config.max_attempts as i32)
+        .bind(CLAIM_SECS)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        for notification in &claimed {
+            let result = self.send(url, notification).await;
+            if let Err(e) = &result {
+                tracing::warn!(
+                    recipient = %notification.recipient,
+                    "Failed to send {} notification: {}",
+                    notification.notification_type,
+                    e
+                );
+            }
+            self.release(notification.id, result).await?;
+        }
+
+        self.prune_notifications().await
+    }
+
+    async fn send(&self, url: &str, notification: &ClaimedNotification) -> AppResult<()> {
+        let body = NotificationBody {
+            id: notification.id,
+            notification_type: &notification.notification_type,
+            recipient: &notification.recipient,
+            email: notification.email.as_deref(),
+            time: notification.created_at,
+            data: &notification.data,
+        };
+        let mut request = self.client.post(url).json(&body);
+        if let Some(token) = &self.config.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.map_err(|e| {
+            AppError::Upstream(format!("Notification service request failed: {}", e))
+        })?;
+        if !response.status().is_success() {
+            return Err(AppError::Upstream(format!(
+                "Notification service answered with {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Give up the claim on a notification, recording whether it was sent
+    async fn release(&self, notification_id: i64, result: AppResult<()>) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE spatialvault.notifications
+            SET claimed_until = NULL,
+                sent_at = CASE WHEN $2::text IS NULL THEN NOW() END,
+                last_error = $2
+            WHERE id = $1
+            "#,
+        )
+        .bind(notification_id)
+        .bind(result.err().map(|e| e.to_string()))
+        .execute(self.db.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// Forget notifications older than the retention period, sent or not
+    async fn prune_notifications(&self) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            DELETE FROM spatialvault.notifications
+            WHERE created_at < NOW() - make_interval(days => $1)
+            "#,
+        )
+        .bind(self.config.retention_days as i32)
+        .execute(self.db.pool())
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,297 @@
+use bytes::Bytes;
+use futures::{StreamExt, stream::BoxStream};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::pointclouds::pnts::{encode_pnts, geodetic_to_ecef};
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::net;
+use crate::processing::copc::{COPC_HEADER_PROBE_SIZE, LasHeader, LasPointSampler};
+use crate::storage::{S3Storage, s3_key_from_uri};
+use crate::telemetry;
+
+/// Most points served in a single tile; larger files are thinned
+pub const MAX_TILE_POINTS: usize = 500_000;
+
+/// Largest point cloud file read to build a tile (4 GiB)
+///
+/// Files are streamed, so this bounds the time spent reading rather than
+/// memory.
+const MAX_SOURCE_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Points reprojected to ECEF per database query
+const TRANSFORM_BATCH: usize = 50_000;
+
+/// SRIDs of longitude, latitude (and ellipsoidal height) in WGS84
+const WGS84_SRIDS: [i32; 2] = [4326, 4979];
+
+/// A point cloud item and its footprint
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PointCloudItem {
+    pub id: Uuid,
+    pub minx: f64,
+    pub miny: f64,
+    pub maxx: f64,
+    pub maxy: f64,
+}
+
+pub struct PointCloudService {
+    db: Arc<Database>,
+    storage: Arc<S3Storage>,
+}
+
+impl PointCloudService {
+    pub fn new(db: Arc<Database>, storage: Arc<S3Storage>) -> Self {
+        Self { db, storage }
+    }
+
+    /// List the items of a collection that have a data asset
+    pub async fn list_items(&self, collection_id: Uuid) -> AppResult<Vec<PointCloudItem>> {
+        let items = sqlx::query_as(
+            r#"
+            SELECT i.id,
+                   ST_XMin(i.geometry) AS minx, ST_YMin(i.geometry) AS miny,
+                   ST_XMax(i.geometry) AS maxx, ST_YMax(i.geometry) AS maxy
+            FROM spatialvault.items i
+            WHERE i.collection_id = $1
+              AND EXISTS (
+                  SELECT 1 FROM spatialvault.assets a
+                  WHERE a.item_id = i.id AND a.key = 'data'
+              )
+            ORDER BY i.created_at, i.id
+            "#,
+        )
+        .bind(collection_id)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Render the data asset of an item as a 3D Tiles point cloud tile
+    ///
+    /// Tiles are kept in storage under the item's version, so a file is
+    /// only read again after the item changed. The CRS of the file is the
+    /// `proj:epsg` of the asset or the item; without one, files whose
+    /// bounds look like longitudes and latitudes are taken to be in WGS84.
+    pub async fn get_item_tile(
+        &self,
+        owner: &str,
+        collection_id: Uuid,
+        item_id: Uuid,
+    ) -> AppResult<Bytes> {
+        let asset: Option<(String, Option<i64>, Option<i32>, i64)> = sqlx::query_as(
+            r#"
+            SELECT a.href, a.file_size,
+                   COALESCE(a.extra_fields->>'proj:epsg', i.properties->>'proj:epsg')::int,
+                   i.version
+            FROM spatialvault.assets a
+            JOIN spatialvault.items i ON i.id = a.item_id
+            WHERE i.collection_id = $1 AND i.id = $2 AND a.key = 'data'
+            "#,
+        )
+        .bind(collection_id)
+        .bind(item_id)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        let (href, file_size, srid, version) =
+            asset.ok_or_else(|| AppError::NotFound(format!("Item not found: {}", item_id)))?;
+        if file_size.is_some_and(|size| size as u64 > MAX_SOURCE_BYTES) {
+            return Err(AppError::Conflict(format!(
+                "Item {} is too large to serve as a single tile",
+                item_id
+            )));
+        }
+
+        let prefix = format!("{}/3dtiles/{}/", owner, item_id);
+        let key = format!("{}{}.pnts", prefix, version);
+        if self.storage.exists(&key).await? {
+            return self.storage.get(&key).await;
+        }
+
+        let tile = Bytes::from(self.render_tile(&href, srid).await?);
+        // Tiles of earlier versions are replaced
+        let cached = async {
+            self.storage.delete_prefix(&prefix).await?;
+            self.storage.put(&key, tile.clone()).await
+        };
+        if let Err(e) = cached.await {
+            tracing::warn!(item_id = %item_id, error = %e, "Failed to cache point cloud tile");
+        }
+        Ok(tile)
+    }
+
+    /// Read a LAS file as a stream and encode its points as a tile
+    async fn render_tile(&self, href: &str, srid: Option<i32>) -> AppResult<Vec<u8>> {
+        let mut stream = self.fetch(href).await?;
+
+        // Enough of the file for its header, which decides whether and how
+        // the points can be read before any of them are
+        let mut head = Vec::new();
+        while head.len() < COPC_HEADER_PROBE_SIZE
+            && let Some(chunk) = stream.next().await
+        {
+            head.extend_from_slice(&chunk?);
+        }
+        let header = LasHeader::parse(&head)?;
+        let srid = match srid {
+            Some(srid) => srid,
+            None if header.is_geographic() => 4326,
+            None => {
+                return Err(AppError::Conflict(
+                    "The CRS of the point cloud is unknown; set proj:epsg on its data asset"
+                        .to_string(),
+                ));
+            }
+        };
+
+        let mut sampler = LasPointSampler::new(header, MAX_TILE_POINTS)?;
+        let mut size = head.len() as u64;
+        sampler.push(&head);
+        drop(head);
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            size += chunk.len() as u64;
+            if size > MAX_SOURCE_BYTES {
+                return Err(AppError::Conflict(
+                    "Point cloud is too large to serve as a single tile".to_string(),
+                ));
+            }
+            sampler.push(&chunk);
+        }
+        let points = sampler.finish()?;
+
+        let positions: Vec<[f64; 3]> = points.iter().map(|p| p.position).collect();
+        let ecef = if WGS84_SRIDS.contains(&srid) {
+            positions
+                .iter()
+                .map(|p| geodetic_to_ecef(p[0], p[1], p[2]))
+                .collect()
+        } else {
+            self.transform_to_ecef(&positions, srid).await?
+        };
+
+        // LAS colors are 16-bit, though some writers store 8-bit values
+        let is_16_bit = points
+            .iter()
+            .flat_map(|p| p.color)
+            .flatten()
+            .any(|c| c > 255);
+        let colors: Option<Vec<[u8; 3]>> = points
+            .iter()
+            .map(|p| {
+                p.color
+                    .map(|rgb| rgb.map(|c| if is_16_bit { (c >> 8) as u8 } else { c as u8 }))
+            })
+            .collect();
+
+        Ok(ecef_to_pnts(&ecef, colors.as_deref()))
+    }
+
+    /// Reproject points from `srid` to ECEF (EPSG:4978) with PostGIS
+    ///
+    /// Heights are taken as ellipsoidal heights.
+    async fn transform_to_ecef(&self, points: &[[f64; 3]], srid: i32) -> AppResult<Vec<[f64; 3]>> {
+        let mut ecef = Vec::with_capacity(points.len());
+        for batch in points.chunks(TRANSFORM_BATCH) {
+            let [xs, ys, zs] =
+                [0, 1, 2].map(|axis| batch.iter().map(|p| p[axis]).collect::<Vec<f64>>());
+            let rows: Vec<(f64, f64, f64)> = sqlx::query_as(
+                r#"
+                SELECT ST_X(p), ST_Y(p), ST_Z(p)
+                FROM (
+                    SELECT n, ST_Transform(ST_SetSRID(ST_MakePoint(x, y, z), $4), 4978) AS p
+                    FROM unnest($1::float8[], $2::float8[], $3::float8[])
+                        WITH ORDINALITY AS t(x, y, z, n)
+                ) transformed
+                ORDER BY n
+                "#,
+            )
+            .bind(&xs)
+            .bind(&ys)
+            .bind(&zs)
+            .bind(srid)
+            .fetch_all(self.db.pool())
+            .await
+            .map_err(|e| {
+                AppError::Conflict(format!(
+                    "Point cloud cannot be reprojected from EPSG:{}: {}",
+                    srid, e
+                ))
+            })?;
+            ecef.extend(rows.into_iter().map(|(x, y, z)| [x, y, z]));
+        }
+        Ok(ecef)
+    }
+
+    /// Read an asset from S3 or over HTTP as a stream of chunks
+    async fn fetch(&self, href: &str) -> AppResult<BoxStream<'static, AppResult<Bytes>>> {
+        if href.starts_with("s3://") {
+            return self.storage.get_stream(s3_key_from_uri(href)).await;
+        }
+        let url = reqwest::Url::parse(href)
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .ok_or_else(|| AppError::Internal(format!("Unsupported asset URL scheme: {}", href)))?;
+        net::check_url(&url).map_err(AppError::Upstream)?;
+
+        let client = net::client_builder()
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to build HTTP client: {}", e)))?;
+        let response = client
+            .get(url)
+            .headers(telemetry::trace_headers())
+            .send()
+            .await
+            .map_err(|e| AppError::Upstream(format!("Failed to fetch point cloud: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(AppError::Upstream(format!(
+                "Fetching point cloud failed with status: {}",
+                response.status()
+            )));
+        }
+        Ok(
+            futures::stream::unfold(Some(response), |response| async move {
+                let mut response = response?;
+                match response.chunk().await {
+                    Ok(Some(chunk)) => Some((Ok(chunk), Some(response))),
+                    Ok(None) => None,
+                    Err(e) => Some((
+                        Err(AppError::Upstream(format!(
+                            "Failed to read point cloud: {}",
+                            e
+                        ))),
+                        None,
+                    )),
+                }
+            })
+            .boxed(),
+        )
+    }
+}
+
+/// Encode ECEF points as a `.pnts` tile centered on them
+fn ecef_to_pnts(ecef: &[[f64; 3]], colors: Option<&[[u8; 3]]>) -> Vec<u8> {
+    let center = if ecef.is_empty() {
+        [0.0; 3]
+    } else {
+        let sum = ecef.iter().fold([0.0; 3], |acc, p| {
+            [acc[0] + p[0], acc[1] + p[1], acc[2] + p[2]]
+        });
+        sum.map(|v| v / ecef.len() as f64)
+    };
+    let positions: Vec<[f32; 3]> = ecef
+        .iter()
+        .map(|p| {
+            [
+                (p[0] - center[0]) as f32,
+                (p[1] - center[1]) as f32,
+                (p[2] - center[2]) as f32,
+            ]
+        })
+        .collect();
+
+    encode_pnts(center, &positions, colors)
+}
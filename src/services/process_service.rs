@@ -1,9 +1,24 @@
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::db::{Database, ProcessJob};
+use crate::api::processes::deploy::ApplicationPackage;
+use crate::api::processes::workflow::{self, WorkflowRequest};
+use crate::auth::RoleManager;
+use crate::db::{Database, DeployedProcess, ProcessJob};
 use crate::error::{AppError, AppResult};
 
+/// Filter for the job list; `None` fields do not restrict the result
+#[derive(Debug, Default)]
+pub struct JobListFilter {
+    pub statuses: Option<Vec<String>>,
+    pub process_ids: Option<Vec<String>>,
+    pub types: Option<Vec<String>>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+}
+
 pub struct ProcessService {
     db: Arc<Database>,
 }
@@ -15,23 +30,24 @@ impl ProcessService {
 
     pub async fn create_job(
         &self,
+        job_id: Uuid,
         username: &str,
         process_id: &str,
         inputs: &serde_json::Value,
+        expires: Option<DateTime<Utc>>,
     ) -> AppResult<Uuid> {
-        let job_id = Uuid::new_v4();
-
         sqlx::query(
             r#"
             INSERT INTO spatialvault.processes_jobs
-            (id, process_id, owner, inputs)
-            VALUES ($1, $2, $3, $4)
+            (id, process_id, owner, inputs, expires)
+            VALUES ($1, $2, $3, $4, $5)
             "#,
         )
         .bind(job_id)
         .bind(process_id)
         .bind(username)
         .bind(inputs)
+        .bind(expires)
         .execute(self.db.pool())
         .await?;
 
@@ -42,22 +58,185 @@ impl ProcessService {
         Ok(job_id)
     }
 
-    pub async fn list_jobs(&self, username: &str) -> AppResult<Vec<ProcessJob>> {
-        let jobs: Vec<ProcessJob> = sqlx::query_as(
+    /// Create a workflow job with one child job per step
+    pub async fn create_workflow_job(
+        &self,
+        username: &str,
+        workflow: &WorkflowRequest,
+    ) -> AppResult<Uuid> {
+        let job_id = Uuid::new_v4();
+        let mut tx = self.db.pool().begin().await?;
+
+        sqlx::query(
             r#"
-            SELECT * FROM spatialvault.processes_jobs
-            WHERE owner = $1
-            ORDER BY created DESC
-            LIMIT 100
+            INSERT INTO spatialvault.processes_jobs
+            (id, process_id, owner, inputs, expires)
+            VALUES ($1, $2, $3, $4, $5)
             "#,
         )
+        .bind(job_id)
+        .bind(workflow::PROCESS_ID)
         .bind(username)
+        .bind(serde_json::to_value(workflow)?)
+        .bind(workflow.expires)
+        .execute(&mut *tx)
+        .await?;
+
+        for (index, step) in workflow.steps.iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO spatialvault.processes_jobs
+                (process_id, owner, inputs, parent_job_id, step_index, message)
+                VALUES ($1, $2, $3, $4, $5, 'Waiting for previous steps')
+                "#,
+            )
+            .bind(&step.process)
+            .bind(username)
+            .bind(serde_json::Value::Object(step.inputs.clone()))
+            .bind(job_id)
+            .bind(index as i32)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(job_id)
+    }
+
+    /// Find finished jobs past their expiry or the retention policy
+    ///
+    /// An explicit `expires` on the job takes precedence over the policy.
+    pub async fn list_expired_jobs(
+        &self,
+        successful_retention_days: u32,
+        failed_retention_days: u32,
+        limit: i64,
+    ) -> AppResult<Vec<(Uuid, String)>> {
+        let jobs: Vec<(Uuid, String)> = sqlx::query_as(
+            r#"
+            SELECT id, owner FROM spatialvault.processes_jobs
+            WHERE parent_job_id IS NULL
+              AND status IN ('successful', 'failed', 'dismissed')
+              AND COALESCE(
+                  expires,
+                  COALESCE(finished, updated) + make_interval(days => CASE
+                      WHEN status = 'successful' THEN $1
+                      ELSE $2
+                  END)
+              ) < NOW()
+            ORDER BY COALESCE(finished, updated)
+            LIMIT $3
+            "#,
+        )
+        .bind(successful_retention_days as i32)
+        .bind(failed_retention_days as i32)
+        .bind(limit)
         .fetch_all(self.db.pool())
         .await?;
 
         Ok(jobs)
     }
 
+    /// Delete a job record (workflow steps are removed by cascade)
+    pub async fn delete_job(&self, job_id: Uuid) -> AppResult<()> {
+        sqlx::query("DELETE FROM spatialvault.processes_jobs WHERE id = $1")
+            .bind(job_id)
+            .execute(self.db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get the step jobs of a workflow, in execution order
+    pub async fn list_workflow_steps(&self, parent_job_id: Uuid) -> AppResult<Vec<ProcessJob>> {
+        let steps: Vec<ProcessJob> = sqlx::query_as(
+            r#"
+            SELECT * FROM spatialvault.processes_jobs
+            WHERE parent_job_id = $1
+            ORDER BY step_index
+            "#,
+        )
+        .bind(parent_job_id)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(steps)
+    }
+
+    /// Mark the steps of a workflow that have not started as dismissed, with
+    /// `reason` as their message
+    pub async fn dismiss_pending_steps(&self, parent_job_id: Uuid, reason: &str) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE spatialvault.processes_jobs
+            SET status = 'dismissed', message = $2, finished = NOW(), updated = NOW()
+            WHERE parent_job_id = $1 AND status = 'accepted'
+            "#,
+        )
+        .bind(parent_job_id)
+        .bind(reason)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// List top-level jobs matching a filter, newest first
+    ///
+    /// Returns the requested page together with the number of matches per
+    /// job type.
+    pub async fn list_jobs(
+        &self,
+        username: &str,
+        filter: &JobListFilter,
+        limit: u32,
+        offset: u32,
+    ) -> AppResult<(Vec<ProcessJob>, BTreeMap<String, u64>)> {
+        const FILTER: &str = r#"
+            WHERE owner = $1 AND parent_job_id IS NULL
+              AND ($2::text[] IS NULL OR status = ANY($2))
+              AND ($3::text[] IS NULL OR process_id = ANY($3))
+              AND ($4::text[] IS NULL OR COALESCE(type, 'process') = ANY($4))
+              AND ($5::timestamptz IS NULL OR created >= $5)
+              AND ($6::timestamptz IS NULL OR created <= $6)
+        "#;
+
+        let jobs: Vec<ProcessJob> = sqlx::query_as(&format!(
+            "SELECT * FROM spatialvault.processes_jobs {} ORDER BY created DESC LIMIT $7 OFFSET $8",
+            FILTER
+        ))
+        .bind(username)
+        .bind(&filter.statuses)
+        .bind(&filter.process_ids)
+        .bind(&filter.types)
+        .bind(filter.created_after)
+        .bind(filter.created_before)
+        .bind(limit as i64)
+        .bind(offset as i64)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let counts: Vec<(String, i64)> = sqlx::query_as(&format!(
+            "SELECT COALESCE(type, 'process'), COUNT(*) FROM spatialvault.processes_jobs {} GROUP BY 1",
+            FILTER
+        ))
+        .bind(username)
+        .bind(&filter.statuses)
+        .bind(&filter.process_ids)
+        .bind(&filter.types)
+        .bind(filter.created_after)
+        .bind(filter.created_before)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let type_counts = counts
+            .into_iter()
+            .map(|(job_type, count)| (job_type, count as u64))
+            .collect();
+        Ok((jobs, type_counts))
+    }
+
     pub async fn get_job(&self, username: &str, job_id: Uuid) -> AppResult<Option<ProcessJob>> {
         let job: Option<ProcessJob> = sqlx::query_as(
             "SELECT * FROM spatialvault.processes_jobs WHERE id = $1 AND owner = $2",
@@ -87,7 +266,7 @@ impl ProcessService {
                 updated = NOW(),
                 started = CASE WHEN $2 = 'running' AND started IS NULL THEN NOW() ELSE started END,
                 finished = CASE WHEN $2 IN ('successful', 'failed', 'dismissed') THEN NOW() ELSE finished END
-            WHERE id = $1
+            WHERE id = $1 AND status <> 'dismissed'
             "#,
         )
         .bind(job_id)
@@ -109,7 +288,7 @@ impl ProcessService {
             r#"
             UPDATE spatialvault.processes_jobs
             SET outputs = $2, status = 'successful', finished = NOW(), updated = NOW()
-            WHERE id = $1
+            WHERE id = $1 AND status <> 'dismissed'
             "#,
         )
         .bind(job_id)
@@ -120,12 +299,126 @@ impl ProcessService {
         Ok(())
     }
 
+    /// Put a job back in the queue after a transient failure
+    ///
+    /// The error is appended to the job's error chain and the job becomes
+    /// eligible again once the backoff delay has passed. Workflow steps that
+    /// didn't succeed are reset, so the workflow resumes at the failed step.
+    pub async fn schedule_retry(
+        &self,
+        job_id: Uuid,
+        attempt: i32,
+        error: &str,
+        delay: std::time::Duration,
+    ) -> AppResult<()> {
+        let mut tx = self.db.pool().begin().await?;
+
+        let requeued = sqlx::query(
+            r#"
+            UPDATE spatialvault.processes_jobs
+            SET
+                status = 'accepted',
+                message = $3,
+                next_attempt_at = NOW() + make_interval(secs => $4),
+                errors = errors || jsonb_build_array(
+                    jsonb_build_object('attempt', $2, 'error', $5, 'time', NOW())
+                ),
+                updated = NOW()
+            WHERE id = $1 AND status <> 'dismissed'
+            "#,
+        )
+        .bind(job_id)
+        .bind(attempt)
+        .bind(format!("Attempt {} failed, retrying: {}", attempt, error))
+        .bind(delay.as_secs_f64())
+        .bind(error)
+        .execute(&mut *tx)
+        .await?;
+
+        if requeued.rows_affected() > 0 {
+            reset_workflow_steps(&mut tx, job_id).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Mark a job as failed for good, keeping the errors of all attempts
+    pub async fn fail_job(&self, job_id: Uuid, attempt: i32, error: &str) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE spatialvault.processes_jobs
+            SET
+                status = 'failed',
+                message = $3,
+                errors = errors || jsonb_build_array(
+                    jsonb_build_object('attempt', $2, 'error', $4, 'time', NOW())
+                ),
+                finished = NOW(),
+                updated = NOW()
+            WHERE id = $1 AND status <> 'dismissed'
+            "#,
+        )
+        .bind(job_id)
+        .bind(attempt)
+        .bind(format!("Failed after {} attempt(s): {}", attempt, error))
+        .bind(error)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Manually requeue a failed job with a fresh attempt budget
+    pub async fn requeue_job(&self, username: &str, job_id: Uuid) -> AppResult<()> {
+        let mut tx = self.db.pool().begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE spatialvault.processes_jobs
+            SET
+                status = 'accepted',
+                message = 'Retry requested',
+                progress = 0,
+                outputs = NULL,
+                attempts = 0,
+                next_attempt_at = NULL,
+                started = NULL,
+                finished = NULL,
+                updated = NOW()
+            WHERE id = $1 AND owner = $2 AND parent_job_id IS NULL AND status = 'failed'
+            "#,
+        )
+        .bind(job_id)
+        .bind(username)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return match self.get_job(username, job_id).await? {
+                Some(job) => Err(AppError::Conflict(format!(
+                    "Only failed jobs can be retried (job is {})",
+                    job.status
+                ))),
+                None => Err(AppError::NotFound(format!("Job not found: {}", job_id))),
+            };
+        }
+
+        reset_workflow_steps(&mut tx, job_id).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
     pub async fn dismiss_job(&self, username: &str, job_id: Uuid) -> AppResult<()> {
         let result = sqlx::query(
             r#"
             UPDATE spatialvault.processes_jobs
-            SET status = 'dismissed', finished = NOW(), updated = NOW()
-            WHERE id = $1 AND owner = $2 AND status IN ('accepted', 'running')
+            SET status = 'dismissed', message = 'Dismissed by the user',
+                finished = NOW(), updated = NOW()
+            WHERE (id = $1 OR parent_job_id = $1)
+              AND owner = $2 AND status IN ('accepted', 'running')
             "#,
         )
         .bind(job_id)
@@ -141,4 +434,144 @@ impl ProcessService {
 
         Ok(())
     }
+
+    /// Whether a user was made a member of the process admin group in the
+    /// database, e.g. with `spatialvault admin grant-admin`
+    pub async fn is_admin_member(&self, username: &str, admin_group: &str) -> AppResult<bool> {
+        RoleManager::new(self.db.pool())
+            .is_member(admin_group, username)
+            .await
+    }
+
+    /// Store a new user-defined process
+    pub async fn deploy_process(
+        &self,
+        username: &str,
+        package: &ApplicationPackage,
+    ) -> AppResult<DeployedProcess> {
+        let description = &package.process_description;
+
+        let process: Option<DeployedProcess> = sqlx::query_as(
+            r#"
+            INSERT INTO spatialvault.processes
+            (id, title, description, version, owner, process_description, execution_unit)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (id) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(&description.id)
+        .bind(&description.title)
+        .bind(&description.description)
+        .bind(&description.version)
+        .bind(username)
+        .bind(serde_json::to_value(description)?)
+        .bind(serde_json::to_value(&package.execution_unit)?)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        process.ok_or_else(|| {
+            AppError::Conflict(format!("Process already exists: {}", description.id))
+        })
+    }
+
+    /// Replace the definition of an existing user-defined process
+    pub async fn replace_process(
+        &self,
+        process_id: &str,
+        package: &ApplicationPackage,
+    ) -> AppResult<DeployedProcess> {
+        let description = &package.process_description;
+
+        let process: Option<DeployedProcess> = sqlx::query_as(
+            r#"
+            UPDATE spatialvault.processes
+            SET
+                title = $2,
+                description = $3,
+                version = $4,
+                process_description = $5,
+                execution_unit = $6,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(process_id)
+        .bind(&description.title)
+        .bind(&description.description)
+        .bind(&description.version)
+        .bind(serde_json::to_value(description)?)
+        .bind(serde_json::to_value(&package.execution_unit)?)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        process.ok_or_else(|| AppError::NotFound(format!("Process not found: {}", process_id)))
+    }
+
+    /// Remove a user-defined process
+    pub async fn undeploy_process(&self, process_id: &str) -> AppResult<()> {
+        let result = sqlx::query("DELETE FROM spatialvault.processes WHERE id = $1")
+            .bind(process_id)
+            .execute(self.db.pool())
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "Process not found: {}",
+                process_id
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub async fn list_deployed_processes(&self) -> AppResult<Vec<DeployedProcess>> {
+        let processes: Vec<DeployedProcess> =
+            sqlx::query_as("SELECT * FROM spatialvault.processes ORDER BY id")
+                .fetch_all(self.db.pool())
+                .await?;
+
+        Ok(processes)
+    }
+
+    pub async fn get_deployed_process(
+        &self,
+        process_id: &str,
+    ) -> AppResult<Option<DeployedProcess>> {
+        let process: Option<DeployedProcess> =
+            sqlx::query_as("SELECT * FROM spatialvault.processes WHERE id = $1")
+                .bind(process_id)
+                .fetch_optional(self.db.pool())
+                .await?;
+
+        Ok(process)
+    }
+}
+
+/// Return the steps of a workflow job that didn't succeed to their initial
+/// waiting state; successful steps keep their outputs and aren't run again
+async fn reset_workflow_steps(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    parent_job_id: Uuid,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE spatialvault.processes_jobs
+        SET
+            status = 'accepted',
+            message = 'Waiting for previous steps',
+            progress = NULL,
+            outputs = NULL,
+            started = NULL,
+            finished = NULL,
+            updated = NOW()
+        WHERE parent_job_id = $1 AND status <> 'successful'
+        "#,
+    )
+    .bind(parent_job_id)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
 }
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::config::QuotaConfig;
+use crate::db::{Database, StorageUsage};
+use crate::error::{AppError, AppResult};
+
+/// Storage used by the collections matching `condition`, which binds `$1`
+///
+/// Asset sizes are the `file:size` recorded for each asset; assets without
+/// one don't count. Feature tables count with their indexes and TOAST data.
+fn usage_query(condition: &str) -> String {
+    format!(
+        r#"
+        WITH matched AS (
+            SELECT id, schema_name, table_name, collection_type
+            FROM spatialvault.collections
+            WHERE {condition}
+        )
+        SELECT
+            (SELECT COALESCE(SUM(a.file_size), 0)
+             FROM spatialvault.assets a
+             JOIN spatialvault.items i ON i.id = a.item_id
+             WHERE i.collection_id IN (SELECT id FROM matched))::bigint
+          + (SELECT COALESCE(SUM(a.file_size), 0)
+             FROM spatialvault.collection_assets a
+             WHERE a.collection_id IN (SELECT id FROM matched))::bigint AS asset_bytes,
+            (SELECT COALESCE(SUM(pg_total_relation_size(
+                        to_regclass(format('%I.%I', schema_name, table_name)))), 0)
+             FROM matched
+             WHERE collection_type IN ('vector', 'table'))::bigint AS table_bytes
+        "#
+    )
+}
+
+/// Whether usage has reached a quota
+fn over_quota(usage: &StorageUsage, quota: u64) -> bool {
+    usage.total_bytes().max(0) as u64 >= quota
+}
+
+/// Storage usage and quotas of user and group namespaces
+///
+/// Usage is computed when asked for rather than tracked, so it needs no
+/// bookkeeping on writes. Quota checks reuse a namespace's usage for
+/// [`QuotaConfig::cache_secs`], as computing it sums the namespace's assets and table sizes; a
+/// namespace may thus go over its quota by what is written meanwhile.
+pub struct QuotaService {
+    db: Arc<Database>,
+    config: QuotaConfig,
+    checked: RwLock<HashMap<String, (Instant, StorageUsage)>>,
+}
+
+impl QuotaService {
+    pub fn new(db: Arc<Database>, config: &QuotaConfig) -> Self {
+        Self {
+            db,
+            config: config.clone(),
+            checked: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Whether any namespace has a quota
+    pub fn is_enabled(&self) -> bool {
+        self.config.default_bytes.is_some() || !self.config.namespaces.is_empty()
+    }
+
+    /// Quota of a namespace in bytes, if it has one
+    pub fn quota(&self, namespace: &str) -> Option<u64> {
+        self.config.limit(namespace)
+    }
+
+    /// Storage used by the collections owned by a user or group
+    pub async fn namespace_usage(&self, namespace: &str) -> AppResult<StorageUsage> {
+        let usage = sqlx::query_as(&usage_query("owner = $1"))
+            .bind(namespace)
+            .fetch_one(self.db.pool())
+            .await?;
+        Ok(usage)
+    }
+
+    /// Storage used by a collection
+    pub async fn collection_usage(&self, collection_id: Uuid) -> AppResult<StorageUsage> {
+        let usage = sqlx::query_as(&usage_query("id = $1"))
+            .bind(collection_id)
+            .fetch_one(self.db.pool())
+            .await?;
+        Ok(usage)
+    }
+
+    /// Usage of a namespace for a quota check, computed at most once per
+    /// [`QuotaConfig::cache_secs`]
+    async fn checked_usage(&self, namespace: &str) -> AppResult<StorageUsage> {
+        let ttl = Duration::from_secs(self.config.cache_secs);
+        let cached = self
+            .checked
+            .read()
+            .ok()
+            .and_then(|checked| checked.get(namespace).copied())
+            .filter(|(at, _)| at.elapsed() < ttl);
+        if let Some((_, usage)) = cached {
+            return Ok(usage);
+        }
+
+        let usage = self.namespace_usage(namespace).await?;
+        if let Ok(mut checked) = self.checked.write() {
+            checked.retain(|_, (at, _)| at.elapsed() < ttl);
+            checked.insert(namespace.to_string(), (Instant::now(), usage));
+        }
+        Ok(usage)
+    }
+
+    /// Fail with 507 when a namespace has reached its quota
+    pub async fn check(&self, namespace: &str) -> AppResult<()> {
+        let Some(quota) = self.quota(namespace) else {
+            return Ok(());
+        };
+        let usage = self.checked_usage(namespace).await?;
+        if over_quota(&usage, quota) {
+            return Err(AppError::InsufficientStorage(format!(
+                "Namespace {} uses {} of its {} bytes of storage; delete data to write more",
+                namespace,
+                usage.total_bytes(),
+                quota
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_over_quota() {
+        let usage = StorageUsage {
+            asset_bytes: 600,
+            table_bytes: 400,
+        };
+        assert!(over_quota(&usage, 1000));
+        assert!(over_quota(&usage, 999));
+        assert!(!over_quota(&usage, 1001));
+        assert!(!over_quota(&StorageUsage::default(), 1));
+    }
+}
@@ -0,0 +1,540 @@
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::api::read_only;
+use crate::auth::quote_ident;
+use crate::config::{RemoteInstance, ReplicationConfig};
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+
+/// Property of the remote features holding the id of the feature they were
+/// pushed from, which matches them on the next push
+pub const SOURCE_ID_PROPERTY: &str = "source_id";
+
+/// Most replications pushed in one round
+const REPLICATION_BATCH_SIZE: i64 = 10;
+
+/// Features pushed per request
+const PUSH_BATCH_SIZE: i64 = 1000;
+
+/// Deleted features looked up on the remote per request
+const DELETE_BATCH_SIZE: usize = 100;
+
+/// How long a server may push a replication before others may take it over
+const CLAIM_SECS: f64 = 15.0 * 60.0;
+
+/// A replication claimed for a push
+#[derive(Debug, sqlx::FromRow)]
+struct ClaimedReplication {
+    id: Uuid,
+    collection_id: Uuid,
+    canonical_name: String,
+    remote: String,
+    remote_collection: String,
+    synced_until: Option<DateTime<Utc>>,
+    version: i64,
+    schema_name: String,
+    table_name: String,
+    collection_type: String,
+}
+
+/// A replication whose collection was deleted, claimed for removing the
+/// features pushed from it
+#[derive(Debug, sqlx::FromRow)]
+struct OrphanedReplication {
+    id: Uuid,
+    remote: String,
+    remote_collection: String,
+}
+
+/// A changed feature, in the order features are pushed
+#[derive(Debug, sqlx::FromRow)]
+struct ChangedFeature {
+    id: Uuid,
+    changed_at: DateTime<Utc>,
+    geometry: Option<serde_json::Value>,
+    properties: Option<serde_json::Value>,
+}
+
+/// Remote feature as pushed: the local properties plus the source id
+fn remote_feature(feature: ChangedFeature) -> serde_json::Value {
+    let mut properties = match feature.properties {
+        Some(serde_json::Value::Object(properties)) => properties,
+        _ => serde_json::Map::new(),
+    };
+    properties.insert(
+        SOURCE_ID_PROPERTY.to_string(),
+        serde_json::Value::String(feature.id.to_string()),
+    );
+    serde_json::json!({
+        "type": "Feature",
+        "geometry": feature.geometry,
+        "properties": properties,
+    })
+}
+
+/// CQL2 filter matching the remote features pushed from `ids`
+fn source_filter(ids: &[Uuid]) -> String {
+    let ids = ids
+        .iter()
+        .map(|id| format!("'{}'", id))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{} IN ({})", SOURCE_ID_PROPERTY, ids)
+}
+
+/// Pushes changes of published collections to other SpatialVault instances
+///
+/// Features changed since the last push are sent to the remote's bulk ingest,
+/// matched by [`SOURCE_ID_PROPERTY`], and deleted features are deleted there.
+/// Deletions are recorded by a trigger of the feature tables, so features
+/// removed by a snapshot restore are deleted as well. When a collection is
+/// deleted, the features pushed from it are deleted from the remote before
+/// the replication is dropped.
+/// Replications are claimed for a while before they are pushed, so every
+/// server may run the loop.
+pub struct ReplicationService {
+    db: Arc<Database>,
+    config: ReplicationConfig,
+    client: reqwest::Client,
+}
+
+impl ReplicationService {
+    /// Pusher to the configured remotes, if any
+    pub fn new(db: Arc<Database>, config: &ReplicationConfig) -> Option<Self> {
+        if config.remotes.is_empty() {
+            return None;
+        }
+        Some(Self {
+            db,
+            config: config.clone(),
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// Push every `interval`, for as long as the server runs
+    pub async fn run(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if read_only::paused(&self.db).await {
+                continue;
+            }
+            if let Err(e) = self.push_changed().await {
+                tracing::warn!("Failed to replicate collections: {}", e);
+            }
+        }
+    }
+
+    /// Push the changes of replications no other server is pushing
+    pub async fn push_changed(&self) -> AppResult<()> {
+        let claimed: Vec<ClaimedReplication> = sqlx::query_as(
+            r#"
+            WITH due AS (
+                SELECT id FROM spatialvault.collection_replications
+                WHERE claimed_until IS NULL OR claimed_until < NOW()
+                ORDER BY synced_at NULLS FIRST
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE spatialvault.collection_replications r
+            SET claimed_until = NOW() + make_interval(secs => $2)
+            FROM due, spatialvault.collections c
+            WHERE r.id = due.id AND c.id = r.collection_id
+            RETURNING r.id, r.collection_id, c.canonical_name, r.remote, r.remote_collection,
+                      r.synced_until, c.version, c.schema_name, c.table_name, c.collection_type
+            "#,
+        )
+        .bind(REPLICATION_BATCH_SIZE)
+        .bind(CLAIM_SECS)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        for replication in &claimed {
+            let result = self.push(replication).await;
+            if let Err(e) = &result {
+                tracing::warn!(
+                    collection = %replication.canonical_name,
+                    remote = %replication.remote,
+                    "Failed to replicate collection: {}",
+                    e
+                );
+            }
+            self.release(replication, result).await?;
+        }
+
+        self.remove_orphaned().await?;
+        self.prune_deletions().await
+    }
+
+    /// Delete the features pushed from deleted collections from the remotes,
+    /// then drop their replications
+    async fn remove_orphaned(&self) -> AppResult<()> {
+        let claimed: Vec<OrphanedReplication> = sqlx::query_as(
+            r#"
+            WITH due AS (
+                SELECT id FROM spatialvault.collection_replications
+                WHERE collection_id IS NULL
+                  AND (claimed_until IS NULL OR claimed_until < NOW())
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE spatialvault.collection_replications r
+            SET claimed_until = NOW() + make_interval(secs => $2)
+            FROM due
+            WHERE r.id = due.id
+            RETURNING r.id, r.remote, r.remote_collection
+            "#,
+        )
+        .bind(REPLICATION_BATCH_SIZE)
+        .bind(CLAIM_SECS)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        for replication in &claimed {
+            match self.delete_pushed(replication).await {
+                Ok(()) => {
+                    sqlx::query("DELETE FROM spatialvault.collection_replications WHERE id = $1")
+                        .bind(replication.id)
+                        .execute(self.db.pool())
+                        .await?;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        remote = %replication.remote,
+                        collection = %replication.remote_collection,
+                        "Failed to remove replicated features: {}",
+                        e
+                    );
+                    sqlx::query(
+                        r#"
+                        UPDATE spatialvault.collection_replications
+                        SET claimed_until = NULL, last_error = $2
+                        WHERE id = $1
+                        "#,
+                    )
+                    .bind(replication.id)
+                    .bind(e.to_string())
+                    .execute(self.db.pool())
+                    .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete every feature pushed to the remote collection of a replication
+    async fn delete_pushed(&self, replication: &OrphanedReplication) -> AppResult<()> {
+        let remote = self
+            .config
+            .remotes
+            .get(&replication.remote)
+            .ok_or_else(|| AppError::Config(format!("Unknown remote: {}", replication.remote)))?;
+        let items_url = format!(
+            "{}/collections/{}/items",
+            remote.url.trim_end_matches('/'),
+            replication.remote_collection
+        );
+        let filter = format!("{} IS NOT NULL", SOURCE_ID_PROPERTY);
+        while self
+            .delete_remote(remote, &items_url, &filter, DELETE_BATCH_SIZE)
+            .await?
+            > 0
+        {}
+        Ok(())
+    }
+
+    /// Push the changes of one replication; returns the time changes are
+    /// pushed up to
+    async fn push(&self, replication: &ClaimedReplication) -> AppResult<DateTime<Utc>> {
+        let remote = self
+            .config
+            .remotes
+            .get(&replication.remote)
+            .ok_or_else(|| AppError::Config(format!("Unknown remote: {}", replication.remote)))?;
+
+        // Writes of transactions still open may become visible with an
+        // earlier updated_at, so changes are only pushed up to their start
+        let until: DateTime<Utc> = sqlx::query_scalar(
+            r#"
+            SELECT LEAST(NOW(), MIN(xact_start)) FROM pg_stat_activity
+            WHERE datname = current_database() AND backend_type = 'client backend'
+              AND pid <> pg_backend_pid()
+            "#,
+        )
+        .fetch_one(self.db.pool())
+        .await?;
+
+        // Before the first push the remote has none of the features
+        if let Some(since) = replication.synced_until {
+            self.push_deletions(remote, replication, since, until)
+                .await?;
+        }
+        self.push_features(remote, replication, until).await?;
+
+        Ok(until)
+    }
+
+    async fn push_deletions(
+        &self,
+        remote: &RemoteInstance,
+        replication: &ClaimedReplication,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> AppResult<()> {
+        let deleted: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT feature_id FROM spatialvault.feature_deletions
+            WHERE collection_id = $1 AND deleted_at > $2 AND deleted_at <= $3
+            "#,
+        )
+        .bind(replication.collection_id)
+        .bind(since)
+        .bind(until)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let items_url = format!(
+            "{}/collections/{}/items",
+            remote.url.trim_end_matches('/'),
+            replication.remote_collection
+        );
+        for ids in deleted.chunks(DELETE_BATCH_SIZE) {
+            self.delete_remote(remote, &items_url, &source_filter(ids), ids.len())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Delete up to `limit` remote features matching a CQL2 filter; returns
+    /// how many were found
+    async fn delete_remote(
+        &self,
+        remote: &RemoteInstance,
+        items_url: &str,
+        filter: &str,
+        limit: usize,
+    ) -> AppResult<usize> {
+        let limit = limit.to_string();
+        let response = self
+            .client
+            .get(items_url)
+            .bearer_auth(&remote.api_key)
+            .query(&[("filter", filter), ("limit", limit.as_str())])
+            .send()
+            .await
+            .map_err(|e| AppError::Upstream(format!("Remote request failed: {}", e)))?;
+        let matches: serde_json::Value = check_response(response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| AppError::Upstream(format!("Invalid response from remote: {}", e)))?;
+
+        let remote_ids: Vec<&str> = matches["features"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|feature| feature["id"].as_str())
+            .collect();
+        for remote_id in &remote_ids {
+            let response = self
+                .client
+                .delete(format!("{}/{}", items_url, remote_id))
+                .bearer_auth(&remote.api_key)
+                .send()
+                .await
+                .map_err(|e| AppError::Upstream(format!("Remote request failed: {}", e)))?;
+            // Deleted on the remote already
+            if response.status() != reqwest::StatusCode::NOT_FOUND {
+                check_response(response).await?;
+            }
+        }
+        Ok(remote_ids.len())
+    }
+
+    async fn push_features(
+        &self,
+        remote: &RemoteInstance,
+        replication: &ClaimedReplication,
+        until: DateTime<Utc>,
+    ) -> AppResult<()> {
+        let geometry = if replication.collection_type == "vector" {
+            "ST_AsGeoJSON(geometry)::jsonb"
+        } else {
+            "NULL::jsonb"
+        };
+        // Features are paged by (changed_at, id), as a bulk write gives many
+        // features the same updated_at
+        let sql = format!(
+            r#"
+            SELECT id, changed_at, geometry, properties FROM (
+                SELECT id, COALESCE(updated_at, 'epoch') AS changed_at,
+                       {} AS geometry, properties
+                FROM {}.{}
+                WHERE ($1::timestamptz IS NULL OR updated_at > $1)
+                  AND (updated_at <= $2 OR updated_at IS NULL)
+            ) changed
+            WHERE (changed_at, id) > ($3, $4)
+            ORDER BY changed_at, id
+            LIMIT $5
+            "#,
+            geometry,
+            quote_ident(&replication.schema_name),
+            quote_ident(&replication.table_name)
+        );
+
+        let url = format!(
+            "{}/collections/{}/items",
+            remote.url.trim_end_matches('/'),
+            replication.remote_collection
+        );
+        let mut after = (DateTime::<Utc>::UNIX_EPOCH, Uuid::nil());
+        loop {
+            let features: Vec<ChangedFeature> = sqlx::query_as(&sql)
+                .bind(replication.synced_until)
+                .bind(until)
+                .bind(after.0)
+                .bind(after.1)
+                .bind(PUSH_BATCH_SIZE)
+                .fetch_all(self.db.pool())
+                .await?;
+            let Some(last) = features.last() else {
+                return Ok(());
+            };
+            after = (last.changed_at, last.id);
+            let count = features.len();
+
+            let body = serde_json::json!({
+                "type": "FeatureCollection",
+                "features": features.into_iter().map(remote_feature).collect::<Vec<_>>(),
+            });
+            let response = self
+                .client
+                .post(&url)
+                .bearer_auth(&remote.api_key)
+                .query(&[
+                    ("on-conflict", "update"),
+                    ("on-conflict-property", SOURCE_ID_PROPERTY),
+                ])
+                .header(reqwest::header::CONTENT_TYPE, "application/geo+json")
+                .body(body.to_string())
+                .send()
+                .await
+                .map_err(|e| AppError::Upstream(format!("Remote request failed: {}", e)))?;
+            check_response(response).await?;
+
+            if (count as i64) < PUSH_BATCH_SIZE {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Record the outcome of a push and let the replication be claimed again
+    async fn release(
+        &self,
+        replication: &ClaimedReplication,
+        result: AppResult<DateTime<Utc>>,
+    ) -> AppResult<()> {
+        match result {
+            Ok(until) => {
+                sqlx::query(
+                    r#"
+                    UPDATE spatialvault.collection_replications
+                    SET synced_until = $2, synced_version = $3, synced_at = NOW(),
+                        claimed_until = NULL, last_error = NULL
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(replication.id)
+                .bind(until)
+                .bind(replication.version)
+                .execute(self.db.pool())
+                .await?;
+            }
+            Err(e) => {
+                sqlx::query(
+                    r#"
+                    UPDATE spatialvault.collection_replications
+                    SET claimed_until = NULL, last_error = $2
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(replication.id)
+                .bind(e.to_string())
+                .execute(self.db.pool())
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget deletions that every replication of their collection has pushed
+    async fn prune_deletions(&self) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            DELETE FROM spatialvault.feature_deletions d
+            WHERE NOT EXISTS (
+                SELECT 1 FROM spatialvault.collection_replications r
+                WHERE r.collection_id = d.collection_id
+                  AND (r.synced_until IS NULL OR r.synced_until < d.deleted_at)
+            )
+            "#,
+        )
+        .execute(self.db.pool())
+        .await?;
+        Ok(())
+    }
+}
+
+/// Fail unless the remote accepted a request
+async fn check_response(response: reqwest::Response) -> AppResult<reqwest::Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    Err(AppError::Upstream(format!(
+        "Remote responded with {}: {}",
+        status, body
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_feature() {
+        let id = Uuid::new_v4();
+        let feature = remote_feature(ChangedFeature {
+            id,
+            changed_at: Utc::now(),
+            geometry: Some(serde_json::json!({"type": "Point", "coordinates": [1.0, 2.0]})),
+            properties: Some(serde_json::json!({"name": "Town hall"})),
+        });
+        assert_eq!(feature["properties"]["name"], "Town hall");
+        assert_eq!(feature["properties"][SOURCE_ID_PROPERTY], id.to_string());
+        assert_eq!(feature["geometry"]["type"], "Point");
+
+        let feature = remote_feature(ChangedFeature {
+            id,
+            changed_at: Utc::now(),
+            geometry: None,
+            properties: None,
+        });
+        assert!(feature["geometry"].is_null());
+        assert_eq!(feature["properties"][SOURCE_ID_PROPERTY], id.to_string());
+    }
+
+    #[test]
+    fn test_source_filter() {
+        let a = Uuid::nil();
+        assert_eq!(
+            source_filter(&[a]),
+            "source_id IN ('00000000-0000-0000-0000-000000000000')"
+        );
+    }
+}
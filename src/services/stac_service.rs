@@ -3,9 +3,20 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::api::common::{Link, media_type, rel};
+use crate::api::features::query::parse_ids;
 use crate::api::stac::item::{StacItem, StacItemProperties, StacSearchParams};
-use crate::db::Database;
+use crate::db::{Database, QueryClass};
 use crate::error::AppResult;
+use crate::services::PropertyType;
+
+/// Items sampled for the properties common to the searchable items
+const QUERYABLES_SAMPLE: i64 = 10_000;
+
+/// Share of the sampled items a property must be present in to be queryable
+const QUERYABLES_MIN_SHARE: f64 = 0.1;
+
+/// Most custom properties listed as queryables
+const MAX_CUSTOM_QUERYABLES: usize = 100;
 
 pub struct StacSearchResult {
     pub items: Vec<StacItem>,
@@ -27,24 +38,33 @@ pub struct StacAsset {
     pub roles: Option<Vec<String>>,
     #[serde(rename = "file:size", skip_serializing_if = "Option::is_none")]
     pub file_size: Option<i64>,
+    /// Outcome of checking the asset against the object it references
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation: Option<serde_json::Value>,
+    /// Further STAC asset fields registered with the asset
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub extra_fields: Option<serde_json::Map<String, serde_json::Value>>,
 }
 
 pub struct StacService {
     db: Arc<Database>,
-    base_url: String,
 }
 
 impl StacService {
-    pub fn new(db: Arc<Database>, base_url: String) -> Self {
-        Self { db, base_url }
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
     }
 
+    /// Search items; their links start with `base_url`
     pub async fn search(
         &self,
         username: &str,
         params: &StacSearchParams,
+        base_url: &str,
     ) -> AppResult<StacSearchResult> {
-        let mut where_clauses = vec!["TRUE".to_string()];
+        // Filter by item IDs (bound as $1, NULL when not filtering)
+        let ids = params.ids.as_deref().map(parse_ids).transpose()?;
+        let mut where_clauses = vec!["($1::uuid[] IS NULL OR i.id = ANY($1))".to_string()];
 
         // Filter by collections
         if let Some(ref collections) = params.collections {
@@ -56,16 +76,6 @@ impl StacService {
             where_clauses.push(format!("c.canonical_name IN ({})", quoted.join(", ")));
         }
 
-        // Filter by item IDs
-        if let Some(ref ids) = params.ids {
-            let id_list: Vec<&str> = ids.split(',').map(|s| s.trim()).collect();
-            let quoted: Vec<String> = id_list
-                .iter()
-                .map(|s| format!("'{}'", s.replace('\'', "''")))
-                .collect();
-            where_clauses.push(format!("i.id::text IN ({})", quoted.join(", ")));
-        }
-
         // Filter by bbox
         if let Some(ref bbox) = params.bbox {
             let parts: Vec<f64> = bbox
@@ -110,7 +120,10 @@ impl StacService {
             where_clause
         );
 
-        let count: (i64,) = sqlx::query_as(&count_sql).fetch_one(self.db.pool()).await?;
+        let count: (i64,) = sqlx::query_as(&count_sql)
+            .bind(&ids)
+            .fetch_one(self.db.pool())
+            .await?;
 
         // Data query - get items
         let sql = format!(
@@ -144,7 +157,10 @@ impl StacService {
             f64,
             Option<chrono::DateTime<chrono::Utc>>,
             Option<serde_json::Value>,
-        )> = sqlx::query_as(&sql).fetch_all(self.db.pool()).await?;
+        )> = sqlx::query_as(&sql)
+            .bind(&ids)
+            .fetch_all(self.db.pool())
+            .await?;
 
         // Collect item IDs for asset lookup
         let item_ids: Vec<Uuid> = rows.iter().map(|(id, ..)| *id).collect();
@@ -176,19 +192,16 @@ impl StacService {
                         },
                         links: vec![
                             Link::new(
-                                format!(
-                                    "{}/collections/{}/items/{}",
-                                    self.base_url, collection, id_str
-                                ),
+                                format!("{}/collections/{}/items/{}", base_url, collection, id_str),
                                 rel::SELF,
                             )
                             .with_type(media_type::GEOJSON),
                             Link::new(
-                                format!("{}/collections/{}", self.base_url, collection),
+                                format!("{}/collections/{}", base_url, collection),
                                 rel::COLLECTION,
                             )
                             .with_type(media_type::JSON),
-                            Link::new(format!("{}/stac", self.base_url), rel::ROOT)
+                            Link::new(format!("{}/stac", base_url), rel::ROOT)
                                 .with_type(media_type::JSON),
                         ],
                         assets: item_assets,
@@ -205,6 +218,58 @@ impl StacService {
         })
     }
 
+    /// Item properties frequently present in the collections the user can
+    /// read, most common first
+    ///
+    /// Looks at a sample of the items on the background pool, so their
+    /// number doesn't make listing queryables slow.
+    #[tracing::instrument(skip(self))]
+    pub async fn common_properties(
+        &self,
+        username: &str,
+    ) -> AppResult<Vec<(String, PropertyType)>> {
+        let mut tx = self.db.begin_with_budget(QueryClass::Background).await?;
+        let rows: Vec<(String, Vec<String>, bool, i64, i64)> = sqlx::query_as(
+            r#"
+            WITH sample AS (
+                SELECT i.properties
+                FROM spatialvault.items i
+                JOIN spatialvault.collections c ON i.collection_id = c.id
+                WHERE jsonb_typeof(i.properties) = 'object'
+                  AND (c.owner = $1
+                       OR pg_catalog.has_table_privilege($1, c.schema_name || '.' || c.table_name, 'SELECT'))
+                LIMIT $2
+            )
+            SELECT
+                key,
+                array_agg(DISTINCT jsonb_typeof(value)),
+                bool_and(jsonb_typeof(value) <> 'number' OR value::text ~ '^-?[0-9]+$'),
+                COUNT(*),
+                (SELECT COUNT(*) FROM sample)
+            FROM sample, jsonb_each(sample.properties)
+            WHERE jsonb_typeof(value) <> 'null'
+            GROUP BY key
+            ORDER BY COUNT(*) DESC, key
+            "#,
+        )
+        .bind(username)
+        .bind(QUERYABLES_SAMPLE)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(rows
+            .into_iter()
+            .filter(|(_, _, _, count, sampled)| {
+                *count as f64 >= *sampled as f64 * QUERYABLES_MIN_SHARE
+            })
+            .take(MAX_CUSTOM_QUERYABLES)
+            .map(|(key, types, integral, _, _)| {
+                (key, PropertyType::from_json_types(&types, integral))
+            })
+            .collect())
+    }
+
     /// Get assets for a list of item IDs
     async fn get_assets_for_items(
         &self,
@@ -223,7 +288,8 @@ impl StacService {
 
         let sql = format!(
             r#"
-            SELECT item_id, key, href, type, title, description, roles, file_size, extra_fields
+            SELECT item_id, key, href, type, title, description, roles, file_size, extra_fields,
+                   validation
             FROM spatialvault.assets
             WHERE item_id IN ({})
             "#,
@@ -242,6 +308,7 @@ impl StacService {
                 Option<Vec<String>>,
                 Option<i64>,
                 Option<serde_json::Value>,
+                Option<serde_json::Value>,
             ),
         >(&sql);
 
@@ -254,7 +321,19 @@ impl StacService {
         // Group assets by item_id
         let mut assets_map: HashMap<Uuid, HashMap<String, StacAsset>> = HashMap::new();
 
-        for (item_id, key, href, media_type, title, description, roles, file_size, _extra) in rows {
+        for (
+            item_id,
+            key,
+            href,
+            media_type,
+            title,
+            description,
+            roles,
+            file_size,
+            extra,
+            validation,
+        ) in rows
+        {
             let asset = StacAsset {
                 href,
                 media_type,
@@ -262,6 +341,11 @@ impl StacService {
                 description,
                 roles,
                 file_size,
+                validation,
+                extra_fields: match extra {
+                    Some(serde_json::Value::Object(map)) => Some(map),
+                    _ => None,
+                },
             };
 
             assets_map.entry(item_id).or_default().insert(key, asset);
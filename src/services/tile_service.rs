@@ -1,17 +1,95 @@
-use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
 
-use crate::api::tiles::raster::{RasterFormat, RasterTileParams, render_raster_tile};
-use crate::api::tiles::vector::mvt_sql;
-use crate::db::{Collection, Database};
+use crate::api::common::Bbox;
+use crate::api::tiles::properties::{parse_property_rules, properties_at_zoom};
+use crate::api::tiles::raster::{
+    RasterFormat, RasterRendering, RasterTileParams, render_raster_tile,
+};
+use crate::api::tiles::vector::{mvt_sql, zoom_range};
+use crate::db::{Collection, Database, QueryClass};
 use crate::error::{AppError, AppResult};
+use crate::services::CollectionService;
+
+/// Bounding box of a collection without data
+const WORLD_BBOX: [f64; 4] = [-180.0, -90.0, 180.0, 90.0];
+
+/// Rendered tile sizes kept before the record starts over
+const MAX_TILE_SIZES: usize = 100_000;
+
+/// Data bounding boxes by collection, with the `data_updated_at` they were
+/// computed at
+type BboxCache = RwLock<HashMap<Uuid, (Option<DateTime<Utc>>, [f64; 4])>>;
 
 pub struct TileService {
     db: Arc<Database>,
+    bboxes: BboxCache,
+    /// Sizes of served tiles by representation, answering HEAD requests
+    /// without rendering
+    tile_sizes: RwLock<HashMap<String, usize>>,
 }
 
 impl TileService {
     pub fn new(db: Arc<Database>) -> Self {
-        Self { db }
+        Self {
+            db,
+            bboxes: RwLock::new(HashMap::new()),
+            tile_sizes: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Size of a tile representation when it was served before
+    ///
+    /// `key` names the representation, including the validator of the data
+    /// it was rendered from, so sizes of outdated tiles are never returned.
+    pub fn tile_size(&self, key: &str) -> Option<usize> {
+        self.tile_sizes
+            .read()
+            .ok()
+            .and_then(|sizes| sizes.get(key).copied())
+    }
+
+    /// Record the size of a served tile representation
+    pub fn record_tile_size(&self, key: String, size: usize) {
+        if let Ok(mut sizes) = self.tile_sizes.write() {
+            // Sizes of outdated tiles are never looked up again
+            if sizes.len() >= MAX_TILE_SIZES {
+                sizes.clear();
+            }
+            sizes.insert(key, size);
+        }
+    }
+
+    /// CRS84 bounding box of a collection's data, from which its tile matrix
+    /// set limits are derived
+    ///
+    /// A collection without data covers the whole world. The box is computed
+    /// once per change of the data, as every tile request checks it.
+    pub async fn data_bbox(&self, collection: &Collection) -> AppResult<[f64; 4]> {
+        let cached = self
+            .bboxes
+            .read()
+            .ok()
+            .and_then(|bboxes| bboxes.get(&collection.id).copied())
+            .filter(|(at, _)| *at == collection.data_updated_at);
+        if let Some((_, bbox)) = cached {
+            return Ok(bbox);
+        }
+
+        let bbox = CollectionService::new(self.db.clone())
+            .compute_spatial_extent(collection)
+            .await?
+            .and_then(|extent| extent.bbox.into_iter().next())
+            .map_or(WORLD_BBOX, |bbox| match bbox {
+                Bbox::TwoD(bbox) => bbox,
+                Bbox::ThreeD([minx, miny, _, maxx, maxy, _]) => [minx, miny, maxx, maxy],
+            });
+        if let Ok(mut bboxes) = self.bboxes.write() {
+            bboxes.insert(collection.id, (collection.data_updated_at, bbox));
+        }
+        Ok(bbox)
     }
 
     pub async fn get_collection(
@@ -35,6 +113,7 @@ impl TileService {
         z: u32,
         x: u32,
         y: u32,
+        geom: Option<&str>,
     ) -> AppResult<Vec<u8>> {
         let collection = self
             .get_collection(username, collection_id)
@@ -43,6 +122,101 @@ impl TileService {
                 AppError::NotFound(format!("Collection not found: {}", collection_id))
             })?;
 
+        self.render_vector_layer(&collection, z, x, y, geom).await
+    }
+
+    /// Resolve the collections of a multi-collection tile, following
+    /// aliases of renamed collections
+    ///
+    /// Every collection must be readable by the user, a vector collection and
+    /// have its own layer name.
+    pub async fn resolve_tile_layers(
+        &self,
+        username: &str,
+        collection_ids: &[String],
+    ) -> AppResult<Vec<Collection>> {
+        let mut collections = Vec::with_capacity(collection_ids.len());
+        let mut layer_names = HashSet::new();
+        for collection_id in collection_ids {
+            let mut collection = self.get_collection(username, collection_id).await?;
+            if collection.is_none() {
+                let alias: Option<(String,)> = sqlx::query_as(
+                    "SELECT new_name FROM spatialvault.collection_aliases WHERE old_name = $1",
+                )
+                .bind(collection_id)
+                .fetch_optional(self.db.pool())
+                .await?;
+                if let Some((new_name,)) = alias {
+                    collection = self.get_collection(username, &new_name).await?;
+                }
+            }
+            let readable = match &collection {
+                Some(collection) => {
+                    CollectionService::new(self.db.clone())
+                        .can_read(username, collection)
+                        .await?
+                }
+                None => false,
+            };
+            let Some(collection) = collection.filter(|_| readable) else {
+                return Err(AppError::NotFound(format!(
+                    "Collection not found: {}",
+                    collection_id
+                )));
+            };
+
+            if collection.collection_type != "vector" {
+                return Err(AppError::BadRequest(format!(
+                    "Vector tiles only available for vector collections: {}",
+                    collection_id
+                )));
+            }
+            if !layer_names.insert(collection.tile_layer_name().to_string()) {
+                return Err(AppError::BadRequest(format!(
+                    "Duplicate tile layer name: {}",
+                    collection.tile_layer_name()
+                )));
+            }
+            collections.push(collection);
+        }
+
+        Ok(collections)
+    }
+
+    /// Get a vector tile with one layer per collection
+    ///
+    /// Collections whose zoom range excludes `z` contribute no layer.
+    #[tracing::instrument(skip(self, collections))]
+    pub async fn get_vector_tile_layers(
+        &self,
+        collections: &[Collection],
+        z: u32,
+        x: u32,
+        y: u32,
+    ) -> AppResult<Vec<u8>> {
+        // Tiles are protobuf messages of repeated layers, so concatenating
+        // single-layer tiles yields a valid multi-layer tile
+        let mut tile = Vec::new();
+        for collection in collections {
+            if zoom_range(collection.min_zoom, collection.max_zoom).contains(&z) {
+                tile.extend(self.render_vector_layer(collection, z, x, y, None).await?);
+            }
+        }
+
+        Ok(tile)
+    }
+
+    /// Render a collection as a single-layer vector tile of the geometry
+    /// column selected by `geom`
+    #[tracing::instrument(skip(self, collection), fields(collection = %collection.canonical_name))]
+    async fn render_vector_layer(
+        &self,
+        collection: &Collection,
+        z: u32,
+        x: u32,
+        y: u32,
+        geom: Option<&str>,
+    ) -> AppResult<Vec<u8>> {
         if collection.collection_type != "vector" {
             return Err(AppError::BadRequest(
                 "Vector tiles only available for vector collections".to_string(),
@@ -50,25 +224,33 @@ impl TileService {
         }
 
         // Get storage SRID
-        let storage_srid = self.get_storage_srid(&collection).await?;
+        let storage_srid = self.get_storage_srid(collection).await?;
 
         // Build MVT query
         let sql = mvt_sql(
             &collection.schema_name,
             &collection.table_name,
-            "geometry",
+            &collection.geometry_column(geom)?,
             z,
             x,
             y,
             storage_srid,
         );
 
-        let result: Option<(Vec<u8>,)> =
-            sqlx::query_as(&sql).fetch_optional(self.db.pool()).await?;
+        let rules = parse_property_rules(collection.tile_properties.as_ref())?;
+        let mut tx = self.db.begin_with_budget(QueryClass::Tiles).await?;
+        let result: Option<(Vec<u8>,)> = sqlx::query_as(&sql)
+            .bind(collection.tile_layer_name())
+            .bind(properties_at_zoom(&rules, z))
+            .fetch_optional(&mut *tx)
+            .await?;
+        tx.commit().await?;
 
         Ok(result.map(|(data,)| data).unwrap_or_default())
     }
 
+    #[tracing::instrument(skip(self, _username, rendering))]
+    #[allow(clippy::too_many_arguments)]
     pub async fn get_raster_tile(
         &self,
         _username: &str,
@@ -77,6 +259,7 @@ impl TileService {
         x: u32,
         y: u32,
         format: RasterFormat,
+        rendering: RasterRendering,
     ) -> AppResult<Vec<u8>> {
         let collection = self
             .get_collection("", collection_id)
@@ -109,6 +292,7 @@ impl TileService {
             y,
             format,
             tile_size: 256,
+            rendering,
         };
 
         // Run GDAL rendering in blocking task (GDAL is not async)
@@ -158,7 +342,7 @@ impl TileService {
     async fn get_storage_srid(&self, collection: &Collection) -> AppResult<i32> {
         let sql = r#"
             SELECT srid FROM geometry_columns
-            WHERE f_table_schema = $1 AND f_table_name = $2
+            WHERE f_table_schema = $1 AND f_table_name = $2 AND f_geometry_column = 'geometry'
         "#;
 
         let result: Option<(i32,)> = sqlx::query_as(sql)
@@ -0,0 +1,382 @@
+use bytes::Bytes;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::{Database, Upload, UploadPart};
+use crate::error::{AppError, AppResult};
+use crate::storage::S3Storage;
+
+/// Scheme of process input hrefs that refer to a completed upload
+pub const UPLOAD_SCHEME: &str = "upload://";
+
+/// Highest part number S3 accepts
+pub const MAX_PART_NUMBER: i32 = 10_000;
+
+/// Smallest size S3 accepts for any part but the last (5 MiB)
+pub const MIN_PART_BYTES: i64 = 5 * 1024 * 1024;
+
+pub struct UploadService {
+    db: Arc<Database>,
+    storage: Arc<S3Storage>,
+}
+
+/// Check that parts 1..=n are all present and large enough to be assembled
+pub fn check_parts_complete(parts: &[UploadPart]) -> AppResult<()> {
+    if parts.is_empty() {
+        return Err(AppError::BadRequest("Upload has no parts".to_string()));
+    }
+    for (index, part) in parts.iter().enumerate() {
+        let expected = index as i32 + 1;
+        if part.part_number != expected {
+            return Err(AppError::BadRequest(format!(
+                "Part {} is missing",
+                expected
+            )));
+        }
+        if index + 1 < parts.len() && part.size < MIN_PART_BYTES {
+            return Err(AppError::BadRequest(format!(
+                "Part {} is smaller than {} bytes; only the last part may be smaller",
+                part.part_number, MIN_PART_BYTES
+            )));
+        }
+    }
+    Ok(())
+}
+
+impl UploadService {
+    pub fn new(db: Arc<Database>, storage: Arc<S3Storage>) -> Self {
+        Self { db, storage }
+    }
+
+    /// Start an upload and its S3 multipart upload
+    pub async fn create_upload(&self, owner: &str, media_type: Option<&str>) -> AppResult<Upload> {
+        let id = Uuid::new_v4();
+        let object_key = format!("{}/uploads/{}", owner, id);
+        let multipart_id = self.storage.create_multipart(&object_key).await?;
+
+        let upload = sqlx::query_as(
+            r#"
+            INSERT INTO spatialvault.uploads (id, owner, object_key, multipart_id, media_type)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(owner)
+        .bind(&object_key)
+        .bind(&multipart_id)
+        .bind(media_type)
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok(upload)
+    }
+
+    /// Get an upload of the user; other users' uploads are not found
+    pub async fn get_upload(&self, owner: &str, id: Uuid) -> AppResult<Upload> {
+        sqlx::query_as("SELECT * FROM spatialvault.uploads WHERE id = $1 AND owner = $2")
+            .bind(id)
+            .bind(owner)
+            .fetch_optional(self.db.pool())
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Upload not found: {}", id)))
+    }
+
+    /// Parts received for an upload, by part number
+    pub async fn list_parts(&self, id: Uuid) -> AppResult<Vec<UploadPart>> {
+        let parts = sqlx::query_as(
+            r#"
+            SELECT part_number, etag, size FROM spatialvault.upload_parts
+            WHERE upload_id = $1
+            ORDER BY part_number
+            "#,
+        )
+        .bind(id)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(parts)
+    }
+
+    /// Store a part of an open upload, replacing an earlier copy of it
+    ///
+    /// The parts together may not exceed `max_upload_bytes`.
+    pub async fn put_part(
+        &self,
+        owner: &str,
+        id: Uuid,
+        part_number: i32,
+        data: Bytes,
+        max_upload_bytes: u64,
+    ) -> AppResult<UploadPart> {
+        if !(1..=MAX_PART_NUMBER).contains(&part_number) {
+            return Err(AppError::BadRequest(format!(
+                "Part number must be between 1 and {}",
+                MAX_PART_NUMBER
+            )));
+        }
+        let upload = self.get_upload(owner, id).await?;
+        if upload.status != "open" {
+            return Err(AppError::Conflict(format!(
+                "Upload {} is already complete",
+                id
+            )));
+        }
+
+        // Reserve the part's bytes first, so concurrent parts can't together
+        // exceed the maximum; a part sent again may use the size it replaces
+        let size = data.len() as i64;
+        let reserved: Option<(i64,)> = sqlx::query_as(
+            r#"
+            UPDATE spatialvault.uploads SET reserved_bytes = reserved_bytes + $2
+            WHERE id = $1 AND status = 'open'
+            AND reserved_bytes + $2 - COALESCE(
+                (SELECT size FROM spatialvault.upload_parts
+                 WHERE upload_id = $1 AND part_number = $3),
+                0
+            ) <= $4
+            RETURNING reserved_bytes
+            "#,
+        )
+        .bind(id)
+        .bind(size)
+        .bind(part_number)
+        .bind(i64::try_from(max_upload_bytes).unwrap_or(i64::MAX))
+        .fetch_optional(self.db.pool())
+        .await?;
+        if reserved.is_none() {
+            return Err(AppError::BadRequest(format!(
+                "Upload exceeds the maximum size of {} bytes",
+                max_upload_bytes
+            )));
+        }
+
+        let etag = match self
+            .storage
+            .put_part(
+                &upload.object_key,
+                &upload.multipart_id,
+                part_number as usize,
+                data,
+            )
+            .await
+        {
+            Ok(etag) => etag,
+            Err(e) => {
+                self.release_bytes(id, size).await?;
+                return Err(e);
+            }
+        };
+
+        // Record the part and release the size of the part it replaces
+        let mut tx = self.db.pool().begin().await?;
+        let replaced: Option<(i64,)> = sqlx::query_as(
+            r#"
+            SELECT size FROM spatialvault.upload_parts
+            WHERE upload_id = $1 AND part_number = $2
+            FOR UPDATE
+            "#,
+        )
+        .bind(id)
+        .bind(part_number)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let part = sqlx::query_as(
+            r#"
+            INSERT INTO spatialvault.upload_parts (upload_id, part_number, etag, size)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (upload_id, part_number)
+            DO UPDATE SET etag = EXCLUDED.etag, size = EXCLUDED.size
+            RETURNING part_number, etag, size
+            "#,
+        )
+        .bind(id)
+        .bind(part_number)
+        .bind(&etag)
+        .bind(size)
+        .fetch_one(&mut *tx)
+        .await?;
+        if let Some((replaced,)) = replaced {
+            sqlx::query(
+                "UPDATE spatialvault.uploads SET reserved_bytes = reserved_bytes - $2 WHERE id = $1",
+            )
+            .bind(id)
+            .bind(replaced)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(part)
+    }
+
+    /// Return bytes reserved for a part that wasn't stored
+    async fn release_bytes(&self, id: Uuid, size: i64) -> AppResult<()> {
+        sqlx::query(
+            "UPDATE spatialvault.uploads SET reserved_bytes = reserved_bytes - $2 WHERE id = $1",
+        )
+        .bind(id)
+        .bind(size)
+        .execute(self.db.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// Assemble the parts of an upload into its file
+    ///
+    /// Completing an already complete upload returns it unchanged, so a
+    /// client can safely retry.
+    pub async fn complete_upload(&self, owner: &str, id: Uuid) -> AppResult<Upload> {
+        let upload = self.get_upload(owner, id).await?;
+        if upload.status == "complete" {
+            return Ok(upload);
+        }
+
+        let parts = self.list_parts(id).await?;
+        check_parts_complete(&parts)?;
+        let size: i64 = parts.iter().map(|part| part.size).sum();
+        self.storage
+            .complete_multipart(
+                &upload.object_key,
+                &upload.multipart_id,
+                parts.into_iter().map(|part| part.etag).collect(),
+            )
+            .await?;
+
+        let upload = sqlx::query_as(
+            r#"
+            UPDATE spatialvault.uploads
+            SET status = 'complete', size = $2, completed_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(size)
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok(upload)
+    }
+
+    /// Delete an upload with its parts or assembled file
+    pub async fn delete_upload(&self, owner: &str, id: Uuid) -> AppResult<()> {
+        let upload = self.get_upload(owner, id).await?;
+        self.remove_upload(&upload).await
+    }
+
+    async fn remove_upload(&self, upload: &Upload) -> AppResult<()> {
+        if upload.status == "open" {
+            self.storage
+                .abort_multipart(&upload.object_key, &upload.multipart_id)
+                .await?;
+        } else {
+            self.storage.delete(&upload.object_key).await?;
+        }
+
+        sqlx::query("DELETE FROM spatialvault.uploads WHERE id = $1")
+            .bind(upload.id)
+            .execute(self.db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Delete uploads started more than `retention_days` ago
+    ///
+    /// Returns the number of uploads removed.
+    pub async fn purge_expired_uploads(&self, retention_days: u32, limit: i64) -> AppResult<usize> {
+        let expired: Vec<Upload> = sqlx::query_as(
+            r#"
+            SELECT * FROM spatialvault.uploads
+            WHERE created_at + make_interval(days => $1) < NOW()
+            ORDER BY created_at
+            LIMIT $2
+            "#,
+        )
+        .bind(retention_days as i32)
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        for upload in &expired {
+            self.remove_upload(upload).await?;
+        }
+
+        Ok(expired.len())
+    }
+
+    /// Resolve an `upload://{id}` href to the S3 URI of the completed upload
+    ///
+    /// Other hrefs are returned unchanged.
+    pub async fn resolve_href(&self, owner: &str, href: &str) -> AppResult<String> {
+        let Some(id) = href.strip_prefix(UPLOAD_SCHEME) else {
+            return Ok(href.to_string());
+        };
+        let id = Uuid::parse_str(id)
+            .map_err(|_| AppError::BadRequest(format!("Invalid upload reference: {}", href)))?;
+        let upload = self
+            .get_upload(owner, id)
+            .await
+            .map_err(|_| AppError::BadRequest(format!("Unknown upload: {}", href)))?;
+        if upload.status != "complete" {
+            return Err(AppError::BadRequest(format!(
+                "Upload {} is not complete",
+                id
+            )));
+        }
+
+        Ok(self.storage.s3_uri(&upload.object_key))
+    }
+
+    /// Resolve every `upload://` href in a JSON value of process inputs
+    pub async fn resolve_hrefs(&self, owner: &str, value: &mut serde_json::Value) -> AppResult<()> {
+        let mut pending = vec![value];
+        while let Some(value) = pending.pop() {
+            match value {
+                serde_json::Value::Object(map) => {
+                    for (key, value) in map.iter_mut() {
+                        match value {
+                            serde_json::Value::String(href) if key == "href" => {
+                                *href = self.resolve_href(owner, href).await?;
+                            }
+                            _ => pending.push(value),
+                        }
+                    }
+                }
+                serde_json::Value::Array(values) => pending.extend(values.iter_mut()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(part_number: i32, size: i64) -> UploadPart {
+        UploadPart {
+            part_number,
+            etag: format!("etag-{}", part_number),
+            size,
+        }
+    }
+
+    #[test]
+    fn test_check_parts_complete() {
+        assert!(check_parts_complete(&[part(1, 10)]).is_ok());
+        assert!(check_parts_complete(&[part(1, MIN_PART_BYTES), part(2, 10)]).is_ok());
+
+        assert!(check_parts_complete(&[]).is_err());
+        let missing = check_parts_complete(&[part(1, MIN_PART_BYTES), part(3, 10)]);
+        assert!(
+            missing
+                .unwrap_err()
+                .to_string()
+                .contains("Part 2 is missing")
+        );
+        assert!(check_parts_complete(&[part(1, 10), part(2, 10)]).is_err());
+    }
+}
@@ -0,0 +1,305 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::cdn_service::hex;
+use crate::api::collections::events::EventResponse;
+use crate::api::read_only;
+use crate::config::WebhookConfig;
+use crate::db::{CollectionEvent, Database};
+use crate::error::{AppError, AppResult};
+use crate::net;
+
+/// Header holding the HMAC-SHA256 signature of a webhook body
+pub const SIGNATURE_HEADER: &str = "X-SpatialVault-Signature";
+
+/// Most webhooks delivered to in one round
+const WEBHOOK_BATCH_SIZE: i64 = 10;
+
+/// Most events delivered to a webhook in one round
+const EVENT_BATCH_SIZE: i64 = 100;
+
+/// How long a server may deliver to a webhook before others may take it over
+const CLAIM_SECS: f64 = 5.0 * 60.0;
+
+/// How long a receiver may take to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Kind of change to a collection, apart from changes to its features
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionEventType {
+    /// Title, description, keywords, limits and other metadata
+    Metadata,
+    /// Queryables and schema: relations, computed, unique properties and
+    /// geometry columns
+    Schema,
+}
+
+impl CollectionEventType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Metadata => "metadata",
+            Self::Schema => "schema",
+        }
+    }
+}
+
+/// Remember a change to the metadata or schema of a collection, in the
+/// transaction making it
+pub async fn record_collection_event(
+    conn: &mut sqlx::PgConnection,
+    collection_id: Uuid,
+    event_type: CollectionEventType,
+    collection_version: i64,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO spatialvault.collection_events (collection_id, event_type, collection_version)
+        VALUES ($1, $2, $3)
+        "#,
+    )
+    .bind(collection_id)
+    .bind(event_type.as_str())
+    .bind(collection_version)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Value of the [`SIGNATURE_HEADER`] of a body
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex(&mac.finalize().into_bytes()))
+}
+
+/// A webhook claimed for delivery
+#[derive(Debug, sqlx::FromRow)]
+struct ClaimedWebhook {
+    id: Uuid,
+    collection_id: Uuid,
+    canonical_name: String,
+    url: String,
+    secret: Option<String>,
+    delivered_until: DateTime<Utc>,
+    delivered_event_id: i64,
+}
+
+/// Posts collection events to the webhooks of their collections
+///
+/// Each webhook remembers the last event delivered to it and gets the events
+/// after it in order; a failed delivery is retried in the next round.
+/// Webhooks are claimed for a while before delivery, so every server may run
+/// the loop.
+pub struct WebhookService {
+    db: Arc<Database>,
+    config: WebhookConfig,
+    client: reqwest::Client,
+}
+
+impl WebhookService {
+    /// Deliverer of events, if webhooks are enabled
+    pub fn new(db: Arc<Database>, config: &WebhookConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        // Receivers are resolved on each delivery, so a URL whose host
+        // later resolves to a non-public address isn't posted to
+        let client = net::client_builder()
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .ok()?;
+        Some(Self {
+            db,
+            config: config.clone(),
+            client,
+        })
+    }
+
+    /// Deliver every `interval`, for as long as the server runs
+    pub async fn run(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if read_only::paused(&self.db).await {
+                continue;
+            }
+            if let Err(e) = self.deliver_pending().await {
+                tracing::warn!("Failed to deliver collection events: {}", e);
+            }
+        }
+    }
+
+    /// Deliver the pending events of webhooks no other server is delivering
+    pub async fn deliver_pending(&self) -> AppResult<()> {
+        // Events of transactions still open may become visible with an
+        // earlier time, so events are only delivered up to their start
+        let until: DateTime<Utc> = sqlx::query_scalar(
+            r#"
+            SELECT LEAST(NOW(), MIN(xact_start)) FROM pg_stat_activity
+            WHERE datname = current_database() AND backend_type = 'client backend'
+              AND pid <> pg_backend_pid()
+            "#,
+        )
+        .fetch_one(self.db.pool())
+        .await?;
+
+        let claimed: Vec<ClaimedWebhook> = sqlx::query_as(
+            r#"
+            WITH due AS (
+                SELECT w.id FROM spatialvault.collection_webhooks w
+                WHERE (w.claimed_until IS NULL OR w.claimed_until < NOW())
+                  AND EXISTS (
+                      SELECT 1 FROM spatialvault.collection_events e
+                      WHERE e.collection_id = w.collection_id
+                        AND (e.created_at, e.id) > (w.delivered_until, w.delivered_event_id)
+                        AND e.created_at < $3
+                  )
+                ORDER BY w.delivered_at NULLS FIRST
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE spatialvault.collection_webhooks w
+            SET claimed_until = NOW() + make_interval(secs => $2)
+            FROM due, spatialvault.collections c
+            WHERE w.id = due.id AND c.id = w.collection_id
+            RETURNING w.id, w.collection_id, c.canonical_name, w.url, w.secret,
+                      w.delivered_until, w.delivered_event_id
+            "#,
+        )
+        .bind(WEBHOOK_BATCH_SIZE)
+        .bind(CLAIM_SECS)
+        .bind(until)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        for webhook in &claimed {
+            let result = self.deliver(webhook, until).await;
+            if let Err(e) = &result {
+                tracing::warn!(
+                    collection = %webhook.canonical_name,
+                    url = %webhook.url,
+                    "Failed to deliver collection event: {}",
+                    e
+                );
+            }
+            self.release(webhook.id, result).await?;
+        }
+
+        self.prune_events().await
+    }
+
+    /// Deliver the pending events of one webhook in order, recording each
+    /// delivered event so that a failure resumes after it
+    async fn deliver(&self, webhook: &ClaimedWebhook, until: DateTime<Utc>) -> AppResult<()> {
+        let url = reqwest::Url::parse(&webhook.url)
+            .map_err(|e| AppError::Upstream(format!("Invalid webhook URL: {}", e)))?;
+        net::check_url(&url)
+            .map_err(|e| AppError::Upstream(format!("Webhook URL refused: {}", e)))?;
+
+        let events: Vec<CollectionEvent> = sqlx::query_as(
+            r#"
+            SELECT * FROM spatialvault.collection_events
+            WHERE collection_id = $1 AND (created_at, id) > ($2, $3) AND created_at < $4
+            ORDER BY created_at, id
+            LIMIT $5
+            "#,
+        )
+        .bind(webhook.collection_id)
+        .bind(webhook.delivered_until)
+        .bind(webhook.delivered_event_id)
+        .bind(until)
+        .bind(EVENT_BATCH_SIZE)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        for event in events {
+            let (created_at, event_id) = (event.created_at, event.id);
+            let body = serde_json::to_vec(&EventResponse::new(event, &webhook.canonical_name))?;
+
+            let mut request = self
+                .client
+                .post(url.clone())
+                .header(reqwest::header::CONTENT_TYPE, "application/json");
+            if let Some(secret) = &webhook.secret {
+                request = request.header(SIGNATURE_HEADER, signature(secret, &body));
+            }
+            let response = request
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| AppError::Upstream(format!("Webhook request failed: {}", e)))?;
+            if !response.status().is_success() {
+                return Err(AppError::Upstream(format!(
+                    "Webhook answered with {}",
+                    response.status()
+                )));
+            }
+
+            sqlx::query(
+                r#"
+                UPDATE spatialvault.collection_webhooks
+                SET delivered_until = $2, delivered_event_id = $3, delivered_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(webhook.id)
+            .bind(created_at)
+            .bind(event_id)
+            .execute(self.db.pool())
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Give up the claim on a webhook, recording why delivery failed
+    async fn release(&self, webhook_id: Uuid, result: AppResult<()>) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE spatialvault.collection_webhooks
+            SET claimed_until = NULL, last_error = $2
+            WHERE id = $1
+            "#,
+        )
+        .bind(webhook_id)
+        .bind(result.err().map(|e| e.to_string()))
+        .execute(self.db.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// Forget events older than the retention period
+    async fn prune_events(&self) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            DELETE FROM spatialvault.collection_events
+            WHERE created_at < NOW() - make_interval(days => $1)
+            "#,
+        )
+        .bind(self.config.event_retention_days as i32)
+        .execute(self.db.pool())
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature() {
+        // HMAC-SHA256 test vector from RFC 4231, test case 2
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
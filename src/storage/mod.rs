@@ -1,3 +1,3 @@
 pub mod s3;
 
-pub use s3::S3Storage;
+pub use s3::{S3Storage, s3_key_from_uri};
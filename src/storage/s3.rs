@@ -1,12 +1,23 @@
 use bytes::Bytes;
-use object_store::{ObjectStore, aws::AmazonS3Builder, path::Path};
+use futures::{Stream, StreamExt, stream::BoxStream};
+use object_store::{
+    ObjectStore, WriteMultipart,
+    aws::AmazonS3Builder,
+    multipart::{MultipartStore, PartId},
+    path::Path,
+};
 use std::sync::Arc;
 
 use crate::config::S3Config;
 use crate::error::{AppError, AppResult};
 
+/// Number of parts uploaded in parallel by streaming uploads
+const UPLOAD_CONCURRENCY: usize = 4;
+
 pub struct S3Storage {
     store: Arc<dyn ObjectStore>,
+    /// The same store, for multipart uploads whose parts arrive separately
+    multipart: Arc<dyn MultipartStore>,
     bucket: String,
 }
 
@@ -30,17 +41,21 @@ impl S3Storage {
             builder = builder.with_secret_access_key(secret_access_key);
         }
 
-        let store = builder
-            .build()
-            .map_err(|e| AppError::Storage(format!("Failed to create S3 client: {}", e)))?;
+        let store = Arc::new(
+            builder
+                .build()
+                .map_err(|e| AppError::Storage(format!("Failed to create S3 client: {}", e)))?,
+        );
 
         Ok(Self {
-            store: Arc::new(store),
+            store: store.clone(),
+            multipart: store,
             bucket: config.bucket.clone(),
         })
     }
 
     /// Get an object from S3
+    #[tracing::instrument(name = "s3.get", skip_all, fields(key = %key))]
     pub async fn get(&self, key: &str) -> AppResult<Bytes> {
         let path = Path::from(key);
         let result = self
@@ -57,7 +72,36 @@ impl S3Storage {
         Ok(bytes)
     }
 
+    /// Get an object from S3 as a stream of chunks
+    #[tracing::instrument(name = "s3.get_stream", skip_all, fields(key = %key))]
+    pub async fn get_stream(&self, key: &str) -> AppResult<BoxStream<'static, AppResult<Bytes>>> {
+        let path = Path::from(key);
+        let result = self
+            .store
+            .get(&path)
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to get object: {}", e)))?;
+
+        Ok(result
+            .into_stream()
+            .map(|chunk| {
+                chunk.map_err(|e| AppError::Storage(format!("Failed to read object: {}", e)))
+            })
+            .boxed())
+    }
+
+    /// Get a byte range of an object from S3
+    #[tracing::instrument(name = "s3.get_range", skip_all, fields(key = %key))]
+    pub async fn get_range(&self, key: &str, range: std::ops::Range<usize>) -> AppResult<Bytes> {
+        let path = Path::from(key);
+        self.store
+            .get_range(&path, range)
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to get object range: {}", e)))
+    }
+
     /// Put an object to S3
+    #[tracing::instrument(name = "s3.put", skip_all, fields(key = %key))]
     pub async fn put(&self, key: &str, data: Bytes) -> AppResult<()> {
         let path = Path::from(key);
         self.store
@@ -68,7 +112,123 @@ impl S3Storage {
         Ok(())
     }
 
+    /// Upload a stream of chunks as a multipart upload without buffering it
+    ///
+    /// The upload is aborted if the stream fails or grows beyond `max_bytes`.
+    /// Returns the number of bytes written.
+    #[tracing::instrument(name = "s3.put_stream", skip_all, fields(key = %key))]
+    pub async fn put_stream<S, E>(&self, key: &str, mut stream: S, max_bytes: u64) -> AppResult<u64>
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin,
+        E: std::fmt::Display,
+    {
+        let path = Path::from(key);
+        let upload = self
+            .store
+            .put_multipart(&path)
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to start upload: {}", e)))?;
+        let mut writer = WriteMultipart::new(upload);
+        let mut size = 0u64;
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    writer.abort().await.ok();
+                    return Err(AppError::BadRequest(format!(
+                        "Failed to read upload: {}",
+                        e
+                    )));
+                }
+            };
+
+            size += chunk.len() as u64;
+            if size > max_bytes {
+                writer.abort().await.ok();
+                return Err(AppError::BadRequest(format!(
+                    "Upload exceeds the maximum size of {} bytes",
+                    max_bytes
+                )));
+            }
+
+            writer
+                .wait_for_capacity(UPLOAD_CONCURRENCY)
+                .await
+                .map_err(|e| AppError::Storage(format!("Failed to upload part: {}", e)))?;
+            writer.put(chunk);
+        }
+
+        writer
+            .finish()
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to complete upload: {}", e)))?;
+
+        Ok(size)
+    }
+
+    /// Start a multipart upload whose parts are sent separately, returning its id
+    #[tracing::instrument(name = "s3.create_multipart", skip_all, fields(key = %key))]
+    pub async fn create_multipart(&self, key: &str) -> AppResult<String> {
+        let path = Path::from(key);
+        self.multipart
+            .create_multipart(&path)
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to start upload: {}", e)))
+    }
+
+    /// Upload one part (numbered from 1) of a multipart upload, returning its ETag
+    #[tracing::instrument(name = "s3.put_part", skip_all, fields(key = %key))]
+    pub async fn put_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        data: Bytes,
+    ) -> AppResult<String> {
+        let path = Path::from(key);
+        let part = self
+            .multipart
+            .put_part(&path, &upload_id.to_string(), part_number - 1, data.into())
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to upload part: {}", e)))?;
+
+        Ok(part.content_id)
+    }
+
+    /// Assemble a multipart upload from the ETags of all its parts, in order
+    #[tracing::instrument(name = "s3.complete_multipart", skip_all, fields(key = %key))]
+    pub async fn complete_multipart(
+        &self,
+        key: &str,
+        upload_id: &str,
+        etags: Vec<String>,
+    ) -> AppResult<()> {
+        let path = Path::from(key);
+        let parts = etags
+            .into_iter()
+            .map(|content_id| PartId { content_id })
+            .collect();
+        self.multipart
+            .complete_multipart(&path, &upload_id.to_string(), parts)
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to complete upload: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Abort a multipart upload, discarding its parts
+    #[tracing::instrument(name = "s3.abort_multipart", skip_all, fields(key = %key))]
+    pub async fn abort_multipart(&self, key: &str, upload_id: &str) -> AppResult<()> {
+        let path = Path::from(key);
+        self.multipart
+            .abort_multipart(&path, &upload_id.to_string())
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to abort upload: {}", e)))
+    }
+
     /// Delete an object from S3
+    #[tracing::instrument(name = "s3.delete", skip_all, fields(key = %key))]
     pub async fn delete(&self, key: &str) -> AppResult<()> {
         let path = Path::from(key);
         self.store
@@ -79,7 +239,19 @@ impl S3Storage {
         Ok(())
     }
 
+    /// Delete all objects under a prefix, returning how many were removed
+    #[tracing::instrument(name = "s3.delete_prefix", skip_all, fields(prefix = %prefix))]
+    pub async fn delete_prefix(&self, prefix: &str) -> AppResult<usize> {
+        let keys = self.list(prefix).await?;
+        for key in &keys {
+            self.delete(key).await?;
+        }
+
+        Ok(keys.len())
+    }
+
     /// Check if an object exists
+    #[tracing::instrument(name = "s3.exists", skip_all, fields(key = %key))]
     pub async fn exists(&self, key: &str) -> AppResult<bool> {
         let path = Path::from(key);
         match self.store.head(&path).await {
@@ -90,6 +262,7 @@ impl S3Storage {
     }
 
     /// Get object metadata (size, content-type, etc.)
+    #[tracing::instrument(name = "s3.head", skip_all, fields(key = %key))]
     pub async fn head(&self, key: &str) -> AppResult<ObjectMeta> {
         let path = Path::from(key);
         let meta = self
@@ -106,9 +279,8 @@ impl S3Storage {
     }
 
     /// List objects with a prefix
+    #[tracing::instrument(name = "s3.list", skip_all, fields(prefix = %prefix))]
     pub async fn list(&self, prefix: &str) -> AppResult<Vec<String>> {
-        use futures::StreamExt;
-
         let path = Path::from(prefix);
         let mut stream = self.store.list(Some(&path));
         let mut keys = Vec::new();
@@ -140,3 +312,9 @@ pub struct ObjectMeta {
     pub location: String,
     pub last_modified: chrono::DateTime<chrono::Utc>,
 }
+
+/// Extract the object key from an s3://bucket/key URI
+pub fn s3_key_from_uri(url: &str) -> &str {
+    let path = url.strip_prefix("s3://").unwrap_or(url);
+    path.split_once('/').map(|(_, k)| k).unwrap_or(path)
+}
@@ -0,0 +1,261 @@
+//! Tracing setup with optional OpenTelemetry export
+//!
+//! Spans from `tracing` are exported over OTLP/HTTP when
+//! `telemetry.otlp_endpoint` is configured, together with request metrics.
+//! Incoming `traceparent` headers are honoured so spans join the caller's
+//! trace, and outgoing HTTP requests from the worker carry the current one.
+//!
+//! Every request gets an id, taken from `X-Request-Id` or generated, which is
+//! returned in the response and logged with the request span.
+
+use axum::{
+    Router,
+    extract::{MatchedPath, RawPathParams, Request, rejection::RawPathParamsRejection},
+    http::{HeaderMap, Response},
+    middleware::Next,
+};
+use opentelemetry::{KeyValue, global, metrics::Histogram, trace::TracerProvider as _};
+use opentelemetry_http::{HeaderExtractor, HeaderInjector};
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    Resource,
+    metrics::{PeriodicReader, SdkMeterProvider},
+    propagation::TraceContextPropagator,
+    trace::{Sampler, SdkTracerProvider},
+};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::{OnResponse, TraceLayer},
+};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use crate::config::{LogFormat, TelemetryConfig};
+
+/// Flushes and shuts down the exporters when dropped
+#[must_use = "telemetry is shut down when the guard is dropped"]
+pub struct TelemetryGuard {
+    tracer_provider: Option<SdkTracerProvider>,
+    meter_provider: Option<SdkMeterProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(Err(e)) = self.tracer_provider.take().map(|p| p.shutdown()) {
+            eprintln!("Failed to shut down trace export: {}", e);
+        }
+        if let Some(Err(e)) = self.meter_provider.take().map(|p| p.shutdown()) {
+            eprintln!("Failed to shut down metric export: {}", e);
+        }
+    }
+}
+
+/// Join a collector base URL and a signal path, e.g. `/v1/traces`
+fn signal_endpoint(base: &str, path: &str) -> String {
+    format!("{}{}", base.trim_end_matches('/'), path)
+}
+
+/// Install the global tracing subscriber, exporting to OTLP when configured
+pub fn init(config: &TelemetryConfig) -> anyhow::Result<TelemetryGuard> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let mut guard = TelemetryGuard {
+        tracer_provider: None,
+        meter_provider: None,
+    };
+    let otel_layer = match config.otlp_endpoint.as_deref() {
+        Some(endpoint) => {
+            let resource = Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build();
+
+            let span_exporter = SpanExporter::builder()
+                .with_http()
+                .with_endpoint(signal_endpoint(endpoint, "/v1/traces"))
+                .build()?;
+            let tracer_provider = SdkTracerProvider::builder()
+                .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                    config.sample_ratio,
+                ))))
+                .with_resource(resource.clone())
+                .with_batch_exporter(span_exporter)
+                .build();
+
+            let metric_exporter = MetricExporter::builder()
+                .with_http()
+                .with_endpoint(signal_endpoint(endpoint, "/v1/metrics"))
+                .build()?;
+            let meter_provider = SdkMeterProvider::builder()
+                .with_resource(resource)
+                .with_reader(PeriodicReader::builder(metric_exporter).build())
+                .build();
+            global::set_meter_provider(meter_provider.clone());
+
+            let tracer = tracer_provider.tracer(config.service_name.clone());
+            guard.tracer_provider = Some(tracer_provider);
+            guard.meter_provider = Some(meter_provider);
+            Some(tracing_opentelemetry::layer().with_tracer(tracer))
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+                "spatialvault=debug,tower_http=debug,axum::rejection=trace".into()
+            }),
+        )
+        .with((config.log_format == LogFormat::Text).then(tracing_subscriber::fmt::layer))
+        .with((config.log_format == LogFormat::Json).then(|| {
+            tracing_subscriber::fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_current_span(false)
+                .with_span_list(true)
+        }))
+        .with(otel_layer)
+        .init();
+
+    if let Some(endpoint) = &config.otlp_endpoint {
+        tracing::info!("Exporting traces and metrics to {}", endpoint);
+    }
+
+    Ok(guard)
+}
+
+/// Wrap a router with request ids and a span per request
+///
+/// Apply this around the finished router so that every response, including
+/// those from outer layers, carries `X-Request-Id`.
+pub fn with_request_tracing(router: Router) -> Router {
+    router
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(request_span)
+                .on_response(RecordResponse),
+        )
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+}
+
+/// Span for an incoming HTTP request, continuing the trace from `traceparent`
+///
+/// `route` and `collection` are filled in by [`record_route`] once the
+/// request has been routed, `client_ip` by the forwarded headers middleware,
+/// and `user` and `service_account` by the auth middleware.
+pub fn request_span<B>(request: &axum::http::Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "request",
+        otel.name = %format!("{} {}", request.method(), request.uri().path()),
+        otel.kind = "server",
+        request_id,
+        http.request.method = %request.method(),
+        url.path = %request.uri().path(),
+        client_ip = tracing::field::Empty,
+        route = tracing::field::Empty,
+        collection = tracing::field::Empty,
+        user = tracing::field::Empty,
+        service_account = tracing::field::Empty,
+        http.response.status_code = tracing::field::Empty,
+        latency_ms = tracing::field::Empty,
+    );
+    let parent = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(request.headers()))
+    });
+    // Only fails when the span is disabled, in which case there is nothing to join
+    let _ = span.set_parent(parent);
+    span
+}
+
+/// Route layer recording the matched route and collection on the request span
+pub async fn record_route(
+    matched_path: Option<MatchedPath>,
+    params: Result<RawPathParams, RawPathParamsRejection>,
+    request: Request,
+    next: Next,
+) -> axum::response::Response {
+    let span = Span::current();
+    if let Some(path) = &matched_path {
+        span.record("route", path.as_str());
+        span.record(
+            "otel.name",
+            format!("{} {}", request.method(), path.as_str()),
+        );
+    }
+    if let Some(collection) = params.as_ref().ok().and_then(|params| {
+        params
+            .iter()
+            .find(|(key, _)| *key == "collection_id")
+            .map(|(_, value)| value.to_string())
+    }) {
+        span.record("collection", collection);
+    }
+    next.run(request).await
+}
+
+/// Headers carrying the current trace context, for outgoing HTTP requests
+pub fn trace_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(&mut headers))
+    });
+    headers
+}
+
+fn request_duration() -> &'static Histogram<f64> {
+    static HISTOGRAM: OnceLock<Histogram<f64>> = OnceLock::new();
+    HISTOGRAM.get_or_init(|| {
+        global::meter("spatialvault")
+            .f64_histogram("http.server.request.duration")
+            .with_unit("s")
+            .with_description("Duration of HTTP requests")
+            .build()
+    })
+}
+
+/// Logs each response with its status and latency and records the duration metric
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RecordResponse;
+
+impl<B> OnResponse<B> for RecordResponse {
+    fn on_response(self, response: &Response<B>, latency: Duration, span: &Span) {
+        let status = response.status().as_u16();
+        let latency_ms = latency.as_millis() as u64;
+        span.record("http.response.status_code", status);
+        span.record("latency_ms", latency_ms);
+        request_duration().record(
+            latency.as_secs_f64(),
+            &[KeyValue::new(
+                "http.response.status_code",
+                i64::from(status),
+            )],
+        );
+        tracing::info!(status, latency_ms, "finished processing request");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_endpoint() {
+        assert_eq!(
+            signal_endpoint("http://collector:4318/", "/v1/traces"),
+            "http://collector:4318/v1/traces"
+        );
+        assert_eq!(
+            signal_endpoint("http://collector:4318", "/v1/metrics"),
+            "http://collector:4318/v1/metrics"
+        );
+    }
+}
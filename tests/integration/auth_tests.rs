@@ -2,8 +2,9 @@
 //!
 //! Tests authentication behavior with mock auth middleware.
 
-use crate::common::TestApp;
+use crate::common::{MockAuthState, TestApp, test_collection_request};
 use axum::http::StatusCode;
+use spatialvault::auth::AccessScope;
 
 /// Test that public endpoints work without authentication (mock auth still injects user)
 #[tokio::test]
@@ -93,3 +94,167 @@ async fn test_user_can_access_own_collections() {
         .await;
     items_response.assert_success();
 }
+
+/// Test that access tokens can be exchanged for scoped tokens
+#[tokio::test]
+async fn test_create_scoped_token() {
+    let app = TestApp::new().await;
+
+    let request = serde_json::json!({
+        "collections": ["roads", "parks"],
+        "actions": ["read"],
+        "expiresIn": 600
+    });
+    let response = app.post_json("/tokens", &request).await;
+    response.assert_success();
+
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["tokenType"], "Bearer");
+    assert!(body["accessToken"].as_str().is_some_and(|t| !t.is_empty()));
+    assert!(body["expires"].is_string());
+
+    // Only known actions can be granted
+    let request = serde_json::json!({ "actions": ["admin"] });
+    let response = app.post_json("/tokens", &request).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test that tokens limited to some collections can't use routes across collections
+#[tokio::test]
+async fn test_scoped_token_routes() {
+    let scope = AccessScope {
+        collections: vec!["roads".to_string()],
+        actions: vec!["read".to_string()],
+        processes: vec![],
+    };
+    let app = TestApp::with_auth(MockAuthState::with_scope("testuser", scope)).await;
+
+    for path in [
+        "/collections",
+        "/jobs",
+        "/usage",
+        "/notifications/settings",
+        "/queryables",
+        "/broken-assets",
+    ] {
+        app.get(path).await.assert_status(StatusCode::FORBIDDEN);
+    }
+    app.get("/collections/testuser:parks/items")
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    // Collections in scope are only subject to the usual checks
+    app.get("/collections/testuser:roads/items")
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+/// Test that a token's scope applies to each step of a workflow
+#[tokio::test]
+async fn test_scoped_token_workflow_steps() {
+    let scope = AccessScope {
+        collections: vec!["roads".to_string()],
+        actions: vec![],
+        processes: vec!["export-collection".to_string()],
+    };
+    let app = TestApp::with_auth(MockAuthState::with_scope("testuser", scope)).await;
+
+    let workflow = |process: &str, collection: &str| {
+        serde_json::json!({
+            "steps": [{ "process": process, "inputs": { "collection": collection } }]
+        })
+    };
+    app.post_json("/workflows", &workflow("backup-collection", "roads"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+    app.post_json("/workflows", &workflow("export-collection", "parks"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+/// Test that collections of other users are not found unless shared
+#[tokio::test]
+async fn test_private_collection_not_found() {
+    let app = TestApp::new().await;
+    let collection = test_collection_request("private-roads", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let etag = create_response.etag().expect("Should have ETag");
+
+    let other = TestApp::builder()
+        .auth(MockAuthState::with_username("otheruser"))
+        .database_url(app.config.database.url.clone())
+        .start()
+        .await;
+    other.ensure_role_exists("otheruser").await;
+
+    for path in [
+        format!("/collections/{}", collection_id),
+        format!("/collections/{}/items", collection_id),
+        "/collections/missing-collection".to_string(),
+    ] {
+        other.get(&path).await.assert_status(StatusCode::NOT_FOUND);
+    }
+
+    // Nor is where it moved to
+    app.patch_json(
+        &format!("/collections/{}", collection_id),
+        &serde_json::json!({ "id": "private-streets" }),
+        &etag,
+    )
+    .await
+    .assert_success();
+    app.get(&format!("/collections/{}", collection_id))
+        .await
+        .assert_status(StatusCode::TEMPORARY_REDIRECT);
+    other
+        .get(&format!("/collections/{}", collection_id))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+/// Test that processes can't read collections of other users
+#[tokio::test]
+async fn test_private_collection_not_processed() {
+    let app = TestApp::new().await;
+    let collection = test_collection_request("private-parcels", "vector");
+    let response = app.post_json("/collections", &collection).await;
+    response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    let other = TestApp::builder()
+        .auth(MockAuthState::with_username("otheruser"))
+        .database_url(app.config.database.url.clone())
+        .start()
+        .await;
+    other.ensure_role_exists("otheruser").await;
+
+    other
+        .post_json(
+            "/processes/export-collection/execution",
+            &serde_json::json!({ "inputs": { "collection": collection_id, "format": "kml" } }),
+        )
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    let raster = test_collection_request("private-imagery", "raster");
+    let response = app.post_json("/collections", &raster).await;
+    response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    other
+        .post_json(
+            "/processes/temporal-composite/execution",
+            &serde_json::json!({
+                "inputs": {
+                    "collection": created["id"],
+                    "datetime": "2024-01-01T00:00:00Z/2024-12-31T23:59:59Z",
+                    "target": "stolen-composite"
+                }
+            }),
+        )
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
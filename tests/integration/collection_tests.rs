@@ -1,7 +1,8 @@
 //! Collection CRUD integration tests
 
-use crate::common::{TestApp, test_collection_request};
-use axum::http::StatusCode;
+use crate::common::{MockAuthState, TestApp, test_collection_request, test_feature_request};
+use axum::http::{StatusCode, header};
+use spatialvault::services::CollectionService;
 
 /// Test creating a collection
 #[tokio::test]
@@ -94,6 +95,422 @@ async fn test_update_collection_with_etag() {
     assert_eq!(body["title"].as_str(), Some("Updated Title"));
 }
 
+/// Test setting and removing collection processing defaults
+#[tokio::test]
+async fn test_update_collection_processing_defaults() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("integration-defaults-test", "pointcloud");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let etag = create_response.etag().expect("Should have ETag");
+
+    let update = serde_json::json!({
+        "processingDefaults": {
+            "keepSource": true,
+            "pipeline": { "targetCrs": "http://www.opengis.net/def/crs/EPSG/0/3006" }
+        }
+    });
+    let patch_response = app
+        .patch_json(&format!("/collections/{}", collection_id), &update, &etag)
+        .await;
+    patch_response.assert_success();
+
+    let body: serde_json::Value = patch_response.json();
+    assert_eq!(body["processingDefaults"]["keepSource"], true);
+    let etag = patch_response.etag().expect("Should have ETag");
+
+    // An empty object removes the defaults
+    let update = serde_json::json!({ "processingDefaults": {} });
+    let patch_response = app
+        .patch_json(&format!("/collections/{}", collection_id), &update, &etag)
+        .await;
+    patch_response.assert_success();
+
+    let body: serde_json::Value = patch_response.json();
+    assert!(body.get("processingDefaults").is_none());
+}
+
+/// Test attaching assets to a collection
+#[tokio::test]
+async fn test_collection_assets() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("integration-assets-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let etag = create_response.etag().expect("Should have ETag");
+
+    let asset = serde_json::json!({
+        "href": "https://example.com/roads.pmtiles",
+        "type": "application/vnd.pmtiles",
+        "roles": ["data"]
+    });
+    let uri = format!("/collections/{}/assets/pmtiles", collection_id);
+    let response = app.put_json(&uri, &asset, &etag).await;
+    response.assert_status(StatusCode::CREATED);
+    let response = app.put_json(&uri, &asset, &etag).await;
+    response.assert_status(StatusCode::OK);
+
+    let get_response = app.get(&format!("/collections/{}", collection_id)).await;
+    get_response.assert_success();
+    let body: serde_json::Value = get_response.json();
+    assert_eq!(
+        body["assets"]["pmtiles"]["href"].as_str(),
+        Some("https://example.com/roads.pmtiles")
+    );
+    assert_ne!(get_response.etag(), Some(etag.clone()));
+
+    let response = app
+        .request_without_etag(axum::http::Method::DELETE, &uri)
+        .await;
+    response.assert_status(StatusCode::NO_CONTENT);
+    app.get(&uri).await.assert_status(StatusCode::NOT_FOUND);
+}
+
+/// Test listing assets whose links were found broken
+#[tokio::test]
+async fn test_broken_assets_report() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("integration-links-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let etag = create_response.etag().expect("Should have ETag");
+
+    let asset = serde_json::json!({ "href": "https://example.com/gone.pdf" });
+    let uri = format!("/collections/{}/assets/spec", collection_id);
+    app.put_json(&uri, &asset, &etag)
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    let report = format!("/broken-assets?collection={}", collection_id);
+    let body: serde_json::Value = app.get(&report).await.json();
+    assert_eq!(body["numberReturned"], 0);
+
+    // As recorded by the link check
+    sqlx::query(
+        r#"
+        UPDATE spatialvault.collection_assets
+        SET link_status = 'broken', link_checked_at = NOW()
+        WHERE href = 'https://example.com/gone.pdf'
+        "#,
+    )
+    .execute(app.db.pool())
+    .await
+    .expect("Failed to record the link check");
+
+    let body: serde_json::Value = app.get(&report).await.json();
+    assert_eq!(body["numberReturned"], 1);
+    assert_eq!(body["assets"][0]["key"], "spec");
+    assert_eq!(body["assets"][0]["collection"], collection_id);
+    assert!(body["assets"][0].get("checked").is_some());
+    assert!(body["assets"][0].get("itemId").is_none());
+
+    // Replacing the asset clears its link status until it is checked again
+    let etag = app
+        .get(&format!("/collections/{}", collection_id))
+        .await
+        .etag();
+    let asset = serde_json::json!({ "href": "https://example.com/spec.pdf" });
+    app.put_json(&uri, &asset, &etag.expect("Should have ETag"))
+        .await
+        .assert_status(StatusCode::OK);
+    let body: serde_json::Value = app.get(&report).await.json();
+    assert_eq!(body["numberReturned"], 0);
+
+    app.get("/broken-assets?limit=0")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test storage usage reporting and quota enforcement
+#[tokio::test]
+async fn test_storage_quota() {
+    let app = TestApp::builder()
+        .auth(MockAuthState::with_username("quotauser"))
+        .configure(|config| {
+            config
+                .quotas
+                .namespaces
+                .insert("quotauser".to_string(), 10_000_000);
+            // Each check sees the writes before it
+            config.quotas.cache_secs = 0;
+        })
+        .start()
+        .await;
+    app.ensure_role_exists("quotauser").await;
+
+    let collection = test_collection_request("integration-quota-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let items = format!("/collections/{}/items", collection_id);
+    app.post_json(&items, &test_feature_request())
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    let body: serde_json::Value = app.get("/usage").await.json();
+    let namespace = &body["namespaces"][0];
+    assert_eq!(namespace["namespace"], "quotauser");
+    assert_eq!(namespace["quotaBytes"], 10_000_000);
+    assert_eq!(namespace["assetBytes"], 0);
+    assert!(namespace["tableBytes"].as_i64().unwrap() > 0);
+
+    let response = app.get(&format!("/collections/{}", collection_id)).await;
+    let body: serde_json::Value = response.json();
+    assert!(body["usage"]["tableBytes"].as_i64().unwrap() > 0);
+
+    // An asset filling the quota
+    let asset = serde_json::json!({ "href": "s3://bucket/archive.zip", "file:size": 20_000_000 });
+    let uri = format!("/collections/{}/assets/archive", collection_id);
+    app.put_json(&uri, &asset, &response.etag().expect("Should have ETag"))
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    let body: serde_json::Value = app.get("/usage").await.json();
+    assert_eq!(body["namespaces"][0]["assetBytes"], 20_000_000);
+
+    app.post_json(&items, &test_feature_request())
+        .await
+        .assert_status(StatusCode::INSUFFICIENT_STORAGE);
+
+    // Nor can jobs add to the namespace
+    let raster = test_collection_request("integration-quota-raster", "raster");
+    let response = app.post_json("/collections", &raster).await;
+    response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    app.post_json(
+        "/processes/temporal-composite/execution",
+        &serde_json::json!({
+            "inputs": {
+                "collection": created["id"],
+                "datetime": "2024-01-01T00:00:00Z/2024-12-31T23:59:59Z",
+                "target": "integration-quota-composite"
+            }
+        }),
+    )
+    .await
+    .assert_status(StatusCode::INSUFFICIENT_STORAGE);
+
+    // Deleting makes room again
+    let etag = app
+        .get(&format!("/collections/{}", collection_id))
+        .await
+        .etag()
+        .expect("Should have ETag");
+    app.delete(&uri, &etag).await.assert_success();
+    app.post_json(&items, &test_feature_request())
+        .await
+        .assert_status(StatusCode::CREATED);
+}
+
+/// Test searching collections by keyword, text and extent
+#[tokio::test]
+async fn test_collection_discovery() {
+    let app = TestApp::new().await;
+
+    let mut collection = test_collection_request("integration-discovery-test", "vector");
+    collection["keywords"] = serde_json::json!(["discovery-hydrology", " discovery-rivers "]);
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    assert_eq!(
+        created["keywords"],
+        serde_json::json!(["discovery-hydrology", "discovery-rivers"])
+    );
+
+    app.post_json(
+        &format!("/collections/{}/items", collection_id),
+        &test_feature_request(),
+    )
+    .await
+    .assert_status(StatusCode::CREATED);
+
+    // Extents are computed in the background; stand in for a round
+    let collections = CollectionService::new(app.db.clone());
+    assert!(collections.refresh_stale_extents().await.unwrap() >= 1);
+
+    let ids = |body: &serde_json::Value| -> Vec<String> {
+        body["collections"]
+            .as_array()
+            .expect("Should have collections")
+            .iter()
+            .filter_map(|c| c["id"].as_str().map(str::to_string))
+            .collect()
+    };
+
+    let response = app.get("/collections?keyword=discovery-hydrology").await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(ids(&body), vec![collection_id.to_string()]);
+    assert_eq!(body["facets"]["keywords"]["discovery-rivers"], 1);
+    assert_eq!(body["facets"]["type"]["vector"], 1);
+
+    let response = app
+        .get("/collections?q=integration-discovery&keyword=discovery-rivers&bbox=-1,-1,1,1")
+        .await;
+    response.assert_success();
+    assert_eq!(ids(&response.json()), vec![collection_id.to_string()]);
+
+    let response = app
+        .get("/collections?keyword=discovery-hydrology&bbox=50,50,60,60")
+        .await;
+    response.assert_success();
+    assert!(ids(&response.json()).is_empty());
+
+    // A write makes the cached extent stale until the next round
+    let mut feature = test_feature_request();
+    feature["geometry"] = serde_json::json!({"type": "Point", "coordinates": [55.0, 55.0]});
+    app.post_json(&format!("/collections/{}/items", collection_id), &feature)
+        .await
+        .assert_status(StatusCode::CREATED);
+    collections.refresh_stale_extents().await.unwrap();
+    let response = app
+        .get("/collections?keyword=discovery-hydrology&bbox=50,50,60,60")
+        .await;
+    response.assert_success();
+    assert_eq!(ids(&response.json()), vec![collection_id.to_string()]);
+
+    app.get("/collections?bbox=1,2,3")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test the ISO 19139 metadata document of a collection
+#[tokio::test]
+async fn test_collection_iso19139_metadata() {
+    let app = TestApp::builder()
+        .configure(|config| {
+            config.metadata.contact_email = Some("gis@example.com".to_string());
+        })
+        .start()
+        .await;
+
+    let mut collection = test_collection_request("integration-iso-test", "vector");
+    collection["keywords"] = serde_json::json!(["iso-metadata", "inlandWaters"]);
+    collection["license"] = "CC0-1.0".into();
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    let response = app
+        .get(&format!(
+            "/collections/{}/metadata?f=iso19139",
+            collection_id
+        ))
+        .await;
+    response.assert_success();
+    response.assert_content_type("application/xml");
+    let xml = response.text();
+    assert!(xml.contains("<gmd:MD_Metadata"));
+    assert!(xml.contains("<gco:CharacterString>iso-metadata</gco:CharacterString>"));
+    assert!(xml.contains("<gco:CharacterString>CC0-1.0</gco:CharacterString>"));
+    assert!(xml.contains("<gmd:MD_TopicCategoryCode>inlandWaters</gmd:MD_TopicCategoryCode>"));
+    assert!(xml.contains("<gco:CharacterString>gis@example.com</gco:CharacterString>"));
+    assert!(xml.contains("<gmd:LI_Lineage>"));
+    assert!(xml.contains("codeListValue=\"eng\""));
+
+    app.get(&format!("/collections/{}/metadata?f=dcat", collection_id))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test every response carries a request id, reusing the client's when given
+#[tokio::test]
+async fn test_request_id_header() {
+    let app = TestApp::new().await;
+
+    let response = app.get("/collections").await;
+    response.assert_success();
+    let generated = response
+        .header("x-request-id")
+        .expect("Response must have X-Request-Id");
+    assert!(!generated.is_empty());
+
+    let response = app
+        .get_with_headers(
+            "/collections/does-not-exist",
+            vec![(
+                header::HeaderName::from_static("x-request-id"),
+                "support-case-42",
+            )],
+        )
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.header("x-request-id").as_deref(),
+        Some("support-case-42")
+    );
+}
+
+/// Test localized titles and descriptions are picked by Accept-Language
+#[tokio::test]
+async fn test_collection_localization() {
+    let app = TestApp::new().await;
+
+    let mut collection = test_collection_request("integration-i18n-test", "vector");
+    collection["description"] = "Road network".into();
+    collection["titles"] = serde_json::json!({"sv": "Vägar", "de-DE": "Straßen"});
+    collection["descriptions"] = serde_json::json!({"sv": "Vägnät"});
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    assert_eq!(created["titles"]["de-de"], "Straßen");
+
+    let response = app
+        .get_with_headers(
+            &format!("/collections/{}", collection_id),
+            vec![(header::ACCEPT_LANGUAGE, "sv-SE, en;q=0.5")],
+        )
+        .await;
+    response.assert_success();
+    assert_eq!(response.header("content-language").as_deref(), Some("sv"));
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["title"], "Vägar");
+    assert_eq!(body["description"], "Vägnät");
+    let self_link = body["links"]
+        .as_array()
+        .expect("links must be an array")
+        .iter()
+        .find(|link| link["rel"] == "self")
+        .expect("self link");
+    assert_eq!(self_link["hreflang"], "sv");
+
+    let response = app
+        .get_with_headers(
+            &format!("/collections/{}", collection_id),
+            vec![(header::ACCEPT_LANGUAGE, "fi")],
+        )
+        .await;
+    response.assert_success();
+    assert!(response.header("content-language").is_none());
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["title"], created["title"]);
+    assert_eq!(body["description"], "Road network");
+
+    let mut invalid = test_collection_request("integration-i18n-invalid", "vector");
+    invalid["titles"] = serde_json::json!({"not a tag": "Title"});
+    app.post_json("/collections", &invalid)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
 /// Test update fails without ETag
 #[tokio::test]
 async fn test_update_collection_without_etag() {
@@ -374,3 +791,882 @@ async fn test_raster_collection_links() {
         "Detail collection should have tiles link for raster type"
     );
 }
+
+/// Test that table collections store features without geometry
+#[tokio::test]
+async fn test_table_collection_features() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("table-test", "table");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let items = format!("/collections/{}/items", collection_id);
+
+    let feature = serde_json::json!({
+        "type": "Feature",
+        "geometry": null,
+        "properties": {"name": "Alice"}
+    });
+    let response = app.post_json(&items, &feature).await;
+    response.assert_status(StatusCode::CREATED);
+
+    // Features with a geometry are rejected
+    let response = app.post_json(&items, &test_feature_request()).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let response = app.get(&items).await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    let features = body["features"].as_array().expect("Should have features");
+    assert_eq!(features.len(), 1);
+    assert!(features[0]["geometry"].is_null());
+    assert_eq!(features[0]["properties"]["name"], "Alice");
+
+    // Table collections have no tiles
+    let links = created["links"].as_array().expect("Should have links");
+    assert!(!links.iter().any(|l| l["rel"].as_str() == Some("tiles")));
+}
+
+/// Test that duplicate values of unique properties are rejected with 409
+#[tokio::test]
+async fn test_unique_properties() {
+    let app = TestApp::new().await;
+
+    let mut collection = test_collection_request("unique-test", "vector");
+    collection["uniqueProperties"] = serde_json::json!(["code"]);
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let items = format!("/collections/{}/items", collection_id);
+
+    let mut feature = test_feature_request();
+    feature["properties"] = serde_json::json!({"code": "A1", "name": "first"});
+    let response = app.post_json(&items, &feature).await;
+    response.assert_status(StatusCode::CREATED);
+
+    feature["properties"] = serde_json::json!({"code": "A1", "name": "second"});
+    let response = app.post_json(&items, &feature).await;
+    response.assert_status(StatusCode::CONFLICT);
+
+    // Declaring a property with duplicate values fails
+    let schema = format!("/collections/{}/schema", collection_id);
+    feature["properties"] = serde_json::json!({"code": "A2", "name": "first"});
+    let response = app.post_json(&items, &feature).await;
+    response.assert_status(StatusCode::CREATED);
+    let response = app
+        .patch_json_without_etag(
+            &schema,
+            &serde_json::json!({"x-uniqueProperties": ["code", "name"]}),
+        )
+        .await;
+    response.assert_status(StatusCode::CONFLICT);
+
+    // Dropping the constraint allows duplicates again
+    let response = app
+        .patch_json_without_etag(&schema, &serde_json::json!({"x-uniqueProperties": []}))
+        .await;
+    response.assert_success();
+    feature["properties"] = serde_json::json!({"code": "A1", "name": "third"});
+    let response = app.post_json(&items, &feature).await;
+    response.assert_status(StatusCode::CREATED);
+}
+
+/// Test that re-ingesting a FeatureCollection with on-conflict updates or
+/// skips matching features instead of duplicating them
+#[tokio::test]
+async fn test_bulk_ingest_on_conflict() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("upsert-test", "table");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let items = format!("/collections/{}/items", collection_id);
+
+    let sync = |rows: &[(&str, &str)]| {
+        serde_json::json!({
+            "type": "FeatureCollection",
+            "features": rows
+                .iter()
+                .map(|(id, name)| serde_json::json!({
+                    "type": "Feature",
+                    "geometry": null,
+                    "properties": {"external_id": id, "name": name}
+                }))
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let response = app
+        .post_json(&items, &sync(&[("a", "Alice"), ("b", "Bob")]))
+        .await;
+    response.assert_status(StatusCode::CREATED);
+
+    let upsert = format!(
+        "{}?on-conflict=update&on-conflict-property=external_id",
+        items
+    );
+    let response = app
+        .post_json(
+            &upsert,
+            &sync(&[("a", "Alice"), ("b", "Robert"), ("c", "Carol")]),
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["numberInserted"], 1);
+    assert_eq!(body["numberUpdated"], 1);
+    assert_eq!(body["numberSkipped"], 1);
+
+    let skip = format!(
+        "{}?on-conflict=skip&on-conflict-property=external_id",
+        items
+    );
+    let response = app.post_json(&skip, &sync(&[("c", "Charlie")])).await;
+    response.assert_status(StatusCode::CREATED);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["numberInserted"], 0);
+    assert_eq!(body["numberSkipped"], 1);
+
+    // Duplicate keys within a batch: the last one wins, the others are
+    // skipped
+    let response = app
+        .post_json(
+            &upsert,
+            &sync(&[("b", "Bobby"), ("d", "Dan"), ("b", "Bob"), ("d", "Dana")]),
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["numberInserted"], 1);
+    assert_eq!(body["numberUpdated"], 1);
+    assert_eq!(body["numberSkipped"], 2);
+
+    let response = app.get(&items).await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    let mut names: Vec<&str> = body["features"]
+        .as_array()
+        .expect("Should have features")
+        .iter()
+        .map(|f| f["properties"]["name"].as_str().unwrap_or_default())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["Alice", "Bob", "Carol", "Dana"]);
+
+    // A feature updating several existing features with its key counts once
+    app.post_json(&items, &sync(&[("a", "Alice")]))
+        .await
+        .assert_status(StatusCode::CREATED);
+    let response = app.post_json(&upsert, &sync(&[("a", "Alicia")])).await;
+    response.assert_status(StatusCode::CREATED);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["numberInserted"], 0);
+    assert_eq!(body["numberUpdated"], 1);
+    assert_eq!(body["numberSkipped"], 0);
+}
+
+/// Test that resultType lists only the ids or only the count of matches
+#[tokio::test]
+async fn test_items_result_type() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_json(
+            "/collections",
+            &test_collection_request("result-type-test", "vector"),
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let items = format!("/collections/{}/items", collection_id);
+    for _ in 0..3 {
+        app.post_json(&items, &test_feature_request())
+            .await
+            .assert_status(StatusCode::CREATED);
+    }
+
+    let response = app.get(&format!("{}?resultType=ids&limit=2", items)).await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["ids"].as_array().map(Vec::len), Some(2));
+    assert_eq!(body["numberReturned"], 2);
+    assert!(body.get("features").is_none());
+    let next = body["links"]
+        .as_array()
+        .and_then(|links| links.iter().find(|link| link["rel"] == "next"))
+        .expect("Should have a next link");
+    assert!(next["href"].as_str().unwrap().contains("resultType=ids"));
+
+    let response = app.get(&format!("{}?resultType=hits", items)).await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["numberMatched"], 3);
+    assert_eq!(body["features"].as_array().map(Vec::len), Some(0));
+
+    app.get(&format!("{}?resultType=hits&count=false", items))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    app.get(&format!("{}?resultType=ids&f=csv", items))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test that the declared geometry type is reported and enforced on writes
+#[tokio::test]
+async fn test_collection_geometry_type() {
+    let app = TestApp::new().await;
+
+    let mut collection = test_collection_request("points-test", "vector");
+    collection["geometryType"] = serde_json::json!("Point");
+    let response = app.post_json("/collections", &collection).await;
+    response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    assert_eq!(created["geometryType"], "Point");
+    assert_eq!(created["geometryDimension"], 2);
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let items = format!("/collections/{}/items", collection_id);
+
+    app.post_json(&items, &test_feature_request())
+        .await
+        .assert_status(StatusCode::CREATED);
+    let mut line = test_feature_request();
+    line["geometry"] = serde_json::json!({
+        "type": "LineString",
+        "coordinates": [[0.0, 0.0], [1.0, 1.0]]
+    });
+    app.post_json(&items, &line)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let response = app
+        .get(&format!("/collections/{}/schema", collection_id))
+        .await;
+    let schema: serde_json::Value = response.json();
+    assert_eq!(schema["properties"]["geometry"]["format"], "geometry-point");
+
+    let mut table = test_collection_request("typed-table-test", "table");
+    table["geometryType"] = serde_json::json!("Point");
+    app.post_json("/collections", &table)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test that geometries written with Content-Crs are transformed into the
+/// storage CRS
+#[tokio::test]
+async fn test_write_content_crs() {
+    let app = TestApp::new().await;
+
+    let mut collection = test_collection_request("sweref-test", "vector");
+    collection["crs"] = serde_json::json!(3006);
+    let response = app.post_json("/collections", &collection).await;
+    response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let items = format!("/collections/{}/items", collection_id);
+
+    let mut feature = test_feature_request();
+    feature["geometry"]["coordinates"] = serde_json::json!([18.07, 59.33]);
+    let response = app
+        .request_with_headers(
+            axum::http::Method::POST,
+            &items,
+            feature.to_string(),
+            vec![
+                (header::CONTENT_TYPE, "application/json"),
+                (
+                    header::HeaderName::from_static("content-crs"),
+                    "<http://www.opengis.net/def/crs/OGC/1.3/CRS84>",
+                ),
+            ],
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+
+    // Stored in SWEREF 99 TM, whose eastings and northings are in meters
+    let body: serde_json::Value = app.get(&items).await.json();
+    let coordinates = &body["features"][0]["geometry"]["coordinates"];
+    assert!(coordinates[0].as_f64().unwrap() > 600_000.0);
+    assert!(coordinates[1].as_f64().unwrap() > 6_500_000.0);
+
+    let response = app
+        .request_with_headers(
+            axum::http::Method::POST,
+            &items,
+            feature.to_string(),
+            vec![
+                (header::CONTENT_TYPE, "application/json"),
+                (
+                    header::HeaderName::from_static("content-crs"),
+                    "<urn:unknown>",
+                ),
+            ],
+        )
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test registering assets of an item whose files are already in the bucket
+#[tokio::test]
+async fn test_register_item_assets() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("scenes-test", "raster");
+    let response = app.post_json("/collections", &collection).await;
+    response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    let response = app
+        .post_json(
+            &format!("/collections/{}/items", collection_id),
+            &test_feature_request(),
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let item: serde_json::Value = response.json();
+    let item_id = item["id"].as_str().expect("Item must have id");
+    let batch = format!(
+        "/collections/{}/items/{}/assets:batch",
+        collection_id, item_id
+    );
+
+    let request = serde_json::json!({
+        "assets": {
+            "B04": {
+                "href": "s3://test-bucket/testuser/scenes/B04.tif",
+                "type": "image/tiff; application=geotiff",
+                "roles": ["data"],
+                "proj:epsg": 32633
+            },
+            "thumbnail": {
+                "href": "s3://test-bucket/testuser/scenes/thumb.png",
+                "type": "image/png",
+                "roles": ["thumbnail"]
+            }
+        }
+    });
+    let response = app.post_json(&batch, &request).await;
+    response.assert_status(StatusCode::OK);
+    assert!(response.header("etag").is_some());
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["registered"], 2);
+    assert_eq!(body["assets"]["B04"]["proj:epsg"], 32633);
+    // Assets are only checked against their objects when asked to
+    assert!(body["assets"]["B04"].get("validation").is_none());
+
+    let item: serde_json::Value = app
+        .get(&format!("/collections/{}/items/{}", collection_id, item_id))
+        .await
+        .json();
+    assert_eq!(
+        item["assets"]["thumbnail"]["href"],
+        "s3://test-bucket/testuser/scenes/thumb.png"
+    );
+    assert_eq!(item["assets"]["B04"]["roles"], serde_json::json!(["data"]));
+
+    // One invalid asset rejects the whole batch
+    let request = serde_json::json!({
+        "assets": {
+            "B08": { "href": "s3://test-bucket/testuser/scenes/B08.tif" },
+            "preview": { "href": "https://example.com/preview.png" }
+        }
+    });
+    app.post_json(&batch, &request)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    let item: serde_json::Value = app
+        .get(&format!("/collections/{}/items/{}", collection_id, item_id))
+        .await
+        .json();
+    assert!(item["assets"].get("B08").is_none());
+
+    // Objects of other namespaces and buckets can't be registered
+    let request = serde_json::json!({
+        "assets": { "B08": { "href": "s3://test-bucket/otheruser/scenes/B08.tif" } }
+    });
+    app.post_json(&batch, &request)
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+    let request = serde_json::json!({
+        "assets": { "B08": { "href": "s3://other-bucket/testuser/scenes/B08.tif" } }
+    });
+    app.post_json(&batch, &request)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let request = serde_json::json!({
+        "assets": { "B08": { "href": "s3://test-bucket/testuser/scenes/B08.tif" } }
+    });
+    app.post_json(
+        &format!(
+            "/collections/{}/items/{}/assets:batch",
+            collection_id,
+            uuid::Uuid::new_v4()
+        ),
+        &request,
+    )
+    .await
+    .assert_status(StatusCode::NOT_FOUND);
+}
+
+/// Test that secondary geometry columns are generated from properties and
+/// selectable with geom
+#[tokio::test]
+async fn test_secondary_geometry_columns() {
+    let app = TestApp::new().await;
+
+    let mut collection = test_collection_request("label-test", "vector");
+    collection["geometryColumns"] = serde_json::json!(["label"]);
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let items = format!("/collections/{}/items", collection_id);
+
+    let mut feature = test_feature_request();
+    feature["properties"] = serde_json::json!({
+        "name": "Town hall",
+        "label": {"type": "Point", "coordinates": [11.5, 57.5]}
+    });
+    let response = app.post_json(&items, &feature).await;
+    response.assert_status(StatusCode::CREATED);
+
+    let response = app.get(&format!("{}?geom=label", items)).await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(
+        body["features"][0]["geometry"]["coordinates"],
+        serde_json::json!([11.5, 57.5])
+    );
+
+    let response = app.get(&format!("{}?geom=missing", items)).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let response = app
+        .get(&format!("/collections/{}/schema", collection_id))
+        .await;
+    response.assert_success();
+    assert_eq!(
+        response.header("content-type").as_deref(),
+        Some("application/schema+json")
+    );
+    let schema: serde_json::Value = response.json();
+    assert_eq!(schema["x-geometryColumns"], serde_json::json!(["label"]));
+    assert_eq!(schema["properties"]["label"]["format"], "geometry-any");
+    assert_eq!(schema["properties"]["name"]["type"], "string");
+    assert_eq!(schema["properties"]["id"]["x-ogc-role"], "id");
+    assert_eq!(
+        schema["properties"]["geometry"]["x-ogc-role"],
+        "primary-geometry"
+    );
+    assert!(schema["properties"].get("properties").is_none());
+    assert!(schema["properties"].get("version").is_none());
+    assert!(schema["$id"].as_str().unwrap().starts_with("http"));
+
+    // Receivables leave out what the server maintains
+    let response = app
+        .get(&format!(
+            "/collections/{}/schema?profile=receivables",
+            collection_id
+        ))
+        .await;
+    response.assert_success();
+    let schema: serde_json::Value = response.json();
+    assert!(schema["properties"].get("id").is_none());
+    assert!(schema["properties"].get("label").is_some());
+    assert!(schema["properties"].get("name").is_some());
+}
+
+/// Test restoring a snapshot after a bad edit
+#[tokio::test]
+async fn test_collection_snapshots() {
+    let app = TestApp::new().await;
+
+    let mut collection = test_collection_request("snapshot-test", "vector");
+    collection["geometryColumns"] = serde_json::json!(["label"]);
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let items = format!("/collections/{}/items", collection_id);
+    let snapshots = format!("/collections/{}/snapshots", collection_id);
+
+    let mut feature = test_feature_request();
+    feature["properties"]["label"] =
+        serde_json::json!({"type": "Point", "coordinates": [1.0, 2.0]});
+    let response = app.post_json(&items, &feature).await;
+    response.assert_status(StatusCode::CREATED);
+    let original_etag = response.etag().expect("Feature must have ETag");
+    let original: serde_json::Value = response.json();
+    let feature_path = format!("{}/{}", items, original["id"].as_str().unwrap());
+
+    let response = app
+        .post_json(&snapshots, &serde_json::json!({"name": "before-edit"}))
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    assert!(response.location().is_some());
+    let snapshot: serde_json::Value = response.json();
+    assert_eq!(snapshot["numberOfFeatures"], 1);
+
+    app.post_json(&snapshots, &serde_json::json!({"name": "before-edit"}))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+    app.post_json(&snapshots, &serde_json::json!({"name": "bad/name"}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // A botched bulk edit
+    app.patch_json(
+        &feature_path,
+        &serde_json::json!({"properties": {"name": "Oops"}}),
+        &original_etag,
+    )
+    .await
+    .assert_success();
+    app.post_json(&items, &test_feature_request())
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    let response = app
+        .request_without_etag(
+            axum::http::Method::POST,
+            &format!("{}/before-edit/restore", snapshots),
+        )
+        .await;
+    response.assert_success();
+    assert!(response.etag().is_some());
+
+    let body: serde_json::Value = app.get(&items).await.json();
+    assert_eq!(body["numberMatched"].as_u64(), Some(1));
+    let response = app.get(&feature_path).await;
+    response.assert_success();
+    assert_ne!(response.etag(), Some(original_etag));
+    let restored: serde_json::Value = response.json();
+    assert_eq!(restored["properties"]["name"], "Test Feature");
+
+    // Generated columns are computed again
+    let response = app.get(&format!("{}?geom=label", items)).await;
+    let body: serde_json::Value = response.json();
+    assert_eq!(
+        body["features"][0]["geometry"]["coordinates"],
+        serde_json::json!([1.0, 2.0])
+    );
+
+    let body: serde_json::Value = app.get(&snapshots).await.json();
+    assert_eq!(body["snapshots"].as_array().map(Vec::len), Some(1));
+
+    app.request_without_etag(
+        axum::http::Method::DELETE,
+        &format!("{}/before-edit", snapshots),
+    )
+    .await
+    .assert_status(StatusCode::NO_CONTENT);
+    app.get(&format!("{}/before-edit", snapshots))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+/// Test publishing a collection to a configured remote instance
+#[tokio::test]
+async fn test_collection_replications() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("replication-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let replications = format!("/collections/{}/replications", collection_id);
+
+    let request = serde_json::json!({"remote": "public", "remoteCollection": "public:parcels"});
+    let response = app.post_json(&replications, &request).await;
+    response.assert_status(StatusCode::CREATED);
+    assert!(response.location().is_some());
+    let replication: serde_json::Value = response.json();
+    assert_eq!(replication["remote"], "public");
+    assert!(replication["syncedAt"].is_null());
+    let replication_path = format!(
+        "{}/{}",
+        replications,
+        replication["id"]
+            .as_str()
+            .expect("Replication must have id")
+    );
+
+    app.post_json(&replications, &request)
+        .await
+        .assert_status(StatusCode::CONFLICT);
+    app.post_json(
+        &replications,
+        &serde_json::json!({"remote": "unknown", "remoteCollection": "public:parcels"}),
+    )
+    .await
+    .assert_status(StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = app.get(&replications).await.json();
+    assert_eq!(body["replications"].as_array().map(Vec::len), Some(1));
+
+    // Deleting features of a published collection still works
+    let response = app
+        .post_json(
+            &format!("/collections/{}/items", collection_id),
+            &test_feature_request(),
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let etag = response.etag().expect("Feature must have ETag");
+    let feature: serde_json::Value = response.json();
+    app.delete(
+        &format!(
+            "/collections/{}/items/{}",
+            collection_id,
+            feature["id"].as_str().unwrap()
+        ),
+        &etag,
+    )
+    .await
+    .assert_status(StatusCode::NO_CONTENT);
+
+    // Deletions are recorded however the features are deleted
+    let response = app
+        .post_json(
+            &format!("/collections/{}/items", collection_id),
+            &test_feature_request(),
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let (schema_name, table_name): (String, String) = sqlx::query_as(
+        "SELECT schema_name, table_name FROM spatialvault.collections WHERE id = $1::uuid",
+    )
+    .bind(collection_id)
+    .fetch_one(app.db.pool())
+    .await
+    .expect("Failed to look up the feature table");
+    sqlx::query(&format!(
+        "DELETE FROM \"{}\".\"{}\"",
+        schema_name, table_name
+    ))
+    .execute(app.db.pool())
+    .await
+    .expect("Failed to delete the features");
+    let deletions: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM spatialvault.feature_deletions WHERE collection_id = $1::uuid",
+    )
+    .bind(collection_id)
+    .fetch_one(app.db.pool())
+    .await
+    .expect("Failed to count deletions");
+    assert_eq!(deletions, 2);
+
+    app.request_without_etag(axum::http::Method::DELETE, &replication_path)
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    app.get(&replication_path)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+/// Test creating a scratch collection that expires
+#[tokio::test]
+async fn test_collection_expiry() {
+    let app = TestApp::new().await;
+
+    let mut collection = test_collection_request("scratch-test", "vector");
+    collection["expiresIn"] = serde_json::json!(3600);
+    let response = app.post_json("/collections", &collection).await;
+    response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    assert!(created["expires"].is_string());
+
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let fetched: serde_json::Value = app
+        .get(&format!("/collections/{}", collection_id))
+        .await
+        .json();
+    assert_eq!(fetched["expires"], created["expires"]);
+
+    let mut invalid = test_collection_request("scratch-invalid", "vector");
+    invalid["expiresIn"] = serde_json::json!(0);
+    app.post_json("/collections", &invalid)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test that writing features sets when the data was last changed, served
+/// as Last-Modified of the items listing
+#[tokio::test]
+async fn test_collection_data_updated() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_json(
+            "/collections",
+            &test_collection_request("data-updated-test", "vector"),
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    assert!(created["dataUpdated"].is_null());
+
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let items = format!("/collections/{}/items", collection_id);
+    app.post_json(&items, &test_feature_request())
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    let fetched: serde_json::Value = app
+        .get(&format!("/collections/{}", collection_id))
+        .await
+        .json();
+    assert!(fetched["dataUpdated"].is_string());
+
+    let response = app.get(&items).await;
+    response.assert_success();
+    let last_modified = response
+        .header("last-modified")
+        .expect("Items listing should have Last-Modified");
+
+    app.get_with_headers(&items, vec![(header::IF_MODIFIED_SINCE, &last_modified)])
+        .await
+        .assert_status(StatusCode::NOT_MODIFIED);
+    app.get_with_headers(
+        &items,
+        vec![(header::IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:00 GMT")],
+    )
+    .await
+    .assert_success();
+}
+
+/// Test that metadata and schema changes are listed as events and that
+/// webhooks can be registered for them
+#[tokio::test]
+async fn test_collection_events() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("events-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let etag = create_response.etag().expect("Should have ETag");
+    let events = format!("/collections/{}/events", collection_id);
+
+    app.patch_json(
+        &format!("/collections/{}", collection_id),
+        &serde_json::json!({"title": "Renamed"}),
+        &etag,
+    )
+    .await
+    .assert_success();
+    app.patch_json_without_etag(
+        &format!("/collections/{}/schema", collection_id),
+        &serde_json::json!({"x-uniqueProperties": ["code"]}),
+    )
+    .await
+    .assert_success();
+
+    // Feature changes are not events
+    app.post_json(
+        &format!("/collections/{}/items", collection_id),
+        &test_feature_request(),
+    )
+    .await
+    .assert_status(StatusCode::CREATED);
+
+    let body: serde_json::Value = app.get(&events).await.json();
+    let listed = body["events"].as_array().unwrap();
+    let types: Vec<&str> = listed.iter().filter_map(|e| e["type"].as_str()).collect();
+    assert_eq!(types, vec!["metadata", "schema"]);
+
+    let first = listed[0]["id"].as_i64().unwrap();
+    let body: serde_json::Value = app.get(&format!("{}?after={}", events, first)).await.json();
+    assert_eq!(body["events"].as_array().unwrap().len(), 1);
+
+    let webhooks = format!("/collections/{}/webhooks", collection_id);
+    let request = serde_json::json!({"url": "https://hooks.invalid/schema", "secret": "s3cret"});
+    let response = app.post_json(&webhooks, &request).await;
+    response.assert_status(StatusCode::CREATED);
+    let webhook: serde_json::Value = response.json();
+    assert_eq!(webhook["signed"], true);
+    assert!(webhook.get("secret").is_none());
+    app.post_json(&webhooks, &request)
+        .await
+        .assert_status(StatusCode::CONFLICT);
+    app.post_json(
+        &webhooks,
+        &serde_json::json!({"url": "http://hooks.invalid"}),
+    )
+    .await
+    .assert_status(StatusCode::BAD_REQUEST);
+    for url in [
+        "https://169.254.169.254/latest",
+        "https://localhost:8080/hook",
+    ] {
+        app.post_json(&webhooks, &serde_json::json!({ "url": url }))
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    let webhook_path = format!("{}/{}", webhooks, webhook["id"].as_str().unwrap());
+    app.request_without_etag(axum::http::Method::DELETE, &webhook_path)
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    app.get(&webhook_path)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_read_only_maintenance() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_json(
+            "/collections",
+            &test_collection_request("read-only-test", "vector"),
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let items = format!("/collections/{}/items", collection_id);
+
+    sqlx::query("UPDATE spatialvault.maintenance SET read_only = TRUE, reason = 'upgrade'")
+        .execute(app.db.pool())
+        .await
+        .expect("Failed to switch to read-only");
+
+    let response = app.post_json(&items, &test_feature_request()).await;
+    response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.text().contains("upgrade"));
+    // Background writers wait too
+    assert!(spatialvault::api::read_only::paused(&app.db).await);
+
+    // Reads, including searches taking a POST, keep working
+    app.get(&items).await.assert_success();
+    app.post_json("/stac/search", &serde_json::json!({ "limit": 1 }))
+        .await
+        .assert_success();
+
+    sqlx::query("UPDATE spatialvault.maintenance SET read_only = FALSE, reason = NULL")
+        .execute(app.db.pool())
+        .await
+        .expect("Failed to switch back");
+    assert!(!spatialvault::api::read_only::paused(&app.db).await);
+    app.post_json(&items, &test_feature_request())
+        .await
+        .assert_status(StatusCode::CREATED);
+}
@@ -311,3 +311,56 @@ async fn test_alias_redirect_on_sharing_endpoint() {
         );
     }
 }
+
+/// Test that alias redirects keep the rest of the path and the query string
+#[tokio::test]
+async fn test_alias_redirect_preserves_path_and_query() {
+    let app = TestApp::new().await;
+
+    // Create a collection
+    let collection = test_collection_request("alias-query-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    // Rename the collection
+    let etag = create_response.etag().expect("Should have ETag");
+    let rename = serde_json::json!({
+        "id": "alias-query-renamed",
+    });
+    let rename_response = app
+        .patch_json(&format!("/collections/{}", collection_id), &rename, &etag)
+        .await;
+
+    // If rename is supported
+    if rename_response.status == StatusCode::OK {
+        let renamed: serde_json::Value = rename_response.json();
+        let new_id = renamed["id"].as_str().expect("Should have new id");
+        let old_name = collection_id.split(':').last().unwrap();
+
+        let tile_response = app
+            .get(&format!(
+                "/collections/{}/tiles/WebMercatorQuad/0/0/0?f=png",
+                old_name
+            ))
+            .await;
+        assert_eq!(tile_response.status, StatusCode::TEMPORARY_REDIRECT);
+        let location = tile_response
+            .location()
+            .expect("Should have Location header");
+        assert!(
+            location.ends_with(&format!(
+                "/collections/{}/tiles/WebMercatorQuad/0/0/0?f=png",
+                new_id
+            )),
+            "Unexpected Location header: {}",
+            location
+        );
+    }
+
+    // Unknown collections are rejected before reaching the handler
+    app.get("/collections/no-such-collection/sharing")
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
@@ -1,6 +1,6 @@
 //! Sharing and permissions integration tests
 
-use crate::common::{TestApp, test_collection_request};
+use crate::common::{MockAuthState, TestApp, test_collection_request, test_feature_request};
 use axum::http::StatusCode;
 
 /// Test listing shares for a collection
@@ -134,3 +134,108 @@ async fn test_remove_share() {
         .any(|s| s["principal"].as_str() == Some("shareuser"));
     assert!(!has_share, "Share should be removed");
 }
+
+/// Test notification settings default to nothing and need a notification service
+#[tokio::test]
+async fn test_notification_settings() {
+    let app = TestApp::new().await;
+
+    let response = app.get("/notifications/settings").await;
+    response.assert_success();
+
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["collectionShared"], false);
+    assert_eq!(body["jobFinished"], false);
+
+    // The test configuration has no notification service
+    let response = app
+        .request_with_headers(
+            axum::http::Method::PUT,
+            "/notifications/settings",
+            r#"{"collectionShared": true}"#.to_string(),
+            vec![(axum::http::header::CONTENT_TYPE, "application/json")],
+        )
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test that readers of a shared collection can't filter on relations to
+/// collections that aren't shared with them
+#[tokio::test]
+async fn test_shared_collection_relations_need_read_access() {
+    let app = TestApp::new().await;
+    app.ensure_role_exists("relationuser").await;
+
+    let mut ids = Vec::new();
+    for name in ["relation-owners", "relation-parcels"] {
+        let response = app
+            .post_json("/collections", &test_collection_request(name, "vector"))
+            .await;
+        response.assert_status(StatusCode::CREATED);
+        let created: serde_json::Value = response.json();
+        ids.push(
+            created["id"]
+                .as_str()
+                .expect("Collection must have id")
+                .to_string(),
+        );
+    }
+    let (owners, parcels) = (&ids[0], &ids[1]);
+
+    let mut owner = test_feature_request();
+    owner["properties"] = serde_json::json!({ "name": "Alice" });
+    app.post_json(&format!("/collections/{}/items", owners), &owner)
+        .await
+        .assert_status(StatusCode::CREATED);
+    let mut parcel = test_feature_request();
+    parcel["properties"] = serde_json::json!({ "owner_name": "Alice" });
+    app.post_json(&format!("/collections/{}/items", parcels), &parcel)
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    let relations = serde_json::json!({
+        "relations": [{
+            "name": "owner",
+            "collection": owners,
+            "property": "owner_name",
+            "references": "name"
+        }]
+    });
+    app.request_with_headers(
+        axum::http::Method::PUT,
+        &format!("/collections/{}/relations", parcels),
+        relations.to_string(),
+        vec![(axum::http::header::CONTENT_TYPE, "application/json")],
+    )
+    .await
+    .assert_success();
+    app.post_json(
+        &format!("/collections/{}/sharing", parcels),
+        &serde_json::json!({
+            "principal": "relationuser",
+            "principal_type": "user",
+            "permission": "read"
+        }),
+    )
+    .await
+    .assert_status(StatusCode::CREATED);
+
+    let filtered = format!(
+        "/collections/{}/items?filter=owner.name%20%3D%20%27Alice%27",
+        parcels
+    );
+    let response = app.get(&filtered).await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["features"].as_array().map(Vec::len), Some(1));
+
+    let reader = TestApp::builder()
+        .auth(MockAuthState::with_username("relationuser"))
+        .database_url(app.config.database.url.clone())
+        .start()
+        .await;
+    let response = reader.get(&filtered).await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["features"].as_array().map(Vec::len), Some(0));
+}
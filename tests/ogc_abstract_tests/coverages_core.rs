@@ -122,3 +122,201 @@ async fn test_coverage_rejects_vector_collection() {
     // Should return 400 Bad Request for non-raster collection
     response.assert_status(StatusCode::BAD_REQUEST);
 }
+
+/// Test coverage data requests with a range subset
+#[tokio::test]
+async fn test_coverage_range_subset() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("range-subset-test", "raster");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    // The description is still returned without a data request
+    let response = app
+        .get("/collections/range-subset-test/coverage?f=json")
+        .await;
+    response.assert_success();
+    response.assert_content_type("application/json");
+
+    // No items to read bands from
+    let response = app
+        .get("/collections/range-subset-test/coverage?properties=band1")
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+    let response = app
+        .get("/collections/range-subset-test/coverage?rangeSubset=band1")
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+
+    // Windows are data requests too
+    let response = app
+        .get("/collections/range-subset-test/coverage?subset=Lat(40:50)&scale-factor=2")
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+
+    // Data is only returned in its native CRS
+    let response = app
+        .get("/collections/range-subset-test/coverage?properties=band1&crs=EPSG:3857")
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test selecting time slices of coverage data
+#[tokio::test]
+async fn test_coverage_datetime() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("coverage-datetime-test", "raster");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    // Without dated items there is no time axis
+    let response = app
+        .get("/collections/coverage-datetime-test/coverage/domainset")
+        .await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert!(
+        !body["generalGrid"]["axisLabels"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!("time"))
+    );
+
+    let response = app
+        .get("/collections/coverage-datetime-test/coverage?datetime=2024-05-01T00:00:00Z")
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+    let response = app
+        .get("/collections/coverage-datetime-test/coverage?datetime=yesterday")
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test MosaicJSON documents of raster collections
+#[tokio::test]
+async fn test_mosaic_json() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("mosaic-test", "raster");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let response = app
+        .get("/collections/mosaic-test/mosaic.json?minzoom=2&maxzoom=14")
+        .await;
+    response.assert_success();
+    response.assert_content_type("application/json");
+    assert!(response.etag().is_some());
+
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["mosaicjson"], "0.0.3");
+    assert_eq!(body["minzoom"], 2);
+    assert_eq!(body["maxzoom"], 14);
+    assert_eq!(body["quadkey_zoom"], 2);
+    assert_eq!(body["tiles"], serde_json::json!({}));
+
+    let response = app
+        .get("/collections/mosaic-test/mosaic.json?minzoom=8&quadkey_zoom=4")
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let vector = test_collection_request("vector-mosaic-test", "vector");
+    app.post_json("/collections", &vector)
+        .await
+        .assert_status(StatusCode::CREATED);
+    let response = app.get("/collections/vector-mosaic-test/mosaic.json").await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test previews of raster coverages
+#[tokio::test]
+async fn test_coverage_preview() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("coverage-preview-test", "raster");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let response = app.get("/collections/coverage-preview-test/coverage").await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    let links = body["links"].as_array().expect("links must be an array");
+    assert!(links.iter().any(|link| link["rel"] == "preview"));
+
+    let response = app
+        .get("/collections/coverage-preview-test/coverage/preview")
+        .await;
+    response.assert_success();
+    response.assert_content_type("image/png");
+    assert!(response.etag().is_some());
+
+    let response = app
+        .get("/collections/coverage-preview-test/coverage/preview?f=webp&colormap_name=viridis")
+        .await;
+    response.assert_success();
+    response.assert_content_type("image/webp");
+
+    let response = app
+        .get("/collections/coverage-preview-test/coverage/preview?rescale=10,0")
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let vector = test_collection_request("vector-preview-test", "vector");
+    app.post_json("/collections", &vector)
+        .await
+        .assert_status(StatusCode::CREATED);
+    let response = app
+        .get("/collections/vector-preview-test/coverage/preview")
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test EDR position and area queries validate their parameters
+#[tokio::test]
+async fn test_edr_query_validation() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("edr-test", "raster");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    for uri in [
+        "/collections/edr-test/position",
+        "/collections/edr-test/position?coords=POINT(200%200)",
+        "/collections/edr-test/position?coords=POLYGON((0%200,1%200,1%201,0%200))",
+        "/collections/edr-test/position?coords=POINT(1%202)&datetime=yesterday",
+        "/collections/edr-test/position?coords=POINT(1%202)&parameter-name=temperature",
+        "/collections/edr-test/area?coords=POINT(1%202)",
+        "/collections/edr-test/area?coords=POLYGON((0%200,1%200,1%201))",
+    ] {
+        let response = app.get(uri).await;
+        assert_eq!(
+            response.status,
+            StatusCode::BAD_REQUEST,
+            "{} should be rejected",
+            uri
+        );
+    }
+
+    // No items cover the location
+    let response = app
+        .get("/collections/edr-test/position?coords=POINT(1%202)")
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+    let response = app
+        .get("/collections/edr-test/area?coords=POLYGON((0%200,1%200,1%201,0%200))")
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+
+    // Only raster collections can be queried
+    let vector = test_collection_request("vector-edr-test", "vector");
+    app.post_json("/collections", &vector)
+        .await
+        .assert_status(StatusCode::CREATED);
+    let response = app
+        .get("/collections/vector-edr-test/position?coords=POINT(1%202)")
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
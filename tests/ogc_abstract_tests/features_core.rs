@@ -6,7 +6,7 @@
 //! Tests use TestApp with testcontainers for the database and mock authentication.
 
 use crate::common::{TestApp, assert_has_link, test_collection_request, test_feature_request};
-use axum::http::StatusCode;
+use axum::http::{HeaderName, Method, StatusCode, header};
 
 /// A.2.1: Landing page response
 #[tokio::test]
@@ -61,6 +61,103 @@ async fn api_definition_retrieval() {
     assert!(body["paths"].is_object(), "Missing paths object");
 }
 
+/// The API definition is also available as YAML and as OpenAPI 3.1
+#[tokio::test]
+async fn api_definition_formats() {
+    let app = TestApp::new().await;
+
+    let response = app.get("/api?f=yaml").await;
+    response.assert_success();
+    response.assert_content_type("application/yaml");
+    assert!(response.text().starts_with("openapi: 3.0.3"));
+
+    let response = app
+        .get_with_headers(
+            "/api",
+            vec![(
+                axum::http::header::ACCEPT,
+                "application/vnd.oai.openapi+json;version=3.1",
+            )],
+        )
+        .await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["openapi"].as_str(), Some("3.1.0"));
+
+    app.get("/api?f=xml")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// The API console is served as an HTML page pointing at the definition
+#[tokio::test]
+async fn api_console() {
+    let app = TestApp::new().await;
+
+    let response = app.get("/docs").await;
+    response.assert_success();
+    response.assert_content_type("text/html");
+    assert!(response.text().contains("api?version=3.1"));
+}
+
+/// OPTIONS reports the methods of a resource, as does a 405 response
+#[tokio::test]
+async fn allowed_methods() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .request_without_etag(axum::http::Method::OPTIONS, "/collections")
+        .await;
+    response.assert_status(StatusCode::NO_CONTENT);
+    let allow = response.header("allow").expect("Allow header");
+    for method in ["GET", "POST", "OPTIONS"] {
+        assert!(allow.contains(method), "{} missing from {}", method, allow);
+    }
+
+    let response = app
+        .request_without_etag(axum::http::Method::DELETE, "/collections")
+        .await;
+    response.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+    let allow = response.header("allow").expect("Allow header");
+    assert!(allow.contains("POST") && !allow.contains("DELETE"));
+}
+
+/// HEAD on a feature returns its headers without the body
+#[tokio::test]
+async fn feature_head() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("head-feature-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().unwrap();
+
+    let create_response = app
+        .post_json(
+            &format!("/collections/{}/items", collection_id),
+            &test_feature_request(),
+        )
+        .await;
+    create_response.assert_status(StatusCode::CREATED);
+    let feature: serde_json::Value = create_response.json();
+    let feature_id = feature["id"].as_str().unwrap();
+    let uri = format!("/collections/{}/items/{}", collection_id, feature_id);
+
+    let get_response = app.get(&uri).await;
+    let response = app
+        .request_without_etag(axum::http::Method::HEAD, &uri)
+        .await;
+    response.assert_success();
+    assert!(response.text().is_empty());
+    assert_eq!(response.etag(), get_response.etag());
+    assert!(response.header("content-crs").is_some());
+    assert_eq!(
+        response.header("content-length"),
+        Some(get_response.text().len().to_string())
+    );
+}
+
 /// A.2.3: Conformance declaration
 #[tokio::test]
 async fn conformance_declaration() {
@@ -108,6 +205,51 @@ async fn collections_metadata() {
     assert!(assert_has_link(links, "self"), "Missing self link");
 }
 
+/// Collections listing reports numberMatched and pages with next/prev links
+#[tokio::test]
+async fn collections_paging() {
+    let app = TestApp::new().await;
+
+    for id in ["paging-a", "paging-b", "paging-c"] {
+        let mut collection = test_collection_request(id, "vector");
+        collection["keywords"] = serde_json::json!(["collections-paging"]);
+        app.post_json("/collections", &collection)
+            .await
+            .assert_status(StatusCode::CREATED);
+    }
+
+    let response = app
+        .get("/collections?keyword=collections-paging&limit=2")
+        .await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["numberMatched"].as_u64(), Some(3));
+    assert_eq!(body["numberReturned"].as_u64(), Some(2));
+    let links = body["links"].as_array().expect("links must be an array");
+    assert!(assert_has_link(links, "next"), "Missing next link");
+    assert!(
+        !assert_has_link(links, "prev"),
+        "First page has no prev link"
+    );
+
+    let response = app
+        .get("/collections?keyword=collections-paging&limit=2&offset=2")
+        .await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["numberReturned"].as_u64(), Some(1));
+    let links = body["links"].as_array().expect("links must be an array");
+    assert!(assert_has_link(links, "prev"), "Missing prev link");
+    assert!(
+        !assert_has_link(links, "next"),
+        "Last page has no next link"
+    );
+
+    app.get("/collections?limit=0")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
 /// A.2.5-7: Full CRUD workflow with features
 #[tokio::test]
 async fn features_crud_workflow() {
@@ -223,6 +365,398 @@ async fn features_crud_workflow() {
     assert_eq!(features.len(), 1, "Should have one feature");
 }
 
+/// numberMatched can be omitted with count=false or Prefer: return=minimal
+#[tokio::test]
+async fn number_matched_control() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("count-features", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().unwrap();
+    let items = format!("/collections/{}/items", collection_id);
+
+    app.post_json(&items, &test_feature_request())
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    // Small collections are counted exactly by default
+    let body: serde_json::Value = app.get(&items).await.json();
+    assert_eq!(body["numberMatched"].as_u64(), Some(1));
+
+    let response = app.get(&format!("{}?count=false", items)).await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert!(body.get("numberMatched").is_none());
+    assert_eq!(body["numberReturned"].as_u64(), Some(1));
+
+    let response = app
+        .get_with_headers(
+            &items,
+            vec![(HeaderName::from_static("prefer"), "return=minimal")],
+        )
+        .await;
+    response.assert_success();
+    assert_eq!(
+        response.header("preference-applied").as_deref(),
+        Some("return=minimal")
+    );
+    let body: serde_json::Value = response.json();
+    assert!(body.get("numberMatched").is_none());
+
+    // An explicit count wins over the preference
+    let response = app
+        .get_with_headers(
+            &format!("{}?count=true", items),
+            vec![(HeaderName::from_static("prefer"), "return=minimal")],
+        )
+        .await;
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["numberMatched"].as_u64(), Some(1));
+}
+
+/// Collections can override the default and maximum page size
+#[tokio::test]
+async fn collection_page_limits() {
+    let app = TestApp::new().await;
+
+    let mut collection = test_collection_request("limit-features", "vector");
+    collection["defaultLimit"] = serde_json::json!(1);
+    collection["maxLimit"] = serde_json::json!(20000);
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    assert_eq!(created["maxLimit"].as_u64(), Some(20000));
+    let collection_id = created["id"].as_str().unwrap();
+    let items = format!("/collections/{}/items", collection_id);
+
+    for _ in 0..2 {
+        app.post_json(&items, &test_feature_request())
+            .await
+            .assert_status(StatusCode::CREATED);
+    }
+
+    // The collection's default page size applies without a limit
+    let body: serde_json::Value = app.get(&items).await.json();
+    assert_eq!(body["numberReturned"].as_u64(), Some(1));
+
+    // Pages beyond the server maximum are allowed for this collection
+    app.get(&format!("{}?limit=15000", items))
+        .await
+        .assert_success();
+    app.get(&format!("{}?limit=20001", items))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Invalid overrides are rejected
+    let mut invalid = test_collection_request("bad-limits", "vector");
+    invalid["defaultLimit"] = serde_json::json!(100);
+    invalid["maxLimit"] = serde_json::json!(50);
+    app.post_json("/collections", &invalid)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Also against the stored maximum
+    let collection_path = format!("/collections/{}", collection_id);
+    let etag = app
+        .get(&collection_path)
+        .await
+        .etag()
+        .expect("Collection must have ETag");
+    app.patch_json(
+        &collection_path,
+        &serde_json::json!({ "defaultLimit": 20001 }),
+        &etag,
+    )
+    .await
+    .assert_status(StatusCode::BAD_REQUEST);
+
+    // null reverts to the server default page size
+    let response = app
+        .patch_json(
+            &collection_path,
+            &serde_json::json!({ "defaultLimit": null }),
+            &etag,
+        )
+        .await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert!(body.get("defaultLimit").is_none());
+    assert_eq!(body["maxLimit"].as_u64(), Some(20000));
+    let body: serde_json::Value = app.get(&items).await.json();
+    assert_eq!(body["numberReturned"].as_u64(), Some(2));
+}
+
+/// Items can be fetched by a list of ids
+#[tokio::test]
+async fn items_by_ids() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("ids-features", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().unwrap();
+    let items = format!("/collections/{}/items", collection_id);
+
+    let mut ids = Vec::new();
+    for _ in 0..3 {
+        let response = app.post_json(&items, &test_feature_request()).await;
+        response.assert_status(StatusCode::CREATED);
+        let feature: serde_json::Value = response.json();
+        ids.push(feature["id"].as_str().unwrap().to_string());
+    }
+
+    let response = app
+        .get(&format!("{}?ids={},{}", items, ids[0], ids[2]))
+        .await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    let mut returned: Vec<&str> = body["features"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|f| f["id"].as_str())
+        .collect();
+    returned.sort();
+    let mut expected = vec![ids[0].as_str(), ids[2].as_str()];
+    expected.sort();
+    assert_eq!(returned, expected);
+    assert_eq!(body["numberMatched"].as_u64(), Some(2));
+
+    app.get(&format!("{}?ids=not-a-uuid", items))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Items can be filtered by a GeoJSON geometry with intersects
+#[tokio::test]
+async fn items_intersects() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("intersects-features", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().unwrap();
+    let items = format!("/collections/{}/items", collection_id);
+
+    let mut ids = Vec::new();
+    for x in [0.0, 10.0] {
+        let mut feature = test_feature_request();
+        feature["geometry"]["coordinates"] = serde_json::json!([x, x]);
+        let response = app.post_json(&items, &feature).await;
+        response.assert_status(StatusCode::CREATED);
+        let feature: serde_json::Value = response.json();
+        ids.push(feature["id"].as_str().unwrap().to_string());
+    }
+
+    // A triangle around the first point only
+    let polygon = r#"{"type":"Polygon","coordinates":[[[-1,-1],[2,-1],[-1,2],[-1,-1]]]}"#;
+    let encoded = polygon
+        .replace('{', "%7B")
+        .replace('}', "%7D")
+        .replace('[', "%5B")
+        .replace(']', "%5D")
+        .replace('"', "%22");
+    let response = app.get(&format!("{}?intersects={}", items, encoded)).await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    let features = body["features"].as_array().unwrap();
+    assert_eq!(features.len(), 1);
+    assert_eq!(features[0]["id"].as_str(), Some(ids[0].as_str()));
+
+    app.get(&format!("{}?intersects={}&bbox=0,0,1,1", items, encoded))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    app.get(&format!("{}?intersects=%7B%7D", items))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Items can be filtered by their distance in meters from a point
+#[tokio::test]
+async fn items_within_distance() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("distance-features", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().unwrap();
+    let items = format!("/collections/{}/items", collection_id);
+
+    // About 330 m and 3.3 km north of the origin
+    for lat in [0.003, 0.03] {
+        let mut feature = test_feature_request();
+        feature["geometry"]["coordinates"] = serde_json::json!([0.0, lat]);
+        app.post_json(&items, &feature)
+            .await
+            .assert_status(StatusCode::CREATED);
+    }
+
+    let response = app.get(&format!("{}?within-distance=0,0,500", items)).await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["features"].as_array().unwrap().len(), 1);
+
+    let response = app
+        .get(&format!("{}?within-distance=0,0,5000", items))
+        .await;
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["features"].as_array().unwrap().len(), 2);
+
+    app.get(&format!("{}?within-distance=0,0", items))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Returned geometries can be clipped to the requested bbox
+#[tokio::test]
+async fn items_clip() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("clip-features", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().unwrap();
+    let items = format!("/collections/{}/items", collection_id);
+
+    let mut feature = test_feature_request();
+    feature["geometry"] = serde_json::json!({
+        "type": "LineString",
+        "coordinates": [[-10.0, 0.5], [10.0, 0.5]]
+    });
+    app.post_json(&items, &feature)
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    let response = app.get(&format!("{}?bbox=0,0,1,1&clip=true", items)).await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    let coordinates = body["features"][0]["geometry"]["coordinates"]
+        .as_array()
+        .unwrap();
+    let xs: Vec<f64> = coordinates.iter().filter_map(|c| c[0].as_f64()).collect();
+    assert!(xs.iter().all(|x| (0.0..=1.0).contains(x)), "{:?}", xs);
+
+    let response = app.get(&format!("{}?bbox=0,0,1,1", items)).await;
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["features"][0]["geometry"]["coordinates"][0][0], -10.0);
+
+    app.get(&format!("{}?clip=true", items))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Items and exports can be returned as newline-delimited GeoJSON
+#[tokio::test]
+async fn items_ndjson() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("ndjson-features", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().unwrap();
+    let items = format!("/collections/{}/items", collection_id);
+
+    for _ in 0..3 {
+        app.post_json(&items, &test_feature_request())
+            .await
+            .assert_status(StatusCode::CREATED);
+    }
+
+    let response = app.get(&format!("{}?f=ndjson&limit=2", items)).await;
+    response.assert_success();
+    response.assert_content_type("application/x-ndjson");
+    assert!(response.header("link").unwrap().contains("rel=\"next\""));
+    let text = response.text();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 2);
+    for line in lines {
+        let feature: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(feature["type"], "Feature");
+    }
+
+    let response = app
+        .get(&format!("/collections/{}/export?f=ndjson", collection_id))
+        .await;
+    response.assert_success();
+    response.assert_content_type("application/x-ndjson");
+    assert_eq!(response.text().lines().count(), 3);
+
+    // Exports read the features in pages, which they must all span
+    let (schema_name, table_name): (String, String) = sqlx::query_as(
+        "SELECT schema_name, table_name FROM spatialvault.collections WHERE id = $1::uuid",
+    )
+    .bind(collection_id)
+    .fetch_one(app.db.pool())
+    .await
+    .expect("Failed to look up the feature table");
+    sqlx::query(&format!(
+        "INSERT INTO \"{0}\".\"{1}\" (geometry, properties) \
+         SELECT geometry, jsonb_build_object('n', n) \
+         FROM (SELECT geometry FROM \"{0}\".\"{1}\" LIMIT 1) f, generate_series(1, 1000) n",
+        schema_name, table_name
+    ))
+    .execute(app.db.pool())
+    .await
+    .expect("Failed to add features");
+    let response = app
+        .get(&format!("/collections/{}/export?f=ndjson", collection_id))
+        .await;
+    response.assert_success();
+    let text = response.text();
+    let ids: std::collections::HashSet<String> = text
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["id"].to_string())
+        .collect();
+    assert_eq!(ids.len(), 1003);
+}
+
+/// Shapefile exports are written to a file and streamed from it
+#[tokio::test]
+async fn export_shapefile() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("shapefile-export", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().unwrap();
+    for _ in 0..2 {
+        app.post_json(
+            &format!("/collections/{}/items", collection_id),
+            &test_feature_request(),
+        )
+        .await
+        .assert_status(StatusCode::CREATED);
+    }
+
+    let response = app
+        .get(&format!(
+            "/collections/{}/export?f=shapefile",
+            collection_id
+        ))
+        .await;
+    response.assert_success();
+    response.assert_content_type("application/zip");
+    assert!(response.body.starts_with(b"PK\x03\x04"));
+    assert_eq!(
+        response.header("content-length"),
+        Some(response.body.len().to_string())
+    );
+    assert!(
+        response
+            .header("content-disposition")
+            .unwrap()
+            .ends_with(".zip\"")
+    );
+}
+
 /// A.2.8: Link headers and relations
 #[tokio::test]
 async fn link_headers_and_relations() {
@@ -248,3 +782,308 @@ async fn link_headers_and_relations() {
         "Self link should have type"
     );
 }
+
+/// Feature responses are compressed with the best encoding the client accepts
+#[tokio::test]
+async fn response_compression() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("compression-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let items = format!("/collections/{}/items", collection_id);
+
+    for _ in 0..5 {
+        app.post_json(&items, &test_feature_request())
+            .await
+            .assert_status(StatusCode::CREATED);
+    }
+
+    for encoding in ["br", "zstd", "gzip"] {
+        let response = app
+            .get_with_headers(&items, vec![(header::ACCEPT_ENCODING, encoding)])
+            .await;
+        response.assert_success();
+        assert_eq!(
+            response.header("content-encoding").as_deref(),
+            Some(encoding)
+        );
+    }
+
+    // Without Accept-Encoding the response is sent as is
+    let response = app.get(&items).await;
+    assert!(response.header("content-encoding").is_none());
+}
+
+/// Feature writes with If-Match fail once the collection has changed
+#[tokio::test]
+async fn conditional_feature_writes() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("conditional-write-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let collection_uri = format!("/collections/{}", collection_id);
+    let items = format!("{}/items", collection_uri);
+
+    let etag = app
+        .get(&collection_uri)
+        .await
+        .etag()
+        .expect("Collection must have an ETag");
+
+    let response = app
+        .request_with_headers(
+            Method::POST,
+            &items,
+            test_feature_request().to_string(),
+            vec![
+                (header::CONTENT_TYPE, "application/geo+json"),
+                (header::IF_MATCH, etag.as_str()),
+            ],
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+
+    // The write changed the collection, so its old ETag no longer matches
+    let feature_collection = serde_json::json!({
+        "type": "FeatureCollection",
+        "features": [test_feature_request(), test_feature_request()]
+    });
+    let response = app
+        .request_with_headers(
+            Method::POST,
+            &items,
+            feature_collection.to_string(),
+            vec![
+                (header::CONTENT_TYPE, "application/geo+json"),
+                (header::IF_MATCH, etag.as_str()),
+            ],
+        )
+        .await;
+    response.assert_status(StatusCode::PRECONDITION_FAILED);
+
+    let body: serde_json::Value = app.get(&items).await.json();
+    assert_eq!(body["numberMatched"].as_u64(), Some(1));
+
+    let etag = app
+        .get(&collection_uri)
+        .await
+        .etag()
+        .expect("Collection must have an ETag");
+    let response = app
+        .request_with_headers(
+            Method::POST,
+            &items,
+            feature_collection.to_string(),
+            vec![
+                (header::CONTENT_TYPE, "application/geo+json"),
+                (header::IF_MATCH, etag.as_str()),
+            ],
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["numberInserted"].as_u64(), Some(2));
+}
+
+/// Validate-only writes report what would happen without changing anything
+#[tokio::test]
+async fn validate_only_writes() {
+    let app = TestApp::new().await;
+
+    let mut collection = test_collection_request("validate-only-test", "vector");
+    collection["uniqueProperties"] = serde_json::json!(["code"]);
+    let response = app
+        .request_with_headers(
+            Method::POST,
+            "/collections",
+            collection.to_string(),
+            vec![
+                (header::CONTENT_TYPE, "application/json"),
+                (HeaderName::from_static("prefer"), "handling=validate-only"),
+            ],
+        )
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(
+        response.header("preference-applied").as_deref(),
+        Some("handling=validate-only")
+    );
+    let validated: serde_json::Value = response.json();
+    let collection_id = validated["id"].as_str().expect("Collection must have id");
+    app.get(&format!("/collections/{}", collection_id))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let items = format!("/collections/{}/items", collection_id);
+
+    let mut feature = test_feature_request();
+    feature["properties"] = serde_json::json!({"code": "A1"});
+    let response = app
+        .post_json(&format!("{}?dryRun=true", items), &feature)
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert!(response.location().is_none());
+    let body: serde_json::Value = app.get(&items).await.json();
+    assert_eq!(body["numberMatched"].as_u64(), Some(0));
+
+    // Database constraints are checked like for a real write
+    app.post_json(&items, &feature)
+        .await
+        .assert_status(StatusCode::CREATED);
+    app.post_json(&format!("{}?dryRun=true", items), &feature)
+        .await
+        .assert_status(StatusCode::CONFLICT);
+}
+
+/// Test locking a feature, renewing and releasing the lock
+#[tokio::test]
+async fn feature_locks() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("feature-lock-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let body: serde_json::Value = create_response.json();
+    let collection_id = body["id"].as_str().expect("Collection must have id");
+
+    let feature_response = app
+        .post_json(
+            &format!("/collections/{}/items", collection_id),
+            &test_feature_request(),
+        )
+        .await;
+    feature_response.assert_status(StatusCode::CREATED);
+    let feature: serde_json::Value = feature_response.json();
+    let feature_id = feature["id"].as_str().expect("Feature must have id");
+    let feature_path = format!("/collections/{}/items/{}", collection_id, feature_id);
+    let lock_path = format!("{}/lock", feature_path);
+
+    app.get(&lock_path)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    let response = app
+        .post_json(&lock_path, &serde_json::json!({"expiresIn": 60}))
+        .await;
+    response.assert_status(StatusCode::OK);
+    let lock: serde_json::Value = response.json();
+    assert_eq!(lock["lockedBy"], "testuser");
+    let expires = lock["expires"]
+        .as_str()
+        .expect("Lock must expire")
+        .to_string();
+
+    let lock: serde_json::Value = app.get(&lock_path).await.json();
+    assert_eq!(lock["expires"], expires.as_str());
+
+    // Locking again renews the lock
+    let response = app
+        .post_json(&lock_path, &serde_json::json!({"expiresIn": 3600}))
+        .await;
+    response.assert_status(StatusCode::OK);
+    let lock: serde_json::Value = response.json();
+    assert_ne!(lock["expires"], expires.as_str());
+
+    app.post_json(&lock_path, &serde_json::json!({"expiresIn": 0}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // The lock holder can still write the feature
+    let feature = app.get(&feature_path).await;
+    let etag = feature.etag().expect("Feature must have ETag");
+    app.patch_json(
+        &feature_path,
+        &serde_json::json!({"properties": {"name": "locked edit"}}),
+        &etag,
+    )
+    .await
+    .assert_success();
+
+    app.request_without_etag(Method::DELETE, &lock_path)
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    app.request_without_etag(Method::DELETE, &lock_path)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    app.get(&lock_path)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    let missing = format!(
+        "/collections/{}/items/{}/lock",
+        collection_id,
+        uuid::Uuid::new_v4()
+    );
+    app.post_json(&missing, &serde_json::json!({}))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+/// Test that a stale write gets the current feature and the conflicting fields
+#[tokio::test]
+async fn stale_write_returns_server_version() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("stale-write-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let body: serde_json::Value = create_response.json();
+    let collection_id = body["id"].as_str().expect("Collection must have id");
+
+    let feature_response = app
+        .post_json(
+            &format!("/collections/{}/items", collection_id),
+            &test_feature_request(),
+        )
+        .await;
+    feature_response.assert_status(StatusCode::CREATED);
+    let stale_etag = feature_response.etag().expect("Feature must have ETag");
+    let feature: serde_json::Value = feature_response.json();
+    let feature_id = feature["id"].as_str().expect("Feature must have id");
+    let feature_path = format!("/collections/{}/items/{}", collection_id, feature_id);
+
+    let response = app
+        .patch_json(
+            &feature_path,
+            &serde_json::json!({"properties": {"name": "Server edit"}}),
+            &stale_etag,
+        )
+        .await;
+    response.assert_success();
+    let current_etag = response.etag().expect("Feature must have ETag");
+
+    let response = app
+        .patch_json(
+            &feature_path,
+            &serde_json::json!({"properties": {"name": "Client edit", "value": 42}}),
+            &stale_etag,
+        )
+        .await;
+    response.assert_status(StatusCode::PRECONDITION_FAILED);
+    assert_eq!(response.etag().as_deref(), Some(current_etag.as_str()));
+    let conflict: serde_json::Value = response.json();
+    assert_eq!(conflict["code"], "PreconditionFailed");
+    assert_eq!(conflict["server"]["properties"]["name"], "Server edit");
+    assert_eq!(
+        conflict["conflicts"],
+        serde_json::json!([{
+            "path": "/properties/name",
+            "server": "Server edit",
+            "client": "Client edit"
+        }])
+    );
+
+    let response = app.delete(&feature_path, &stale_etag).await;
+    response.assert_status(StatusCode::PRECONDITION_FAILED);
+    let conflict: serde_json::Value = response.json();
+    assert_eq!(conflict["server"]["id"], feature_id);
+    assert_eq!(conflict["conflicts"], serde_json::json!([]));
+}
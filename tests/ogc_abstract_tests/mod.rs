@@ -7,5 +7,6 @@ pub mod coverages_core;
 pub mod features_core;
 pub mod features_crs;
 pub mod processes_core;
+pub mod records_core;
 pub mod stac_transaction;
 pub mod tiles_core;
@@ -3,7 +3,7 @@
 //! Implements abstract test requirements from:
 //! http://www.opengis.net/spec/ogcapi-processes-1/1.0/conf/core
 
-use crate::common::{TestApp, test_collection_request};
+use crate::common::{MockAuthState, TestApp, test_collection_request};
 use axum::http::StatusCode;
 
 /// Test process list endpoint
@@ -67,6 +67,66 @@ async fn test_process_not_found() {
     response.assert_status(StatusCode::NOT_FOUND);
 }
 
+/// Test that deploying a process requires process admin privileges
+#[tokio::test]
+async fn test_deploy_requires_admin() {
+    let app = TestApp::new().await;
+
+    let package = serde_json::json!({
+        "processDescription": { "id": "ndvi", "title": "NDVI" },
+        "executionUnit": { "type": "docker", "image": "example/ndvi:1.0" }
+    });
+
+    let response = app.post_json("/processes", &package).await;
+    response.assert_status(StatusCode::FORBIDDEN);
+}
+
+/// Test deploy, describe and undeploy of a user-defined process
+#[tokio::test]
+async fn test_deploy_and_undeploy_process() {
+    let app = TestApp::with_auth(MockAuthState::with_groups(
+        "admin",
+        vec!["spatialvault-admins".to_string()],
+    ))
+    .await;
+
+    let package = serde_json::json!({
+        "processDescription": {
+            "id": "ndvi",
+            "title": "NDVI",
+            "inputs": { "red": { "schema": { "type": "string" } } }
+        },
+        "executionUnit": { "type": "docker", "image": "example/ndvi:1.0" }
+    });
+
+    let response = app.post_json("/processes", &package).await;
+    response.assert_status(StatusCode::CREATED);
+    assert!(response.header("location").is_some());
+
+    let response = app.get("/processes/ndvi").await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["id"], "ndvi");
+    assert!(body["jobControlOptions"].is_array());
+
+    // Missing required input
+    let response = app
+        .post_json(
+            "/processes/ndvi/execution",
+            &serde_json::json!({ "inputs": {} }),
+        )
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let response = app
+        .request_without_etag(axum::http::Method::DELETE, "/processes/ndvi")
+        .await;
+    response.assert_status(StatusCode::NO_CONTENT);
+
+    let response = app.get("/processes/ndvi").await;
+    response.assert_status(StatusCode::NOT_FOUND);
+}
+
 /// Test job execution (async)
 #[tokio::test]
 async fn test_job_execution() {
@@ -113,6 +173,283 @@ async fn test_job_execution() {
     );
 }
 
+/// Test that collection exports run as jobs of vector collections only
+#[tokio::test]
+async fn test_export_collection_execution() {
+    let app = TestApp::new().await;
+
+    let response = app.get("/processes/export-collection").await;
+    response.assert_status(StatusCode::OK);
+
+    let collection = test_collection_request("export-job-test", "vector");
+    let response = app.post_json("/collections", &collection).await;
+    response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    let response = app
+        .post_json(
+            "/processes/export-collection/execution",
+            &serde_json::json!({ "inputs": { "collection": collection_id, "format": "kml" } }),
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["status"].as_str(), Some("accepted"));
+
+    // Results can't be downloaded before the job has run
+    let response = app
+        .get(&format!(
+            "/jobs/{}/results/export",
+            body["jobId"].as_str().unwrap()
+        ))
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let response = app
+        .post_json(
+            "/processes/export-collection/execution",
+            &serde_json::json!({ "inputs": { "collection": collection_id, "format": "geojson" } }),
+        )
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let response = app
+        .post_json(
+            "/processes/export-collection/execution",
+            &serde_json::json!({ "inputs": { "collection": collection_id, "format": "gpkg" } }),
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let response = app
+        .post_json(
+            "/processes/export-collection/execution",
+            &serde_json::json!({ "inputs": { "collection": collection_id, "format": "pmtiles" } }),
+        )
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let raster = test_collection_request("export-job-raster", "raster");
+    let response = app.post_json("/collections", &raster).await;
+    response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    let response = app
+        .post_json(
+            "/processes/export-collection/execution",
+            &serde_json::json!({ "inputs": { "collection": created["id"], "format": "kml" } }),
+        )
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test that backups run as jobs and restores only accept the user's own backups
+#[tokio::test]
+async fn test_backup_and_restore_collection_execution() {
+    let app = TestApp::new().await;
+
+    let response = app.get("/processes/backup-collection").await;
+    response.assert_status(StatusCode::OK);
+    let response = app.get("/processes/restore-collection").await;
+    response.assert_status(StatusCode::OK);
+
+    let collection = test_collection_request("backup-job-test", "vector");
+    let response = app.post_json("/collections", &collection).await;
+    response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    let response = app
+        .post_json(
+            "/processes/backup-collection/execution",
+            &serde_json::json!({ "inputs": { "collection": collection_id } }),
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["status"].as_str(), Some("accepted"));
+
+    let response = app
+        .post_json(
+            "/processes/backup-collection/execution",
+            &serde_json::json!({ "inputs": { "collection": "testuser:no-such-collection" } }),
+        )
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+
+    // Backups of other users, or files that aren't backups, can't be restored
+    let response = app
+        .post_json(
+            "/processes/restore-collection/execution",
+            &serde_json::json!({ "inputs": { "backup": "s3://test-bucket/otheruser/backups/x/a.backup.json.gz" } }),
+        )
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let response = app
+        .post_json(
+            "/processes/restore-collection/execution",
+            &serde_json::json!({ "inputs": { "backup": "https://example.com/a.backup.json.gz" } }),
+        )
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test that a job expiry override must lie in the future
+#[tokio::test]
+async fn test_job_expiry_override() {
+    let app = TestApp::new().await;
+
+    let inputs = serde_json::json!({
+        "collection": "expiry-test",
+        "data": { "href": "s3://test-bucket/test.tif" }
+    });
+
+    let response = app
+        .post_json(
+            "/processes/import-raster/execution",
+            &serde_json::json!({ "inputs": inputs, "expires": "2000-01-01T00:00:00Z" }),
+        )
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test that unknown chunked uploads are rejected as process inputs
+#[tokio::test]
+async fn test_job_execution_unknown_upload() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .get("/uploads/00000000-0000-0000-0000-000000000000")
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+
+    let inputs = serde_json::json!({
+        "collection": "upload-test",
+        "data": { "href": "upload://00000000-0000-0000-0000-000000000000" }
+    });
+
+    let response = app
+        .post_json(
+            "/processes/import-raster/execution",
+            &serde_json::json!({ "inputs": inputs }),
+        )
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Test that only failed jobs can be requeued
+#[tokio::test]
+async fn test_job_retry_requires_failed_job() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_json(
+            &format!("/jobs/{}/retry", uuid::Uuid::new_v4()),
+            &serde_json::json!({}),
+        )
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+
+    let inputs = serde_json::json!({
+        "collection": "retry-test",
+        "data": { "href": "s3://test-bucket/test.tif" }
+    });
+    let response = app
+        .post_json(
+            "/processes/import-raster/execution",
+            &serde_json::json!({ "inputs": inputs }),
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let job: serde_json::Value = response.json();
+    let job_id = job["jobId"].as_str().expect("Job must have jobId");
+
+    let response = app
+        .post_json(&format!("/jobs/{}/retry", job_id), &serde_json::json!({}))
+        .await;
+    response.assert_status(StatusCode::CONFLICT);
+}
+
+/// Test that retrying a workflow only reruns the steps that didn't succeed
+#[tokio::test]
+async fn test_workflow_retry_keeps_successful_steps() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("retry-workflow", "vector");
+    let response = app.post_json("/collections", &collection).await;
+    response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    let step = |format: &str| {
+        serde_json::json!({
+            "process": "export-collection",
+            "inputs": { "collection": collection_id, "format": format }
+        })
+    };
+    let response = app
+        .post_json(
+            "/workflows",
+            &serde_json::json!({ "steps": [step("kml"), step("ndjson")] }),
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let job: serde_json::Value = response.json();
+    let job_id = job["jobId"].as_str().expect("Job must have jobId");
+
+    // The first step succeeded and the second failed
+    sqlx::query(
+        r#"
+        UPDATE spatialvault.processes_jobs
+        SET status = CASE WHEN step_index = 0 THEN 'successful' ELSE 'failed' END
+        WHERE id = $1::uuid OR parent_job_id = $1::uuid
+        "#,
+    )
+    .bind(job_id)
+    .execute(app.db.pool())
+    .await
+    .expect("Failed to fail the workflow");
+
+    let response = app
+        .post_json(&format!("/jobs/{}/retry", job_id), &serde_json::json!({}))
+        .await;
+    response.assert_status(StatusCode::ACCEPTED);
+
+    let statuses: Vec<String> = sqlx::query_scalar(
+        "SELECT status FROM spatialvault.processes_jobs WHERE parent_job_id = $1::uuid ORDER BY step_index",
+    )
+    .bind(job_id)
+    .fetch_all(app.db.pool())
+    .await
+    .expect("Failed to read the steps");
+    assert_eq!(statuses, ["successful", "accepted"]);
+}
+
+/// Test execution with a multipart/form-data request
+#[tokio::test]
+async fn test_job_execution_multipart() {
+    let app = TestApp::new().await;
+
+    let inputs = serde_json::json!({
+        "collection": "multipart-test",
+        "data": { "href": "s3://test-bucket/test.tif" }
+    })
+    .to_string();
+
+    let response = app
+        .post_multipart("/processes/import-raster/execution", &[("inputs", &inputs)])
+        .await;
+    response.assert_status(StatusCode::CREATED);
+
+    let response = app
+        .post_multipart(
+            "/processes/import-raster/execution",
+            &[("inputs", &inputs), ("collection", "other")],
+        )
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
 /// Test job list endpoint
 #[tokio::test]
 async fn test_job_list() {
@@ -128,6 +465,66 @@ async fn test_job_list() {
     assert!(body["links"].is_array(), "Should have links");
 }
 
+/// Test job list filters and paging
+#[tokio::test]
+async fn test_job_list_filters() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .get("/jobs?status=successful,failed&processID=import-raster&type=process&limit=1")
+        .await;
+    response.assert_success();
+
+    let body: serde_json::Value = response.json();
+    let jobs = body["jobs"].as_array().expect("Should have jobs array");
+    assert!(jobs.len() <= 1, "limit should be applied");
+    for job in jobs {
+        assert_eq!(job["processId"], "import-raster");
+    }
+
+    // Jobs are counted per type over all pages
+    let collection = test_collection_request("job-facets-test", "raster");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let inputs = serde_json::json!({
+        "collection": created["id"],
+        "data": { "href": "s3://test-bucket/test.tif" }
+    });
+    for _ in 0..2 {
+        app.post_json(
+            "/processes/import-raster/execution",
+            &serde_json::json!({ "inputs": inputs }),
+        )
+        .await
+        .assert_status(StatusCode::CREATED);
+    }
+    let response = app.get("/jobs?processID=import-raster&limit=1").await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["jobs"].as_array().map(Vec::len), Some(1));
+    assert!(body["facets"]["type"]["process"].as_u64() >= Some(2));
+
+    // Pages hold 100 jobs unless a limit is given
+    let response = app.get("/jobs").await;
+    let body: serde_json::Value = response.json();
+    assert!(
+        body["links"][0]["href"]
+            .as_str()
+            .is_some_and(|href| href.contains("limit=100"))
+    );
+
+    app.get("/jobs?status=bogus")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    app.get("/jobs?datetime=not-a-date")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    app.get("/jobs?limit=0")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
 /// Test job status endpoint
 #[tokio::test]
 async fn test_job_status() {
@@ -235,4 +632,5 @@ async fn test_job_dismiss() {
         Some("dismissed"),
         "Job should be dismissed"
     );
+    assert_eq!(status_body["message"], "Dismissed by the user");
 }
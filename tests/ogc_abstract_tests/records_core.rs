@@ -0,0 +1,69 @@
+//! OGC API Records conformance tests
+//!
+//! Implements abstract test requirements from:
+//! http://www.opengis.net/spec/ogcapi-records-1/1.0/conf/record-api
+
+use crate::common::{TestApp, assert_has_link, test_collection_request};
+use axum::http::StatusCode;
+
+/// The main catalogue describes itself as a record collection
+#[tokio::test]
+async fn test_catalogue_description() {
+    let app = TestApp::new().await;
+
+    let response = app.get("/catalogues/main").await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["itemType"], "record");
+    let links = body["links"].as_array().expect("links must be an array");
+    assert!(assert_has_link(links, "items"), "Missing items link");
+
+    app.get("/catalogues/other")
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+/// Collections are exposed as records with their descriptive metadata
+#[tokio::test]
+async fn test_collection_records() {
+    let app = TestApp::new().await;
+
+    let mut collection = test_collection_request("records-test", "vector");
+    collection["keywords"] = serde_json::json!(["records-core"]);
+    collection["license"] = "CC-BY-4.0".into();
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    let response = app.get("/catalogues/main/items?keyword=records-core").await;
+    response.assert_success();
+    response.assert_content_type("application/geo+json");
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["type"], "FeatureCollection");
+    assert_eq!(body["numberMatched"].as_u64(), Some(1));
+
+    let record = &body["features"][0];
+    assert_eq!(record["id"].as_str(), Some(collection_id));
+    assert_eq!(record["properties"]["type"], "dataset");
+    assert_eq!(record["properties"]["license"], "CC-BY-4.0");
+    assert_eq!(
+        record["properties"]["title"].as_str(),
+        created["title"].as_str()
+    );
+
+    let response = app
+        .get(&format!("/catalogues/main/items/{}", collection_id))
+        .await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    let links = body["links"].as_array().expect("links must be an array");
+    assert!(
+        assert_has_link(links, "describes"),
+        "Missing describes link"
+    );
+
+    app.get("/catalogues/main/items/testuser:no-such-record")
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
@@ -5,7 +5,7 @@
 //! - https://api.stacspec.org/v1.0.0/ogcapi-features/extensions/transaction
 
 use crate::common::{TestApp, test_collection_request, test_stac_item_request};
-use axum::http::StatusCode;
+use axum::http::{Method, StatusCode, header};
 
 /// Test that conformance declaration includes STAC Collection Transaction extension
 #[tokio::test]
@@ -80,6 +80,63 @@ async fn test_create_collection() {
     assert!(body["id"].as_str().unwrap().contains("stac-create-test"));
 }
 
+/// Transactions reject bodies that are not sent with a supported media type
+#[tokio::test]
+async fn test_unsupported_media_type() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("stac-media-type-test", "vector");
+    let body = serde_json::to_string(&collection).unwrap();
+    let response = app
+        .request_with_headers(
+            Method::POST,
+            "/collections",
+            body.clone(),
+            vec![(header::CONTENT_TYPE, "text/plain")],
+        )
+        .await;
+    response.assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    let error: serde_json::Value = response.json();
+    assert!(
+        error["description"]
+            .as_str()
+            .unwrap()
+            .contains("application/geo+json")
+    );
+
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().unwrap();
+    let etag = create_response.etag().unwrap();
+
+    // PATCH requires a JSON Merge Patch document
+    let response = app
+        .request_with_headers(
+            Method::PATCH,
+            &format!("/collections/{}", collection_id),
+            r#"{"title":"Patched"}"#.to_string(),
+            vec![
+                (header::CONTENT_TYPE, "application/json"),
+                (header::IF_MATCH, &etag),
+            ],
+        )
+        .await;
+    response.assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    // Features are accepted as GeoJSON
+    let response = app
+        .request_with_headers(
+            Method::POST,
+            &format!("/collections/{}/items", collection_id),
+            r#"{"type":"Feature","geometry":{"type":"Point","coordinates":[1,2]},"properties":{}}"#
+                .to_string(),
+            vec![(header::CONTENT_TYPE, "application/geo+json")],
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+}
+
 /// Collection Transaction: PUT /collections/{collectionId} replaces a collection
 #[tokio::test]
 async fn test_replace_collection() {
@@ -340,6 +397,56 @@ async fn test_patch_item() {
     assert_eq!(body["properties"]["title"].as_str(), Some("Patched Item"));
 }
 
+/// Item Transaction: PATCH follows JSON Merge Patch, so null removes a property
+#[tokio::test]
+async fn test_patch_feature_merge_semantics() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("stac-merge-patch-test", "vector");
+    let create_coll_response = app.post_json("/collections", &collection).await;
+    create_coll_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_coll_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    let feature = serde_json::json!({
+        "type": "Feature",
+        "geometry": { "type": "Point", "coordinates": [10.0, 20.0] },
+        "properties": { "name": "original", "x": 1, "nested": { "a": 1, "b": 2 } }
+    });
+    let create_response = app
+        .post_json(&format!("/collections/{}/items", collection_id), &feature)
+        .await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created_feature: serde_json::Value = create_response.json();
+    let feature_id = created_feature["id"]
+        .as_str()
+        .expect("Feature must have id");
+    let etag = create_response.etag().expect("Should have ETag");
+
+    // No geometry in the patch: it must be left untouched
+    let patch = serde_json::json!({
+        "properties": { "x": null, "nested": { "b": null }, "added": true }
+    });
+    let patch_response = app
+        .patch_json(
+            &format!("/collections/{}/items/{}", collection_id, feature_id),
+            &patch,
+            &etag,
+        )
+        .await;
+    patch_response.assert_success();
+
+    let body: serde_json::Value = patch_response.json();
+    assert_eq!(
+        body["properties"],
+        serde_json::json!({ "name": "original", "nested": { "a": 1 }, "added": true })
+    );
+    assert_eq!(
+        body["geometry"]["coordinates"],
+        serde_json::json!([10.0, 20.0])
+    );
+}
+
 /// Item Transaction: DELETE /collections/{collectionId}/items/{itemId} removes an item
 #[tokio::test]
 async fn test_delete_item() {
@@ -4,7 +4,7 @@
 //! http://www.opengis.net/spec/ogcapi-tiles-1/1.0/conf/core
 
 use crate::common::{TestApp, test_collection_request, test_feature_request};
-use axum::http::StatusCode;
+use axum::http::{Method, StatusCode, header};
 
 /// Test TileMatrixSets endpoint
 #[tokio::test]
@@ -46,13 +46,37 @@ async fn test_collection_tileset_metadata() {
     let created: serde_json::Value = create_response.json();
     let collection_id = created["id"].as_str().expect("Collection must have id");
 
-    // Get tileset metadata
+    // The tileset list links the tileset description
     let response = app
         .get(&format!("/collections/{}/tiles", collection_id))
         .await;
     response.assert_success();
     response.assert_content_type("application/json");
 
+    let list: serde_json::Value = response.json();
+    let tilesets = list["tilesets"]
+        .as_array()
+        .expect("tilesets must be an array");
+    assert_eq!(tilesets.len(), 1);
+    assert_eq!(tilesets[0]["dataType"], "vector");
+    assert!(tilesets[0]["tileMatrixSetURI"].is_string());
+    let href = tilesets[0]["links"]
+        .as_array()
+        .and_then(|links| links.iter().find(|link| link["rel"] == "self"))
+        .and_then(|link| link["href"].as_str())
+        .expect("Tileset entry must link its description");
+    assert!(href.ends_with("/tiles/WebMercatorQuad"));
+
+    // Get tileset metadata
+    let response = app
+        .get(&format!(
+            "/collections/{}/tiles/WebMercatorQuad",
+            collection_id
+        ))
+        .await;
+    response.assert_success();
+    response.assert_content_type("application/json");
+
     let body: serde_json::Value = response.json();
 
     // Should have required properties
@@ -64,6 +88,61 @@ async fn test_collection_tileset_metadata() {
         "Tileset should have tileMatrixSetId"
     );
     assert!(body["links"].is_array(), "Tileset should have links");
+
+    // Only WebMercatorQuad tilesets are offered
+    app.get(&format!(
+        "/collections/{}/tiles/WorldCRS84Quad",
+        collection_id
+    ))
+    .await
+    .assert_status(StatusCode::NOT_FOUND);
+}
+
+/// Test the dataset tileset combining the accessible vector collections
+#[tokio::test]
+async fn test_dataset_tilesets() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("dataset-tiles-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    let response = app.get("/tiles").await;
+    response.assert_status(StatusCode::OK);
+    let list: serde_json::Value = response.json();
+    assert_eq!(list["tilesets"][0]["dataType"], "vector");
+
+    let response = app.get("/tiles/WebMercatorQuad").await;
+    response.assert_status(StatusCode::OK);
+    let tileset: serde_json::Value = response.json();
+    let layers = tileset["layers"]
+        .as_array()
+        .expect("layers must be an array");
+    assert!(
+        layers
+            .iter()
+            .any(|layer| layer["id"] == "dataset-tiles-test")
+    );
+
+    let template = tileset["links"]
+        .as_array()
+        .and_then(|links| links.iter().find(|link| link["rel"] == "item"))
+        .and_then(|link| link["href"].as_str())
+        .expect("Tileset must link a tile URL template");
+    assert!(template.contains(collection_id));
+    let tile_uri = template
+        .strip_prefix(&app.config.base_url)
+        .expect("Template starts with the base URL")
+        .replace("{tileMatrix}/{tileRow}/{tileCol}", "0/0/0");
+    let response = app.get(&tile_uri).await;
+    response.assert_status(StatusCode::OK);
+    response.assert_content_type("application/vnd.mapbox-vector-tile");
+
+    app.get("/tiles/WorldCRS84Quad")
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
 }
 
 /// Test tile retrieval at various zoom levels
@@ -105,6 +184,123 @@ async fn test_tile_retrieval() {
     );
 }
 
+/// HEAD on a tile returns its headers without rendering a body
+#[tokio::test]
+async fn test_tile_head() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("tile-head-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    let response = app
+        .request_without_etag(
+            Method::HEAD,
+            &format!("/collections/{}/tiles/WebMercatorQuad/0/0/0", collection_id),
+        )
+        .await;
+    response.assert_success();
+    response.assert_content_type("application/vnd.mapbox-vector-tile");
+    assert!(response.text().is_empty());
+    let etag = response.etag().expect("HEAD must carry the tile's ETag");
+
+    // Once served, HEAD reports the tile's length
+    let tile_uri = format!("/collections/{}/tiles/WebMercatorQuad/0/0/0", collection_id);
+    let get_response = app.get(&tile_uri).await;
+    assert_eq!(get_response.etag(), Some(etag.clone()));
+    let response = app.request_without_etag(Method::HEAD, &tile_uri).await;
+    assert_eq!(
+        response.header("content-length"),
+        Some(get_response.text().len().to_string())
+    );
+
+    // Revalidation answers as GET does
+    app.request_with_headers(
+        Method::HEAD,
+        &tile_uri,
+        String::new(),
+        vec![(header::IF_NONE_MATCH, etag.as_str())],
+    )
+    .await
+    .assert_status(StatusCode::NOT_MODIFIED);
+
+    // Invalid tiles are still rejected
+    app.request_without_etag(
+        Method::HEAD,
+        &format!("/collections/{}/tiles/WebMercatorQuad/2/4/4", collection_id),
+    )
+    .await
+    .assert_status(StatusCode::NOT_FOUND);
+}
+
+/// Served tiles are counted in the collection's analytics, HEAD requests are not
+#[tokio::test]
+async fn test_tile_analytics() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("tile-analytics-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    app.post_json(
+        &format!("/collections/{}/items", collection_id),
+        &test_feature_request(),
+    )
+    .await
+    .assert_status(StatusCode::CREATED);
+
+    for tile in ["0/0/0", "1/1/1"] {
+        app.get(&format!(
+            "/collections/{}/tiles/WebMercatorQuad/{}",
+            collection_id, tile
+        ))
+        .await
+        .assert_success();
+    }
+    app.request_without_etag(
+        Method::HEAD,
+        &format!("/collections/{}/tiles/WebMercatorQuad/0/0/0", collection_id),
+    )
+    .await
+    .assert_success();
+
+    let response = app
+        .get(&format!(
+            "/collections/{}/analytics?interval=hour",
+            collection_id
+        ))
+        .await;
+    response.assert_status(StatusCode::OK);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["interval"], "hour");
+    assert_eq!(body["requests"], 2);
+    assert_eq!(body["usage"].as_array().map(Vec::len), Some(1));
+
+    // Usage outside the requested period is left out
+    let response = app
+        .get(&format!(
+            "/collections/{}/analytics?datetime=2000-01-01T00:00:00Z/2000-12-31T00:00:00Z",
+            collection_id
+        ))
+        .await;
+    response.assert_status(StatusCode::OK);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["requests"], 0);
+
+    app.get(&format!(
+        "/collections/{}/analytics?interval=week",
+        collection_id
+    ))
+    .await
+    .assert_status(StatusCode::BAD_REQUEST);
+}
+
 /// Test tile with valid coordinates
 #[tokio::test]
 async fn test_tile_valid_coordinates() {
@@ -195,3 +391,574 @@ async fn test_unsupported_tile_matrix_set() {
     // Should return 404 Not Found
     response.assert_status(StatusCode::NOT_FOUND);
 }
+
+/// Tileset limits follow the collection's zoom range and data extent
+#[tokio::test]
+async fn test_tile_matrix_set_limits() {
+    let app = TestApp::new().await;
+
+    let mut collection = test_collection_request("tile-limits-test", "vector");
+    collection["minZoom"] = serde_json::json!(2);
+    collection["maxZoom"] = serde_json::json!(5);
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    assert_eq!(created["minZoom"].as_u64(), Some(2));
+    assert_eq!(created["maxZoom"].as_u64(), Some(5));
+
+    // A feature at (0, 0) lies at the corner of the four central tiles
+    let feature = test_feature_request();
+    app.post_json(&format!("/collections/{}/items", collection_id), &feature)
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    let response = app
+        .get(&format!(
+            "/collections/{}/tiles/WebMercatorQuad",
+            collection_id
+        ))
+        .await;
+    response.assert_success();
+
+    let body: serde_json::Value = response.json();
+    let limits = body["tileMatrixSetLimits"]
+        .as_array()
+        .expect("tileMatrixSetLimits must be an array");
+    let matrices: Vec<&str> = limits
+        .iter()
+        .map(|l| l["tileMatrix"].as_str().unwrap())
+        .collect();
+    assert_eq!(matrices, ["2", "3", "4", "5"]);
+    assert_eq!(limits[0]["minTileCol"].as_u64(), Some(2));
+    assert_eq!(limits[0]["maxTileCol"].as_u64(), Some(2));
+
+    // Tiles outside the zoom range do not exist
+    for z in [1, 6] {
+        app.get(&format!(
+            "/collections/{}/tiles/WebMercatorQuad/{}/0/0",
+            collection_id, z
+        ))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    }
+    app.get(&format!(
+        "/collections/{}/tiles/WebMercatorQuad/2/2/2",
+        collection_id
+    ))
+    .await
+    .assert_success();
+
+    // Neither do tiles outside the data extent
+    app.get(&format!(
+        "/collections/{}/tiles/WebMercatorQuad/2/1/1",
+        collection_id
+    ))
+    .await
+    .assert_status(StatusCode::NOT_FOUND);
+
+    // The zoom range must be valid
+    let mut invalid = test_collection_request("tile-limits-invalid", "vector");
+    invalid["minZoom"] = serde_json::json!(8);
+    invalid["maxZoom"] = serde_json::json!(4);
+    app.post_json("/collections", &invalid)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Also against the stored zoom levels
+    let collection_path = format!("/collections/{}", collection_id);
+    let etag = app
+        .get(&collection_path)
+        .await
+        .etag()
+        .expect("Collection must have ETag");
+    app.patch_json(
+        &collection_path,
+        &serde_json::json!({ "minZoom": 6 }),
+        &etag,
+    )
+    .await
+    .assert_status(StatusCode::BAD_REQUEST);
+
+    // null reverts to the full zoom range
+    let response = app
+        .patch_json(
+            &collection_path,
+            &serde_json::json!({ "maxZoom": null }),
+            &etag,
+        )
+        .await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["minZoom"].as_u64(), Some(2));
+    assert!(body.get("maxZoom").is_none());
+    app.get(&format!(
+        "/collections/{}/tiles/WebMercatorQuad/6/32/32",
+        collection_id
+    ))
+    .await
+    .assert_success();
+}
+
+/// A multi-collection tile has one named layer per collection
+#[tokio::test]
+async fn test_layered_tile() {
+    let app = TestApp::new().await;
+
+    let mut ids = Vec::new();
+    for (name, layer) in [("layered-roads", None), ("layered-water", Some("water"))] {
+        let mut collection = test_collection_request(name, "vector");
+        if let Some(layer) = layer {
+            collection["tileLayer"] = serde_json::json!(layer);
+        }
+        let create_response = app.post_json("/collections", &collection).await;
+        create_response.assert_status(StatusCode::CREATED);
+        let created: serde_json::Value = create_response.json();
+        let collection_id = created["id"].as_str().unwrap().to_string();
+
+        app.post_json(
+            &format!("/collections/{}/items", collection_id),
+            &test_feature_request(),
+        )
+        .await
+        .assert_status(StatusCode::CREATED);
+        ids.push(collection_id);
+    }
+
+    let response = app
+        .get(&format!(
+            "/tiles/WebMercatorQuad/0/0/0?collections={}",
+            ids.join(",")
+        ))
+        .await;
+    response.assert_success();
+    response.assert_content_type("application/vnd.mapbox-vector-tile");
+
+    // Layer names are stored verbatim in the tile
+    let contains = |name: &str| {
+        response
+            .body
+            .windows(name.len())
+            .any(|w| w == name.as_bytes())
+    };
+    assert!(
+        contains("layered-roads"),
+        "Default layer name is the short name"
+    );
+    assert!(contains("water"), "Configured layer name is used");
+
+    // Every collection must exist
+    app.get(&format!(
+        "/tiles/WebMercatorQuad/0/0/0?collections={},missing",
+        ids[0]
+    ))
+    .await
+    .assert_status(StatusCode::NOT_FOUND);
+
+    // Layer names must be unique
+    app.get(&format!(
+        "/tiles/WebMercatorQuad/0/0/0?collections={},{}",
+        ids[0], ids[0]
+    ))
+    .await
+    .assert_status(StatusCode::BAD_REQUEST);
+
+    // Every collection must be a vector collection, whatever its zoom range
+    let mut raster = test_collection_request("layered-raster", "raster");
+    raster["minZoom"] = serde_json::json!(5);
+    let create_response = app.post_json("/collections", &raster).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let raster_id = created["id"].as_str().unwrap();
+    for method in [Method::GET, Method::HEAD] {
+        app.request_without_etag(
+            method,
+            &format!(
+                "/tiles/WebMercatorQuad/0/0/0?collections={},{}",
+                ids[0], raster_id
+            ),
+        )
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    // Renamed collections are found by their old names
+    let collection_path = format!("/collections/{}", ids[1]);
+    let etag = app
+        .get(&collection_path)
+        .await
+        .etag()
+        .expect("Collection must have ETag");
+    app.patch_json(
+        &collection_path,
+        &serde_json::json!({ "id": "layered-lakes" }),
+        &etag,
+    )
+    .await
+    .assert_success();
+    let uri = format!("/tiles/WebMercatorQuad/0/0/0?collections={}", ids[1]);
+    let get_response = app.get(&uri).await;
+    get_response.assert_success();
+    let etag = get_response.etag().expect("Layered tile must have ETag");
+    let response = app.request_without_etag(Method::HEAD, &uri).await;
+    response.assert_success();
+    assert_eq!(response.etag(), Some(etag.clone()));
+    assert_eq!(
+        response.header("content-length"),
+        Some(get_response.text().len().to_string())
+    );
+    for method in [Method::GET, Method::HEAD] {
+        app.request_with_headers(
+            method,
+            &uri,
+            String::new(),
+            vec![(header::IF_NONE_MATCH, etag.as_str())],
+        )
+        .await
+        .assert_status(StatusCode::NOT_MODIFIED);
+    }
+}
+
+/// Property rules select the attributes encoded at each zoom level
+#[tokio::test]
+async fn test_tile_property_rules() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("tile-properties-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    app.post_json(
+        &format!("/collections/{}/items", collection_id),
+        &test_feature_request(),
+    )
+    .await
+    .assert_status(StatusCode::CREATED);
+
+    // Only `name` from zoom 12, nothing below
+    let patch = serde_json::json!({
+        "propertyRules": [
+            { "maxZoom": 11, "properties": [] },
+            { "minZoom": 12, "properties": ["name"] }
+        ]
+    });
+    let response = app
+        .patch_json_without_etag(
+            &format!("/collections/{}/tiles/WebMercatorQuad", collection_id),
+            &patch,
+        )
+        .await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["propertyRules"].as_array().map(Vec::len), Some(2));
+    assert_eq!(body["propertyRules"][1]["minZoom"].as_u64(), Some(12));
+
+    let tile_uri = |z: u32, xy: u32| {
+        format!(
+            "/collections/{}/tiles/WebMercatorQuad/{}/{}/{}",
+            collection_id, z, xy, xy
+        )
+    };
+    let contains = |body: &[u8], s: &str| body.windows(s.len()).any(|w| w == s.as_bytes());
+
+    // The feature at (0, 0) lies in the top-left corner of tile 12/2048/2048
+    let low = app.get(&tile_uri(2, 2)).await.body;
+    assert!(!contains(&low, "Test Feature"));
+    assert!(!contains(&low, "value"));
+
+    let high = app.get(&tile_uri(12, 2048)).await.body;
+    assert!(contains(&high, "Test Feature"));
+    assert!(!contains(&high, "value"));
+
+    // Invalid rules are rejected
+    let invalid = serde_json::json!({
+        "propertyRules": [{ "minZoom": 10, "maxZoom": 5, "properties": [] }]
+    });
+    app.patch_json_without_etag(
+        &format!("/collections/{}/tiles/WebMercatorQuad", collection_id),
+        &invalid,
+    )
+    .await
+    .assert_status(StatusCode::BAD_REQUEST);
+
+    // An empty list restores all properties
+    let reset = serde_json::json!({ "propertyRules": [] });
+    let response = app
+        .patch_json_without_etag(
+            &format!("/collections/{}/tiles/WebMercatorQuad", collection_id),
+            &reset,
+        )
+        .await;
+    response.assert_success();
+    let body: serde_json::Value = response.json();
+    assert!(body.get("propertyRules").is_none());
+    assert!(contains(&app.get(&tile_uri(2, 2)).await.body, "value"));
+}
+
+/// Invalid raster styling and terrain parameters are rejected before rendering
+#[tokio::test]
+async fn test_raster_style_validation() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("tile-style-test", "raster");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    for query in [
+        "bidx=1&expression=b1",
+        "expression=(b4-b3)/(b4%2B",
+        "colormap_name=unknown",
+        "colormap_name=viridis&bidx=1,2,3",
+        "rescale=10,0",
+        "bidx=0",
+        "algorithm=slope",
+        "algorithm=hillshade&azimuth=400",
+        "algorithm=hillshade&bidx=1",
+        "algorithm=terrain-rgb&f=jpeg",
+        "altitude=30",
+    ] {
+        let response = app
+            .get(&format!(
+                "/collections/{}/tiles/WebMercatorQuad/0/0/0?{}",
+                collection_id, query
+            ))
+            .await;
+        assert_eq!(
+            response.status,
+            StatusCode::BAD_REQUEST,
+            "{} should be rejected",
+            query
+        );
+    }
+}
+
+/// Test: Point cloud collections expose a 3D Tiles tileset
+#[tokio::test]
+async fn test_pointcloud_tileset() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("pointcloud-3dtiles-test", "pointcloud");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+
+    let response = app
+        .get(&format!(
+            "/collections/{}/3dtiles/tileset.json",
+            collection_id
+        ))
+        .await;
+    response.assert_success();
+    let tileset: serde_json::Value = response.json();
+    assert_eq!(tileset["asset"]["version"], "1.1");
+    assert_eq!(
+        tileset["root"]["boundingVolume"]["region"]
+            .as_array()
+            .unwrap()
+            .len(),
+        6
+    );
+    assert!(tileset["root"].get("children").is_none());
+
+    let response = app
+        .get(&format!(
+            "/collections/{}/3dtiles/items/00000000-0000-0000-0000-000000000000",
+            collection_id
+        ))
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+
+    // Only point cloud collections have 3D Tiles
+    let vector = test_collection_request("vector-3dtiles-test", "vector");
+    app.post_json("/collections", &vector)
+        .await
+        .assert_status(StatusCode::CREATED);
+    let response = app
+        .get("/collections/vector-3dtiles-test/3dtiles/tileset.json")
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+/// Signed tile URLs serve public tiles until they expire or are revoked
+#[tokio::test]
+async fn test_signed_tiles() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("signed-tiles-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let sign_uri = format!("/collections/{}/tiles/sign", collection_id);
+
+    // Tiles must be made public first
+    app.post_json(&sign_uri, &serde_json::json!({}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let response = app
+        .patch_json_without_etag(
+            &format!("/collections/{}/tiles/WebMercatorQuad", collection_id),
+            &serde_json::json!({ "publicTiles": true }),
+        )
+        .await;
+    response.assert_status(StatusCode::OK);
+    let tileset: serde_json::Value = response.json();
+    assert_eq!(tileset["publicTiles"], true);
+
+    app.post_json(&sign_uri, &serde_json::json!({ "expiresIn": 0 }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let response = app
+        .post_json(&sign_uri, &serde_json::json!({ "expiresIn": 600 }))
+        .await;
+    response.assert_status(StatusCode::OK);
+    let signed: serde_json::Value = response.json();
+    let template = signed["tiles"].as_str().expect("Signed URL template");
+    let tile_uri = template
+        .strip_prefix(&app.config.base_url)
+        .expect("Template starts with the base URL")
+        .replace("{tileMatrix}/{tileRow}/{tileCol}", "0/0/0");
+
+    let response = app.get(&tile_uri).await;
+    response.assert_status(StatusCode::OK);
+    response.assert_content_type("application/vnd.mapbox-vector-tile");
+
+    // The signature only covers its own expiry and collection
+    app.get(&tile_uri.replace("expires=", "expires=1"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+    app.get(&tile_uri.replace(collection_id, "other"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    // Making the tiles private again revokes the URL
+    app.patch_json_without_etag(
+        &format!("/collections/{}/tiles/WebMercatorQuad", collection_id),
+        &serde_json::json!({ "publicTiles": false }),
+    )
+    .await
+    .assert_status(StatusCode::OK);
+    app.get(&tile_uri)
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+/// Tiles carry an ETag of the collection version and are cached for long
+#[tokio::test]
+async fn test_tile_cache_headers() {
+    let app = TestApp::new().await;
+
+    let collection = test_collection_request("tile-cache-test", "vector");
+    let create_response = app.post_json("/collections", &collection).await;
+    create_response.assert_status(StatusCode::CREATED);
+
+    let created: serde_json::Value = create_response.json();
+    let collection_id = created["id"].as_str().expect("Collection must have id");
+    let tile_uri = format!("/collections/{}/tiles/WebMercatorQuad/0/0/0", collection_id);
+
+    let response = app.get(&tile_uri).await;
+    response.assert_status(StatusCode::OK);
+    let etag = response.etag().expect("Tile must have an ETag");
+    let cache_control = response
+        .header("cache-control")
+        .expect("Tile must have Cache-Control");
+    assert!(cache_control.contains("stale-while-revalidate"));
+    assert!(
+        response
+            .header("surrogate-key")
+            .is_some_and(|key| key.starts_with("collection-"))
+    );
+
+    // An unchanged tile is revalidated without a body
+    let response = app
+        .get_with_headers(&tile_uri, vec![(header::IF_NONE_MATCH, etag.as_str())])
+        .await;
+    response.assert_status(StatusCode::NOT_MODIFIED);
+
+    // A new feature changes the collection version and thereby the tile
+    let response = app
+        .post_json(
+            &format!("/collections/{}/items", collection_id),
+            &test_feature_request(),
+        )
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let feature: serde_json::Value = response.json();
+    let feature_path = format!(
+        "/collections/{}/items/{}",
+        collection_id,
+        feature["id"].as_str().expect("Feature must have id")
+    );
+    let response = app
+        .get_with_headers(&tile_uri, vec![(header::IF_NONE_MATCH, etag.as_str())])
+        .await;
+    response.assert_status(StatusCode::OK);
+    let mut etag = response.etag().expect("Tile must have an ETag");
+
+    // So do replacing, updating and deleting it
+    for method in [Method::PUT, Method::PATCH, Method::DELETE] {
+        let feature_etag = app
+            .get(&feature_path)
+            .await
+            .etag()
+            .expect("Feature must have ETag");
+        let response = match method {
+            Method::PUT => {
+                app.put_json(&feature_path, &test_feature_request(), &feature_etag)
+                    .await
+            }
+            Method::PATCH => {
+                app.patch_json(
+                    &feature_path,
+                    &serde_json::json!({"properties": {"name": "patched"}}),
+                    &feature_etag,
+                )
+                .await
+            }
+            _ => app.delete(&feature_path, &feature_etag).await,
+        };
+        response.assert_success();
+
+        let response = app
+            .get_with_headers(&tile_uri, vec![(header::IF_NONE_MATCH, etag.as_str())])
+            .await;
+        response.assert_status(StatusCode::OK);
+        let changed = response.etag().expect("Tile must have an ETag");
+        assert_ne!(changed, etag, "{} must change the tile ETag", method);
+        etag = changed;
+    }
+
+    // Tiles from the tileset's versioned URL template never change
+    let response = app
+        .get(&format!(
+            "/collections/{}/tiles/WebMercatorQuad",
+            collection_id
+        ))
+        .await;
+    response.assert_status(StatusCode::OK);
+    let tileset: serde_json::Value = response.json();
+    let template = tileset["links"]
+        .as_array()
+        .and_then(|links| links.iter().find(|link| link["rel"] == "item"))
+        .and_then(|link| link["href"].as_str())
+        .expect("Tileset must link a tile URL template");
+    let versioned_uri = template
+        .strip_prefix(&app.config.base_url)
+        .expect("Template starts with the base URL")
+        .replace("{tileMatrix}/{tileRow}/{tileCol}", "0/0/0");
+    let response = app.get(&versioned_uri).await;
+    response.assert_status(StatusCode::OK);
+    let cache_control = response
+        .header("cache-control")
+        .expect("Tile must have Cache-Control");
+    assert!(cache_control.contains("immutable"));
+}